@@ -2,7 +2,8 @@
 //!
 //! Uses the globally configured ailloy provider for AI requests.
 
-use ailloy::{ChatOptions, Client, Message};
+use ailloy::config::ProviderKind;
+use ailloy::{ChatOptions, ChatResponse, Client, Message};
 
 /// Generate text using the globally configured ailloy provider.
 ///
@@ -18,7 +19,28 @@ pub async fn generate_text_with_limit(
     user_prompt: &str,
     max_tokens: u32,
 ) -> anyhow::Result<String> {
-    let client = Client::from_config()?;
+    Ok(chat(system_prompt, user_prompt, max_tokens).await?.content)
+}
+
+/// Send a system + user prompt and return the full response, including the
+/// model and token usage reported by the provider.
+pub async fn chat(
+    system_prompt: &str,
+    user_prompt: &str,
+    max_tokens: u32,
+) -> anyhow::Result<ChatResponse> {
+    chat_with_api_key(system_prompt, user_prompt, max_tokens, None).await
+}
+
+/// Like [`chat`], but an Azure OpenAI default node authenticates with
+/// `api_key` instead of its configured auth (ignored for other providers).
+pub async fn chat_with_api_key(
+    system_prompt: &str,
+    user_prompt: &str,
+    max_tokens: u32,
+    api_key: Option<&str>,
+) -> anyhow::Result<ChatResponse> {
+    let client = client(api_key)?;
     let opts = ChatOptions::builder()
         .temperature(0.3)
         .max_tokens(max_tokens)
@@ -29,7 +51,30 @@ pub async fn generate_text_with_limit(
             &opts,
         )
         .await?;
-    Ok(response.content)
+    Ok(response)
+}
+
+/// The ailloy client for the default chat node, with `api_key` replacing
+/// the auth of an Azure OpenAI node.
+pub fn client(api_key: Option<&str>) -> anyhow::Result<Client> {
+    if let Some(key) = api_key {
+        let config = ailloy::config::Config::load()?;
+        let (_, node) = config.default_chat_node()?;
+        if node.provider == ProviderKind::AzureOpenAi {
+            let mut builder = Client::builder().azure().api_key(key);
+            if let Some(ref endpoint) = node.endpoint {
+                builder = builder.endpoint(endpoint);
+            }
+            if let Some(ref deployment) = node.deployment {
+                builder = builder.deployment(deployment);
+            }
+            if let Some(ref api_version) = node.api_version {
+                builder = builder.api_version(api_version);
+            }
+            return builder.build();
+        }
+    }
+    Client::from_config()
 }
 
 /// Check if ailloy is configured with a default chat node.
@@ -3,7 +3,7 @@
 use serde::{Deserialize, Serialize};
 use tracing::debug;
 
-use crate::auth::{ARM_RESOURCE, AzCliAuth};
+use crate::auth::{self, ARM_RESOURCE};
 use crate::error::ClientError;
 
 const ARM_SUBSCRIPTIONS_API_VERSION: &str = "2024-11-01";
@@ -65,9 +65,9 @@ pub struct ArmClient {
 impl ArmClient {
     /// Create a new ARM client, acquiring a token via the Azure CLI.
     pub async fn new() -> Result<Self, ClientError> {
-        let token = AzCliAuth::get_token(ARM_RESOURCE).await?;
+        let token = auth::get_token(ARM_RESOURCE).await?;
         Ok(Self {
-            http: reqwest::Client::new(),
+            http: crate::http::client(),
             token,
         })
     }
@@ -160,6 +160,23 @@ impl ArmClient {
     ) -> Result<bool, ClientError> {
         debug!(principal_id, "checking Cosmos DB SQL role assignments");
 
+        let has_role = self
+            .list_sql_role_assignments(account_resource_id)
+            .await?
+            .iter()
+            .any(|a| a.properties.principal_id == principal_id);
+
+        debug!(has_role, "data plane role check complete");
+        Ok(has_role)
+    }
+
+    /// List the Cosmos DB SQL (data plane) role assignments on an account.
+    pub async fn list_sql_role_assignments(
+        &self,
+        account_resource_id: &str,
+    ) -> Result<Vec<SqlRoleAssignment>, ClientError> {
+        debug!("listing Cosmos DB SQL role assignments");
+
         let url = format!(
             "{ARM_BASE_URL}{account_resource_id}/sqlRoleAssignments?api-version={COSMOS_DB_API_VERSION}"
         );
@@ -168,17 +185,39 @@ impl ArmClient {
         let status = resp.status();
         if !status.is_success() {
             let body = resp.text().await.unwrap_or_default();
+            if status.as_u16() == 403 {
+                return Err(ClientError::forbidden(
+                    body,
+                    "Listing data plane role assignments requires Reader (or Cosmos DB Account Reader) on the account.",
+                ));
+            }
             return Err(ClientError::api(status.as_u16(), body));
         }
 
         let list: SqlRoleAssignmentListResponse = resp.json().await?;
-        let has_role = list
-            .value
-            .iter()
-            .any(|a| a.properties.principal_id == principal_id);
+        Ok(list.value)
+    }
 
-        debug!(has_role, "data plane role check complete");
-        Ok(has_role)
+    /// List the Cosmos DB SQL role definitions (built-in and custom) of an account.
+    pub async fn list_sql_role_definitions(
+        &self,
+        account_resource_id: &str,
+    ) -> Result<Vec<SqlRoleDefinition>, ClientError> {
+        debug!("listing Cosmos DB SQL role definitions");
+
+        let url = format!(
+            "{ARM_BASE_URL}{account_resource_id}/sqlRoleDefinitions?api-version={COSMOS_DB_API_VERSION}"
+        );
+        let resp = self.http.get(&url).bearer_auth(&self.token).send().await?;
+
+        let status = resp.status();
+        if !status.is_success() {
+            let body = resp.text().await.unwrap_or_default();
+            return Err(ClientError::api(status.as_u16(), body));
+        }
+
+        let list: SqlRoleDefinitionListResponse = resp.json().await?;
+        Ok(list.value)
     }
 
     /// Assign the Cosmos DB Built-in Data Contributor role to a principal.
@@ -227,6 +266,556 @@ impl ArmClient {
         debug!("data contributor role assigned successfully");
         Ok(())
     }
+
+    /// Add a composite index to a container's indexing policy.
+    ///
+    /// `index` holds `(path, order)` pairs such as `("/date", "descending")`.
+    /// Returns `false` if an identical composite index already exists. The
+    /// index transformation itself runs in the background after the update.
+    pub async fn add_composite_index(
+        &self,
+        account_resource_id: &str,
+        database: &str,
+        container: &str,
+        index: &[(String, String)],
+    ) -> Result<bool, ClientError> {
+        debug!(database, container, "adding composite index");
+
+        let url = format!(
+            "{ARM_BASE_URL}{account_resource_id}/sqlDatabases/{database}/containers/{container}?api-version={COSMOS_DB_API_VERSION}"
+        );
+        let resp = self.http.get(&url).bearer_auth(&self.token).send().await?;
+        let status = resp.status();
+        if !status.is_success() {
+            let body = resp.text().await.unwrap_or_default();
+            if status.as_u16() == 403 {
+                return Err(ClientError::forbidden(
+                    body,
+                    "Updating the indexing policy requires Contributor (or Cosmos DB Operator) on the account.",
+                ));
+            }
+            return Err(ClientError::api(status.as_u16(), body));
+        }
+
+        let current: serde_json::Value = resp.json().await?;
+        let Some(mut resource) = current.pointer("/properties/resource").cloned() else {
+            return Err(ClientError::Other(
+                "container resource is missing from the ARM response".into(),
+            ));
+        };
+        if !add_to_indexing_policy(&mut resource, index) {
+            return Ok(false);
+        }
+
+        let body = serde_json::json!({ "properties": { "resource": resource } });
+        let resp = self
+            .http
+            .put(&url)
+            .bearer_auth(&self.token)
+            .json(&body)
+            .send()
+            .await?;
+        let status = resp.status();
+        if !status.is_success() {
+            let body = resp.text().await.unwrap_or_default();
+            return Err(ClientError::api(status.as_u16(), body));
+        }
+
+        debug!("indexing policy update submitted");
+        Ok(true)
+    }
+
+    /// Read an account's properties (location, kind, backup policy).
+    pub async fn get_account(
+        &self,
+        account_resource_id: &str,
+    ) -> Result<AccountDetails, ClientError> {
+        debug!("reading Cosmos DB account");
+
+        let url =
+            format!("{ARM_BASE_URL}{account_resource_id}?api-version={COSMOS_DB_API_VERSION}");
+        let resp = self.http.get(&url).bearer_auth(&self.token).send().await?;
+        let status = resp.status();
+        if !status.is_success() {
+            let body = resp.text().await.unwrap_or_default();
+            if status.as_u16() == 404 {
+                return Err(ClientError::not_found(format!(
+                    "account {account_resource_id}"
+                )));
+            }
+            return Err(ClientError::api(status.as_u16(), body));
+        }
+        Ok(resp.json().await?)
+    }
+
+    /// Read the throughput provisioned on a database, or on a container when
+    /// `container` is given. Returns `None` when nothing is provisioned at
+    /// that level (shared database throughput, or a serverless account).
+    pub async fn get_throughput(
+        &self,
+        account_resource_id: &str,
+        database: &str,
+        container: Option<&str>,
+    ) -> Result<Option<Throughput>, ClientError> {
+        debug!(database, container, "reading throughput settings");
+
+        let scope = match container {
+            Some(container) => format!("sqlDatabases/{database}/containers/{container}"),
+            None => format!("sqlDatabases/{database}"),
+        };
+        let url = format!(
+            "{ARM_BASE_URL}{account_resource_id}/{scope}/throughputSettings/default?api-version={COSMOS_DB_API_VERSION}"
+        );
+        let resp = self.http.get(&url).bearer_auth(&self.token).send().await?;
+        let status = resp.status();
+        if !status.is_success() {
+            let body = resp.text().await.unwrap_or_default();
+            // ARM answers 404 (or 400 on serverless) when there is no
+            // throughput resource at this level
+            if matches!(status.as_u16(), 400 | 404) {
+                return Ok(None);
+            }
+            return Err(ClientError::api(status.as_u16(), body));
+        }
+        let settings: ThroughputSettingsResource = resp.json().await?;
+        Ok(Some(settings.properties.resource.into()))
+    }
+
+    /// Read the restorable account of an account with continuous backup
+    /// (see [`restorable_account_id`]).
+    pub async fn get_restorable_account(
+        &self,
+        restorable_account_id: &str,
+    ) -> Result<RestorableAccount, ClientError> {
+        debug!(restorable_account_id, "reading restorable account");
+
+        let url =
+            format!("{ARM_BASE_URL}{restorable_account_id}?api-version={COSMOS_DB_API_VERSION}");
+        let resp = self.http.get(&url).bearer_auth(&self.token).send().await?;
+        let status = resp.status();
+        if !status.is_success() {
+            let body = resp.text().await.unwrap_or_default();
+            return Err(ClientError::api(status.as_u16(), body));
+        }
+        let resource: RestorableAccountResource = resp.json().await?;
+        Ok(resource.properties)
+    }
+
+    /// Start a point-in-time restore of `source` into a new account
+    /// `target` in `resource_group`. Restores run for a long time, so this
+    /// returns once ARM has accepted the request.
+    pub async fn restore_account(
+        &self,
+        source: &AccountDetails,
+        restore_source: &str,
+        timestamp: &str,
+        subscription: &str,
+        resource_group: &str,
+        target: &str,
+    ) -> Result<(), ClientError> {
+        debug!(target, timestamp, "starting point-in-time restore");
+
+        let url = format!(
+            "{ARM_BASE_URL}{}?api-version={COSMOS_DB_API_VERSION}",
+            account_resource_id(subscription, resource_group, target)
+        );
+        let resp = self
+            .http
+            .put(&url)
+            .bearer_auth(&self.token)
+            .json(&restore_request_body(source, restore_source, timestamp))
+            .send()
+            .await?;
+        let status = resp.status();
+        if !status.is_success() {
+            let body = resp.text().await.unwrap_or_default();
+            if status.as_u16() == 403 {
+                return Err(ClientError::forbidden(
+                    body,
+                    "Restoring requires Contributor (or the Cosmos DB Operator role plus Microsoft.DocumentDB/locations/restorableDatabaseAccounts/restore/action) on the source account and the target resource group.",
+                ));
+            }
+            return Err(ClientError::api(status.as_u16(), body));
+        }
+        Ok(())
+    }
+
+    /// Read the account's master keys (`listKeys`).
+    pub async fn list_keys(&self, account_resource_id: &str) -> Result<AccountKeys, ClientError> {
+        debug!("listing Cosmos DB account keys");
+
+        let url = format!(
+            "{ARM_BASE_URL}{account_resource_id}/listKeys?api-version={COSMOS_DB_API_VERSION}"
+        );
+        let resp = self
+            .http
+            .post(&url)
+            .bearer_auth(&self.token)
+            .header("Content-Length", "0")
+            .send()
+            .await?;
+        let status = resp.status();
+        if !status.is_success() {
+            let body = resp.text().await.unwrap_or_default();
+            if status.as_u16() == 403 {
+                return Err(ClientError::forbidden(
+                    body,
+                    "Listing keys requires Contributor (or a role with Microsoft.DocumentDB/databaseAccounts/listKeys/action) on the account.",
+                ));
+            }
+            return Err(ClientError::api(status.as_u16(), body));
+        }
+        Ok(resp.json().await?)
+    }
+
+    /// Regenerate one of the account's keys (`regenerateKey`) and wait for
+    /// the operation to finish.
+    pub async fn regenerate_key(
+        &self,
+        account_resource_id: &str,
+        kind: AccountKeyKind,
+    ) -> Result<(), ClientError> {
+        debug!(key = kind.as_arm(), "regenerating Cosmos DB account key");
+
+        let url = format!(
+            "{ARM_BASE_URL}{account_resource_id}/regenerateKey?api-version={COSMOS_DB_API_VERSION}"
+        );
+        let resp = self
+            .http
+            .post(&url)
+            .bearer_auth(&self.token)
+            .json(&serde_json::json!({ "keyKind": kind.as_arm() }))
+            .send()
+            .await?;
+        let status = resp.status();
+        if !status.is_success() {
+            let body = resp.text().await.unwrap_or_default();
+            if status.as_u16() == 403 {
+                return Err(ClientError::forbidden(
+                    body,
+                    "Regenerating keys requires Contributor (or a role with Microsoft.DocumentDB/databaseAccounts/regenerateKey/action) on the account.",
+                ));
+            }
+            return Err(ClientError::api(status.as_u16(), body));
+        }
+
+        // 202 Accepted: poll the async operation until it completes
+        let Some(operation) = resp
+            .headers()
+            .get("Azure-AsyncOperation")
+            .and_then(|v| v.to_str().ok())
+            .map(str::to_string)
+        else {
+            return Ok(());
+        };
+        for _ in 0..REGENERATE_POLL_ATTEMPTS {
+            tokio::time::sleep(std::time::Duration::from_secs(2)).await;
+            let resp = self
+                .http
+                .get(&operation)
+                .bearer_auth(&self.token)
+                .send()
+                .await?;
+            let status = resp.status();
+            if !status.is_success() {
+                let body = resp.text().await.unwrap_or_default();
+                return Err(ClientError::api(status.as_u16(), body));
+            }
+            let op: AsyncOperation = resp.json().await?;
+            match op.status.as_str() {
+                "Succeeded" => return Ok(()),
+                "Failed" | "Canceled" => {
+                    return Err(ClientError::Other(format!(
+                        "key regeneration {}",
+                        op.status.to_lowercase()
+                    )));
+                }
+                _ => debug!(status = %op.status, "key regeneration in progress"),
+            }
+        }
+        Err(ClientError::Other(
+            "key regeneration is still running — check the account in the Azure portal".into(),
+        ))
+    }
+}
+
+/// Account properties read by `cosq account` (location, backup policy)
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AccountDetails {
+    pub id: String,
+    pub name: String,
+    pub location: String,
+    pub kind: Option<String>,
+    pub properties: AccountDetailsProperties,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AccountDetailsProperties {
+    /// Identifies the account for restores (part of the restorable account ID)
+    pub instance_id: Option<String>,
+    #[serde(default)]
+    pub backup_policy: BackupPolicy,
+    #[serde(default)]
+    pub capabilities: Vec<Capability>,
+    /// Whether Synapse Link (analytical storage) is enabled on the account
+    #[serde(default)]
+    pub enable_analytical_storage: bool,
+}
+
+/// An account capability such as `EnableServerless`
+#[derive(Debug, Clone, Deserialize)]
+pub struct Capability {
+    pub name: String,
+}
+
+impl AccountDetails {
+    /// Whether the account bills per request (serverless) rather than for
+    /// provisioned RU/s.
+    pub fn is_serverless(&self) -> bool {
+        self.properties
+            .capabilities
+            .iter()
+            .any(|c| c.name.eq_ignore_ascii_case("EnableServerless"))
+    }
+}
+
+/// Provisioned throughput of a database or container: a fixed RU/s or an
+/// autoscale maximum
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Throughput {
+    pub manual: Option<u32>,
+    pub autoscale_max: Option<u32>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ThroughputSettingsResource {
+    properties: ThroughputSettingsProperties,
+}
+
+#[derive(Debug, Deserialize)]
+struct ThroughputSettingsProperties {
+    resource: ThroughputResource,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ThroughputResource {
+    throughput: Option<u32>,
+    autoscale_settings: Option<AutoscaleSettings>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct AutoscaleSettings {
+    max_throughput: Option<u32>,
+}
+
+impl From<ThroughputResource> for Throughput {
+    fn from(resource: ThroughputResource) -> Self {
+        match resource.autoscale_settings.and_then(|a| a.max_throughput) {
+            Some(max) => Throughput {
+                manual: None,
+                autoscale_max: Some(max),
+            },
+            None => Throughput {
+                manual: resource.throughput,
+                autoscale_max: None,
+            },
+        }
+    }
+}
+
+/// An account's backup policy: `Continuous` (point-in-time restore) or `Periodic`
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BackupPolicy {
+    #[serde(rename = "type", default)]
+    pub policy_type: String,
+    pub continuous_mode_properties: Option<ContinuousModeProperties>,
+    pub periodic_mode_properties: Option<PeriodicModeProperties>,
+}
+
+impl BackupPolicy {
+    /// Whether the account supports point-in-time restore.
+    pub fn is_continuous(&self) -> bool {
+        self.policy_type.eq_ignore_ascii_case("Continuous")
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ContinuousModeProperties {
+    /// `Continuous7Days` or `Continuous30Days`
+    pub tier: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PeriodicModeProperties {
+    pub backup_interval_in_minutes: Option<u32>,
+    pub backup_retention_interval_in_hours: Option<u32>,
+    pub backup_storage_redundancy: Option<String>,
+}
+
+/// The restorable view of an account with continuous backup
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RestorableAccount {
+    pub creation_time: Option<String>,
+    /// Earliest time the account can be restored to (RFC 3339)
+    pub oldest_restorable_time: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct RestorableAccountResource {
+    #[serde(default)]
+    properties: RestorableAccount,
+}
+
+/// ARM resource ID of the restorable account a restore reads from.
+pub fn restorable_account_id(subscription: &str, location: &str, instance_id: &str) -> String {
+    let location = location.replace(' ', "").to_lowercase();
+    format!(
+        "/subscriptions/{subscription}/providers/Microsoft.DocumentDB/locations/{location}/restorableDatabaseAccounts/{instance_id}"
+    )
+}
+
+/// Request body that creates a new account from `source`'s continuous
+/// backup as of `timestamp` (UTC, `YYYY-MM-DDTHH:MM:SSZ`).
+fn restore_request_body(
+    source: &AccountDetails,
+    restore_source: &str,
+    timestamp: &str,
+) -> serde_json::Value {
+    serde_json::json!({
+        "location": source.location,
+        "kind": source.kind.as_deref().unwrap_or("GlobalDocumentDB"),
+        "properties": {
+            "createMode": "Restore",
+            "databaseAccountOfferType": "Standard",
+            "locations": [
+                { "locationName": source.location, "failoverPriority": 0 }
+            ],
+            "backupPolicy": { "type": "Continuous" },
+            "restoreParameters": {
+                "restoreMode": "PointInTime",
+                "restoreSource": restore_source,
+                "restoreTimestampInUtc": timestamp
+            }
+        }
+    })
+}
+
+/// Which account key to regenerate
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AccountKeyKind {
+    Primary,
+    Secondary,
+    PrimaryReadonly,
+    SecondaryReadonly,
+}
+
+impl AccountKeyKind {
+    /// The `keyKind` value ARM expects.
+    pub fn as_arm(&self) -> &'static str {
+        match self {
+            AccountKeyKind::Primary => "primary",
+            AccountKeyKind::Secondary => "secondary",
+            AccountKeyKind::PrimaryReadonly => "primaryReadonly",
+            AccountKeyKind::SecondaryReadonly => "secondaryReadonly",
+        }
+    }
+
+    /// Whether the key only grants read access.
+    pub fn is_readonly(&self) -> bool {
+        matches!(
+            self,
+            AccountKeyKind::PrimaryReadonly | AccountKeyKind::SecondaryReadonly
+        )
+    }
+}
+
+/// The master keys of a Cosmos DB account
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AccountKeys {
+    pub primary_master_key: String,
+    pub secondary_master_key: String,
+    pub primary_readonly_master_key: String,
+    pub secondary_readonly_master_key: String,
+}
+
+impl AccountKeys {
+    pub fn get(&self, kind: AccountKeyKind) -> &str {
+        match kind {
+            AccountKeyKind::Primary => &self.primary_master_key,
+            AccountKeyKind::Secondary => &self.secondary_master_key,
+            AccountKeyKind::PrimaryReadonly => &self.primary_readonly_master_key,
+            AccountKeyKind::SecondaryReadonly => &self.secondary_readonly_master_key,
+        }
+    }
+
+    /// Which key `key` is, if it is one of these.
+    pub fn kind_of(&self, key: &str) -> Option<AccountKeyKind> {
+        [
+            AccountKeyKind::Primary,
+            AccountKeyKind::Secondary,
+            AccountKeyKind::PrimaryReadonly,
+            AccountKeyKind::SecondaryReadonly,
+        ]
+        .into_iter()
+        .find(|kind| self.get(*kind) == key)
+    }
+}
+
+/// How often (every 2 seconds) a key regeneration is polled before giving up
+const REGENERATE_POLL_ATTEMPTS: u32 = 150;
+
+#[derive(Debug, Deserialize)]
+struct AsyncOperation {
+    status: String,
+}
+
+/// ARM resource ID of a Cosmos DB account.
+pub fn account_resource_id(subscription: &str, resource_group: &str, account: &str) -> String {
+    format!(
+        "/subscriptions/{subscription}/resourceGroups/{resource_group}/providers/Microsoft.DocumentDB/databaseAccounts/{account}"
+    )
+}
+
+/// Append a composite index to a container resource's `indexingPolicy`,
+/// unless an identical one exists. Returns whether the resource changed.
+fn add_to_indexing_policy(resource: &mut serde_json::Value, index: &[(String, String)]) -> bool {
+    let entry: Vec<serde_json::Value> = index
+        .iter()
+        .map(|(path, order)| serde_json::json!({ "path": path, "order": order }))
+        .collect();
+
+    let Some(resource) = resource.as_object_mut() else {
+        return false;
+    };
+    // System properties are read-only and must not be sent back
+    resource.retain(|k, _| !k.starts_with('_'));
+    let policy = resource
+        .entry("indexingPolicy")
+        .or_insert_with(|| serde_json::json!({}));
+    let Some(policy) = policy.as_object_mut() else {
+        return false;
+    };
+    let composites = policy
+        .entry("compositeIndexes")
+        .or_insert_with(|| serde_json::json!([]));
+    let Some(composites) = composites.as_array_mut() else {
+        return false;
+    };
+    let entry = serde_json::Value::Array(entry);
+    if composites.contains(&entry) {
+        return false;
+    }
+    composites.push(entry);
+    true
 }
 
 /// Cosmos DB Built-in Data Contributor role definition ID
@@ -237,15 +826,68 @@ struct SqlRoleAssignmentListResponse {
     value: Vec<SqlRoleAssignment>,
 }
 
-#[derive(Debug, Deserialize)]
-struct SqlRoleAssignment {
-    properties: SqlRoleAssignmentProperties,
+/// A Cosmos DB SQL role assignment: a principal granted a role at a scope
+#[derive(Debug, Clone, Deserialize)]
+pub struct SqlRoleAssignment {
+    pub id: String,
+    pub properties: SqlRoleAssignmentProperties,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SqlRoleAssignmentProperties {
+    /// Entra ID object ID of the user, group or service principal
+    pub principal_id: String,
+    /// Full resource ID of the role definition
+    #[serde(default)]
+    pub role_definition_id: String,
+    /// Full resource ID of the account, or of a database/container below it
+    /// (`.../databaseAccounts/<name>/dbs/<db>/colls/<container>`)
+    #[serde(default)]
+    pub scope: String,
 }
 
 #[derive(Debug, Deserialize)]
+struct SqlRoleDefinitionListResponse {
+    value: Vec<SqlRoleDefinition>,
+}
+
+/// A Cosmos DB SQL role definition (built-in or custom)
+#[derive(Debug, Clone, Deserialize)]
+pub struct SqlRoleDefinition {
+    pub id: String,
+    pub properties: SqlRoleDefinitionProperties,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
 #[serde(rename_all = "camelCase")]
-struct SqlRoleAssignmentProperties {
-    principal_id: String,
+pub struct SqlRoleDefinitionProperties {
+    #[serde(default)]
+    pub role_name: String,
+    /// `BuiltInRole` or `CustomRole`
+    #[serde(rename = "type", default)]
+    pub role_type: String,
+    #[serde(default)]
+    pub permissions: Vec<SqlRolePermission>,
+}
+
+/// Data actions granted by a role definition
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SqlRolePermission {
+    #[serde(default)]
+    pub data_actions: Vec<String>,
+}
+
+impl SqlRoleDefinition {
+    /// Every data action the role grants.
+    pub fn data_actions(&self) -> Vec<String> {
+        self.properties
+            .permissions
+            .iter()
+            .flat_map(|p| p.data_actions.iter().cloned())
+            .collect()
+    }
 }
 
 #[derive(Debug, Serialize)]
@@ -260,3 +902,154 @@ struct SqlRoleAssignmentCreateProperties {
     scope: String,
     principal_id: String,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_add_to_indexing_policy() {
+        let mut resource = json!({
+            "id": "orders",
+            "_rid": "abc",
+            "partitionKey": {"paths": ["/pk"]},
+            "indexingPolicy": {"indexingMode": "consistent"}
+        });
+        let index = vec![
+            ("/date".to_string(), "descending".to_string()),
+            ("/total".to_string(), "ascending".to_string()),
+        ];
+        assert!(add_to_indexing_policy(&mut resource, &index));
+        assert!(resource.get("_rid").is_none());
+        assert_eq!(
+            resource["indexingPolicy"]["compositeIndexes"],
+            json!([[
+                {"path": "/date", "order": "descending"},
+                {"path": "/total", "order": "ascending"}
+            ]])
+        );
+        // Adding the same index again is a no-op
+        assert!(!add_to_indexing_policy(&mut resource, &index));
+    }
+
+    #[test]
+    fn test_sql_role_deserialization() {
+        let assignments: SqlRoleAssignmentListResponse = serde_json::from_value(json!({
+            "value": [{
+                "id": "/subscriptions/sub/resourceGroups/rg/providers/Microsoft.DocumentDB/databaseAccounts/prod/sqlRoleAssignments/a1",
+                "name": "a1",
+                "properties": {
+                    "roleDefinitionId": "/subscriptions/sub/resourceGroups/rg/providers/Microsoft.DocumentDB/databaseAccounts/prod/sqlRoleDefinitions/00000000-0000-0000-0000-000000000002",
+                    "scope": "/subscriptions/sub/resourceGroups/rg/providers/Microsoft.DocumentDB/databaseAccounts/prod/dbs/shop",
+                    "principalId": "p1"
+                }
+            }]
+        }))
+        .unwrap();
+        assert_eq!(assignments.value[0].properties.principal_id, "p1");
+        assert!(assignments.value[0].properties.scope.ends_with("/dbs/shop"));
+
+        let definition: SqlRoleDefinition = serde_json::from_value(json!({
+            "id": "/subscriptions/sub/resourceGroups/rg/providers/Microsoft.DocumentDB/databaseAccounts/prod/sqlRoleDefinitions/00000000-0000-0000-0000-000000000002",
+            "properties": {
+                "roleName": "Cosmos DB Built-in Data Contributor",
+                "type": "BuiltInRole",
+                "permissions": [{
+                    "dataActions": [
+                        "Microsoft.DocumentDB/databaseAccounts/readMetadata",
+                        "Microsoft.DocumentDB/databaseAccounts/sqlDatabases/containers/*"
+                    ]
+                }]
+            }
+        }))
+        .unwrap();
+        assert_eq!(definition.properties.role_type, "BuiltInRole");
+        assert_eq!(definition.data_actions().len(), 2);
+    }
+
+    #[test]
+    fn test_account_keys() {
+        let keys: AccountKeys = serde_json::from_value(json!({
+            "primaryMasterKey": "p",
+            "secondaryMasterKey": "s",
+            "primaryReadonlyMasterKey": "pr",
+            "secondaryReadonlyMasterKey": "sr"
+        }))
+        .unwrap();
+        assert_eq!(keys.get(AccountKeyKind::SecondaryReadonly), "sr");
+        assert_eq!(keys.kind_of("s"), Some(AccountKeyKind::Secondary));
+        assert_eq!(keys.kind_of("other"), None);
+        assert_eq!(AccountKeyKind::PrimaryReadonly.as_arm(), "primaryReadonly");
+    }
+
+    #[test]
+    fn test_restore_request_body() {
+        let source: AccountDetails = serde_json::from_value(json!({
+            "id": "/subscriptions/sub/resourceGroups/rg/providers/Microsoft.DocumentDB/databaseAccounts/prod",
+            "name": "prod",
+            "location": "West Europe",
+            "kind": "GlobalDocumentDB",
+            "properties": {
+                "instanceId": "abc-123",
+                "backupPolicy": {
+                    "type": "Continuous",
+                    "continuousModeProperties": {"tier": "Continuous30Days"}
+                }
+            }
+        }))
+        .unwrap();
+        assert!(source.properties.backup_policy.is_continuous());
+
+        let restore_source = restorable_account_id("sub", &source.location, "abc-123");
+        assert_eq!(
+            restore_source,
+            "/subscriptions/sub/providers/Microsoft.DocumentDB/locations/westeurope/restorableDatabaseAccounts/abc-123"
+        );
+        let body = restore_request_body(&source, &restore_source, "2026-10-01T08:00:00Z");
+        assert_eq!(body["properties"]["createMode"], "Restore");
+        assert_eq!(
+            body["properties"]["restoreParameters"]["restoreTimestampInUtc"],
+            "2026-10-01T08:00:00Z"
+        );
+        assert_eq!(
+            body["properties"]["locations"][0]["locationName"],
+            "West Europe"
+        );
+    }
+
+    #[test]
+    fn test_capacity_detection() {
+        let account: AccountDetails = serde_json::from_value(json!({
+            "id": "/subscriptions/sub/resourceGroups/rg/providers/Microsoft.DocumentDB/databaseAccounts/dev",
+            "name": "dev",
+            "location": "West Europe",
+            "properties": {"capabilities": [{"name": "EnableServerless"}]}
+        }))
+        .unwrap();
+        assert!(account.is_serverless());
+
+        let settings: ThroughputSettingsResource = serde_json::from_value(json!({
+            "properties": {"resource": {
+                "throughput": 1000,
+                "autoscaleSettings": {"maxThroughput": 10000}
+            }}
+        }))
+        .unwrap();
+        assert_eq!(
+            Throughput::from(settings.properties.resource),
+            Throughput {
+                manual: None,
+                autoscale_max: Some(10000)
+            }
+        );
+    }
+
+    #[test]
+    fn test_account_resource_id() {
+        assert_eq!(
+            account_resource_id("sub", "rg", "acct"),
+            "/subscriptions/sub/resourceGroups/rg/providers/Microsoft.DocumentDB/databaseAccounts/acct"
+        );
+    }
+}
@@ -1,12 +1,17 @@
-//! Azure authentication via the Azure CLI
+//! Azure authentication via the Azure CLI or workload identity
 //!
 //! Uses `az account get-access-token` to acquire tokens for Azure Resource Manager
-//! and Cosmos DB data plane access.
+//! and Cosmos DB data plane access, or — in CI, without the Azure CLI — exchanges
+//! a federated token for them (see [`crate::workload_identity`]).
 
+use std::sync::OnceLock;
+
+use cosq_core::config::AuthMethod;
 use serde::Deserialize;
 use tokio::process::Command;
 
 use crate::error::ClientError;
+use crate::workload_identity::WorkloadIdentity;
 
 /// Cosmos DB data plane resource scope
 pub const COSMOS_RESOURCE: &str = "https://cosmos.azure.com";
@@ -14,11 +19,62 @@ pub const COSMOS_RESOURCE: &str = "https://cosmos.azure.com";
 /// Azure Resource Manager resource scope
 pub const ARM_RESOURCE: &str = "https://management.azure.com";
 
+/// Azure Storage data plane resource scope
+pub const STORAGE_RESOURCE: &str = "https://storage.azure.com";
+
+/// Microsoft Graph resource scope
+pub const GRAPH_RESOURCE: &str = "https://graph.microsoft.com";
+
+static METHOD: OnceLock<AuthMethod> = OnceLock::new();
+
+/// Use `method` for every token (`auth:` in the config) instead of detecting
+/// it. Must run before the first token is requested; later calls are ignored.
+pub fn configure(method: AuthMethod) {
+    let _ = METHOD.set(method);
+}
+
+/// The method tokens are acquired with: the configured one, otherwise
+/// workload identity when its environment is set (e.g. a GitHub Actions job
+/// with `id-token: write` and `AZURE_CLIENT_ID`/`AZURE_TENANT_ID`),
+/// otherwise the Azure CLI.
+pub fn method() -> AuthMethod {
+    METHOD.get().copied().unwrap_or_else(|| {
+        if WorkloadIdentity::from_env().is_some() {
+            AuthMethod::WorkloadIdentity
+        } else {
+            AuthMethod::AzureCli
+        }
+    })
+}
+
+/// Get an access token for `resource` with the method in effect.
+pub async fn get_token(resource: &str) -> Result<String, ClientError> {
+    match method() {
+        AuthMethod::AzureCli => AzCliAuth::get_token(resource).await,
+        AuthMethod::WorkloadIdentity => {
+            WorkloadIdentity::from_env()
+                .ok_or_else(|| {
+                    ClientError::auth(
+                        "workload identity needs AZURE_CLIENT_ID, AZURE_TENANT_ID and a federated \
+                         token (a GitHub Actions job with `permissions: id-token: write`, or \
+                         AZURE_FEDERATED_TOKEN_FILE)",
+                    )
+                })?
+                .get_token(resource)
+                .await
+        }
+    }
+}
+
 /// Status of the current Azure CLI authentication session
 #[derive(Debug, Clone)]
 pub struct AuthStatus {
     pub logged_in: bool,
+    /// User principal name, or the application (client) ID when signed in
+    /// as a service principal
     pub user: Option<String>,
+    /// `user` or `servicePrincipal`
+    pub user_type: Option<String>,
     pub subscription_name: Option<String>,
     pub subscription_id: Option<String>,
     pub tenant_id: Option<String>,
@@ -37,6 +93,8 @@ struct AzAccountInfo {
 #[derive(Debug, Deserialize)]
 struct AzUser {
     name: String,
+    #[serde(rename = "type")]
+    user_type: Option<String>,
 }
 
 /// Azure CLI-based authentication provider.
@@ -62,6 +120,7 @@ impl AzCliAuth {
                 return Ok(AuthStatus {
                     logged_in: false,
                     user: None,
+                    user_type: None,
                     subscription_name: None,
                     subscription_id: None,
                     tenant_id: None,
@@ -79,6 +138,7 @@ impl AzCliAuth {
         Ok(AuthStatus {
             logged_in: true,
             user: Some(info.user.name),
+            user_type: info.user.user_type,
             subscription_name: Some(info.name),
             subscription_id: Some(info.id),
             tenant_id: Some(info.tenant_id),
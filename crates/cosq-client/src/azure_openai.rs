@@ -0,0 +1,390 @@
+//! Azure OpenAI structured output
+//!
+//! ailloy's chat API only returns free text. For Azure OpenAI deployments
+//! this client calls the chat completions endpoint directly with a
+//! `response_format` JSON schema, so the model has to answer with an object
+//! that matches it. Used by `cosq queries generate` when the default ailloy
+//! chat node is an Azure OpenAI node.
+//!
+//! Throttled (429) and unavailable (5xx) responses are retried, honoring the
+//! service's `retry-after-ms` / `Retry-After` hint.
+
+use std::time::Duration;
+
+use ailloy::config::{AiNode, Auth, ProviderKind};
+use ailloy::{ChatResponse, Usage};
+use serde::Deserialize;
+use serde_json::{Value, json};
+use tracing::debug;
+
+use crate::error::ClientError;
+use crate::retry::{RetryOn, RetryPolicy, retry_after};
+
+/// Resource for Entra ID tokens accepted by Azure OpenAI
+pub const COGNITIVE_SERVICES_RESOURCE: &str = "https://cognitiveservices.azure.com";
+
+/// API version used when the ailloy node does not set one (same as ailloy)
+pub const DEFAULT_API_VERSION: &str = "2025-04-01-preview";
+
+/// How requests are authenticated
+#[derive(Debug, Clone, PartialEq)]
+pub enum AzureOpenAIAuth {
+    /// `api-key` header
+    ApiKey(String),
+    /// Bearer token from `az account get-access-token`
+    AzureCli,
+}
+
+/// Client for structured chat completions against an Azure OpenAI deployment
+pub struct AzureOpenAIClient {
+    http: reqwest::Client,
+    endpoint: String,
+    deployment: String,
+    api_version: String,
+    auth: AzureOpenAIAuth,
+    retry: RetryPolicy,
+}
+
+#[derive(Deserialize)]
+struct ChatCompletion {
+    #[serde(default)]
+    model: String,
+    choices: Vec<Choice>,
+    #[serde(default)]
+    usage: Option<CompletionUsage>,
+}
+
+#[derive(Deserialize)]
+struct Choice {
+    message: ChoiceMessage,
+}
+
+#[derive(Deserialize)]
+struct ChoiceMessage {
+    #[serde(default)]
+    content: Option<String>,
+    /// Set instead of `content` when the model declines to answer
+    #[serde(default)]
+    refusal: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct CompletionUsage {
+    prompt_tokens: u32,
+    completion_tokens: u32,
+    total_tokens: u32,
+}
+
+impl AzureOpenAIClient {
+    pub fn new(endpoint: &str, deployment: &str, api_version: &str, auth: AzureOpenAIAuth) -> Self {
+        Self {
+            http: crate::http::client(),
+            endpoint: endpoint.trim_end_matches('/').to_string(),
+            deployment: deployment.to_string(),
+            api_version: api_version.to_string(),
+            auth,
+            retry: default_retry_policy(),
+        }
+    }
+
+    /// Replace the retry policy for throttled (429) and unavailable (5xx) responses.
+    pub fn with_retry_policy(mut self, policy: RetryPolicy) -> Self {
+        self.retry = policy;
+        self
+    }
+
+    /// Build a client from the default ailloy chat node, if that node is an
+    /// Azure OpenAI deployment. Returns `Ok(None)` for any other provider or
+    /// when ailloy is not configured. `api_key` replaces the node's auth.
+    pub fn from_ailloy_config(api_key: Option<&str>) -> Result<Option<Self>, ClientError> {
+        let Ok(config) = ailloy::config::Config::load() else {
+            return Ok(None);
+        };
+        let Ok((id, node)) = config.default_chat_node() else {
+            return Ok(None);
+        };
+        Self::from_node(id, node, api_key)
+    }
+
+    /// Build a client from an ailloy node; `Ok(None)` unless it is Azure OpenAI.
+    pub fn from_node(
+        id: &str,
+        node: &AiNode,
+        api_key: Option<&str>,
+    ) -> Result<Option<Self>, ClientError> {
+        if node.provider != ProviderKind::AzureOpenAi {
+            return Ok(None);
+        }
+        let endpoint = node
+            .endpoint
+            .as_deref()
+            .ok_or_else(|| ClientError::openai(format!("no endpoint for Azure node '{id}'")))?;
+        let deployment = node
+            .deployment
+            .as_deref()
+            .ok_or_else(|| ClientError::openai(format!("no deployment for Azure node '{id}'")))?;
+        let auth = match (api_key, &node.auth) {
+            (Some(key), _) => AzureOpenAIAuth::ApiKey(key.to_string()),
+            (None, Some(Auth::ApiKey(key))) => AzureOpenAIAuth::ApiKey(key.clone()),
+            (None, Some(Auth::Env(var))) => {
+                AzureOpenAIAuth::ApiKey(std::env::var(var).map_err(|_| {
+                    ClientError::openai(format!(
+                        "environment variable '{var}' not set for Azure node '{id}'"
+                    ))
+                })?)
+            }
+            _ => AzureOpenAIAuth::AzureCli,
+        };
+        Ok(Some(Self::new(
+            endpoint,
+            deployment,
+            node.api_version.as_deref().unwrap_or(DEFAULT_API_VERSION),
+            auth,
+        )))
+    }
+
+    pub fn deployment(&self) -> &str {
+        &self.deployment
+    }
+
+    fn chat_url(&self) -> String {
+        format!(
+            "{}/openai/deployments/{}/chat/completions?api-version={}",
+            self.endpoint, self.deployment, self.api_version
+        )
+    }
+
+    /// Error for a failed response after `attempts` tries, spelling out
+    /// throttling and unavailability instead of the raw API message.
+    fn status_error(&self, status: u16, attempts: u32, message: &str) -> ClientError {
+        let tries = if attempts > 1 {
+            format!(" after {attempts} attempts")
+        } else {
+            String::new()
+        };
+        match status {
+            429 => ClientError::openai(format!(
+                "deployment '{}' is throttled (HTTP 429){tries}: {message}\n\n\
+                 Hint: wait a minute and try again, or raise the deployment's \
+                 tokens-per-minute quota in the Azure portal",
+                self.deployment
+            )),
+            503 => ClientError::openai(format!(
+                "deployment '{}' is temporarily unavailable (HTTP 503){tries}: {message}",
+                self.deployment
+            )),
+            _ => ClientError::openai(format!("HTTP {status}: {message}")),
+        }
+    }
+
+    /// Send a system + user prompt and require the answer to match `schema`
+    /// (a JSON Schema object, in strict mode). The response content is the
+    /// JSON text of the object.
+    pub async fn chat_structured(
+        &self,
+        system_prompt: &str,
+        user_prompt: &str,
+        schema_name: &str,
+        schema: &Value,
+        max_tokens: u32,
+    ) -> Result<ChatResponse, ClientError> {
+        let url = self.chat_url();
+        debug!(url = %url, deployment = %self.deployment, "structured chat request");
+
+        let body = structured_request(system_prompt, user_prompt, schema_name, schema, max_tokens);
+        let auth = match &self.auth {
+            AzureOpenAIAuth::ApiKey(key) => ("api-key", key.clone()),
+            AzureOpenAIAuth::AzureCli => {
+                let token = crate::auth::get_token(COGNITIVE_SERVICES_RESOURCE).await?;
+                ("Authorization", format!("Bearer {token}"))
+            }
+        };
+
+        let mut attempt = 1;
+        let resp = loop {
+            let result = self
+                .http
+                .post(&url)
+                .header(auth.0, &auth.1)
+                .json(&body)
+                .send()
+                .await;
+            let can_retry = attempt < self.retry.max_attempts;
+            let server_hint = match result {
+                Ok(ref resp) if can_retry && self.retry.retries_status(resp.status().as_u16()) => {
+                    retry_after(resp.headers())
+                }
+                Err(ref e) if can_retry && self.retry.retries_error(e) => None,
+                _ => break result?,
+            };
+            let delay = self.retry.backoff(attempt, server_hint);
+            debug!(attempt, ?delay, deployment = %self.deployment, "retrying Azure OpenAI request");
+            tokio::time::sleep(delay).await;
+            attempt += 1;
+        };
+
+        let status = resp.status().as_u16();
+        if !resp.status().is_success() {
+            let text = resp.text().await.unwrap_or_default();
+            return Err(self.status_error(status, attempt, &api_error_message(&text)));
+        }
+
+        let completion: ChatCompletion = resp
+            .json()
+            .await
+            .map_err(|e| ClientError::openai(format!("unexpected response: {e}")))?;
+        let message = completion
+            .choices
+            .into_iter()
+            .next()
+            .map(|c| c.message)
+            .ok_or_else(|| ClientError::openai("response has no choices"))?;
+        if let Some(refusal) = message.refusal {
+            return Err(ClientError::openai(format!("model refused: {refusal}")));
+        }
+
+        Ok(ChatResponse {
+            content: message.content.unwrap_or_default(),
+            model: completion.model,
+            usage: completion.usage.map(|u| Usage {
+                prompt_tokens: u.prompt_tokens,
+                completion_tokens: u.completion_tokens,
+                total_tokens: u.total_tokens,
+            }),
+        })
+    }
+}
+
+/// Azure OpenAI quotas are per minute, so throttled requests wait longer than
+/// Cosmos DB ones before giving up.
+fn default_retry_policy() -> RetryPolicy {
+    RetryPolicy {
+        max_attempts: 4,
+        base_backoff: Duration::from_secs(2),
+        max_backoff: Duration::from_secs(60),
+        retry_on: vec![
+            RetryOn::Throttled,
+            RetryOn::ServerError,
+            RetryOn::Connection,
+        ],
+    }
+}
+
+/// Chat completions body asking for a strict `json_schema` response.
+fn structured_request(
+    system_prompt: &str,
+    user_prompt: &str,
+    schema_name: &str,
+    schema: &Value,
+    max_tokens: u32,
+) -> Value {
+    json!({
+        "messages": [
+            { "role": "system", "content": system_prompt },
+            { "role": "user", "content": user_prompt },
+        ],
+        "max_completion_tokens": max_tokens,
+        "response_format": {
+            "type": "json_schema",
+            "json_schema": {
+                "name": schema_name,
+                "strict": true,
+                "schema": schema,
+            },
+        },
+    })
+}
+
+/// The `error.message` of an Azure OpenAI error body, or the body itself.
+fn api_error_message(body: &str) -> String {
+    serde_json::from_str::<Value>(body)
+        .ok()
+        .and_then(|v| v["error"]["message"].as_str().map(str::to_string))
+        .unwrap_or_else(|| body.trim().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn node(value: Value) -> AiNode {
+        serde_json::from_value(value).unwrap()
+    }
+
+    #[test]
+    fn test_from_node() {
+        let ollama = node(json!({ "provider": "ollama", "model": "llama3" }));
+        assert!(
+            AzureOpenAIClient::from_node("o", &ollama, None)
+                .unwrap()
+                .is_none()
+        );
+
+        let azure = node(json!({
+            "provider": "azure-openai",
+            "endpoint": "https://x.openai.azure.com/",
+            "deployment": "gpt-4o",
+            "auth": { "api_key": "k" },
+        }));
+        let client = AzureOpenAIClient::from_node("a", &azure, None)
+            .unwrap()
+            .unwrap();
+        assert_eq!(client.auth, AzureOpenAIAuth::ApiKey("k".into()));
+        assert_eq!(
+            client.chat_url(),
+            "https://x.openai.azure.com/openai/deployments/gpt-4o/chat/completions?api-version=2025-04-01-preview"
+        );
+
+        let overridden = AzureOpenAIClient::from_node("a", &azure, Some("from-config"))
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            overridden.auth,
+            AzureOpenAIAuth::ApiKey("from-config".into())
+        );
+
+        let no_deployment = node(json!({
+            "provider": "azure-openai",
+            "endpoint": "https://x.openai.azure.com",
+        }));
+        assert!(AzureOpenAIClient::from_node("a", &no_deployment, None).is_err());
+    }
+
+    #[test]
+    fn test_structured_request() {
+        let schema = json!({ "type": "object" });
+        let body = structured_request("sys", "user", "cosq_query", &schema, 100);
+        assert_eq!(body["messages"][0]["role"], "system");
+        assert_eq!(body["messages"][1]["content"], "user");
+        assert_eq!(body["response_format"]["type"], "json_schema");
+        assert_eq!(body["response_format"]["json_schema"]["name"], "cosq_query");
+        assert_eq!(body["response_format"]["json_schema"]["strict"], true);
+        assert_eq!(body["response_format"]["json_schema"]["schema"], schema);
+    }
+
+    #[test]
+    fn test_status_error() {
+        let client = AzureOpenAIClient::new(
+            "https://x.openai.azure.com",
+            "gpt-4o",
+            DEFAULT_API_VERSION,
+            AzureOpenAIAuth::ApiKey("k".into()),
+        );
+        let throttled = client
+            .status_error(429, 4, "Rate limit reached")
+            .to_string();
+        assert!(throttled.contains("deployment 'gpt-4o' is throttled"));
+        assert!(throttled.contains("after 4 attempts"));
+        let other = client.status_error(400, 1, "bad schema").to_string();
+        assert_eq!(other, "Azure OpenAI error: HTTP 400: bad schema");
+    }
+
+    #[test]
+    fn test_api_error_message() {
+        assert_eq!(
+            api_error_message(r#"{"error":{"code":"x","message":"bad schema"}}"#),
+            "bad schema"
+        );
+        assert_eq!(api_error_message("oops\n"), "oops");
+    }
+}
@@ -0,0 +1,818 @@
+//! Backend abstraction over the Cosmos DB data plane
+//!
+//! [`CosmosBackend`] covers the operations cosq needs (listing, queries and
+//! point reads/writes). [`CosmosClient`] implements it against the REST API;
+//! [`MemoryBackend`] keeps everything in memory so commands and library users
+//! can be tested without a live account or the emulator.
+
+use std::collections::BTreeMap;
+use std::future::Future;
+use std::sync::Mutex;
+
+use cosq_core::local_query::LocalQuery;
+use serde_json::Value;
+
+use crate::cosmos::{
+    ContainerProperties, CosmosClient, IndexingPolicy, PartitionKeyDefinition, PatchOperation,
+    QueryResult, WrittenDocument,
+};
+use crate::error::ClientError;
+
+/// Data plane operations against a Cosmos DB account.
+pub trait CosmosBackend: Send + Sync {
+    /// List all databases in the account.
+    fn list_databases(&self) -> impl Future<Output = Result<Vec<String>, ClientError>> + Send;
+
+    /// List all containers in a database.
+    fn list_containers(
+        &self,
+        database: &str,
+    ) -> impl Future<Output = Result<Vec<String>, ClientError>> + Send;
+
+    /// Get a container's properties (partition key definition and indexing policy).
+    fn get_container(
+        &self,
+        database: &str,
+        container: &str,
+    ) -> impl Future<Output = Result<ContainerProperties, ClientError>> + Send;
+
+    /// Execute a parameterized SQL query against a container.
+    fn query_with_params(
+        &self,
+        database: &str,
+        container: &str,
+        sql: &str,
+        parameters: Vec<Value>,
+    ) -> impl Future<Output = Result<QueryResult, ClientError>> + Send;
+
+    /// Execute a SQL query against a container.
+    fn query(
+        &self,
+        database: &str,
+        container: &str,
+        sql: &str,
+    ) -> impl Future<Output = Result<QueryResult, ClientError>> + Send {
+        self.query_with_params(database, container, sql, Vec::new())
+    }
+
+    /// Read a single document by id and partition key value.
+    fn read_document(
+        &self,
+        database: &str,
+        container: &str,
+        id: &str,
+        partition_key: &Value,
+    ) -> impl Future<Output = Result<Option<Value>, ClientError>> + Send;
+
+    /// Create or replace a document.
+    fn upsert_document(
+        &self,
+        database: &str,
+        container: &str,
+        document: &Value,
+        partition_key: &Value,
+    ) -> impl Future<Output = Result<Value, ClientError>> + Send;
+
+    /// Create a new document; fails if one with the same id already exists.
+    fn create_document(
+        &self,
+        database: &str,
+        container: &str,
+        document: &Value,
+        partition_key: &Value,
+    ) -> impl Future<Output = Result<WrittenDocument, ClientError>> + Send;
+
+    /// Replace an existing document, optionally only if its `_etag` still matches.
+    fn replace_document(
+        &self,
+        database: &str,
+        container: &str,
+        id: &str,
+        document: &Value,
+        partition_key: &Value,
+        if_match: Option<&str>,
+    ) -> impl Future<Output = Result<Value, ClientError>> + Send;
+
+    /// Apply patch operations to an existing document, atomically.
+    fn patch_document(
+        &self,
+        database: &str,
+        container: &str,
+        id: &str,
+        partition_key: &Value,
+        operations: &[PatchOperation],
+    ) -> impl Future<Output = Result<WrittenDocument, ClientError>> + Send;
+
+    /// Delete a document by id and partition key value, returning the request
+    /// charge (`None` if it didn't exist).
+    fn delete_document(
+        &self,
+        database: &str,
+        container: &str,
+        id: &str,
+        partition_key: &Value,
+    ) -> impl Future<Output = Result<Option<f64>, ClientError>> + Send;
+}
+
+impl CosmosBackend for CosmosClient {
+    async fn list_databases(&self) -> Result<Vec<String>, ClientError> {
+        CosmosClient::list_databases(self).await
+    }
+
+    async fn list_containers(&self, database: &str) -> Result<Vec<String>, ClientError> {
+        CosmosClient::list_containers(self, database).await
+    }
+
+    async fn get_container(
+        &self,
+        database: &str,
+        container: &str,
+    ) -> Result<ContainerProperties, ClientError> {
+        CosmosClient::get_container(self, database, container).await
+    }
+
+    async fn query_with_params(
+        &self,
+        database: &str,
+        container: &str,
+        sql: &str,
+        parameters: Vec<Value>,
+    ) -> Result<QueryResult, ClientError> {
+        CosmosClient::query_with_params(self, database, container, sql, parameters).await
+    }
+
+    async fn read_document(
+        &self,
+        database: &str,
+        container: &str,
+        id: &str,
+        partition_key: &Value,
+    ) -> Result<Option<Value>, ClientError> {
+        CosmosClient::read_document(self, database, container, id, partition_key).await
+    }
+
+    async fn upsert_document(
+        &self,
+        database: &str,
+        container: &str,
+        document: &Value,
+        partition_key: &Value,
+    ) -> Result<Value, ClientError> {
+        CosmosClient::upsert_document(self, database, container, document, partition_key).await
+    }
+
+    async fn create_document(
+        &self,
+        database: &str,
+        container: &str,
+        document: &Value,
+        partition_key: &Value,
+    ) -> Result<WrittenDocument, ClientError> {
+        CosmosClient::create_document(self, database, container, document, partition_key).await
+    }
+
+    async fn replace_document(
+        &self,
+        database: &str,
+        container: &str,
+        id: &str,
+        document: &Value,
+        partition_key: &Value,
+        if_match: Option<&str>,
+    ) -> Result<Value, ClientError> {
+        CosmosClient::replace_document(
+            self,
+            database,
+            container,
+            id,
+            document,
+            partition_key,
+            if_match,
+        )
+        .await
+    }
+
+    async fn patch_document(
+        &self,
+        database: &str,
+        container: &str,
+        id: &str,
+        partition_key: &Value,
+        operations: &[PatchOperation],
+    ) -> Result<WrittenDocument, ClientError> {
+        CosmosClient::patch_document(self, database, container, id, partition_key, operations).await
+    }
+
+    async fn delete_document(
+        &self,
+        database: &str,
+        container: &str,
+        id: &str,
+        partition_key: &Value,
+    ) -> Result<Option<f64>, ClientError> {
+        CosmosClient::delete_document(self, database, container, id, partition_key).await
+    }
+}
+
+/// A container held by [`MemoryBackend`]
+#[derive(Debug, Clone)]
+struct MemoryContainer {
+    partition_key_path: String,
+    documents: Vec<Value>,
+}
+
+impl MemoryContainer {
+    fn position(&self, id: &str, partition_key: &Value) -> Option<usize> {
+        self.documents.iter().position(|d| {
+            d.get("id").and_then(Value::as_str) == Some(id)
+                && lookup_path(d, &self.partition_key_path) == Some(partition_key)
+        })
+    }
+}
+
+/// In-memory [`CosmosBackend`] for tests.
+///
+/// A query returns the result registered for that exact SQL text with
+/// [`MemoryBackend::with_query_result`]; otherwise it is evaluated against the
+/// container's documents with [`cosq_core::local_query`], which rejects SQL
+/// outside its subset (aggregates, `JOIN`, ...) with a 400 like the gateway
+/// would. Executed queries are recorded for assertions.
+#[derive(Debug, Default)]
+pub struct MemoryBackend {
+    containers: Mutex<BTreeMap<(String, String), MemoryContainer>>,
+    query_results: BTreeMap<String, Vec<Value>>,
+    queries: Mutex<Vec<(String, Vec<Value>)>>,
+    request_charge: f64,
+}
+
+impl MemoryBackend {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add an empty container partitioned on `partition_key_path` (e.g. `/tenantId`).
+    pub fn with_container(self, database: &str, container: &str, partition_key_path: &str) -> Self {
+        self.containers.lock().unwrap().insert(
+            (database.to_string(), container.to_string()),
+            MemoryContainer {
+                partition_key_path: partition_key_path.to_string(),
+                documents: Vec::new(),
+            },
+        );
+        self
+    }
+
+    /// Add documents to a container, creating it (partitioned on `/id`) if needed.
+    pub fn with_documents(self, database: &str, container: &str, documents: Vec<Value>) -> Self {
+        self.containers
+            .lock()
+            .unwrap()
+            .entry((database.to_string(), container.to_string()))
+            .or_insert_with(|| MemoryContainer {
+                partition_key_path: "/id".to_string(),
+                documents: Vec::new(),
+            })
+            .documents
+            .extend(documents);
+        self
+    }
+
+    /// Return `documents` whenever exactly this SQL text is queried.
+    pub fn with_query_result(mut self, sql: &str, documents: Vec<Value>) -> Self {
+        self.query_results.insert(sql.to_string(), documents);
+        self
+    }
+
+    /// Request charge reported for every query.
+    pub fn with_request_charge(mut self, charge: f64) -> Self {
+        self.request_charge = charge;
+        self
+    }
+
+    /// Queries executed so far, as (SQL, parameters) pairs.
+    pub fn queries(&self) -> Vec<(String, Vec<Value>)> {
+        self.queries.lock().unwrap().clone()
+    }
+
+    /// Current documents of a container.
+    pub fn documents(&self, database: &str, container: &str) -> Vec<Value> {
+        self.containers
+            .lock()
+            .unwrap()
+            .get(&(database.to_string(), container.to_string()))
+            .map(|c| c.documents.clone())
+            .unwrap_or_default()
+    }
+
+    fn with_container_mut<T>(
+        &self,
+        database: &str,
+        container: &str,
+        f: impl FnOnce(&mut MemoryContainer) -> T,
+    ) -> Result<T, ClientError> {
+        let mut containers = self.containers.lock().unwrap();
+        containers
+            .get_mut(&(database.to_string(), container.to_string()))
+            .map(f)
+            .ok_or_else(|| {
+                ClientError::not_found(format!("container '{container}' in database '{database}'"))
+            })
+    }
+}
+
+impl CosmosBackend for MemoryBackend {
+    async fn list_databases(&self) -> Result<Vec<String>, ClientError> {
+        let mut names: Vec<String> = self
+            .containers
+            .lock()
+            .unwrap()
+            .keys()
+            .map(|(db, _)| db.clone())
+            .collect();
+        names.dedup();
+        Ok(names)
+    }
+
+    async fn list_containers(&self, database: &str) -> Result<Vec<String>, ClientError> {
+        Ok(self
+            .containers
+            .lock()
+            .unwrap()
+            .keys()
+            .filter(|(db, _)| db == database)
+            .map(|(_, ctr)| ctr.clone())
+            .collect())
+    }
+
+    async fn get_container(
+        &self,
+        database: &str,
+        container: &str,
+    ) -> Result<ContainerProperties, ClientError> {
+        self.with_container_mut(database, container, |c| ContainerProperties {
+            id: container.to_string(),
+            partition_key: PartitionKeyDefinition {
+                paths: vec![c.partition_key_path.clone()],
+            },
+            indexing_policy: IndexingPolicy::default(),
+            default_ttl: None,
+            analytical_storage_ttl: None,
+        })
+    }
+
+    async fn query_with_params(
+        &self,
+        database: &str,
+        container: &str,
+        sql: &str,
+        parameters: Vec<Value>,
+    ) -> Result<QueryResult, ClientError> {
+        let all = self.with_container_mut(database, container, |c| c.documents.clone())?;
+        self.queries
+            .lock()
+            .unwrap()
+            .push((sql.to_string(), parameters.clone()));
+        let documents = match self.query_results.get(sql) {
+            Some(canned) => canned.clone(),
+            None => LocalQuery::parse_with_params(sql, &parameters)
+                .map_err(|e| ClientError::api(400, e.to_string()))?
+                .execute(all),
+        };
+        Ok(QueryResult {
+            documents,
+            request_charge: self.request_charge,
+            partitions: Vec::new(),
+            partial: false,
+        })
+    }
+
+    async fn read_document(
+        &self,
+        database: &str,
+        container: &str,
+        id: &str,
+        partition_key: &Value,
+    ) -> Result<Option<Value>, ClientError> {
+        self.with_container_mut(database, container, |c| {
+            c.position(id, partition_key)
+                .map(|i| c.documents[i].clone())
+        })
+    }
+
+    async fn upsert_document(
+        &self,
+        database: &str,
+        container: &str,
+        document: &Value,
+        partition_key: &Value,
+    ) -> Result<Value, ClientError> {
+        let id = document
+            .get("id")
+            .and_then(Value::as_str)
+            .ok_or_else(|| ClientError::api(400, "document is missing a string 'id'"))?;
+        self.with_container_mut(database, container, |c| {
+            match c.position(id, partition_key) {
+                Some(i) => c.documents[i] = document.clone(),
+                None => c.documents.push(document.clone()),
+            }
+            document.clone()
+        })
+    }
+
+    async fn create_document(
+        &self,
+        database: &str,
+        container: &str,
+        document: &Value,
+        partition_key: &Value,
+    ) -> Result<WrittenDocument, ClientError> {
+        let id = document
+            .get("id")
+            .and_then(Value::as_str)
+            .ok_or_else(|| ClientError::api(400, "document is missing a string 'id'"))?;
+        self.with_container_mut(database, container, |c| {
+            if c.position(id, partition_key).is_some() {
+                return Err(ClientError::conflict(format!(
+                    "a document with id \"{id}\" already exists in partition {partition_key}"
+                )));
+            }
+            c.documents.push(document.clone());
+            Ok(WrittenDocument {
+                document: document.clone(),
+                request_charge: self.request_charge,
+            })
+        })?
+    }
+
+    async fn replace_document(
+        &self,
+        database: &str,
+        container: &str,
+        id: &str,
+        document: &Value,
+        partition_key: &Value,
+        if_match: Option<&str>,
+    ) -> Result<Value, ClientError> {
+        self.with_container_mut(database, container, |c| {
+            let i = c
+                .position(id, partition_key)
+                .ok_or_else(|| ClientError::not_found(format!("document '{id}'")))?;
+            let current = c.documents[i].get("_etag").and_then(Value::as_str);
+            if let (Some(expected), Some(current)) = (if_match, current) {
+                if expected != current {
+                    return Err(ClientError::precondition_failed(format!(
+                        "document '{id}' was modified since it was read"
+                    )));
+                }
+            }
+            c.documents[i] = document.clone();
+            Ok(document.clone())
+        })?
+    }
+
+    async fn patch_document(
+        &self,
+        database: &str,
+        container: &str,
+        id: &str,
+        partition_key: &Value,
+        operations: &[PatchOperation],
+    ) -> Result<WrittenDocument, ClientError> {
+        self.with_container_mut(database, container, |c| {
+            let i = c
+                .position(id, partition_key)
+                .ok_or_else(|| ClientError::not_found(format!("document '{id}'")))?;
+            // Apply to a copy so a failing operation leaves the document as it was
+            let mut document = c.documents[i].clone();
+            for operation in operations {
+                apply_patch(&mut document, operation).map_err(|e| ClientError::api(400, e))?;
+            }
+            c.documents[i] = document.clone();
+            Ok(WrittenDocument {
+                document,
+                request_charge: self.request_charge,
+            })
+        })?
+    }
+
+    async fn delete_document(
+        &self,
+        database: &str,
+        container: &str,
+        id: &str,
+        partition_key: &Value,
+    ) -> Result<Option<f64>, ClientError> {
+        self.with_container_mut(database, container, |c| {
+            c.position(id, partition_key)
+                .map(|i| c.documents.remove(i))
+                .map(|_| self.request_charge)
+        })
+    }
+}
+
+/// Follow a partition key path like `/address/city` into a document.
+fn lookup_path<'a>(doc: &'a Value, path: &str) -> Option<&'a Value> {
+    path.trim_start_matches('/')
+        .split('/')
+        .try_fold(doc, |value, key| value.get(key))
+}
+
+/// Apply one patch operation the way Cosmos DB does.
+fn apply_patch(document: &mut Value, operation: &PatchOperation) -> Result<(), String> {
+    let path = operation.path();
+    let (parent_path, key) = path
+        .rsplit_once('/')
+        .filter(|(_, key)| !key.is_empty())
+        .ok_or_else(|| format!("invalid patch path '{path}'"))?;
+    let key = key.replace("~1", "/").replace("~0", "~");
+    let parent = document
+        .pointer_mut(parent_path)
+        .ok_or_else(|| format!("'{parent_path}' not found for patch path '{path}'"))?;
+    let missing = || format!("'{path}' not found");
+
+    match (operation, parent) {
+        (
+            PatchOperation::Add { value, .. } | PatchOperation::Set { value, .. },
+            Value::Object(map),
+        ) => {
+            map.insert(key, value.clone());
+        }
+        (PatchOperation::Add { value, .. }, Value::Array(items)) => {
+            let index = match key.as_str() {
+                "-" => items.len(),
+                index => index
+                    .parse()
+                    .ok()
+                    .filter(|i| *i <= items.len())
+                    .ok_or_else(|| format!("invalid array index in '{path}'"))?,
+            };
+            items.insert(index, value.clone());
+        }
+        (
+            PatchOperation::Set { value, .. } | PatchOperation::Replace { value, .. },
+            Value::Array(items),
+        ) => {
+            let slot = key
+                .parse::<usize>()
+                .ok()
+                .and_then(|i| items.get_mut(i))
+                .ok_or_else(missing)?;
+            *slot = value.clone();
+        }
+        (PatchOperation::Replace { value, .. }, Value::Object(map)) => {
+            *map.get_mut(&key).ok_or_else(missing)? = value.clone();
+        }
+        (PatchOperation::Remove { .. }, Value::Object(map)) => {
+            map.remove(&key).ok_or_else(missing)?;
+        }
+        (PatchOperation::Remove { .. }, Value::Array(items)) => {
+            let index = key
+                .parse::<usize>()
+                .ok()
+                .filter(|i| *i < items.len())
+                .ok_or_else(missing)?;
+            items.remove(index);
+        }
+        (PatchOperation::Increment { value, .. }, Value::Object(map)) => {
+            let current = map.entry(key).or_insert(Value::from(0));
+            *current = match (current.as_i64(), value.as_i64()) {
+                (Some(a), Some(b)) => Value::from(a + b),
+                _ => match (current.as_f64(), value.as_f64()) {
+                    (Some(a), Some(b)) => Value::from(a + b),
+                    _ => return Err(format!("'{path}' and the increment must be numbers")),
+                },
+            };
+        }
+        _ => return Err(format!("can't patch '{path}': its parent is not an object")),
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn backend() -> MemoryBackend {
+        MemoryBackend::new()
+            .with_container("app", "users", "/tenant")
+            .with_documents(
+                "app",
+                "users",
+                vec![
+                    json!({"id": "1", "tenant": "a", "name": "Ada"}),
+                    json!({"id": "1", "tenant": "b", "name": "Bob"}),
+                ],
+            )
+            .with_container("app", "orders", "/id")
+            .with_container("logs", "events", "/id")
+    }
+
+    #[tokio::test]
+    async fn test_listing() {
+        let b = backend();
+        assert_eq!(b.list_databases().await.unwrap(), vec!["app", "logs"]);
+        assert_eq!(
+            b.list_containers("app").await.unwrap(),
+            vec!["orders", "users"]
+        );
+        let props = b.get_container("app", "users").await.unwrap();
+        assert_eq!(props.partition_key.paths, vec!["/tenant"]);
+        assert!(b.get_container("app", "missing").await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_query_records_and_returns_canned_results() {
+        let b = backend()
+            .with_query_result("SELECT VALUE COUNT(1) FROM c", vec![json!(2)])
+            .with_request_charge(2.5);
+        let all = b.query("app", "users", "SELECT * FROM c").await.unwrap();
+        assert_eq!(all.documents.len(), 2);
+        assert_eq!(all.request_charge, 2.5);
+
+        let count = b
+            .query_with_params(
+                "app",
+                "users",
+                "SELECT VALUE COUNT(1) FROM c",
+                vec![json!({"name": "@x", "value": 1})],
+            )
+            .await
+            .unwrap();
+        assert_eq!(count.documents, vec![json!(2)]);
+
+        let queries = b.queries();
+        assert_eq!(queries.len(), 2);
+        assert_eq!(queries[1].1.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_query_evaluates_sql() {
+        let b = backend();
+        let found = b
+            .query_with_params(
+                "app",
+                "users",
+                "SELECT VALUE c.name FROM c WHERE c.tenant = @tenant",
+                vec![json!({"name": "@tenant", "value": "b"})],
+            )
+            .await
+            .unwrap();
+        assert_eq!(found.documents, vec![json!("Bob")]);
+
+        let unsupported = b
+            .query("app", "users", "SELECT VALUE COUNT(1) FROM c")
+            .await;
+        assert!(matches!(
+            unsupported,
+            Err(ClientError::Api { status: 400, .. })
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_point_operations() {
+        let b = backend();
+        let doc = b
+            .read_document("app", "users", "1", &json!("b"))
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(doc["name"], "Bob");
+        assert!(
+            b.read_document("app", "users", "1", &json!("c"))
+                .await
+                .unwrap()
+                .is_none()
+        );
+
+        let updated = json!({"id": "1", "tenant": "b", "name": "Bobby"});
+        b.upsert_document("app", "users", &updated, &json!("b"))
+            .await
+            .unwrap();
+        assert_eq!(b.documents("app", "users").len(), 2);
+        assert_eq!(b.documents("app", "users")[1]["name"], "Bobby");
+
+        let created = json!({"id": "2", "tenant": "a", "name": "Cy"});
+        b.create_document("app", "users", &created, &json!("a"))
+            .await
+            .unwrap();
+        let duplicate = b
+            .create_document("app", "users", &created, &json!("a"))
+            .await;
+        assert!(matches!(duplicate, Err(ClientError::Conflict { .. })));
+        assert_eq!(b.documents("app", "users").len(), 3);
+
+        assert!(
+            b.delete_document("app", "users", "1", &json!("a"))
+                .await
+                .unwrap()
+                .is_some()
+        );
+        assert!(
+            b.delete_document("app", "users", "1", &json!("a"))
+                .await
+                .unwrap()
+                .is_none()
+        );
+        assert_eq!(b.documents("app", "users").len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_patch() {
+        let b = MemoryBackend::new()
+            .with_container("app", "orders", "/id")
+            .with_documents(
+                "app",
+                "orders",
+                vec![
+                    json!({"id": "o1", "status": "new", "retries": 1, "tmp": true, "tags": ["a"]}),
+                ],
+            );
+        let pk = json!("o1");
+        let op = |op: &str, path: &str, value: Value| -> PatchOperation {
+            serde_json::from_value(json!({"op": op, "path": path, "value": value})).unwrap()
+        };
+
+        let patched = b
+            .patch_document(
+                "app",
+                "orders",
+                "o1",
+                &pk,
+                &[
+                    op("set", "/status", json!("shipped")),
+                    op("incr", "/retries", json!(2)),
+                    op("add", "/tags/-", json!("b")),
+                    op("add", "/shipping", json!({"carrier": "ups"})),
+                    op("replace", "/shipping/carrier", json!("dhl")),
+                    PatchOperation::Remove {
+                        path: "/tmp".to_string(),
+                    },
+                ],
+            )
+            .await
+            .unwrap();
+        assert_eq!(
+            patched.document,
+            json!({"id": "o1", "status": "shipped", "retries": 3, "tags": ["a", "b"], "shipping": {"carrier": "dhl"}})
+        );
+
+        // One failing operation leaves the document untouched
+        let failed = b
+            .patch_document(
+                "app",
+                "orders",
+                "o1",
+                &pk,
+                &[
+                    op("set", "/status", json!("lost")),
+                    PatchOperation::Remove {
+                        path: "/missing".to_string(),
+                    },
+                ],
+            )
+            .await;
+        assert!(matches!(failed, Err(ClientError::Api { .. })));
+        assert_eq!(b.documents("app", "orders")[0]["status"], "shipped");
+
+        let missing = b
+            .patch_document("app", "orders", "nope", &json!("nope"), &[])
+            .await;
+        assert!(matches!(missing, Err(ClientError::NotFound { .. })));
+    }
+
+    #[tokio::test]
+    async fn test_replace_with_etag() {
+        let b = MemoryBackend::new()
+            .with_container("app", "orders", "/id")
+            .with_documents(
+                "app",
+                "orders",
+                vec![json!({"id": "o1", "status": "new", "_etag": "\"v1\""})],
+            );
+        let pk = json!("o1");
+
+        let stale = b
+            .replace_document(
+                "app",
+                "orders",
+                "o1",
+                &json!({"id": "o1"}),
+                &pk,
+                Some("\"v0\""),
+            )
+            .await;
+        assert!(matches!(stale, Err(ClientError::PreconditionFailed { .. })));
+
+        let updated = json!({"id": "o1", "status": "shipped"});
+        b.replace_document("app", "orders", "o1", &updated, &pk, Some("\"v1\""))
+            .await
+            .unwrap();
+        assert_eq!(b.documents("app", "orders"), vec![updated.clone()]);
+
+        let missing = b
+            .replace_document("app", "orders", "nope", &updated, &json!("nope"), None)
+            .await;
+        assert!(matches!(missing, Err(ClientError::NotFound { .. })));
+    }
+}
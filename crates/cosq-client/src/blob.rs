@@ -0,0 +1,142 @@
+//! Azure Blob Storage client
+//!
+//! Uploads query output as block blobs using the REST API with AAD token
+//! authentication, for `--out azblob://container/path` destinations.
+
+use tracing::debug;
+
+use crate::auth::{self, STORAGE_RESOURCE};
+use crate::error::ClientError;
+
+const API_VERSION: &str = "2023-11-03";
+
+/// A blob location parsed from an `azblob://container/path` URL
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BlobLocation {
+    pub container: String,
+    pub path: String,
+}
+
+impl BlobLocation {
+    /// Parse an `azblob://container/path/to/blob` URL.
+    pub fn parse(url: &str) -> Option<Self> {
+        let rest = url.strip_prefix("azblob://")?;
+        let (container, path) = rest.split_once('/')?;
+        if container.is_empty() || path.is_empty() || path.ends_with('/') {
+            return None;
+        }
+        Some(Self {
+            container: container.to_string(),
+            path: path.to_string(),
+        })
+    }
+}
+
+/// Azure Blob Storage client for a single storage account
+pub struct BlobClient {
+    http: reqwest::Client,
+    account: String,
+    token: String,
+}
+
+impl BlobClient {
+    /// Create a client for the storage account, acquiring a token via the Azure CLI.
+    pub async fn new(account: &str) -> Result<Self, ClientError> {
+        let token = auth::get_token(STORAGE_RESOURCE).await?;
+        Ok(Self {
+            http: crate::http::client(),
+            account: account.to_string(),
+            token,
+        })
+    }
+
+    /// The blob URL for a location in this account.
+    pub fn blob_url(&self, location: &BlobLocation) -> String {
+        let path: Vec<String> = location
+            .path
+            .split('/')
+            .map(|segment| urlencoding::encode(segment).into_owned())
+            .collect();
+        format!(
+            "https://{}.blob.core.windows.net/{}/{}",
+            self.account,
+            location.container,
+            path.join("/")
+        )
+    }
+
+    /// Upload `body` as a block blob, replacing any existing blob.
+    pub async fn upload(
+        &self,
+        location: &BlobLocation,
+        body: Vec<u8>,
+        content_type: &str,
+    ) -> Result<String, ClientError> {
+        let url = self.blob_url(location);
+        debug!(url = %url, bytes = body.len(), "uploading blob");
+
+        let resp = self
+            .http
+            .put(&url)
+            .bearer_auth(&self.token)
+            .header("x-ms-version", API_VERSION)
+            .header("x-ms-blob-type", "BlockBlob")
+            .header("Content-Type", content_type)
+            .body(body)
+            .send()
+            .await?;
+
+        let status = resp.status();
+        if !status.is_success() {
+            let body = resp.text().await.unwrap_or_default();
+            if status.as_u16() == 403 {
+                return Err(ClientError::forbidden(
+                    body,
+                    "Uploading needs the 'Storage Blob Data Contributor' role on the storage account.",
+                ));
+            }
+            if status.as_u16() == 404 {
+                return Err(ClientError::not_found(format!(
+                    "container '{}' not found in storage account '{}'",
+                    location.container, self.account
+                )));
+            }
+            return Err(ClientError::api(status.as_u16(), body));
+        }
+
+        Ok(url)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_blob_location() {
+        assert_eq!(
+            BlobLocation::parse("azblob://reports/daily/orders.csv"),
+            Some(BlobLocation {
+                container: "reports".into(),
+                path: "daily/orders.csv".into(),
+            })
+        );
+        assert_eq!(BlobLocation::parse("azblob://reports"), None);
+        assert_eq!(BlobLocation::parse("azblob://reports/"), None);
+        assert_eq!(BlobLocation::parse("reports/orders.csv"), None);
+    }
+
+    #[test]
+    fn test_blob_url_encodes_segments() {
+        let client = BlobClient {
+            http: reqwest::Client::new(),
+            account: "acct".into(),
+            token: String::new(),
+        };
+        let location = BlobLocation::parse("azblob://reports/2024 q1/orders.csv").unwrap();
+        assert_eq!(
+            client.blob_url(&location),
+            "https://acct.blob.core.windows.net/reports/2024%20q1/orders.csv"
+        );
+    }
+}
@@ -1,25 +1,171 @@
 //! Cosmos DB data plane client
 //!
 //! Executes SQL queries against Cosmos DB containers using the REST API
-//! with AAD token authentication. Handles cross-partition queries by
-//! fetching partition key ranges and fanning out the query.
+//! with AAD token authentication (or master key auth, e.g. for the emulator).
+//! Handles cross-partition queries by fetching partition key ranges and
+//! fanning out the query.
 
-use serde::Deserialize;
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use hmac::{Hmac, Mac};
+use reqwest::header::HeaderMap;
+use reqwest::{Method, RequestBuilder, Response};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
 use serde_json::Value;
+use sha2::Sha256;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use tokio::sync::mpsc;
 use tracing::debug;
 
-use crate::auth::{AzCliAuth, COSMOS_RESOURCE};
+use cosq_core::plan_cache::{self, CachedPlan, QueryPlan};
+
+use crate::auth::{self, COSMOS_RESOURCE};
 use crate::error::ClientError;
+use crate::http;
+use crate::retry::{self, RetryPolicy};
 
 const API_VERSION: &str = "2018-12-31";
 
+/// The first REST API version with the Patch operation
+const PATCH_API_VERSION: &str = "2020-07-15";
+
+/// Most operations Cosmos DB accepts in one patch request
+pub const MAX_PATCH_OPERATIONS: usize = 10;
+
+/// Whether a request changes data, which decides what the retry policy may
+/// retry
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Effect {
+    Read,
+    Write,
+}
+
 /// Result of a Cosmos DB SQL query
 #[derive(Debug)]
-pub struct QueryResult {
-    pub documents: Vec<Value>,
+pub struct QueryResult<T = Value> {
+    pub documents: Vec<T>,
+    pub request_charge: f64,
+    /// Documents and request charge per partition key range queried
+    pub partitions: Vec<PartitionStats>,
+    /// The query was cancelled before all pages were fetched
+    pub partial: bool,
+}
+
+/// What a query cost on one partition key range
+#[derive(Debug, Clone, PartialEq)]
+pub struct PartitionStats {
+    pub range_id: String,
+    pub documents: usize,
     pub request_charge: f64,
 }
 
+/// A document written to a container, as stored (with system properties)
+#[derive(Debug, Clone, PartialEq)]
+pub struct WrittenDocument {
+    pub document: Value,
+    pub request_charge: f64,
+}
+
+/// One operation of a partial document update (the Patch API). Paths are
+/// JSON pointers like `/status` or `/items/0/qty`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "lowercase")]
+pub enum PatchOperation {
+    /// Add a property, or insert into an array at an index (`-` appends)
+    Add { path: String, value: Value },
+    /// Set a property, creating it if it's missing
+    Set { path: String, value: Value },
+    /// Replace a property that must already exist
+    Replace { path: String, value: Value },
+    /// Remove a property that must exist
+    Remove { path: String },
+    /// Add a (possibly negative) number to a numeric property, creating it if missing
+    #[serde(rename = "incr")]
+    Increment { path: String, value: Value },
+}
+
+impl PatchOperation {
+    pub fn path(&self) -> &str {
+        match self {
+            PatchOperation::Add { path, .. }
+            | PatchOperation::Set { path, .. }
+            | PatchOperation::Replace { path, .. }
+            | PatchOperation::Remove { path }
+            | PatchOperation::Increment { path, .. } => path,
+        }
+    }
+}
+
+/// Diagnostics of one data plane response, as support engineers ask for them
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ResponseDiagnostics {
+    pub method: String,
+    /// Request path relative to the account endpoint
+    pub path: String,
+    pub status: u16,
+    /// `x-ms-activity-id`
+    pub activity_id: Option<String>,
+    /// `x-ms-session-token`
+    pub session_token: Option<String>,
+    /// `x-ms-request-duration-ms` (time spent on the server)
+    pub server_duration_ms: Option<f64>,
+    /// `x-ms-request-charge`
+    pub request_charge: Option<f64>,
+    /// `x-ms-retry-after-ms` (sent with 429s)
+    pub retry_after_ms: Option<f64>,
+}
+
+impl ResponseDiagnostics {
+    pub fn from_headers(method: &str, path: &str, status: u16, headers: &HeaderMap) -> Self {
+        let text = |name: &str| {
+            headers
+                .get(name)
+                .and_then(|v| v.to_str().ok())
+                .map(str::to_string)
+        };
+        let number = |name: &str| text(name).and_then(|v| v.parse().ok());
+        Self {
+            method: method.to_string(),
+            path: path.to_string(),
+            status,
+            activity_id: text("x-ms-activity-id"),
+            session_token: text("x-ms-session-token"),
+            server_duration_ms: number("x-ms-request-duration-ms"),
+            request_charge: number("x-ms-request-charge"),
+            retry_after_ms: number("x-ms-retry-after-ms"),
+        }
+    }
+}
+
+/// Callback invoked with the diagnostics of every response (including retried ones)
+pub type DiagnosticsHook = Arc<dyn Fn(&ResponseDiagnostics) + Send + Sync>;
+
+impl QueryResult {
+    /// Deserialize every document into `T`.
+    ///
+    /// Fails on the first document that doesn't match, reporting its index.
+    pub fn into_typed<T: DeserializeOwned>(self) -> Result<QueryResult<T>, ClientError> {
+        let documents = self
+            .documents
+            .into_iter()
+            .enumerate()
+            .map(|(index, doc)| {
+                serde_json::from_value(doc)
+                    .map_err(|source| ClientError::Deserialize { index, source })
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(QueryResult {
+            documents,
+            request_charge: self.request_charge,
+            partitions: self.partitions,
+            partial: self.partial,
+        })
+    }
+}
+
 /// Cosmos DB REST API response for queries
 #[derive(Debug, Deserialize)]
 struct QueryResponse {
@@ -51,9 +197,102 @@ struct CollectionEntry {
     id: String,
 }
 
+/// Container (collection) properties from the data plane
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ContainerProperties {
+    pub id: String,
+    #[serde(default)]
+    pub partition_key: PartitionKeyDefinition,
+    #[serde(default)]
+    pub indexing_policy: IndexingPolicy,
+    /// Time to live in seconds for documents (`-1`: no expiry unless set per document)
+    #[serde(default)]
+    pub default_ttl: Option<i64>,
+    /// Analytical store (Synapse Link) retention in seconds (`-1`: forever);
+    /// absent or `0` when the analytical store is off for this container
+    #[serde(default)]
+    pub analytical_storage_ttl: Option<i64>,
+}
+
+impl ContainerProperties {
+    /// Whether the container has an analytical store (Synapse Link / Fabric mirroring).
+    pub fn has_analytical_store(&self) -> bool {
+        self.analytical_storage_ttl.is_some_and(|ttl| ttl != 0)
+    }
+}
+
+/// Storage used by a container, from its quota info (`x-ms-resource-usage`)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ContainerUsage {
+    pub documents_count: u64,
+    /// Size of the stored documents in KB
+    pub documents_size_kb: u64,
+}
+
+impl ContainerUsage {
+    /// Parse an `x-ms-resource-usage` header such as
+    /// `documentsSize=1024;documentsCount=10;collectionSize=1100`.
+    pub fn from_header(value: &str) -> Self {
+        let mut usage = Self::default();
+        for (key, number) in value
+            .split(';')
+            .filter_map(|pair| pair.split_once('='))
+            .filter_map(|(key, number)| Some((key.trim(), number.trim().parse().ok()?)))
+        {
+            match key {
+                "documentsCount" => usage.documents_count = number,
+                "documentsSize" => usage.documents_size_kb = number,
+                _ => {}
+            }
+        }
+        usage
+    }
+}
+
+/// Partition key definition of a container
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct PartitionKeyDefinition {
+    #[serde(default)]
+    pub paths: Vec<String>,
+}
+
+/// Indexing policy of a container
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IndexingPolicy {
+    #[serde(default)]
+    pub included_paths: Vec<IndexPath>,
+    #[serde(default)]
+    pub excluded_paths: Vec<IndexPath>,
+    #[serde(default)]
+    pub composite_indexes: Vec<Vec<CompositePath>>,
+}
+
+/// An included or excluded index path
+#[derive(Debug, Clone, Deserialize)]
+pub struct IndexPath {
+    pub path: String,
+}
+
+/// One path of a composite index
+#[derive(Debug, Clone, Deserialize)]
+pub struct CompositePath {
+    pub path: String,
+    #[serde(default = "default_composite_order")]
+    pub order: String,
+}
+
+fn default_composite_order() -> String {
+    "ascending".to_string()
+}
+
 /// Partition key range info from the pkranges endpoint
 #[derive(Debug, Deserialize)]
 struct PartitionKeyRangesResponse {
+    /// Resource id of the container
+    #[serde(rename = "_rid", default)]
+    rid: String,
     #[serde(rename = "PartitionKeyRanges")]
     partition_key_ranges: Vec<PartitionKeyRange>,
 }
@@ -61,6 +300,71 @@ struct PartitionKeyRangesResponse {
 #[derive(Debug, Deserialize)]
 struct PartitionKeyRange {
     id: String,
+    #[serde(rename = "minInclusive")]
+    min_inclusive: Option<String>,
+    #[serde(rename = "maxExclusive")]
+    max_exclusive: Option<String>,
+}
+
+/// Query features the client accepts in a gateway query plan
+const SUPPORTED_QUERY_FEATURES: &str = "Aggregate, CompositeAggregate, Distinct, GroupBy, \
+     MultipleAggregates, MultipleOrderBy, OffsetAndLimit, OrderBy, Top, NonValueAggregate, DCount";
+
+/// Priority of query requests on accounts with priority-based execution enabled
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PriorityLevel {
+    Low,
+    High,
+}
+
+impl PriorityLevel {
+    /// Value of the `x-ms-cosmos-priority-level` header
+    fn as_header(self) -> &'static str {
+        match self {
+            PriorityLevel::Low => "Low",
+            PriorityLevel::High => "High",
+        }
+    }
+}
+
+/// Access a resource token grants
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PermissionMode {
+    Read,
+    All,
+}
+
+impl PermissionMode {
+    fn as_str(self) -> &'static str {
+        match self {
+            PermissionMode::Read => "Read",
+            PermissionMode::All => "All",
+        }
+    }
+}
+
+/// A permission on a container, carrying its scoped resource token
+#[derive(Debug, Clone, Deserialize)]
+pub struct Permission {
+    pub id: String,
+    #[serde(rename = "permissionMode")]
+    pub mode: String,
+    /// Resource link the token is scoped to (`dbs/<db>/colls/<container>`)
+    pub resource: String,
+    #[serde(rename = "resourcePartitionKey", default)]
+    pub partition_key: Option<Vec<Value>>,
+    /// The resource token
+    #[serde(rename = "_token")]
+    pub token: String,
+}
+
+/// How requests are authorized
+#[derive(Clone)]
+enum Credential {
+    /// AAD access token from the Azure CLI
+    Aad(String),
+    /// Decoded account master key (HMAC-signed requests)
+    MasterKey(Vec<u8>),
 }
 
 /// Client for the Cosmos DB data plane REST API.
@@ -68,25 +372,290 @@ struct PartitionKeyRange {
 pub struct CosmosClient {
     http: reqwest::Client,
     endpoint: String,
-    token: String,
+    credential: Credential,
+    retry: RetryPolicy,
+    priority: Option<PriorityLevel>,
+    cancelled: Option<Arc<AtomicBool>>,
+    on_response: Option<DiagnosticsHook>,
+    /// Directory of cached query plans (see [`CosmosClient::with_plan_cache`])
+    plan_cache: Option<PathBuf>,
+}
+
+/// Builder for [`CosmosClient`] with authentication, TLS and retry options.
+#[derive(Debug, Clone)]
+pub struct CosmosClientBuilder {
+    endpoint: String,
+    master_key: Option<String>,
+    root_certificate: Option<Vec<u8>>,
+    retry: RetryPolicy,
+    priority: Option<PriorityLevel>,
+}
+
+impl CosmosClientBuilder {
+    /// Sign requests with a base64 account master key instead of an Azure CLI token.
+    pub fn master_key(mut self, key: impl Into<String>) -> Self {
+        self.master_key = Some(key.into());
+        self
+    }
+
+    /// Trust an additional root certificate (PEM).
+    pub fn root_certificate(mut self, pem: impl Into<Vec<u8>>) -> Self {
+        self.root_certificate = Some(pem.into());
+        self
+    }
+
+    /// Retry policy applied to every request (queries, listings and writes).
+    pub fn retry_policy(mut self, policy: RetryPolicy) -> Self {
+        self.retry = policy;
+        self
+    }
+
+    /// Priority level sent with query requests.
+    pub fn priority(mut self, level: PriorityLevel) -> Self {
+        self.priority = Some(level);
+        self
+    }
+
+    /// Build the client, acquiring an AAD token via the Azure CLI unless a master key is set.
+    pub async fn build(self) -> Result<CosmosClient, ClientError> {
+        let mut client = match self.master_key {
+            Some(ref key) => CosmosClient::with_master_key(&self.endpoint, key)?,
+            None => CosmosClient::new(&self.endpoint).await?,
+        };
+        if let Some(ref pem) = self.root_certificate {
+            client = client.with_root_certificate(pem)?;
+        }
+        client.retry = self.retry;
+        client.priority = self.priority;
+        Ok(client)
+    }
 }
 
 impl CosmosClient {
+    /// Start building a client for the account endpoint.
+    pub fn builder(endpoint: &str) -> CosmosClientBuilder {
+        CosmosClientBuilder {
+            endpoint: endpoint.to_string(),
+            master_key: None,
+            root_certificate: None,
+            retry: RetryPolicy::default(),
+            priority: None,
+        }
+    }
+
     /// Create a new Cosmos client, acquiring a Cosmos DB token via the Azure CLI.
     pub async fn new(endpoint: &str) -> Result<Self, ClientError> {
-        let token = AzCliAuth::get_token(COSMOS_RESOURCE).await?;
+        let token = auth::get_token(COSMOS_RESOURCE).await?;
         let endpoint = endpoint.trim_end_matches('/').to_string();
         Ok(Self {
-            http: reqwest::Client::new(),
+            http: http::client(),
             endpoint,
-            token,
+            credential: Credential::Aad(token),
+            retry: RetryPolicy::default(),
+            priority: None,
+            cancelled: None,
+            on_response: None,
+            plan_cache: None,
         })
     }
 
-    /// Build the Authorization header value for AAD token auth.
-    fn auth_header(&self) -> String {
-        let sig = urlencoding::encode(&self.token);
-        format!("type%3Daad%26ver%3D1.0%26sig%3D{sig}")
+    /// Create a Cosmos client that signs requests with a base64 account master key.
+    pub fn with_master_key(endpoint: &str, key: &str) -> Result<Self, ClientError> {
+        let key = BASE64
+            .decode(key.trim())
+            .map_err(|e| ClientError::auth(format!("invalid master key: {e}")))?;
+        Ok(Self {
+            http: http::client(),
+            endpoint: endpoint.trim_end_matches('/').to_string(),
+            credential: Credential::MasterKey(key),
+            retry: RetryPolicy::default(),
+            priority: None,
+            cancelled: None,
+            on_response: None,
+            plan_cache: None,
+        })
+    }
+
+    /// Trust an additional root certificate (PEM), e.g. the emulator's self-signed certificate.
+    pub fn with_root_certificate(mut self, pem: &[u8]) -> Result<Self, ClientError> {
+        let cert = reqwest::Certificate::from_pem(pem)?;
+        self.http = http::builder()?.add_root_certificate(cert).build()?;
+        Ok(self)
+    }
+
+    /// Send query requests with this priority level (`x-ms-cosmos-priority-level`),
+    /// so low-priority queries are throttled before production traffic.
+    pub fn with_priority(mut self, level: PriorityLevel) -> Self {
+        self.priority = Some(level);
+        self
+    }
+
+    /// Stop issuing query page requests once `cancelled` is set (e.g. on Ctrl+C);
+    /// queries then return the documents fetched so far, marked `partial`.
+    pub fn with_cancellation(mut self, cancelled: Arc<AtomicBool>) -> Self {
+        self.cancelled = Some(cancelled);
+        self
+    }
+
+    /// Call `hook` with the diagnostics headers of every response, e.g. to print
+    /// activity ids for a support ticket.
+    pub fn with_diagnostics(mut self, hook: DiagnosticsHook) -> Self {
+        self.on_response = Some(hook);
+        self
+    }
+
+    /// Fetch a gateway query plan before fanning a query out, and only query
+    /// the partition key ranges it can match. Plans are cached in `dir`, so
+    /// a repeated query (same SQL and parameter values on the same container)
+    /// skips the plan request. Meant for queries that run again and again,
+    /// like stored queries.
+    pub fn with_plan_cache(mut self, dir: PathBuf) -> Self {
+        self.plan_cache = Some(dir);
+        self
+    }
+
+    fn is_cancelled(&self) -> bool {
+        self.cancelled
+            .as_ref()
+            .is_some_and(|c| c.load(Ordering::Relaxed))
+    }
+
+    /// Build the Authorization header value for a request.
+    ///
+    /// `resource_type` is e.g. `docs` and `resource_link` the path of the owning
+    /// resource (e.g. `dbs/mydb/colls/users`); both are only used for master key signing.
+    fn auth_header(
+        &self,
+        verb: &str,
+        resource_type: &str,
+        resource_link: &str,
+        date: &str,
+    ) -> String {
+        match &self.credential {
+            Credential::Aad(token) => {
+                let sig = urlencoding::encode(token);
+                format!("type%3Daad%26ver%3D1.0%26sig%3D{sig}")
+            }
+            Credential::MasterKey(key) => {
+                let payload = format!(
+                    "{}\n{}\n{}\n{}\n\n",
+                    verb.to_lowercase(),
+                    resource_type.to_lowercase(),
+                    resource_link,
+                    date.to_lowercase()
+                );
+                let mut mac =
+                    Hmac::<Sha256>::new_from_slice(key).expect("HMAC accepts keys of any length");
+                mac.update(payload.as_bytes());
+                let sig = BASE64.encode(mac.finalize().into_bytes());
+                urlencoding::encode(&format!("type=master&ver=1.0&sig={sig}")).into_owned()
+            }
+        }
+    }
+
+    /// Send a signed request that reads (including queries), retrying
+    /// transient failures per the retry policy.
+    ///
+    /// The request is rebuilt for every attempt (fresh date and signature);
+    /// `configure` adds the operation-specific headers and body.
+    async fn send(
+        &self,
+        method: Method,
+        url: &str,
+        resource_type: &str,
+        resource_link: &str,
+        configure: impl Fn(RequestBuilder) -> RequestBuilder,
+    ) -> Result<Response, ClientError> {
+        self.send_versioned(
+            method,
+            url,
+            resource_type,
+            resource_link,
+            API_VERSION,
+            Effect::Read,
+            configure,
+        )
+        .await
+    }
+
+    /// [`Self::send`] for a request that changes data: it is only retried
+    /// when throttled or never sent, so a retry can't apply it twice.
+    async fn send_write(
+        &self,
+        method: Method,
+        url: &str,
+        resource_type: &str,
+        resource_link: &str,
+        configure: impl Fn(RequestBuilder) -> RequestBuilder,
+    ) -> Result<Response, ClientError> {
+        self.send_versioned(
+            method,
+            url,
+            resource_type,
+            resource_link,
+            API_VERSION,
+            Effect::Write,
+            configure,
+        )
+        .await
+    }
+
+    /// [`Self::send`] with a specific `x-ms-version`, for operations the
+    /// default API version doesn't have.
+    #[allow(clippy::too_many_arguments)]
+    async fn send_versioned(
+        &self,
+        method: Method,
+        url: &str,
+        resource_type: &str,
+        resource_link: &str,
+        api_version: &str,
+        effect: Effect,
+        configure: impl Fn(RequestBuilder) -> RequestBuilder,
+    ) -> Result<Response, ClientError> {
+        let mut attempt = 1;
+        loop {
+            let date = Self::date_header();
+            let request = self
+                .http
+                .request(method.clone(), url)
+                .header(
+                    "Authorization",
+                    self.auth_header(method.as_str(), resource_type, resource_link, &date),
+                )
+                .header("x-ms-date", &date)
+                .header("x-ms-version", api_version);
+            let result = configure(request).send().await;
+            if let (Some(hook), Ok(resp)) = (&self.on_response, &result) {
+                let path = url.strip_prefix(&self.endpoint).unwrap_or(url);
+                hook(&ResponseDiagnostics::from_headers(
+                    method.as_str(),
+                    path,
+                    resp.status().as_u16(),
+                    resp.headers(),
+                ));
+            }
+
+            let can_retry = attempt < self.retry.max_attempts;
+            let retries = match (&result, effect) {
+                (Ok(resp), Effect::Read) => self.retry.retries_status(resp.status().as_u16()),
+                (Ok(resp), Effect::Write) => {
+                    self.retry.retries_write_status(resp.status().as_u16())
+                }
+                (Err(e), Effect::Read) => self.retry.retries_error(e),
+                (Err(e), Effect::Write) => self.retry.retries_write_error(e),
+            };
+            let server_hint = match result {
+                Ok(ref resp) if can_retry && retries => retry::retry_after(resp.headers()),
+                Err(_) if can_retry && retries => None,
+                _ => return Ok(result?),
+            };
+
+            let delay = self.retry.backoff(attempt, server_hint);
+            debug!(attempt, ?delay, url, "retrying request");
+            tokio::time::sleep(delay).await;
+            attempt += 1;
+        }
     }
 
     /// Build the x-ms-date header value in RFC 1123 format.
@@ -100,16 +669,8 @@ impl CosmosClient {
     pub async fn list_databases(&self) -> Result<Vec<String>, ClientError> {
         debug!("listing databases");
         let url = format!("{}/dbs", self.endpoint);
-        let date = Self::date_header();
 
-        let resp = self
-            .http
-            .get(&url)
-            .header("Authorization", self.auth_header())
-            .header("x-ms-date", &date)
-            .header("x-ms-version", API_VERSION)
-            .send()
-            .await?;
+        let resp = self.send(Method::GET, &url, "dbs", "", |r| r).await?;
 
         let status = resp.status();
         if !status.is_success() {
@@ -133,16 +694,9 @@ impl CosmosClient {
     pub async fn list_containers(&self, database: &str) -> Result<Vec<String>, ClientError> {
         debug!(database, "listing containers");
         let url = format!("{}/dbs/{}/colls", self.endpoint, database);
-        let date = Self::date_header();
+        let link = format!("dbs/{database}");
 
-        let resp = self
-            .http
-            .get(&url)
-            .header("Authorization", self.auth_header())
-            .header("x-ms-date", &date)
-            .header("x-ms-version", API_VERSION)
-            .send()
-            .await?;
+        let resp = self.send(Method::GET, &url, "colls", &link, |r| r).await?;
 
         let status = resp.status();
         if !status.is_success() {
@@ -160,25 +714,82 @@ impl CosmosClient {
         Ok(names)
     }
 
+    /// Get a container's properties (partition key definition and indexing policy).
+    pub async fn get_container(
+        &self,
+        database: &str,
+        container: &str,
+    ) -> Result<ContainerProperties, ClientError> {
+        debug!(database, container, "reading container properties");
+        let url = format!("{}/dbs/{}/colls/{}", self.endpoint, database, container);
+        let link = format!("dbs/{database}/colls/{container}");
+
+        let resp = self.send(Method::GET, &url, "colls", &link, |r| r).await?;
+
+        let status = resp.status();
+        if !status.is_success() {
+            let body = resp.text().await.unwrap_or_default();
+            if status.as_u16() == 404 {
+                return Err(ClientError::not_found(format!(
+                    "container '{container}' in database '{database}'"
+                )));
+            }
+            return Err(ClientError::api(status.as_u16(), body));
+        }
+
+        Ok(resp.json().await?)
+    }
+
+    /// Get a container's properties together with its document count and
+    /// storage (the container read with quota info populated).
+    pub async fn get_container_usage(
+        &self,
+        database: &str,
+        container: &str,
+    ) -> Result<(ContainerProperties, ContainerUsage), ClientError> {
+        debug!(database, container, "reading container usage");
+        let url = format!("{}/dbs/{}/colls/{}", self.endpoint, database, container);
+        let link = format!("dbs/{database}/colls/{container}");
+
+        let resp = self
+            .send(Method::GET, &url, "colls", &link, |r| {
+                r.header("x-ms-documentdb-populatequotainfo", "True")
+            })
+            .await?;
+
+        let status = resp.status();
+        if !status.is_success() {
+            let body = resp.text().await.unwrap_or_default();
+            if status.as_u16() == 404 {
+                return Err(ClientError::not_found(format!(
+                    "container '{container}' in database '{database}'"
+                )));
+            }
+            return Err(ClientError::api(status.as_u16(), body));
+        }
+        let usage = resp
+            .headers()
+            .get("x-ms-resource-usage")
+            .and_then(|v| v.to_str().ok())
+            .map(ContainerUsage::from_header)
+            .unwrap_or_default();
+        Ok((resp.json().await?, usage))
+    }
+
     /// Get partition key ranges for a container.
     async fn get_partition_key_ranges(
         &self,
         database: &str,
         container: &str,
-    ) -> Result<Vec<String>, ClientError> {
+    ) -> Result<PartitionKeyRangesResponse, ClientError> {
         let url = format!(
             "{}/dbs/{}/colls/{}/pkranges",
             self.endpoint, database, container
         );
-        let date = Self::date_header();
+        let link = format!("dbs/{database}/colls/{container}");
 
         let resp = self
-            .http
-            .get(&url)
-            .header("Authorization", self.auth_header())
-            .header("x-ms-date", &date)
-            .header("x-ms-version", API_VERSION)
-            .send()
+            .send(Method::GET, &url, "pkranges", &link, |r| r)
             .await?;
 
         let status = resp.status();
@@ -188,88 +799,204 @@ impl CosmosClient {
         }
 
         let ranges: PartitionKeyRangesResponse = resp.json().await?;
-        let ids: Vec<String> = ranges
-            .partition_key_ranges
-            .into_iter()
-            .map(|r| r.id)
-            .collect();
-        debug!(count = ids.len(), "found partition key ranges");
-        Ok(ids)
+        debug!(
+            count = ranges.partition_key_ranges.len(),
+            "found partition key ranges"
+        );
+        Ok(ranges)
+    }
+
+    /// Request the gateway query plan for a query.
+    async fn get_query_plan(
+        &self,
+        url: &str,
+        resource_link: &str,
+        body: &Value,
+    ) -> Result<QueryPlan, ClientError> {
+        let resp = self
+            .send(Method::POST, url, "docs", resource_link, |r| {
+                r.header("x-ms-documentdb-isquery", "True")
+                    .header("x-ms-documentdb-query-enablecrosspartition", "True")
+                    .header("x-ms-cosmos-is-query-plan-request", "True")
+                    .header(
+                        "x-ms-cosmos-supported-query-features",
+                        SUPPORTED_QUERY_FEATURES,
+                    )
+                    .header("x-ms-cosmos-query-version", "1.4")
+                    .header("Content-Type", "application/query+json")
+                    .json(body)
+            })
+            .await?;
+        let status = resp.status();
+        if !status.is_success() {
+            let body = resp.text().await.unwrap_or_default();
+            return Err(ClientError::api(status.as_u16(), body));
+        }
+        Ok(resp.json().await?)
+    }
+
+    /// The query plan from the cache in `dir` when it was made for the
+    /// container with `container_rid` and hasn't expired, or from the gateway
+    /// (then cached). `None` if the plan can't be had; the query then fans out
+    /// to every range.
+    async fn query_plan(
+        &self,
+        dir: &Path,
+        url: &str,
+        resource_link: &str,
+        body: &Value,
+        key: &str,
+        container_rid: &str,
+    ) -> Option<QueryPlan> {
+        match plan_cache::load_from(dir, key) {
+            Ok(Some(cached)) if cached.is_valid(container_rid, chrono::Utc::now()) => {
+                debug!("using cached query plan");
+                return Some(cached.plan);
+            }
+            Ok(_) => {}
+            Err(e) => debug!(error = %e, "ignoring unreadable cached query plan"),
+        }
+        match self.get_query_plan(url, resource_link, body).await {
+            Ok(plan) => {
+                // Without the container's id a plan can't be checked later
+                if !container_rid.is_empty() {
+                    let cached = CachedPlan::new(container_rid, plan.clone());
+                    if let Err(e) = plan_cache::save_to(dir, key, &cached) {
+                        debug!(error = %e, "failed to cache query plan");
+                    }
+                }
+                Some(plan)
+            }
+            Err(e) => {
+                debug!(error = %e, "query plan request failed, querying every range");
+                None
+            }
+        }
+    }
+
+    /// Request one page of a query against a single partition key range.
+    async fn query_page(
+        &self,
+        url: &str,
+        resource_link: &str,
+        body: &Value,
+        partition_key_range_id: &str,
+        continuation: Option<&str>,
+    ) -> Result<Response, ClientError> {
+        let resp = self
+            .send(Method::POST, url, "docs", resource_link, |r| {
+                let r = r
+                    .header("x-ms-documentdb-isquery", "True")
+                    .header("x-ms-documentdb-query-enablecrosspartition", "True")
+                    .header(
+                        "x-ms-documentdb-partitionkeyrangeid",
+                        partition_key_range_id,
+                    )
+                    .header("Content-Type", "application/query+json")
+                    .json(body);
+                let r = match self.priority {
+                    Some(level) => r.header("x-ms-cosmos-priority-level", level.as_header()),
+                    None => r,
+                };
+                match continuation {
+                    Some(token) => r.header("x-ms-continuation", token),
+                    None => r,
+                }
+            })
+            .await?;
+        let status = resp.status();
+
+        if !status.is_success() {
+            let body_text = resp.text().await.unwrap_or_default();
+            if status.as_u16() == 403 {
+                return Err(ClientError::forbidden(
+                    body_text,
+                    "You may not have data plane access. Check your Cosmos DB RBAC roles.",
+                ));
+            }
+            return Err(ClientError::api(status.as_u16(), body_text));
+        }
+        Ok(resp)
     }
 
     /// Execute a SQL query against a single partition key range, handling pagination.
+    ///
+    /// The continuation token arrives in the response headers, so the next
+    /// page is requested while the current page's body is still being
+    /// downloaded and parsed. With `pages`, each page is sent on as it
+    /// arrives instead of collected; a dropped receiver stops the query like
+    /// a cancellation. Returns the collected documents, the document count,
+    /// the request charge and whether the query stopped early.
     async fn query_partition(
         &self,
         url: &str,
+        resource_link: &str,
         body: &Value,
         partition_key_range_id: &str,
-    ) -> Result<(Vec<Value>, f64), ClientError> {
+        pages: Option<&mpsc::Sender<Vec<Value>>>,
+    ) -> Result<(Vec<Value>, usize, f64, bool), ClientError> {
         let mut documents = Vec::new();
+        let mut count = 0;
         let mut total_charge = 0.0_f64;
-        let mut continuation: Option<String> = None;
+        let mut resp = self
+            .query_page(url, resource_link, body, partition_key_range_id, None)
+            .await?;
 
         loop {
-            let date = Self::date_header();
-            let mut request = self
-                .http
-                .post(url)
-                .header("Authorization", self.auth_header())
-                .header("x-ms-date", &date)
-                .header("x-ms-version", API_VERSION)
-                .header("x-ms-documentdb-isquery", "True")
-                .header("x-ms-documentdb-query-enablecrosspartition", "True")
-                .header(
-                    "x-ms-documentdb-partitionkeyrangeid",
-                    partition_key_range_id,
-                )
-                .header("Content-Type", "application/query+json")
-                .json(body);
-
-            if let Some(ref token) = continuation {
-                request = request.header("x-ms-continuation", token);
-            }
-
-            let resp = request.send().await?;
-            let status = resp.status();
-
-            if !status.is_success() {
-                let body_text = resp.text().await.unwrap_or_default();
-                if status.as_u16() == 403 {
-                    return Err(ClientError::forbidden(
-                        body_text,
-                        "You may not have data plane access. Check your Cosmos DB RBAC roles.",
-                    ));
-                }
-                return Err(ClientError::api(status.as_u16(), body_text));
-            }
-
             let next_continuation = resp
                 .headers()
                 .get("x-ms-continuation")
                 .and_then(|v| v.to_str().ok())
+                .filter(|s| !s.is_empty())
                 .map(|s| s.to_string());
 
-            let charge: f64 = resp
-                .headers()
-                .get("x-ms-request-charge")
-                .and_then(|v| v.to_str().ok())
-                .and_then(|v| v.parse().ok())
-                .unwrap_or(0.0);
+            let charge = request_charge(resp.headers());
             total_charge += charge;
 
-            let query_resp: QueryResponse = resp.json().await?;
-            documents.extend(query_resp.documents);
+            let cancelled = next_continuation.is_some()
+                && (self.is_cancelled() || pages.is_some_and(|p| p.is_closed()));
+            let next_page = async {
+                match next_continuation {
+                    Some(ref token) if !cancelled => {
+                        debug!("prefetching next page with pagination token");
+                        Some(
+                            self.query_page(
+                                url,
+                                resource_link,
+                                body,
+                                partition_key_range_id,
+                                Some(token),
+                            )
+                            .await,
+                        )
+                    }
+                    _ => None,
+                }
+            };
+            let (page, next_page) = tokio::join!(resp.json::<QueryResponse>(), next_page);
+            let page = page?.documents;
+            count += page.len();
+            match pages {
+                Some(pages) => {
+                    if pages.send(page).await.is_err() {
+                        debug!("page receiver dropped, skipping remaining pages");
+                        return Ok((documents, count, total_charge, true));
+                    }
+                }
+                None => documents.extend(page),
+            }
 
-            match next_continuation {
-                Some(token) if !token.is_empty() => {
-                    debug!("continuing with pagination token");
-                    continuation = Some(token);
+            match next_page {
+                Some(next) => resp = next?,
+                None if cancelled => {
+                    debug!("query cancelled, skipping remaining pages");
+                    return Ok((documents, count, total_charge, true));
                 }
-                _ => break,
+                None => break,
             }
         }
 
-        Ok((documents, total_charge))
+        Ok((documents, count, total_charge, false))
     }
 
     /// Execute a SQL query against a container, handling cross-partition fanout and pagination.
@@ -283,6 +1010,30 @@ impl CosmosClient {
             .await
     }
 
+    /// Execute a SQL query and deserialize each document into `T`.
+    pub async fn query_as<T: DeserializeOwned>(
+        &self,
+        database: &str,
+        container: &str,
+        sql: &str,
+    ) -> Result<QueryResult<T>, ClientError> {
+        self.query_as_with_params(database, container, sql, Vec::new())
+            .await
+    }
+
+    /// Execute a parameterized SQL query and deserialize each document into `T`.
+    pub async fn query_as_with_params<T: DeserializeOwned>(
+        &self,
+        database: &str,
+        container: &str,
+        sql: &str,
+        parameters: Vec<Value>,
+    ) -> Result<QueryResult<T>, ClientError> {
+        self.query_with_params(database, container, sql, parameters)
+            .await?
+            .into_typed()
+    }
+
     /// Execute a parameterized SQL query against a container.
     ///
     /// Parameters should be in Cosmos DB format:
@@ -293,6 +1044,37 @@ impl CosmosClient {
         container: &str,
         sql: &str,
         parameters: Vec<Value>,
+    ) -> Result<QueryResult, ClientError> {
+        self.fan_out(database, container, sql, parameters, None)
+            .await
+    }
+
+    /// Execute a parameterized SQL query, sending each page of documents to
+    /// `pages` as it arrives instead of collecting them.
+    ///
+    /// The returned result has no documents; its partition stats count the
+    /// documents sent. Dropping the receiver stops the query early, and the
+    /// result is then marked partial.
+    pub async fn query_stream(
+        &self,
+        database: &str,
+        container: &str,
+        sql: &str,
+        parameters: Vec<Value>,
+        pages: mpsc::Sender<Vec<Value>>,
+    ) -> Result<QueryResult, ClientError> {
+        self.fan_out(database, container, sql, parameters, Some(&pages))
+            .await
+    }
+
+    /// Run a query on every partition key range in turn.
+    async fn fan_out(
+        &self,
+        database: &str,
+        container: &str,
+        sql: &str,
+        parameters: Vec<Value>,
+        pages: Option<&mpsc::Sender<Vec<Value>>>,
     ) -> Result<QueryResult, ClientError> {
         debug!(database, container, sql, params = ?parameters, "executing query");
 
@@ -300,59 +1082,674 @@ impl CosmosClient {
             "{}/dbs/{}/colls/{}/docs",
             self.endpoint, database, container
         );
+        let resource_link = format!("dbs/{database}/colls/{container}");
         let body = serde_json::json!({
             "query": sql,
             "parameters": parameters
         });
 
-        // Get partition key ranges and fan out the query
+        // Get partition key ranges (and the query plan, when plans are
+        // enabled) and fan out the query
         let ranges = self.get_partition_key_ranges(database, container).await?;
-        debug!(count = ranges.len(), "querying across partition key ranges");
+        let plan = match self.plan_cache {
+            Some(ref dir) => {
+                let key = plan_cache::key(&self.endpoint, database, container, sql, &parameters);
+                self.query_plan(dir, &url, &resource_link, &body, &key, &ranges.rid)
+                    .await
+            }
+            None => None,
+        };
+        let ranges = ranges.partition_key_ranges;
+        let total_ranges = ranges.len();
+        let ranges: Vec<String> = ranges
+            .into_iter()
+            .filter(|r| match (&plan, &r.min_inclusive, &r.max_exclusive) {
+                (Some(plan), Some(min), Some(max)) => plan.covers(min, max),
+                _ => true,
+            })
+            .map(|r| r.id)
+            .collect();
+        debug!(
+            count = ranges.len(),
+            total = total_ranges,
+            "querying across partition key ranges"
+        );
 
         let mut all_documents = Vec::new();
+        let mut count = 0;
         let mut total_charge = 0.0_f64;
+        let mut partitions = Vec::with_capacity(ranges.len());
+        let mut partial = false;
 
         for range_id in &ranges {
-            let (docs, charge) = self.query_partition(&url, &body, range_id).await?;
-            debug!(
-                range_id,
-                docs = docs.len(),
-                charge,
-                "partition query complete"
-            );
+            if self.is_cancelled() || pages.is_some_and(|p| p.is_closed()) {
+                debug!("query cancelled, skipping remaining partition key ranges");
+                partial = true;
+                break;
+            }
+            let (docs, documents, charge, cancelled) = self
+                .query_partition(&url, &resource_link, &body, range_id, pages)
+                .await?;
+            partial |= cancelled;
+            debug!(range_id, documents, charge, "partition query complete");
+            partitions.push(PartitionStats {
+                range_id: range_id.clone(),
+                documents,
+                request_charge: charge,
+            });
             all_documents.extend(docs);
+            count += documents;
             total_charge += charge;
         }
 
-        debug!(
-            count = all_documents.len(),
-            request_charge = total_charge,
-            "query complete"
-        );
+        debug!(count, request_charge = total_charge, "query complete");
 
         Ok(QueryResult {
             documents: all_documents,
             request_charge: total_charge,
+            partitions,
+            partial,
+        })
+    }
+
+    /// Read a single document by id and partition key value.
+    ///
+    /// Returns `None` if the document does not exist.
+    pub async fn read_document(
+        &self,
+        database: &str,
+        container: &str,
+        id: &str,
+        partition_key: &Value,
+    ) -> Result<Option<Value>, ClientError> {
+        debug!(database, container, id, "reading document");
+        let link = format!("dbs/{database}/colls/{container}/docs/{id}");
+        let url = format!("{}/{}", self.endpoint, link);
+
+        let resp = self
+            .send(Method::GET, &url, "docs", &link, |r| {
+                r.header(
+                    "x-ms-documentdb-partitionkey",
+                    partition_key_header(partition_key),
+                )
+            })
+            .await?;
+
+        let status = resp.status();
+        if status.as_u16() == 404 {
+            return Ok(None);
+        }
+        if !status.is_success() {
+            let body = resp.text().await.unwrap_or_default();
+            return Err(ClientError::api(status.as_u16(), body));
+        }
+        Ok(Some(resp.json().await?))
+    }
+
+    /// Create or replace a document. Returns the stored document (with system properties).
+    pub async fn upsert_document(
+        &self,
+        database: &str,
+        container: &str,
+        document: &Value,
+        partition_key: &Value,
+    ) -> Result<Value, ClientError> {
+        debug!(database, container, "upserting document");
+        let link = format!("dbs/{database}/colls/{container}");
+        let url = format!("{}/{}/docs", self.endpoint, link);
+
+        let resp = self
+            .send_write(Method::POST, &url, "docs", &link, |r| {
+                r.header("x-ms-documentdb-is-upsert", "True")
+                    .header(
+                        "x-ms-documentdb-partitionkey",
+                        partition_key_header(partition_key),
+                    )
+                    .json(document)
+            })
+            .await?;
+
+        let status = resp.status();
+        if !status.is_success() {
+            let body = resp.text().await.unwrap_or_default();
+            return Err(ClientError::api(status.as_u16(), body));
+        }
+        Ok(resp.json().await?)
+    }
+
+    /// Create a new document.
+    ///
+    /// Fails with [`ClientError::Conflict`] if a document with the same id
+    /// already exists in the partition.
+    pub async fn create_document(
+        &self,
+        database: &str,
+        container: &str,
+        document: &Value,
+        partition_key: &Value,
+    ) -> Result<WrittenDocument, ClientError> {
+        debug!(database, container, "creating document");
+        let link = format!("dbs/{database}/colls/{container}");
+        let url = format!("{}/{}/docs", self.endpoint, link);
+
+        let resp = self
+            .send_write(Method::POST, &url, "docs", &link, |r| {
+                r.header(
+                    "x-ms-documentdb-partitionkey",
+                    partition_key_header(partition_key),
+                )
+                .json(document)
+            })
+            .await?;
+
+        let status = resp.status();
+        if !status.is_success() {
+            let body = resp.text().await.unwrap_or_default();
+            return Err(match status.as_u16() {
+                409 => ClientError::conflict(format!(
+                    "a document with id {} already exists in partition {partition_key}",
+                    document.get("id").unwrap_or(&Value::Null)
+                )),
+                code => ClientError::api(code, body),
+            });
+        }
+        let request_charge = request_charge(resp.headers());
+        Ok(WrittenDocument {
+            document: resp.json().await?,
+            request_charge,
+        })
+    }
+
+    /// Replace an existing document.
+    ///
+    /// With `if_match` (an `_etag` from a previous read) the write only
+    /// succeeds if nobody changed the document in between; otherwise it fails
+    /// with [`ClientError::PreconditionFailed`].
+    pub async fn replace_document(
+        &self,
+        database: &str,
+        container: &str,
+        id: &str,
+        document: &Value,
+        partition_key: &Value,
+        if_match: Option<&str>,
+    ) -> Result<Value, ClientError> {
+        debug!(database, container, id, "replacing document");
+        let link = format!("dbs/{database}/colls/{container}/docs/{id}");
+        let url = format!("{}/{}", self.endpoint, link);
+
+        let resp = self
+            .send_write(Method::PUT, &url, "docs", &link, |r| {
+                let r = r
+                    .header(
+                        "x-ms-documentdb-partitionkey",
+                        partition_key_header(partition_key),
+                    )
+                    .json(document);
+                match if_match {
+                    Some(etag) => r.header("If-Match", etag),
+                    None => r,
+                }
+            })
+            .await?;
+
+        let status = resp.status();
+        if !status.is_success() {
+            let body = resp.text().await.unwrap_or_default();
+            return Err(match status.as_u16() {
+                404 => ClientError::not_found(format!("document '{id}'")),
+                412 => ClientError::precondition_failed(format!(
+                    "document '{id}' was modified since it was read"
+                )),
+                code => ClientError::api(code, body),
+            });
+        }
+        Ok(resp.json().await?)
+    }
+
+    /// Apply `operations` to an existing document in one request, without
+    /// reading and rewriting it. Returns the updated document.
+    ///
+    /// The operations apply in order and atomically: if one fails (e.g.
+    /// `replace` or `remove` of a missing property) none of them do.
+    pub async fn patch_document(
+        &self,
+        database: &str,
+        container: &str,
+        id: &str,
+        partition_key: &Value,
+        operations: &[PatchOperation],
+    ) -> Result<WrittenDocument, ClientError> {
+        debug!(
+            database,
+            container,
+            id,
+            operations = operations.len(),
+            "patching document"
+        );
+        let link = format!("dbs/{database}/colls/{container}/docs/{id}");
+        let url = format!("{}/{}", self.endpoint, link);
+        let body = serde_json::json!({ "operations": operations });
+
+        let resp = self
+            .send_versioned(
+                Method::PATCH,
+                &url,
+                "docs",
+                &link,
+                PATCH_API_VERSION,
+                Effect::Write,
+                |r| {
+                    r.header(
+                        "x-ms-documentdb-partitionkey",
+                        partition_key_header(partition_key),
+                    )
+                    .header("Content-Type", "application/json_patch+json")
+                    .json(&body)
+                },
+            )
+            .await?;
+
+        let status = resp.status();
+        if !status.is_success() {
+            let body = resp.text().await.unwrap_or_default();
+            return Err(match status.as_u16() {
+                404 => ClientError::not_found(format!("document '{id}'")),
+                code => ClientError::api(code, body),
+            });
+        }
+        let request_charge = request_charge(resp.headers());
+        Ok(WrittenDocument {
+            document: resp.json().await?,
+            request_charge,
         })
     }
+
+    /// Create (or refresh) a permission for `user` on a container and return
+    /// its resource token, valid for `ttl_seconds`.
+    ///
+    /// The database user is created if needed. Users and permissions can only
+    /// be managed with master key auth.
+    #[allow(clippy::too_many_arguments)]
+    pub async fn create_resource_token(
+        &self,
+        database: &str,
+        container: &str,
+        user: &str,
+        permission_id: &str,
+        mode: PermissionMode,
+        partition_key: Option<&Value>,
+        ttl_seconds: u64,
+    ) -> Result<Permission, ClientError> {
+        if matches!(self.credential, Credential::Aad(_)) {
+            return Err(ClientError::auth(
+                "resource tokens can only be created with master key auth (set `master_key` on the account)",
+            ));
+        }
+        debug!(
+            database,
+            container, user, permission_id, "creating resource token"
+        );
+
+        let db_link = format!("dbs/{database}");
+        let url = format!("{}/{db_link}/users", self.endpoint);
+        let body = serde_json::json!({ "id": user });
+        let resp = self
+            .send_write(Method::POST, &url, "users", &db_link, |r| {
+                r.header("x-ms-documentdb-is-upsert", "True").json(&body)
+            })
+            .await?;
+        let status = resp.status();
+        if !status.is_success() {
+            let text = resp.text().await.unwrap_or_default();
+            return Err(ClientError::api(status.as_u16(), text));
+        }
+
+        let user_link = format!("dbs/{database}/users/{user}");
+        let url = format!("{}/{user_link}/permissions", self.endpoint);
+        let mut body = serde_json::json!({
+            "id": permission_id,
+            "permissionMode": mode.as_str(),
+            "resource": format!("dbs/{database}/colls/{container}"),
+        });
+        if let Some(pk) = partition_key {
+            body["resourcePartitionKey"] = serde_json::json!([pk]);
+        }
+        let resp = self
+            .send_write(Method::POST, &url, "permissions", &user_link, |r| {
+                r.header("x-ms-documentdb-is-upsert", "True")
+                    .header("x-ms-documentdb-expiry-seconds", ttl_seconds.to_string())
+                    .json(&body)
+            })
+            .await?;
+        let status = resp.status();
+        if !status.is_success() {
+            let text = resp.text().await.unwrap_or_default();
+            return Err(ClientError::api(status.as_u16(), text));
+        }
+        Ok(resp.json().await?)
+    }
+
+    /// Delete a document by id and partition key value.
+    ///
+    /// Returns the request charge, or `None` if the document did not exist.
+    pub async fn delete_document(
+        &self,
+        database: &str,
+        container: &str,
+        id: &str,
+        partition_key: &Value,
+    ) -> Result<Option<f64>, ClientError> {
+        debug!(database, container, id, "deleting document");
+        let link = format!("dbs/{database}/colls/{container}/docs/{id}");
+        let url = format!("{}/{}", self.endpoint, link);
+
+        let resp = self
+            .send_write(Method::DELETE, &url, "docs", &link, |r| {
+                r.header(
+                    "x-ms-documentdb-partitionkey",
+                    partition_key_header(partition_key),
+                )
+            })
+            .await?;
+
+        let status = resp.status();
+        if status.as_u16() == 404 {
+            return Ok(None);
+        }
+        if !status.is_success() {
+            let body = resp.text().await.unwrap_or_default();
+            return Err(ClientError::api(status.as_u16(), body));
+        }
+        Ok(Some(request_charge(resp.headers())))
+    }
+}
+
+/// The `x-ms-request-charge` of a response (0 when missing).
+fn request_charge(headers: &HeaderMap) -> f64 {
+    headers
+        .get("x-ms-request-charge")
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(0.0)
+}
+
+/// Format a partition key value for the `x-ms-documentdb-partitionkey` header.
+fn partition_key_header(value: &Value) -> String {
+    Value::Array(vec![value.clone()]).to_string()
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    /// Serve `bodies` (gzip-compressed) to one request each on a local port,
+    /// returning the endpoint and the request heads received.
+    async fn gzip_server(bodies: Vec<Value>) -> (String, tokio::task::JoinHandle<Vec<String>>) {
+        use std::io::Write as _;
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let endpoint = format!("http://{}", listener.local_addr().unwrap());
+        let handle = tokio::spawn(async move {
+            let mut heads = Vec::new();
+            for body in bodies {
+                let (mut socket, _) = listener.accept().await.unwrap();
+                let mut request = Vec::new();
+                let mut buf = [0; 4096];
+                while !request.windows(4).any(|w| w == b"\r\n\r\n") {
+                    let n = socket.read(&mut buf).await.unwrap();
+                    request.extend_from_slice(&buf[..n]);
+                }
+                heads.push(String::from_utf8_lossy(&request).to_lowercase());
+
+                let mut encoder =
+                    flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+                encoder.write_all(body.to_string().as_bytes()).unwrap();
+                let compressed = encoder.finish().unwrap();
+                let head = format!(
+                    "HTTP/1.1 200 OK\r\ncontent-type: application/json\r\ncontent-encoding: gzip\r\ncontent-length: {}\r\nconnection: close\r\n\r\n",
+                    compressed.len()
+                );
+                socket.write_all(head.as_bytes()).await.unwrap();
+                socket.write_all(&compressed).await.unwrap();
+            }
+            heads
+        });
+        (endpoint, handle)
+    }
+
+    #[tokio::test]
+    async fn test_query_requests_gzip() {
+        let (endpoint, server) = gzip_server(vec![
+            serde_json::json!({"PartitionKeyRanges": [{"id": "0"}]}),
+            serde_json::json!({"Documents": [{"id": "1", "name": "Ada"}], "_count": 1}),
+        ])
+        .await;
+        let client = CosmosClient::with_master_key(&endpoint, "a2V5").unwrap();
+        let result = client
+            .query("app", "users", "SELECT * FROM c")
+            .await
+            .unwrap();
+        assert_eq!(
+            result.documents,
+            vec![serde_json::json!({"id": "1", "name": "Ada"})]
+        );
+
+        let heads = server.await.unwrap();
+        assert!(heads[1].starts_with("post /dbs/app/colls/users/docs"));
+        assert!(heads.iter().all(|h| h.contains("accept-encoding: gzip")));
+    }
+
+    #[tokio::test]
+    async fn test_query_stream_sends_pages() {
+        let (endpoint, server) = gzip_server(vec![
+            serde_json::json!({"PartitionKeyRanges": [{"id": "0"}, {"id": "1"}]}),
+            serde_json::json!({"Documents": [{"id": "1"}, {"id": "2"}], "_count": 2}),
+            serde_json::json!({"Documents": [{"id": "3"}], "_count": 1}),
+        ])
+        .await;
+        let client = CosmosClient::with_master_key(&endpoint, "a2V5").unwrap();
+        let (tx, mut rx) = mpsc::channel(4);
+        let result = client
+            .query_stream("app", "users", "SELECT * FROM c", Vec::new(), tx)
+            .await
+            .unwrap();
+        server.await.unwrap();
+
+        let mut pages = Vec::new();
+        while let Some(page) = rx.recv().await {
+            pages.push(page.len());
+        }
+        assert_eq!(pages, vec![2, 1]);
+        assert!(result.documents.is_empty());
+        assert!(!result.partial);
+        let counts: Vec<usize> = result.partitions.iter().map(|p| p.documents).collect();
+        assert_eq!(counts, vec![2, 1]);
+    }
+
+    #[tokio::test]
+    async fn test_query_plan_narrows_ranges() {
+        let (endpoint, server) = gzip_server(vec![
+            serde_json::json!({"_rid": "c1", "PartitionKeyRanges": [
+                {"id": "0", "minInclusive": "", "maxExclusive": "7F"},
+                {"id": "1", "minInclusive": "7F", "maxExclusive": "FF"}
+            ]}),
+            serde_json::json!({"Documents": [{"id": "1"}], "_count": 1}),
+        ])
+        .await;
+        let plans = tempfile::tempdir().unwrap();
+        let sql = "SELECT * FROM c WHERE c.pk = 'x'";
+        let plan: QueryPlan = serde_json::from_value(serde_json::json!({
+            "queryRanges": [{"min": "A0", "max": "A0", "isMinInclusive": true, "isMaxInclusive": true}]
+        }))
+        .unwrap();
+        let key = plan_cache::key(&endpoint, "app", "users", sql, &[]);
+        plan_cache::save_to(plans.path(), &key, &CachedPlan::new("c1", plan)).unwrap();
+
+        let client = CosmosClient::with_master_key(&endpoint, "a2V5")
+            .unwrap()
+            .with_plan_cache(plans.path().to_path_buf());
+        let result = client.query("app", "users", sql).await.unwrap();
+        let heads = server.await.unwrap();
+
+        // The cached plan means no plan request, and only range 1 is queried
+        assert_eq!(heads.len(), 2);
+        assert!(heads[1].contains("x-ms-documentdb-partitionkeyrangeid: 1"));
+        assert_eq!(result.documents.len(), 1);
+        let ranges: Vec<&str> = result
+            .partitions
+            .iter()
+            .map(|p| p.range_id.as_str())
+            .collect();
+        assert_eq!(ranges, vec!["1"]);
+    }
+
+    #[tokio::test]
+    async fn test_query_plan_of_recreated_container_is_refetched() {
+        let plan = serde_json::json!({
+            "queryRanges": [{"min": "A0", "max": "A0", "isMinInclusive": true, "isMaxInclusive": true}]
+        });
+        let (endpoint, server) = gzip_server(vec![
+            serde_json::json!({"_rid": "c2", "PartitionKeyRanges": [
+                {"id": "0", "minInclusive": "", "maxExclusive": "7F"},
+                {"id": "1", "minInclusive": "7F", "maxExclusive": "FF"}
+            ]}),
+            plan.clone(),
+            serde_json::json!({"Documents": [{"id": "1"}], "_count": 1}),
+        ])
+        .await;
+        let plans = tempfile::tempdir().unwrap();
+        let sql = "SELECT * FROM c WHERE c.pk = 'x'";
+        // Cached for the container before it was recreated: range 0 only
+        let stale: QueryPlan = serde_json::from_value(serde_json::json!({
+            "queryRanges": [{"min": "10", "max": "10", "isMinInclusive": true, "isMaxInclusive": true}]
+        }))
+        .unwrap();
+        let key = plan_cache::key(&endpoint, "app", "users", sql, &[]);
+        plan_cache::save_to(plans.path(), &key, &CachedPlan::new("c1", stale)).unwrap();
+
+        let client = CosmosClient::with_master_key(&endpoint, "a2V5")
+            .unwrap()
+            .with_plan_cache(plans.path().to_path_buf());
+        let result = client.query("app", "users", sql).await.unwrap();
+        let heads = server.await.unwrap();
+
+        assert_eq!(heads.len(), 3);
+        assert!(heads[1].contains("x-ms-cosmos-is-query-plan-request: true"));
+        assert!(heads[2].contains("x-ms-documentdb-partitionkeyrangeid: 1"));
+        assert_eq!(result.documents.len(), 1);
+        let cached = plan_cache::load_from(plans.path(), &key).unwrap().unwrap();
+        assert_eq!(cached.container_rid, "c2");
+        assert_eq!(
+            cached.plan,
+            serde_json::from_value::<QueryPlan>(plan).unwrap()
+        );
+    }
+
     #[test]
     fn test_auth_header_format() {
         let client = CosmosClient {
             http: reqwest::Client::new(),
             endpoint: "https://test.documents.azure.com".into(),
-            token: "eyJ0eXAi.test.token".into(),
+            credential: Credential::Aad("eyJ0eXAi.test.token".into()),
+            retry: RetryPolicy::default(),
+            priority: None,
+            cancelled: None,
+            on_response: None,
+            plan_cache: None,
         };
-        let header = client.auth_header();
+        let header = client.auth_header("GET", "dbs", "", "date");
         assert!(header.starts_with("type%3Daad%26ver%3D1.0%26sig%3D"));
         assert!(header.contains("eyJ0eXAi"));
     }
 
+    #[test]
+    fn test_master_key_auth_header() {
+        // Example from the Cosmos DB REST API "access control" documentation
+        let client = CosmosClient::with_master_key(
+            "https://test.documents.azure.com",
+            "dsZQi3KtZmCv1ljt3VNWNm7sQUF1y5rJfC6kv5JiwvW0EndXdDku/dkKBp8/ufDToSxLzR4y+O/0H/t4bQtVNw==",
+        )
+        .unwrap();
+        let header = client.auth_header(
+            "GET",
+            "dbs",
+            "dbs/ToDoList",
+            "Thu, 27 Apr 2017 00:51:12 GMT",
+        );
+        assert_eq!(
+            header.to_lowercase(),
+            "type%3dmaster%26ver%3d1.0%26sig%3dc09pevjrgp2uqrkr934kfbtqhbyc7tvr3ohyqlu%2bc%2bc%3d"
+        );
+    }
+
+    #[test]
+    fn test_response_diagnostics_from_headers() {
+        let mut headers = HeaderMap::new();
+        headers.insert("x-ms-activity-id", "3c6e1b8a-0000".parse().unwrap());
+        headers.insert("x-ms-session-token", "0:-1#42".parse().unwrap());
+        headers.insert("x-ms-request-duration-ms", "3.21".parse().unwrap());
+        headers.insert("x-ms-request-charge", "2.9".parse().unwrap());
+        let d = ResponseDiagnostics::from_headers("POST", "/dbs/db/colls/c/docs", 200, &headers);
+        assert_eq!(d.activity_id.as_deref(), Some("3c6e1b8a-0000"));
+        assert_eq!(d.session_token.as_deref(), Some("0:-1#42"));
+        assert_eq!(d.server_duration_ms, Some(3.21));
+        assert_eq!(d.request_charge, Some(2.9));
+        assert_eq!(d.retry_after_ms, None);
+    }
+
+    #[test]
+    fn test_into_typed() {
+        #[derive(Debug, Deserialize)]
+        struct User {
+            id: String,
+            age: u32,
+        }
+
+        let result = QueryResult {
+            documents: vec![
+                serde_json::json!({"id": "a", "age": 30}),
+                serde_json::json!({"id": "b", "age": 41, "extra": true}),
+            ],
+            request_charge: 3.5,
+            partitions: Vec::new(),
+            partial: false,
+        };
+        let typed: QueryResult<User> = result.into_typed().unwrap();
+        assert_eq!(typed.documents.len(), 2);
+        assert_eq!(typed.documents[1].id, "b");
+        assert_eq!(typed.documents[0].age, 30);
+        assert_eq!(typed.request_charge, 3.5);
+
+        let bad = QueryResult {
+            documents: vec![
+                serde_json::json!({"id": "a", "age": 30}),
+                serde_json::json!({"id": "b", "age": "old"}),
+            ],
+            request_charge: 0.0,
+            partitions: Vec::new(),
+            partial: false,
+        };
+        let err = bad.into_typed::<User>().unwrap_err();
+        assert!(matches!(err, ClientError::Deserialize { index: 1, .. }));
+        assert!(err.to_string().contains("document 1"));
+    }
+
+    #[test]
+    fn test_partition_key_header() {
+        assert_eq!(
+            partition_key_header(&serde_json::json!("tenant-1")),
+            r#"["tenant-1"]"#
+        );
+        assert_eq!(partition_key_header(&serde_json::json!(42)), "[42]");
+    }
+
+    #[test]
+    fn test_master_key_invalid() {
+        assert!(CosmosClient::with_master_key("https://x", "not base64!").is_err());
+    }
+
     #[test]
     fn test_date_header_format() {
         let date = CosmosClient::date_header();
@@ -395,6 +1792,73 @@ mod tests {
         assert_eq!(resp.document_collections[1].id, "coll2");
     }
 
+    #[test]
+    fn test_container_properties_deserialization() {
+        let json = r#"{
+            "id": "orders",
+            "partitionKey": {"paths": ["/customerId"], "kind": "Hash"},
+            "indexingPolicy": {
+                "indexingMode": "consistent",
+                "includedPaths": [{"path": "/*"}],
+                "excludedPaths": [{"path": "/\"_etag\"/?"}],
+                "compositeIndexes": [[{"path": "/date", "order": "descending"}, {"path": "/total"}]]
+            },
+            "analyticalStorageTtl": -1
+        }"#;
+        let props: ContainerProperties = serde_json::from_str(json).unwrap();
+        assert_eq!(props.partition_key.paths, vec!["/customerId"]);
+        assert_eq!(props.indexing_policy.excluded_paths.len(), 1);
+        let composite = &props.indexing_policy.composite_indexes[0];
+        assert_eq!(composite[0].order, "descending");
+        assert_eq!(composite[1].order, "ascending");
+        assert!(props.has_analytical_store());
+        assert_eq!(props.default_ttl, None);
+    }
+
+    #[test]
+    fn test_container_usage_from_header() {
+        let usage = ContainerUsage::from_header(
+            "functions=0;storedProcedures=0;documentSize=1;documentsSize=1536;documentsCount=42;collectionSize=1700",
+        );
+        assert_eq!(
+            usage,
+            ContainerUsage {
+                documents_count: 42,
+                documents_size_kb: 1536
+            }
+        );
+        assert_eq!(
+            ContainerUsage::from_header("bogus"),
+            ContainerUsage::default()
+        );
+    }
+
+    #[test]
+    fn test_patch_operation_serialization() {
+        let ops = vec![
+            PatchOperation::Set {
+                path: "/status".to_string(),
+                value: serde_json::json!("shipped"),
+            },
+            PatchOperation::Increment {
+                path: "/retries".to_string(),
+                value: serde_json::json!(1),
+            },
+            PatchOperation::Remove {
+                path: "/tmp".to_string(),
+            },
+        ];
+        assert_eq!(
+            serde_json::to_value(&ops).unwrap(),
+            serde_json::json!([
+                {"op": "set", "path": "/status", "value": "shipped"},
+                {"op": "incr", "path": "/retries", "value": 1},
+                {"op": "remove", "path": "/tmp"},
+            ])
+        );
+        assert_eq!(ops[2].path(), "/tmp");
+    }
+
     #[test]
     fn test_partition_key_ranges_deserialization() {
         let json =
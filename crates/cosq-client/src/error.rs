@@ -19,6 +19,12 @@ pub enum ClientError {
     #[error("not found: {message}")]
     NotFound { message: String },
 
+    #[error("precondition failed: {message}")]
+    PreconditionFailed { message: String },
+
+    #[error("conflict: {message}")]
+    Conflict { message: String },
+
     #[error("Azure CLI error: {message}\n\nHint: {hint}")]
     AzCli { message: String, hint: String },
 
@@ -28,6 +34,12 @@ pub enum ClientError {
     #[error("local AI agent error: {message}")]
     LocalAgent { message: String },
 
+    #[error("failed to deserialize document {index}: {source}")]
+    Deserialize {
+        index: usize,
+        source: serde_json::Error,
+    },
+
     #[error("{0}")]
     Other(String),
 }
@@ -59,6 +71,18 @@ impl ClientError {
         }
     }
 
+    pub fn precondition_failed(msg: impl Into<String>) -> Self {
+        Self::PreconditionFailed {
+            message: msg.into(),
+        }
+    }
+
+    pub fn conflict(msg: impl Into<String>) -> Self {
+        Self::Conflict {
+            message: msg.into(),
+        }
+    }
+
     pub fn openai(msg: impl Into<String>) -> Self {
         Self::OpenAI {
             message: msg.into(),
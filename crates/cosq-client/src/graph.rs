@@ -0,0 +1,216 @@
+//! Microsoft Graph client
+//!
+//! Resolves Entra ID object IDs, as found in role assignments, to the users,
+//! groups and service principals behind them, so `cosq init`, `cosq auth
+//! status` and `cosq auth audit` can show names instead of bare GUIDs.
+
+use serde::{Deserialize, Serialize};
+use tracing::debug;
+
+use crate::auth::{self, GRAPH_RESOURCE};
+use crate::error::ClientError;
+
+const GRAPH_BASE_URL: &str = "https://graph.microsoft.com/v1.0";
+
+/// Most IDs `directoryObjects/getByIds` accepts per request
+const MAX_IDS_PER_REQUEST: usize = 1000;
+
+/// What kind of directory object a principal is
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum PrincipalKind {
+    User,
+    Group,
+    ServicePrincipal,
+    Other,
+}
+
+impl PrincipalKind {
+    pub fn label(self) -> &'static str {
+        match self {
+            PrincipalKind::User => "user",
+            PrincipalKind::Group => "group",
+            PrincipalKind::ServicePrincipal => "service principal",
+            PrincipalKind::Other => "other",
+        }
+    }
+}
+
+/// A user, group or service principal from the directory
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Principal {
+    pub id: String,
+    pub kind: PrincipalKind,
+    pub display_name: String,
+    /// User principal name of a user, application (client) ID of a service principal
+    pub sign_in_name: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct DirectoryObjectList {
+    value: Vec<DirectoryObject>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct DirectoryObject {
+    #[serde(rename = "@odata.type", default)]
+    odata_type: String,
+    id: String,
+    display_name: Option<String>,
+    user_principal_name: Option<String>,
+    app_id: Option<String>,
+}
+
+impl From<DirectoryObject> for Principal {
+    fn from(object: DirectoryObject) -> Self {
+        let kind = match object.odata_type.as_str() {
+            "#microsoft.graph.user" => PrincipalKind::User,
+            "#microsoft.graph.group" => PrincipalKind::Group,
+            "#microsoft.graph.servicePrincipal" => PrincipalKind::ServicePrincipal,
+            _ => PrincipalKind::Other,
+        };
+        Principal {
+            display_name: object.display_name.unwrap_or_else(|| object.id.clone()),
+            sign_in_name: object.user_principal_name.or(object.app_id),
+            id: object.id,
+            kind,
+        }
+    }
+}
+
+impl Principal {
+    /// `Display Name (sign-in name)`, or just the display name.
+    pub fn label(&self) -> String {
+        match self.sign_in_name {
+            Some(ref name) if *name != self.display_name => {
+                format!("{} ({name})", self.display_name)
+            }
+            _ => self.display_name.clone(),
+        }
+    }
+}
+
+/// Microsoft Graph client for directory lookups.
+pub struct GraphClient {
+    http: reqwest::Client,
+    token: String,
+}
+
+impl GraphClient {
+    /// Create a client, acquiring a token via the Azure CLI.
+    pub async fn new() -> Result<Self, ClientError> {
+        let token = auth::get_token(GRAPH_RESOURCE).await?;
+        Ok(Self {
+            http: crate::http::client(),
+            token,
+        })
+    }
+
+    /// Look up users, groups and service principals by object ID. IDs that
+    /// aren't in the directory (e.g. deleted principals) are left out.
+    pub async fn get_by_ids(&self, ids: &[String]) -> Result<Vec<Principal>, ClientError> {
+        debug!(count = ids.len(), "resolving directory object IDs");
+
+        let url = format!("{GRAPH_BASE_URL}/directoryObjects/getByIds");
+        let mut principals = Vec::new();
+        for chunk in ids.chunks(MAX_IDS_PER_REQUEST) {
+            let body = serde_json::json!({
+                "ids": chunk,
+                "types": ["user", "group", "servicePrincipal"],
+            });
+            let resp = self
+                .http
+                .post(&url)
+                .bearer_auth(&self.token)
+                .json(&body)
+                .send()
+                .await?;
+
+            let status = resp.status();
+            if !status.is_success() {
+                let body = resp.text().await.unwrap_or_default();
+                if status.as_u16() == 403 {
+                    return Err(ClientError::forbidden(
+                        body,
+                        "Reading the directory needs permission to read users, groups and service principals in your Entra ID tenant.",
+                    ));
+                }
+                return Err(ClientError::api(status.as_u16(), body));
+            }
+
+            let list: DirectoryObjectList = resp.json().await?;
+            principals.extend(list.value.into_iter().map(Principal::from));
+        }
+        Ok(principals)
+    }
+
+    /// Look up one user, group or service principal by object ID.
+    pub async fn get_principal(&self, id: &str) -> Result<Option<Principal>, ClientError> {
+        Ok(self.get_by_ids(&[id.to_string()]).await?.into_iter().next())
+    }
+
+    /// Find the service principal of an application (client) ID, e.g. the
+    /// identity `az login --service-principal` signed in with.
+    pub async fn get_service_principal_by_app_id(
+        &self,
+        app_id: &str,
+    ) -> Result<Option<Principal>, ClientError> {
+        debug!(app_id, "looking up service principal");
+
+        let url = format!("{GRAPH_BASE_URL}/servicePrincipals(appId='{app_id}')");
+        let resp = self.http.get(&url).bearer_auth(&self.token).send().await?;
+
+        let status = resp.status();
+        if status.as_u16() == 404 {
+            return Ok(None);
+        }
+        if !status.is_success() {
+            let body = resp.text().await.unwrap_or_default();
+            return Err(ClientError::api(status.as_u16(), body));
+        }
+        let mut object: DirectoryObject = resp.json().await?;
+        object.odata_type = "#microsoft.graph.servicePrincipal".to_string();
+        Ok(Some(object.into()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_directory_objects() {
+        let list: DirectoryObjectList = serde_json::from_value(json!({
+            "value": [
+                {
+                    "@odata.type": "#microsoft.graph.user",
+                    "id": "u1",
+                    "displayName": "Ada Lovelace",
+                    "userPrincipalName": "ada@contoso.com"
+                },
+                {
+                    "@odata.type": "#microsoft.graph.servicePrincipal",
+                    "id": "s1",
+                    "displayName": "orders-api",
+                    "appId": "app-1"
+                },
+                {"@odata.type": "#microsoft.graph.group", "id": "g1", "displayName": null}
+            ]
+        }))
+        .unwrap();
+        let principals: Vec<Principal> = list.value.into_iter().map(Principal::from).collect();
+        assert_eq!(principals[0].kind, PrincipalKind::User);
+        assert_eq!(
+            principals[0].sign_in_name.as_deref(),
+            Some("ada@contoso.com")
+        );
+        assert_eq!(principals[1].kind.label(), "service principal");
+        assert_eq!(principals[1].sign_in_name.as_deref(), Some("app-1"));
+        assert_eq!(principals[2].display_name, "g1");
+
+        assert_eq!(principals[0].label(), "Ada Lovelace (ada@contoso.com)");
+        assert_eq!(principals[2].label(), "g1");
+    }
+}
@@ -0,0 +1,102 @@
+//! Shared HTTP client for outgoing requests
+//!
+//! The Cosmos DB, ARM, Blob Storage and Azure OpenAI clients (and webhook
+//! notifications) all use one `reqwest::Client`, so the pkranges call, every
+//! partition query and the clients of a federated query reuse pooled
+//! connections instead of each paying for a TLS handshake. `configure`
+//! applies the config's `http:` section (proxy, extra CA certificate,
+//! timeouts) before the first request.
+
+use std::sync::OnceLock;
+use std::time::Duration;
+
+use cosq_core::config::HttpConfig;
+use tracing::debug;
+
+use crate::error::ClientError;
+
+static SETTINGS: OnceLock<HttpConfig> = OnceLock::new();
+static CLIENT: OnceLock<reqwest::Client> = OnceLock::new();
+
+/// Apply `http:` settings to the shared client. Must run before the first
+/// request; later calls (or calls after the client was created) are ignored.
+pub fn configure(config: &HttpConfig) -> Result<(), ClientError> {
+    let client = builder_for(config)?.build()?;
+    if SETTINGS.set(config.clone()).is_err() || CLIENT.set(client).is_err() {
+        debug!("shared HTTP client already created, ignoring http settings");
+    }
+    Ok(())
+}
+
+/// The shared client (cheap to clone; clones share the connection pool).
+pub fn client() -> reqwest::Client {
+    CLIENT
+        .get_or_init(|| {
+            builder().and_then(|b| Ok(b.build()?)).unwrap_or_else(|e| {
+                debug!(error = %e, "falling back to a default HTTP client");
+                reqwest::Client::new()
+            })
+        })
+        .clone()
+}
+
+/// A builder with the configured settings, for a client that needs more
+/// (e.g. an account's own root certificate). It has its own connection pool.
+pub fn builder() -> Result<reqwest::ClientBuilder, ClientError> {
+    builder_for(SETTINGS.get().unwrap_or(&HttpConfig::default()))
+}
+
+fn builder_for(config: &HttpConfig) -> Result<reqwest::ClientBuilder, ClientError> {
+    // Responses are requested gzip-compressed and decompressed transparently
+    let mut builder = reqwest::Client::builder().gzip(true);
+    if let Some(ref url) = config.proxy {
+        let proxy = reqwest::Proxy::all(url)
+            .map_err(|e| ClientError::Other(format!("invalid proxy URL '{url}': {e}")))?;
+        builder = builder.proxy(proxy);
+    }
+    if let Some(ref path) = config.ca_cert {
+        let pem = std::fs::read(path).map_err(|e| {
+            ClientError::Other(format!("failed to read CA certificate {path}: {e}"))
+        })?;
+        builder = builder.add_root_certificate(reqwest::Certificate::from_pem(&pem)?);
+    }
+    if let Some(secs) = config.timeout_secs {
+        builder = builder.timeout(Duration::from_secs(secs));
+    }
+    if let Some(secs) = config.connect_timeout_secs {
+        builder = builder.connect_timeout(Duration::from_secs(secs));
+    }
+    Ok(builder)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_builder_for() {
+        assert!(builder_for(&HttpConfig::default()).unwrap().build().is_ok());
+
+        let config = HttpConfig {
+            proxy: Some("http://proxy.corp:8080".into()),
+            timeout_secs: Some(30),
+            connect_timeout_secs: Some(5),
+            ..Default::default()
+        };
+        assert!(builder_for(&config).unwrap().build().is_ok());
+
+        let bad_proxy = HttpConfig {
+            proxy: Some("not a url".into()),
+            ..Default::default()
+        };
+        let err = builder_for(&bad_proxy).unwrap_err().to_string();
+        assert!(err.contains("invalid proxy URL 'not a url'"));
+
+        let missing_cert = HttpConfig {
+            ca_cert: Some("/nonexistent/ca.pem".into()),
+            ..Default::default()
+        };
+        let err = builder_for(&missing_cert).unwrap_err().to_string();
+        assert!(err.contains("/nonexistent/ca.pem"));
+    }
+}
@@ -3,5 +3,13 @@
 pub mod ai;
 pub mod arm;
 pub mod auth;
+pub mod azure_openai;
+pub mod backend;
+pub mod blob;
 pub mod cosmos;
 pub mod error;
+pub mod graph;
+pub mod http;
+pub mod ollama;
+pub mod retry;
+pub mod workload_identity;
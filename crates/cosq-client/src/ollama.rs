@@ -0,0 +1,193 @@
+//! Ollama model management
+//!
+//! Lists, pulls and deletes models on a local (or remote) Ollama server via
+//! its REST API, for `cosq ai models`. Chat requests themselves go through
+//! ailloy.
+
+use serde::Deserialize;
+use tracing::debug;
+
+use crate::error::ClientError;
+
+/// Endpoint used when neither the ailloy config nor `OLLAMA_HOST` names one
+pub const DEFAULT_ENDPOINT: &str = "http://localhost:11434";
+
+/// A model installed on the Ollama server
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct OllamaModel {
+    pub name: String,
+    /// Size on disk in bytes
+    #[serde(default)]
+    pub size: u64,
+    #[serde(default)]
+    pub modified_at: String,
+}
+
+/// One progress update streamed by `/api/pull`
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+pub struct PullProgress {
+    #[serde(default)]
+    pub status: String,
+    /// Layer being downloaded
+    #[serde(default)]
+    pub digest: Option<String>,
+    #[serde(default)]
+    pub total: Option<u64>,
+    #[serde(default)]
+    pub completed: Option<u64>,
+    #[serde(default)]
+    pub error: Option<String>,
+}
+
+impl PullProgress {
+    /// Download progress of the current layer in percent, when known.
+    pub fn percent(&self) -> Option<f64> {
+        match (self.completed, self.total) {
+            (Some(completed), Some(total)) if total > 0 => {
+                Some(completed as f64 * 100.0 / total as f64)
+            }
+            _ => None,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct TagsResponse {
+    #[serde(default)]
+    models: Vec<OllamaModel>,
+}
+
+/// Client for an Ollama server's model management API
+pub struct OllamaClient {
+    http: reqwest::Client,
+    endpoint: String,
+}
+
+impl OllamaClient {
+    pub fn new(endpoint: &str) -> Self {
+        Self {
+            // Usually a local server: not the shared client, so `http.proxy` doesn't apply
+            http: reqwest::Client::new(),
+            endpoint: endpoint.trim_end_matches('/').to_string(),
+        }
+    }
+
+    pub fn endpoint(&self) -> &str {
+        &self.endpoint
+    }
+
+    /// Models installed on the server (`GET /api/tags`).
+    pub async fn list_models(&self) -> Result<Vec<OllamaModel>, ClientError> {
+        let resp = self
+            .http
+            .get(format!("{}/api/tags", self.endpoint))
+            .send()
+            .await?;
+        if !resp.status().is_success() {
+            return Err(ClientError::api(resp.status().as_u16(), resp.text().await?));
+        }
+        Ok(resp.json::<TagsResponse>().await?.models)
+    }
+
+    /// Download a model (`POST /api/pull`), reporting each progress update.
+    pub async fn pull(
+        &self,
+        model: &str,
+        mut on_progress: impl FnMut(&PullProgress),
+    ) -> Result<(), ClientError> {
+        debug!(model, "pulling Ollama model");
+        let mut resp = self
+            .http
+            .post(format!("{}/api/pull", self.endpoint))
+            .json(&serde_json::json!({ "model": model, "stream": true }))
+            .send()
+            .await?;
+        if !resp.status().is_success() {
+            return Err(ClientError::api(resp.status().as_u16(), resp.text().await?));
+        }
+
+        // The body is NDJSON, one update per line, possibly split across chunks
+        let mut buffer = Vec::new();
+        while let Some(chunk) = resp.chunk().await? {
+            buffer.extend_from_slice(&chunk);
+            while let Some(newline) = buffer.iter().position(|&b| b == b'\n') {
+                let line: Vec<u8> = buffer.drain(..=newline).collect();
+                handle_pull_line(&line, &mut on_progress)?;
+            }
+        }
+        handle_pull_line(&buffer, &mut on_progress)
+    }
+
+    /// Remove a model from the server (`DELETE /api/delete`).
+    pub async fn delete(&self, model: &str) -> Result<(), ClientError> {
+        let resp = self
+            .http
+            .delete(format!("{}/api/delete", self.endpoint))
+            .json(&serde_json::json!({ "model": model }))
+            .send()
+            .await?;
+        match resp.status().as_u16() {
+            200..=299 => Ok(()),
+            404 => Err(ClientError::not_found(format!("model '{model}'"))),
+            status => Err(ClientError::api(status, resp.text().await?)),
+        }
+    }
+}
+
+/// Parse one NDJSON line of a pull stream, failing on an `error` update.
+fn handle_pull_line(
+    line: &[u8],
+    on_progress: &mut impl FnMut(&PullProgress),
+) -> Result<(), ClientError> {
+    let line = String::from_utf8_lossy(line);
+    let line = line.trim();
+    if line.is_empty() {
+        return Ok(());
+    }
+    let progress: PullProgress = serde_json::from_str(line)
+        .map_err(|e| ClientError::Other(format!("unexpected pull response '{line}': {e}")))?;
+    if let Some(error) = progress.error {
+        return Err(ClientError::Other(format!("pull failed: {error}")));
+    }
+    on_progress(&progress);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pull_progress() {
+        let mut updates = Vec::new();
+        let mut collect = |p: &PullProgress| updates.push(p.clone());
+        handle_pull_line(
+            br#"{"status":"pulling 1a2b","digest":"sha256:1a2b","total":200,"completed":50}"#,
+            &mut collect,
+        )
+        .unwrap();
+        handle_pull_line(b"  \n", &mut collect).unwrap();
+        handle_pull_line(br#"{"status":"success"}"#, &mut collect).unwrap();
+        assert_eq!(updates.len(), 2);
+        assert_eq!(updates[0].percent(), Some(25.0));
+        assert_eq!(updates[1].status, "success");
+        assert_eq!(updates[1].percent(), None);
+
+        let err = handle_pull_line(
+            br#"{"error":"pull model manifest: file does not exist"}"#,
+            &mut |_| {},
+        )
+        .unwrap_err();
+        assert!(err.to_string().contains("file does not exist"));
+    }
+
+    #[test]
+    fn test_tags_response() {
+        let tags: TagsResponse = serde_json::from_str(
+            r#"{"models":[{"name":"llama3.2:latest","size":2019393189,"modified_at":"2025-01-01T00:00:00Z","digest":"abc"}]}"#,
+        )
+        .unwrap();
+        assert_eq!(tags.models[0].name, "llama3.2:latest");
+        assert_eq!(tags.models[0].size, 2019393189);
+    }
+}
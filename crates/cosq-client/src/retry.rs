@@ -0,0 +1,229 @@
+//! Retry policy for Cosmos DB and Azure OpenAI requests
+//!
+//! Throttling (429), request timeouts (408), server errors (5xx) and dropped
+//! connections are retried with capped exponential backoff. Writes, which a
+//! retry could apply twice, are only retried when throttled or when the
+//! connection failed before they were sent. The server's retry hint
+//! (`x-ms-retry-after-ms`, `retry-after-ms` or `Retry-After`) is honored when
+//! present.
+
+use std::time::Duration;
+
+use cosq_core::config::RetryConfig;
+
+/// A class of failure that can be retried
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RetryOn {
+    /// 429 Too Many Requests (request rate too large)
+    Throttled,
+    /// 408 Request Timeout
+    Timeout,
+    /// Any 5xx response
+    ServerError,
+    /// Connection refused/reset or a client-side timeout
+    Connection,
+}
+
+impl RetryOn {
+    /// Parse a config value: `429`, `408`, `5xx` or `connection`.
+    pub fn parse(s: &str) -> Option<Self> {
+        match s.trim().to_lowercase().as_str() {
+            "429" | "throttled" => Some(RetryOn::Throttled),
+            "408" | "timeout" => Some(RetryOn::Timeout),
+            "5xx" | "server" => Some(RetryOn::ServerError),
+            "connection" | "connection-reset" => Some(RetryOn::Connection),
+            _ => None,
+        }
+    }
+}
+
+/// How failed requests are retried
+#[derive(Debug, Clone, PartialEq)]
+pub struct RetryPolicy {
+    /// Total attempts including the first (1 disables retries)
+    pub max_attempts: u32,
+    /// Delay before the first retry; doubled for every further retry
+    pub base_backoff: Duration,
+    /// Upper bound for any single delay
+    pub max_backoff: Duration,
+    /// Failures that are retried
+    pub retry_on: Vec<RetryOn>,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 4,
+            base_backoff: Duration::from_millis(500),
+            max_backoff: Duration::from_secs(10),
+            retry_on: vec![
+                RetryOn::Throttled,
+                RetryOn::Timeout,
+                RetryOn::ServerError,
+                RetryOn::Connection,
+            ],
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// A policy that never retries.
+    pub fn none() -> Self {
+        Self {
+            max_attempts: 1,
+            ..Self::default()
+        }
+    }
+
+    /// Build a policy from the `retry:` config section; unset fields keep their defaults
+    /// and unknown `retry_on` entries are ignored.
+    pub fn from_config(config: &RetryConfig) -> Self {
+        let defaults = Self::default();
+        Self {
+            max_attempts: config.max_attempts.unwrap_or(defaults.max_attempts).max(1),
+            base_backoff: config
+                .base_backoff_ms
+                .map(Duration::from_millis)
+                .unwrap_or(defaults.base_backoff),
+            max_backoff: config
+                .max_backoff_ms
+                .map(Duration::from_millis)
+                .unwrap_or(defaults.max_backoff),
+            retry_on: match config.retry_on {
+                Some(ref list) => list.iter().filter_map(|s| RetryOn::parse(s)).collect(),
+                None => defaults.retry_on,
+            },
+        }
+    }
+
+    /// Whether a response with this HTTP status should be retried.
+    pub fn retries_status(&self, status: u16) -> bool {
+        let class = match status {
+            429 => RetryOn::Throttled,
+            408 => RetryOn::Timeout,
+            500..=599 => RetryOn::ServerError,
+            _ => return false,
+        };
+        self.retry_on.contains(&class)
+    }
+
+    /// Whether a transport error should be retried.
+    pub fn retries_error(&self, err: &reqwest::Error) -> bool {
+        (err.is_connect() || err.is_timeout() || err.is_request())
+            && self.retry_on.contains(&RetryOn::Connection)
+    }
+
+    /// Whether a write that got this HTTP status should be retried. A write
+    /// may already have been applied when it timed out or hit a server error,
+    /// so only throttling, which rejects it before it runs, qualifies.
+    pub fn retries_write_status(&self, status: u16) -> bool {
+        status == 429 && self.retries_status(status)
+    }
+
+    /// Whether a write that failed with this transport error should be
+    /// retried: only when no connection was made, so it was never sent.
+    pub fn retries_write_error(&self, err: &reqwest::Error) -> bool {
+        err.is_connect() && self.retry_on.contains(&RetryOn::Connection)
+    }
+
+    /// Delay before retry number `retry` (1-based), preferring the server's hint.
+    pub fn backoff(&self, retry: u32, server_hint: Option<Duration>) -> Duration {
+        let delay = server_hint.unwrap_or_else(|| {
+            self.base_backoff
+                .saturating_mul(2u32.saturating_pow(retry.saturating_sub(1)))
+        });
+        delay.min(self.max_backoff)
+    }
+}
+
+/// The server's retry hint from `retry-after-ms`, `x-ms-retry-after-ms` or
+/// `Retry-After` (seconds), in that order.
+pub fn retry_after(headers: &reqwest::header::HeaderMap) -> Option<Duration> {
+    let header = |name: &str| headers.get(name).and_then(|v| v.to_str().ok());
+    header("retry-after-ms")
+        .or_else(|| header("x-ms-retry-after-ms"))
+        .and_then(|v| v.trim().parse::<f64>().ok())
+        .map(|ms| Duration::from_millis(ms as u64))
+        .or_else(|| {
+            header("retry-after")
+                .and_then(|v| v.trim().parse::<u64>().ok())
+                .map(Duration::from_secs)
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_default_retries_transient_statuses() {
+        let policy = RetryPolicy::default();
+        assert!(policy.retries_status(429));
+        assert!(policy.retries_status(408));
+        assert!(policy.retries_status(503));
+        assert!(!policy.retries_status(400));
+        assert!(!policy.retries_status(404));
+    }
+
+    #[test]
+    fn test_writes_retry_only_throttling() {
+        let policy = RetryPolicy::default();
+        assert!(policy.retries_write_status(429));
+        assert!(!policy.retries_write_status(408));
+        assert!(!policy.retries_write_status(503));
+        let policy = RetryPolicy {
+            retry_on: vec![RetryOn::ServerError],
+            ..RetryPolicy::default()
+        };
+        assert!(!policy.retries_write_status(429));
+    }
+
+    #[test]
+    fn test_backoff_is_exponential_and_capped() {
+        let policy = RetryPolicy {
+            base_backoff: Duration::from_millis(100),
+            max_backoff: Duration::from_millis(500),
+            ..RetryPolicy::default()
+        };
+        assert_eq!(policy.backoff(1, None), Duration::from_millis(100));
+        assert_eq!(policy.backoff(2, None), Duration::from_millis(200));
+        assert_eq!(policy.backoff(3, None), Duration::from_millis(400));
+        assert_eq!(policy.backoff(4, None), Duration::from_millis(500));
+        assert_eq!(
+            policy.backoff(1, Some(Duration::from_millis(50))),
+            Duration::from_millis(50)
+        );
+        assert_eq!(
+            policy.backoff(1, Some(Duration::from_secs(5))),
+            Duration::from_millis(500)
+        );
+    }
+
+    #[test]
+    fn test_from_config() {
+        let config = RetryConfig {
+            max_attempts: Some(0),
+            base_backoff_ms: Some(250),
+            max_backoff_ms: None,
+            retry_on: Some(vec!["429".into(), "bogus".into()]),
+        };
+        let policy = RetryPolicy::from_config(&config);
+        assert_eq!(policy.max_attempts, 1);
+        assert_eq!(policy.base_backoff, Duration::from_millis(250));
+        assert_eq!(policy.max_backoff, RetryPolicy::default().max_backoff);
+        assert_eq!(policy.retry_on, vec![RetryOn::Throttled]);
+        assert!(!policy.retries_status(503));
+    }
+
+    #[test]
+    fn test_retry_after() {
+        use reqwest::header::{HeaderMap, HeaderValue};
+
+        let mut headers = HeaderMap::new();
+        assert_eq!(retry_after(&headers), None);
+        headers.insert("retry-after", HeaderValue::from_static("7"));
+        assert_eq!(retry_after(&headers), Some(Duration::from_secs(7)));
+        headers.insert("retry-after-ms", HeaderValue::from_static("1500"));
+        assert_eq!(retry_after(&headers), Some(Duration::from_millis(1500)));
+    }
+}
@@ -0,0 +1,290 @@
+//! Workload identity (federated credential) authentication
+//!
+//! Exchanges a federated token — the GitHub Actions OIDC ID token, or the
+//! file named by `AZURE_FEDERATED_TOKEN_FILE` (AKS and other CI systems) — for
+//! an Entra ID access token with the client credentials flow, so cosq runs in
+//! CI without the Azure CLI or a stored secret. The app registration (or user
+//! assigned managed identity) in `AZURE_CLIENT_ID` needs a federated
+//! credential trusting the token's issuer and subject.
+
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use serde::Deserialize;
+use tracing::debug;
+
+use crate::error::ClientError;
+
+const DEFAULT_AUTHORITY_HOST: &str = "https://login.microsoftonline.com";
+
+/// Audience Entra ID expects on federated tokens
+const TOKEN_EXCHANGE_AUDIENCE: &str = "api://AzureADTokenExchange";
+
+/// Access tokens are renewed this long before they expire
+const EXPIRY_MARGIN: Duration = Duration::from_secs(300);
+
+/// Access tokens per resource, with the time they should be renewed
+static TOKENS: Mutex<BTreeMap<String, (String, Instant)>> = Mutex::new(BTreeMap::new());
+
+/// Where the federated token comes from
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FederatedToken {
+    /// A file holding the token (`AZURE_FEDERATED_TOKEN_FILE`), re-read for
+    /// every exchange since it is rotated
+    File(PathBuf),
+    /// The GitHub Actions OIDC provider (needs `permissions: id-token: write`)
+    GitHubActions {
+        request_url: String,
+        request_token: String,
+    },
+}
+
+/// An Entra ID application that trusts a federated token
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WorkloadIdentity {
+    pub client_id: String,
+    pub tenant_id: String,
+    pub authority_host: String,
+    pub token: FederatedToken,
+}
+
+#[derive(Debug, Deserialize)]
+struct GitHubIdToken {
+    value: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct TokenResponse {
+    access_token: String,
+    #[serde(default)]
+    expires_in: u64,
+}
+
+#[derive(Debug, Deserialize)]
+struct TokenError {
+    error: String,
+    #[serde(default)]
+    error_description: String,
+}
+
+impl WorkloadIdentity {
+    /// Read the identity from `AZURE_CLIENT_ID`, `AZURE_TENANT_ID` and either
+    /// `AZURE_FEDERATED_TOKEN_FILE` or the GitHub Actions
+    /// `ACTIONS_ID_TOKEN_REQUEST_URL`/`ACTIONS_ID_TOKEN_REQUEST_TOKEN`.
+    pub fn from_env() -> Option<Self> {
+        Self::from_vars(|name| std::env::var(name).ok().filter(|v| !v.is_empty()))
+    }
+
+    /// [`WorkloadIdentity::from_env`] over any variable lookup.
+    pub fn from_vars(var: impl Fn(&str) -> Option<String>) -> Option<Self> {
+        let client_id = var("AZURE_CLIENT_ID")?;
+        let tenant_id = var("AZURE_TENANT_ID")?;
+        let token = match var("AZURE_FEDERATED_TOKEN_FILE") {
+            Some(path) => FederatedToken::File(path.into()),
+            None => FederatedToken::GitHubActions {
+                request_url: var("ACTIONS_ID_TOKEN_REQUEST_URL")?,
+                request_token: var("ACTIONS_ID_TOKEN_REQUEST_TOKEN")?,
+            },
+        };
+        let authority_host = var("AZURE_AUTHORITY_HOST")
+            .unwrap_or_else(|| DEFAULT_AUTHORITY_HOST.to_string())
+            .trim_end_matches('/')
+            .to_string();
+        Some(Self {
+            client_id,
+            tenant_id,
+            authority_host,
+            token,
+        })
+    }
+
+    /// Where the identity and its federated token come from, for `cosq auth status`.
+    pub fn describe(&self) -> String {
+        let source = match self.token {
+            FederatedToken::File(ref path) => format!("token file {}", path.display()),
+            FederatedToken::GitHubActions { .. } => "GitHub Actions OIDC".to_string(),
+        };
+        format!(
+            "client {} in tenant {} via {source}",
+            self.client_id, self.tenant_id
+        )
+    }
+
+    /// Get an access token for `resource`, exchanging a fresh federated token
+    /// when there's no cached one.
+    pub async fn get_token(&self, resource: &str) -> Result<String, ClientError> {
+        if let Some((token, renew_at)) = TOKENS.lock().unwrap().get(resource) {
+            if Instant::now() < *renew_at {
+                return Ok(token.clone());
+            }
+        }
+
+        let assertion = self.federated_token().await?;
+        debug!(resource, client_id = %self.client_id, "exchanging federated token");
+        let resp = crate::http::client()
+            .post(self.token_url())
+            .header("Content-Type", "application/x-www-form-urlencoded")
+            .body(self.exchange_form(resource, &assertion))
+            .send()
+            .await?;
+
+        let status = resp.status();
+        if !status.is_success() {
+            let body = resp.text().await.unwrap_or_default();
+            let message = match serde_json::from_str::<TokenError>(&body) {
+                Ok(e) if !e.error_description.is_empty() => e.error_description,
+                Ok(e) => e.error,
+                Err(_) => body,
+            };
+            return Err(ClientError::auth(format!(
+                "workload identity token exchange failed ({status}): {message}"
+            )));
+        }
+        let token: TokenResponse = resp.json().await?;
+        let lifetime = Duration::from_secs(token.expires_in).saturating_sub(EXPIRY_MARGIN);
+        TOKENS.lock().unwrap().insert(
+            resource.to_string(),
+            (token.access_token.clone(), Instant::now() + lifetime),
+        );
+        Ok(token.access_token)
+    }
+
+    /// The federated token to exchange.
+    async fn federated_token(&self) -> Result<String, ClientError> {
+        match self.token {
+            FederatedToken::File(ref path) => {
+                let token = tokio::fs::read_to_string(path).await.map_err(|e| {
+                    ClientError::auth(format!(
+                        "failed to read federated token file {}: {e}",
+                        path.display()
+                    ))
+                })?;
+                Ok(token.trim().to_string())
+            }
+            FederatedToken::GitHubActions {
+                ref request_url,
+                ref request_token,
+            } => {
+                let separator = if request_url.contains('?') { '&' } else { '?' };
+                let url = format!(
+                    "{request_url}{separator}audience={}",
+                    urlencoding::encode(TOKEN_EXCHANGE_AUDIENCE)
+                );
+                let resp = crate::http::client()
+                    .get(&url)
+                    .bearer_auth(request_token)
+                    .send()
+                    .await?;
+                let status = resp.status();
+                if !status.is_success() {
+                    let body = resp.text().await.unwrap_or_default();
+                    return Err(ClientError::auth(format!(
+                        "failed to get the GitHub Actions ID token ({status}): {body}"
+                    )));
+                }
+                let token: GitHubIdToken = resp.json().await?;
+                Ok(token.value)
+            }
+        }
+    }
+
+    fn token_url(&self) -> String {
+        format!(
+            "{}/{}/oauth2/v2.0/token",
+            self.authority_host, self.tenant_id
+        )
+    }
+
+    /// The client credentials request body with the federated token as the
+    /// client assertion.
+    fn exchange_form(&self, resource: &str, assertion: &str) -> String {
+        let scope = format!("{}/.default", resource.trim_end_matches('/'));
+        [
+            ("client_id", self.client_id.as_str()),
+            ("scope", scope.as_str()),
+            ("grant_type", "client_credentials"),
+            (
+                "client_assertion_type",
+                "urn:ietf:params:oauth:client-assertion-type:jwt-bearer",
+            ),
+            ("client_assertion", assertion),
+        ]
+        .iter()
+        .map(|(key, value)| format!("{key}={}", urlencoding::encode(value)))
+        .collect::<Vec<_>>()
+        .join("&")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn vars(pairs: &[(&str, &str)]) -> impl Fn(&str) -> Option<String> {
+        let map: HashMap<String, String> = pairs
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect();
+        move |name| map.get(name).cloned()
+    }
+
+    #[test]
+    fn test_from_vars() {
+        let github = WorkloadIdentity::from_vars(vars(&[
+            ("AZURE_CLIENT_ID", "app"),
+            ("AZURE_TENANT_ID", "tenant"),
+            (
+                "ACTIONS_ID_TOKEN_REQUEST_URL",
+                "https://token.actions/x?api-version=2.0",
+            ),
+            ("ACTIONS_ID_TOKEN_REQUEST_TOKEN", "req"),
+        ]))
+        .unwrap();
+        assert!(matches!(github.token, FederatedToken::GitHubActions { .. }));
+        assert_eq!(
+            github.token_url(),
+            "https://login.microsoftonline.com/tenant/oauth2/v2.0/token"
+        );
+        assert!(github.describe().contains("GitHub Actions OIDC"));
+
+        let file = WorkloadIdentity::from_vars(vars(&[
+            ("AZURE_CLIENT_ID", "app"),
+            ("AZURE_TENANT_ID", "tenant"),
+            ("AZURE_FEDERATED_TOKEN_FILE", "/var/run/token"),
+            ("AZURE_AUTHORITY_HOST", "https://login.microsoftonline.us/"),
+        ]))
+        .unwrap();
+        assert_eq!(file.token, FederatedToken::File("/var/run/token".into()));
+        assert_eq!(file.authority_host, "https://login.microsoftonline.us");
+
+        // A client ID alone (e.g. for a managed identity) isn't enough
+        assert!(WorkloadIdentity::from_vars(vars(&[("AZURE_CLIENT_ID", "app")])).is_none());
+        assert!(
+            WorkloadIdentity::from_vars(vars(&[
+                ("AZURE_CLIENT_ID", "app"),
+                ("AZURE_TENANT_ID", "tenant"),
+            ]))
+            .is_none()
+        );
+    }
+
+    #[test]
+    fn test_exchange_form() {
+        let identity = WorkloadIdentity::from_vars(vars(&[
+            ("AZURE_CLIENT_ID", "app"),
+            ("AZURE_TENANT_ID", "tenant"),
+            ("AZURE_FEDERATED_TOKEN_FILE", "/t"),
+        ]))
+        .unwrap();
+        let form = identity.exchange_form("https://cosmos.azure.com", "a.b.c");
+        assert_eq!(
+            form,
+            "client_id=app&scope=https%3A%2F%2Fcosmos.azure.com%2F.default&grant_type=client_credentials\
+             &client_assertion_type=urn%3Aietf%3Aparams%3Aoauth%3Aclient-assertion-type%3Ajwt-bearer\
+             &client_assertion=a.b.c"
+        );
+    }
+}
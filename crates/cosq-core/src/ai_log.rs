@@ -0,0 +1,141 @@
+//! Transcripts of AI interactions
+//!
+//! With `ai_log: true` in the config, every AI request is appended to
+//! `~/.cosq/ai-logs/<YYYY-MM-DD>.jsonl`: the prompts exactly as sent, the
+//! response, provider and duration. `cosq ai log` shows them, so users can
+//! audit which data left the machine and where it went.
+
+use std::io::Write as _;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum AiLogError {
+    #[error("could not determine home directory")]
+    NoHomeDir,
+
+    #[error("failed to read or write AI transcript: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("failed to encode AI transcript: {0}")]
+    Encode(#[from] serde_json::Error),
+}
+
+/// One AI request and its outcome
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Transcript {
+    /// RFC 3339 timestamp of the request
+    pub at: String,
+    /// What the request was for (e.g. `generate`, `template fix`)
+    pub feature: String,
+    /// Configured ailloy node id
+    pub provider: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+    pub duration_ms: u64,
+    pub system_prompt: String,
+    pub user_prompt: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub response: Option<String>,
+    /// Set instead of `response` when the request failed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Transcript directory: `~/.cosq/ai-logs/`.
+pub fn log_dir() -> Result<PathBuf, AiLogError> {
+    dirs::home_dir()
+        .map(|d| d.join(".cosq").join("ai-logs"))
+        .ok_or(AiLogError::NoHomeDir)
+}
+
+/// Append a transcript to the log for its day.
+pub fn append(transcript: &Transcript) -> Result<(), AiLogError> {
+    append_to(&log_dir()?, transcript)
+}
+
+/// The most recent `limit` transcripts, oldest first.
+pub fn load_recent(limit: usize) -> Result<Vec<Transcript>, AiLogError> {
+    load_recent_from(&log_dir()?, limit)
+}
+
+pub fn append_to(dir: &Path, transcript: &Transcript) -> Result<(), AiLogError> {
+    std::fs::create_dir_all(dir)?;
+    let day = transcript.at.get(..10).unwrap_or("unknown");
+    let mut file = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(dir.join(format!("{day}.jsonl")))?;
+    writeln!(file, "{}", serde_json::to_string(transcript)?)?;
+    Ok(())
+}
+
+pub fn load_recent_from(dir: &Path, limit: usize) -> Result<Vec<Transcript>, AiLogError> {
+    let mut files: Vec<PathBuf> = match std::fs::read_dir(dir) {
+        Ok(entries) => entries
+            .flatten()
+            .map(|e| e.path())
+            .filter(|p| p.extension().is_some_and(|ext| ext == "jsonl"))
+            .collect(),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e.into()),
+    };
+    // Day files sort chronologically by name; read newest first until full
+    files.sort();
+    let mut transcripts = Vec::new();
+    for file in files.iter().rev() {
+        let contents = std::fs::read_to_string(file)?;
+        let day: Vec<Transcript> = contents
+            .lines()
+            .filter_map(|line| serde_json::from_str(line).ok())
+            .collect();
+        for transcript in day.into_iter().rev() {
+            if transcripts.len() == limit {
+                break;
+            }
+            transcripts.push(transcript);
+        }
+    }
+    transcripts.reverse();
+    Ok(transcripts)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn transcript(at: &str) -> Transcript {
+        Transcript {
+            at: at.to_string(),
+            feature: "generate".to_string(),
+            provider: "openai".to_string(),
+            model: Some("gpt-4o".to_string()),
+            duration_ms: 1200,
+            system_prompt: "system".to_string(),
+            user_prompt: "user".to_string(),
+            response: Some("SELECT * FROM c".to_string()),
+            error: None,
+        }
+    }
+
+    #[test]
+    fn test_append_and_load_recent() {
+        let dir = tempfile::tempdir().unwrap();
+        assert!(load_recent_from(dir.path(), 10).unwrap().is_empty());
+
+        append_to(dir.path(), &transcript("2026-01-01T10:00:00Z")).unwrap();
+        append_to(dir.path(), &transcript("2026-01-02T09:00:00Z")).unwrap();
+        append_to(dir.path(), &transcript("2026-01-02T11:00:00Z")).unwrap();
+        assert!(dir.path().join("2026-01-02.jsonl").is_file());
+
+        let all = load_recent_from(dir.path(), 10).unwrap();
+        assert_eq!(all.len(), 3);
+        assert_eq!(all[0].at, "2026-01-01T10:00:00Z");
+
+        let last_two = load_recent_from(dir.path(), 2).unwrap();
+        let times: Vec<_> = last_two.iter().map(|t| t.at.as_str()).collect();
+        assert_eq!(times, vec!["2026-01-02T09:00:00Z", "2026-01-02T11:00:00Z"]);
+    }
+}
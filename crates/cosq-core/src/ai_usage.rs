@@ -0,0 +1,153 @@
+//! Token accounting for AI calls
+//!
+//! Every AI request cosq makes appends one line to `~/.cosq/ai-usage.jsonl`
+//! with the token counts reported by the provider. `cosq ai usage` aggregates
+//! them per month (and checks the `ai_budget:` from the config).
+
+use std::collections::BTreeMap;
+use std::io::Write as _;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum AiUsageError {
+    #[error("could not determine home directory")]
+    NoHomeDir,
+
+    #[error("failed to read or write AI usage log: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("failed to encode AI usage record: {0}")]
+    Encode(#[from] serde_json::Error),
+}
+
+/// One AI call
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct UsageRecord {
+    /// RFC 3339 timestamp of the call
+    pub at: String,
+    /// What the call was for (e.g. `generate`, `template fix`)
+    pub feature: String,
+    /// Configured ailloy node id
+    pub provider: String,
+    pub model: String,
+    /// Token counts, when the provider reports them
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub prompt_tokens: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub completion_tokens: Option<u64>,
+}
+
+/// Aggregated usage over a set of calls
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct UsageTotals {
+    pub calls: u64,
+    /// Calls whose provider reported no token counts
+    pub unreported: u64,
+    pub prompt_tokens: u64,
+    pub completion_tokens: u64,
+}
+
+impl UsageTotals {
+    pub fn add(&mut self, record: &UsageRecord) {
+        self.calls += 1;
+        if record.prompt_tokens.is_none() && record.completion_tokens.is_none() {
+            self.unreported += 1;
+        }
+        self.prompt_tokens += record.prompt_tokens.unwrap_or(0);
+        self.completion_tokens += record.completion_tokens.unwrap_or(0);
+    }
+
+    pub fn total_tokens(&self) -> u64 {
+        self.prompt_tokens + self.completion_tokens
+    }
+}
+
+/// Usage log: `~/.cosq/ai-usage.jsonl`.
+pub fn usage_file() -> Result<PathBuf, AiUsageError> {
+    dirs::home_dir()
+        .map(|d| d.join(".cosq").join("ai-usage.jsonl"))
+        .ok_or(AiUsageError::NoHomeDir)
+}
+
+/// Append a record to the usage log.
+pub fn append(record: &UsageRecord) -> Result<(), AiUsageError> {
+    append_to(&usage_file()?, record)
+}
+
+/// All records in the usage log (oldest first).
+pub fn load() -> Result<Vec<UsageRecord>, AiUsageError> {
+    load_from(&usage_file()?)
+}
+
+pub fn append_to(path: &Path, record: &UsageRecord) -> Result<(), AiUsageError> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let mut file = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)?;
+    writeln!(file, "{}", serde_json::to_string(record)?)?;
+    Ok(())
+}
+
+/// Read records, skipping lines that don't parse (e.g. a truncated last line).
+pub fn load_from(path: &Path) -> Result<Vec<UsageRecord>, AiUsageError> {
+    match std::fs::read_to_string(path) {
+        Ok(contents) => Ok(contents
+            .lines()
+            .filter_map(|line| serde_json::from_str(line).ok())
+            .collect()),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Vec::new()),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Totals per calendar month (`YYYY-MM`, from the record timestamps).
+pub fn totals_by_month(records: &[UsageRecord]) -> BTreeMap<String, UsageTotals> {
+    let mut months: BTreeMap<String, UsageTotals> = BTreeMap::new();
+    for record in records {
+        let month = record.at.get(..7).unwrap_or(&record.at).to_string();
+        months.entry(month).or_default().add(record);
+    }
+    months
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(at: &str, tokens: Option<(u64, u64)>) -> UsageRecord {
+        UsageRecord {
+            at: at.to_string(),
+            feature: "generate".to_string(),
+            provider: "openai".to_string(),
+            model: "gpt-4o".to_string(),
+            prompt_tokens: tokens.map(|t| t.0),
+            completion_tokens: tokens.map(|t| t.1),
+        }
+    }
+
+    #[test]
+    fn test_append_and_totals() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("usage.jsonl");
+        assert!(load_from(&path).unwrap().is_empty());
+
+        append_to(&path, &record("2026-01-05T10:00:00Z", Some((100, 20)))).unwrap();
+        append_to(&path, &record("2026-01-20T10:00:00Z", None)).unwrap();
+        append_to(&path, &record("2026-02-01T10:00:00Z", Some((50, 5)))).unwrap();
+        let records = load_from(&path).unwrap();
+        assert_eq!(records.len(), 3);
+
+        let months = totals_by_month(&records);
+        let january = months["2026-01"];
+        assert_eq!(january.calls, 2);
+        assert_eq!(january.unreported, 1);
+        assert_eq!(january.total_tokens(), 120);
+        assert_eq!(months["2026-02"].prompt_tokens, 50);
+    }
+}
@@ -0,0 +1,485 @@
+//! Terminal and SVG charts for `--chart` / `chart:`
+//!
+//! A chart spec is `<kind>:<label field>:<value field>`, e.g.
+//! `bar:status:count` for the rows of `SELECT c.status, COUNT(1) AS count
+//! FROM c GROUP BY c.status`. Fields may be dotted paths (`address.city`).
+//! `bar` draws one horizontal bar per result; `line` draws the values in
+//! result order (e.g. per day, oldest first) as columns. Results without a
+//! numeric value are skipped.
+
+use std::fmt::Write as _;
+
+use serde_json::Value;
+use thiserror::Error;
+
+/// Label of results that don't have the label field
+pub const UNDEFINED_LABEL: &str = "(undefined)";
+
+/// Rows of a terminal line chart
+const LINE_HEIGHT: usize = 8;
+
+/// Partial blocks for the end of a bar, in eighths
+const HORIZONTAL_EIGHTHS: [char; 8] = [' ', '▏', '▎', '▍', '▌', '▋', '▊', '▉'];
+
+/// Partial blocks for the top of a column, in eighths
+const VERTICAL_EIGHTHS: [char; 8] = [' ', '▁', '▂', '▃', '▄', '▅', '▆', '▇'];
+
+#[derive(Debug, Error)]
+#[error(
+    "invalid chart '{0}': expected <bar|line>:<label field>:<value field>, e.g. bar:status:count"
+)]
+pub struct ChartError(pub String);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChartKind {
+    Bar,
+    Line,
+}
+
+/// A parsed `<kind>:<label field>:<value field>`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChartSpec {
+    pub kind: ChartKind,
+    pub label: String,
+    pub value: String,
+}
+
+impl ChartSpec {
+    pub fn parse(spec: &str) -> Result<Self, ChartError> {
+        let invalid = || ChartError(spec.to_string());
+        let mut parts = spec.trim().split(':').map(str::trim);
+        let kind = match parts.next().map(str::to_ascii_lowercase).as_deref() {
+            Some("bar") => ChartKind::Bar,
+            Some("line") => ChartKind::Line,
+            _ => return Err(invalid()),
+        };
+        let (Some(label), Some(value), None) = (parts.next(), parts.next(), parts.next()) else {
+            return Err(invalid());
+        };
+        if label.is_empty() || value.is_empty() {
+            return Err(invalid());
+        }
+        let field = |f: &str| f.strip_prefix("c.").unwrap_or(f).to_string();
+        Ok(ChartSpec {
+            kind,
+            label: field(label),
+            value: field(value),
+        })
+    }
+}
+
+/// One labelled value
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChartPoint {
+    pub label: String,
+    pub value: f64,
+}
+
+/// The values to chart, in result order
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChartData {
+    pub points: Vec<ChartPoint>,
+    /// Results skipped because the value field is missing or not a number
+    pub skipped: usize,
+}
+
+impl ChartData {
+    /// Read the spec's label and value fields from each result.
+    pub fn new(spec: &ChartSpec, documents: &[Value]) -> Self {
+        let mut points = Vec::new();
+        let mut skipped = 0;
+        for doc in documents {
+            let Some(value) = lookup(doc, &spec.value).and_then(Value::as_f64) else {
+                skipped += 1;
+                continue;
+            };
+            let label = match lookup(doc, &spec.label) {
+                Some(Value::String(s)) => s.clone(),
+                Some(Value::Null) | None => UNDEFINED_LABEL.to_string(),
+                Some(other) => other.to_string(),
+            };
+            points.push(ChartPoint { label, value });
+        }
+        ChartData { points, skipped }
+    }
+
+    fn range(&self) -> (f64, f64) {
+        self.points
+            .iter()
+            .fold((f64::INFINITY, f64::NEG_INFINITY), |(min, max), p| {
+                (min.min(p.value), max.max(p.value))
+            })
+    }
+}
+
+/// Render a chart as text, at most `width` columns wide.
+pub fn render_text(spec: &ChartSpec, data: &ChartData, width: usize) -> String {
+    if data.points.is_empty() {
+        return format!("No numeric '{}' values to chart\n", spec.value);
+    }
+    match spec.kind {
+        ChartKind::Bar => bar_text(data, width),
+        ChartKind::Line => line_text(spec, data, width),
+    }
+}
+
+fn bar_text(data: &ChartData, width: usize) -> String {
+    let label_width = data
+        .points
+        .iter()
+        .map(|p| p.label.chars().count())
+        .max()
+        .unwrap_or_default()
+        .min(24);
+    let value_width = data
+        .points
+        .iter()
+        .map(|p| format_value(p.value).len())
+        .max()
+        .unwrap_or_default();
+    let bar_width = width.saturating_sub(label_width + value_width + 3).max(10);
+    let max = data.range().1.max(0.0);
+
+    let mut out = String::new();
+    for point in &data.points {
+        let eighths = if max > 0.0 {
+            (point.value.max(0.0) / max * (bar_width * 8) as f64).round() as usize
+        } else {
+            0
+        };
+        let mut bar = "█".repeat(eighths / 8);
+        if eighths % 8 > 0 {
+            bar.push(HORIZONTAL_EIGHTHS[eighths % 8]);
+        }
+        let _ = writeln!(
+            out,
+            "{:<label_width$} {bar:<bar_width$} {:>value_width$}",
+            truncate_label(&point.label, label_width),
+            format_value(point.value),
+        );
+    }
+    out
+}
+
+fn line_text(spec: &ChartSpec, data: &ChartData, width: usize) -> String {
+    let (min, max) = data.range();
+    let axis_width = format_value(min).len().max(format_value(max).len());
+    let columns = bucket_means(&data.points, width.saturating_sub(axis_width + 2).max(10));
+    // Columns rise from the minimum (or zero, when every value is positive)
+    let floor = min.min(0.0);
+    let span = max - floor;
+
+    let mut out = String::new();
+    for row in (0..LINE_HEIGHT).rev() {
+        let axis = match row {
+            r if r == LINE_HEIGHT - 1 => format_value(max),
+            0 => format_value(floor),
+            _ => String::new(),
+        };
+        let _ = write!(out, "{axis:>axis_width$} ┤");
+        for value in &columns {
+            let eighths = if span > 0.0 {
+                ((value - floor) / span * (LINE_HEIGHT * 8) as f64).round() as usize
+            } else {
+                LINE_HEIGHT * 8
+            };
+            let filled = eighths.saturating_sub(row * 8);
+            out.push(match filled {
+                0 => ' ',
+                f if f >= 8 => '█',
+                f => VERTICAL_EIGHTHS[f],
+            });
+        }
+        out.push('\n');
+    }
+    let first = &data.points[0].label;
+    let last = &data.points[data.points.len() - 1].label;
+    let gap = columns
+        .len()
+        .saturating_sub(first.chars().count() + last.chars().count());
+    let _ = writeln!(out, "{:>axis_width$} └{}", "", "─".repeat(columns.len()));
+    if data.points.len() > 1 && gap > 0 {
+        let _ = writeln!(out, "{:>axis_width$}  {first}{}{last}", "", " ".repeat(gap));
+    } else {
+        let _ = writeln!(out, "{:>axis_width$}  {first}", "");
+    }
+    let _ = writeln!(
+        out,
+        "{:>axis_width$}  {} by {}, {} point(s)",
+        "",
+        spec.value,
+        spec.label,
+        data.points.len()
+    );
+    out
+}
+
+/// Average consecutive values into at most `columns` buckets.
+fn bucket_means(points: &[ChartPoint], columns: usize) -> Vec<f64> {
+    if points.len() <= columns {
+        return points.iter().map(|p| p.value).collect();
+    }
+    (0..columns)
+        .map(|col| {
+            let bucket = &points[col * points.len() / columns..(col + 1) * points.len() / columns];
+            bucket.iter().map(|p| p.value).sum::<f64>() / bucket.len() as f64
+        })
+        .collect()
+}
+
+/// Render a chart as a standalone SVG image.
+pub fn render_svg(spec: &ChartSpec, data: &ChartData) -> String {
+    match spec.kind {
+        ChartKind::Bar => bar_svg(spec, data),
+        ChartKind::Line => line_svg(spec, data),
+    }
+}
+
+const SVG_WIDTH: f64 = 720.0;
+const SVG_STYLE: &str = "<style>text{font:12px sans-serif;fill:#333}.title{font-size:14px;font-weight:bold}.axis{stroke:#999}</style>";
+
+fn svg_open(height: f64, title: &str) -> String {
+    format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{SVG_WIDTH}\" height=\"{height}\" viewBox=\"0 0 {SVG_WIDTH} {height}\">\n{SVG_STYLE}\n<rect width=\"100%\" height=\"100%\" fill=\"#fff\"/>\n<text class=\"title\" x=\"16\" y=\"24\">{}</text>\n",
+        escape_xml(title)
+    )
+}
+
+fn bar_svg(spec: &ChartSpec, data: &ChartData) -> String {
+    const ROW: f64 = 24.0;
+    const LABEL: f64 = 180.0;
+    const TOP: f64 = 44.0;
+    let height = TOP + ROW * data.points.len() as f64 + 16.0;
+    let bar_space = SVG_WIDTH - LABEL - 100.0;
+    let max = data.range().1.max(0.0);
+
+    let mut svg = svg_open(height, &format!("{} by {}", spec.value, spec.label));
+    for (i, point) in data.points.iter().enumerate() {
+        let y = TOP + ROW * i as f64;
+        let length = if max > 0.0 {
+            point.value.max(0.0) / max * bar_space
+        } else {
+            0.0
+        };
+        let _ = writeln!(
+            svg,
+            "<text x=\"{:.1}\" y=\"{:.1}\" text-anchor=\"end\">{}</text>\
+             <rect x=\"{LABEL}\" y=\"{:.1}\" width=\"{length:.1}\" height=\"{:.1}\" fill=\"#4e79a7\"/>\
+             <text x=\"{:.1}\" y=\"{:.1}\">{}</text>",
+            LABEL - 8.0,
+            y + 16.0,
+            escape_xml(&truncate_label(&point.label, 28)),
+            y + 4.0,
+            ROW - 6.0,
+            LABEL + length + 6.0,
+            y + 16.0,
+            format_value(point.value)
+        );
+    }
+    svg.push_str("</svg>\n");
+    svg
+}
+
+fn line_svg(spec: &ChartSpec, data: &ChartData) -> String {
+    const HEIGHT: f64 = 360.0;
+    const LEFT: f64 = 80.0;
+    const RIGHT: f64 = 24.0;
+    const TOP: f64 = 44.0;
+    const BOTTOM: f64 = 48.0;
+    let (plot_width, plot_height) = (SVG_WIDTH - LEFT - RIGHT, HEIGHT - TOP - BOTTOM);
+
+    let mut svg = svg_open(HEIGHT, &format!("{} by {}", spec.value, spec.label));
+    let _ = writeln!(
+        svg,
+        "<line class=\"axis\" x1=\"{LEFT}\" y1=\"{TOP}\" x2=\"{LEFT}\" y2=\"{:.1}\"/>\
+         <line class=\"axis\" x1=\"{LEFT}\" y1=\"{:.1}\" x2=\"{:.1}\" y2=\"{:.1}\"/>",
+        TOP + plot_height,
+        TOP + plot_height,
+        LEFT + plot_width,
+        TOP + plot_height
+    );
+    if data.points.is_empty() {
+        svg.push_str("</svg>\n");
+        return svg;
+    }
+
+    let (min, max) = data.range();
+    let span = if max > min { max - min } else { 1.0 };
+    let step = plot_width / (data.points.len().max(2) - 1) as f64;
+    let position = |i: usize, value: f64| {
+        (
+            LEFT + step * i as f64,
+            TOP + plot_height - (value - min) / span * plot_height,
+        )
+    };
+    let coordinates: Vec<String> = data
+        .points
+        .iter()
+        .enumerate()
+        .map(|(i, p)| {
+            let (x, y) = position(i, p.value);
+            format!("{x:.1},{y:.1}")
+        })
+        .collect();
+    let _ = writeln!(
+        svg,
+        "<polyline fill=\"none\" stroke=\"#4e79a7\" stroke-width=\"2\" points=\"{}\"/>",
+        coordinates.join(" ")
+    );
+    for (i, point) in data.points.iter().enumerate() {
+        let (x, y) = position(i, point.value);
+        let _ = writeln!(
+            svg,
+            "<circle cx=\"{x:.1}\" cy=\"{y:.1}\" r=\"3\" fill=\"#4e79a7\"><title>{}: {}</title></circle>",
+            escape_xml(&point.label),
+            format_value(point.value)
+        );
+    }
+    for (value, y) in [(max, TOP), (min, TOP + plot_height)] {
+        let _ = writeln!(
+            svg,
+            "<text x=\"{:.1}\" y=\"{:.1}\" text-anchor=\"end\">{}</text>",
+            LEFT - 8.0,
+            y + 4.0,
+            format_value(value)
+        );
+    }
+    let last = data.points.len() - 1;
+    let mut x_labels = vec![(0, "start")];
+    if last > 0 {
+        x_labels.push((last, "end"));
+    }
+    for (i, anchor) in x_labels {
+        let _ = writeln!(
+            svg,
+            "<text x=\"{:.1}\" y=\"{:.1}\" text-anchor=\"{anchor}\">{}</text>",
+            position(i, min).0,
+            TOP + plot_height + 20.0,
+            escape_xml(&truncate_label(&data.points[i].label, 32))
+        );
+    }
+    svg.push_str("</svg>\n");
+    svg
+}
+
+/// Whole numbers without a fraction, others with up to two decimals.
+pub fn format_value(value: f64) -> String {
+    if value.fract() == 0.0 && value.abs() < 1e15 {
+        format!("{value:.0}")
+    } else {
+        let formatted = format!("{value:.2}");
+        formatted
+            .trim_end_matches('0')
+            .trim_end_matches('.')
+            .to_string()
+    }
+}
+
+fn truncate_label(label: &str, max: usize) -> String {
+    if label.chars().count() <= max {
+        label.to_string()
+    } else {
+        let mut truncated: String = label.chars().take(max.saturating_sub(1)).collect();
+        truncated.push('…');
+        truncated
+    }
+}
+
+fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Look up a dotted path (`address.city`) in a document.
+fn lookup<'a>(doc: &'a Value, path: &str) -> Option<&'a Value> {
+    path.split('.').try_fold(doc, |value, key| value.get(key))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_parse_spec() {
+        let spec = ChartSpec::parse("bar:status:c.count").unwrap();
+        assert_eq!(spec.kind, ChartKind::Bar);
+        assert_eq!(
+            (spec.label.as_str(), spec.value.as_str()),
+            ("status", "count")
+        );
+        assert_eq!(
+            ChartSpec::parse("Line:day:n").unwrap().kind,
+            ChartKind::Line
+        );
+        for invalid in ["pie:a:b", "bar:status", "bar::n", "bar:a:b:c"] {
+            assert!(ChartSpec::parse(invalid).is_err(), "{invalid}");
+        }
+    }
+
+    #[test]
+    fn test_bar_chart() {
+        let spec = ChartSpec::parse("bar:status:count").unwrap();
+        let docs = [
+            json!({"status": "paid", "count": 40}),
+            json!({"status": "open", "count": 10.5}),
+            json!({"count": 0}),
+            json!({"status": "void", "count": "n/a"}),
+        ];
+        let data = ChartData::new(&spec, &docs);
+        assert_eq!(data.points.len(), 3);
+        assert_eq!(data.skipped, 1);
+
+        let text = render_text(&spec, &data, 40);
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(lines.len(), 3);
+        // Label (11), bar (22 cells), value (4)
+        assert_eq!(lines[0], format!("paid        {}   40", "█".repeat(22)));
+        assert_eq!(
+            lines[1],
+            format!("open        █████▊{} 10.5", " ".repeat(16))
+        );
+        assert!(lines[2].starts_with(UNDEFINED_LABEL));
+
+        let svg = render_svg(&spec, &data);
+        assert!(svg.starts_with("<svg"));
+        assert_eq!(svg.matches("<rect x=").count(), 3);
+    }
+
+    #[test]
+    fn test_line_chart() {
+        let spec = ChartSpec::parse("line:day.date:total").unwrap();
+        let docs: Vec<Value> = (0..30)
+            .map(|i| json!({"day": {"date": format!("2026-01-{:02}", i + 1)}, "total": i}))
+            .collect();
+        let data = ChartData::new(&spec, &docs);
+        let text = render_text(&spec, &data, 22);
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(lines.len(), LINE_HEIGHT + 3);
+        assert!(lines[0].starts_with("29 ┤"));
+        assert!(lines[LINE_HEIGHT - 1].starts_with(" 0 ┤"));
+        // 30 points averaged into 18 columns, rising from an empty first column
+        assert_eq!(lines[LINE_HEIGHT].chars().filter(|&c| c == '─').count(), 18);
+        assert!(lines[LINE_HEIGHT - 1].starts_with(" 0 ┤ ▃█"));
+        assert!(lines[0].ends_with("▂▇"));
+        assert!(lines[LINE_HEIGHT + 1].contains("2026-01-01"));
+
+        let svg = render_svg(&spec, &data);
+        assert_eq!(svg.matches("<circle").count(), 30);
+        assert!(svg.contains("2026-01-30"));
+        assert_eq!(
+            render_text(&spec, &ChartData::new(&spec, &[]), 40),
+            "No numeric 'total' values to chart\n"
+        );
+    }
+
+    #[test]
+    fn test_format_value() {
+        assert_eq!(format_value(42.0), "42");
+        assert_eq!(format_value(2.5), "2.5");
+        assert_eq!(format_value(1.0 / 3.0), "0.33");
+        assert_eq!(escape_xml("<a & \"b\">"), "&lt;a &amp; &quot;b&quot;&gt;");
+    }
+}
@@ -0,0 +1,250 @@
+//! Data quality checks (`checks:` in stored queries)
+//!
+//! `cosq run --check` evaluates each check against the query's results and
+//! fails when any does not hold, so a folder of stored queries doubles as a
+//! data-quality suite for CI or cron. A check is one of:
+//!
+//! - `row_count > 0` — the number of results compared with a number
+//!   (`=`/`==`, `!=`, `<`, `<=`, `>`, `>=`)
+//! - `count(<condition>) <= 5` — the number of results matching a condition
+//! - `all(<condition>)`, `any(<condition>)`, `none(<condition>)`
+//!
+//! Conditions are Cosmos DB SQL `WHERE` expressions over each result as `c`,
+//! e.g. `all(c.total >= 0 AND IS_DEFINED(c.customerId))`, evaluated locally
+//! (see [`crate::local_query`]).
+
+use serde_json::Value;
+use thiserror::Error;
+
+use crate::local_query::LocalQuery;
+
+#[derive(Debug, Error)]
+#[error("invalid check '{check}': {reason}")]
+pub struct CheckError {
+    pub check: String,
+    pub reason: String,
+}
+
+/// The result of one check
+#[derive(Debug, Clone, PartialEq)]
+pub struct CheckOutcome {
+    /// The check as written
+    pub check: String,
+    pub passed: bool,
+    /// What was measured, e.g. `row_count = 0` or `3 of 120 rows fail`
+    pub detail: String,
+}
+
+/// A parsed check
+#[derive(Debug)]
+pub struct Check {
+    source: String,
+    kind: CheckKind,
+}
+
+#[derive(Debug)]
+enum CheckKind {
+    RowCount(Comparison),
+    Count(LocalQuery, Comparison),
+    All(LocalQuery),
+    Any(LocalQuery),
+    None(LocalQuery),
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Comparison {
+    op: &'static str,
+    value: f64,
+}
+
+impl Comparison {
+    fn holds(self, actual: f64) -> bool {
+        match self.op {
+            "=" => actual == self.value,
+            "!=" => actual != self.value,
+            "<" => actual < self.value,
+            "<=" => actual <= self.value,
+            ">" => actual > self.value,
+            _ => actual >= self.value,
+        }
+    }
+}
+
+impl Check {
+    /// Parse a check expression.
+    pub fn parse(source: &str) -> Result<Self, CheckError> {
+        let invalid = |reason: String| CheckError {
+            check: source.to_string(),
+            reason,
+        };
+        let text = source.trim();
+        let (name, rest) = match text.find(|c: char| !(c.is_ascii_alphanumeric() || c == '_')) {
+            Some(end) => (&text[..end], text[end..].trim_start()),
+            None => (text, ""),
+        };
+        let kind = match name.to_ascii_lowercase().as_str() {
+            "row_count" => CheckKind::RowCount(parse_comparison(rest).map_err(invalid)?),
+            function @ ("count" | "all" | "any" | "none") => {
+                let (condition, tail) = split_call(rest).map_err(invalid)?;
+                let query = condition_query(condition).map_err(invalid)?;
+                match function {
+                    "count" => CheckKind::Count(query, parse_comparison(tail).map_err(invalid)?),
+                    _ if !tail.trim().is_empty() => {
+                        return Err(invalid(format!(
+                            "unexpected '{}' after {name}(...)",
+                            tail.trim()
+                        )));
+                    }
+                    "all" => CheckKind::All(query),
+                    "any" => CheckKind::Any(query),
+                    _ => CheckKind::None(query),
+                }
+            }
+            _ => {
+                return Err(invalid(
+                    "expected row_count, count(...), all(...), any(...) or none(...)".into(),
+                ));
+            }
+        };
+        Ok(Check {
+            source: source.to_string(),
+            kind,
+        })
+    }
+
+    /// Evaluate the check against a query's results.
+    pub fn evaluate(&self, documents: &[Value]) -> CheckOutcome {
+        let total = documents.len();
+        let matching = |query: &LocalQuery| query.execute(documents.to_vec()).len();
+        let (passed, detail) = match self.kind {
+            CheckKind::RowCount(comparison) => (
+                comparison.holds(total as f64),
+                format!("row_count = {total}"),
+            ),
+            CheckKind::Count(ref query, comparison) => {
+                let count = matching(query);
+                (comparison.holds(count as f64), format!("count = {count}"))
+            }
+            CheckKind::All(ref query) => {
+                let failing = total - matching(query);
+                (failing == 0, format!("{failing} of {total} rows fail"))
+            }
+            CheckKind::Any(ref query) => {
+                let count = matching(query);
+                (count > 0, format!("{count} of {total} rows match"))
+            }
+            CheckKind::None(ref query) => {
+                let count = matching(query);
+                (count == 0, format!("{count} of {total} rows match"))
+            }
+        };
+        CheckOutcome {
+            check: self.source.clone(),
+            passed,
+            detail,
+        }
+    }
+}
+
+/// Parse every check, failing on the first invalid one.
+pub fn parse_checks(checks: &[String]) -> Result<Vec<Check>, CheckError> {
+    checks.iter().map(|check| Check::parse(check)).collect()
+}
+
+/// `<op> <number>`
+fn parse_comparison(text: &str) -> Result<Comparison, String> {
+    let text = text.trim();
+    let (op, rest) = ["==", "!=", "<>", "<=", ">=", "=", "<", ">"]
+        .iter()
+        .find_map(|op| text.strip_prefix(op).map(|rest| (*op, rest)))
+        .ok_or_else(|| "expected a comparison such as > 0".to_string())?;
+    let op = match op {
+        "==" => "=",
+        "<>" => "!=",
+        op => op,
+    };
+    let value = rest
+        .trim()
+        .parse::<f64>()
+        .map_err(|_| format!("expected a number after {op}, found '{}'", rest.trim()))?;
+    Ok(Comparison { op, value })
+}
+
+/// Split `(<condition>)<tail>` at the parenthesis closing the first one,
+/// ignoring parentheses inside string literals.
+fn split_call(text: &str) -> Result<(&str, &str), String> {
+    let inner = text
+        .strip_prefix('(')
+        .ok_or_else(|| "expected '(' after the function name".to_string())?;
+    let mut depth = 0usize;
+    let mut quote = None;
+    for (i, c) in inner.char_indices() {
+        match (quote, c) {
+            (None, '\'' | '"') => quote = Some(c),
+            (Some(q), c) if c == q => quote = None,
+            (None, '(') => depth += 1,
+            (None, ')') if depth == 0 => return Ok((&inner[..i], &inner[i + 1..])),
+            (None, ')') => depth -= 1,
+            _ => {}
+        }
+    }
+    Err("missing ')'".into())
+}
+
+/// A query returning the results for which `condition` holds.
+fn condition_query(condition: &str) -> Result<LocalQuery, String> {
+    if condition.trim().is_empty() {
+        return Err("the condition is empty".into());
+    }
+    LocalQuery::parse(&format!("SELECT VALUE c FROM c WHERE {condition}"))
+        .map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn outcome(check: &str, documents: &[Value]) -> CheckOutcome {
+        Check::parse(check).unwrap().evaluate(documents)
+    }
+
+    #[test]
+    fn test_checks() {
+        let docs = vec![
+            json!({"id": "1", "total": 10, "status": "paid"}),
+            json!({"id": "2", "total": -5, "status": "open"}),
+            json!({"id": "3", "total": 0}),
+        ];
+        assert!(outcome("row_count > 0", &docs).passed);
+        assert!(!outcome("row_count == 0", &docs).passed);
+        assert_eq!(outcome("row_count <= 2", &docs).detail, "row_count = 3");
+
+        let all = outcome("all(c.total >= 0)", &docs);
+        assert!(!all.passed);
+        assert_eq!(all.detail, "1 of 3 rows fail");
+        assert!(outcome("any(c.status = 'paid')", &docs).passed);
+        assert!(outcome("none(c.status = 'refunded (full)')", &docs).passed);
+        // Documents without the field fail all(): the comparison is undefined
+        assert!(!outcome("all(c.status != 'void')", &docs).passed);
+        assert!(outcome("count(IS_DEFINED(c.status)) = 2", &docs).passed);
+        assert!(outcome("row_count = 0", &[]).passed);
+    }
+
+    #[test]
+    fn test_invalid_checks() {
+        for check in [
+            "rows > 0",
+            "row_count",
+            "row_count > many",
+            "all c.total > 0",
+            "all(c.total > 0",
+            "all(c.total > 0) > 1",
+            "count(c.total > 0)",
+            "any()",
+            "any(c.total >>> 0)",
+        ] {
+            assert!(Check::parse(check).is_err(), "{check} should be rejected");
+        }
+    }
+}
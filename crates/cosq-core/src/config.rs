@@ -3,6 +3,7 @@
 //! Config is stored at `~/.config/cosq/config.yaml` (or the platform equivalent
 //! via `dirs::config_dir()`).
 
+use std::collections::BTreeMap;
 use std::path::{Path, PathBuf};
 
 use serde::{Deserialize, Serialize};
@@ -27,10 +28,13 @@ pub enum ConfigError {
 
     #[error("could not determine config directory")]
     NoConfigDir,
+
+    #[error("profile '{0}' not found in config — add it under `profiles:`")]
+    ProfileNotFound(String),
 }
 
 /// Cosmos DB account configuration
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct AccountConfig {
     /// Cosmos DB account name
     pub name: String,
@@ -43,10 +47,418 @@ pub struct AccountConfig {
 
     /// Cosmos DB account endpoint URL
     pub endpoint: String,
+
+    /// Base64 master key for key-based auth (e.g. the emulator's well-known key);
+    /// AAD tokens from the Azure CLI are used when unset
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub master_key: Option<String>,
+
+    /// Extra PEM root certificate to trust (e.g. the emulator's self-signed certificate)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ca_cert: Option<String>,
+
+    /// Serverless or provisioned throughput, as last detected by `cosq account show`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub capacity_mode: Option<CapacityMode>,
 }
 
-/// Top-level cosq configuration
+/// How an account is billed for request units
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CapacityMode {
+    /// Billed per request unit consumed
+    Serverless,
+    /// Billed per provisioned RU/s (manual or autoscale), whether used or not
+    Provisioned,
+}
+
+impl CapacityMode {
+    /// Advice printed with the slow query warning.
+    pub fn budget_hint(self) -> &'static str {
+        match self {
+            CapacityMode::Serverless => {
+                "Serverless account: every RU of this run is billed, so narrowing the query cuts cost directly."
+            }
+            CapacityMode::Provisioned => {
+                "Provisioned throughput: this run competes with other traffic for the container's RU/s and can cause 429s; consider --priority low."
+            }
+        }
+    }
+}
+
+/// A named connection profile (e.g. `analytics`) that stored queries can pin to
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProfileConfig {
+    /// Cosmos DB account details
+    pub account: AccountConfig,
+
+    /// Default database name for this profile
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub database: Option<String>,
+
+    /// Default container name for this profile
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub container: Option<String>,
+}
+
+/// Kind of incoming webhook, which determines the payload shape
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum WebhookKind {
+    Slack,
+    Teams,
+}
+
+impl WebhookKind {
+    /// Guess the webhook kind from its URL (Slack unless it looks like a Teams/Power Automate URL).
+    pub fn detect(url: &str) -> Self {
+        let lower = url.to_lowercase();
+        if lower.contains("office.com")
+            || lower.contains("office365")
+            || lower.contains("logic.azure.com")
+            || lower.contains("powerplatform")
+        {
+            WebhookKind::Teams
+        } else {
+            WebhookKind::Slack
+        }
+    }
+}
+
+/// A named incoming webhook for result notifications
 #[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WebhookConfig {
+    /// Incoming webhook URL
+    pub url: String,
+
+    /// Payload kind (detected from the URL when omitted)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub kind: Option<WebhookKind>,
+}
+
+impl WebhookConfig {
+    /// The payload kind, explicit or detected from the URL
+    pub fn kind(&self) -> WebhookKind {
+        self.kind.unwrap_or_else(|| WebhookKind::detect(&self.url))
+    }
+}
+
+/// Retry settings for Cosmos DB requests (`retry:` in the config)
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct RetryConfig {
+    /// Total attempts including the first (1 disables retries)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_attempts: Option<u32>,
+
+    /// Delay before the first retry in milliseconds; doubled for each further retry
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub base_backoff_ms: Option<u64>,
+
+    /// Upper bound for a single retry delay in milliseconds
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_backoff_ms: Option<u64>,
+
+    /// Failures to retry: `429`, `408`, `5xx`, `connection`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub retry_on: Option<Vec<String>>,
+}
+
+/// How cosq acquires Entra ID (AAD) tokens
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum AuthMethod {
+    /// `az account get-access-token` from a signed-in Azure CLI
+    AzureCli,
+    /// Exchange a federated workload identity token (GitHub Actions OIDC or
+    /// `AZURE_FEDERATED_TOKEN_FILE`) for an Entra ID token, without the Azure CLI
+    WorkloadIdentity,
+}
+
+/// HTTP client settings for every outgoing request (`http:` in the config)
+///
+/// Without `proxy`, the `HTTPS_PROXY`/`HTTP_PROXY`/`NO_PROXY` environment
+/// variables apply.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct HttpConfig {
+    /// Proxy URL for all requests (e.g. `http://proxy.corp:8080`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub proxy: Option<String>,
+
+    /// Path to an extra PEM root certificate to trust (e.g. a TLS-inspecting proxy's)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ca_cert: Option<String>,
+
+    /// Timeout for a whole request, including reading the response, in seconds
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timeout_secs: Option<u64>,
+
+    /// Timeout for establishing a connection, in seconds
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub connect_timeout_secs: Option<u64>,
+}
+
+/// Settings for one container (`containers:` in the config)
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ContainerConfig {
+    /// Ask for confirmation (or `--force`) before SQL that is not a plain
+    /// SELECT, or a document write, is sent to this container
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub protected: bool,
+}
+
+/// Request unit pricing (`pricing:` in the config)
+///
+/// Set one of `per_million_ru` (serverless price per million RUs),
+/// `per_100_rus_hour` (provisioned price per 100 RU/s per hour, assuming full
+/// utilization), or `region` to use the serverless list price for that region.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct PricingConfig {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub per_million_ru: Option<f64>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub per_100_rus_hour: Option<f64>,
+
+    /// Azure region (e.g. `westeurope`) for the built-in serverless list price
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub region: Option<String>,
+
+    /// Currency code for display (default `USD`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub currency: Option<String>,
+}
+
+/// Approximate serverless list prices (USD per million RUs) by region
+const SERVERLESS_LIST_PRICES: &[(&str, f64)] = &[
+    ("centralus", 0.25),
+    ("eastus", 0.25),
+    ("eastus2", 0.25),
+    ("northcentralus", 0.25),
+    ("southcentralus", 0.25),
+    ("westus", 0.25),
+    ("westus2", 0.25),
+    ("westus3", 0.25),
+    ("northeurope", 0.25),
+    ("westeurope", 0.28),
+    ("swedencentral", 0.28),
+    ("uksouth", 0.28),
+    ("germanywestcentral", 0.30),
+    ("switzerlandnorth", 0.31),
+    ("japaneast", 0.32),
+    ("australiaeast", 0.32),
+];
+
+impl PricingConfig {
+    /// Price of one request unit, if enough pricing information is configured.
+    pub fn price_per_ru(&self) -> Option<f64> {
+        if let Some(price) = self.per_million_ru {
+            return Some(price / 1_000_000.0);
+        }
+        if let Some(price) = self.per_100_rus_hour {
+            // 100 RU/s for an hour is 360,000 RUs
+            return Some(price / 360_000.0);
+        }
+        let region = self
+            .region
+            .as_deref()?
+            .to_lowercase()
+            .replace([' ', '-'], "");
+        SERVERLESS_LIST_PRICES
+            .iter()
+            .find(|(r, _)| *r == region)
+            .map(|(_, price)| price / 1_000_000.0)
+    }
+
+    /// Estimated cost of `request_units`.
+    pub fn cost(&self, request_units: f64) -> Option<f64> {
+        self.price_per_ru().map(|p| p * request_units)
+    }
+
+    pub fn currency(&self) -> &str {
+        self.currency.as_deref().unwrap_or("USD")
+    }
+
+    /// Format a cost with the currency symbol, keeping two significant digits
+    /// for sub-cent amounts (e.g. `$0.0000031`).
+    pub fn format_cost(&self, cost: f64) -> String {
+        let amount = if cost == 0.0 || cost >= 0.01 {
+            format!("{cost:.2}")
+        } else {
+            let decimals = (-cost.log10()).ceil() as usize + 1;
+            format!("{cost:.decimals$}")
+        };
+        match self.currency().to_uppercase().as_str() {
+            "USD" => format!("${amount}"),
+            "EUR" => format!("€{amount}"),
+            "GBP" => format!("£{amount}"),
+            other => format!("{amount} {other}"),
+        }
+    }
+}
+
+/// Slow query warning thresholds (`slow_query:` in the config)
+///
+/// A run that exceeds either threshold prints a warning with its
+/// per-partition breakdown. Set a threshold to `0` to disable it.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SlowQueryConfig {
+    /// Request units (default 1000)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub warn_ru: Option<f64>,
+
+    /// Wall-clock seconds (default 30)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub warn_seconds: Option<f64>,
+}
+
+impl SlowQueryConfig {
+    pub const DEFAULT_WARN_RU: f64 = 1000.0;
+    pub const DEFAULT_WARN_SECONDS: f64 = 30.0;
+
+    pub fn warn_ru(&self) -> f64 {
+        self.warn_ru.unwrap_or(Self::DEFAULT_WARN_RU)
+    }
+
+    pub fn warn_seconds(&self) -> f64 {
+        self.warn_seconds.unwrap_or(Self::DEFAULT_WARN_SECONDS)
+    }
+
+    /// Whether a run with this charge and duration crosses a threshold.
+    pub fn is_slow(&self, request_charge: f64, seconds: f64) -> bool {
+        let over = |value: f64, limit: f64| limit > 0.0 && value >= limit;
+        over(request_charge, self.warn_ru()) || over(seconds, self.warn_seconds())
+    }
+}
+
+/// Whether cosq may write AI-generated content (generated queries, template
+/// fixes) to .cosq files (`ai_save:` in the config). A diff is always shown first.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AiSavePolicy {
+    /// Never write; the change is shown but not saved
+    Never,
+    /// Ask before each write (skipped when not interactive)
+    #[default]
+    Prompt,
+    /// Write without asking
+    Always,
+}
+
+/// AI token budget and pricing (`ai_budget:` in the config)
+///
+/// Token counts come from provider responses; prices are per million tokens
+/// and only used for estimates.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct AiBudgetConfig {
+    /// Warn once a calendar month's prompt + completion tokens exceed this
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub monthly_tokens: Option<u64>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub per_million_prompt_tokens: Option<f64>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub per_million_completion_tokens: Option<f64>,
+
+    /// Currency code for display (default `USD`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub currency: Option<String>,
+}
+
+impl AiBudgetConfig {
+    /// Estimated cost of the given token counts, if any price is configured.
+    pub fn cost(&self, prompt_tokens: u64, completion_tokens: u64) -> Option<f64> {
+        if self.per_million_prompt_tokens.is_none() && self.per_million_completion_tokens.is_none()
+        {
+            return None;
+        }
+        let price = |per_million: Option<f64>, tokens: u64| {
+            per_million.unwrap_or(0.0) * tokens as f64 / 1_000_000.0
+        };
+        Some(
+            price(self.per_million_prompt_tokens, prompt_tokens)
+                + price(self.per_million_completion_tokens, completion_tokens),
+        )
+    }
+
+    /// Format a cost in the configured currency.
+    pub fn format_cost(&self, cost: f64) -> String {
+        PricingConfig {
+            currency: self.currency.clone(),
+            ..Default::default()
+        }
+        .format_cost(cost)
+    }
+
+    /// Whether `tokens` used this month exceed the budget.
+    pub fn is_over(&self, tokens: u64) -> bool {
+        self.monthly_tokens.is_some_and(|limit| tokens > limit)
+    }
+}
+
+/// Settings for the AI provider (`ai:` in the config)
+///
+/// The provider itself is configured in ailloy; these override it for cosq.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct AiConfig {
+    /// Azure OpenAI API key (usually `keyring:<name>`), sent instead of an
+    /// Entra ID token for deployments where the user has a key but no RBAC role
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub api_key: Option<String>,
+}
+
+/// How much document data may go into AI prompts (`ai_data:` in the config)
+///
+/// Applies on top of the `redact:` patterns, which are always masked.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct AiDataConfig {
+    /// Sample documents per prompt (default 3)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_documents: Option<usize>,
+
+    /// Top-level fields kept per document (default 50)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_fields: Option<usize>,
+
+    /// Never send document values, only field names and types (like `--no-ai-data`)
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub schema_only: bool,
+}
+
+impl AiDataConfig {
+    pub const DEFAULT_MAX_DOCUMENTS: usize = 3;
+    pub const DEFAULT_MAX_FIELDS: usize = 50;
+
+    pub fn max_documents(&self) -> usize {
+        self.max_documents.unwrap_or(Self::DEFAULT_MAX_DOCUMENTS)
+    }
+
+    pub fn max_fields(&self) -> usize {
+        self.max_fields.unwrap_or(Self::DEFAULT_MAX_FIELDS)
+    }
+
+    /// Drop documents and top-level fields beyond the limits, noting how many
+    /// fields were left out of each document.
+    pub fn limit(&self, documents: &mut Vec<serde_json::Value>) {
+        documents.truncate(self.max_documents());
+        let max_fields = self.max_fields();
+        for document in documents.iter_mut() {
+            if let serde_json::Value::Object(map) = document {
+                if map.len() > max_fields {
+                    let omitted = map.len() - max_fields;
+                    *map = std::mem::take(map).into_iter().take(max_fields).collect();
+                    map.insert(
+                        "…".to_string(),
+                        serde_json::Value::String(format!("{omitted} more fields omitted")),
+                    );
+                }
+            }
+        }
+    }
+}
+
+/// Top-level cosq configuration
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct Config {
     /// Cosmos DB account details
     pub account: AccountConfig,
@@ -58,9 +470,188 @@ pub struct Config {
     /// Default container name
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub container: Option<String>,
+
+    /// Named connection profiles for additional accounts
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub profiles: BTreeMap<String, ProfileConfig>,
+
+    /// Named Slack/Teams incoming webhooks for `--notify`
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub webhooks: BTreeMap<String, WebhookConfig>,
+
+    /// Azure Storage account for `--out azblob://...` destinations
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub storage_account: Option<String>,
+
+    /// Retry behavior for Cosmos DB requests (defaults apply when unset)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub retry: Option<RetryConfig>,
+
+    /// Request unit pricing used to show an estimated cost next to RU charges
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pricing: Option<PricingConfig>,
+
+    /// Field patterns (`*.email`, `c.ssn`) masked before data is sent to AI
+    /// providers, and in output with `--redact`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub redact: Vec<String>,
+
+    /// Thresholds for the slow query warning
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub slow_query: Option<SlowQueryConfig>,
+
+    /// `default_cmd:` commands of stored query parameters that run without confirmation
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub trusted_commands: Vec<String>,
+
+    /// When AI-generated changes may be written to .cosq files (default: prompt)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ai_save: Option<AiSavePolicy>,
+
+    /// Monthly AI token budget and token prices for `cosq ai usage`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ai_budget: Option<AiBudgetConfig>,
+
+    /// Keep transcripts of AI requests under `~/.cosq/ai-logs/` (see `cosq ai log`)
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub ai_log: bool,
+
+    /// Limits on the document data included in AI prompts
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ai_data: Option<AiDataConfig>,
+
+    /// AI provider overrides (e.g. an Azure OpenAI API key)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ai: Option<AiConfig>,
+
+    /// Output format for `query`/`run` when no `-o` is given and the query
+    /// sets none (json, json-compact, table, csv)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub output: Option<String>,
+
+    /// Proxy, extra CA certificate and timeouts for all outgoing requests
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub http: Option<HttpConfig>,
+
+    /// How Entra ID tokens are acquired (default: detected from the environment)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub auth: Option<AuthMethod>,
+
+    /// Per-container settings, keyed by `<container>`, `<database>/<container>`
+    /// or `<account>/<database>/<container>`
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub containers: BTreeMap<String, ContainerConfig>,
 }
 
 impl Config {
+    /// Return a config that targets the named profile instead of the default account.
+    pub fn for_profile(&self, name: &str) -> Result<Config, ConfigError> {
+        let profile = self
+            .profiles
+            .get(name)
+            .ok_or_else(|| ConfigError::ProfileNotFound(name.to_string()))?;
+        Ok(Config {
+            account: profile.account.clone(),
+            database: profile.database.clone(),
+            container: profile.container.clone(),
+            profiles: BTreeMap::new(),
+            webhooks: self.webhooks.clone(),
+            storage_account: self.storage_account.clone(),
+            retry: self.retry.clone(),
+            pricing: self.pricing.clone(),
+            redact: self.redact.clone(),
+            slow_query: self.slow_query.clone(),
+            trusted_commands: self.trusted_commands.clone(),
+            ai_save: self.ai_save,
+            ai_budget: self.ai_budget.clone(),
+            ai_log: self.ai_log,
+            ai_data: self.ai_data.clone(),
+            ai: self.ai.clone(),
+            output: self.output.clone(),
+            http: self.http.clone(),
+            auth: self.auth,
+            containers: self.containers.clone(),
+        })
+    }
+
+    /// Return a config that targets the named Cosmos DB account.
+    ///
+    /// Matches the default account first, then profiles by account name. Unknown
+    /// accounts use the standard `https://<name>.documents.azure.com:443/` endpoint.
+    pub fn for_account(&self, name: &str) -> Config {
+        if self.account.name == name {
+            return self.clone();
+        }
+        if let Some(profile) = self.profiles.values().find(|p| p.account.name == name) {
+            return Config {
+                account: profile.account.clone(),
+                database: profile.database.clone(),
+                container: profile.container.clone(),
+                profiles: BTreeMap::new(),
+                webhooks: self.webhooks.clone(),
+                storage_account: self.storage_account.clone(),
+                retry: self.retry.clone(),
+                pricing: self.pricing.clone(),
+                redact: self.redact.clone(),
+                slow_query: self.slow_query.clone(),
+                trusted_commands: self.trusted_commands.clone(),
+                ai_save: self.ai_save,
+                ai_budget: self.ai_budget.clone(),
+                ai_log: self.ai_log,
+                ai_data: self.ai_data.clone(),
+                ai: self.ai.clone(),
+                output: self.output.clone(),
+                http: self.http.clone(),
+                auth: self.auth,
+                containers: self.containers.clone(),
+            };
+        }
+        Config {
+            account: AccountConfig {
+                name: name.to_string(),
+                subscription: String::new(),
+                resource_group: String::new(),
+                endpoint: format!("https://{name}.documents.azure.com:443/"),
+                master_key: None,
+                ca_cert: None,
+                capacity_mode: None,
+            },
+            database: None,
+            container: None,
+            profiles: BTreeMap::new(),
+            webhooks: self.webhooks.clone(),
+            storage_account: self.storage_account.clone(),
+            retry: self.retry.clone(),
+            pricing: self.pricing.clone(),
+            redact: self.redact.clone(),
+            slow_query: self.slow_query.clone(),
+            trusted_commands: self.trusted_commands.clone(),
+            ai_save: self.ai_save,
+            ai_budget: self.ai_budget.clone(),
+            ai_log: self.ai_log,
+            ai_data: self.ai_data.clone(),
+            ai: self.ai.clone(),
+            output: self.output.clone(),
+            http: self.http.clone(),
+            auth: self.auth,
+            containers: self.containers.clone(),
+        }
+    }
+
+    /// Whether `containers:` marks this container as protected. The most
+    /// specific key wins (`<account>/<database>/<container>`, then
+    /// `<database>/<container>`, then `<container>`).
+    pub fn is_protected(&self, database: &str, container: &str) -> bool {
+        [
+            format!("{}/{database}/{container}", self.account.name),
+            format!("{database}/{container}"),
+            container.to_string(),
+        ]
+        .iter()
+        .find_map(|key| self.containers.get(key))
+        .is_some_and(|c| c.protected)
+    }
+
     /// Return the path to the config file: `<config_dir>/cosq/config.yaml`.
     pub fn path() -> Result<PathBuf, ConfigError> {
         dirs::config_dir()
@@ -127,9 +718,29 @@ mod tests {
                 subscription: "sub-123".into(),
                 resource_group: "rg-test".into(),
                 endpoint: "https://test-account.documents.azure.com:443/".into(),
+                master_key: None,
+                ca_cert: None,
+                capacity_mode: None,
             },
             database: None,
             container: None,
+            profiles: BTreeMap::new(),
+            webhooks: BTreeMap::new(),
+            storage_account: None,
+            retry: None,
+            pricing: None,
+            redact: Vec::new(),
+            slow_query: None,
+            trusted_commands: Vec::new(),
+            ai_save: None,
+            ai_budget: None,
+            ai_log: false,
+            ai_data: None,
+            ai: None,
+            output: None,
+            http: None,
+            auth: None,
+            containers: BTreeMap::new(),
         };
 
         let yaml = serde_yaml::to_string(&config).unwrap();
@@ -151,9 +762,29 @@ mod tests {
                 subscription: "sub-123".into(),
                 resource_group: "rg-test".into(),
                 endpoint: "https://test-account.documents.azure.com:443/".into(),
+                master_key: None,
+                ca_cert: None,
+                capacity_mode: None,
             },
             database: Some("mydb".into()),
             container: Some("users".into()),
+            profiles: BTreeMap::new(),
+            webhooks: BTreeMap::new(),
+            storage_account: None,
+            retry: None,
+            pricing: None,
+            redact: Vec::new(),
+            slow_query: None,
+            trusted_commands: Vec::new(),
+            ai_save: None,
+            ai_budget: None,
+            ai_log: false,
+            ai_data: None,
+            ai: None,
+            output: None,
+            http: None,
+            auth: None,
+            containers: BTreeMap::new(),
         };
 
         let yaml = serde_yaml::to_string(&config).unwrap();
@@ -185,9 +816,29 @@ account:
                 subscription: "sub".into(),
                 resource_group: "rg".into(),
                 endpoint: "https://test.documents.azure.com:443/".into(),
+                master_key: None,
+                ca_cert: None,
+                capacity_mode: None,
             },
             database: None,
             container: None,
+            profiles: BTreeMap::new(),
+            webhooks: BTreeMap::new(),
+            storage_account: None,
+            retry: None,
+            pricing: None,
+            redact: Vec::new(),
+            slow_query: None,
+            trusted_commands: Vec::new(),
+            ai_save: None,
+            ai_budget: None,
+            ai_log: false,
+            ai_data: None,
+            ai: None,
+            output: None,
+            http: None,
+            auth: None,
+            containers: BTreeMap::new(),
         };
 
         let yaml = serde_yaml::to_string(&config).unwrap();
@@ -205,9 +856,29 @@ account:
                 subscription: "sub-abc".into(),
                 resource_group: "rg-prod".into(),
                 endpoint: "https://my-cosmos.documents.azure.com:443/".into(),
+                master_key: None,
+                ca_cert: None,
+                capacity_mode: None,
             },
             database: Some("testdb".into()),
             container: None,
+            profiles: BTreeMap::new(),
+            webhooks: BTreeMap::new(),
+            storage_account: None,
+            retry: None,
+            pricing: None,
+            redact: Vec::new(),
+            slow_query: None,
+            trusted_commands: Vec::new(),
+            ai_save: None,
+            ai_budget: None,
+            ai_log: false,
+            ai_data: None,
+            ai: None,
+            output: None,
+            http: None,
+            auth: None,
+            containers: BTreeMap::new(),
         };
 
         config.save_to(&path).unwrap();
@@ -219,6 +890,281 @@ account:
         assert!(loaded.container.is_none());
     }
 
+    #[test]
+    fn test_config_profiles() {
+        let yaml = r#"
+account:
+  name: main
+  subscription: sub-main
+  resource_group: rg-main
+  endpoint: https://main.documents.azure.com:443/
+database: appdb
+profiles:
+  analytics:
+    account:
+      name: analytics-cosmos
+      subscription: sub-an
+      resource_group: rg-an
+      endpoint: https://analytics-cosmos.documents.azure.com:443/
+    database: warehouse
+"#;
+        let config: Config = serde_yaml::from_str(yaml).unwrap();
+
+        let analytics = config.for_profile("analytics").unwrap();
+        assert_eq!(analytics.account.name, "analytics-cosmos");
+        assert_eq!(analytics.database.as_deref(), Some("warehouse"));
+        assert!(matches!(
+            config.for_profile("missing"),
+            Err(ConfigError::ProfileNotFound(_))
+        ));
+
+        assert_eq!(
+            config.for_account("main").database.as_deref(),
+            Some("appdb")
+        );
+        assert_eq!(
+            config.for_account("analytics-cosmos").database.as_deref(),
+            Some("warehouse")
+        );
+        let other = config.for_account("other");
+        assert_eq!(
+            other.account.endpoint,
+            "https://other.documents.azure.com:443/"
+        );
+        assert!(other.database.is_none());
+    }
+
+    #[test]
+    fn test_config_retry() {
+        let yaml = r#"
+account:
+  name: main
+  subscription: sub-main
+  resource_group: rg-main
+  endpoint: https://main.documents.azure.com:443/
+retry:
+  max_attempts: 6
+  retry_on: ["429", "5xx"]
+"#;
+        let config: Config = serde_yaml::from_str(yaml).unwrap();
+        let retry = config.retry.as_ref().unwrap();
+        assert_eq!(retry.max_attempts, Some(6));
+        assert!(retry.base_backoff_ms.is_none());
+        assert_eq!(retry.retry_on.as_ref().unwrap().len(), 2);
+        // Profiles inherit the retry settings
+        assert_eq!(config.for_account("other").retry, config.retry);
+    }
+
+    #[test]
+    fn test_pricing() {
+        let serverless = PricingConfig {
+            per_million_ru: Some(0.25),
+            ..Default::default()
+        };
+        assert_eq!(serverless.cost(2_000_000.0), Some(0.5));
+        assert_eq!(serverless.format_cost(0.5), "$0.50");
+        assert_eq!(serverless.format_cost(0.0000031), "$0.0000031");
+
+        let provisioned = PricingConfig {
+            per_100_rus_hour: Some(0.008),
+            currency: Some("eur".into()),
+            ..Default::default()
+        };
+        let cost = provisioned.cost(360_000.0).unwrap();
+        assert!((cost - 0.008).abs() < 1e-12);
+        assert_eq!(provisioned.format_cost(0.008), "€0.0080");
+
+        let by_region = PricingConfig {
+            region: Some("West Europe".into()),
+            ..Default::default()
+        };
+        assert!(by_region.price_per_ru().is_some());
+        let unknown = PricingConfig {
+            region: Some("mars".into()),
+            ..Default::default()
+        };
+        assert!(unknown.price_per_ru().is_none());
+    }
+
+    #[test]
+    fn test_slow_query() {
+        let defaults = SlowQueryConfig::default();
+        assert!(!defaults.is_slow(999.0, 29.0));
+        assert!(defaults.is_slow(1000.0, 1.0));
+        assert!(defaults.is_slow(1.0, 30.0));
+
+        let config: SlowQueryConfig =
+            serde_yaml::from_str("warn_ru: 500\nwarn_seconds: 0").unwrap();
+        assert!(config.is_slow(500.0, 0.0));
+        // 0 disables the time threshold
+        assert!(!config.is_slow(10.0, 3600.0));
+    }
+
+    #[test]
+    fn test_capacity_mode() {
+        let yaml = "account:\n  name: a\n  subscription: s\n  resource_group: rg\n  endpoint: https://a.documents.azure.com:443/\n";
+        let mut config: Config = serde_yaml::from_str(yaml).unwrap();
+        assert_eq!(config.account.capacity_mode, None);
+        assert!(
+            !serde_yaml::to_string(&config)
+                .unwrap()
+                .contains("capacity_mode")
+        );
+
+        config.account.capacity_mode = Some(CapacityMode::Serverless);
+        let saved = serde_yaml::to_string(&config).unwrap();
+        assert!(saved.contains("capacity_mode: serverless"));
+        let config: Config = serde_yaml::from_str(&saved).unwrap();
+        assert_eq!(config.account.capacity_mode, Some(CapacityMode::Serverless));
+    }
+
+    #[test]
+    fn test_ai_save_policy() {
+        let yaml = "account:\n  name: a\n  subscription: s\n  resource_group: rg\n  endpoint: https://a.documents.azure.com:443/\n";
+        let config: Config = serde_yaml::from_str(yaml).unwrap();
+        assert_eq!(config.ai_save.unwrap_or_default(), AiSavePolicy::Prompt);
+
+        let config: Config = serde_yaml::from_str(&format!("{yaml}ai_save: never\n")).unwrap();
+        assert_eq!(config.ai_save, Some(AiSavePolicy::Never));
+        assert_eq!(
+            config.for_account("other").ai_save,
+            Some(AiSavePolicy::Never)
+        );
+    }
+
+    #[test]
+    fn test_ai_api_key() {
+        let yaml = "account:\n  name: a\n  subscription: s\n  resource_group: rg\n  endpoint: https://a.documents.azure.com:443/\nai:\n  api_key: keyring:azure-openai\n";
+        let config: Config = serde_yaml::from_str(yaml).unwrap();
+        let ai = config.for_account("other").ai.unwrap();
+        assert_eq!(ai.api_key.as_deref(), Some("keyring:azure-openai"));
+        assert!(
+            serde_yaml::to_string(&config)
+                .unwrap()
+                .contains("api_key: keyring:azure-openai")
+        );
+    }
+
+    #[test]
+    fn test_output_default() {
+        let yaml = "account:\n  name: a\n  subscription: s\n  resource_group: rg\n  endpoint: https://a.documents.azure.com:443/\noutput: table\nprofiles:\n  b:\n    account:\n      name: b\n      subscription: s\n      resource_group: rg\n      endpoint: https://b.documents.azure.com:443/\n";
+        let config: Config = serde_yaml::from_str(yaml).unwrap();
+        assert_eq!(config.output.as_deref(), Some("table"));
+        assert_eq!(
+            config.for_profile("b").unwrap().output.as_deref(),
+            Some("table")
+        );
+        assert!(
+            serde_yaml::to_string(&config)
+                .unwrap()
+                .contains("output: table")
+        );
+    }
+
+    #[test]
+    fn test_http_config() {
+        let yaml = "account:\n  name: a\n  subscription: s\n  resource_group: rg\n  endpoint: https://a.documents.azure.com:443/\nhttp:\n  proxy: http://proxy.corp:8080\n  timeout_secs: 120\n";
+        let config: Config = serde_yaml::from_str(yaml).unwrap();
+        let http = config.http.as_ref().unwrap();
+        assert_eq!(http.proxy.as_deref(), Some("http://proxy.corp:8080"));
+        assert_eq!(http.timeout_secs, Some(120));
+        assert_eq!(http.ca_cert, None);
+        let saved = serde_yaml::to_string(&config).unwrap();
+        assert!(saved.contains("timeout_secs: 120"));
+        assert!(!saved.contains("connect_timeout_secs"));
+    }
+
+    #[test]
+    fn test_auth_method() {
+        let yaml = "account:\n  name: a\n  subscription: s\n  resource_group: rg\n  endpoint: https://a.documents.azure.com:443/\nauth: workload-identity\n";
+        let config: Config = serde_yaml::from_str(yaml).unwrap();
+        assert_eq!(config.auth, Some(AuthMethod::WorkloadIdentity));
+        assert_eq!(config.for_account("other").auth, config.auth);
+        assert!(
+            serde_yaml::to_string(&config)
+                .unwrap()
+                .contains("auth: workload-identity")
+        );
+    }
+
+    #[test]
+    fn test_is_protected() {
+        let yaml = "account:\n  name: prod\n  subscription: s\n  resource_group: rg\n  endpoint: https://prod.documents.azure.com:443/\ncontainers:\n  orders:\n    protected: true\n  app/users:\n    protected: true\n  prod/app/orders:\n    protected: false\n";
+        let config: Config = serde_yaml::from_str(yaml).unwrap();
+        assert!(config.is_protected("shop", "orders"));
+        assert!(config.is_protected("app", "users"));
+        assert!(!config.is_protected("other", "users"));
+        // The account-qualified key overrides the bare container name
+        assert!(!config.is_protected("app", "orders"));
+        assert!(!config.is_protected("app", "events"));
+    }
+
+    #[test]
+    fn test_ai_budget() {
+        let budget: AiBudgetConfig = serde_yaml::from_str(
+            "monthly_tokens: 1000\nper_million_prompt_tokens: 3\nper_million_completion_tokens: 15",
+        )
+        .unwrap();
+        let cost = budget.cost(1_000_000, 100_000).unwrap();
+        assert!((cost - 4.5).abs() < 1e-9);
+        assert_eq!(budget.format_cost(cost), "$4.50");
+        assert!(!budget.is_over(1000));
+        assert!(budget.is_over(1001));
+        assert_eq!(AiBudgetConfig::default().cost(10, 10), None);
+    }
+
+    #[test]
+    fn test_ai_data_limit() {
+        let mut documents = vec![
+            serde_json::json!({"a": 1, "b": 2, "c": 3}),
+            serde_json::json!({"a": 1}),
+            serde_json::json!({"a": 1}),
+        ];
+        let limits = AiDataConfig {
+            max_documents: Some(2),
+            max_fields: Some(2),
+            schema_only: false,
+        };
+        limits.limit(&mut documents);
+        assert_eq!(documents.len(), 2);
+        assert_eq!(
+            documents[0],
+            serde_json::json!({"a": 1, "b": 2, "…": "1 more fields omitted"})
+        );
+        assert_eq!(documents[1], serde_json::json!({"a": 1}));
+        assert_eq!(AiDataConfig::default().max_documents(), 3);
+    }
+
+    #[test]
+    fn test_webhook_kind() {
+        assert_eq!(
+            WebhookKind::detect("https://hooks.slack.com/services/T0/B0/x"),
+            WebhookKind::Slack
+        );
+        assert_eq!(
+            WebhookKind::detect("https://contoso.webhook.office.com/webhookb2/abc"),
+            WebhookKind::Teams
+        );
+
+        let yaml = r#"
+account:
+  name: a
+  subscription: s
+  resource_group: rg
+  endpoint: https://a.documents.azure.com:443/
+webhooks:
+  ops:
+    url: https://example.com/hook
+    kind: teams
+"#;
+        let config: Config = serde_yaml::from_str(yaml).unwrap();
+        assert_eq!(config.webhooks["ops"].kind(), WebhookKind::Teams);
+        assert_eq!(
+            config.for_account("other").webhooks["ops"].url,
+            "https://example.com/hook"
+        );
+    }
+
     #[test]
     fn test_config_load_not_found() {
         let dir = tempfile::tempdir().unwrap();
@@ -237,9 +1183,29 @@ account:
                 subscription: "sub".into(),
                 resource_group: "rg".into(),
                 endpoint: "https://test.documents.azure.com:443/".into(),
+                master_key: None,
+                ca_cert: None,
+                capacity_mode: None,
             },
             database: None,
             container: None,
+            profiles: BTreeMap::new(),
+            webhooks: BTreeMap::new(),
+            storage_account: None,
+            retry: None,
+            pricing: None,
+            redact: Vec::new(),
+            slow_query: None,
+            trusted_commands: Vec::new(),
+            ai_save: None,
+            ai_budget: None,
+            ai_log: false,
+            ai_data: None,
+            ai: None,
+            output: None,
+            http: None,
+            auth: None,
+            containers: BTreeMap::new(),
         };
 
         config.save_to(&path).unwrap();
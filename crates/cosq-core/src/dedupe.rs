@@ -0,0 +1,127 @@
+//! Duplicate document detection for `cosq dedupe`
+//!
+//! Reads the id, `_ts`, partition key and one or more key expressions (e.g.
+//! `c.email`) of every document, groups the rows client-side by the key
+//! values and keeps the newest document of each group (highest `_ts`).
+//! Documents where a key expression is undefined are never duplicates.
+
+use std::collections::BTreeMap;
+
+use serde_json::Value;
+
+use crate::key_distribution::path_to_sql;
+
+/// Documents sharing the same key values
+#[derive(Debug, Clone, PartialEq)]
+pub struct DuplicateGroup {
+    /// The key values, as compact JSON joined by ` / `
+    pub key: String,
+    /// The newest document (kept)
+    pub keep: DuplicateDoc,
+    /// The older documents (deleted with `--delete`), newest first
+    pub remove: Vec<DuplicateDoc>,
+}
+
+/// One document of a [`DuplicateGroup`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct DuplicateDoc {
+    pub id: String,
+    /// Last modified, in seconds since the epoch (`_ts`)
+    pub ts: i64,
+    /// Partition key value (`{}` when the document has none)
+    pub partition_key: Value,
+}
+
+/// SQL reading what [`find_duplicates`] needs: `id`, `_ts`, the partition key
+/// as `pk` and each of `by` as `k0`, `k1`, ...
+pub fn dedupe_sql(by: &[String], partition_key_path: &str) -> String {
+    let keys = by
+        .iter()
+        .enumerate()
+        .map(|(i, expr)| format!("{} AS k{i}", expr.trim()))
+        .collect::<Vec<_>>()
+        .join(", ");
+    format!(
+        "SELECT c.id, c._ts, {} AS pk, {keys} FROM c",
+        path_to_sql(partition_key_path)
+    )
+}
+
+/// Group rows of [`dedupe_sql`] by their key values and return the groups
+/// with more than one document, largest first.
+pub fn find_duplicates(rows: &[Value], keys: usize) -> Vec<DuplicateGroup> {
+    let mut groups: BTreeMap<String, Vec<DuplicateDoc>> = BTreeMap::new();
+    for row in rows {
+        let values: Option<Vec<String>> = (0..keys)
+            .map(|i| row.get(format!("k{i}")).map(Value::to_string))
+            .collect();
+        let (Some(values), Some(id)) = (values, row.get("id").and_then(Value::as_str)) else {
+            continue;
+        };
+        groups
+            .entry(values.join(" / "))
+            .or_default()
+            .push(DuplicateDoc {
+                id: id.to_string(),
+                ts: row.get("_ts").and_then(Value::as_i64).unwrap_or_default(),
+                partition_key: row
+                    .get("pk")
+                    .cloned()
+                    .unwrap_or_else(|| Value::Object(Default::default())),
+            });
+    }
+
+    let mut duplicates: Vec<DuplicateGroup> = groups
+        .into_iter()
+        .filter(|(_, docs)| docs.len() > 1)
+        .map(|(key, mut docs)| {
+            // Newest first; the id breaks ties so the result is stable
+            docs.sort_by(|a, b| b.ts.cmp(&a.ts).then_with(|| a.id.cmp(&b.id)));
+            let keep = docs.remove(0);
+            DuplicateGroup {
+                key,
+                keep,
+                remove: docs,
+            }
+        })
+        .collect();
+    duplicates.sort_by_key(|group| std::cmp::Reverse(group.remove.len()));
+    duplicates
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_dedupe_sql() {
+        assert_eq!(
+            dedupe_sql(&["c.email".into(), " LOWER(c.name) ".into()], "/tenant"),
+            r#"SELECT c.id, c._ts, c["tenant"] AS pk, c.email AS k0, LOWER(c.name) AS k1 FROM c"#
+        );
+    }
+
+    #[test]
+    fn test_find_duplicates() {
+        let rows = vec![
+            json!({"id": "1", "_ts": 100, "pk": "t1", "k0": "a@x.com"}),
+            json!({"id": "2", "_ts": 300, "pk": "t1", "k0": "a@x.com"}),
+            json!({"id": "3", "_ts": 200, "pk": "t2", "k0": "a@x.com"}),
+            json!({"id": "4", "_ts": 100, "pk": "t1", "k0": "b@x.com"}),
+            json!({"id": "5", "_ts": 100, "k0": "c@x.com"}),
+            json!({"id": "6", "_ts": 200, "k0": "c@x.com"}),
+            // No key: never a duplicate
+            json!({"id": "7", "_ts": 100, "pk": "t1"}),
+            json!({"id": "8", "_ts": 100, "pk": "t1"}),
+        ];
+        let groups = find_duplicates(&rows, 1);
+        assert_eq!(groups.len(), 2);
+        assert_eq!(groups[0].key, r#""a@x.com""#);
+        assert_eq!(groups[0].keep.id, "2");
+        let removed: Vec<&str> = groups[0].remove.iter().map(|d| d.id.as_str()).collect();
+        assert_eq!(removed, ["3", "1"]);
+        assert_eq!(groups[1].keep.id, "6");
+        assert_eq!(groups[1].remove[0].partition_key, json!({}));
+    }
+}
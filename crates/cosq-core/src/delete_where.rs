@@ -0,0 +1,205 @@
+//! Document selection for `cosq docs delete-where`
+//!
+//! The user's SELECT is rewritten to read only what a delete needs — the id
+//! and the partition key value of each matching document — keeping its
+//! FROM, JOIN, WHERE, ORDER BY and `TOP`. Rows are then reduced to unique
+//! (id, partition key) pairs, since a JOIN returns a document once per
+//! matching array element.
+
+use std::collections::BTreeSet;
+
+use regex::Regex;
+use serde_json::{Map, Value};
+use thiserror::Error;
+
+use crate::key_distribution::path_to_sql;
+use crate::lint::{SqlShape, sql_shape, strip_comments};
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum DeleteWhereError {
+    #[error("only a single SELECT query can select documents to delete")]
+    NotSelect,
+
+    #[error("the query has no FROM clause")]
+    NoFrom,
+
+    #[error("`FROM ... IN` iterates array elements, not documents; use a JOIN instead")]
+    Iteration,
+
+    #[error("GROUP BY returns groups, not documents")]
+    GroupBy,
+}
+
+/// A document to delete
+#[derive(Debug, Clone, PartialEq)]
+pub struct DeleteTarget {
+    pub id: String,
+    /// Partition key value (`{}` when the document has none)
+    pub partition_key: Value,
+}
+
+/// Keywords that can follow the FROM source instead of an alias
+const CLAUSE_KEYWORDS: &[&str] = &["WHERE", "JOIN", "ORDER", "GROUP", "OFFSET", "AS"];
+
+/// Rewrite `sql` to select `id` and the partition key value (as `pk`) of
+/// every document it matches.
+pub fn delete_where_sql(sql: &str, partition_key_path: &str) -> Result<String, DeleteWhereError> {
+    if sql_shape(sql) != SqlShape::Select {
+        return Err(DeleteWhereError::NotSelect);
+    }
+    let sql = strip_comments(sql);
+    let sql = sql.trim().trim_end_matches(';').trim_end();
+    let from = top_level_keyword(sql, "FROM").ok_or(DeleteWhereError::NoFrom)?;
+    if top_level_keyword(sql, "GROUP").is_some() {
+        return Err(DeleteWhereError::GroupBy);
+    }
+
+    let mut words = sql[from + 4..]
+        .split(|c: char| c.is_whitespace() || c == '(' || c == ')')
+        .filter(|w| !w.is_empty());
+    let source = words.next().ok_or(DeleteWhereError::NoFrom)?;
+    let alias = match words.next() {
+        Some(word) if word.eq_ignore_ascii_case("IN") => return Err(DeleteWhereError::Iteration),
+        Some(word) if word.eq_ignore_ascii_case("AS") => words.next().unwrap_or(source),
+        Some(word) if !CLAUSE_KEYWORDS.iter().any(|k| word.eq_ignore_ascii_case(k)) => word,
+        _ => source,
+    };
+    if words.next().is_some_and(|w| w.eq_ignore_ascii_case("IN")) {
+        return Err(DeleteWhereError::Iteration);
+    }
+
+    let top = Regex::new(r"(?i)^\s*SELECT\s+(?:DISTINCT\s+)?(TOP\s+\S+\s+)")
+        .unwrap()
+        .captures(&sql[..from])
+        .map(|c| c[1].to_string())
+        .unwrap_or_default();
+    let partition_key = path_to_sql(partition_key_path);
+    Ok(format!(
+        "SELECT {top}{alias}.id, {alias}{} AS pk {}",
+        &partition_key[1..],
+        &sql[from..]
+    ))
+}
+
+/// The unique documents among rows of [`delete_where_sql`], in order.
+pub fn delete_targets(rows: &[Value]) -> Vec<DeleteTarget> {
+    let mut seen = BTreeSet::new();
+    rows.iter()
+        .filter_map(|row| {
+            let id = row.get("id")?.as_str()?.to_string();
+            let partition_key = row
+                .get("pk")
+                .cloned()
+                .unwrap_or_else(|| Value::Object(Map::new()));
+            seen.insert((id.clone(), partition_key.to_string()))
+                .then_some(DeleteTarget { id, partition_key })
+        })
+        .collect()
+}
+
+/// Byte offset of `keyword` as a whole word outside string literals and
+/// parentheses (so subqueries don't count).
+fn top_level_keyword(sql: &str, keyword: &str) -> Option<usize> {
+    let bytes = sql.as_bytes();
+    let (mut quote, mut depth) = (None, 0usize);
+    // Bytes of non-ASCII characters count as word bytes, as in `c.éfrom`
+    let is_word = |b: u8| b.is_ascii_alphanumeric() || b == b'_' || !b.is_ascii();
+    for (i, &b) in bytes.iter().enumerate() {
+        match (quote, b) {
+            (Some(q), b) if b == q => quote = None,
+            (Some(_), _) => {}
+            (None, b'\'' | b'"') => quote = Some(b),
+            (None, b'(') => depth += 1,
+            (None, b')') => depth = depth.saturating_sub(1),
+            (None, _) if depth == 0 => {
+                let end = i + keyword.len();
+                if end <= bytes.len()
+                    && bytes[i..end].eq_ignore_ascii_case(keyword.as_bytes())
+                    // `c.group` is a property, not the keyword
+                    && (i == 0 || !(is_word(bytes[i - 1]) || bytes[i - 1] == b'.'))
+                    && bytes.get(end).is_none_or(|&b| !is_word(b))
+                {
+                    return Some(i);
+                }
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_delete_where_sql() {
+        assert_eq!(
+            delete_where_sql("SELECT * FROM c WHERE c.status = 'bad'", "/customerId").unwrap(),
+            r#"SELECT c.id, c["customerId"] AS pk FROM c WHERE c.status = 'bad'"#
+        );
+        assert_eq!(
+            delete_where_sql(
+                "select distinct top 10 VALUE o.total from orders o\n\
+                 JOIN i IN o.items WHERE i.sku = 'from x' -- old rows\nORDER BY o._ts;",
+                "/tenant/id"
+            )
+            .unwrap(),
+            "SELECT top 10 o.id, o[\"tenant\"][\"id\"] AS pk from orders o\n\
+             JOIN i IN o.items WHERE i.sku = 'from x' \nORDER BY o._ts"
+        );
+        assert_eq!(
+            delete_where_sql(
+                "SELECT (SELECT VALUE 1 FROM x IN r.tags) FROM root AS r",
+                "/pk"
+            )
+            .unwrap(),
+            r#"SELECT r.id, r["pk"] AS pk FROM root AS r"#
+        );
+        assert_eq!(
+            delete_where_sql(
+                "SELECT c.prénom FROM c WHERE c.code = 'AB--1' AND c.ville = 'Zürich'",
+                "/pk"
+            )
+            .unwrap(),
+            r#"SELECT c.id, c["pk"] AS pk FROM c WHERE c.code = 'AB--1' AND c.ville = 'Zürich'"#
+        );
+    }
+
+    #[test]
+    fn test_delete_where_sql_errors() {
+        assert_eq!(
+            delete_where_sql("DELETE FROM c", "/pk"),
+            Err(DeleteWhereError::NotSelect)
+        );
+        assert_eq!(
+            delete_where_sql("SELECT 1", "/pk"),
+            Err(DeleteWhereError::NoFrom)
+        );
+        assert_eq!(
+            delete_where_sql("SELECT * FROM i IN c.items", "/pk"),
+            Err(DeleteWhereError::Iteration)
+        );
+        assert!(delete_where_sql("SELECT * FROM c WHERE c.group = 'x'", "/pk").is_ok());
+        assert_eq!(
+            delete_where_sql("SELECT c.status, COUNT(1) FROM c GROUP BY c.status", "/pk"),
+            Err(DeleteWhereError::GroupBy)
+        );
+    }
+
+    #[test]
+    fn test_delete_targets() {
+        let rows = vec![
+            json!({"id": "1", "pk": "a"}),
+            json!({"id": "1", "pk": "a"}),
+            json!({"id": "1", "pk": "b"}),
+            json!({"id": "2"}),
+            json!({"pk": "a"}),
+        ];
+        let targets = delete_targets(&rows);
+        assert_eq!(targets.len(), 3);
+        assert_eq!(targets[1].partition_key, json!("b"));
+        assert_eq!(targets[2].partition_key, json!({}));
+    }
+}
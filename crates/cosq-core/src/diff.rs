@@ -0,0 +1,112 @@
+//! Line diffs for previewing changes to .cosq files and documents
+
+/// One line of a diff between two texts
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DiffLine<'a> {
+    Same(&'a str),
+    Removed(&'a str),
+    Added(&'a str),
+}
+
+impl DiffLine<'_> {
+    pub fn is_change(&self) -> bool {
+        !matches!(self, DiffLine::Same(_))
+    }
+}
+
+/// Changed sections with more line pairs than this are shown as removed,
+/// then added, rather than allocating an LCS table for them (a 2 MB document
+/// can have tens of thousands of lines)
+const MAX_LCS_CELLS: usize = 4_000_000;
+
+/// Line-by-line diff of `old` and `new`: longest common subsequence over the
+/// lines between their common prefix and suffix.
+pub fn diff_lines<'a>(old: &'a str, new: &'a str) -> Vec<DiffLine<'a>> {
+    let old: Vec<&str> = old.lines().collect();
+    let new: Vec<&str> = new.lines().collect();
+    let prefix = old.iter().zip(&new).take_while(|(a, b)| a == b).count();
+    let suffix = old[prefix..]
+        .iter()
+        .rev()
+        .zip(new[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+
+    let mut lines: Vec<DiffLine> = old[..prefix].iter().map(|l| DiffLine::Same(l)).collect();
+    let old_mid = &old[prefix..old.len() - suffix];
+    let new_mid = &new[prefix..new.len() - suffix];
+    if (old_mid.len() + 1).saturating_mul(new_mid.len() + 1) <= MAX_LCS_CELLS {
+        lcs_diff(old_mid, new_mid, &mut lines);
+    } else {
+        lines.extend(old_mid.iter().map(|l| DiffLine::Removed(l)));
+        lines.extend(new_mid.iter().map(|l| DiffLine::Added(l)));
+    }
+    lines.extend(old[old.len() - suffix..].iter().map(|l| DiffLine::Same(l)));
+    lines
+}
+
+fn lcs_diff<'a>(old: &[&'a str], new: &[&'a str], lines: &mut Vec<DiffLine<'a>>) {
+    // lcs[i][j] = length of the LCS of old[i..] and new[j..]
+    let mut lcs = vec![vec![0u32; new.len() + 1]; old.len() + 1];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            lcs[i][j] = if old[i] == new[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+
+    let (mut i, mut j) = (0, 0);
+    while i < old.len() && j < new.len() {
+        if old[i] == new[j] {
+            lines.push(DiffLine::Same(old[i]));
+            i += 1;
+            j += 1;
+        } else if lcs[i + 1][j] >= lcs[i][j + 1] {
+            lines.push(DiffLine::Removed(old[i]));
+            i += 1;
+        } else {
+            lines.push(DiffLine::Added(new[j]));
+            j += 1;
+        }
+    }
+    lines.extend(old[i..].iter().map(|l| DiffLine::Removed(l)));
+    lines.extend(new[j..].iter().map(|l| DiffLine::Added(l)));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_diff_lines() {
+        let diff = diff_lines("a\nb\nc\n", "a\nB\nc\nd\n");
+        assert_eq!(
+            diff,
+            vec![
+                DiffLine::Same("a"),
+                DiffLine::Removed("b"),
+                DiffLine::Added("B"),
+                DiffLine::Same("c"),
+                DiffLine::Added("d"),
+            ]
+        );
+        assert!(diff_lines("x\n", "x\n").iter().all(|l| !l.is_change()));
+        assert_eq!(diff_lines("", "new"), vec![DiffLine::Added("new")]);
+    }
+
+    #[test]
+    fn test_diff_lines_large_change() {
+        let old: String = (0..3000).map(|i| format!("a{i}\n")).collect();
+        let new: String = (0..3000).map(|i| format!("b{i}\n")).collect();
+        let (old, new) = (format!("{{\n{old}}}"), format!("{{\n{new}}}"));
+        let diff = diff_lines(&old, &new);
+        assert_eq!(diff.len(), 6002);
+        assert_eq!(diff[0], DiffLine::Same("{"));
+        assert_eq!(diff[1], DiffLine::Removed("a0"));
+        assert_eq!(diff[3001], DiffLine::Added("b0"));
+        assert_eq!(diff[6001], DiffLine::Same("}"));
+    }
+}
@@ -0,0 +1,149 @@
+//! Project `.env` files
+//!
+//! cosq reads `.cosq/.env` (or, failing that, `.env`) from the project
+//! directory before parsing arguments, so per-project `COSQ_*` settings and AI
+//! keys travel with the repository instead of the global shell config.
+//! Variables already set in the environment take precedence.
+//!
+//! Supported syntax: `KEY=value` lines, optional `export ` prefix, `#`
+//! comments, single-quoted (literal) and double-quoted (`\n`, `\t`, `\"`,
+//! `\\` escapes) values.
+
+use std::path::{Path, PathBuf};
+
+use thiserror::Error;
+
+/// Set to skip loading project `.env` files
+pub const NO_DOTENV_ENV: &str = "COSQ_NO_DOTENV";
+
+#[derive(Debug, Error)]
+pub enum DotenvError {
+    #[error("failed to read env file: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("line {line}: {message}")]
+    Syntax { line: usize, message: String },
+}
+
+/// The env file to load for a project directory: `.cosq/.env`, else `.env`.
+pub fn project_env_file(dir: &Path) -> Option<PathBuf> {
+    [dir.join(".cosq").join(".env"), dir.join(".env")]
+        .into_iter()
+        .find(|p| p.is_file())
+}
+
+/// Read and parse an env file.
+pub fn read(path: &Path) -> Result<Vec<(String, String)>, DotenvError> {
+    parse(&std::fs::read_to_string(path)?)
+}
+
+/// Parse env file contents into `(name, value)` pairs, in file order.
+pub fn parse(contents: &str) -> Result<Vec<(String, String)>, DotenvError> {
+    let mut vars = Vec::new();
+    for (i, raw) in contents.lines().enumerate() {
+        let line = raw.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let syntax = |message: &str| DotenvError::Syntax {
+            line: i + 1,
+            message: message.to_string(),
+        };
+        let line = line.strip_prefix("export ").unwrap_or(line);
+        let (key, value) = line
+            .split_once('=')
+            .ok_or_else(|| syntax("expected KEY=value"))?;
+        let key = key.trim();
+        if key.is_empty()
+            || key.starts_with(|c: char| c.is_ascii_digit())
+            || !key.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+        {
+            return Err(syntax(&format!("invalid variable name '{key}'")));
+        }
+        vars.push((key.to_string(), parse_value(value.trim()).map_err(syntax)?));
+    }
+    Ok(vars)
+}
+
+fn parse_value(value: &str) -> Result<String, &'static str> {
+    if let Some(rest) = value.strip_prefix('\'') {
+        let end = rest.find('\'').ok_or("unterminated single quote")?;
+        return Ok(rest[..end].to_string());
+    }
+    if let Some(rest) = value.strip_prefix('"') {
+        let mut out = String::new();
+        let mut chars = rest.chars();
+        while let Some(c) = chars.next() {
+            match c {
+                '"' => return Ok(out),
+                '\\' => match chars.next() {
+                    Some('n') => out.push('\n'),
+                    Some('t') => out.push('\t'),
+                    Some('r') => out.push('\r'),
+                    Some(other) => out.push(other),
+                    None => break,
+                },
+                _ => out.push(c),
+            }
+        }
+        return Err("unterminated double quote");
+    }
+    // Unquoted: a ` #` starts a comment
+    let value = match value.find(" #") {
+        Some(pos) => &value[..pos],
+        None => value,
+    };
+    Ok(value.trim_end().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let vars = parse(
+            "# project settings\n\
+             COSQ_PROFILE=staging\n\
+             export AZURE_OPENAI_KEY = 'abc#123'\n\
+             GREETING=\"hello\\nworld\"\n\
+             EMPTY=\n\
+             URL=https://x.example.com/a#b # trailing comment\n",
+        )
+        .unwrap();
+        assert_eq!(
+            vars,
+            vec![
+                ("COSQ_PROFILE".into(), "staging".into()),
+                ("AZURE_OPENAI_KEY".into(), "abc#123".into()),
+                ("GREETING".into(), "hello\nworld".into()),
+                ("EMPTY".into(), String::new()),
+                ("URL".into(), "https://x.example.com/a#b".into()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert!(matches!(
+            parse("OK=1\nnot a pair\n"),
+            Err(DotenvError::Syntax { line: 2, .. })
+        ));
+        assert!(parse("1KEY=x").is_err());
+        assert!(parse("KEY=\"open").is_err());
+    }
+
+    #[test]
+    fn test_project_env_file() {
+        let dir = tempfile::tempdir().unwrap();
+        assert_eq!(project_env_file(dir.path()), None);
+        std::fs::write(dir.path().join(".env"), "A=1").unwrap();
+        assert_eq!(project_env_file(dir.path()), Some(dir.path().join(".env")));
+        std::fs::create_dir(dir.path().join(".cosq")).unwrap();
+        std::fs::write(dir.path().join(".cosq").join(".env"), "A=2").unwrap();
+        assert_eq!(
+            project_env_file(dir.path()),
+            Some(dir.path().join(".cosq").join(".env"))
+        );
+    }
+}
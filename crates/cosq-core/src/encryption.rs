@@ -0,0 +1,200 @@
+//! Encrypted stored query values
+//!
+//! Parameter defaults marked `encrypted: true` hold `enc:v1:<base64>` strings:
+//! the JSON value sealed with XChaCha20-Poly1305 (random 24-byte nonce
+//! followed by the ciphertext). The 256-bit key lives in the OS keyring and
+//! is created by the first `cosq queries encrypt`; set `COSQ_ENCRYPTION_KEY`
+//! (base64) to supply it where no keyring is available, such as CI.
+//!
+//! ```
+//! use cosq_core::encryption::EncryptionKey;
+//! use serde_json::json;
+//!
+//! let key = EncryptionKey::generate();
+//! let sealed = key.encrypt(&json!("s3cret")).unwrap();
+//! assert!(sealed.starts_with("enc:v1:"));
+//! assert_eq!(key.decrypt(&sealed).unwrap(), json!("s3cret"));
+//! ```
+
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, OsRng};
+use chacha20poly1305::{Key, XChaCha20Poly1305, XNonce};
+use serde_json::Value;
+use thiserror::Error;
+
+use crate::secrets;
+
+/// Prefix of encrypted values
+pub const PREFIX: &str = "enc:v1:";
+
+/// Environment variable that overrides the keyring
+pub const KEY_ENV: &str = "COSQ_ENCRYPTION_KEY";
+
+/// Name of the key's entry in the OS keyring (see [`crate::secrets`])
+const SECRET_NAME: &str = "query-encryption-key";
+const NONCE_LEN: usize = 24;
+
+#[derive(Debug, Error)]
+pub enum EncryptionError {
+    #[error("no encryption key found — run `cosq queries encrypt` to create one, or set {KEY_ENV}")]
+    NoKey,
+
+    #[error("{0} (set {KEY_ENV} to supply the key without a keyring)")]
+    Keyring(String),
+
+    #[error("invalid encryption key (expected 32 bytes, base64-encoded)")]
+    InvalidKey,
+
+    #[error("not an encrypted value (expected {PREFIX}...)")]
+    Malformed,
+
+    #[error("could not decrypt value — it was encrypted with a different key")]
+    Decrypt,
+
+    #[error("encryption failed")]
+    Encrypt,
+}
+
+/// Symmetric key for encrypting stored query values
+#[derive(Clone)]
+pub struct EncryptionKey(Key);
+
+impl std::fmt::Debug for EncryptionKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("EncryptionKey(..)")
+    }
+}
+
+impl EncryptionKey {
+    pub fn generate() -> Self {
+        Self(XChaCha20Poly1305::generate_key(&mut OsRng))
+    }
+
+    pub fn from_base64(encoded: &str) -> Result<Self, EncryptionError> {
+        let bytes = STANDARD
+            .decode(encoded.trim())
+            .map_err(|_| EncryptionError::InvalidKey)?;
+        if bytes.len() != 32 {
+            return Err(EncryptionError::InvalidKey);
+        }
+        Ok(Self(*Key::from_slice(&bytes)))
+    }
+
+    pub fn to_base64(&self) -> String {
+        STANDARD.encode(self.0)
+    }
+
+    /// Load the key from `COSQ_ENCRYPTION_KEY` or the OS keyring.
+    pub fn load() -> Result<Self, EncryptionError> {
+        if let Ok(encoded) = std::env::var(KEY_ENV) {
+            return Self::from_base64(&encoded);
+        }
+        match secrets::get(SECRET_NAME) {
+            Ok(Some(encoded)) => Self::from_base64(&encoded),
+            Ok(None) => Err(EncryptionError::NoKey),
+            Err(e) => Err(EncryptionError::Keyring(e.to_string())),
+        }
+    }
+
+    /// Load the key, generating and storing one in the OS keyring on first use.
+    ///
+    /// Returns the key and whether it was just created.
+    pub fn load_or_create() -> Result<(Self, bool), EncryptionError> {
+        match Self::load() {
+            Ok(key) => Ok((key, false)),
+            Err(EncryptionError::NoKey) => {
+                let key = Self::generate();
+                secrets::set(SECRET_NAME, &key.to_base64())
+                    .map_err(|e| EncryptionError::Keyring(e.to_string()))?;
+                Ok((key, true))
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Encrypt a JSON value to an `enc:v1:` string.
+    pub fn encrypt(&self, value: &Value) -> Result<String, EncryptionError> {
+        let cipher = XChaCha20Poly1305::new(&self.0);
+        let nonce = XChaCha20Poly1305::generate_nonce(&mut OsRng);
+        let plaintext = serde_json::to_vec(value).map_err(|_| EncryptionError::Encrypt)?;
+        let ciphertext = cipher
+            .encrypt(&nonce, plaintext.as_slice())
+            .map_err(|_| EncryptionError::Encrypt)?;
+        let mut sealed = nonce.to_vec();
+        sealed.extend_from_slice(&ciphertext);
+        Ok(format!("{PREFIX}{}", STANDARD.encode(sealed)))
+    }
+
+    /// Decrypt an `enc:v1:` string back to its JSON value.
+    pub fn decrypt(&self, encrypted: &str) -> Result<Value, EncryptionError> {
+        let encoded = encrypted
+            .trim()
+            .strip_prefix(PREFIX)
+            .ok_or(EncryptionError::Malformed)?;
+        let sealed = STANDARD
+            .decode(encoded)
+            .map_err(|_| EncryptionError::Malformed)?;
+        if sealed.len() <= NONCE_LEN {
+            return Err(EncryptionError::Malformed);
+        }
+        let (nonce, ciphertext) = sealed.split_at(NONCE_LEN);
+        let plaintext = XChaCha20Poly1305::new(&self.0)
+            .decrypt(XNonce::from_slice(nonce), ciphertext)
+            .map_err(|_| EncryptionError::Decrypt)?;
+        serde_json::from_slice(&plaintext).map_err(|_| EncryptionError::Malformed)
+    }
+}
+
+/// Whether a value is an `enc:v1:` string.
+pub fn is_encrypted(value: &Value) -> bool {
+    value.as_str().is_some_and(|s| s.starts_with(PREFIX))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_round_trip_keeps_type() {
+        let key = EncryptionKey::generate();
+        for value in [json!("token"), json!(42), json!(true)] {
+            let sealed = key.encrypt(&value).unwrap();
+            assert!(is_encrypted(&json!(sealed)));
+            assert_eq!(key.decrypt(&sealed).unwrap(), value);
+        }
+        // Fresh nonce every time
+        assert_ne!(
+            key.encrypt(&json!("x")).unwrap(),
+            key.encrypt(&json!("x")).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_wrong_key_and_malformed() {
+        let sealed = EncryptionKey::generate().encrypt(&json!("x")).unwrap();
+        assert!(matches!(
+            EncryptionKey::generate().decrypt(&sealed),
+            Err(EncryptionError::Decrypt)
+        ));
+        let key = EncryptionKey::generate();
+        assert!(matches!(
+            key.decrypt("plain"),
+            Err(EncryptionError::Malformed)
+        ));
+        assert!(matches!(
+            key.decrypt("enc:v1:AAAA"),
+            Err(EncryptionError::Malformed)
+        ));
+    }
+
+    #[test]
+    fn test_key_base64() {
+        let key = EncryptionKey::generate();
+        let restored = EncryptionKey::from_base64(&key.to_base64()).unwrap();
+        let sealed = key.encrypt(&json!(1)).unwrap();
+        assert_eq!(restored.decrypt(&sealed).unwrap(), json!(1));
+        assert!(EncryptionKey::from_base64("c2hvcnQ=").is_err());
+    }
+}
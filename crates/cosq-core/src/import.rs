@@ -0,0 +1,529 @@
+//! Document files for `cosq import`
+//!
+//! Reads documents one at a time from a JSON array, NDJSON or CSV file, so
+//! files larger than memory import in bounded batches. CSV cells are typed
+//! the way `-o csv` writes them: `true`/`false` and numbers become JSON
+//! booleans and numbers, cells holding a JSON object or array are parsed,
+//! empty cells are left out, and a dotted header (`address.city`) builds
+//! nested objects. `id` is always a string.
+
+use std::io::BufRead;
+use std::path::Path;
+
+use serde_json::{Map, Value};
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum ImportError {
+    #[error("failed to read import file: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("invalid JSON in {location}: {source}")]
+    Json {
+        location: String,
+        source: serde_json::Error,
+    },
+
+    #[error("{location} is not a JSON object")]
+    NotAnObject { location: String },
+
+    #[error("malformed import file: {0}")]
+    Malformed(String),
+
+    #[error("{location} has {found} fields, the header has {expected}")]
+    FieldCount {
+        location: String,
+        expected: usize,
+        found: usize,
+    },
+
+    #[error("CSV file has no header row")]
+    MissingHeader,
+}
+
+impl ImportError {
+    /// Whether reading can go on with the next document.
+    pub fn is_recoverable(&self) -> bool {
+        match self {
+            ImportError::Io(_) | ImportError::Malformed(_) | ImportError::MissingHeader => false,
+            // Array elements are delimited by bracket matching, not parsing,
+            // so a broken one can be skipped too
+            ImportError::Json { .. }
+            | ImportError::NotAnObject { .. }
+            | ImportError::FieldCount { .. } => true,
+        }
+    }
+}
+
+/// Layout of an import file
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImportFormat {
+    /// One JSON array of documents
+    Json,
+    /// One JSON document per line
+    Ndjson,
+    /// A header row and one document per record
+    Csv,
+}
+
+impl ImportFormat {
+    /// The format of `path` from its extension; `.json` files that don't
+    /// start with `[` (after `first_byte`) are read as NDJSON.
+    pub fn detect(path: &Path, first_byte: Option<u8>) -> Self {
+        let extension = path
+            .extension()
+            .and_then(|e| e.to_str())
+            .unwrap_or_default()
+            .to_ascii_lowercase();
+        match extension.as_str() {
+            "csv" => ImportFormat::Csv,
+            "ndjson" | "jsonl" => ImportFormat::Ndjson,
+            _ if first_byte == Some(b'[') => ImportFormat::Json,
+            _ => ImportFormat::Ndjson,
+        }
+    }
+}
+
+/// Reads the documents of an import file in order
+pub struct DocumentReader<R> {
+    reader: R,
+    format: ImportFormat,
+    /// Bytes consumed so far (for progress)
+    bytes_read: u64,
+    /// Documents (or lines/records) read so far, for error locations
+    position: usize,
+    state: State,
+}
+
+enum State {
+    /// Nothing read yet
+    Start,
+    /// Inside a JSON array, or reading NDJSON/CSV records
+    Reading,
+    /// CSV columns, split into nested field paths
+    Csv(Vec<Vec<String>>),
+    Done,
+}
+
+impl<R: BufRead> DocumentReader<R> {
+    pub fn new(reader: R, format: ImportFormat) -> Self {
+        Self {
+            reader,
+            format,
+            bytes_read: 0,
+            position: 0,
+            state: State::Start,
+        }
+    }
+
+    pub fn bytes_read(&self) -> u64 {
+        self.bytes_read
+    }
+
+    fn next_document(&mut self) -> Result<Option<Value>, ImportError> {
+        match self.format {
+            ImportFormat::Json => self.next_array_element(),
+            ImportFormat::Ndjson => self.next_line(),
+            ImportFormat::Csv => self.next_record(),
+        }
+    }
+
+    fn next_line(&mut self) -> Result<Option<Value>, ImportError> {
+        let mut line = String::new();
+        loop {
+            line.clear();
+            let n = self.reader.read_line(&mut line)?;
+            if n == 0 {
+                return Ok(None);
+            }
+            self.bytes_read += n as u64;
+            self.position += 1;
+            if !line.trim().is_empty() {
+                break;
+            }
+        }
+        let location = format!("line {}", self.position);
+        let document = serde_json::from_str(line.trim()).map_err(|source| ImportError::Json {
+            location: location.clone(),
+            source,
+        })?;
+        expect_object(document, location).map(Some)
+    }
+
+    fn next_array_element(&mut self) -> Result<Option<Value>, ImportError> {
+        if let State::Start = self.state {
+            match self.skip_whitespace_and(b"")? {
+                Some(b'[') => self.consume(1),
+                Some(_) => return Err(ImportError::Malformed("expected `[`".to_string())),
+                None => return Ok(None),
+            }
+            self.state = State::Reading;
+        }
+        if let State::Done = self.state {
+            return Ok(None);
+        }
+        match self.skip_whitespace_and(b",")? {
+            Some(b']') => {
+                self.consume(1);
+                self.state = State::Done;
+                Ok(None)
+            }
+            Some(_) => {
+                self.position += 1;
+                let location = format!("element {}", self.position);
+                let bytes = self.read_json_value()?;
+                let document =
+                    serde_json::from_slice(&bytes).map_err(|source| ImportError::Json {
+                        location: location.clone(),
+                        source,
+                    })?;
+                expect_object(document, location).map(Some)
+            }
+            None => Err(ImportError::Malformed(
+                "file ends before the closing `]`".to_string(),
+            )),
+        }
+    }
+
+    /// Skip whitespace and any of `also`, returning the next byte unconsumed.
+    fn skip_whitespace_and(&mut self, also: &[u8]) -> Result<Option<u8>, ImportError> {
+        loop {
+            let buf = self.reader.fill_buf()?;
+            let Some(&byte) = buf.first() else {
+                return Ok(None);
+            };
+            if byte.is_ascii_whitespace() || also.contains(&byte) {
+                self.consume(1);
+            } else {
+                return Ok(Some(byte));
+            }
+        }
+    }
+
+    fn consume(&mut self, n: usize) {
+        self.reader.consume(n);
+        self.bytes_read += n as u64;
+    }
+
+    /// The bytes of one JSON value inside an array: up to its closing
+    /// bracket, or for scalars up to the next `,`, `]` or whitespace.
+    fn read_json_value(&mut self) -> Result<Vec<u8>, ImportError> {
+        let mut value = Vec::new();
+        let (mut depth, mut in_string, mut escaped) = (0usize, false, false);
+        loop {
+            let buf = self.reader.fill_buf()?;
+            if buf.is_empty() {
+                return Err(ImportError::Malformed(
+                    "file ends inside a document".to_string(),
+                ));
+            }
+            let mut used = 0;
+            let mut complete = false;
+            for &byte in buf {
+                if in_string {
+                    used += 1;
+                    match byte {
+                        _ if escaped => escaped = false,
+                        b'\\' => escaped = true,
+                        b'"' => in_string = false,
+                        _ => {}
+                    }
+                    continue;
+                }
+                // Only scalars end at depth 0 (the first byte is none of these)
+                if depth == 0 && (byte == b',' || byte == b']' || byte.is_ascii_whitespace()) {
+                    complete = true;
+                    break;
+                }
+                used += 1;
+                match byte {
+                    b'"' => in_string = true,
+                    b'{' | b'[' => depth += 1,
+                    b'}' | b']' => {
+                        depth = depth.saturating_sub(1);
+                        if depth == 0 {
+                            complete = true;
+                            break;
+                        }
+                    }
+                    _ => {}
+                }
+            }
+            value.extend_from_slice(&buf[..used]);
+            self.consume(used);
+            if complete {
+                return Ok(value);
+            }
+        }
+    }
+
+    fn next_record(&mut self) -> Result<Option<Value>, ImportError> {
+        if let State::Start = self.state {
+            let header = self.read_csv_record()?.ok_or(ImportError::MissingHeader)?;
+            let columns = header
+                .iter()
+                .map(|h| {
+                    h.trim_start_matches('\u{feff}')
+                        .trim()
+                        .split('.')
+                        .map(str::to_string)
+                        .collect()
+                })
+                .collect();
+            self.state = State::Csv(columns);
+        }
+        let fields = loop {
+            match self.read_csv_record()? {
+                None => return Ok(None),
+                // Blank lines (e.g. a trailing one) aren't records
+                Some(fields) if fields.len() == 1 && fields[0].is_empty() => continue,
+                Some(fields) => break fields,
+            }
+        };
+        self.position += 1;
+        let State::Csv(ref columns) = self.state else {
+            unreachable!("CSV columns are read first")
+        };
+        if fields.len() != columns.len() {
+            return Err(ImportError::FieldCount {
+                location: format!("record {}", self.position),
+                expected: columns.len(),
+                found: fields.len(),
+            });
+        }
+        let mut document = Map::new();
+        for (path, field) in columns.iter().zip(fields) {
+            if field.is_empty() {
+                continue;
+            }
+            let value = if path.len() == 1 && path[0] == "id" {
+                Value::String(field)
+            } else {
+                csv_value(field)
+            };
+            insert_path(&mut document, path, value);
+        }
+        Ok(Some(Value::Object(document)))
+    }
+
+    /// One CSV record (RFC 4180: quoted fields may hold commas, `""` and
+    /// line breaks), or `None` at the end of the file.
+    fn read_csv_record(&mut self) -> Result<Option<Vec<String>>, ImportError> {
+        let mut fields = Vec::new();
+        let mut field = String::new();
+        let mut in_quotes = false;
+        let mut line = String::new();
+        let mut started = false;
+        loop {
+            line.clear();
+            let n = self.reader.read_line(&mut line)?;
+            self.bytes_read += n as u64;
+            if n == 0 {
+                if in_quotes {
+                    return Err(ImportError::Malformed(
+                        "unterminated quoted CSV field".to_string(),
+                    ));
+                }
+                if !started {
+                    return Ok(None);
+                }
+                fields.push(field);
+                return Ok(Some(fields));
+            }
+            started = true;
+            let mut chars = line.chars().peekable();
+            while let Some(c) = chars.next() {
+                match c {
+                    '"' if in_quotes && chars.peek() == Some(&'"') => {
+                        chars.next();
+                        field.push('"');
+                    }
+                    '"' => in_quotes = !in_quotes,
+                    ',' if !in_quotes => fields.push(std::mem::take(&mut field)),
+                    '\r' | '\n' if !in_quotes => {}
+                    c => field.push(c),
+                }
+            }
+            if !in_quotes {
+                fields.push(field);
+                return Ok(Some(fields));
+            }
+        }
+    }
+}
+
+impl<R: BufRead> Iterator for DocumentReader<R> {
+    type Item = Result<Value, ImportError>;
+
+    /// The next document; unrecoverable errors end the iteration.
+    fn next(&mut self) -> Option<Self::Item> {
+        if let State::Done = self.state {
+            return None;
+        }
+        match self.next_document() {
+            Ok(document) => document.map(Ok),
+            Err(e) => {
+                if !e.is_recoverable() {
+                    self.state = State::Done;
+                }
+                Some(Err(e))
+            }
+        }
+    }
+}
+
+fn expect_object(document: Value, location: String) -> Result<Value, ImportError> {
+    if document.is_object() {
+        Ok(document)
+    } else {
+        Err(ImportError::NotAnObject { location })
+    }
+}
+
+/// A CSV cell as JSON: booleans, numbers (without leading zeros, so codes
+/// like `007` stay strings), JSON objects and arrays, otherwise a string.
+fn csv_value(field: String) -> Value {
+    match field.as_str() {
+        "true" => return Value::Bool(true),
+        "false" => return Value::Bool(false),
+        _ => {}
+    }
+    let digits = field.trim_start_matches('-');
+    let leading_zero = digits.len() > 1 && digits.starts_with('0') && !digits.starts_with("0.");
+    if !leading_zero && digits.starts_with(|c: char| c.is_ascii_digit()) {
+        if let Ok(Value::Number(n)) = serde_json::from_str(&field) {
+            return Value::Number(n);
+        }
+    }
+    if field.starts_with('{') || field.starts_with('[') {
+        if let Ok(value @ (Value::Object(_) | Value::Array(_))) = serde_json::from_str(&field) {
+            return value;
+        }
+    }
+    Value::String(field)
+}
+
+/// Set `value` at a nested field path, creating objects on the way (a
+/// non-object in the way is replaced).
+fn insert_path(document: &mut Map<String, Value>, path: &[String], value: Value) {
+    let (last, parents) = path.split_last().expect("paths have a segment");
+    let mut target = document;
+    for segment in parents {
+        let entry = target
+            .entry(segment.clone())
+            .or_insert_with(|| Value::Object(Map::new()));
+        if !entry.is_object() {
+            *entry = Value::Object(Map::new());
+        }
+        target = entry.as_object_mut().expect("just made an object");
+    }
+    target.insert(last.clone(), value);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use std::io::BufReader;
+
+    fn read_all(text: &str, format: ImportFormat) -> Vec<Result<Value, String>> {
+        // A tiny buffer so values span buffer refills
+        DocumentReader::new(BufReader::with_capacity(4, text.as_bytes()), format)
+            .map(|r| r.map_err(|e| e.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_detect() {
+        assert_eq!(
+            ImportFormat::detect(Path::new("a.CSV"), None),
+            ImportFormat::Csv
+        );
+        assert_eq!(
+            ImportFormat::detect(Path::new("a.jsonl"), Some(b'[')),
+            ImportFormat::Ndjson
+        );
+        assert_eq!(
+            ImportFormat::detect(Path::new("a.json"), Some(b'[')),
+            ImportFormat::Json
+        );
+        assert_eq!(
+            ImportFormat::detect(Path::new("a.json"), Some(b'{')),
+            ImportFormat::Ndjson
+        );
+    }
+
+    #[test]
+    fn test_json_array() {
+        let text = r#" [ {"id": "1", "note": "a ] , { \"quoted\" }"},
+            {"id": "2", "tags": [1, [2]]} ,3, {"id": "4"} ] "#;
+        let docs = read_all(text, ImportFormat::Json);
+        assert_eq!(docs.len(), 4);
+        assert_eq!(
+            docs[0],
+            Ok(json!({"id": "1", "note": "a ] , { \"quoted\" }"}))
+        );
+        assert_eq!(docs[1], Ok(json!({"id": "2", "tags": [1, [2]]})));
+        assert_eq!(docs[2], Err("element 3 is not a JSON object".to_string()));
+        assert_eq!(docs[3], Ok(json!({"id": "4"})));
+
+        assert!(read_all("[]", ImportFormat::Json).is_empty());
+        let truncated = read_all(r#"[{"id": "1"}, {"id": "#, ImportFormat::Json);
+        assert_eq!(truncated.len(), 2);
+        assert!(truncated[1].is_err());
+    }
+
+    #[test]
+    fn test_ndjson() {
+        let text = "{\"id\": \"1\"}\n\n{broken\n[1]\r\n{\"id\": \"2\"}";
+        let docs = read_all(text, ImportFormat::Ndjson);
+        assert_eq!(docs.len(), 4);
+        assert_eq!(docs[0], Ok(json!({"id": "1"})));
+        assert!(docs[1].as_ref().unwrap_err().contains("line 3"));
+        assert_eq!(docs[2], Err("line 4 is not a JSON object".to_string()));
+        assert_eq!(docs[3], Ok(json!({"id": "2"})));
+    }
+
+    #[test]
+    fn test_csv() {
+        let text = "id,qty,code,active,address.city,tags,note\r\n\
+                    001,3,007,true,Oslo,\"[\"\"a\"\"]\",\"multi\nline, \"\"quoted\"\"\"\n\
+                    2,-1.5,,false,,[broken,plain\n\
+                    3,1\n\n";
+        let docs = read_all(text, ImportFormat::Csv);
+        assert_eq!(docs.len(), 3);
+        assert_eq!(
+            docs[0],
+            Ok(json!({
+                "id": "001",
+                "qty": 3,
+                "code": "007",
+                "active": true,
+                "address": {"city": "Oslo"},
+                "tags": ["a"],
+                "note": "multi\nline, \"quoted\""
+            }))
+        );
+        assert_eq!(
+            docs[1],
+            Ok(
+                json!({"id": "2", "qty": -1.5, "active": false, "tags": "[broken", "note": "plain"})
+            )
+        );
+        assert_eq!(
+            docs[2],
+            Err("record 3 has 2 fields, the header has 7".to_string())
+        );
+        assert!(read_all("", ImportFormat::Csv)[0].is_err());
+    }
+
+    #[test]
+    fn test_bytes_read() {
+        let text = "{\"id\": \"1\"}\n{\"id\": \"2\"}\n";
+        let mut reader = DocumentReader::new(text.as_bytes(), ImportFormat::Ndjson);
+        reader.next();
+        assert_eq!(reader.bytes_read(), 12);
+        assert_eq!(reader.by_ref().count(), 1);
+        assert_eq!(reader.bytes_read(), text.len() as u64);
+    }
+}
@@ -0,0 +1,139 @@
+//! Estate inventory for `cosq inventory`
+//!
+//! One row per container across several accounts — the configured profiles,
+//! or every account discovered through Azure Resource Manager — with its
+//! partition key, document count, storage and throughput. Accounts that
+//! can't be read are listed as failures instead of aborting the report.
+
+use serde::{Deserialize, Serialize};
+
+/// One container of the inventory
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ContainerEntry {
+    pub account: String,
+    /// Profile or subscription the account was found through
+    pub source: String,
+    pub database: String,
+    pub container: String,
+    pub partition_key: Vec<String>,
+    /// Documents stored, from the container's quota info
+    pub documents: Option<u64>,
+    /// Size of the stored documents in KB
+    pub size_kb: Option<u64>,
+    /// Provisioned throughput (e.g. `400 RU/s (manual)`) or `serverless`;
+    /// unset when Azure Resource Manager can't be reached for the account
+    pub throughput: Option<String>,
+}
+
+/// An account (or one of its databases) that couldn't be inventoried
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AccountFailure {
+    pub account: String,
+    pub source: String,
+    pub error: String,
+}
+
+/// The inventory of all accounts walked
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Inventory {
+    pub containers: Vec<ContainerEntry>,
+    pub failures: Vec<AccountFailure>,
+}
+
+impl Inventory {
+    /// Sort containers by account, database and container, and failures by
+    /// account, so reports don't depend on the order accounts answered in.
+    pub fn sort(&mut self) {
+        self.containers.sort_by(|a, b| {
+            (&a.account, &a.database, &a.container).cmp(&(&b.account, &b.database, &b.container))
+        });
+        self.failures
+            .sort_by(|a, b| (&a.account, &a.source).cmp(&(&b.account, &b.source)));
+    }
+
+    /// Distinct accounts with at least one container
+    pub fn accounts(&self) -> usize {
+        let mut names: Vec<&str> = self.containers.iter().map(|c| c.account.as_str()).collect();
+        names.sort_unstable();
+        names.dedup();
+        names.len()
+    }
+
+    /// Documents and KB stored over all containers that reported them
+    pub fn totals(&self) -> (u64, u64) {
+        self.containers.iter().fold((0, 0), |(docs, kb), c| {
+            (docs + c.documents.unwrap_or(0), kb + c.size_kb.unwrap_or(0))
+        })
+    }
+}
+
+/// Format a size in KB with a readable unit (`512 KB`, `1.5 MB`, `2.0 GB`).
+pub fn format_size_kb(kb: u64) -> String {
+    const UNITS: [&str; 3] = ["MB", "GB", "TB"];
+    if kb < 1024 {
+        return format!("{kb} KB");
+    }
+    let mut size = kb as f64 / 1024.0;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    format!("{size:.1} {}", UNITS[unit])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(
+        account: &str,
+        database: &str,
+        container: &str,
+        documents: Option<u64>,
+    ) -> ContainerEntry {
+        ContainerEntry {
+            account: account.to_string(),
+            source: "default".to_string(),
+            database: database.to_string(),
+            container: container.to_string(),
+            partition_key: vec!["/id".to_string()],
+            documents,
+            size_kb: documents.map(|d| d * 2),
+            throughput: None,
+        }
+    }
+
+    #[test]
+    fn test_sort_and_totals() {
+        let mut inventory = Inventory {
+            containers: vec![
+                entry("prod", "shop", "orders", Some(10)),
+                entry("dev", "shop", "orders", None),
+                entry("prod", "app", "users", Some(5)),
+            ],
+            failures: Vec::new(),
+        };
+        inventory.sort();
+        let order: Vec<(&str, &str)> = inventory
+            .containers
+            .iter()
+            .map(|c| (c.account.as_str(), c.database.as_str()))
+            .collect();
+        assert_eq!(
+            order,
+            vec![("dev", "shop"), ("prod", "app"), ("prod", "shop")]
+        );
+        assert_eq!(inventory.accounts(), 2);
+        assert_eq!(inventory.totals(), (15, 30));
+    }
+
+    #[test]
+    fn test_format_size_kb() {
+        assert_eq!(format_size_kb(0), "0 KB");
+        assert_eq!(format_size_kb(512), "512 KB");
+        assert_eq!(format_size_kb(1536), "1.5 MB");
+        assert_eq!(format_size_kb(2 * 1024 * 1024), "2.0 GB");
+        assert_eq!(format_size_kb(3 * 1024 * 1024 * 1024), "3.0 TB");
+    }
+}
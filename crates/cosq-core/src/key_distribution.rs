@@ -0,0 +1,178 @@
+//! Partition key value distribution for `cosq container keys`
+//!
+//! Counts documents per partition key value — with a `GROUP BY` over the
+//! partition key paths, or over a sample of documents — and summarizes the
+//! most frequent values and how skewed the distribution is. A few keys
+//! holding most of the documents (or traffic) are the usual cause of hot
+//! partitions and 429s on a container with plenty of RU/s overall.
+
+use std::collections::HashMap;
+
+use serde_json::Value;
+
+/// Label of documents that don't have the partition key
+pub const UNDEFINED_KEY: &str = "(undefined)";
+
+/// Documents counted for one partition key value
+#[derive(Debug, Clone, PartialEq)]
+pub struct KeyCount {
+    pub key: String,
+    pub count: u64,
+}
+
+/// Summary of how documents spread over partition key values
+#[derive(Debug, Clone, PartialEq)]
+pub struct KeyDistribution {
+    /// Documents counted
+    pub total: u64,
+    /// Distinct partition key values seen
+    pub distinct: usize,
+    /// Most frequent values, largest first
+    pub top: Vec<KeyCount>,
+    /// Largest count divided by the mean count per value (1.0 is perfectly even)
+    pub skew: f64,
+}
+
+impl KeyDistribution {
+    /// Summarize `counts` (one entry per value; repeated values are added up),
+    /// keeping the `top` most frequent.
+    pub fn new(counts: impl IntoIterator<Item = KeyCount>, top: usize) -> Self {
+        let mut merged: HashMap<String, u64> = HashMap::new();
+        for KeyCount { key, count } in counts {
+            *merged.entry(key).or_default() += count;
+        }
+        let mut counts: Vec<KeyCount> = merged
+            .into_iter()
+            .map(|(key, count)| KeyCount { key, count })
+            .collect();
+        counts.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.key.cmp(&b.key)));
+
+        let total: u64 = counts.iter().map(|c| c.count).sum();
+        let distinct = counts.len();
+        let skew = match counts.first() {
+            Some(largest) if total > 0 => largest.count as f64 * distinct as f64 / total as f64,
+            _ => 0.0,
+        };
+        counts.truncate(top);
+        KeyDistribution {
+            total,
+            distinct,
+            top: counts,
+            skew,
+        }
+    }
+
+    /// Share of all documents held by `count`, in percent.
+    pub fn percent(&self, count: u64) -> f64 {
+        if self.total == 0 {
+            0.0
+        } else {
+            count as f64 * 100.0 / self.total as f64
+        }
+    }
+}
+
+/// A partition key path (`/customer/id`) as a SQL property reference
+/// (`c["customer"]["id"]`), safe for any property name.
+pub fn path_to_sql(path: &str) -> String {
+    path.trim_start_matches('/')
+        .split('/')
+        .fold(String::from("c"), |mut sql, segment| {
+            sql.push('[');
+            sql.push_str(&Value::String(segment.to_string()).to_string());
+            sql.push(']');
+            sql
+        })
+}
+
+/// SQL counting documents per partition key value. Each row has `k0`, `k1`,
+/// ... (one per path; absent when undefined) and `n`.
+pub fn group_by_sql(paths: &[String]) -> String {
+    let keys: Vec<String> = paths.iter().map(|p| path_to_sql(p)).collect();
+    let projection = keys
+        .iter()
+        .enumerate()
+        .map(|(i, key)| format!("{key} AS k{i}"))
+        .collect::<Vec<_>>()
+        .join(", ");
+    format!(
+        "SELECT {projection}, COUNT(1) AS n FROM c GROUP BY {}",
+        keys.join(", ")
+    )
+}
+
+/// SQL reading the partition key values of up to `limit` documents, in the
+/// same row shape as [`group_by_sql`] without `n`.
+pub fn sample_sql(paths: &[String], limit: usize) -> String {
+    let projection = paths
+        .iter()
+        .enumerate()
+        .map(|(i, path)| format!("{} AS k{i}", path_to_sql(path)))
+        .collect::<Vec<_>>()
+        .join(", ");
+    format!("SELECT TOP {limit} {projection} FROM c")
+}
+
+/// The count in a row of [`group_by_sql`] or [`sample_sql`] (1 for samples).
+pub fn row_count(row: &Value, paths: usize) -> KeyCount {
+    let key = (0..paths)
+        .map(|i| match row.get(format!("k{i}")) {
+            Some(Value::String(s)) => s.clone(),
+            Some(value) => value.to_string(),
+            None => UNDEFINED_KEY.to_string(),
+        })
+        .collect::<Vec<_>>()
+        .join(" / ");
+    let count = row.get("n").and_then(Value::as_u64).unwrap_or(1);
+    KeyCount { key, count }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_sql() {
+        let paths = vec!["/tenant".to_string(), "/customer/id".to_string()];
+        assert_eq!(
+            group_by_sql(&paths),
+            r#"SELECT c["tenant"] AS k0, c["customer"]["id"] AS k1, COUNT(1) AS n FROM c GROUP BY c["tenant"], c["customer"]["id"]"#
+        );
+        assert_eq!(
+            sample_sql(&paths[..1], 100),
+            r#"SELECT TOP 100 c["tenant"] AS k0 FROM c"#
+        );
+    }
+
+    #[test]
+    fn test_distribution() {
+        let rows = [
+            json!({"k0": "a", "n": 70}),
+            json!({"k0": "b", "n": 10}),
+            json!({"k0": 7, "n": 10}),
+            json!({"n": 5}),
+            json!({"k0": "b", "n": 5}),
+        ];
+        let distribution = KeyDistribution::new(rows.iter().map(|r| row_count(r, 1)), 2);
+        assert_eq!(distribution.total, 100);
+        assert_eq!(distribution.distinct, 4);
+        assert_eq!(
+            distribution.top,
+            vec![
+                KeyCount {
+                    key: "a".into(),
+                    count: 70
+                },
+                KeyCount {
+                    key: "b".into(),
+                    count: 15
+                },
+            ]
+        );
+        // 70 documents on one key vs. a mean of 25
+        assert!((distribution.skew - 2.8).abs() < 1e-9);
+        assert_eq!(distribution.percent(15), 15.0);
+        assert_eq!(row_count(&json!({}), 1).key, UNDEFINED_KEY);
+    }
+}
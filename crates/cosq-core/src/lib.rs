@@ -1,4 +1,34 @@
 //! Core types and configuration for cosq
 
+pub mod ai_log;
+pub mod ai_usage;
+pub mod chart;
+pub mod checks;
 pub mod config;
+pub mod debug_bundle;
+pub mod dedupe;
+pub mod delete_where;
+pub mod diff;
+pub mod dotenv;
+pub mod encryption;
+pub mod import;
+pub mod inventory;
+pub mod key_distribution;
+pub mod lint;
+pub mod listing_cache;
+pub mod local_query;
+pub mod plan_cache;
+pub mod prefs;
+pub mod query_builder;
+pub mod query_history;
+pub mod rbac_audit;
+pub mod redact;
+pub mod result_cache;
+pub mod schedule;
+pub mod schema;
+pub mod schema_export;
+pub mod secrets;
+pub mod session;
+pub mod snapshot;
+pub mod stats;
 pub mod stored_query;
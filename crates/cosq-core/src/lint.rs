@@ -0,0 +1,779 @@
+//! Static analysis of stored queries with Cosmos DB-specific rules
+//!
+//! Rules that only need the query file run offline. Rules that depend on the
+//! container's partition key or indexing policy run when a [`ContainerInfo`]
+//! is supplied for the targeted container.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use regex::Regex;
+
+use crate::stored_query::StoredQuery;
+
+/// Maximum number of template fields for which `SELECT *` is flagged
+const SELECT_STAR_FIELD_LIMIT: usize = 5;
+
+/// A single lint finding
+#[derive(Debug, Clone, PartialEq)]
+pub struct LintWarning {
+    /// Rule identifier (e.g. `cross-partition`)
+    pub rule: &'static str,
+    /// Step name for multi-step queries
+    pub step: Option<String>,
+    /// What is wrong
+    pub message: String,
+    /// How to fix it
+    pub suggestion: String,
+}
+
+/// Container metadata used by the partition key and indexing rules
+#[derive(Debug, Clone, Default)]
+pub struct ContainerInfo {
+    /// Partition key paths (e.g. `/customerId`)
+    pub partition_key_paths: Vec<String>,
+    /// Paths excluded from the range index (e.g. `/payload/*`)
+    pub excluded_paths: Vec<String>,
+    /// Composite indexes as ordered lists of `(path, order)` pairs
+    pub composite_indexes: Vec<Vec<(String, String)>>,
+}
+
+/// Lint a stored query. `containers` maps container names to their metadata;
+/// rules that need metadata are skipped for containers that are missing.
+pub fn lint_query(
+    query: &StoredQuery,
+    containers: &BTreeMap<String, ContainerInfo>,
+) -> Vec<LintWarning> {
+    let mut warnings = Vec::new();
+
+    if let Some(ref steps) = query.metadata.steps {
+        for step in steps {
+            if let Some(sql) = query.step_queries.get(&step.name) {
+                let template_fields = query
+                    .metadata
+                    .template
+                    .as_deref()
+                    .map(|t| template_fields(t, &step.name));
+                lint_sql(
+                    sql,
+                    Some(&step.name),
+                    template_fields.as_ref(),
+                    containers.get(&step.container),
+                    &mut warnings,
+                );
+            }
+        }
+    } else {
+        let template_fields = query
+            .metadata
+            .template
+            .as_deref()
+            .map(|t| template_fields(t, "documents"));
+        let info = query
+            .metadata
+            .container
+            .as_ref()
+            .and_then(|c| containers.get(c));
+        lint_sql(
+            &query.sql,
+            None,
+            template_fields.as_ref(),
+            info,
+            &mut warnings,
+        );
+    }
+
+    warnings
+}
+
+fn lint_sql(
+    sql: &str,
+    step: Option<&str>,
+    template_fields: Option<&BTreeSet<String>>,
+    info: Option<&ContainerInfo>,
+    warnings: &mut Vec<LintWarning>,
+) {
+    let sql = strip_comments(sql);
+    let mut push = |rule, message: String, suggestion: String| {
+        warnings.push(LintWarning {
+            rule,
+            step: step.map(String::from),
+            message,
+            suggestion,
+        });
+    };
+
+    // cross-partition: no WHERE at all, or no equality filter on the partition key
+    let where_clause = where_clause(&sql);
+    match (where_clause.as_deref(), info) {
+        (None, _) if !is_aggregate_only(&sql) && !has_top(&sql) => push(
+            "cross-partition",
+            "query has no WHERE clause and scans every partition".into(),
+            "add a filter on the partition key, or a TOP clause to bound the scan".into(),
+        ),
+        (Some(clause), Some(info)) if !info.partition_key_paths.is_empty() => {
+            let filtered = info.partition_key_paths.iter().any(|path| {
+                let field = path_to_field(path);
+                Regex::new(&format!(r"(?i)\bc\.{}\s*(=|\bIN\b)", regex::escape(&field)))
+                    .map(|re| re.is_match(clause))
+                    .unwrap_or(false)
+            });
+            if !filtered {
+                let paths = info.partition_key_paths.join(", ");
+                push(
+                    "cross-partition",
+                    format!(
+                        "query does not filter on the partition key ({paths}) and fans out to every partition"
+                    ),
+                    format!(
+                        "add `c.{} = @{}` to the WHERE clause when the value is known",
+                        path_to_field(&info.partition_key_paths[0]),
+                        param_name(&path_to_field(&info.partition_key_paths[0]))
+                    ),
+                );
+            }
+        }
+        _ => {}
+    }
+
+    // select-star: SELECT * while the template only uses a handful of fields
+    if let Some(fields) = template_fields {
+        let select_star = Regex::new(r"(?i)^\s*SELECT\s+(TOP\s+\S+\s+)?\*").unwrap();
+        if select_star.is_match(&sql)
+            && !fields.is_empty()
+            && fields.len() <= SELECT_STAR_FIELD_LIMIT
+        {
+            let projection = fields
+                .iter()
+                .map(|f| format!("c.{f}"))
+                .collect::<Vec<_>>()
+                .join(", ");
+            push(
+                "select-star",
+                format!(
+                    "SELECT * returns whole documents but the template only uses {} field(s)",
+                    fields.len()
+                ),
+                format!("project only what is rendered: SELECT {projection} FROM c"),
+            );
+        }
+    }
+
+    // order-by-unindexed: ORDER BY on excluded paths or without a composite index
+    if let Some(info) = info {
+        let order_by = order_by_fields(&sql);
+        if order_by.len() == 1 {
+            let (field, _) = &order_by[0];
+            if let Some(excluded) = info.excluded_paths.iter().find(|p| path_excluded(p, field)) {
+                push(
+                    "order-by-unindexed",
+                    format!("ORDER BY c.{field} uses a path excluded from indexing ({excluded})"),
+                    format!(
+                        "include /{}/? in the indexing policy",
+                        field.replace('.', "/")
+                    ),
+                );
+            }
+        } else if order_by.len() > 1 && !has_composite_index(&info.composite_indexes, &order_by) {
+            let fields = order_by
+                .iter()
+                .map(|(f, o)| format!("/{} {}", f.replace('.', "/"), o))
+                .collect::<Vec<_>>()
+                .join(", ");
+            push(
+                "order-by-unindexed",
+                "ORDER BY on multiple fields requires a matching composite index".into(),
+                format!("add a composite index ({fields}) to the indexing policy"),
+            );
+        }
+    }
+
+    // literal: hard-coded values compared in WHERE should usually be parameters
+    if let Some(clause) = where_clause {
+        let literal = Regex::new(
+            r#"(?i)\bc\.([\w.]+)\s*(=|!=|<>|>=|<=|<|>)\s*('[^']*'|"[^"]*"|-?\d+(?:\.\d+)?)"#,
+        )
+        .unwrap();
+        for cap in literal.captures_iter(&clause) {
+            let field = &cap[1];
+            push(
+                "literal",
+                format!("c.{field} is compared against the literal {}", &cap[3]),
+                format!(
+                    "replace it with @{} and declare it under `params:`",
+                    param_name(field)
+                ),
+            );
+        }
+    }
+}
+
+/// The statement shape of a SQL string, used to guard protected containers
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SqlShape {
+    /// A single SELECT statement
+    Select,
+    /// A single statement starting with a data-changing keyword (`DELETE`, `UPDATE`, ...)
+    Write(String),
+    /// More than one statement separated by `;`
+    Multiple,
+    /// Anything else (empty, or an unrecognised leading keyword)
+    Other,
+}
+
+impl SqlShape {
+    pub fn is_select(&self) -> bool {
+        matches!(self, SqlShape::Select)
+    }
+}
+
+/// Keywords that start a statement which would change data or schema
+const WRITE_KEYWORDS: &[&str] = &[
+    "DELETE", "UPDATE", "INSERT", "UPSERT", "REPLACE", "MERGE", "PATCH", "DROP", "CREATE", "ALTER",
+    "TRUNCATE",
+];
+
+/// Classify `sql` by its statements, ignoring comments, string literals and
+/// a trailing `;`.
+pub fn sql_shape(sql: &str) -> SqlShape {
+    let sql = strip_comments(sql);
+    let mut statements = split_statements(&sql)
+        .into_iter()
+        .filter(|s| !s.trim().is_empty());
+    let Some(first) = statements.next() else {
+        return SqlShape::Other;
+    };
+    if statements.next().is_some() {
+        return SqlShape::Multiple;
+    }
+    let keyword = first
+        .split(|c: char| !c.is_ascii_alphabetic())
+        .find(|w| !w.is_empty())
+        .unwrap_or_default()
+        .to_ascii_uppercase();
+    if keyword == "SELECT" {
+        SqlShape::Select
+    } else if WRITE_KEYWORDS.contains(&keyword.as_str()) {
+        SqlShape::Write(keyword)
+    } else {
+        SqlShape::Other
+    }
+}
+
+/// Split on `;` outside single- and double-quoted string literals.
+fn split_statements(sql: &str) -> Vec<&str> {
+    let mut statements = Vec::new();
+    let mut quote = None;
+    let mut start = 0;
+    for (i, c) in sql.char_indices() {
+        match (quote, c) {
+            (None, '\'' | '"') => quote = Some(c),
+            (Some(q), c) if c == q => quote = None,
+            (None, ';') => {
+                statements.push(&sql[start..i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    statements.push(&sql[start..]);
+    statements
+}
+
+/// Whether `sql` is an obviously analytical full scan: it aggregates
+/// (`GROUP BY`, `COUNT`/`SUM`/`AVG`/`MIN`/`MAX`) without any WHERE filter,
+/// so it reads every document of the container. Such queries are cheaper on
+/// the analytical store (Synapse Link / Fabric mirroring) when it is enabled.
+pub fn is_analytical_scan(sql: &str) -> bool {
+    let sql = strip_comments(sql);
+    if where_clause(&sql).is_some() {
+        return false;
+    }
+    Regex::new(r"(?is)\bGROUP\s+BY\b|^\s*SELECT\b.*?\b(COUNT|SUM|AVG|MIN|MAX)\s*\(.*?\bFROM\b")
+        .unwrap()
+        .is_match(&sql)
+}
+
+/// Whether a Cosmos DB error message is the "missing composite index" failure
+/// of an `ORDER BY` on multiple fields.
+pub fn is_composite_index_error(message: &str) -> bool {
+    message
+        .to_lowercase()
+        .contains("does not have a corresponding composite index")
+}
+
+/// The composite index that would serve the query's multi-field `ORDER BY`,
+/// as `(path, order)` pairs (e.g. `("/date", "descending")`).
+pub fn composite_index_for(sql: &str) -> Option<Vec<(String, String)>> {
+    let order_by = order_by_fields(&strip_comments(sql));
+    if order_by.len() < 2 {
+        return None;
+    }
+    Some(
+        order_by
+            .into_iter()
+            .map(|(field, order)| (format!("/{}", field.replace('.', "/")), order))
+            .collect(),
+    )
+}
+
+/// Remove `--` line comments from SQL, leaving `--` inside string literals.
+pub(crate) fn strip_comments(sql: &str) -> String {
+    let mut out = String::with_capacity(sql.len());
+    let mut quote = None;
+    let mut chars = sql.chars().peekable();
+    while let Some(ch) = chars.next() {
+        match (quote, ch) {
+            // A backslash escapes the next character, a doubled quote
+            // closes and reopens the literal
+            (Some(_), '\\') => {
+                out.push(ch);
+                out.extend(chars.next());
+                continue;
+            }
+            (Some(q), _) if ch == q => quote = None,
+            (Some(_), _) => {}
+            (None, '\'' | '"') => quote = Some(ch),
+            (None, '-') if chars.peek() == Some(&'-') => {
+                while chars.next_if(|&c| c != '\n').is_some() {}
+                continue;
+            }
+            (None, _) => {}
+        }
+        out.push(ch);
+    }
+    out
+}
+
+/// Extract the text of the WHERE clause (up to ORDER BY / GROUP BY / OFFSET).
+fn where_clause(sql: &str) -> Option<String> {
+    let re =
+        Regex::new(r"(?is)\bWHERE\b(.*?)(\bORDER\s+BY\b|\bGROUP\s+BY\b|\bOFFSET\b|$)").unwrap();
+    re.captures(sql).map(|c| c[1].trim().to_string())
+}
+
+/// Queries like `SELECT VALUE COUNT(1) FROM c` are expected to scan.
+fn is_aggregate_only(sql: &str) -> bool {
+    Regex::new(r"(?i)^\s*SELECT\s+VALUE\s+(COUNT|SUM|MIN|MAX|AVG)\s*\(")
+        .unwrap()
+        .is_match(sql)
+}
+
+/// Whether the query bounds its result with `SELECT TOP n`.
+fn has_top(sql: &str) -> bool {
+    Regex::new(r"(?i)^\s*SELECT\s+(DISTINCT\s+)?TOP\b")
+        .unwrap()
+        .is_match(sql)
+}
+
+/// Extract `(field, order)` pairs from the ORDER BY clause.
+fn order_by_fields(sql: &str) -> Vec<(String, String)> {
+    let re = Regex::new(r"(?is)\bORDER\s+BY\b(.*?)(\bOFFSET\b|$)").unwrap();
+    let Some(cap) = re.captures(sql) else {
+        return Vec::new();
+    };
+    let field_re = Regex::new(r"(?i)^c\.([\w.]+)(?:\s+(ASC|DESC))?$").unwrap();
+    cap[1]
+        .split(',')
+        .filter_map(|part| {
+            let part = part.trim();
+            field_re.captures(part).map(|c| {
+                let order = c
+                    .get(2)
+                    .map(|o| o.as_str().to_lowercase())
+                    .unwrap_or_else(|| "asc".into());
+                let order = if order == "desc" {
+                    "descending"
+                } else {
+                    "ascending"
+                };
+                (c[1].to_string(), order.to_string())
+            })
+        })
+        .collect()
+}
+
+/// Whether an excluded path pattern (e.g. `/payload/*`, `/*`) covers a field.
+fn path_excluded(pattern: &str, field: &str) -> bool {
+    let path = format!("/{}", field.replace('.', "/"));
+    let pattern = pattern.trim_end_matches("/?").trim_end_matches('/');
+    if let Some(prefix) = pattern.strip_suffix("/*") {
+        prefix.is_empty() || path == prefix || path.starts_with(&format!("{prefix}/"))
+    } else if pattern == "/*" || pattern.is_empty() {
+        true
+    } else {
+        path == pattern
+    }
+}
+
+fn has_composite_index(indexes: &[Vec<(String, String)>], order_by: &[(String, String)]) -> bool {
+    let flip = |o: &str| {
+        if o == "descending" {
+            "ascending"
+        } else {
+            "descending"
+        }
+    };
+    indexes.iter().any(|index| {
+        if index.len() < order_by.len() {
+            return false;
+        }
+        let pairs = index.iter().zip(order_by);
+        let same = pairs
+            .clone()
+            .all(|((path, order), (field, want))| path_to_field(path) == *field && order == want);
+        // A composite index also serves the fully reversed ordering
+        let reversed = pairs.clone().all(|((path, order), (field, want))| {
+            path_to_field(path) == *field && order == flip(want)
+        });
+        same || reversed
+    })
+}
+
+/// Convert an index/partition path (`/address/city`) to a SQL field (`address.city`).
+fn path_to_field(path: &str) -> String {
+    path.trim_start_matches('/')
+        .trim_end_matches("/?")
+        .replace('/', ".")
+}
+
+/// Suggest a parameter name from a field path (`address.city` → `city`).
+fn param_name(field: &str) -> String {
+    field.rsplit('.').next().unwrap_or(field).to_string()
+}
+
+/// The property names a query's results can have, from its SELECT list:
+/// aliases, the last segment of `c.a.b` paths, and `$1`, `$2`, ... for
+/// unnamed expressions. `None` when the shape is open-ended (`SELECT *`,
+/// `SELECT VALUE`, or a projection cosq can't parse).
+pub fn projected_fields(sql: &str) -> Option<BTreeSet<String>> {
+    let sql = strip_comments(sql);
+    let head = Regex::new(r"(?is)^\s*SELECT\s+(DISTINCT\s+)?(TOP\s+\S+\s+)?").unwrap();
+    let rest = &sql[head.find(&sql)?.end()..];
+    if Regex::new(r"(?i)^(VALUE\b|\*)").unwrap().is_match(rest) {
+        return None;
+    }
+
+    // The SELECT list ends at the first FROM outside brackets
+    let mut depth = 0i32;
+    let mut items = vec![String::new()];
+    let from = Regex::new(r"(?i)^FROM\b").unwrap();
+    for (i, c) in rest.char_indices() {
+        match c {
+            '(' | '[' | '{' => depth += 1,
+            ')' | ']' | '}' => depth -= 1,
+            ',' if depth == 0 => {
+                items.push(String::new());
+                continue;
+            }
+            _ if depth == 0
+                && from.is_match(&rest[i..])
+                && rest[..i].ends_with(char::is_whitespace) =>
+            {
+                break;
+            }
+            _ => {}
+        }
+        items.last_mut()?.push(c);
+    }
+
+    let alias = Regex::new(r"(?is)\bAS\s+(\w+)\s*$").unwrap();
+    let path = Regex::new(r"^\w+(?:\.(\w+))+$").unwrap();
+    let bracket = Regex::new(r#"^\w+(?:\.\w+)*\[\s*["']([^"']+)["']\s*\]$"#).unwrap();
+    let mut fields = BTreeSet::new();
+    let mut unnamed = 0;
+    for item in items.iter().map(|i| i.trim()) {
+        if item.is_empty() {
+            return None;
+        }
+        let name = if let Some(cap) = alias.captures(item) {
+            cap[1].to_string()
+        } else if let Some(cap) = path.captures(item) {
+            cap[1].to_string()
+        } else if let Some(cap) = bracket.captures(item) {
+            cap[1].to_string()
+        } else {
+            unnamed += 1;
+            format!("${unnamed}")
+        };
+        fields.insert(name);
+    }
+    Some(fields)
+}
+
+/// Collect the top-level document fields a template reads from `collection`.
+///
+/// Understands `{% for x in collection %} … x.field` loops as well as direct
+/// `collection[0].field` indexing.
+pub fn template_fields(template: &str, collection: &str) -> BTreeSet<String> {
+    let mut fields = BTreeSet::new();
+    let collection = regex::escape(collection);
+
+    let loop_re = Regex::new(&format!(r"\{{%-?\s*for\s+(\w+)\s+in\s+{collection}\b")).unwrap();
+    for cap in loop_re.captures_iter(template) {
+        let var = regex::escape(&cap[1]);
+        let field_re = Regex::new(&format!(r"\b{var}\.(\w+)")).unwrap();
+        for f in field_re.captures_iter(template) {
+            fields.insert(f[1].to_string());
+        }
+    }
+
+    let index_re = Regex::new(&format!(r"\b{collection}\[\d+\]\.(\w+)")).unwrap();
+    for cap in index_re.captures_iter(template) {
+        fields.insert(cap[1].to_string());
+    }
+
+    fields
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(contents: &str) -> StoredQuery {
+        StoredQuery::parse("test", contents).unwrap()
+    }
+
+    fn rules(warnings: &[LintWarning]) -> Vec<&'static str> {
+        warnings.iter().map(|w| w.rule).collect()
+    }
+
+    #[test]
+    fn test_projected_fields() {
+        let fields = |sql| projected_fields(sql).map(|f| f.into_iter().collect::<Vec<_>>());
+        assert_eq!(fields("SELECT * FROM c"), None);
+        assert_eq!(fields("SELECT TOP 10 * FROM c"), None);
+        assert_eq!(fields("SELECT VALUE c.id FROM c"), None);
+        assert_eq!(
+            fields("SELECT c.id, c.address.city, c[\"first-name\"] FROM c WHERE c.x = 1"),
+            Some(vec!["city".into(), "first-name".into(), "id".into()])
+        );
+        assert_eq!(
+            fields(
+                "SELECT DISTINCT TOP 5 c.id, COUNT(1) AS total, CONCAT(c.a, \", \", c.b) AS label, LOWER(c.name)\nFROM c GROUP BY c.id"
+            ),
+            Some(vec![
+                "$1".into(),
+                "id".into(),
+                "label".into(),
+                "total".into()
+            ])
+        );
+        assert_eq!(
+            fields("SELECT c.id, c.tags['primary'] FROM c"),
+            Some(vec!["id".into(), "primary".into()])
+        );
+    }
+
+    #[test]
+    fn test_no_where_is_cross_partition() {
+        let query = parse("---\ndescription: all\n---\nSELECT * FROM c\n");
+        let warnings = lint_query(&query, &BTreeMap::new());
+        assert_eq!(rules(&warnings), vec!["cross-partition"]);
+    }
+
+    #[test]
+    fn test_count_and_top_are_not_flagged() {
+        let query = parse("---\ndescription: count\n---\nSELECT VALUE COUNT(1) FROM c\n");
+        assert!(lint_query(&query, &BTreeMap::new()).is_empty());
+        let query = parse("---\ndescription: peek\n---\nSELECT TOP 10 * FROM c\n");
+        assert!(lint_query(&query, &BTreeMap::new()).is_empty());
+    }
+
+    #[test]
+    fn test_missing_partition_key_filter() {
+        let query = parse(
+            "---\ndescription: t\ncontainer: orders\nparams:\n  - name: status\n    type: string\n---\nSELECT * FROM c WHERE c.status = @status\n",
+        );
+        let mut containers = BTreeMap::new();
+        containers.insert(
+            "orders".to_string(),
+            ContainerInfo {
+                partition_key_paths: vec!["/customerId".into()],
+                ..Default::default()
+            },
+        );
+        let warnings = lint_query(&query, &containers);
+        assert_eq!(rules(&warnings), vec!["cross-partition"]);
+        assert!(
+            warnings[0]
+                .suggestion
+                .contains("c.customerId = @customerId")
+        );
+
+        let query = parse(
+            "---\ndescription: t\ncontainer: orders\n---\nSELECT * FROM c WHERE c.customerId = @id\n",
+        );
+        assert!(lint_query(&query, &containers).is_empty());
+    }
+
+    #[test]
+    fn test_literal_should_be_param() {
+        let query = parse(
+            "---\ndescription: t\n---\nSELECT * FROM c WHERE c.status = 'shipped' AND c.total > 100\n",
+        );
+        let warnings = lint_query(&query, &BTreeMap::new());
+        assert_eq!(rules(&warnings), vec!["literal", "literal"]);
+        assert!(warnings[0].suggestion.contains("@status"));
+        assert!(warnings[1].message.contains("100"));
+    }
+
+    #[test]
+    fn test_select_star_with_narrow_template() {
+        let query = parse(
+            "---\ndescription: t\ntemplate: |\n  {% for doc in documents %}{{ doc.id }} {{ doc.name }}{% endfor %}\n---\nSELECT * FROM c WHERE c.id = @id\n",
+        );
+        let warnings = lint_query(&query, &BTreeMap::new());
+        assert_eq!(rules(&warnings), vec!["select-star"]);
+        assert!(
+            warnings[0]
+                .suggestion
+                .contains("SELECT c.id, c.name FROM c")
+        );
+    }
+
+    #[test]
+    fn test_order_by_excluded_path() {
+        let query = parse(
+            "---\ndescription: t\ncontainer: logs\n---\nSELECT * FROM c WHERE c.tenant = @t ORDER BY c.payload.ts DESC\n",
+        );
+        let mut containers = BTreeMap::new();
+        containers.insert(
+            "logs".to_string(),
+            ContainerInfo {
+                partition_key_paths: vec!["/tenant".into()],
+                excluded_paths: vec!["/payload/*".into()],
+                ..Default::default()
+            },
+        );
+        let warnings = lint_query(&query, &containers);
+        assert_eq!(rules(&warnings), vec!["order-by-unindexed"]);
+    }
+
+    #[test]
+    fn test_order_by_multiple_needs_composite() {
+        let sql = "---\ndescription: t\ncontainer: orders\n---\nSELECT * FROM c WHERE c.pk = @pk ORDER BY c.date DESC, c.total ASC\n";
+        let query = parse(sql);
+        let mut info = ContainerInfo {
+            partition_key_paths: vec!["/pk".into()],
+            ..Default::default()
+        };
+        let mut containers = BTreeMap::new();
+        containers.insert("orders".to_string(), info.clone());
+        assert_eq!(
+            rules(&lint_query(&query, &containers)),
+            vec!["order-by-unindexed"]
+        );
+
+        // The reversed composite index also satisfies the ordering
+        info.composite_indexes = vec![vec![
+            ("/date".into(), "ascending".into()),
+            ("/total".into(), "descending".into()),
+        ]];
+        containers.insert("orders".to_string(), info);
+        assert!(lint_query(&query, &containers).is_empty());
+    }
+
+    #[test]
+    fn test_composite_index_for() {
+        let index = composite_index_for(
+            "SELECT * FROM c ORDER BY c.date DESC, c.customer.name -- newest first\nOFFSET 0 LIMIT 10",
+        )
+        .unwrap();
+        assert_eq!(
+            index,
+            vec![
+                ("/date".to_string(), "descending".to_string()),
+                ("/customer/name".to_string(), "ascending".to_string()),
+            ]
+        );
+        assert!(composite_index_for("SELECT * FROM c ORDER BY c.date").is_none());
+        assert!(is_composite_index_error(
+            "The order by query does not have a corresponding composite index that it can be served from."
+        ));
+    }
+
+    #[test]
+    fn test_multi_step_warnings_carry_step_name() {
+        let query = parse(
+            "---\ndescription: t\nsteps:\n  - name: all\n    container: c1\n---\n-- step: all\nSELECT * FROM c\n",
+        );
+        let warnings = lint_query(&query, &BTreeMap::new());
+        assert_eq!(warnings[0].step.as_deref(), Some("all"));
+    }
+
+    #[test]
+    fn test_template_fields() {
+        let fields = template_fields(
+            "{{ documents[0].total }}{% for o in documents %}{{ o.id }}{% endfor %}",
+            "documents",
+        );
+        assert_eq!(
+            fields.into_iter().collect::<Vec<_>>(),
+            vec!["id".to_string(), "total".to_string()]
+        );
+    }
+
+    #[test]
+    fn test_path_excluded() {
+        assert!(path_excluded("/*", "anything"));
+        assert!(path_excluded("/payload/*", "payload.ts"));
+        assert!(path_excluded("/name/?", "name"));
+        assert!(!path_excluded("/payload/*", "status"));
+    }
+
+    #[test]
+    fn test_is_analytical_scan() {
+        assert!(is_analytical_scan(
+            "SELECT c.status, COUNT(1) AS n FROM c GROUP BY c.status"
+        ));
+        assert!(is_analytical_scan("SELECT VALUE SUM(c.total) FROM c"));
+        assert!(!is_analytical_scan(
+            "SELECT VALUE SUM(c.total) FROM c WHERE c.customerId = @id"
+        ));
+        assert!(!is_analytical_scan("SELECT * FROM c"));
+        assert!(!is_analytical_scan("SELECT c.id FROM c JOIN t IN c.tags"));
+    }
+
+    #[test]
+    fn test_sql_shape() {
+        assert_eq!(sql_shape("SELECT * FROM c"), SqlShape::Select);
+        assert_eq!(
+            sql_shape("-- top orders\n  select TOP 5 * FROM c;"),
+            SqlShape::Select
+        );
+        assert_eq!(
+            sql_shape("SELECT * FROM c WHERE c.note = 'a; DELETE FROM c'"),
+            SqlShape::Select
+        );
+        assert_eq!(
+            sql_shape("delete FROM c WHERE c.id = '1'"),
+            SqlShape::Write("DELETE".into())
+        );
+        assert_eq!(
+            sql_shape("SELECT * FROM c; DROP TABLE c"),
+            SqlShape::Multiple
+        );
+        assert_eq!(sql_shape("EXEC sp_reset"), SqlShape::Other);
+        assert_eq!(sql_shape("  "), SqlShape::Other);
+        assert_eq!(
+            sql_shape("SELECT * FROM c WHERE c.code = 'AB--1'; DELETE FROM c"),
+            SqlShape::Multiple
+        );
+    }
+
+    #[test]
+    fn test_strip_comments() {
+        assert_eq!(
+            strip_comments("SELECT * FROM c -- all\nWHERE c.a = 1"),
+            "SELECT * FROM c \nWHERE c.a = 1"
+        );
+        assert_eq!(
+            strip_comments("SELECT * FROM c WHERE c.code = 'AB--1' -- note"),
+            "SELECT * FROM c WHERE c.code = 'AB--1' "
+        );
+        assert_eq!(
+            strip_comments(r#"WHERE c.a = 'it''s -- x' AND c.b = "q\"--" --"#),
+            r#"WHERE c.a = 'it''s -- x' AND c.b = "q\"--" "#
+        );
+    }
+}
@@ -0,0 +1,149 @@
+//! Cached database and container listings
+//!
+//! Interactive pickers and shell completions read database and container
+//! names from `~/.cosq/cache/listings/<account>.json` instead of listing the
+//! account every time. Listings older than [`TTL_SECS`] are still shown, but
+//! refreshed from the account while the picker is open.
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+/// How long a listing is used without refreshing it
+pub const TTL_SECS: i64 = 600;
+
+#[derive(Debug, Error)]
+pub enum ListingCacheError {
+    #[error("could not determine home directory")]
+    NoHomeDir,
+
+    #[error("failed to read or write listing cache: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("invalid listing cache file: {0}")]
+    Parse(#[from] serde_json::Error),
+}
+
+/// One listing (database or container names) and when it was fetched
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Listing {
+    /// RFC 3339 timestamp of the request
+    pub fetched_at: String,
+    pub names: Vec<String>,
+}
+
+impl Listing {
+    pub fn new(names: Vec<String>) -> Self {
+        Self {
+            fetched_at: Utc::now().to_rfc3339(),
+            names,
+        }
+    }
+
+    /// Whether the listing is younger than [`TTL_SECS`] at `now`.
+    pub fn is_fresh(&self, now: DateTime<Utc>) -> bool {
+        DateTime::parse_from_rfc3339(&self.fetched_at)
+            .is_ok_and(|at| (now - at.with_timezone(&Utc)).num_seconds() < TTL_SECS)
+    }
+}
+
+/// Cached listings of one account
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct AccountListings {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub databases: Option<Listing>,
+    /// Container listings keyed by database
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub containers: BTreeMap<String, Listing>,
+}
+
+impl AccountListings {
+    /// Cache path: `~/.cosq/cache/listings/<account>.json`.
+    pub fn path(account: &str) -> Result<PathBuf, ListingCacheError> {
+        dirs::home_dir()
+            .map(|d| {
+                d.join(".cosq")
+                    .join("cache")
+                    .join("listings")
+                    .join(format!("{account}.json"))
+            })
+            .ok_or(ListingCacheError::NoHomeDir)
+    }
+
+    /// Load the listings cached for this account (empty if there are none).
+    pub fn load(account: &str) -> Result<Self, ListingCacheError> {
+        Self::load_from(&Self::path(account)?)
+    }
+
+    pub fn load_from(path: &Path) -> Result<Self, ListingCacheError> {
+        match std::fs::read_to_string(path) {
+            Ok(contents) => Ok(serde_json::from_str(&contents)?),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    pub fn save_to(&self, path: &Path) -> Result<(), ListingCacheError> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    /// Load the listings at `path`, apply `change`, and save them back.
+    pub fn update(path: &Path, change: impl FnOnce(&mut Self)) -> Result<(), ListingCacheError> {
+        let mut listings = Self::load_from(path).unwrap_or_default();
+        change(&mut listings);
+        listings.save_to(path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_fresh() {
+        let listing = Listing {
+            fetched_at: "2026-01-01T12:00:00Z".into(),
+            names: vec!["app".into()],
+        };
+        let at = |s: &str| DateTime::parse_from_rfc3339(s).unwrap().with_timezone(&Utc);
+        assert!(listing.is_fresh(at("2026-01-01T12:09:59Z")));
+        assert!(!listing.is_fresh(at("2026-01-01T12:10:00Z")));
+
+        let garbled = Listing {
+            fetched_at: "yesterday".into(),
+            names: Vec::new(),
+        };
+        assert!(!garbled.is_fresh(Utc::now()));
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("listings").join("prod.json");
+        assert_eq!(
+            AccountListings::load_from(&path).unwrap(),
+            AccountListings::default()
+        );
+
+        let mut listings = AccountListings {
+            databases: Some(Listing::new(vec!["app".into(), "logs".into()])),
+            ..Default::default()
+        };
+        listings
+            .containers
+            .insert("app".into(), Listing::new(vec!["orders".into()]));
+        listings.save_to(&path).unwrap();
+
+        let loaded = AccountListings::load_from(&path).unwrap();
+        assert_eq!(loaded, listings);
+        assert!(loaded.databases.unwrap().is_fresh(Utc::now()));
+        assert_eq!(loaded.containers["app"].names, vec!["orders"]);
+    }
+}
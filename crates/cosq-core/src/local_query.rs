@@ -0,0 +1,1042 @@
+//! Evaluate a subset of Cosmos DB SQL against documents in memory
+//!
+//! Lets `cosq query --from-file` re-query exported snapshots offline with the
+//! same syntax. Supported: `SELECT [TOP n] [VALUE] * | <expr> [AS name], ...
+//! FROM <alias> [WHERE ...] [ORDER BY ...] [OFFSET n LIMIT m]`, with
+//! comparisons, `AND`/`OR`/`NOT`, `IN`, `BETWEEN` and common type checking,
+//! string and array functions. `@name` parameters are bound from Cosmos-style
+//! `{"name": "@name", "value": ...}` objects. Mismatched types compare as
+//! undefined, like in Cosmos DB, so such documents are filtered out.
+//!
+//! ```
+//! use cosq_core::local_query::evaluate;
+//! use serde_json::json;
+//!
+//! let docs = vec![json!({"id": "a", "n": 3}), json!({"id": "b", "n": 7})];
+//! let out = evaluate("SELECT VALUE c.id FROM c WHERE c.n > 5", docs).unwrap();
+//! assert_eq!(out, vec![json!("b")]);
+//! ```
+
+use std::cmp::Ordering;
+
+use serde_json::{Map, Value};
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum LocalQueryError {
+    #[error("syntax error: {0}")]
+    Syntax(String),
+
+    #[error("{0}: not supported when querying a local file")]
+    Unsupported(String),
+
+    #[error("unknown identifier '{0}' — only the FROM alias '{1}' is in scope")]
+    UnknownIdentifier(String, String),
+
+    #[error("query parameter '@{0}' has no value")]
+    UnboundParameter(String),
+}
+
+type ParseResult<T> = Result<T, LocalQueryError>;
+
+/// Evaluate `sql` against `documents`.
+pub fn evaluate(sql: &str, documents: Vec<Value>) -> Result<Vec<Value>, LocalQueryError> {
+    Ok(LocalQuery::parse(sql)?.execute(documents))
+}
+
+/// Evaluate `sql` against `documents`, binding `@name` parameters from
+/// `{"name": "@name", "value": ...}` objects.
+pub fn evaluate_with_params(
+    sql: &str,
+    parameters: &[Value],
+    documents: Vec<Value>,
+) -> Result<Vec<Value>, LocalQueryError> {
+    Ok(LocalQuery::parse_with_params(sql, parameters)?.execute(documents))
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Ident(String),
+    Number(Value),
+    Str(String),
+    Param(String),
+    Symbol(&'static str),
+}
+
+const SYMBOLS: &[&str] = &[
+    "!=", "<>", "<=", ">=", "=", "<", ">", "(", ")", ",", ".", "[", "]", "*", "-",
+];
+
+fn tokenize(sql: &str) -> ParseResult<Vec<Token>> {
+    let chars: Vec<char> = sql.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let ch = chars[i];
+        if ch.is_whitespace() {
+            i += 1;
+        } else if ch == '-' && chars.get(i + 1) == Some(&'-') {
+            while i < chars.len() && chars[i] != '\n' {
+                i += 1;
+            }
+        } else if ch.is_ascii_alphabetic() || ch == '_' {
+            let start = i;
+            while i < chars.len() && (chars[i].is_ascii_alphanumeric() || chars[i] == '_') {
+                i += 1;
+            }
+            tokens.push(Token::Ident(chars[start..i].iter().collect()));
+        } else if ch.is_ascii_digit() {
+            let start = i;
+            while i < chars.len()
+                && (chars[i].is_ascii_digit()
+                    || matches!(chars[i], 'e' | 'E')
+                    || (chars[i] == '.' && chars.get(i + 1).is_some_and(char::is_ascii_digit))
+                    || (matches!(chars[i], '+' | '-') && matches!(chars[i - 1], 'e' | 'E')))
+            {
+                i += 1;
+            }
+            let text: String = chars[start..i].iter().collect();
+            let number = serde_json::from_str::<Value>(&text)
+                .ok()
+                .filter(Value::is_number)
+                .ok_or_else(|| LocalQueryError::Syntax(format!("invalid number '{text}'")))?;
+            tokens.push(Token::Number(number));
+        } else if ch == '\'' || ch == '"' {
+            let mut text = String::new();
+            i += 1;
+            loop {
+                match chars.get(i) {
+                    None => {
+                        return Err(LocalQueryError::Syntax(
+                            "unterminated string literal".to_string(),
+                        ));
+                    }
+                    Some(&c) if c == ch => break,
+                    Some('\\') => {
+                        i += 1;
+                        match chars.get(i) {
+                            Some('n') => text.push('\n'),
+                            Some('t') => text.push('\t'),
+                            Some(&c) => text.push(c),
+                            None => continue,
+                        }
+                    }
+                    Some(&c) => text.push(c),
+                }
+                i += 1;
+            }
+            i += 1;
+            tokens.push(Token::Str(text));
+        } else if ch == '@' {
+            let start = i + 1;
+            i = start;
+            while i < chars.len() && (chars[i].is_ascii_alphanumeric() || chars[i] == '_') {
+                i += 1;
+            }
+            if i == start {
+                return Err(LocalQueryError::Syntax(
+                    "expected a parameter name after '@'".to_string(),
+                ));
+            }
+            tokens.push(Token::Param(chars[start..i].iter().collect()));
+        } else {
+            let rest: String = chars[i..chars.len().min(i + 2)].iter().collect();
+            let symbol = SYMBOLS
+                .iter()
+                .find(|s| rest.starts_with(**s))
+                .ok_or_else(|| LocalQueryError::Syntax(format!("unexpected character '{ch}'")))?;
+            i += symbol.len();
+            tokens.push(Token::Symbol(symbol));
+        }
+    }
+    Ok(tokens)
+}
+
+/// One step of a property path: `.name` / `["name"]` or `[0]`
+#[derive(Debug, Clone, PartialEq)]
+enum Segment {
+    Key(String),
+    Index(usize),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum CompareOp {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Expr {
+    Literal(Value),
+    /// Path below the FROM alias (empty for the alias itself)
+    Path(Vec<Segment>),
+    Not(Box<Expr>),
+    And(Box<Expr>, Box<Expr>),
+    Or(Box<Expr>, Box<Expr>),
+    Compare(Box<Expr>, CompareOp, Box<Expr>),
+    In(Box<Expr>, Vec<Expr>),
+    Between(Box<Expr>, Box<Expr>, Box<Expr>),
+    Call(Function, Vec<Expr>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Function {
+    IsDefined,
+    IsNull,
+    IsString,
+    IsNumber,
+    IsBool,
+    IsArray,
+    IsObject,
+    Contains,
+    StartsWith,
+    EndsWith,
+    Lower,
+    Upper,
+    Length,
+    ArrayContains,
+    ArrayLength,
+}
+
+impl Function {
+    fn lookup(name: &str) -> Option<(Function, usize, usize)> {
+        // (function, min args, max args)
+        Some(match name.to_ascii_uppercase().as_str() {
+            "IS_DEFINED" => (Function::IsDefined, 1, 1),
+            "IS_NULL" => (Function::IsNull, 1, 1),
+            "IS_STRING" => (Function::IsString, 1, 1),
+            "IS_NUMBER" => (Function::IsNumber, 1, 1),
+            "IS_BOOL" => (Function::IsBool, 1, 1),
+            "IS_ARRAY" => (Function::IsArray, 1, 1),
+            "IS_OBJECT" => (Function::IsObject, 1, 1),
+            "CONTAINS" => (Function::Contains, 2, 3),
+            "STARTSWITH" => (Function::StartsWith, 2, 3),
+            "ENDSWITH" => (Function::EndsWith, 2, 3),
+            "LOWER" => (Function::Lower, 1, 1),
+            "UPPER" => (Function::Upper, 1, 1),
+            "LENGTH" => (Function::Length, 1, 1),
+            "ARRAY_CONTAINS" => (Function::ArrayContains, 2, 3),
+            "ARRAY_LENGTH" => (Function::ArrayLength, 1, 1),
+            _ => return None,
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Projection {
+    /// `SELECT *`
+    All,
+    /// `SELECT VALUE <expr>`
+    Value(Expr),
+    /// `SELECT <expr> [AS name], ...`
+    Fields(Vec<(Expr, Option<String>)>),
+}
+
+/// A parsed query, ready to run against documents
+#[derive(Debug, Clone, PartialEq)]
+pub struct LocalQuery {
+    top: Option<usize>,
+    projection: Projection,
+    filter: Option<Expr>,
+    order_by: Vec<(Expr, bool)>,
+    offset_limit: Option<(usize, usize)>,
+}
+
+const RESERVED: &[&str] = &[
+    "SELECT", "TOP", "VALUE", "DISTINCT", "FROM", "WHERE", "ORDER", "BY", "ASC", "DESC", "OFFSET",
+    "LIMIT", "AND", "OR", "NOT", "IN", "BETWEEN", "AS", "JOIN", "GROUP",
+];
+
+struct Parser<'a> {
+    tokens: Vec<Token>,
+    pos: usize,
+    alias: String,
+    parameters: &'a [Value],
+}
+
+impl Parser<'_> {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token
+    }
+
+    fn peek_keyword(&self, keyword: &str) -> bool {
+        matches!(self.peek(), Some(Token::Ident(word)) if word.eq_ignore_ascii_case(keyword))
+    }
+
+    fn eat_keyword(&mut self, keyword: &str) -> bool {
+        let found = self.peek_keyword(keyword);
+        if found {
+            self.pos += 1;
+        }
+        found
+    }
+
+    fn expect_keyword(&mut self, keyword: &str) -> ParseResult<()> {
+        if self.eat_keyword(keyword) {
+            Ok(())
+        } else {
+            Err(self.unexpected(keyword))
+        }
+    }
+
+    fn eat_symbol(&mut self, symbol: &str) -> bool {
+        let found = matches!(self.peek(), Some(Token::Symbol(s)) if *s == symbol);
+        if found {
+            self.pos += 1;
+        }
+        found
+    }
+
+    fn expect_symbol(&mut self, symbol: &str) -> ParseResult<()> {
+        if self.eat_symbol(symbol) {
+            Ok(())
+        } else {
+            Err(self.unexpected(&format!("'{symbol}'")))
+        }
+    }
+
+    fn unexpected(&self, expected: &str) -> LocalQueryError {
+        let found = match self.peek() {
+            None => "end of query".to_string(),
+            Some(Token::Ident(word)) => format!("'{word}'"),
+            Some(Token::Number(n)) => n.to_string(),
+            Some(Token::Str(s)) => format!("'{s}'"),
+            Some(Token::Param(name)) => format!("'@{name}'"),
+            Some(Token::Symbol(s)) => format!("'{s}'"),
+        };
+        LocalQueryError::Syntax(format!("expected {expected}, found {found}"))
+    }
+
+    fn identifier(&mut self) -> ParseResult<String> {
+        match self.next() {
+            Some(Token::Ident(word)) if !RESERVED.iter().any(|r| word.eq_ignore_ascii_case(r)) => {
+                Ok(word)
+            }
+            _ => {
+                self.pos -= 1;
+                Err(self.unexpected("an identifier"))
+            }
+        }
+    }
+
+    fn unsigned(&mut self) -> ParseResult<usize> {
+        match self.next() {
+            Some(Token::Number(Value::Number(n))) if n.as_u64().is_some() => {
+                Ok(n.as_u64().unwrap_or_default() as usize)
+            }
+            _ => {
+                self.pos -= 1;
+                Err(self.unexpected("a non-negative integer"))
+            }
+        }
+    }
+
+    fn query(&mut self) -> ParseResult<LocalQuery> {
+        self.expect_keyword("SELECT")?;
+        if self.peek_keyword("DISTINCT") {
+            return Err(LocalQueryError::Unsupported("DISTINCT".to_string()));
+        }
+        let top = if self.eat_keyword("TOP") {
+            Some(self.unsigned()?)
+        } else {
+            None
+        };
+
+        // The projection refers to the FROM alias, which comes later: skip
+        // ahead to find it, then parse the projection.
+        let projection_start = self.pos;
+        let mut depth = 0usize;
+        while let Some(token) = self.peek() {
+            let after_dot = matches!(
+                self.tokens.get(self.pos.wrapping_sub(1)),
+                Some(Token::Symbol("."))
+            );
+            match token {
+                Token::Symbol("(") => depth += 1,
+                Token::Symbol(")") => depth = depth.saturating_sub(1),
+                Token::Ident(word)
+                    if depth == 0 && !after_dot && word.eq_ignore_ascii_case("FROM") =>
+                {
+                    break;
+                }
+                _ => {}
+            }
+            self.pos += 1;
+        }
+        self.expect_keyword("FROM")?;
+        self.alias = self.identifier()?;
+        let aliased = self.eat_keyword("AS")
+            || matches!(self.peek(), Some(Token::Ident(word))
+                if !RESERVED.iter().any(|r| word.eq_ignore_ascii_case(r)));
+        if aliased {
+            // `FROM root r`: the collection name is irrelevant, the alias is in scope
+            self.alias = self.identifier()?;
+        }
+        if self.peek_keyword("JOIN") {
+            return Err(LocalQueryError::Unsupported("JOIN".to_string()));
+        }
+        let after_from = self.pos;
+
+        self.pos = projection_start;
+        let projection = self.projection()?;
+        if !self.peek_keyword("FROM") {
+            return Err(self.unexpected("FROM"));
+        }
+        self.pos = after_from;
+
+        let filter = if self.eat_keyword("WHERE") {
+            Some(self.expr()?)
+        } else {
+            None
+        };
+        if self.peek_keyword("GROUP") {
+            return Err(LocalQueryError::Unsupported("GROUP BY".to_string()));
+        }
+        let mut order_by = Vec::new();
+        if self.eat_keyword("ORDER") {
+            self.expect_keyword("BY")?;
+            loop {
+                let expr = self.expr()?;
+                let descending = if self.eat_keyword("DESC") {
+                    true
+                } else {
+                    self.eat_keyword("ASC");
+                    false
+                };
+                order_by.push((expr, descending));
+                if !self.eat_symbol(",") {
+                    break;
+                }
+            }
+        }
+        let offset_limit = if self.eat_keyword("OFFSET") {
+            let offset = self.unsigned()?;
+            self.expect_keyword("LIMIT")?;
+            Some((offset, self.unsigned()?))
+        } else {
+            None
+        };
+        if self.peek().is_some() {
+            return Err(self.unexpected("end of query"));
+        }
+        Ok(LocalQuery {
+            top,
+            projection,
+            filter,
+            order_by,
+            offset_limit,
+        })
+    }
+
+    fn projection(&mut self) -> ParseResult<Projection> {
+        if self.eat_symbol("*") {
+            return Ok(Projection::All);
+        }
+        if self.eat_keyword("VALUE") {
+            return Ok(Projection::Value(self.expr()?));
+        }
+        let mut fields = Vec::new();
+        loop {
+            let expr = self.expr()?;
+            let named = self.eat_keyword("AS")
+                || (matches!(self.peek(), Some(Token::Ident(_))) && !self.peek_keyword("FROM"));
+            let name = if named {
+                Some(self.identifier()?)
+            } else {
+                None
+            };
+            fields.push((expr, name));
+            if !self.eat_symbol(",") {
+                break;
+            }
+        }
+        Ok(Projection::Fields(fields))
+    }
+
+    fn expr(&mut self) -> ParseResult<Expr> {
+        let mut left = self.and()?;
+        while self.eat_keyword("OR") {
+            left = Expr::Or(Box::new(left), Box::new(self.and()?));
+        }
+        Ok(left)
+    }
+
+    fn and(&mut self) -> ParseResult<Expr> {
+        let mut left = self.not()?;
+        while self.eat_keyword("AND") {
+            left = Expr::And(Box::new(left), Box::new(self.not()?));
+        }
+        Ok(left)
+    }
+
+    fn not(&mut self) -> ParseResult<Expr> {
+        if self.eat_keyword("NOT") {
+            return Ok(Expr::Not(Box::new(self.not()?)));
+        }
+        self.comparison()
+    }
+
+    fn comparison(&mut self) -> ParseResult<Expr> {
+        let left = self.operand()?;
+        let op = match self.peek() {
+            Some(Token::Symbol("=")) => Some(CompareOp::Eq),
+            Some(Token::Symbol("!=" | "<>")) => Some(CompareOp::Ne),
+            Some(Token::Symbol("<")) => Some(CompareOp::Lt),
+            Some(Token::Symbol("<=")) => Some(CompareOp::Le),
+            Some(Token::Symbol(">")) => Some(CompareOp::Gt),
+            Some(Token::Symbol(">=")) => Some(CompareOp::Ge),
+            _ => None,
+        };
+        if let Some(op) = op {
+            self.pos += 1;
+            let right = self.operand()?;
+            return Ok(Expr::Compare(Box::new(left), op, Box::new(right)));
+        }
+
+        let negated = self.peek_keyword("NOT")
+            && matches!(self.tokens.get(self.pos + 1), Some(Token::Ident(word))
+                if word.eq_ignore_ascii_case("IN") || word.eq_ignore_ascii_case("BETWEEN"));
+        if negated {
+            self.pos += 1;
+        }
+        let expr = if self.eat_keyword("IN") {
+            self.expect_symbol("(")?;
+            let mut items = vec![self.operand()?];
+            while self.eat_symbol(",") {
+                items.push(self.operand()?);
+            }
+            self.expect_symbol(")")?;
+            Expr::In(Box::new(left), items)
+        } else if self.eat_keyword("BETWEEN") {
+            let low = self.operand()?;
+            self.expect_keyword("AND")?;
+            let high = self.operand()?;
+            Expr::Between(Box::new(left), Box::new(low), Box::new(high))
+        } else {
+            return Ok(left);
+        };
+        Ok(if negated {
+            Expr::Not(Box::new(expr))
+        } else {
+            expr
+        })
+    }
+
+    fn operand(&mut self) -> ParseResult<Expr> {
+        match self.next() {
+            Some(Token::Number(n)) => Ok(Expr::Literal(n)),
+            Some(Token::Str(s)) => Ok(Expr::Literal(Value::String(s))),
+            Some(Token::Param(name)) => self
+                .parameters
+                .iter()
+                .find(|p| {
+                    p.get("name")
+                        .and_then(Value::as_str)
+                        .and_then(|n| n.strip_prefix('@'))
+                        == Some(name.as_str())
+                })
+                .map(|p| Expr::Literal(p.get("value").cloned().unwrap_or(Value::Null)))
+                .ok_or(LocalQueryError::UnboundParameter(name)),
+            Some(Token::Symbol("-")) => match self.next() {
+                Some(Token::Number(n)) => {
+                    let text = format!("-{n}");
+                    serde_json::from_str(&text)
+                        .map(Expr::Literal)
+                        .map_err(|_| LocalQueryError::Syntax(format!("invalid number '{text}'")))
+                }
+                _ => {
+                    self.pos -= 1;
+                    Err(self.unexpected("a number after '-'"))
+                }
+            },
+            Some(Token::Symbol("(")) => {
+                let expr = self.expr()?;
+                self.expect_symbol(")")?;
+                Ok(expr)
+            }
+            Some(Token::Symbol("[")) => {
+                let mut items = Vec::new();
+                if !self.eat_symbol("]") {
+                    loop {
+                        match self.operand()? {
+                            Expr::Literal(value) => items.push(value),
+                            _ => {
+                                return Err(LocalQueryError::Unsupported(
+                                    "non-literal array elements".to_string(),
+                                ));
+                            }
+                        }
+                        if !self.eat_symbol(",") {
+                            break;
+                        }
+                    }
+                    self.expect_symbol("]")?;
+                }
+                Ok(Expr::Literal(Value::Array(items)))
+            }
+            Some(Token::Ident(word)) => {
+                match word.to_ascii_lowercase().as_str() {
+                    "true" => return Ok(Expr::Literal(Value::Bool(true))),
+                    "false" => return Ok(Expr::Literal(Value::Bool(false))),
+                    "null" => return Ok(Expr::Literal(Value::Null)),
+                    _ => {}
+                }
+                if self.eat_symbol("(") {
+                    return self.call(&word);
+                }
+                if word != self.alias {
+                    return Err(LocalQueryError::UnknownIdentifier(word, self.alias.clone()));
+                }
+                self.path()
+            }
+            _ => {
+                self.pos -= 1;
+                Err(self.unexpected("an expression"))
+            }
+        }
+    }
+
+    fn path(&mut self) -> ParseResult<Expr> {
+        let mut segments = Vec::new();
+        loop {
+            if self.eat_symbol(".") {
+                match self.next() {
+                    Some(Token::Ident(name)) => segments.push(Segment::Key(name)),
+                    _ => {
+                        self.pos -= 1;
+                        return Err(self.unexpected("a property name after '.'"));
+                    }
+                }
+            } else if self.eat_symbol("[") {
+                match self.next() {
+                    Some(Token::Str(name)) => segments.push(Segment::Key(name)),
+                    Some(Token::Number(Value::Number(n))) if n.as_u64().is_some() => {
+                        segments.push(Segment::Index(n.as_u64().unwrap_or_default() as usize))
+                    }
+                    _ => {
+                        self.pos -= 1;
+                        return Err(self.unexpected("a property name or index"));
+                    }
+                }
+                self.expect_symbol("]")?;
+            } else {
+                return Ok(Expr::Path(segments));
+            }
+        }
+    }
+
+    fn call(&mut self, name: &str) -> ParseResult<Expr> {
+        let Some((function, min, max)) = Function::lookup(name) else {
+            return Err(LocalQueryError::Unsupported(format!(
+                "{}()",
+                name.to_ascii_uppercase()
+            )));
+        };
+        let mut args = Vec::new();
+        if !self.eat_symbol(")") {
+            loop {
+                args.push(self.expr()?);
+                if !self.eat_symbol(",") {
+                    break;
+                }
+            }
+            self.expect_symbol(")")?;
+        }
+        if args.len() < min || args.len() > max {
+            return Err(LocalQueryError::Syntax(format!(
+                "{}() takes {} argument(s), got {}",
+                name.to_ascii_uppercase(),
+                if min == max {
+                    min.to_string()
+                } else {
+                    format!("{min}-{max}")
+                },
+                args.len()
+            )));
+        }
+        Ok(Expr::Call(function, args))
+    }
+}
+
+impl LocalQuery {
+    /// Parse `sql`, rejecting anything outside the supported subset.
+    pub fn parse(sql: &str) -> Result<Self, LocalQueryError> {
+        Self::parse_with_params(sql, &[])
+    }
+
+    /// Parse `sql`, binding `@name` parameters from `{"name", "value"}` objects.
+    pub fn parse_with_params(sql: &str, parameters: &[Value]) -> Result<Self, LocalQueryError> {
+        let mut parser = Parser {
+            tokens: tokenize(sql)?,
+            pos: 0,
+            alias: String::new(),
+            parameters,
+        };
+        parser.query()
+    }
+
+    /// Filter, sort, limit and project `documents`.
+    pub fn execute(&self, documents: Vec<Value>) -> Vec<Value> {
+        let mut matched: Vec<Value> = documents
+            .into_iter()
+            .filter(|doc| match self.filter {
+                Some(ref filter) => eval(filter, doc) == Some(Value::Bool(true)),
+                None => true,
+            })
+            .collect();
+
+        if !self.order_by.is_empty() {
+            let mut keyed: Vec<(Vec<Option<Value>>, Value)> = matched
+                .into_iter()
+                .map(|doc| {
+                    let keys = self.order_by.iter().map(|(e, _)| eval(e, &doc)).collect();
+                    (keys, doc)
+                })
+                .collect();
+            keyed.sort_by(|(a, _), (b, _)| {
+                self.order_by
+                    .iter()
+                    .zip(a.iter().zip(b))
+                    .map(|((_, descending), (a, b))| {
+                        let order = sort_order(a.as_ref(), b.as_ref());
+                        if *descending { order.reverse() } else { order }
+                    })
+                    .find(|order| *order != Ordering::Equal)
+                    .unwrap_or(Ordering::Equal)
+            });
+            matched = keyed.into_iter().map(|(_, doc)| doc).collect();
+        }
+
+        let (skip, take) = self.offset_limit.unwrap_or((0, usize::MAX));
+        matched
+            .into_iter()
+            .skip(skip)
+            .take(take)
+            .take(self.top.unwrap_or(usize::MAX))
+            .filter_map(|doc| self.project(doc))
+            .collect()
+    }
+
+    fn project(&self, doc: Value) -> Option<Value> {
+        match self.projection {
+            Projection::All => Some(doc),
+            Projection::Value(ref expr) => eval(expr, &doc),
+            Projection::Fields(ref fields) => {
+                let mut out = Map::new();
+                let mut unnamed = 0;
+                for (expr, name) in fields {
+                    let key = match (name, expr) {
+                        (Some(name), _) => name.clone(),
+                        (None, Expr::Path(segments)) => match segments.last() {
+                            Some(Segment::Key(key)) => key.clone(),
+                            _ => {
+                                unnamed += 1;
+                                format!("${unnamed}")
+                            }
+                        },
+                        (None, _) => {
+                            unnamed += 1;
+                            format!("${unnamed}")
+                        }
+                    };
+                    if let Some(value) = eval(expr, &doc) {
+                        out.insert(key, value);
+                    }
+                }
+                Some(Value::Object(out))
+            }
+        }
+    }
+}
+
+/// Evaluate an expression; `None` is Cosmos DB's `undefined`.
+fn eval(expr: &Expr, doc: &Value) -> Option<Value> {
+    match expr {
+        Expr::Literal(value) => Some(value.clone()),
+        Expr::Path(segments) => {
+            let mut current = doc;
+            for segment in segments {
+                current = match segment {
+                    Segment::Key(key) => current.as_object()?.get(key)?,
+                    Segment::Index(i) => current.as_array()?.get(*i)?,
+                };
+            }
+            Some(current.clone())
+        }
+        Expr::Not(inner) => match eval(inner, doc)? {
+            Value::Bool(b) => Some(Value::Bool(!b)),
+            _ => None,
+        },
+        Expr::And(a, b) => match (as_bool(eval(a, doc)), as_bool(eval(b, doc))) {
+            (Some(false), _) | (_, Some(false)) => Some(Value::Bool(false)),
+            (Some(true), Some(true)) => Some(Value::Bool(true)),
+            _ => None,
+        },
+        Expr::Or(a, b) => match (as_bool(eval(a, doc)), as_bool(eval(b, doc))) {
+            (Some(true), _) | (_, Some(true)) => Some(Value::Bool(true)),
+            (Some(false), Some(false)) => Some(Value::Bool(false)),
+            _ => None,
+        },
+        Expr::Compare(a, op, b) => {
+            let order = compare(&eval(a, doc)?, &eval(b, doc)?)?;
+            Some(Value::Bool(match op {
+                CompareOp::Eq => order == Ordering::Equal,
+                CompareOp::Ne => order != Ordering::Equal,
+                CompareOp::Lt => order == Ordering::Less,
+                CompareOp::Le => order != Ordering::Greater,
+                CompareOp::Gt => order == Ordering::Greater,
+                CompareOp::Ge => order != Ordering::Less,
+            }))
+        }
+        Expr::In(needle, items) => {
+            let needle = eval(needle, doc)?;
+            let found = items.iter().any(|item| {
+                eval(item, doc).is_some_and(|v| compare(&needle, &v) == Some(Ordering::Equal))
+            });
+            Some(Value::Bool(found))
+        }
+        Expr::Between(value, low, high) => {
+            let value = eval(value, doc)?;
+            let above = compare(&value, &eval(low, doc)?)? != Ordering::Less;
+            let below = compare(&value, &eval(high, doc)?)? != Ordering::Greater;
+            Some(Value::Bool(above && below))
+        }
+        Expr::Call(function, args) => {
+            let values: Vec<Option<Value>> = args.iter().map(|a| eval(a, doc)).collect();
+            call(*function, &values)
+        }
+    }
+}
+
+fn as_bool(value: Option<Value>) -> Option<bool> {
+    value.and_then(|v| v.as_bool())
+}
+
+/// Compare two values of the same type; values of different types (and
+/// ordering of arrays/objects) are undefined.
+fn compare(a: &Value, b: &Value) -> Option<Ordering> {
+    match (a, b) {
+        (Value::Null, Value::Null) => Some(Ordering::Equal),
+        (Value::Bool(a), Value::Bool(b)) => Some(a.cmp(b)),
+        (Value::Number(a), Value::Number(b)) => a.as_f64()?.partial_cmp(&b.as_f64()?),
+        (Value::String(a), Value::String(b)) => Some(a.cmp(b)),
+        (Value::Array(_), Value::Array(_)) | (Value::Object(_), Value::Object(_)) if a == b => {
+            Some(Ordering::Equal)
+        }
+        _ => None,
+    }
+}
+
+/// `ORDER BY` order across types: undefined, null, booleans, numbers, strings
+fn sort_order(a: Option<&Value>, b: Option<&Value>) -> Ordering {
+    fn rank(value: Option<&Value>) -> u8 {
+        match value {
+            None => 0,
+            Some(Value::Null) => 1,
+            Some(Value::Bool(_)) => 2,
+            Some(Value::Number(_)) => 3,
+            Some(Value::String(_)) => 4,
+            Some(Value::Array(_)) => 5,
+            Some(Value::Object(_)) => 6,
+        }
+    }
+    match (a, b) {
+        (Some(x), Some(y)) if rank(a) == rank(b) => compare(x, y).unwrap_or(Ordering::Equal),
+        _ => rank(a).cmp(&rank(b)),
+    }
+}
+
+fn call(function: Function, args: &[Option<Value>]) -> Option<Value> {
+    let arg = |i: usize| args.get(i).cloned().flatten();
+    let ignore_case = arg(2).and_then(|v| v.as_bool()).unwrap_or(false);
+    let strings = || -> Option<(String, String)> {
+        let (a, b) = (arg(0)?, arg(1)?);
+        let (a, b) = (a.as_str()?, b.as_str()?);
+        Some(if ignore_case {
+            (a.to_lowercase(), b.to_lowercase())
+        } else {
+            (a.to_string(), b.to_string())
+        })
+    };
+    let result = match function {
+        Function::IsDefined => Value::Bool(arg(0).is_some()),
+        Function::IsNull => Value::Bool(matches!(arg(0), Some(Value::Null))),
+        Function::IsString => Value::Bool(matches!(arg(0), Some(Value::String(_)))),
+        Function::IsNumber => Value::Bool(matches!(arg(0), Some(Value::Number(_)))),
+        Function::IsBool => Value::Bool(matches!(arg(0), Some(Value::Bool(_)))),
+        Function::IsArray => Value::Bool(matches!(arg(0), Some(Value::Array(_)))),
+        Function::IsObject => Value::Bool(matches!(arg(0), Some(Value::Object(_)))),
+        Function::Contains => {
+            let (a, b) = strings()?;
+            Value::Bool(a.contains(&b))
+        }
+        Function::StartsWith => {
+            let (a, b) = strings()?;
+            Value::Bool(a.starts_with(&b))
+        }
+        Function::EndsWith => {
+            let (a, b) = strings()?;
+            Value::Bool(a.ends_with(&b))
+        }
+        Function::Lower => Value::String(arg(0)?.as_str()?.to_lowercase()),
+        Function::Upper => Value::String(arg(0)?.as_str()?.to_uppercase()),
+        Function::Length => Value::from(arg(0)?.as_str()?.chars().count()),
+        Function::ArrayContains => {
+            let array = arg(0)?;
+            let needle = arg(1)?;
+            // The third argument allows a partial (subset) match of objects
+            let partial = ignore_case;
+            Value::Bool(array.as_array()?.iter().any(|item| {
+                if partial {
+                    is_subset(&needle, item)
+                } else {
+                    *item == needle
+                }
+            }))
+        }
+        Function::ArrayLength => Value::from(arg(0)?.as_array()?.len()),
+    };
+    Some(result)
+}
+
+/// Whether every field of `needle` (an object) is present with the same value in `item`
+fn is_subset(needle: &Value, item: &Value) -> bool {
+    match (needle, item) {
+        (Value::Object(needle), Value::Object(item)) => needle
+            .iter()
+            .all(|(key, value)| item.get(key).is_some_and(|v| is_subset(value, v))),
+        _ => needle == item,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn orders() -> Vec<Value> {
+        vec![
+            json!({"id": "1", "status": "shipped", "total": 40, "customer": {"name": "Ada"}, "tags": ["vip"]}),
+            json!({"id": "2", "status": "pending", "total": 15.5, "customer": {"name": "Bo"}, "tags": []}),
+            json!({"id": "3", "status": "shipped", "total": 99, "customer": {"name": "Cy"}}),
+            json!({"id": "4", "status": "cancelled", "total": "n/a"}),
+        ]
+    }
+
+    fn ids(docs: &[Value]) -> Vec<&str> {
+        docs.iter().map(|d| d["id"].as_str().unwrap()).collect()
+    }
+
+    #[test]
+    fn test_where_and_order_by() {
+        let docs = evaluate(
+            "SELECT * FROM c WHERE c.status = 'shipped' OR c.total < 20 ORDER BY c.total DESC",
+            orders(),
+        )
+        .unwrap();
+        assert_eq!(ids(&docs), ["3", "1", "2"]);
+
+        // Mismatched types are undefined, so doc 4 never matches a numeric filter
+        let docs = evaluate("SELECT * FROM c WHERE NOT (c.total > 20)", orders()).unwrap();
+        assert_eq!(ids(&docs), ["2"]);
+
+        let docs = evaluate(
+            "SELECT * FROM c WHERE c.id IN ('1', '4') AND IS_DEFINED(c.customer)",
+            orders(),
+        )
+        .unwrap();
+        assert_eq!(ids(&docs), ["1"]);
+
+        let docs = evaluate(
+            "SELECT * FROM root r WHERE r.total BETWEEN 10 AND 50 AND ARRAY_CONTAINS(r.tags, 'vip')",
+            orders(),
+        )
+        .unwrap();
+        assert_eq!(ids(&docs), ["1"]);
+    }
+
+    #[test]
+    fn test_projection_and_top() {
+        let docs = evaluate(
+            "SELECT TOP 2 c.id, c.customer.name AS who, UPPER(c.status) FROM c ORDER BY c.id",
+            orders(),
+        )
+        .unwrap();
+        assert_eq!(
+            docs,
+            vec![
+                json!({"id": "1", "who": "Ada", "$1": "SHIPPED"}),
+                json!({"id": "2", "who": "Bo", "$1": "PENDING"}),
+            ]
+        );
+
+        let docs = evaluate(
+            "SELECT VALUE c[\"customer\"].name FROM c OFFSET 1 LIMIT 5",
+            orders(),
+        )
+        .unwrap();
+        assert_eq!(docs, vec![json!("Bo"), json!("Cy")]);
+    }
+
+    #[test]
+    fn test_parameters() {
+        let params = vec![
+            json!({"name": "@status", "value": "shipped"}),
+            json!({"name": "@min", "value": 50}),
+        ];
+        let docs = evaluate_with_params(
+            "SELECT * FROM c WHERE c.status = @status AND c.total > @min",
+            &params,
+            orders(),
+        )
+        .unwrap();
+        assert_eq!(ids(&docs), ["3"]);
+
+        let docs = evaluate_with_params(
+            "SELECT * FROM c WHERE c.id IN (@a, @b)",
+            &[
+                json!({"name": "@a", "value": "2"}),
+                json!({"name": "@b", "value": null}),
+            ],
+            orders(),
+        )
+        .unwrap();
+        assert_eq!(ids(&docs), ["2"]);
+    }
+
+    #[test]
+    fn test_unsupported_and_invalid() {
+        assert!(matches!(
+            LocalQuery::parse("SELECT * FROM c JOIN t IN c.tags"),
+            Err(LocalQueryError::Unsupported(_))
+        ));
+        assert_eq!(
+            LocalQuery::parse("SELECT VALUE COUNT(1) FROM c")
+                .unwrap_err()
+                .to_string(),
+            "COUNT(): not supported when querying a local file"
+        );
+        assert!(matches!(
+            LocalQuery::parse("SELECT * FROM c WHERE x.id = '1'"),
+            Err(LocalQueryError::UnknownIdentifier(..))
+        ));
+        assert!(matches!(
+            LocalQuery::parse("SELECT * FROM c WHERE c.id = @id"),
+            Err(LocalQueryError::UnboundParameter(_))
+        ));
+        assert!(matches!(
+            LocalQuery::parse("DELETE FROM c"),
+            Err(LocalQueryError::Syntax(_))
+        ));
+    }
+}
@@ -0,0 +1,277 @@
+//! Local cache of gateway query plans for stored queries
+//!
+//! Before a stored query fans out, the Cosmos DB gateway returns a query
+//! plan: which effective partition key ranges the query can touch. A plan
+//! only depends on the container, the SQL and the parameter values, so it is
+//! stored under `~/.cosq/cache/plans/` (keyed by a hash of those) and
+//! repeated runs skip the plan request. Each plan records the resource id of
+//! its container, so a container recreated under the same name (possibly
+//! with another partition key) never gets a stale plan. Plans expire after
+//! [`TTL_SECS`], and the cache keeps at most [`MAX_ENTRIES`] of them.
+
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use thiserror::Error;
+
+use crate::result_cache;
+
+#[derive(Debug, Error)]
+pub enum PlanCacheError {
+    #[error("could not determine home directory")]
+    NoHomeDir,
+
+    #[error("failed to read or write plan cache: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("invalid plan cache file: {0}")]
+    Parse(#[from] serde_json::Error),
+}
+
+/// Cached plans older than this are requested again
+pub const TTL_SECS: i64 = 24 * 60 * 60;
+
+/// Most plans kept in the cache; the oldest are pruned beyond this
+pub const MAX_ENTRIES: usize = 500;
+
+/// The part of a gateway query plan (as returned for
+/// `x-ms-cosmos-is-query-plan-request`) cosq uses
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct QueryPlan {
+    /// Effective partition key ranges the query can match
+    pub query_ranges: Vec<QueryRange>,
+}
+
+/// A query plan in the cache
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CachedPlan {
+    /// RFC 3339 timestamp of the plan request
+    pub cached_at: String,
+    /// Resource id (`_rid`) of the container the plan was made for
+    pub container_rid: String,
+    pub plan: QueryPlan,
+}
+
+impl CachedPlan {
+    pub fn new(container_rid: &str, plan: QueryPlan) -> Self {
+        Self {
+            cached_at: Utc::now().to_rfc3339(),
+            container_rid: container_rid.to_string(),
+            plan,
+        }
+    }
+
+    /// Whether the plan applies to the container with `container_rid` and
+    /// is younger than [`TTL_SECS`] at `now`.
+    pub fn is_valid(&self, container_rid: &str, now: DateTime<Utc>) -> bool {
+        !container_rid.is_empty()
+            && self.container_rid == container_rid
+            && DateTime::parse_from_rfc3339(&self.cached_at)
+                .is_ok_and(|at| (now - at.with_timezone(&Utc)).num_seconds() < TTL_SECS)
+    }
+}
+
+/// One range of effective partition key values (hex strings)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct QueryRange {
+    pub min: String,
+    pub max: String,
+    #[serde(default = "default_true")]
+    pub is_min_inclusive: bool,
+    #[serde(default)]
+    pub is_max_inclusive: bool,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+impl QueryRange {
+    /// Whether this range overlaps a partition key range `[min, max)`.
+    pub fn overlaps(&self, min: &str, max: &str) -> bool {
+        // Effective partition keys are hex strings of the same scheme, so they
+        // compare lexicographically
+        let above_min = if self.is_max_inclusive {
+            self.max.as_str() >= min
+        } else {
+            self.max.as_str() > min
+        };
+        self.min.as_str() < max && above_min
+    }
+}
+
+impl QueryPlan {
+    /// Whether the query can match documents in the partition key range `[min, max)`.
+    pub fn covers(&self, min: &str, max: &str) -> bool {
+        self.query_ranges.iter().any(|r| r.overlaps(min, max))
+    }
+}
+
+/// Cache key for a query plan: account endpoint, database, container, SQL
+/// and parameters.
+pub fn key(
+    endpoint: &str,
+    database: &str,
+    container: &str,
+    sql: &str,
+    parameters: &[Value],
+) -> String {
+    let parameters = serde_json::to_string(parameters).unwrap_or_default();
+    result_cache::key(&[endpoint, database, container, sql, &parameters])
+}
+
+/// Cache directory: `~/.cosq/cache/plans/`.
+pub fn cache_dir() -> Result<PathBuf, PlanCacheError> {
+    dirs::home_dir()
+        .map(|d| d.join(".cosq").join("cache").join("plans"))
+        .ok_or(PlanCacheError::NoHomeDir)
+}
+
+pub fn load_from(dir: &Path, key: &str) -> Result<Option<CachedPlan>, PlanCacheError> {
+    match std::fs::read_to_string(dir.join(format!("{key}.json"))) {
+        Ok(contents) => Ok(Some(serde_json::from_str(&contents)?)),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Save a plan, then prune the cache.
+pub fn save_to(dir: &Path, key: &str, plan: &CachedPlan) -> Result<(), PlanCacheError> {
+    std::fs::create_dir_all(dir)?;
+    std::fs::write(
+        dir.join(format!("{key}.json")),
+        serde_json::to_string(plan)?,
+    )?;
+    prune(dir, SystemTime::now())?;
+    Ok(())
+}
+
+/// Remove plans written more than [`TTL_SECS`] before `now`, then the
+/// oldest ones beyond [`MAX_ENTRIES`]. Returns how many were removed.
+pub fn prune(dir: &Path, now: SystemTime) -> Result<usize, PlanCacheError> {
+    let ttl = Duration::from_secs(TTL_SECS as u64);
+    let mut entries = Vec::new();
+    for entry in std::fs::read_dir(dir)? {
+        let entry = entry?;
+        let path = entry.path();
+        if path.extension().is_some_and(|e| e == "json") {
+            let modified = entry.metadata()?.modified()?;
+            entries.push((modified, path));
+        }
+    }
+    // Newest first, so everything past the limit is the oldest
+    entries.sort_by_key(|(modified, _)| std::cmp::Reverse(*modified));
+    let mut removed = 0;
+    for (i, (modified, path)) in entries.iter().enumerate() {
+        let expired = now.duration_since(*modified).is_ok_and(|age| age >= ttl);
+        if expired || i >= MAX_ENTRIES {
+            std::fs::remove_file(path)?;
+            removed += 1;
+        }
+    }
+    Ok(removed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_covers() {
+        // Gateway plans carry more (`queryInfo`) than cosq reads
+        let all: QueryPlan = serde_json::from_value(json!({
+            "queryRanges": [{"min": "", "max": "FF", "isMinInclusive": true, "isMaxInclusive": false}],
+            "queryInfo": {"orderBy": []}
+        }))
+        .unwrap();
+        assert!(all.covers("", "7F"));
+        assert!(all.covers("7F", "FF"));
+
+        // A point query (`WHERE c.pk = 'x'`) touches one range
+        let point: QueryPlan = serde_json::from_value(json!({
+            "queryRanges": [{"min": "3A", "max": "3A", "isMinInclusive": true, "isMaxInclusive": true}]
+        }))
+        .unwrap();
+        assert!(point.covers("", "7F"));
+        assert!(!point.covers("7F", "FF"));
+        assert!(point.covers("3A", "4B"));
+        assert!(!point.covers("", "3A"));
+
+        let none = QueryPlan {
+            query_ranges: Vec::new(),
+        };
+        assert!(!none.covers("", "FF"));
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let k = key("https://a/", "db", "c", "SELECT * FROM c", &[]);
+        assert_ne!(
+            k,
+            key(
+                "https://a/",
+                "db",
+                "c",
+                "SELECT * FROM c",
+                &[json!({"name": "@pk", "value": "x"})]
+            )
+        );
+        assert_eq!(load_from(dir.path(), &k).unwrap(), None);
+
+        let plan = CachedPlan::new(
+            "rid1",
+            QueryPlan {
+                query_ranges: vec![QueryRange {
+                    min: "".into(),
+                    max: "FF".into(),
+                    is_min_inclusive: true,
+                    is_max_inclusive: false,
+                }],
+            },
+        );
+        save_to(dir.path(), &k, &plan).unwrap();
+        assert_eq!(load_from(dir.path(), &k).unwrap(), Some(plan));
+    }
+
+    #[test]
+    fn test_is_valid() {
+        let plan = CachedPlan::new(
+            "rid1",
+            QueryPlan {
+                query_ranges: Vec::new(),
+            },
+        );
+        let now = Utc::now();
+        assert!(plan.is_valid("rid1", now));
+        // The container was recreated
+        assert!(!plan.is_valid("rid2", now));
+        assert!(!plan.is_valid("", now));
+        assert!(!plan.is_valid("rid1", now + chrono::Duration::seconds(TTL_SECS)));
+    }
+
+    #[test]
+    fn test_prune() {
+        let dir = tempfile::tempdir().unwrap();
+        for i in 0..MAX_ENTRIES + 3 {
+            std::fs::write(dir.path().join(format!("{i}.json")), "{}").unwrap();
+        }
+        std::fs::write(dir.path().join("notes.txt"), "").unwrap();
+        let now = SystemTime::now();
+        assert_eq!(prune(dir.path(), now).unwrap(), 3);
+        assert_eq!(
+            std::fs::read_dir(dir.path()).unwrap().count(),
+            MAX_ENTRIES + 1
+        );
+
+        let later = now + Duration::from_secs(TTL_SECS as u64 + 60);
+        assert_eq!(prune(dir.path(), later).unwrap(), MAX_ENTRIES);
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 1);
+    }
+}
@@ -0,0 +1,268 @@
+//! Per-container view preferences
+//!
+//! Each container can remember how its results are usually viewed: the
+//! columns and sort order for table/CSV output, a default output format, and
+//! the queries most recently run against it. Preferences live in
+//! `~/.cosq/prefs/<account>/<database>/<container>.yaml`.
+
+use std::cmp::Ordering;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use thiserror::Error;
+
+/// How many recent queries are remembered per container
+pub const MAX_RECENT: usize = 10;
+
+#[derive(Debug, Error)]
+pub enum PrefsError {
+    #[error("could not determine home directory")]
+    NoHomeDir,
+
+    #[error("failed to read or write preferences file: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("invalid preferences file: {0}")]
+    Parse(#[from] serde_yaml::Error),
+}
+
+/// Preferences for one container
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ContainerPrefs {
+    /// Columns to show in table/CSV output, in order (dotted paths allowed)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub columns: Vec<String>,
+    /// Sort for table/CSV output: a field, optionally followed by `asc` or `desc`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sort: Option<String>,
+    /// Output format when neither `-o`, the query, nor a template chooses one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub output: Option<String>,
+    /// Most recent first: ad-hoc SQL, or the name of a stored query
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub recent_queries: Vec<String>,
+}
+
+impl ContainerPrefs {
+    /// Preferences path: `~/.cosq/prefs/<account>/<database>/<container>.yaml`.
+    pub fn path(account: &str, database: &str, container: &str) -> Result<PathBuf, PrefsError> {
+        dirs::home_dir()
+            .map(|d| {
+                d.join(".cosq")
+                    .join("prefs")
+                    .join(account)
+                    .join(database)
+                    .join(format!("{container}.yaml"))
+            })
+            .ok_or(PrefsError::NoHomeDir)
+    }
+
+    /// Load the preferences for this container (empty if none are saved).
+    pub fn load(account: &str, database: &str, container: &str) -> Result<Self, PrefsError> {
+        Self::load_from(&Self::path(account, database, container)?)
+    }
+
+    /// Save the preferences for this container.
+    pub fn save(
+        &self,
+        account: &str,
+        database: &str,
+        container: &str,
+    ) -> Result<PathBuf, PrefsError> {
+        let path = Self::path(account, database, container)?;
+        self.save_to(&path)?;
+        Ok(path)
+    }
+
+    pub fn load_from(path: &Path) -> Result<Self, PrefsError> {
+        match std::fs::read_to_string(path) {
+            Ok(contents) if contents.trim().is_empty() => Ok(Self::default()),
+            Ok(contents) => Ok(serde_yaml::from_str(&contents)?),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    pub fn save_to(&self, path: &Path) -> Result<(), PrefsError> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(path, serde_yaml::to_string(self)?)?;
+        Ok(())
+    }
+
+    /// Move `query` to the front of the recent queries, keeping at most [`MAX_RECENT`].
+    pub fn record_query(&mut self, query: &str) {
+        let query = query.trim();
+        if query.is_empty() {
+            return;
+        }
+        self.recent_queries.retain(|q| q != query);
+        self.recent_queries.insert(0, query.to_string());
+        self.recent_queries.truncate(MAX_RECENT);
+    }
+
+    /// Shape documents for table/CSV output: sort by `sort`, then keep only
+    /// `columns`, in that order. Columns none of the documents have are
+    /// skipped; if none match at all the documents are left as they are.
+    pub fn apply_view(&self, documents: &mut [Value]) {
+        if let Some((field, descending)) = self.sort.as_deref().and_then(parse_sort) {
+            documents.sort_by(|a, b| compare(lookup(a, field), lookup(b, field), descending));
+        }
+
+        let columns: Vec<&str> = self
+            .columns
+            .iter()
+            .map(String::as_str)
+            .filter(|c| documents.iter().any(|d| lookup(d, c).is_some()))
+            .collect();
+        if columns.is_empty() {
+            return;
+        }
+        for doc in documents.iter_mut() {
+            let projected: Map<String, Value> = columns
+                .iter()
+                .map(|c| {
+                    (
+                        c.to_string(),
+                        lookup(doc, c).cloned().unwrap_or(Value::Null),
+                    )
+                })
+                .collect();
+            *doc = Value::Object(projected);
+        }
+    }
+}
+
+/// Split a sort spec (`field`, `field asc`, `field desc`) into the field and
+/// whether it sorts descending.
+pub fn parse_sort(spec: &str) -> Option<(&str, bool)> {
+    let mut parts = spec.split_whitespace();
+    let field = parts.next()?;
+    let descending = match parts.next().map(str::to_ascii_lowercase).as_deref() {
+        None | Some("asc") => false,
+        Some("desc") => true,
+        Some(_) => return None,
+    };
+    if parts.next().is_some() {
+        return None;
+    }
+    Some((field.strip_prefix("c.").unwrap_or(field), descending))
+}
+
+/// Look up a dotted path (`address.city`) in a document.
+fn lookup<'a>(doc: &'a Value, path: &str) -> Option<&'a Value> {
+    path.split('.').try_fold(doc, |value, key| value.get(key))
+}
+
+/// Order values for sorting: numbers numerically, strings lexically, and
+/// missing or null values last in either direction.
+fn compare(a: Option<&Value>, b: Option<&Value>, descending: bool) -> Ordering {
+    let (a, b) = match (a.filter(|v| !v.is_null()), b.filter(|v| !v.is_null())) {
+        (None, None) => return Ordering::Equal,
+        (None, Some(_)) => return Ordering::Greater,
+        (Some(_), None) => return Ordering::Less,
+        (Some(a), Some(b)) => (a, b),
+    };
+    let order = match (a, b) {
+        (Value::Number(a), Value::Number(b)) => a
+            .as_f64()
+            .partial_cmp(&b.as_f64())
+            .unwrap_or(Ordering::Equal),
+        (Value::String(a), Value::String(b)) => a.cmp(b),
+        (Value::Bool(a), Value::Bool(b)) => a.cmp(b),
+        (a, b) => a.to_string().cmp(&b.to_string()),
+    };
+    if descending { order.reverse() } else { order }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("prod").join("app").join("orders.yaml");
+        assert_eq!(
+            ContainerPrefs::load_from(&path).unwrap(),
+            ContainerPrefs::default()
+        );
+
+        let prefs = ContainerPrefs {
+            columns: vec!["id".into(), "customer.name".into()],
+            sort: Some("total desc".into()),
+            output: Some("table".into()),
+            recent_queries: vec!["SELECT * FROM c".into()],
+        };
+        prefs.save_to(&path).unwrap();
+        assert_eq!(ContainerPrefs::load_from(&path).unwrap(), prefs);
+
+        // Empty fields are omitted from the file
+        ContainerPrefs::default().save_to(&path).unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap().trim(), "{}");
+    }
+
+    #[test]
+    fn test_record_query() {
+        let mut prefs = ContainerPrefs::default();
+        prefs.record_query("SELECT * FROM c");
+        prefs.record_query("daily-orders");
+        prefs.record_query("  SELECT * FROM c ");
+        prefs.record_query("");
+        assert_eq!(
+            prefs.recent_queries,
+            vec!["SELECT * FROM c", "daily-orders"]
+        );
+
+        for i in 0..20 {
+            prefs.record_query(&format!("q{i}"));
+        }
+        assert_eq!(prefs.recent_queries.len(), MAX_RECENT);
+        assert_eq!(prefs.recent_queries[0], "q19");
+    }
+
+    #[test]
+    fn test_parse_sort() {
+        assert_eq!(parse_sort("total"), Some(("total", false)));
+        assert_eq!(parse_sort("c.total DESC"), Some(("total", true)));
+        assert_eq!(parse_sort("a.b asc"), Some(("a.b", false)));
+        assert_eq!(parse_sort("total sideways"), None);
+        assert_eq!(parse_sort("total desc extra"), None);
+        assert_eq!(parse_sort(""), None);
+    }
+
+    #[test]
+    fn test_apply_view() {
+        let mut docs = vec![
+            json!({"id": "1", "total": 5, "customer": {"name": "Ada"}}),
+            json!({"id": "2", "customer": {"name": "Bo"}}),
+            json!({"id": "3", "total": 12.5, "customer": {"name": "Cy"}}),
+        ];
+        let prefs = ContainerPrefs {
+            columns: vec!["customer.name".into(), "id".into(), "gone".into()],
+            sort: Some("total desc".into()),
+            ..Default::default()
+        };
+        prefs.apply_view(&mut docs);
+        assert_eq!(
+            docs,
+            vec![
+                json!({"customer.name": "Cy", "id": "3"}),
+                json!({"customer.name": "Ada", "id": "1"}),
+                json!({"customer.name": "Bo", "id": "2"}),
+            ]
+        );
+
+        // Columns that match nothing leave the documents whole
+        let mut docs = vec![json!({"id": "1"})];
+        let stale = ContainerPrefs {
+            columns: vec!["gone".into()],
+            ..Default::default()
+        };
+        stale.apply_view(&mut docs);
+        assert_eq!(docs, vec![json!({"id": "1"})]);
+    }
+}
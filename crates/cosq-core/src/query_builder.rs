@@ -0,0 +1,456 @@
+//! Fluent builder for Cosmos DB SQL queries
+//!
+//! Produces SQL text plus a parameter list, so callers never concatenate
+//! user-supplied values into a query:
+//!
+//! ```
+//! use cosq_core::query_builder::{Query, param};
+//!
+//! let built = Query::from("c")
+//!     .select(["id", "total"])
+//!     .where_eq("status", param("status"))
+//!     .top(50)
+//!     .bind("status", "shipped")
+//!     .build()
+//!     .unwrap();
+//! assert_eq!(
+//!     built.sql,
+//!     "SELECT TOP 50 c.id, c.total FROM c WHERE c.status = @status"
+//! );
+//! ```
+
+use std::collections::BTreeMap;
+
+use serde_json::Value;
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum QueryBuildError {
+    #[error("parameter '@{0}' is used but has no value — call .bind(\"{0}\", ...)")]
+    UnboundParameter(String),
+
+    #[error("invalid parameter name '{0}': use letters, digits and underscores")]
+    InvalidParameterName(String),
+}
+
+/// Comparison operator in a `WHERE` condition
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Op {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    /// `CONTAINS(field, value)` (substring match)
+    Contains,
+    /// `STARTSWITH(field, value)`
+    StartsWith,
+}
+
+impl Op {
+    fn as_sql(self) -> &'static str {
+        match self {
+            Op::Eq => "=",
+            Op::Ne => "!=",
+            Op::Lt => "<",
+            Op::Le => "<=",
+            Op::Gt => ">",
+            Op::Ge => ">=",
+            Op::Contains => "CONTAINS",
+            Op::StartsWith => "STARTSWITH",
+        }
+    }
+
+    fn render(self, path: &str, placeholder: &str) -> String {
+        match self {
+            Op::Contains | Op::StartsWith => {
+                format!("{}({path}, {placeholder})", self.as_sql())
+            }
+            _ => format!("{path} {} {placeholder}", self.as_sql()),
+        }
+    }
+}
+
+/// Sort direction for `ORDER BY`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Order {
+    Asc,
+    Desc,
+}
+
+/// Right-hand side of a condition: a named parameter or a literal value
+/// (literals are bound as generated `@p0`, `@p1`, ... parameters).
+#[derive(Debug, Clone, PartialEq)]
+pub enum Operand {
+    Param(String),
+    Value(Value),
+}
+
+/// Reference a named parameter (`@name`) whose value is supplied with [`Query::bind`].
+pub fn param(name: &str) -> Operand {
+    Operand::Param(name.trim_start_matches('@').to_string())
+}
+
+impl<T: Into<Value>> From<T> for Operand {
+    fn from(value: T) -> Self {
+        Operand::Value(value.into())
+    }
+}
+
+#[derive(Debug, Clone)]
+enum Condition {
+    Compare {
+        field: String,
+        op: Op,
+        operand: Operand,
+    },
+    Raw(String),
+}
+
+/// A Cosmos DB SQL query under construction
+#[derive(Debug, Clone)]
+pub struct Query {
+    alias: String,
+    fields: Vec<String>,
+    top: Option<u64>,
+    conditions: Vec<Condition>,
+    order_by: Vec<(String, Order)>,
+    offset_limit: Option<(u64, u64)>,
+    bindings: BTreeMap<String, Value>,
+}
+
+/// SQL text and its parameters, ready to execute
+#[derive(Debug, Clone, PartialEq)]
+pub struct BuiltQuery {
+    pub sql: String,
+    /// Parameter names (with `@`) and values, in order of first use
+    pub parameters: Vec<(String, Value)>,
+}
+
+impl BuiltQuery {
+    /// Parameters in the Cosmos DB REST format: `[{"name": "@x", "value": ...}]`.
+    pub fn cosmos_parameters(&self) -> Vec<Value> {
+        self.parameters
+            .iter()
+            .map(|(name, value)| serde_json::json!({"name": name, "value": value}))
+            .collect()
+    }
+}
+
+impl Query {
+    /// Start a query over the container, referred to by `alias` (usually `c`).
+    pub fn from(alias: &str) -> Self {
+        Self {
+            alias: alias.to_string(),
+            fields: Vec::new(),
+            top: None,
+            conditions: Vec::new(),
+            order_by: Vec::new(),
+            offset_limit: None,
+            bindings: BTreeMap::new(),
+        }
+    }
+
+    /// Project these fields (dotted paths like `address.city` are allowed).
+    /// Without a projection the query selects `*`.
+    pub fn select<I, S>(mut self, fields: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.fields.extend(fields.into_iter().map(Into::into));
+        self
+    }
+
+    /// Add a `field <op> operand` condition (conditions are joined with `AND`).
+    pub fn filter(mut self, field: &str, op: Op, operand: impl Into<Operand>) -> Self {
+        self.conditions.push(Condition::Compare {
+            field: field.to_string(),
+            op,
+            operand: operand.into(),
+        });
+        self
+    }
+
+    pub fn where_eq(self, field: &str, operand: impl Into<Operand>) -> Self {
+        self.filter(field, Op::Eq, operand)
+    }
+
+    pub fn where_ne(self, field: &str, operand: impl Into<Operand>) -> Self {
+        self.filter(field, Op::Ne, operand)
+    }
+
+    pub fn where_lt(self, field: &str, operand: impl Into<Operand>) -> Self {
+        self.filter(field, Op::Lt, operand)
+    }
+
+    pub fn where_le(self, field: &str, operand: impl Into<Operand>) -> Self {
+        self.filter(field, Op::Le, operand)
+    }
+
+    pub fn where_gt(self, field: &str, operand: impl Into<Operand>) -> Self {
+        self.filter(field, Op::Gt, operand)
+    }
+
+    pub fn where_ge(self, field: &str, operand: impl Into<Operand>) -> Self {
+        self.filter(field, Op::Ge, operand)
+    }
+
+    pub fn where_contains(self, field: &str, operand: impl Into<Operand>) -> Self {
+        self.filter(field, Op::Contains, operand)
+    }
+
+    pub fn where_starts_with(self, field: &str, operand: impl Into<Operand>) -> Self {
+        self.filter(field, Op::StartsWith, operand)
+    }
+
+    /// Add a raw SQL condition, e.g. `IS_DEFINED(c.email)`. Use [`param`]
+    /// placeholders and [`Query::bind`] for any values.
+    pub fn where_raw(mut self, condition: &str) -> Self {
+        self.conditions.push(Condition::Raw(condition.to_string()));
+        self
+    }
+
+    pub fn order_by(mut self, field: &str, order: Order) -> Self {
+        self.order_by.push((field.to_string(), order));
+        self
+    }
+
+    pub fn top(mut self, n: u64) -> Self {
+        self.top = Some(n);
+        self
+    }
+
+    pub fn offset_limit(mut self, offset: u64, limit: u64) -> Self {
+        self.offset_limit = Some((offset, limit));
+        self
+    }
+
+    /// Supply the value of a named parameter.
+    pub fn bind(mut self, name: &str, value: impl Into<Value>) -> Self {
+        self.bindings
+            .insert(name.trim_start_matches('@').to_string(), value.into());
+        self
+    }
+
+    /// Render the SQL text and collect parameters.
+    pub fn build(&self) -> Result<BuiltQuery, QueryBuildError> {
+        let mut parameters: Vec<(String, Value)> = Vec::new();
+        let mut generated = 0usize;
+
+        let mut sql = String::from("SELECT ");
+        if let Some(n) = self.top {
+            sql.push_str(&format!("TOP {n} "));
+        }
+        if self.fields.is_empty() {
+            sql.push('*');
+        } else {
+            let fields: Vec<String> = self.fields.iter().map(|f| self.path(f)).collect();
+            sql.push_str(&fields.join(", "));
+        }
+        sql.push_str(&format!(" FROM {}", self.alias));
+
+        if !self.conditions.is_empty() {
+            let mut rendered = Vec::new();
+            for condition in &self.conditions {
+                match condition {
+                    Condition::Compare { field, op, operand } => {
+                        let placeholder = match operand {
+                            Operand::Param(name) => {
+                                if !is_identifier(name) {
+                                    return Err(QueryBuildError::InvalidParameterName(
+                                        name.clone(),
+                                    ));
+                                }
+                                let value = self.bindings.get(name).ok_or_else(|| {
+                                    QueryBuildError::UnboundParameter(name.clone())
+                                })?;
+                                let placeholder = format!("@{name}");
+                                if !parameters.iter().any(|(n, _)| *n == placeholder) {
+                                    parameters.push((placeholder.clone(), value.clone()));
+                                }
+                                placeholder
+                            }
+                            Operand::Value(value) => {
+                                let placeholder = loop {
+                                    let candidate = format!("@p{generated}");
+                                    generated += 1;
+                                    if !self.bindings.contains_key(&candidate[1..]) {
+                                        break candidate;
+                                    }
+                                };
+                                parameters.push((placeholder.clone(), value.clone()));
+                                placeholder
+                            }
+                        };
+                        rendered.push(op.render(&self.path(field), &placeholder));
+                    }
+                    Condition::Raw(raw) => rendered.push(format!("({raw})")),
+                }
+            }
+            sql.push_str(" WHERE ");
+            sql.push_str(&rendered.join(" AND "));
+        }
+
+        // Named parameters used only in raw conditions still need to be sent
+        for (name, value) in &self.bindings {
+            let placeholder = format!("@{name}");
+            let in_raw = self.conditions.iter().any(
+                |c| matches!(c, Condition::Raw(raw) if contains_placeholder(raw, &placeholder)),
+            );
+            if in_raw && !parameters.iter().any(|(n, _)| *n == placeholder) {
+                parameters.push((placeholder, value.clone()));
+            }
+        }
+
+        if !self.order_by.is_empty() {
+            let order: Vec<String> = self
+                .order_by
+                .iter()
+                .map(|(field, order)| {
+                    let dir = match order {
+                        Order::Asc => "ASC",
+                        Order::Desc => "DESC",
+                    };
+                    format!("{} {dir}", self.path(field))
+                })
+                .collect();
+            sql.push_str(" ORDER BY ");
+            sql.push_str(&order.join(", "));
+        }
+
+        if let Some((offset, limit)) = self.offset_limit {
+            sql.push_str(&format!(" OFFSET {offset} LIMIT {limit}"));
+        }
+
+        Ok(BuiltQuery { sql, parameters })
+    }
+
+    /// Qualify a (possibly dotted) field path with the alias, quoting segments
+    /// that aren't plain identifiers: `my-field` becomes `c["my-field"]`.
+    fn path(&self, field: &str) -> String {
+        let mut out = self.alias.clone();
+        for segment in field.split('.') {
+            if is_identifier(segment) {
+                out.push('.');
+                out.push_str(segment);
+            } else {
+                out.push_str(&format!("[{}]", Value::String(segment.to_string())));
+            }
+        }
+        out
+    }
+}
+
+fn is_identifier(s: &str) -> bool {
+    let mut chars = s.chars();
+    matches!(chars.next(), Some(c) if c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// Whether `sql` references `placeholder` as a whole word (`@id` but not `@identity`).
+fn contains_placeholder(sql: &str, placeholder: &str) -> bool {
+    sql.match_indices(placeholder).any(|(i, _)| {
+        sql[i + placeholder.len()..]
+            .chars()
+            .next()
+            .is_none_or(|c| !(c.is_ascii_alphanumeric() || c == '_'))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_select_all() {
+        let built = Query::from("c").build().unwrap();
+        assert_eq!(built.sql, "SELECT * FROM c");
+        assert!(built.parameters.is_empty());
+    }
+
+    #[test]
+    fn test_named_and_literal_parameters() {
+        let built = Query::from("c")
+            .select(["id", "total"])
+            .where_eq("status", param("status"))
+            .where_gt("total", 100)
+            .top(50)
+            .bind("status", "shipped")
+            .build()
+            .unwrap();
+        assert_eq!(
+            built.sql,
+            "SELECT TOP 50 c.id, c.total FROM c WHERE c.status = @status AND c.total > @p0"
+        );
+        assert_eq!(
+            built.cosmos_parameters(),
+            vec![
+                json!({"name": "@status", "value": "shipped"}),
+                json!({"name": "@p0", "value": 100}),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_paths_order_and_paging() {
+        let built = Query::from("c")
+            .select(["address.city", "first-name"])
+            .where_raw("IS_DEFINED(c.email)")
+            .order_by("createdAt", Order::Desc)
+            .offset_limit(20, 10)
+            .build()
+            .unwrap();
+        assert_eq!(
+            built.sql,
+            "SELECT c.address.city, c[\"first-name\"] FROM c WHERE (IS_DEFINED(c.email)) \
+             ORDER BY c.createdAt DESC OFFSET 20 LIMIT 10"
+        );
+    }
+
+    #[test]
+    fn test_string_functions() {
+        let built = Query::from("c")
+            .where_contains("name", "ada")
+            .where_starts_with("city", "Stock")
+            .build()
+            .unwrap();
+        assert_eq!(
+            built.sql,
+            "SELECT * FROM c WHERE CONTAINS(c.name, @p0) AND STARTSWITH(c.city, @p1)"
+        );
+    }
+
+    #[test]
+    fn test_raw_condition_parameters() {
+        let built = Query::from("c")
+            .where_raw("CONTAINS(c.name, @term)")
+            .bind("term", "ada")
+            .bind("unused", 1)
+            .build()
+            .unwrap();
+        assert_eq!(built.parameters, vec![("@term".to_string(), json!("ada"))]);
+    }
+
+    #[test]
+    fn test_unbound_parameter() {
+        let err = Query::from("c")
+            .where_eq("status", param("status"))
+            .build()
+            .unwrap_err();
+        assert!(matches!(err, QueryBuildError::UnboundParameter(ref n) if n == "status"));
+    }
+
+    #[test]
+    fn test_generated_names_avoid_bindings() {
+        let built = Query::from("c")
+            .where_eq("a", param("p0"))
+            .where_eq("b", true)
+            .bind("p0", 1)
+            .build()
+            .unwrap();
+        assert_eq!(built.sql, "SELECT * FROM c WHERE c.a = @p0 AND c.b = @p1");
+    }
+}
@@ -0,0 +1,208 @@
+//! Version history for stored queries
+//!
+//! Every time cosq writes a `.cosq` file (create, edit, AI generate or template
+//! fix, install, revert) the new contents are snapshotted under a `.history/`
+//! directory next to the query, e.g. `~/.cosq/queries/.history/recent-orders/`:
+//!
+//! ```text
+//! 1.cosq
+//! 2.cosq
+//! log.yaml    # version, timestamp and reason of each snapshot
+//! ```
+//!
+//! `cosq queries history <name>` lists the versions and `cosq queries revert
+//! <name> --to <version>` restores one (recording the revert as a new version).
+
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum HistoryError {
+    #[error("failed to read or write query history: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("invalid query history log: {0}")]
+    Parse(#[from] serde_yaml::Error),
+
+    #[error("version {version} not found in the history of '{name}'")]
+    UnknownVersion { name: String, version: u32 },
+}
+
+/// One recorded version of a stored query
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HistoryEntry {
+    pub version: u32,
+    /// RFC 3339 timestamp of the snapshot
+    pub saved_at: String,
+    /// What changed the query (e.g. `edit`, `ai template fix`, `revert to 2`)
+    pub reason: String,
+}
+
+/// History directory of the query stored at `query_path`.
+pub fn history_dir(query_path: &Path) -> PathBuf {
+    let name = query_path
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .unwrap_or("unknown");
+    query_path
+        .parent()
+        .unwrap_or(Path::new("."))
+        .join(".history")
+        .join(name)
+}
+
+/// Recorded versions of the query at `query_path`, oldest first.
+pub fn list(query_path: &Path) -> Result<Vec<HistoryEntry>, HistoryError> {
+    match std::fs::read_to_string(history_dir(query_path).join("log.yaml")) {
+        Ok(contents) if contents.trim().is_empty() => Ok(Vec::new()),
+        Ok(contents) => Ok(serde_yaml::from_str(&contents)?),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Vec::new()),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Contents of one recorded version.
+pub fn read_version(query_path: &Path, version: u32) -> Result<String, HistoryError> {
+    match std::fs::read_to_string(history_dir(query_path).join(format!("{version}.cosq"))) {
+        Ok(contents) => Ok(contents),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Err(HistoryError::UnknownVersion {
+            name: query_name(query_path),
+            version,
+        }),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Snapshot the current contents of `query_path` as a new version, unless they
+/// match the latest one. Returns the new version number, if one was recorded.
+pub fn record(query_path: &Path, reason: &str) -> Result<Option<u32>, HistoryError> {
+    let contents = match std::fs::read_to_string(query_path) {
+        Ok(contents) => contents,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(e.into()),
+    };
+    let mut entries = list(query_path)?;
+    if let Some(latest) = entries.last() {
+        if read_version(query_path, latest.version).ok().as_deref() == Some(contents.as_str()) {
+            return Ok(None);
+        }
+    }
+
+    let version = entries.last().map_or(1, |e| e.version + 1);
+    let dir = history_dir(query_path);
+    std::fs::create_dir_all(&dir)?;
+    std::fs::write(dir.join(format!("{version}.cosq")), &contents)?;
+    entries.push(HistoryEntry {
+        version,
+        saved_at: chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Secs, true),
+        reason: reason.to_string(),
+    });
+    std::fs::write(dir.join("log.yaml"), serde_yaml::to_string(&entries)?)?;
+    Ok(Some(version))
+}
+
+/// Record the current contents before cosq changes them: the file as it was
+/// before history began, or changes made outside cosq since the last version.
+pub fn checkpoint(query_path: &Path) -> Result<Option<u32>, HistoryError> {
+    let reason = if list(query_path)?.is_empty() {
+        "original"
+    } else {
+        "external change"
+    };
+    record(query_path, reason)
+}
+
+/// Write `contents` to `query_path`, recording history around the write.
+pub fn save(query_path: &Path, contents: &str, reason: &str) -> Result<(), HistoryError> {
+    checkpoint(query_path)?;
+    if let Some(parent) = query_path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    std::fs::write(query_path, contents)?;
+    record(query_path, reason)?;
+    Ok(())
+}
+
+/// Restore `version` of the query at `query_path`, recorded as a new version.
+pub fn revert(query_path: &Path, version: u32) -> Result<(), HistoryError> {
+    let contents = read_version(query_path, version)?;
+    save(query_path, &contents, &format!("revert to {version}"))
+}
+
+/// Move a query's history along with a rename (a no-op without history).
+pub fn rename(from: &Path, to: &Path) -> Result<(), HistoryError> {
+    let (from, to) = (history_dir(from), history_dir(to));
+    if !from.is_dir() {
+        return Ok(());
+    }
+    if let Some(parent) = to.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    std::fs::rename(from, to)?;
+    Ok(())
+}
+
+fn query_name(query_path: &Path) -> String {
+    query_path
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .unwrap_or("unknown")
+        .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_save_and_revert() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("orders.cosq");
+        assert!(list(&path).unwrap().is_empty());
+
+        save(&path, "v1", "create").unwrap();
+        save(&path, "v2", "ai template fix").unwrap();
+        // Unchanged contents are not recorded again
+        save(&path, "v2", "edit").unwrap();
+
+        let reasons: Vec<_> = list(&path).unwrap().into_iter().map(|e| e.reason).collect();
+        assert_eq!(reasons, vec!["create", "ai template fix"]);
+        assert_eq!(
+            history_dir(&path),
+            dir.path().join(".history").join("orders")
+        );
+
+        // A hand edit is captured before the next save
+        std::fs::write(&path, "v3").unwrap();
+        revert(&path, 1).unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "v1");
+        let entries = list(&path).unwrap();
+        assert_eq!(entries.len(), 4);
+        assert_eq!(entries[2].reason, "external change");
+        assert_eq!(entries[3].reason, "revert to 1");
+        assert_eq!(read_version(&path, 3).unwrap(), "v3");
+
+        assert!(matches!(
+            read_version(&path, 9),
+            Err(HistoryError::UnknownVersion { version: 9, .. })
+        ));
+    }
+
+    #[test]
+    fn test_first_save_keeps_original() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("q.cosq");
+        std::fs::write(&path, "before history").unwrap();
+        save(&path, "after", "edit").unwrap();
+        let entries = list(&path).unwrap();
+        assert_eq!(entries[0].reason, "original");
+        assert_eq!(read_version(&path, 1).unwrap(), "before history");
+        assert_eq!(read_version(&path, 2).unwrap(), "after");
+
+        let renamed = dir.path().join("r.cosq");
+        rename(&path, &renamed).unwrap();
+        assert_eq!(list(&renamed).unwrap().len(), 2);
+    }
+}
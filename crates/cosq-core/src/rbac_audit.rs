@@ -0,0 +1,176 @@
+//! Data plane RBAC audit for `cosq auth audit`
+//!
+//! Turns an account's Cosmos DB SQL role assignments into readable rows —
+//! the scope relative to the account, the role's name and data actions — and
+//! flags assignments that grant more than they likely should, or that point
+//! at principals or roles which no longer exist.
+
+use serde::Serialize;
+
+/// Scope label of an assignment covering the whole account
+pub const ACCOUNT_SCOPE: &str = "/";
+
+/// One role assignment, as reported by the audit
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct AuditEntry {
+    pub assignment_id: String,
+    pub principal_id: String,
+    /// Display name from Microsoft Graph, when it could be resolved
+    pub principal_name: Option<String>,
+    /// `user`, `group` or `service principal`, when resolved
+    pub principal_type: Option<String>,
+    pub role: String,
+    pub data_actions: Vec<String>,
+    /// `/` for the whole account, `dbs/<db>` or `dbs/<db>/colls/<container>`
+    pub scope: String,
+    pub findings: Vec<Finding>,
+}
+
+/// Why an assignment is flagged
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Finding {
+    /// Wildcard over every data plane operation of the account or database
+    FullControl,
+    /// Write access to every database and container of the account
+    AccountWideWrite,
+    /// Granted to a group: every current and future member gets it
+    GroupAssignment,
+    /// The principal is not in the directory any more
+    OrphanedPrincipal,
+    /// The role definition doesn't exist any more
+    UnknownRole,
+}
+
+impl Finding {
+    pub fn description(self) -> &'static str {
+        match self {
+            Finding::FullControl => {
+                "wildcard data actions: every data plane operation, including ones added later"
+            }
+            Finding::AccountWideWrite => {
+                "write access to every database and container; scope it to a database or container"
+            }
+            Finding::GroupAssignment => "granted to a group: review who is a member",
+            Finding::OrphanedPrincipal => {
+                "principal not found in the directory (deleted?); remove the assignment"
+            }
+            Finding::UnknownRole => "role definition not found; remove the assignment",
+        }
+    }
+}
+
+/// Whether a data action can change data (or is a wildcard that can).
+pub fn is_write_action(action: &str) -> bool {
+    let action = action.to_ascii_lowercase();
+    !(action.ends_with("/readmetadata")
+        || action.ends_with("/read")
+        || action.ends_with("/executequery")
+        || action.ends_with("/readchangefeed"))
+}
+
+/// Whether a data action is a wildcard over a whole account or database.
+fn is_full_control(action: &str) -> bool {
+    let action = action.to_ascii_lowercase();
+    action.ends_with("/databaseaccounts/*") || action.ends_with("/sqldatabases/*")
+}
+
+/// An assignment scope relative to the account: `/` for the account itself,
+/// otherwise e.g. `dbs/shop/colls/orders`.
+pub fn relative_scope(scope: &str, account_resource_id: &str) -> String {
+    let rest = match scope.get(..account_resource_id.len()) {
+        Some(prefix) if prefix.eq_ignore_ascii_case(account_resource_id) => {
+            &scope[account_resource_id.len()..]
+        }
+        _ => scope,
+    };
+    match rest.trim_matches('/') {
+        "" => ACCOUNT_SCOPE.to_string(),
+        rest => rest.to_string(),
+    }
+}
+
+/// Flag an assignment. `role_known` is false when its role definition is
+/// missing; `principal_found` is `None` when the directory couldn't be read.
+pub fn findings(
+    data_actions: &[String],
+    scope: &str,
+    role_known: bool,
+    principal_found: Option<bool>,
+    principal_type: Option<&str>,
+) -> Vec<Finding> {
+    let mut findings = Vec::new();
+    if !role_known {
+        findings.push(Finding::UnknownRole);
+    }
+    if data_actions.iter().any(|a| is_full_control(a)) {
+        findings.push(Finding::FullControl);
+    }
+    if scope == ACCOUNT_SCOPE && data_actions.iter().any(|a| is_write_action(a)) {
+        findings.push(Finding::AccountWideWrite);
+    }
+    if principal_type == Some("group") {
+        findings.push(Finding::GroupAssignment);
+    }
+    if principal_found == Some(false) {
+        findings.push(Finding::OrphanedPrincipal);
+    }
+    findings
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ACCOUNT: &str =
+        "/subscriptions/sub/resourceGroups/rg/providers/Microsoft.DocumentDB/databaseAccounts/prod";
+
+    fn actions(list: &[&str]) -> Vec<String> {
+        list.iter().map(|a| a.to_string()).collect()
+    }
+
+    #[test]
+    fn test_relative_scope() {
+        assert_eq!(relative_scope(ACCOUNT, ACCOUNT), "/");
+        assert_eq!(relative_scope(&format!("{ACCOUNT}/"), ACCOUNT), "/");
+        assert_eq!(
+            relative_scope(
+                &format!("{}/dbs/shop/colls/orders", ACCOUNT.to_lowercase()),
+                ACCOUNT
+            ),
+            "dbs/shop/colls/orders"
+        );
+        assert_eq!(relative_scope("/other", ACCOUNT), "other");
+    }
+
+    #[test]
+    fn test_findings() {
+        let contributor = actions(&[
+            "Microsoft.DocumentDB/databaseAccounts/readMetadata",
+            "Microsoft.DocumentDB/databaseAccounts/sqlDatabases/containers/*",
+            "Microsoft.DocumentDB/databaseAccounts/sqlDatabases/containers/items/*",
+        ]);
+        let reader = actions(&[
+            "Microsoft.DocumentDB/databaseAccounts/readMetadata",
+            "Microsoft.DocumentDB/databaseAccounts/sqlDatabases/containers/executeQuery",
+            "Microsoft.DocumentDB/databaseAccounts/sqlDatabases/containers/readChangeFeed",
+            "Microsoft.DocumentDB/databaseAccounts/sqlDatabases/containers/items/read",
+        ]);
+        let everything = actions(&["Microsoft.DocumentDB/databaseAccounts/*"]);
+
+        assert_eq!(
+            findings(&contributor, "/", true, Some(true), Some("user")),
+            vec![Finding::AccountWideWrite]
+        );
+        assert!(findings(&contributor, "dbs/shop", true, Some(true), Some("user")).is_empty());
+        assert!(findings(&reader, "/", true, None, None).is_empty());
+        assert_eq!(
+            findings(&everything, "dbs/shop", true, Some(true), Some("group")),
+            vec![Finding::FullControl, Finding::GroupAssignment]
+        );
+        assert_eq!(
+            findings(&[], "/", false, Some(false), None),
+            vec![Finding::UnknownRole, Finding::OrphanedPrincipal]
+        );
+    }
+}
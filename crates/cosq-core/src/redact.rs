@@ -0,0 +1,152 @@
+//! Field redaction (data masking)
+//!
+//! Patterns are dotted field paths from the `redact:` config list. A `*`
+//! segment matches any number of path segments, so `*.email` masks `email`
+//! at any depth and `customer.*` masks every field under `customer`. A
+//! leading `c.` (the conventional query alias) is ignored, and arrays are
+//! searched element by element:
+//!
+//! ```
+//! use cosq_core::redact::Redactor;
+//! use serde_json::json;
+//!
+//! let redactor = Redactor::new(["*.email", "c.ssn"]);
+//! let mut doc = json!({"ssn": "123", "contacts": [{"email": "a@b.c"}]});
+//! redactor.redact(&mut doc);
+//! assert_eq!(doc, json!({"ssn": "***", "contacts": [{"email": "***"}]}));
+//! ```
+
+use serde_json::Value;
+
+/// Replacement for redacted values
+pub const MASK: &str = "***";
+
+/// Masks values whose field path matches any configured pattern
+#[derive(Debug, Clone, Default)]
+pub struct Redactor {
+    patterns: Vec<Vec<String>>,
+}
+
+impl Redactor {
+    pub fn new<I, S>(patterns: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        let patterns = patterns
+            .into_iter()
+            .filter_map(|p| {
+                let p = p.as_ref().trim();
+                let p = p.strip_prefix("c.").unwrap_or(p);
+                (!p.is_empty()).then(|| p.split('.').map(str::to_string).collect())
+            })
+            .collect();
+        Self { patterns }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.patterns.is_empty()
+    }
+
+    /// Whether a field path (e.g. `["customer", "email"]`) matches a pattern.
+    pub fn matches(&self, path: &[&str]) -> bool {
+        self.patterns.iter().any(|p| matches_segments(p, path))
+    }
+
+    /// Mask matching fields in one document.
+    pub fn redact(&self, document: &mut Value) {
+        if !self.is_empty() {
+            self.walk(document, &mut Vec::new());
+        }
+    }
+
+    /// Mask matching fields in every document.
+    pub fn redact_all(&self, documents: &mut [Value]) {
+        for document in documents {
+            self.redact(document);
+        }
+    }
+
+    fn walk<'a>(&self, value: &'a mut Value, path: &mut Vec<&'a str>) {
+        match value {
+            Value::Object(map) => {
+                for (key, child) in map.iter_mut() {
+                    path.push(key.as_str());
+                    if self.matches(path) {
+                        *child = Value::String(MASK.to_string());
+                    } else {
+                        self.walk(child, path);
+                    }
+                    path.pop();
+                }
+            }
+            Value::Array(items) => {
+                for item in items {
+                    self.walk(item, path);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+fn matches_segments(pattern: &[String], path: &[&str]) -> bool {
+    match pattern.split_first() {
+        None => path.is_empty(),
+        // A trailing `*` matches the fields under a path, not the path itself
+        Some((first, rest)) if first == "*" && rest.is_empty() => !path.is_empty(),
+        // Otherwise `*` consumes zero or more segments
+        Some((first, rest)) if first == "*" => {
+            (0..=path.len()).any(|skip| matches_segments(rest, &path[skip..]))
+        }
+        Some((first, rest)) => {
+            matches!(path.split_first(), Some((segment, tail)) if segment == first && matches_segments(rest, tail))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_matches() {
+        let r = Redactor::new(["*.email", "c.ssn", "customer.*", "billing.card.number"]);
+        assert!(r.matches(&["email"]));
+        assert!(r.matches(&["a", "b", "email"]));
+        assert!(r.matches(&["ssn"]));
+        assert!(!r.matches(&["person", "ssn"]));
+        assert!(r.matches(&["customer", "name"]));
+        assert!(!r.matches(&["customer"]));
+        assert!(r.matches(&["billing", "card", "number"]));
+        assert!(!r.matches(&["billing", "card"]));
+    }
+
+    #[test]
+    fn test_redact_documents() {
+        let r = Redactor::new(["*.email", "billing.card"]);
+        let mut docs = vec![
+            json!({"id": "1", "email": "a@x.io", "billing": {"card": {"number": "4111"}, "city": "Oslo"}}),
+            json!({"id": "2", "contacts": [{"email": "b@x.io", "name": "Bo"}], "email": null}),
+        ];
+        r.redact_all(&mut docs);
+        assert_eq!(
+            docs[0],
+            json!({"id": "1", "email": "***", "billing": {"card": "***", "city": "Oslo"}})
+        );
+        assert_eq!(
+            docs[1],
+            json!({"id": "2", "contacts": [{"email": "***", "name": "Bo"}], "email": "***"})
+        );
+    }
+
+    #[test]
+    fn test_empty_patterns() {
+        let r = Redactor::new(["", "  "]);
+        assert!(r.is_empty());
+        let mut doc = json!({"email": "a@x.io"});
+        r.redact(&mut doc);
+        assert_eq!(doc, json!({"email": "a@x.io"}));
+    }
+}
@@ -0,0 +1,124 @@
+//! Local cache of query results for offline replay
+//!
+//! Every successful `cosq query` / `cosq run` stores its raw results under
+//! `~/.cosq/cache/results/`, keyed by a hash of what was queried (account,
+//! database, container, SQL and parameters). With `--offline`, the same
+//! command is answered from the cache instead of Cosmos DB, so templates and
+//! jq filters can be iterated on without network access or RU charges.
+
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum ResultCacheError {
+    #[error("could not determine home directory")]
+    NoHomeDir,
+
+    #[error("failed to read or write result cache: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("invalid result cache file: {0}")]
+    Parse(#[from] serde_json::Error),
+}
+
+/// Results of one query, as returned by Cosmos DB
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct CachedResult {
+    /// RFC 3339 timestamp of the run that produced the results
+    pub cached_at: String,
+    /// Request charge of that run
+    pub request_charge: f64,
+    /// Documents of a single-step query
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub documents: Vec<Value>,
+    /// Documents per step of a multi-step query
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub steps: BTreeMap<String, Vec<Value>>,
+}
+
+/// Cache key for the parts that identify a query (hex SHA-256).
+pub fn key(parts: &[&str]) -> String {
+    let mut hasher = Sha256::new();
+    for part in parts {
+        hasher.update(part.as_bytes());
+        // Separator so ("ab", "c") and ("a", "bc") differ
+        hasher.update([0]);
+    }
+    hasher
+        .finalize()
+        .iter()
+        .fold(String::with_capacity(64), |mut hex, byte| {
+            let _ = write!(hex, "{byte:02x}");
+            hex
+        })
+}
+
+/// Cache directory: `~/.cosq/cache/results/`.
+pub fn cache_dir() -> Result<PathBuf, ResultCacheError> {
+    dirs::home_dir()
+        .map(|d| d.join(".cosq").join("cache").join("results"))
+        .ok_or(ResultCacheError::NoHomeDir)
+}
+
+/// Load cached results for `key`, if any.
+pub fn load(key: &str) -> Result<Option<CachedResult>, ResultCacheError> {
+    load_from(&cache_dir()?, key)
+}
+
+/// Store results for `key`, replacing earlier ones.
+pub fn save(key: &str, result: &CachedResult) -> Result<(), ResultCacheError> {
+    save_to(&cache_dir()?, key, result)
+}
+
+pub fn load_from(dir: &Path, key: &str) -> Result<Option<CachedResult>, ResultCacheError> {
+    match std::fs::read_to_string(dir.join(format!("{key}.json"))) {
+        Ok(contents) => Ok(Some(serde_json::from_str(&contents)?)),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
+
+pub fn save_to(dir: &Path, key: &str, result: &CachedResult) -> Result<(), ResultCacheError> {
+    std::fs::create_dir_all(dir)?;
+    std::fs::write(
+        dir.join(format!("{key}.json")),
+        serde_json::to_string(result)?,
+    )?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_key() {
+        let k = key(&["acct", "db", "SELECT * FROM c"]);
+        assert_eq!(k.len(), 64);
+        assert_eq!(k, key(&["acct", "db", "SELECT * FROM c"]));
+        assert_ne!(key(&["ab", "c"]), key(&["a", "bc"]));
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let k = key(&["q"]);
+        assert_eq!(load_from(dir.path(), &k).unwrap(), None);
+
+        let result = CachedResult {
+            cached_at: "2026-01-01T00:00:00Z".into(),
+            request_charge: 2.5,
+            documents: vec![json!({"id": "1"})],
+            steps: BTreeMap::new(),
+        };
+        save_to(dir.path(), &k, &result).unwrap();
+        assert_eq!(load_from(dir.path(), &k).unwrap(), Some(result));
+    }
+}
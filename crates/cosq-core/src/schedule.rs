@@ -0,0 +1,289 @@
+//! Cron schedules for stored queries
+//!
+//! A stored query declares `schedule: "0 7 * * 1-5"` (standard 5-field cron, or a
+//! shortcut like `@daily`). `cosq schedule export` turns these into crontab lines
+//! or systemd timers so the host's scheduler runs them — cosq has no daemon.
+
+use thiserror::Error;
+
+#[derive(Debug, Error, PartialEq)]
+pub enum ScheduleError {
+    #[error("invalid schedule '{expr}': expected 5 fields (minute hour day month weekday)")]
+    FieldCount { expr: String },
+
+    #[error("invalid schedule '{expr}': bad {field} value '{value}'")]
+    InvalidField {
+        expr: String,
+        field: &'static str,
+        value: String,
+    },
+
+    #[error("unsupported schedule shortcut '{0}'")]
+    UnsupportedShortcut(String),
+}
+
+/// Cron field definitions: name, min, max
+const FIELDS: [(&str, u32, u32); 5] = [
+    ("minute", 0, 59),
+    ("hour", 0, 23),
+    ("day-of-month", 1, 31),
+    ("month", 1, 12),
+    ("day-of-week", 0, 7),
+];
+
+const MONTH_NAMES: [&str; 12] = [
+    "jan", "feb", "mar", "apr", "may", "jun", "jul", "aug", "sep", "oct", "nov", "dec",
+];
+
+/// systemd weekday names indexed by cron day number (0 and 7 are both Sunday)
+const WEEKDAYS: [&str; 8] = ["Sun", "Mon", "Tue", "Wed", "Thu", "Fri", "Sat", "Sun"];
+
+/// A validated cron schedule
+#[derive(Debug, Clone, PartialEq)]
+pub enum Schedule {
+    /// `@hourly`, `@daily`, `@weekly`, `@monthly`, `@yearly` (systemd calendar keyword)
+    Shortcut(&'static str),
+    /// Five cron fields, each expanded to sorted values (`None` = `*`)
+    Fields {
+        expr: String,
+        values: [Option<Vec<u32>>; 5],
+        /// Original step for `*/n` fields, kept so `0/15` stays compact
+        steps: [Option<u32>; 5],
+    },
+}
+
+impl Schedule {
+    /// Parse and validate a cron expression.
+    pub fn parse(expr: &str) -> Result<Self, ScheduleError> {
+        let expr = expr.trim();
+        if let Some(shortcut) = expr.strip_prefix('@') {
+            return match shortcut {
+                "hourly" => Ok(Schedule::Shortcut("hourly")),
+                "daily" | "midnight" => Ok(Schedule::Shortcut("daily")),
+                "weekly" => Ok(Schedule::Shortcut("weekly")),
+                "monthly" => Ok(Schedule::Shortcut("monthly")),
+                "yearly" | "annually" => Ok(Schedule::Shortcut("yearly")),
+                _ => Err(ScheduleError::UnsupportedShortcut(expr.to_string())),
+            };
+        }
+
+        let parts: Vec<&str> = expr.split_whitespace().collect();
+        if parts.len() != 5 {
+            return Err(ScheduleError::FieldCount {
+                expr: expr.to_string(),
+            });
+        }
+
+        let mut values: [Option<Vec<u32>>; 5] = Default::default();
+        let mut steps: [Option<u32>; 5] = [None; 5];
+        for (i, part) in parts.iter().enumerate() {
+            let (name, min, max) = FIELDS[i];
+            let invalid = || ScheduleError::InvalidField {
+                expr: expr.to_string(),
+                field: name,
+                value: part.to_string(),
+            };
+            if *part == "*" {
+                continue;
+            }
+            if let Some(step) = part.strip_prefix("*/") {
+                let step: u32 = step.parse().map_err(|_| invalid())?;
+                if step == 0 {
+                    return Err(invalid());
+                }
+                steps[i] = Some(step);
+                values[i] = Some((min..=max).step_by(step as usize).collect());
+                continue;
+            }
+
+            let mut field_values = Vec::new();
+            for token in part.split(',') {
+                let (range, step) = match token.split_once('/') {
+                    Some((range, step)) => {
+                        let step: u32 = step.parse().map_err(|_| invalid())?;
+                        if step == 0 {
+                            return Err(invalid());
+                        }
+                        (range, step)
+                    }
+                    None => (token, 1),
+                };
+                let (start, end) = match range.split_once('-') {
+                    Some((a, b)) => (
+                        parse_value(a, i).ok_or_else(invalid)?,
+                        parse_value(b, i).ok_or_else(invalid)?,
+                    ),
+                    None => {
+                        let v = parse_value(range, i).ok_or_else(invalid)?;
+                        (v, v)
+                    }
+                };
+                if start < min || end > max || start > end {
+                    return Err(invalid());
+                }
+                field_values.extend((start..=end).step_by(step as usize));
+            }
+            // Sunday may be written as 0 or 7
+            if i == 4 {
+                for v in field_values.iter_mut() {
+                    if *v == 7 {
+                        *v = 0;
+                    }
+                }
+            }
+            field_values.sort_unstable();
+            field_values.dedup();
+            values[i] = Some(field_values);
+        }
+
+        Ok(Schedule::Fields {
+            expr: parts.join(" "),
+            values,
+            steps,
+        })
+    }
+
+    /// The schedule as a crontab time specification.
+    pub fn to_cron(&self) -> String {
+        match self {
+            Schedule::Shortcut(keyword) => format!("@{keyword}"),
+            Schedule::Fields { expr, .. } => expr.clone(),
+        }
+    }
+
+    /// The schedule as a systemd `OnCalendar=` value.
+    pub fn to_on_calendar(&self) -> String {
+        let (values, steps) = match self {
+            Schedule::Shortcut(keyword) => return keyword.to_string(),
+            Schedule::Fields { values, steps, .. } => (values, steps),
+        };
+
+        let field = |i: usize, pad: bool| -> String {
+            match (&values[i], steps[i]) {
+                (None, _) => "*".to_string(),
+                (Some(_), Some(step)) => format!("{}/{step}", FIELDS[i].1),
+                (Some(vals), None) => compact_list(vals, |v| {
+                    if pad {
+                        format!("{v:02}")
+                    } else {
+                        v.to_string()
+                    }
+                }),
+            }
+        };
+
+        let date = format!("*-{}-{}", field(3, false), field(2, false));
+        let time = format!("{}:{}:00", field(1, true), field(0, true));
+        match &values[4] {
+            None => format!("{date} {time}"),
+            Some(days) => {
+                let weekdays = compact_list(days, |d| WEEKDAYS[d as usize].to_string());
+                format!("{weekdays} {date} {time}")
+            }
+        }
+    }
+
+    /// Whether both day-of-month and day-of-week are restricted. cron fires when
+    /// *either* matches, while systemd requires *both*, so the timer may differ.
+    pub fn has_day_ambiguity(&self) -> bool {
+        matches!(self, Schedule::Fields { values, .. } if values[2].is_some() && values[4].is_some())
+    }
+}
+
+/// Parse a numeric value or (for month/weekday) a three-letter name.
+fn parse_value(token: &str, field: usize) -> Option<u32> {
+    if let Ok(v) = token.parse() {
+        return Some(v);
+    }
+    let lower = token.to_lowercase();
+    match field {
+        3 => MONTH_NAMES
+            .iter()
+            .position(|m| *m == lower)
+            .map(|p| p as u32 + 1),
+        4 => WEEKDAYS[..7]
+            .iter()
+            .position(|d| d.to_lowercase() == lower)
+            .map(|p| p as u32),
+        _ => None,
+    }
+}
+
+/// Render sorted values as a systemd list, collapsing consecutive runs into `a..b`.
+fn compact_list(values: &[u32], render: impl Fn(u32) -> String) -> String {
+    let mut parts = Vec::new();
+    let mut i = 0;
+    while i < values.len() {
+        let start = values[i];
+        let mut end = start;
+        while i + 1 < values.len() && values[i + 1] == end + 1 {
+            end += 1;
+            i += 1;
+        }
+        if end >= start + 2 {
+            parts.push(format!("{}..{}", render(start), render(end)));
+        } else if end > start {
+            parts.push(render(start));
+            parts.push(render(end));
+        } else {
+            parts.push(render(start));
+        }
+        i += 1;
+    }
+    parts.join(",")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_weekday_schedule() {
+        let s = Schedule::parse("0 7 * * 1-5").unwrap();
+        assert_eq!(s.to_cron(), "0 7 * * 1-5");
+        assert_eq!(s.to_on_calendar(), "Mon..Fri *-*-* 07:00:00");
+        assert!(!s.has_day_ambiguity());
+    }
+
+    #[test]
+    fn test_steps_and_lists() {
+        let s = Schedule::parse("*/15 * * * *").unwrap();
+        assert_eq!(s.to_on_calendar(), "*-*-* *:0/15:00");
+
+        let s = Schedule::parse("30 6,18 1 jan,jul *").unwrap();
+        assert_eq!(s.to_on_calendar(), "*-1,7-1 06,18:30:00");
+
+        let s = Schedule::parse("0 0 * * 0,6").unwrap();
+        assert_eq!(s.to_on_calendar(), "Sun,Sat *-*-* 00:00:00");
+
+        let s = Schedule::parse("0 8-18/2 * * *").unwrap();
+        assert_eq!(s.to_on_calendar(), "*-*-* 08,10,12,14,16,18:00:00");
+    }
+
+    #[test]
+    fn test_shortcuts() {
+        assert_eq!(Schedule::parse("@daily").unwrap().to_on_calendar(), "daily");
+        assert_eq!(Schedule::parse("@daily").unwrap().to_cron(), "@daily");
+        assert!(matches!(
+            Schedule::parse("@reboot"),
+            Err(ScheduleError::UnsupportedShortcut(_))
+        ));
+    }
+
+    #[test]
+    fn test_invalid_schedules() {
+        assert!(matches!(
+            Schedule::parse("0 7 * *"),
+            Err(ScheduleError::FieldCount { .. })
+        ));
+        assert!(Schedule::parse("60 * * * *").is_err());
+        assert!(Schedule::parse("0 7 * * mon-sun").is_err());
+        assert!(Schedule::parse("*/0 * * * *").is_err());
+        assert!(Schedule::parse("0 25 * * *").is_err());
+    }
+
+    #[test]
+    fn test_day_ambiguity() {
+        assert!(Schedule::parse("0 0 1 * 1").unwrap().has_day_ambiguity());
+    }
+}
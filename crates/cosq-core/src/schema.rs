@@ -0,0 +1,335 @@
+//! Schema inference from sampled documents
+//!
+//! Cosmos DB containers are schemaless, so the shape of a container is
+//! inferred by walking a sample of its documents and recording every field
+//! path (`customer.name`) with the JSON types seen there, how often it is
+//! present or null, and a few example values.
+//!
+//! `cosq schema` caches the result under `~/.cosq/schemas/` so other commands
+//! (such as AI query generation) can reuse it without sampling again.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use thiserror::Error;
+
+/// Example values kept per field
+const MAX_EXAMPLES: usize = 3;
+
+/// Longest string example kept (longer values are truncated)
+const MAX_EXAMPLE_LEN: usize = 40;
+
+#[derive(Debug, Error)]
+pub enum SchemaError {
+    #[error("could not determine home directory")]
+    NoHomeDir,
+
+    #[error("failed to read or write schema cache: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("invalid schema cache file: {0}")]
+    Parse(#[from] serde_json::Error),
+}
+
+/// System properties Cosmos DB adds to every document
+pub const SYSTEM_FIELDS: &[&str] = &["_rid", "_self", "_etag", "_attachments", "_ts"];
+
+/// JSON type of a field value
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum JsonType {
+    Null,
+    Bool,
+    Number,
+    String,
+    Array,
+    Object,
+}
+
+impl JsonType {
+    pub fn of(value: &Value) -> Self {
+        match value {
+            Value::Null => JsonType::Null,
+            Value::Bool(_) => JsonType::Bool,
+            Value::Number(_) => JsonType::Number,
+            Value::String(_) => JsonType::String,
+            Value::Array(_) => JsonType::Array,
+            Value::Object(_) => JsonType::Object,
+        }
+    }
+}
+
+impl fmt::Display for JsonType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            JsonType::Null => "null",
+            JsonType::Bool => "bool",
+            JsonType::Number => "number",
+            JsonType::String => "string",
+            JsonType::Array => "array",
+            JsonType::Object => "object",
+        })
+    }
+}
+
+/// One field path and what was observed there
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FieldSchema {
+    /// Dotted path from the document root (`customer.address.city`)
+    pub path: String,
+    /// Types seen at this path
+    pub types: BTreeSet<JsonType>,
+    /// Number of sampled documents containing the field
+    pub present: usize,
+    /// Number of those where the value was `null`
+    #[serde(default)]
+    pub nulls: usize,
+    /// A few distinct non-null scalar values seen at this path
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub examples: Vec<Value>,
+}
+
+impl FieldSchema {
+    /// The single non-null type seen, if the field is consistently typed.
+    pub fn primary_type(&self) -> Option<JsonType> {
+        let mut types = self.types.iter().filter(|t| **t != JsonType::Null);
+        match (types.next(), types.next()) {
+            (Some(t), None) => Some(*t),
+            _ => None,
+        }
+    }
+
+    /// Percentage of `sampled` documents containing the field.
+    pub fn presence_pct(&self, sampled: usize) -> f64 {
+        percent(self.present, sampled)
+    }
+
+    /// Percentage of documents containing the field where it was `null`.
+    pub fn null_pct(&self) -> f64 {
+        percent(self.nulls, self.present)
+    }
+
+    /// Types joined with `|` (`null|number`).
+    pub fn type_names(&self) -> String {
+        let types: Vec<String> = self.types.iter().map(ToString::to_string).collect();
+        types.join("|")
+    }
+}
+
+fn percent(part: usize, whole: usize) -> f64 {
+    if whole == 0 {
+        0.0
+    } else {
+        part as f64 * 100.0 / whole as f64
+    }
+}
+
+/// Inferred schema of a container
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Schema {
+    /// Number of documents the schema was inferred from
+    pub sampled: usize,
+    /// RFC 3339 timestamp of when the sample was taken
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sampled_at: Option<String>,
+    /// Fields ordered by path
+    pub fields: Vec<FieldSchema>,
+}
+
+impl Schema {
+    /// Infer a schema from sampled documents (system properties are skipped).
+    pub fn infer(documents: &[Value]) -> Self {
+        let mut fields: BTreeMap<String, FieldSchema> = BTreeMap::new();
+        for document in documents {
+            if let Value::Object(map) = document {
+                for (key, value) in map {
+                    if !SYSTEM_FIELDS.contains(&key.as_str()) {
+                        walk(key.clone(), value, &mut fields);
+                    }
+                }
+            }
+        }
+        Self {
+            sampled: documents.len(),
+            sampled_at: None,
+            fields: fields.into_values().collect(),
+        }
+    }
+
+    /// Look up a field by path.
+    pub fn field(&self, path: &str) -> Option<&FieldSchema> {
+        self.fields.iter().find(|f| f.path == path)
+    }
+
+    /// Compact one-line-per-field description for AI prompts.
+    pub fn summary(&self) -> String {
+        self.fields
+            .iter()
+            .map(|f| {
+                format!(
+                    "{}: {} ({:.0}% present)",
+                    f.path,
+                    f.type_names(),
+                    f.presence_pct(self.sampled)
+                )
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    /// Cache path: `~/.cosq/schemas/<account>/<database>/<container>.json`.
+    pub fn cache_path(
+        account: &str,
+        database: &str,
+        container: &str,
+    ) -> Result<PathBuf, SchemaError> {
+        dirs::home_dir()
+            .map(|d| {
+                d.join(".cosq")
+                    .join("schemas")
+                    .join(account)
+                    .join(database)
+                    .join(format!("{container}.json"))
+            })
+            .ok_or(SchemaError::NoHomeDir)
+    }
+
+    /// Load a cached schema, if one has been saved for this container.
+    pub fn load_cached(
+        account: &str,
+        database: &str,
+        container: &str,
+    ) -> Result<Option<Self>, SchemaError> {
+        Self::load_from(&Self::cache_path(account, database, container)?)
+    }
+
+    /// Save to the cache for this container.
+    pub fn save_cached(
+        &self,
+        account: &str,
+        database: &str,
+        container: &str,
+    ) -> Result<PathBuf, SchemaError> {
+        let path = Self::cache_path(account, database, container)?;
+        self.save_to(&path)?;
+        Ok(path)
+    }
+
+    pub fn load_from(path: &Path) -> Result<Option<Self>, SchemaError> {
+        match std::fs::read_to_string(path) {
+            Ok(contents) => Ok(Some(serde_json::from_str(&contents)?)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    pub fn save_to(&self, path: &Path) -> Result<(), SchemaError> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+}
+
+fn walk(path: String, value: &Value, fields: &mut BTreeMap<String, FieldSchema>) {
+    let entry = fields.entry(path.clone()).or_insert_with(|| FieldSchema {
+        path: path.clone(),
+        types: BTreeSet::new(),
+        present: 0,
+        nulls: 0,
+        examples: Vec::new(),
+    });
+    entry.types.insert(JsonType::of(value));
+    entry.present += 1;
+    match value {
+        Value::Null => entry.nulls += 1,
+        Value::Array(_) | Value::Object(_) => {}
+        scalar => {
+            let example = match scalar {
+                Value::String(s) if s.chars().count() > MAX_EXAMPLE_LEN => Value::String(format!(
+                    "{}…",
+                    s.chars().take(MAX_EXAMPLE_LEN).collect::<String>()
+                )),
+                other => other.clone(),
+            };
+            if entry.examples.len() < MAX_EXAMPLES && !entry.examples.contains(&example) {
+                entry.examples.push(example);
+            }
+        }
+    }
+
+    // Arrays are leaves: element paths can't be addressed with dotted syntax
+    if let Value::Object(map) = value {
+        for (key, child) in map {
+            walk(format!("{path}.{key}"), child, fields);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_infer() {
+        let docs = vec![
+            json!({"id": "1", "total": 10, "customer": {"name": "Ada"}, "_ts": 1}),
+            json!({"id": "2", "total": null, "tags": ["a"], "customer": {"name": "Bob", "vip": true}}),
+        ];
+        let schema = Schema::infer(&docs);
+        assert_eq!(schema.sampled, 2);
+
+        let paths: Vec<&str> = schema.fields.iter().map(|f| f.path.as_str()).collect();
+        assert_eq!(
+            paths,
+            [
+                "customer",
+                "customer.name",
+                "customer.vip",
+                "id",
+                "tags",
+                "total"
+            ]
+        );
+
+        let total = schema.field("total").unwrap();
+        assert_eq!(total.present, 2);
+        assert_eq!(total.nulls, 1);
+        assert_eq!(total.null_pct(), 50.0);
+        assert_eq!(total.type_names(), "null|number");
+        assert_eq!(total.examples, vec![json!(10)]);
+        assert_eq!(total.primary_type(), Some(JsonType::Number));
+        let vip = schema.field("customer.vip").unwrap();
+        assert_eq!(vip.present, 1);
+        assert_eq!(vip.presence_pct(schema.sampled), 50.0);
+        assert_eq!(
+            schema.field("customer.name").unwrap().examples,
+            vec![json!("Ada"), json!("Bob")]
+        );
+        assert_eq!(
+            schema.field("tags").unwrap().primary_type(),
+            Some(JsonType::Array)
+        );
+    }
+
+    #[test]
+    fn test_cache_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("acct").join("db").join("orders.json");
+        assert_eq!(Schema::load_from(&path).unwrap(), None);
+
+        let mut schema = Schema::infer(&[json!({"id": "1", "total": 2.5})]);
+        schema.sampled_at = Some("2026-01-01T00:00:00Z".into());
+        schema.save_to(&path).unwrap();
+        assert_eq!(Schema::load_from(&path).unwrap(), Some(schema.clone()));
+        assert_eq!(
+            schema.summary(),
+            "id: string (100% present)\ntotal: number (100% present)"
+        );
+    }
+}
@@ -0,0 +1,370 @@
+//! Export an inferred [`Schema`] as JSON Schema, TypeScript or Rust
+//!
+//! Dotted field paths are rebuilt into nested objects. A field is required
+//! when it was present in every sampled parent object, and nullable when a
+//! `null` was seen; arrays are typed loosely since element shapes aren't
+//! inferred.
+
+use std::collections::BTreeMap;
+
+use serde_json::{Map, Value, json};
+
+use crate::schema::{FieldSchema, JsonType, Schema};
+
+/// A field and its nested fields, rebuilt from dotted paths
+struct Node<'a> {
+    field: &'a FieldSchema,
+    children: BTreeMap<&'a str, Node<'a>>,
+}
+
+/// Top-level fields of `schema` as a tree.
+fn tree(schema: &Schema) -> BTreeMap<&str, Node<'_>> {
+    let mut root: BTreeMap<&str, Node<'_>> = BTreeMap::new();
+    // Fields are sorted by path, so parents are inserted before children
+    for field in &schema.fields {
+        let mut segments = field.path.split('.').peekable();
+        let mut level = &mut root;
+        while let Some(segment) = segments.next() {
+            if segments.peek().is_none() {
+                level.insert(
+                    segment,
+                    Node {
+                        field,
+                        children: BTreeMap::new(),
+                    },
+                );
+                break;
+            }
+            match level.get_mut(segment) {
+                Some(parent) => level = &mut parent.children,
+                None => break,
+            }
+        }
+    }
+    root
+}
+
+fn is_required(field: &FieldSchema, parent_count: usize) -> bool {
+    field.present >= parent_count
+}
+
+fn is_nullable(field: &FieldSchema) -> bool {
+    field.types.contains(&JsonType::Null)
+}
+
+/// JSON Schema (draft 2020-12) document for the inferred shape.
+pub fn to_json_schema(schema: &Schema, title: &str) -> Value {
+    let mut document = object_schema(&tree(schema), schema.sampled);
+    if let Value::Object(ref mut map) = document {
+        let mut header = Map::new();
+        header.insert(
+            "$schema".into(),
+            json!("https://json-schema.org/draft/2020-12/schema"),
+        );
+        header.insert("title".into(), json!(title));
+        header.append(map);
+        return Value::Object(header);
+    }
+    document
+}
+
+fn object_schema(children: &BTreeMap<&str, Node<'_>>, parent_count: usize) -> Value {
+    let mut properties = Map::new();
+    let mut required = Vec::new();
+    for (name, node) in children {
+        properties.insert((*name).to_string(), field_schema(node));
+        if is_required(node.field, parent_count) {
+            required.push(json!(name));
+        }
+    }
+    let mut out = Map::new();
+    out.insert("type".into(), json!("object"));
+    out.insert("properties".into(), Value::Object(properties));
+    if !required.is_empty() {
+        out.insert("required".into(), Value::Array(required));
+    }
+    Value::Object(out)
+}
+
+fn field_schema(node: &Node<'_>) -> Value {
+    let types: Vec<&str> = node
+        .field
+        .types
+        .iter()
+        .map(|t| match t {
+            JsonType::Null => "null",
+            JsonType::Bool => "boolean",
+            JsonType::Number => "number",
+            JsonType::String => "string",
+            JsonType::Array => "array",
+            JsonType::Object => "object",
+        })
+        .collect();
+    let mut out = match node.field.types.contains(&JsonType::Object) {
+        // Objects seen alongside other types still describe their properties
+        true => match object_schema(&node.children, node.field.present - node.field.nulls) {
+            Value::Object(map) => map,
+            _ => Map::new(),
+        },
+        false => Map::new(),
+    };
+    out.insert(
+        "type".into(),
+        match types.as_slice() {
+            [single] => json!(single),
+            many => json!(many),
+        },
+    );
+    if !node.field.examples.is_empty() {
+        out.insert("examples".into(), json!(node.field.examples));
+    }
+    Value::Object(out)
+}
+
+/// TypeScript interfaces; nested objects become `<Parent><Field>` interfaces.
+pub fn to_typescript(schema: &Schema, name: &str) -> String {
+    let mut interfaces = Vec::new();
+    typescript_interface(
+        &pascal_case(name),
+        &tree(schema),
+        schema.sampled,
+        &mut interfaces,
+    );
+    interfaces.join("\n")
+}
+
+fn typescript_interface(
+    name: &str,
+    children: &BTreeMap<&str, Node<'_>>,
+    parent_count: usize,
+    out: &mut Vec<String>,
+) {
+    let mut body = format!("export interface {name} {{\n");
+    let mut nested = Vec::new();
+    for (key, node) in children {
+        let types: Vec<String> = node
+            .field
+            .types
+            .iter()
+            .map(|t| match t {
+                JsonType::Null => "null".to_string(),
+                JsonType::Bool => "boolean".to_string(),
+                JsonType::Number => "number".to_string(),
+                JsonType::String => "string".to_string(),
+                JsonType::Array => "unknown[]".to_string(),
+                JsonType::Object => {
+                    let child = format!("{name}{}", pascal_case(key));
+                    nested.push((child.clone(), node));
+                    child
+                }
+            })
+            .collect();
+        let optional = if is_required(node.field, parent_count) {
+            ""
+        } else {
+            "?"
+        };
+        let key = if is_identifier(key) {
+            (*key).to_string()
+        } else {
+            Value::String((*key).to_string()).to_string()
+        };
+        body.push_str(&format!("  {key}{optional}: {};\n", types.join(" | ")));
+    }
+    body.push_str("}\n");
+    out.push(body);
+    for (child, node) in nested {
+        typescript_interface(
+            &child,
+            &node.children,
+            node.field.present - node.field.nulls,
+            out,
+        );
+    }
+}
+
+/// Rust structs deriving serde's `Serialize`/`Deserialize`.
+///
+/// Optional or nullable fields are `Option<_>`; fields with mixed types
+/// fall back to `serde_json::Value`.
+pub fn to_rust(schema: &Schema, name: &str) -> String {
+    let mut structs = Vec::new();
+    rust_struct(
+        &pascal_case(name),
+        &tree(schema),
+        schema.sampled,
+        &mut structs,
+    );
+    format!(
+        "use serde::{{Deserialize, Serialize}};\n\n{}",
+        structs.join("\n")
+    )
+}
+
+fn rust_struct(
+    name: &str,
+    children: &BTreeMap<&str, Node<'_>>,
+    parent_count: usize,
+    out: &mut Vec<String>,
+) {
+    let mut body =
+        format!("#[derive(Debug, Clone, Serialize, Deserialize)]\npub struct {name} {{\n");
+    let mut nested = Vec::new();
+    for (key, node) in children {
+        let base = match node.field.primary_type() {
+            Some(JsonType::Bool) => "bool".to_string(),
+            Some(JsonType::Number) => "f64".to_string(),
+            Some(JsonType::String) => "String".to_string(),
+            Some(JsonType::Array) => "Vec<serde_json::Value>".to_string(),
+            Some(JsonType::Object) => {
+                let child = format!("{name}{}", pascal_case(key));
+                nested.push((child.clone(), node));
+                child
+            }
+            Some(JsonType::Null) | None => "serde_json::Value".to_string(),
+        };
+        let optional = node.field.primary_type().is_some()
+            && (!is_required(node.field, parent_count) || is_nullable(node.field));
+        let field_name = snake_case(key);
+        if field_name.trim_start_matches("r#") != *key {
+            body.push_str(&format!("    #[serde(rename = {:?})]\n", key));
+        }
+        if optional {
+            body.push_str("    #[serde(default, skip_serializing_if = \"Option::is_none\")]\n");
+            body.push_str(&format!("    pub {field_name}: Option<{base}>,\n"));
+        } else {
+            body.push_str(&format!("    pub {field_name}: {base},\n"));
+        }
+    }
+    body.push_str("}\n");
+    out.push(body);
+    for (child, node) in nested {
+        rust_struct(
+            &child,
+            &node.children,
+            node.field.present - node.field.nulls,
+            out,
+        );
+    }
+}
+
+/// `order-lines` / `order_lines` / `orderLines` → `OrderLines`
+fn pascal_case(name: &str) -> String {
+    let mut out = String::new();
+    let mut upper = true;
+    for c in name.chars() {
+        if c.is_ascii_alphanumeric() {
+            if upper {
+                out.push(c.to_ascii_uppercase());
+            } else {
+                out.push(c);
+            }
+            upper = false;
+        } else {
+            upper = true;
+        }
+    }
+    if out.is_empty() || out.starts_with(|c: char| c.is_ascii_digit()) {
+        out.insert_str(0, "Document");
+    }
+    out
+}
+
+/// `createdAt` / `created-at` → `created_at` (Rust keywords get an `r#` prefix)
+fn snake_case(name: &str) -> String {
+    let mut out = String::new();
+    let mut prev_lower = false;
+    for c in name.chars() {
+        if c.is_ascii_uppercase() {
+            if prev_lower {
+                out.push('_');
+            }
+            out.push(c.to_ascii_lowercase());
+            prev_lower = false;
+        } else if c.is_ascii_alphanumeric() {
+            out.push(c);
+            prev_lower = c.is_ascii_lowercase() || c.is_ascii_digit();
+        } else {
+            if !out.is_empty() && !out.ends_with('_') {
+                out.push('_');
+            }
+            prev_lower = false;
+        }
+    }
+    let out = out.trim_end_matches('_').to_string();
+    if out.is_empty() || out.starts_with(|c: char| c.is_ascii_digit()) {
+        format!("field_{out}")
+    } else if matches!(
+        out.as_str(),
+        "type" | "ref" | "match" | "move" | "static" | "struct" | "enum" | "fn" | "mod" | "use"
+    ) {
+        format!("r#{out}")
+    } else {
+        out
+    }
+}
+
+fn is_identifier(s: &str) -> bool {
+    let mut chars = s.chars();
+    matches!(chars.next(), Some(c) if c.is_ascii_alphabetic() || c == '_' || c == '$')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '$')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample() -> Schema {
+        Schema::infer(&[
+            json!({"id": "1", "total": 10, "customer": {"name": "Ada"}, "tags": ["a"]}),
+            json!({"id": "2", "total": null, "customer": {"name": "Bob", "isVip": true}}),
+        ])
+    }
+
+    #[test]
+    fn test_json_schema() {
+        let doc = to_json_schema(&sample(), "orders");
+        assert_eq!(doc["title"], "orders");
+        assert_eq!(doc["required"], json!(["customer", "id", "total"]));
+        assert_eq!(
+            doc["properties"]["total"]["type"],
+            json!(["null", "number"])
+        );
+        assert_eq!(doc["properties"]["tags"]["type"], "array");
+        let customer = &doc["properties"]["customer"];
+        assert_eq!(customer["required"], json!(["name"]));
+        assert_eq!(customer["properties"]["isVip"]["type"], "boolean");
+    }
+
+    #[test]
+    fn test_typescript() {
+        let ts = to_typescript(&sample(), "order-headers");
+        assert_eq!(
+            ts,
+            "export interface OrderHeaders {\n  customer: OrderHeadersCustomer;\n  id: string;\n  tags?: unknown[];\n  total: null | number;\n}\n\
+             \nexport interface OrderHeadersCustomer {\n  isVip?: boolean;\n  name: string;\n}\n"
+        );
+    }
+
+    #[test]
+    fn test_rust() {
+        let rs = to_rust(&sample(), "orders");
+        assert!(rs.contains("pub struct Orders {"));
+        assert!(rs.contains("    pub customer: OrdersCustomer,\n"));
+        assert!(rs.contains("    pub id: String,\n"));
+        assert!(rs.contains("    pub total: Option<f64>,\n"));
+        assert!(rs.contains("    pub tags: Option<Vec<serde_json::Value>>,\n"));
+        assert!(rs.contains(
+            "    #[serde(rename = \"isVip\")]\n    #[serde(default, skip_serializing_if = \"Option::is_none\")]\n    pub is_vip: Option<bool>,\n"
+        ));
+    }
+
+    #[test]
+    fn test_case_conversion() {
+        assert_eq!(pascal_case("order_lines"), "OrderLines");
+        assert_eq!(pascal_case("123"), "Document123");
+        assert_eq!(snake_case("createdAt"), "created_at");
+        assert_eq!(snake_case("first-name"), "first_name");
+        assert_eq!(snake_case("type"), "r#type");
+    }
+}
@@ -0,0 +1,101 @@
+//! Secrets kept in the OS keyring
+//!
+//! Sensitive config values (account master keys, webhook URLs) can be stored
+//! with `cosq secrets set <name>` and referenced from `config.yaml` as
+//! `keyring:<name>` instead of being written in plaintext:
+//!
+//! ```yaml
+//! account:
+//!   master_key: keyring:prod-key
+//! webhooks:
+//!   ops:
+//!     url: keyring:ops-webhook
+//! ```
+//!
+//! Entries live under the `cosq` service in the platform keyring (macOS
+//! Keychain, Windows Credential Manager, or the Secret Service on Linux).
+
+use thiserror::Error;
+
+/// Prefix marking a config value as a keyring reference
+pub const KEYRING_PREFIX: &str = "keyring:";
+
+/// Keyring service name all cosq secrets are stored under
+const SERVICE: &str = "cosq";
+
+#[derive(Debug, Error)]
+pub enum SecretError {
+    #[error("secret '{0}' not found in the OS keyring — store it with `cosq secrets set {0}`")]
+    NotFound(String),
+
+    #[error("empty secret name in '{0}' (expected keyring:<name>)")]
+    EmptyName(String),
+
+    #[error("OS keyring error: {0}")]
+    Keyring(String),
+}
+
+/// Whether a config value is a `keyring:<name>` reference.
+pub fn is_reference(value: &str) -> bool {
+    value.starts_with(KEYRING_PREFIX)
+}
+
+/// Resolve a config value: `keyring:<name>` is looked up in the OS keyring,
+/// anything else is returned unchanged.
+pub fn resolve(value: &str) -> Result<String, SecretError> {
+    match value.strip_prefix(KEYRING_PREFIX) {
+        Some(name) => {
+            let name = name.trim();
+            if name.is_empty() {
+                return Err(SecretError::EmptyName(value.to_string()));
+            }
+            get(name)?.ok_or_else(|| SecretError::NotFound(name.to_string()))
+        }
+        None => Ok(value.to_string()),
+    }
+}
+
+/// Read a secret, or `None` if it hasn't been stored.
+pub fn get(name: &str) -> Result<Option<String>, SecretError> {
+    match entry(name)?.get_password() {
+        Ok(secret) => Ok(Some(secret)),
+        Err(keyring::Error::NoEntry) => Ok(None),
+        Err(e) => Err(SecretError::Keyring(e.to_string())),
+    }
+}
+
+/// Store (or replace) a secret.
+pub fn set(name: &str, secret: &str) -> Result<(), SecretError> {
+    entry(name)?
+        .set_password(secret)
+        .map_err(|e| SecretError::Keyring(e.to_string()))
+}
+
+/// Delete a secret. Returns `false` if it didn't exist.
+pub fn delete(name: &str) -> Result<bool, SecretError> {
+    match entry(name)?.delete_credential() {
+        Ok(()) => Ok(true),
+        Err(keyring::Error::NoEntry) => Ok(false),
+        Err(e) => Err(SecretError::Keyring(e.to_string())),
+    }
+}
+
+fn entry(name: &str) -> Result<keyring::Entry, SecretError> {
+    keyring::Entry::new(SERVICE, name).map_err(|e| SecretError::Keyring(e.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_plain_values_pass_through() {
+        assert!(!is_reference("https://hooks.slack.com/x"));
+        assert_eq!(resolve("C2y6yDjf5").unwrap(), "C2y6yDjf5");
+        assert!(is_reference("keyring:prod-key"));
+        assert!(matches!(
+            resolve("keyring: "),
+            Err(SecretError::EmptyName(_))
+        ));
+    }
+}
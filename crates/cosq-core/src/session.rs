@@ -0,0 +1,370 @@
+//! Recorded sessions for `cosq record` and `cosq replay`
+//!
+//! A session is a list of cosq commands as they were run: the arguments, the
+//! answers given to prompts (pickers and stored query parameters), the query
+//! results each command received from Cosmos DB and what it printed. Replay
+//! runs the same commands again with the prompts answered and the results
+//! served from the session instead of an account, so a demo, bug report or
+//! documentation example plays back identically anywhere.
+
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use thiserror::Error;
+
+use crate::redact::Redactor;
+use crate::result_cache::CachedResult;
+
+/// Session file format version written by this build
+pub const SESSION_VERSION: u32 = 1;
+
+#[derive(Debug, Error)]
+pub enum SessionError {
+    #[error("failed to read or write session: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("invalid session file: {0}")]
+    Parse(#[from] serde_json::Error),
+
+    #[error(
+        "session format version {0} is not supported (this cosq reads version {SESSION_VERSION})"
+    )]
+    UnsupportedVersion(u32),
+
+    #[error("unterminated quote in command line")]
+    UnterminatedQuote,
+}
+
+/// A recorded session
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Session {
+    pub version: u32,
+    /// Version of the cosq that recorded it
+    pub cosq_version: String,
+    /// RFC 3339 timestamp of when recording started
+    pub recorded_at: String,
+    /// Results were redacted with the config's `redact:` patterns; the
+    /// printed output is then not kept (replay prints it from the results)
+    #[serde(default)]
+    pub redacted: bool,
+    pub steps: Vec<Step>,
+}
+
+/// One command of a session
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Step {
+    /// Arguments after `cosq`
+    pub args: Vec<String>,
+    /// Prompts answered while the command ran, in order
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub answers: Vec<Answer>,
+    /// Query results the command received, by result cache key, in the
+    /// order they were received
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub results: Vec<RecordedResult>,
+    /// What the command printed to stdout
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub output: Option<String>,
+    pub exit_code: i32,
+}
+
+/// A prompt and the value chosen or entered
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Answer {
+    pub prompt: String,
+    pub value: Value,
+}
+
+/// Results of one query, keyed like the result cache
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RecordedResult {
+    pub key: String,
+    pub result: CachedResult,
+}
+
+/// What a command being recorded reports to the recorder, one JSON object
+/// per line of the events file
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "event", rename_all = "kebab-case")]
+pub enum Event {
+    Answer(Answer),
+    Result(RecordedResult),
+}
+
+impl Session {
+    /// An empty session, recorded now.
+    pub fn new(cosq_version: &str, redacted: bool) -> Self {
+        Session {
+            version: SESSION_VERSION,
+            cosq_version: cosq_version.to_string(),
+            recorded_at: chrono::Utc::now().to_rfc3339(),
+            redacted,
+            steps: Vec::new(),
+        }
+    }
+
+    pub fn read(path: &Path) -> Result<Self, SessionError> {
+        let session: Session = serde_json::from_str(&std::fs::read_to_string(path)?)?;
+        if session.version > SESSION_VERSION {
+            return Err(SessionError::UnsupportedVersion(session.version));
+        }
+        Ok(session)
+    }
+
+    pub fn write(&self, path: &Path) -> Result<(), SessionError> {
+        if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(path, serde_json::to_string_pretty(self)? + "\n")?;
+        Ok(())
+    }
+}
+
+impl Step {
+    /// A step for `args` from the events its command reported.
+    pub fn from_events(args: Vec<String>, events: &str, exit_code: i32) -> Self {
+        let mut step = Step {
+            args,
+            exit_code,
+            ..Step::default()
+        };
+        // A line cut short by an interrupted command is skipped
+        for event in events
+            .lines()
+            .filter_map(|line| serde_json::from_str(line).ok())
+        {
+            match event {
+                Event::Answer(answer) => step.answers.push(answer),
+                Event::Result(result) => step.results.push(result),
+            }
+        }
+        step
+    }
+
+    /// Mask matching fields of every recorded result.
+    pub fn redact(&mut self, redactor: &Redactor) {
+        for recorded in &mut self.results {
+            redactor.redact_all(&mut recorded.result.documents);
+            for documents in recorded.result.steps.values_mut() {
+                redactor.redact_all(documents);
+            }
+        }
+    }
+
+    /// The answer recorded for `prompt`.
+    pub fn answer(&self, prompt: &str) -> Option<&Value> {
+        self.answers
+            .iter()
+            .find(|a| a.prompt == prompt)
+            .map(|a| &a.value)
+    }
+}
+
+/// Serves a step's recorded results during replay: by result cache key, or
+/// else the next unused result in recorded order (so a replay against a
+/// differently named account still gets them).
+#[derive(Debug, Default)]
+pub struct ResultQueue {
+    results: BTreeMap<usize, RecordedResult>,
+}
+
+impl ResultQueue {
+    pub fn new(results: Vec<RecordedResult>) -> Self {
+        ResultQueue {
+            results: results.into_iter().enumerate().collect(),
+        }
+    }
+
+    pub fn take(&mut self, key: &str) -> Option<CachedResult> {
+        let index = self
+            .results
+            .iter()
+            .find(|(_, r)| r.key == key)
+            .map(|(i, _)| *i)
+            .or_else(|| self.results.keys().next().copied())?;
+        self.results.remove(&index).map(|r| r.result)
+    }
+}
+
+/// Split a command line into arguments like a POSIX shell would: whitespace
+/// separates, single quotes keep everything literal, double quotes allow
+/// `\"` and `\\`, and a backslash outside quotes escapes the next character.
+pub fn split_command_line(line: &str) -> Result<Vec<String>, SessionError> {
+    let mut args = Vec::new();
+    let mut current = String::new();
+    let mut in_arg = false;
+    let mut chars = line.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            c if c.is_whitespace() => {
+                if in_arg {
+                    args.push(std::mem::take(&mut current));
+                    in_arg = false;
+                }
+            }
+            '\'' => {
+                in_arg = true;
+                loop {
+                    match chars.next() {
+                        Some('\'') => break,
+                        Some(c) => current.push(c),
+                        None => return Err(SessionError::UnterminatedQuote),
+                    }
+                }
+            }
+            '"' => {
+                in_arg = true;
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some('\\') => match chars.next() {
+                            Some(c @ ('"' | '\\')) => current.push(c),
+                            Some(c) => {
+                                current.push('\\');
+                                current.push(c);
+                            }
+                            None => return Err(SessionError::UnterminatedQuote),
+                        },
+                        Some(c) => current.push(c),
+                        None => return Err(SessionError::UnterminatedQuote),
+                    }
+                }
+            }
+            '\\' => {
+                in_arg = true;
+                if let Some(c) = chars.next() {
+                    current.push(c);
+                }
+            }
+            c => {
+                in_arg = true;
+                current.push(c);
+            }
+        }
+    }
+    if in_arg {
+        args.push(current);
+    }
+    Ok(args)
+}
+
+/// Quote an argument for display, so a shown command line can be pasted.
+pub fn quote_arg(arg: &str) -> String {
+    let plain = !arg.is_empty()
+        && arg
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "-_./:=@,+%".contains(c));
+    if plain {
+        arg.to_string()
+    } else {
+        format!("'{}'", arg.replace('\'', r"'\''"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn result(key: &str, documents: Vec<Value>) -> RecordedResult {
+        RecordedResult {
+            key: key.to_string(),
+            result: CachedResult {
+                documents,
+                ..CachedResult::default()
+            },
+        }
+    }
+
+    #[test]
+    fn test_split_command_line() {
+        assert_eq!(
+            split_command_line(r#"query "SELECT * FROM c WHERE c.name = 'x'" -o table"#).unwrap(),
+            vec!["query", "SELECT * FROM c WHERE c.name = 'x'", "-o", "table"]
+        );
+        assert_eq!(
+            split_command_line(r#"run 'a b' c\ d "q\"uote" ''"#).unwrap(),
+            vec!["run", "a b", "c d", "q\"uote", ""]
+        );
+        assert!(split_command_line("  ").unwrap().is_empty());
+        assert!(split_command_line("query 'open").is_err());
+    }
+
+    #[test]
+    fn test_quote_arg() {
+        assert_eq!(quote_arg("--db"), "--db");
+        assert_eq!(quote_arg("SELECT * FROM c"), "'SELECT * FROM c'");
+        assert_eq!(quote_arg("it's"), r"'it'\''s'");
+        let line = ["query", "SELECT 'x'", ""].map(quote_arg).join(" ");
+        assert_eq!(
+            split_command_line(&line).unwrap(),
+            vec!["query", "SELECT 'x'", ""]
+        );
+    }
+
+    #[test]
+    fn test_step_from_events_and_redact() {
+        let events = [
+            serde_json::to_string(&Event::Answer(Answer {
+                prompt: "Select a database:".to_string(),
+                value: json!("shop"),
+            }))
+            .unwrap(),
+            serde_json::to_string(&Event::Result(result(
+                "k1",
+                vec![json!({"id": "1", "email": "a@b.c"})],
+            )))
+            .unwrap(),
+            "{\"event\": \"resu".to_string(),
+        ]
+        .join("\n");
+        let mut step = Step::from_events(vec!["query".to_string()], &events, 0);
+        assert_eq!(step.answer("Select a database:"), Some(&json!("shop")));
+        assert_eq!(step.answer("Select a container:"), None);
+        assert_eq!(step.results.len(), 1);
+
+        step.redact(&Redactor::new(["email"]));
+        assert_eq!(
+            step.results[0].result.documents,
+            vec![json!({"id": "1", "email": "***"})]
+        );
+    }
+
+    #[test]
+    fn test_result_queue() {
+        let mut queue = ResultQueue::new(vec![
+            result("a", vec![json!(1)]),
+            result("b", vec![json!(2)]),
+            result("c", vec![json!(3)]),
+        ]);
+        assert_eq!(queue.take("b").unwrap().documents, vec![json!(2)]);
+        // Unknown key: the next unused result
+        assert_eq!(queue.take("other").unwrap().documents, vec![json!(1)]);
+        assert_eq!(queue.take("c").unwrap().documents, vec![json!(3)]);
+        assert!(queue.take("a").is_none());
+    }
+
+    #[test]
+    fn test_session_roundtrip() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("demo/session.json");
+        let mut session = Session::new("1.2.3", false);
+        session.steps.push(Step {
+            args: vec!["query".to_string(), "SELECT * FROM c".to_string()],
+            results: vec![result("k", vec![json!({"id": "1"})])],
+            output: Some("[]\n".to_string()),
+            ..Step::default()
+        });
+        session.write(&path).unwrap();
+        assert_eq!(Session::read(&path).unwrap(), session);
+
+        session.version = SESSION_VERSION + 1;
+        session.write(&path).unwrap();
+        assert!(matches!(
+            Session::read(&path),
+            Err(SessionError::UnsupportedVersion(_))
+        ));
+    }
+}
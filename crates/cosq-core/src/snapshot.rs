@@ -0,0 +1,206 @@
+//! Container snapshots for ad-hoc backups
+//!
+//! `cosq snapshot create` writes every document of a container to one JSON
+//! file together with a manifest (source account/database/container, partition
+//! key, creation time, document count and a SHA-256 of the documents).
+//! `cosq snapshot restore` checks the manifest before upserting the documents
+//! into a container, so a truncated or hand-edited file is refused.
+
+use std::fmt::Write as _;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use sha2::{Digest, Sha256};
+use thiserror::Error;
+
+/// Snapshot file format version written by this build
+pub const SNAPSHOT_VERSION: u32 = 1;
+
+#[derive(Debug, Error)]
+pub enum SnapshotError {
+    #[error("failed to read or write snapshot: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("invalid snapshot file: {0}")]
+    Parse(#[from] serde_json::Error),
+
+    #[error(
+        "snapshot format version {0} is not supported (this cosq reads version {SNAPSHOT_VERSION})"
+    )]
+    UnsupportedVersion(u32),
+
+    #[error("snapshot manifest lists {expected} documents but the file has {found}")]
+    CountMismatch { expected: usize, found: usize },
+
+    #[error(
+        "snapshot documents do not match the manifest hash (the file was changed or truncated)"
+    )]
+    HashMismatch,
+}
+
+/// Where a snapshot came from and what it holds
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SnapshotManifest {
+    pub version: u32,
+    pub account: String,
+    pub database: String,
+    pub container: String,
+    /// Partition key paths of the source container (e.g. `/customerId`)
+    #[serde(default)]
+    pub partition_key_paths: Vec<String>,
+    /// RFC 3339 timestamp of when the snapshot was taken
+    pub created_at: String,
+    pub count: usize,
+    /// Hex SHA-256 of the documents (see [`hash_documents`])
+    pub sha256: String,
+}
+
+/// A snapshot file: manifest plus the documents as read from Cosmos DB
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Snapshot {
+    pub manifest: SnapshotManifest,
+    pub documents: Vec<Value>,
+}
+
+impl Snapshot {
+    /// Snapshot `documents` of `account/database/container`, taken now.
+    pub fn new(
+        account: &str,
+        database: &str,
+        container: &str,
+        partition_key_paths: Vec<String>,
+        documents: Vec<Value>,
+    ) -> Self {
+        Snapshot {
+            manifest: SnapshotManifest {
+                version: SNAPSHOT_VERSION,
+                account: account.to_string(),
+                database: database.to_string(),
+                container: container.to_string(),
+                partition_key_paths,
+                created_at: chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Secs, true),
+                count: documents.len(),
+                sha256: hash_documents(&documents),
+            },
+            documents,
+        }
+    }
+
+    /// Check the format version, document count and hash against the manifest.
+    pub fn verify(&self) -> Result<(), SnapshotError> {
+        if self.manifest.version != SNAPSHOT_VERSION {
+            return Err(SnapshotError::UnsupportedVersion(self.manifest.version));
+        }
+        if self.manifest.count != self.documents.len() {
+            return Err(SnapshotError::CountMismatch {
+                expected: self.manifest.count,
+                found: self.documents.len(),
+            });
+        }
+        if self.manifest.sha256 != hash_documents(&self.documents) {
+            return Err(SnapshotError::HashMismatch);
+        }
+        Ok(())
+    }
+
+    /// Read and verify a snapshot file.
+    pub fn read(path: &Path) -> Result<Self, SnapshotError> {
+        let snapshot: Snapshot = serde_json::from_str(&std::fs::read_to_string(path)?)?;
+        snapshot.verify()?;
+        Ok(snapshot)
+    }
+
+    /// Write the snapshot to `path` (one document per line, for readable diffs).
+    pub fn write(&self, path: &Path) -> Result<(), SnapshotError> {
+        if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            std::fs::create_dir_all(parent)?;
+        }
+        let mut out = format!(
+            "{{\"manifest\": {},\n\"documents\": [",
+            serde_json::to_string_pretty(&self.manifest)?
+        );
+        for (i, document) in self.documents.iter().enumerate() {
+            out.push_str(if i == 0 { "\n" } else { ",\n" });
+            out.push_str(&serde_json::to_string(document)?);
+        }
+        out.push_str("\n]}\n");
+        std::fs::write(path, out)?;
+        Ok(())
+    }
+}
+
+/// Hex SHA-256 over the documents' compact JSON, one per line.
+pub fn hash_documents(documents: &[Value]) -> String {
+    let mut hasher = Sha256::new();
+    for document in documents {
+        hasher.update(document.to_string().as_bytes());
+        hasher.update(b"\n");
+    }
+    hasher
+        .finalize()
+        .iter()
+        .fold(String::with_capacity(64), |mut hex, byte| {
+            let _ = write!(hex, "{byte:02x}");
+            hex
+        })
+}
+
+/// Default file name for a snapshot of `container`: `<container>-<UTC time>.snapshot.json`.
+pub fn default_file_name(container: &str) -> String {
+    format!(
+        "{container}-{}.snapshot.json",
+        chrono::Utc::now().format("%Y%m%dT%H%M%SZ")
+    )
+}
+
+/// The value at a partition key path (`/customer/id`) of a document. Documents
+/// without it belong to the "undefined" partition, addressed as `{}`.
+pub fn partition_key_value(document: &Value, path: &str) -> Value {
+    path.trim_start_matches('/')
+        .split('/')
+        .try_fold(document, |value, key| value.get(key))
+        .cloned()
+        .unwrap_or_else(|| Value::Object(Map::new()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_round_trip_and_verify() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("orders.snapshot.json");
+        let snapshot = Snapshot::new(
+            "acct",
+            "shop",
+            "orders",
+            vec!["/customerId".into()],
+            vec![json!({"id": "1", "customerId": "c1"}), json!({"id": "2"})],
+        );
+        snapshot.write(&path).unwrap();
+        assert_eq!(Snapshot::read(&path).unwrap(), snapshot);
+
+        let mut changed = snapshot.clone();
+        changed.documents[1] = json!({"id": "2", "extra": true});
+        assert!(matches!(changed.verify(), Err(SnapshotError::HashMismatch)));
+        changed.documents.pop();
+        assert!(matches!(
+            changed.verify(),
+            Err(SnapshotError::CountMismatch {
+                expected: 2,
+                found: 1
+            })
+        ));
+    }
+
+    #[test]
+    fn test_partition_key_value() {
+        let doc = json!({"id": "1", "customer": {"id": "c1"}, "tenant": 7});
+        assert_eq!(partition_key_value(&doc, "/tenant"), json!(7));
+        assert_eq!(partition_key_value(&doc, "/customer/id"), json!("c1"));
+        assert_eq!(partition_key_value(&doc, "/missing"), json!({}));
+    }
+}
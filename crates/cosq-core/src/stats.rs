@@ -0,0 +1,133 @@
+//! Cumulative usage statistics (runs and request units per query)
+//!
+//! Every `cosq query` / `cosq run` adds its request charge to
+//! `~/.cosq/stats.json`; `cosq stats` reports the totals (with an estimated
+//! cost when `pricing:` is configured).
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+/// Name under which ad-hoc `cosq query` runs are recorded
+pub const AD_HOC: &str = "(ad-hoc)";
+
+#[derive(Debug, Error)]
+pub enum StatsError {
+    #[error("could not determine home directory")]
+    NoHomeDir,
+
+    #[error("failed to read or write stats file: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("invalid stats file: {0}")]
+    Parse(#[from] serde_json::Error),
+}
+
+/// Totals for one stored query (or all ad-hoc queries)
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct QueryStats {
+    pub runs: u64,
+    pub request_units: f64,
+    /// RFC 3339 timestamp of the most recent run
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_run: Option<String>,
+}
+
+/// Usage totals keyed by query name
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct UsageStats {
+    #[serde(default)]
+    pub queries: BTreeMap<String, QueryStats>,
+}
+
+impl UsageStats {
+    /// Return the stats file path: `~/.cosq/stats.json`.
+    pub fn path() -> Result<PathBuf, StatsError> {
+        dirs::home_dir()
+            .map(|d| d.join(".cosq").join("stats.json"))
+            .ok_or(StatsError::NoHomeDir)
+    }
+
+    /// Load stats from the default path (empty if the file does not exist).
+    pub fn load() -> Result<Self, StatsError> {
+        Self::load_from(&Self::path()?)
+    }
+
+    pub fn load_from(path: &Path) -> Result<Self, StatsError> {
+        match std::fs::read_to_string(path) {
+            Ok(contents) => Ok(serde_json::from_str(&contents)?),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    pub fn save_to(&self, path: &Path) -> Result<(), StatsError> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    /// Add one run of `name` that consumed `request_units`.
+    pub fn record(&mut self, name: &str, request_units: f64, timestamp: Option<String>) {
+        let entry = self.queries.entry(name.to_string()).or_default();
+        entry.runs += 1;
+        entry.request_units += request_units;
+        if timestamp.is_some() {
+            entry.last_run = timestamp;
+        }
+    }
+
+    /// Total (runs, request units) across all queries.
+    pub fn totals(&self) -> (u64, f64) {
+        self.queries.values().fold((0, 0.0), |(runs, ru), q| {
+            (runs + q.runs, ru + q.request_units)
+        })
+    }
+
+    /// Load, record one run, and save back to the default path.
+    pub fn record_run(
+        name: &str,
+        request_units: f64,
+        timestamp: Option<String>,
+    ) -> Result<(), StatsError> {
+        let path = Self::path()?;
+        let mut stats = Self::load_from(&path)?;
+        stats.record(name, request_units, timestamp);
+        stats.save_to(&path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_record_and_totals() {
+        let mut stats = UsageStats::default();
+        stats.record("daily", 10.5, Some("2026-01-01T00:00:00Z".into()));
+        stats.record("daily", 4.5, None);
+        stats.record(AD_HOC, 1.0, None);
+
+        let daily = &stats.queries["daily"];
+        assert_eq!(daily.runs, 2);
+        assert_eq!(daily.request_units, 15.0);
+        assert_eq!(daily.last_run.as_deref(), Some("2026-01-01T00:00:00Z"));
+        assert_eq!(stats.totals(), (3, 16.0));
+    }
+
+    #[test]
+    fn test_save_and_load() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("nested").join("stats.json");
+        assert_eq!(UsageStats::load_from(&path).unwrap(), UsageStats::default());
+
+        let mut stats = UsageStats::default();
+        stats.record("q", 2.0, None);
+        stats.save_to(&path).unwrap();
+        assert_eq!(UsageStats::load_from(&path).unwrap(), stats);
+    }
+}
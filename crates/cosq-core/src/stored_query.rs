@@ -46,6 +46,30 @@
 //! -- step: lines
 //! SELECT * FROM c WHERE c.orderId = @orderId
 //! ```
+//!
+//! Includes: `include:` pulls in other `.cosq` (or plain `.sql`) files when a query
+//! is loaded. A single-step include becomes a SQL fragment, inserted wherever
+//! `{{> name}}` appears; a multi-step include contributes its steps. Params declared
+//! by included files are merged in (the including query's definitions win).
+//! ```text
+//! ---
+//! description: Active orders for a tenant
+//! include: common/base-filters.cosq
+//! ---
+//! SELECT * FROM c WHERE {{> base-filters}} AND c.status = "active"
+//! ```
+//!
+//! Federated queries: `targets:` runs the same SQL against several accounts
+//! concurrently and merges the results, tagging each document with `_source`.
+//! Each target is `<profile-or-account>[/<database>[/<container>]]`.
+//! ```text
+//! ---
+//! description: Failed jobs across regions
+//! container: jobs
+//! targets: [prod-eu, prod-us, prod-apac/jobsdb]
+//! ---
+//! SELECT c.id, c.error FROM c WHERE c.status = "failed"
+//! ```
 
 use std::collections::BTreeMap;
 use std::path::{Path, PathBuf};
@@ -53,6 +77,8 @@ use std::path::{Path, PathBuf};
 use serde::{Deserialize, Serialize};
 use thiserror::Error;
 
+use crate::encryption::{self, EncryptionError, EncryptionKey};
+
 #[derive(Debug, Error)]
 pub enum StoredQueryError {
     #[error("invalid query file: missing front matter delimiters (---)")]
@@ -67,6 +93,9 @@ pub enum StoredQueryError {
     #[error("failed to read query file: {0}")]
     Read(#[from] std::io::Error),
 
+    #[error("invalid target '{0}': expected <profile-or-account>[/<database>[/<container>]]")]
+    InvalidTarget(String),
+
     #[error("parameter '{name}' is required")]
     MissingParam { name: String },
 
@@ -97,6 +126,12 @@ pub enum StoredQueryError {
         pattern: String,
     },
 
+    #[error("parameter '{name}': invalid `when:` expression: {reason}")]
+    InvalidWhen { name: String, reason: String },
+
+    #[error("parameter '{name}' only applies when {when}")]
+    InactiveParam { name: String, when: String },
+
     #[error("no queries directory found")]
     NoQueriesDir,
 
@@ -109,11 +144,34 @@ pub enum StoredQueryError {
     #[error("SQL has `-- step: {name}` marker but '{name}' is not defined in steps")]
     UnknownStepMarker { name: String },
 
+    #[error("`template_mode: row` renders one document at a time and needs a single-step query")]
+    RowTemplateMultiStep,
+
     #[error("step '{name}' returned no results, cannot resolve @{name}.{field}")]
     EmptyStepResult { name: String, field: String },
 
     #[error("field '{field}' not found in step '{name}' result")]
     StepFieldNotFound { name: String, field: String },
+
+    #[error("alias '{alias}' is ambiguous — it matches: {candidates}")]
+    AmbiguousAlias { alias: String, candidates: String },
+
+    #[error("failed to parse aliases file: {0}")]
+    InvalidAliasesFile(serde_yaml::Error),
+
+    #[error("include '{path}': {reason}")]
+    Include { path: String, reason: String },
+
+    #[error("parameter '{name}': {source}")]
+    Encryption {
+        name: String,
+        source: EncryptionError,
+    },
+
+    #[error(
+        "parameter '{name}' is marked `encrypted: true` but its default is not encrypted — replace it with the output of `cosq queries encrypt`"
+    )]
+    NotEncrypted { name: String },
 }
 
 /// A step definition for multi-step queries
@@ -126,6 +184,19 @@ pub struct StepDef {
     pub container: String,
 }
 
+/// One step in the dependency graph of a multi-step query
+#[derive(Debug, Clone, PartialEq)]
+pub struct StepNode {
+    pub name: String,
+    pub container: String,
+    /// Execution layer (0-based); steps in the same layer run in parallel
+    pub layer: usize,
+    /// Fields read from earlier steps, by step name (`@customer.id` → `customer: [id]`)
+    pub inputs: BTreeMap<String, Vec<String>>,
+    /// Query parameters the step's SQL uses
+    pub params: Vec<String>,
+}
+
 /// Parameter type for stored query parameters
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 #[serde(rename_all = "lowercase")]
@@ -133,6 +204,12 @@ pub enum ParamType {
     String,
     Number,
     Bool,
+    /// A list of values, e.g. several of the `choices` (`ARRAY_CONTAINS(@statuses, c.status)`)
+    Array,
+    /// A string holding a UUID (e.g. a document id)
+    Uuid,
+    /// Any JSON value, typically an object, passed through as is
+    Object,
 }
 
 impl std::fmt::Display for ParamType {
@@ -141,6 +218,9 @@ impl std::fmt::Display for ParamType {
             ParamType::String => write!(f, "string"),
             ParamType::Number => write!(f, "number"),
             ParamType::Bool => write!(f, "bool"),
+            ParamType::Array => write!(f, "array"),
+            ParamType::Uuid => write!(f, "uuid"),
+            ParamType::Object => write!(f, "object"),
         }
     }
 }
@@ -163,6 +243,11 @@ pub struct ParamDef {
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub default: Option<serde_json::Value>,
 
+    /// Shell command whose output is the default (e.g. `git rev-parse --abbrev-ref HEAD`),
+    /// run at execution time after confirmation; `default` is the fallback
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub default_cmd: Option<String>,
+
     /// Allowed values (shown as fuzzy-select in interactive mode)
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub choices: Option<Vec<serde_json::Value>>,
@@ -182,6 +267,15 @@ pub struct ParamDef {
     /// Regex pattern (for string type)
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub pattern: Option<String>,
+
+    /// The default is an `enc:v1:` value from `cosq queries encrypt`, decrypted at run time
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub encrypted: bool,
+
+    /// Only use (and prompt for) this parameter when the expression holds for
+    /// earlier parameters, e.g. `scope == "regional"`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub when: Option<String>,
 }
 
 impl ParamDef {
@@ -222,6 +316,28 @@ impl ParamDef {
                     });
                 }
             }
+            ParamType::Array => {
+                if !value.is_array() {
+                    return Err(StoredQueryError::InvalidParamType {
+                        name: self.name.clone(),
+                        expected: "array".into(),
+                        value: value.to_string(),
+                    });
+                }
+            }
+            ParamType::Uuid => {
+                let valid = value
+                    .as_str()
+                    .is_some_and(|s| uuid::Uuid::try_parse(s).is_ok());
+                if !valid {
+                    return Err(StoredQueryError::InvalidParamType {
+                        name: self.name.clone(),
+                        expected: "uuid".into(),
+                        value: value.to_string(),
+                    });
+                }
+            }
+            ParamType::Object => {}
         }
 
         // Range check for numbers
@@ -246,9 +362,13 @@ impl ParamDef {
             }
         }
 
-        // Choice validation
+        // Choice validation (every element, for arrays)
         if let Some(ref choices) = self.choices {
-            if !choices.contains(value) {
+            let values = match value {
+                serde_json::Value::Array(items) => items.as_slice(),
+                other => std::slice::from_ref(other),
+            };
+            if let Some(value) = values.iter().find(|v| !choices.contains(v)) {
                 let choices_str = choices
                     .iter()
                     .map(|c| match c {
@@ -288,12 +408,126 @@ impl ParamDef {
     }
 }
 
-/// YAML front matter metadata for a stored query
+/// Commands run around a stored query execution
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct QueryHooks {
+    /// Command that receives the query output on stdin after a successful run
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub post_run: Option<PostRunHook>,
+}
+
+/// A shell command run after the query completes
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PostRunHook {
+    /// Shell command line (run via `sh -c`, or `cmd /C` on Windows)
+    pub command: String,
+
+    /// What to pipe to the command's stdin
+    #[serde(default)]
+    pub input: HookInput,
+}
+
+/// Data piped to a post-run hook
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HookInput {
+    /// The rendered output, exactly as printed to stdout
+    #[default]
+    Output,
+    /// The raw JSON results
+    Json,
+}
+
+/// Prometheus exposition settings for `--output prom`
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PrometheusConfig {
+    /// Prefix for metric names (default: `cosq_`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub prefix: Option<String>,
+
+    /// Document fields used as labels (default: all string fields)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub labels: Option<Vec<String>>,
+
+    /// Fields exported as metrics (default: all numeric fields)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub metrics: Vec<PrometheusMetric>,
+}
+
+/// A document field exported as a Prometheus metric
 #[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PrometheusMetric {
+    /// Document field holding the value (dotted paths allowed, e.g. `stats.total`)
+    pub field: String,
+
+    /// Metric name (default: the field name); the prefix is prepended
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+
+    /// `# HELP` text
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub help: Option<String>,
+
+    /// Metric type
+    #[serde(default, rename = "type")]
+    pub kind: MetricKind,
+}
+
+/// Prometheus metric type
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MetricKind {
+    #[default]
+    Gauge,
+    Counter,
+}
+
+/// How a query's template is applied to its results
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TemplateMode {
+    /// Rendered once with all results (`documents`, or one list per step)
+    #[default]
+    All,
+    /// Rendered once per document (as `doc`), outputs concatenated
+    Row,
+}
+
+/// YAML front matter metadata for a stored query
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct StoredQueryMetadata {
     /// Brief description of what the query does
     pub description: String,
 
+    /// Alternative short names that resolve to this query (e.g. `ro` for `recent-orders`)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub aliases: Vec<String>,
+
+    /// Free-form labels for finding the query with `cosq queries search`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+
+    /// Cosmos DB account to run against (overrides the configured default account)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub account: Option<String>,
+
+    /// Named config profile to run against (takes precedence over `account`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub profile: Option<String>,
+
+    /// Run against several profiles/accounts concurrently and merge the results
+    /// (`<profile-or-account>[/<database>[/<container>]]` each)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub targets: Vec<String>,
+
+    /// Other .cosq or .sql files to include (relative to this file, then the queries dirs)
+    #[serde(
+        default,
+        skip_serializing_if = "Vec::is_empty",
+        deserialize_with = "one_or_many"
+    )]
+    pub include: Vec<String>,
+
     /// Target database (overrides config default)
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub database: Option<String>,
@@ -318,6 +552,40 @@ pub struct StoredQueryMetadata {
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub template_file: Option<String>,
 
+    /// `row` renders the template once per document with `doc` in context
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub template_mode: Option<TemplateMode>,
+
+    /// Default output format when no `-o` flag is given (json, json-compact, table, csv, template)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub output: Option<String>,
+
+    /// Chart to render instead of the output format when no `-o`/`--template`
+    /// is given (`<bar|line>:<label field>:<value field>`; see [`crate::chart`])
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub chart: Option<String>,
+
+    /// Metric names and labels for `--output prom`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub prometheus: Option<PrometheusConfig>,
+
+    /// Commands run after the query (e.g. piping output to `pbcopy`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hooks: Option<QueryHooks>,
+
+    /// Cron schedule for `cosq schedule export` (e.g. `0 7 * * 1-5` or `@daily`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub schedule: Option<String>,
+
+    /// Webhook URL or configured webhook name to post results to after a run
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub notify: Option<String>,
+
+    /// Data quality assertions for `cosq run --check` (e.g. `row_count > 0`,
+    /// `all(c.total >= 0)`; see [`crate::checks`])
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub checks: Vec<String>,
+
     /// Marks this query as AI-generated
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub generated_by: Option<String>,
@@ -325,6 +593,58 @@ pub struct StoredQueryMetadata {
     /// The original natural language prompt (for AI-generated queries)
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub generated_from: Option<String>,
+
+    /// Example results bundled by `cosq queries share --results` (redacted)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub sample_results: Vec<serde_json::Value>,
+}
+
+/// One target of a federated query: a profile or account name, optionally
+/// with a database and container
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QueryTarget {
+    /// Profile name, or account name when no such profile exists
+    pub name: String,
+    pub database: Option<String>,
+    pub container: Option<String>,
+}
+
+impl QueryTarget {
+    /// Parse `<profile-or-account>[/<database>[/<container>]]`.
+    pub fn parse(s: &str) -> Result<Self, StoredQueryError> {
+        let invalid = || StoredQueryError::InvalidTarget(s.to_string());
+        let mut parts = s.trim().split('/');
+        let name = parts.next().filter(|n| !n.is_empty()).ok_or_else(invalid)?;
+        let mut next = || -> Result<Option<String>, StoredQueryError> {
+            match parts.next() {
+                Some("") => Err(invalid()),
+                other => Ok(other.map(str::to_string)),
+            }
+        };
+        let database = next()?;
+        let container = next()?;
+        if parts.next().is_some() {
+            return Err(invalid());
+        }
+        Ok(Self {
+            name: name.to_string(),
+            database,
+            container,
+        })
+    }
+}
+
+impl std::fmt::Display for QueryTarget {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.name)?;
+        if let Some(ref db) = self.database {
+            write!(f, "/{db}")?;
+        }
+        if let Some(ref ctr) = self.container {
+            write!(f, "/{ctr}")?;
+        }
+        Ok(())
+    }
 }
 
 /// A fully parsed stored query
@@ -353,6 +673,10 @@ impl StoredQuery {
         }
 
         if let Some(ref steps) = metadata.steps {
+            if metadata.template_mode == Some(TemplateMode::Row) {
+                return Err(StoredQueryError::RowTemplateMultiStep);
+            }
+
             // Multi-step: parse `-- step: <name>` markers
             let step_queries = parse_step_sql(&raw_sql)?;
             let step_names: std::collections::HashSet<&str> =
@@ -392,7 +716,7 @@ impl StoredQuery {
         }
     }
 
-    /// Load a stored query from a file path
+    /// Load a stored query from a file path, resolving any `include:` entries
     pub fn load(path: &Path) -> Result<Self, StoredQueryError> {
         let name = path
             .file_stem()
@@ -400,7 +724,102 @@ impl StoredQuery {
             .unwrap_or("unknown")
             .to_string();
         let contents = std::fs::read_to_string(path)?;
-        Self::parse(&name, &contents)
+        let mut query = Self::parse(&name, &contents)?;
+        let mut stack = vec![path.canonicalize().unwrap_or_else(|_| path.to_path_buf())];
+        query.resolve_includes(path.parent().unwrap_or(Path::new(".")), &mut stack)?;
+        Ok(query)
+    }
+
+    /// Expand `include:` entries: merge included params and steps, and replace
+    /// `{{> name}}` markers with SQL fragments. `stack` holds the files currently
+    /// being resolved, to detect circular includes.
+    fn resolve_includes(
+        &mut self,
+        base_dir: &Path,
+        stack: &mut Vec<PathBuf>,
+    ) -> Result<(), StoredQueryError> {
+        if self.metadata.include.is_empty() {
+            return Ok(());
+        }
+
+        let include_error = |path: &str, reason: String| StoredQueryError::Include {
+            path: path.to_string(),
+            reason,
+        };
+
+        let mut fragments: BTreeMap<String, String> = BTreeMap::new();
+        let mut included_steps: Vec<StepDef> = Vec::new();
+
+        for include in self.metadata.include.clone() {
+            let path = find_include_file(base_dir, &include)
+                .ok_or_else(|| include_error(&include, "file not found".to_string()))?;
+            let canonical = path.canonicalize().unwrap_or_else(|_| path.clone());
+            if stack.contains(&canonical) {
+                return Err(include_error(&include, "circular include".to_string()));
+            }
+
+            let stem = path
+                .file_stem()
+                .and_then(|s| s.to_str())
+                .unwrap_or("unknown")
+                .to_string();
+            let contents = std::fs::read_to_string(&path)
+                .map_err(|e| include_error(&include, e.to_string()))?;
+
+            if path.extension().is_some_and(|ext| ext == "sql") {
+                let sql = contents.trim().to_string();
+                fragments.insert(stem, sql.clone());
+                fragments.insert(include, sql);
+                continue;
+            }
+
+            let mut included = Self::parse(&stem, &contents)
+                .map_err(|e| include_error(&include, e.to_string()))?;
+            stack.push(canonical);
+            included.resolve_includes(path.parent().unwrap_or(Path::new(".")), stack)?;
+            stack.pop();
+
+            for param in included.metadata.params {
+                if !self.metadata.params.iter().any(|p| p.name == param.name) {
+                    self.metadata.params.push(param);
+                }
+            }
+
+            match included.metadata.steps {
+                Some(steps) => {
+                    if self.metadata.steps.is_none() {
+                        return Err(include_error(
+                            &include,
+                            "it defines steps, so the including query must also use `steps:`"
+                                .to_string(),
+                        ));
+                    }
+                    for step in steps {
+                        if let Some(sql) = included.step_queries.remove(&step.name) {
+                            self.step_queries.entry(step.name.clone()).or_insert(sql);
+                        }
+                        included_steps.push(step);
+                    }
+                }
+                None => {
+                    fragments.insert(stem, included.sql.clone());
+                    fragments.insert(include, included.sql);
+                }
+            }
+        }
+
+        // Included steps run first; a step defined locally with the same name wins
+        if let Some(ref mut steps) = self.metadata.steps {
+            included_steps.retain(|inc| !steps.iter().any(|s| s.name == inc.name));
+            included_steps.append(steps);
+            *steps = included_steps;
+        }
+
+        self.sql = expand_fragments(&self.sql, &fragments)?;
+        for sql in self.step_queries.values_mut() {
+            *sql = expand_fragments(sql, &fragments)?;
+        }
+        Ok(())
     }
 
     /// Whether this is a multi-step query
@@ -408,6 +827,23 @@ impl StoredQuery {
         self.metadata.steps.is_some()
     }
 
+    /// Strip everything tied to the author's environment so the query can be
+    /// shared: account/profile/targets, hooks (which run local commands),
+    /// notifications and schedules. Includes must already be resolved (see
+    /// [`StoredQuery::load`]); `include:` is dropped since the SQL and params
+    /// are now inline.
+    pub fn into_snippet(mut self) -> Self {
+        let metadata = &mut self.metadata;
+        metadata.account = None;
+        metadata.profile = None;
+        metadata.targets.clear();
+        metadata.include.clear();
+        metadata.hooks = None;
+        metadata.notify = None;
+        metadata.schedule = None;
+        self
+    }
+
     /// Serialize this stored query back to .cosq file format
     pub fn to_file_contents(&self) -> Result<String, serde_yaml::Error> {
         let yaml = serde_yaml::to_string(&self.metadata)?;
@@ -505,6 +941,82 @@ impl StoredQuery {
         Ok(layers)
     }
 
+    /// The step dependency graph of a multi-step query, in execution order:
+    /// each step's layer, the fields it reads from earlier steps and the
+    /// parameters it uses. Empty for single-step queries.
+    pub fn step_graph(&self) -> Result<Vec<StepNode>, StoredQueryError> {
+        let Some(ref steps) = self.metadata.steps else {
+            return Ok(Vec::new());
+        };
+        let step_names: Vec<String> = steps.iter().map(|s| s.name.clone()).collect();
+        let param_re = regex::Regex::new(r"@(\w+)(\.\w+)?").unwrap();
+
+        let mut nodes = Vec::new();
+        for (layer, names) in self.execution_order()?.into_iter().enumerate() {
+            for name in names {
+                let Some(step) = steps.iter().find(|s| s.name == name) else {
+                    continue;
+                };
+                let sql = self.step_queries.get(&name).map_or("", String::as_str);
+                let mut inputs: BTreeMap<String, Vec<String>> = BTreeMap::new();
+                for (from, field) in Self::find_step_references(sql, &step_names) {
+                    let fields = inputs.entry(from).or_default();
+                    if !fields.contains(&field) {
+                        fields.push(field);
+                    }
+                }
+                let mut params = Vec::new();
+                for cap in param_re.captures_iter(sql) {
+                    let used = &cap[1];
+                    if cap.get(2).is_none()
+                        && self.metadata.params.iter().any(|p| p.name == used)
+                        && !params.iter().any(|p| p == used)
+                    {
+                        params.push(used.to_string());
+                    }
+                }
+                nodes.push(StepNode {
+                    name,
+                    container: step.container.clone(),
+                    layer,
+                    inputs,
+                    params,
+                });
+            }
+        }
+        Ok(nodes)
+    }
+
+    /// Whether any parameter default is stored encrypted
+    pub fn has_encrypted_params(&self) -> bool {
+        self.metadata.params.iter().any(|p| p.encrypted)
+    }
+
+    /// Decrypt `encrypted: true` parameter defaults in place.
+    pub fn decrypt_params(&mut self, key: &EncryptionKey) -> Result<(), StoredQueryError> {
+        for param in self.metadata.params.iter_mut().filter(|p| p.encrypted) {
+            let Some(ref default) = param.default else {
+                continue;
+            };
+            let Some(sealed) = default
+                .as_str()
+                .filter(|_| encryption::is_encrypted(default))
+            else {
+                return Err(StoredQueryError::NotEncrypted {
+                    name: param.name.clone(),
+                });
+            };
+            let value = key
+                .decrypt(sealed)
+                .map_err(|source| StoredQueryError::Encryption {
+                    name: param.name.clone(),
+                    source,
+                })?;
+            param.default = Some(value);
+        }
+        Ok(())
+    }
+
     /// Resolve parameters from a map of CLI-provided values, filling in defaults.
     /// Returns a map of parameter name → resolved JSON value.
     pub fn resolve_params(
@@ -514,6 +1026,17 @@ impl StoredQuery {
         let mut resolved = BTreeMap::new();
 
         for param in &self.metadata.params {
+            if !self.param_applies(param, &resolved)? {
+                if provided.contains_key(&param.name) {
+                    return Err(StoredQueryError::InactiveParam {
+                        name: param.name.clone(),
+                        when: param.when.clone().unwrap_or_default(),
+                    });
+                }
+                // The SQL still references it, and Cosmos DB rejects unbound parameters
+                resolved.insert(param.name.clone(), serde_json::Value::Null);
+                continue;
+            }
             let value = if let Some(raw) = provided.get(&param.name) {
                 // Parse from string to the expected type
                 parse_param_value(&param.name, &param.param_type, raw)?
@@ -544,6 +1067,31 @@ impl StoredQuery {
         Ok(resolved)
     }
 
+    /// Whether `param` applies given the parameters resolved so far: true
+    /// without a `when:` expression, otherwise its result. The expression may
+    /// only reference parameters defined before `param`. Parameters that don't
+    /// apply are bound as `null`.
+    pub fn param_applies(
+        &self,
+        param: &ParamDef,
+        resolved: &BTreeMap<String, serde_json::Value>,
+    ) -> Result<bool, StoredQueryError> {
+        let Some(ref expr) = param.when else {
+            return Ok(true);
+        };
+        let earlier: Vec<&str> = self
+            .metadata
+            .params
+            .iter()
+            .take_while(|p| p.name != param.name)
+            .map(|p| p.name.as_str())
+            .collect();
+        eval_when(expr, resolved, &earlier).map_err(|reason| StoredQueryError::InvalidWhen {
+            name: param.name.clone(),
+            reason,
+        })
+    }
+
     /// Build the Cosmos DB parameters array from resolved parameter values.
     pub fn build_cosmos_params(
         resolved: &BTreeMap<String, serde_json::Value>,
@@ -599,7 +1147,137 @@ fn parse_param_value(
                 value: raw.to_string(),
             }),
         },
+        // A JSON array, or comma-separated strings
+        ParamType::Array => {
+            if raw.trim_start().starts_with('[') {
+                serde_json::from_str::<Vec<serde_json::Value>>(raw)
+                    .map(serde_json::Value::from)
+                    .map_err(|_| StoredQueryError::InvalidParamType {
+                        name: name.to_string(),
+                        expected: "array (JSON array or comma-separated values)".into(),
+                        value: raw.to_string(),
+                    })
+            } else {
+                Ok(raw
+                    .split(',')
+                    .map(str::trim)
+                    .filter(|item| !item.is_empty())
+                    .map(|item| serde_json::Value::String(item.to_string()))
+                    .collect())
+            }
+        }
+        // Kept as typed (ids are case-sensitive), only the format is checked
+        ParamType::Uuid => match uuid::Uuid::try_parse(raw.trim()) {
+            Ok(_) => Ok(serde_json::Value::String(raw.trim().to_string())),
+            Err(_) => Err(StoredQueryError::InvalidParamType {
+                name: name.to_string(),
+                expected: "uuid".into(),
+                value: raw.to_string(),
+            }),
+        },
+        ParamType::Object => {
+            serde_json::from_str(raw).map_err(|_| StoredQueryError::InvalidParamType {
+                name: name.to_string(),
+                expected: "JSON value".into(),
+                value: raw.to_string(),
+            })
+        }
+    }
+}
+
+/// Evaluate a `when:` expression against resolved parameters.
+///
+/// Clauses are `name == value`, `name != value` or a bare `name` (true when set
+/// and not `false`, `null` or `""`), combined with `&&` and `||` (`&&` binds
+/// tighter). Values are JSON literals; unquoted words are strings. Operators
+/// inside quoted strings are part of the value. Parameters without a value
+/// compare as `null`.
+fn eval_when(
+    expr: &str,
+    resolved: &BTreeMap<String, serde_json::Value>,
+    known: &[&str],
+) -> Result<bool, String> {
+    let mut any = false;
+    for alternative in split_unquoted(expr, "||") {
+        let mut all = true;
+        for clause in split_unquoted(alternative, "&&") {
+            all &= eval_clause(clause.trim(), resolved, known)?;
+        }
+        any |= all;
     }
+    Ok(any)
+}
+
+/// Byte offset of the first `pattern` in `text` outside double-quoted strings.
+fn find_unquoted(text: &str, pattern: &str) -> Option<usize> {
+    let (mut quoted, mut escaped) = (false, false);
+    for (i, ch) in text.char_indices() {
+        if quoted {
+            match ch {
+                _ if escaped => escaped = false,
+                '\\' => escaped = true,
+                '"' => quoted = false,
+                _ => {}
+            }
+        } else if ch == '"' {
+            quoted = true;
+        } else if text[i..].starts_with(pattern) {
+            return Some(i);
+        }
+    }
+    None
+}
+
+/// Split `text` on `separator`, except inside double-quoted strings.
+fn split_unquoted<'a>(text: &'a str, separator: &str) -> Vec<&'a str> {
+    let mut parts = Vec::new();
+    let mut rest = text;
+    while let Some(i) = find_unquoted(rest, separator) {
+        parts.push(&rest[..i]);
+        rest = &rest[i + separator.len()..];
+    }
+    parts.push(rest);
+    parts
+}
+
+fn eval_clause(
+    clause: &str,
+    resolved: &BTreeMap<String, serde_json::Value>,
+    known: &[&str],
+) -> Result<bool, String> {
+    let (name, comparison) = if let Some(i) = find_unquoted(clause, "!=") {
+        (clause[..i].trim(), Some((false, clause[i + 2..].trim())))
+    } else if let Some(i) = find_unquoted(clause, "==") {
+        (clause[..i].trim(), Some((true, clause[i + 2..].trim())))
+    } else {
+        (clause, None)
+    };
+    if name.is_empty() {
+        return Err(format!("missing parameter name in '{clause}'"));
+    }
+    if !known.contains(&name) {
+        return Err(format!(
+            "'{name}' is not a parameter defined before this one"
+        ));
+    }
+    let value = resolved.get(name).unwrap_or(&serde_json::Value::Null);
+
+    let Some((equal, literal)) = comparison else {
+        return Ok(!matches!(
+            value,
+            serde_json::Value::Null | serde_json::Value::Bool(false)
+        ) && value.as_str() != Some(""));
+    };
+    if literal.is_empty() {
+        return Err(format!("missing value in '{clause}'"));
+    }
+    let literal = serde_json::from_str(literal)
+        .unwrap_or_else(|_| serde_json::Value::String(literal.to_string()));
+    let same = match (value.as_f64(), literal.as_f64()) {
+        (Some(a), Some(b)) => a == b,
+        _ => *value == literal,
+    };
+    Ok(same == equal)
 }
 
 /// Parse `-- step: <name>` delimited SQL blocks from the SQL body
@@ -697,15 +1375,131 @@ pub fn list_stored_queries() -> Result<Vec<StoredQuery>, StoredQueryError> {
     Ok(queries.into_values().collect())
 }
 
+/// One place a search term was found in a stored query
+#[derive(Debug, Clone, PartialEq)]
+pub struct SearchMatch {
+    /// What matched: `name`, `description`, `alias`, `tag`, `param` or `sql`
+    /// (`sql:<step>` for multi-step queries)
+    pub field: String,
+    /// The matching line (or value)
+    pub text: String,
+    /// Byte range of the first match within `text`
+    pub range: std::ops::Range<usize>,
+}
+
+impl StoredQuery {
+    /// Case-insensitive search of the name, description, aliases, tags, parameter
+    /// names and SQL, one match per matching line.
+    pub fn search(&self, term: &str) -> Vec<SearchMatch> {
+        let mut matches = Vec::new();
+        let mut check = |field: &str, text: &str| {
+            for line in text.lines() {
+                if let Some(range) = find_ignore_case(line, term) {
+                    matches.push(SearchMatch {
+                        field: field.to_string(),
+                        text: line.to_string(),
+                        range,
+                    });
+                }
+            }
+        };
+        check("name", &self.name);
+        check("description", &self.metadata.description);
+        for alias in &self.metadata.aliases {
+            check("alias", alias);
+        }
+        for tag in &self.metadata.tags {
+            check("tag", tag);
+        }
+        for param in &self.metadata.params {
+            check("param", &param.name);
+        }
+        check("sql", &self.sql);
+        for (step, sql) in &self.step_queries {
+            check(&format!("sql:{step}"), sql);
+        }
+        matches
+    }
+}
+
+/// Byte range of the first case-insensitive occurrence of `needle` in `haystack`.
+fn find_ignore_case(haystack: &str, needle: &str) -> Option<std::ops::Range<usize>> {
+    if needle.is_empty() {
+        return None;
+    }
+    let needle: Vec<char> = needle.chars().flat_map(char::to_lowercase).collect();
+    for (start, _) in haystack.char_indices() {
+        let mut rest = haystack[start..].char_indices();
+        let mut lowered = Vec::new();
+        let mut end = start;
+        while lowered.len() < needle.len() {
+            let Some((offset, c)) = rest.next() else {
+                break;
+            };
+            lowered.extend(c.to_lowercase());
+            end = start + offset + c.len_utf8();
+        }
+        if lowered == needle {
+            return Some(start..end);
+        }
+    }
+    None
+}
+
+/// Stored queries whose name matches a glob (`*` any run of characters, `?`
+/// one character), e.g. `report-*`, sorted by name.
+pub fn find_stored_queries(pattern: &str) -> Result<Vec<StoredQuery>, StoredQueryError> {
+    Ok(list_stored_queries()?
+        .into_iter()
+        .filter(|q| glob_match(pattern, &q.name))
+        .collect())
+}
+
+/// Match `name` against a glob with `*` and `?` wildcards.
+pub fn glob_match(pattern: &str, name: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let name: Vec<char> = name.chars().collect();
+    // Backtracking matcher: remember the last `*` and where it started matching
+    let (mut p, mut n) = (0, 0);
+    let mut star: Option<(usize, usize)> = None;
+    while n < name.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == name[n]) {
+            p += 1;
+            n += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            star = Some((p, n));
+            p += 1;
+        } else if let Some((star_p, star_n)) = star {
+            p = star_p + 1;
+            n = star_n + 1;
+            star = Some((star_p, star_n + 1));
+        } else {
+            return false;
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
 /// List stored query names (lightweight — only reads filenames, not file contents).
 /// Used for shell tab-completion.
 pub fn list_query_names() -> Vec<(String, Option<String>)> {
-    // Try full parse first for descriptions; fall back to filenames only
+    // Try full parse first for descriptions and aliases; fall back to filenames only
     if let Ok(queries) = list_stored_queries() {
-        return queries
-            .into_iter()
-            .map(|q| (q.name, Some(q.metadata.description)))
-            .collect();
+        let mut names: Vec<(String, Option<String>)> = Vec::new();
+        for q in &queries {
+            for alias in &q.metadata.aliases {
+                names.push((alias.clone(), Some(format!("alias for {}", q.name))));
+            }
+        }
+        for (alias, target) in load_aliases_file().unwrap_or_default() {
+            names.push((alias, Some(format!("alias for {target}"))));
+        }
+        names.extend(
+            queries
+                .into_iter()
+                .map(|q| (q.name, Some(q.metadata.description))),
+        );
+        return names;
     }
 
     // Fallback: just scan filenames
@@ -736,8 +1530,35 @@ fn collect_names_from_dir(dir: &Path, names: &mut BTreeMap<String, ()>) {
     }
 }
 
-/// Find a stored query by name, checking project dir first, then user dir
+/// Find a stored query by name or alias, checking project dir first, then user dir
 pub fn find_stored_query(name: &str) -> Result<StoredQuery, StoredQueryError> {
+    let path = find_query_file(name)?;
+    StoredQuery::load(&path)
+}
+
+/// Find the `.cosq` file for a query name or alias (project dir first, then user dir).
+pub fn find_query_file(name: &str) -> Result<PathBuf, StoredQueryError> {
+    if let Some(path) = existing_query_file(name) {
+        return Ok(path);
+    }
+
+    // Not a file name — try aliases
+    let queries = list_stored_queries().unwrap_or_default();
+    let file_aliases = load_aliases_file()?;
+    if let Some(target) = resolve_alias(name, &queries, &file_aliases)? {
+        if let Some(path) = existing_query_file(&target) {
+            return Ok(path);
+        }
+    }
+
+    Err(StoredQueryError::Read(std::io::Error::new(
+        std::io::ErrorKind::NotFound,
+        format!("stored query '{name}' not found"),
+    )))
+}
+
+/// Return the path of an existing `.cosq` file with this exact name.
+fn existing_query_file(name: &str) -> Option<PathBuf> {
     let filename = if name.ends_with(".cosq") {
         name.to_string()
     } else {
@@ -748,21 +1569,174 @@ pub fn find_stored_query(name: &str) -> Result<StoredQuery, StoredQueryError> {
     if let Some(project_dir) = project_queries_dir() {
         let path = project_dir.join(&filename);
         if path.exists() {
-            return StoredQuery::load(&path);
+            return Some(path);
         }
     }
 
     // Check user-level
-    let user_dir = user_queries_dir()?;
-    let path = user_dir.join(&filename);
-    if path.exists() {
-        return StoredQuery::load(&path);
+    let path = user_queries_dir().ok()?.join(&filename);
+    path.exists().then_some(path)
+}
+
+/// Locate an included file: relative to the including file, then the project and
+/// user queries dirs. A missing extension defaults to `.cosq`.
+fn find_include_file(base_dir: &Path, include: &str) -> Option<PathBuf> {
+    let relative = if Path::new(include).extension().is_some() {
+        PathBuf::from(include)
+    } else {
+        PathBuf::from(format!("{include}.cosq"))
+    };
+
+    let mut candidates = vec![base_dir.join(&relative)];
+    if let Some(project_dir) = project_queries_dir() {
+        candidates.push(project_dir.join(&relative));
     }
+    if let Ok(user_dir) = user_queries_dir() {
+        candidates.push(user_dir.join(&relative));
+    }
+    candidates.into_iter().find(|p| p.is_file())
+}
 
-    Err(StoredQueryError::Read(std::io::Error::new(
-        std::io::ErrorKind::NotFound,
-        format!("stored query '{name}' not found"),
-    )))
+/// Replace `{{> name}}` markers with the named SQL fragment.
+fn expand_fragments(
+    sql: &str,
+    fragments: &BTreeMap<String, String>,
+) -> Result<String, StoredQueryError> {
+    let re = regex::Regex::new(r"\{\{>\s*([\w\-./]+)\s*\}\}").unwrap();
+    let mut missing = None;
+    let expanded = re.replace_all(sql, |caps: &regex::Captures| {
+        let name = &caps[1];
+        match fragments.get(name) {
+            Some(fragment) => fragment.clone(),
+            None => {
+                missing.get_or_insert_with(|| name.to_string());
+                String::new()
+            }
+        }
+    });
+    match missing {
+        Some(name) => Err(StoredQueryError::Include {
+            path: name,
+            reason: "fragment is not listed in `include:`".to_string(),
+        }),
+        None => Ok(expanded.into_owned()),
+    }
+}
+
+/// Accept either a single string or a list of strings.
+fn one_or_many<'de, D>(deserializer: D) -> Result<Vec<String>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum OneOrMany {
+        One(String),
+        Many(Vec<String>),
+    }
+    Ok(match OneOrMany::deserialize(deserializer)? {
+        OneOrMany::One(s) => vec![s],
+        OneOrMany::Many(v) => v,
+    })
+}
+
+/// Return the user-level aliases file: `~/.cosq/aliases.yaml`
+///
+/// The file maps alias names to stored query names:
+/// ```text
+/// ro: recent-orders
+/// cust: customer-lookup
+/// ```
+pub fn aliases_file_path() -> Result<PathBuf, StoredQueryError> {
+    dirs::home_dir()
+        .map(|d| d.join(".cosq").join("aliases.yaml"))
+        .ok_or(StoredQueryError::NoQueriesDir)
+}
+
+/// Load the user-level aliases file (empty if it does not exist).
+pub fn load_aliases_file() -> Result<BTreeMap<String, String>, StoredQueryError> {
+    let path = aliases_file_path()?;
+    match std::fs::read_to_string(&path) {
+        Ok(contents) if contents.trim().is_empty() => Ok(BTreeMap::new()),
+        Ok(contents) => {
+            serde_yaml::from_str(&contents).map_err(StoredQueryError::InvalidAliasesFile)
+        }
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(BTreeMap::new()),
+        Err(e) => Err(StoredQueryError::Read(e)),
+    }
+}
+
+/// Write the user-level aliases file.
+pub fn save_aliases_file(aliases: &BTreeMap<String, String>) -> Result<(), StoredQueryError> {
+    let path = aliases_file_path()?;
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let contents = serde_yaml::to_string(aliases).map_err(StoredQueryError::InvalidAliasesFile)?;
+    std::fs::write(&path, contents)?;
+    Ok(())
+}
+
+/// Point every alias that targets `old` at `new`, returning the aliases changed.
+pub fn retarget_aliases(
+    aliases: &mut BTreeMap<String, String>,
+    old: &str,
+    new: &str,
+) -> Vec<String> {
+    let mut changed = Vec::new();
+    for (alias, target) in aliases.iter_mut() {
+        if target.strip_suffix(".cosq").unwrap_or(target) == old {
+            *target = new.to_string();
+            changed.push(alias.clone());
+        }
+    }
+    changed
+}
+
+/// Whether an `include:` entry (e.g. `common/base-filters.cosq`) refers to the
+/// stored query `name`.
+pub fn include_refers_to(include: &str, name: &str) -> bool {
+    Path::new(include)
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .is_some_and(|stem| stem == name)
+        && Path::new(include)
+            .extension()
+            .is_none_or(|ext| ext == "cosq")
+}
+
+/// Resolve an alias to a query name using front matter `aliases:` and the aliases file.
+///
+/// Returns `Ok(None)` when nothing matches and an error when the alias points
+/// to more than one query.
+pub fn resolve_alias(
+    alias: &str,
+    queries: &[StoredQuery],
+    file_aliases: &BTreeMap<String, String>,
+) -> Result<Option<String>, StoredQueryError> {
+    let alias = alias.strip_suffix(".cosq").unwrap_or(alias);
+    let mut candidates: Vec<String> = queries
+        .iter()
+        .filter(|q| q.metadata.aliases.iter().any(|a| a == alias))
+        .map(|q| q.name.clone())
+        .collect();
+    if let Some(target) = file_aliases.get(alias) {
+        if !candidates.contains(target) {
+            candidates.push(target.clone());
+        }
+    }
+
+    match candidates.len() {
+        0 => Ok(None),
+        1 => Ok(candidates.pop()),
+        _ => {
+            candidates.sort();
+            Err(StoredQueryError::AmbiguousAlias {
+                alias: alias.to_string(),
+                candidates: candidates.join(", "),
+            })
+        }
+    }
 }
 
 /// Get the path where a stored query should be saved (user-level by default)
@@ -901,6 +1875,28 @@ SELECT c.id, c.total FROM c WHERE c.status = @status
         assert_eq!(limit_param.max, Some(1000.0));
     }
 
+    #[test]
+    fn test_parse_template_mode() {
+        let query = StoredQuery::parse(
+            "lines",
+            "---\ndescription: one line per user\ntemplate_mode: row\ntemplate: \"{{ doc.id }}\"\n---\nSELECT c.id FROM c\n",
+        )
+        .unwrap();
+        assert_eq!(query.metadata.template_mode, Some(TemplateMode::Row));
+        assert!(
+            query
+                .to_file_contents()
+                .unwrap()
+                .contains("template_mode: row")
+        );
+
+        let multi = "---\ndescription: x\ntemplate_mode: row\nsteps:\n  - name: a\n    container: a\n---\n-- step: a\nSELECT * FROM c\n";
+        assert!(matches!(
+            StoredQuery::parse("multi", multi),
+            Err(StoredQueryError::RowTemplateMultiStep)
+        ));
+    }
+
     #[test]
     fn test_parse_query_with_template() {
         let query = StoredQuery::parse("orders-summary", QUERY_WITH_TEMPLATE).unwrap();
@@ -959,6 +1955,140 @@ SELECT c.id, c.total FROM c WHERE c.status = @status
         );
     }
 
+    #[test]
+    fn test_array_param() {
+        let contents = r#"---
+description: Orders in any of several statuses
+params:
+  - name: statuses
+    type: array
+    choices: ["pending", "shipped", "delivered"]
+    default: ["pending"]
+---
+SELECT * FROM c WHERE ARRAY_CONTAINS(@statuses, c.status)
+"#;
+        let query = StoredQuery::parse("by-status", contents).unwrap();
+        let param = &query.metadata.params[0];
+        assert_eq!(param.param_type, ParamType::Array);
+
+        let resolved = query.resolve_params(&BTreeMap::new()).unwrap();
+        assert_eq!(resolved["statuses"], serde_json::json!(["pending"]));
+
+        let mut provided = BTreeMap::new();
+        provided.insert("statuses".to_string(), "pending, shipped".to_string());
+        let resolved = query.resolve_params(&provided).unwrap();
+        assert_eq!(
+            resolved["statuses"],
+            serde_json::json!(["pending", "shipped"])
+        );
+
+        provided.insert("statuses".to_string(), r#"["shipped", "lost"]"#.to_string());
+        let err = query.resolve_params(&provided).unwrap_err();
+        assert!(err.to_string().contains("'lost' is not one of the allowed"));
+
+        assert!(param.validate(&serde_json::json!("pending")).is_err());
+    }
+
+    #[test]
+    fn test_eval_when() {
+        let resolved: BTreeMap<String, serde_json::Value> = [
+            ("scope".to_string(), serde_json::json!("regional")),
+            ("limit".to_string(), serde_json::json!(10)),
+            ("verbose".to_string(), serde_json::json!(false)),
+            ("pair".to_string(), serde_json::json!("a||b")),
+        ]
+        .into();
+        let known = ["scope", "limit", "verbose", "unset", "pair"];
+        let eval = |expr| eval_when(expr, &resolved, &known);
+
+        assert!(eval(r#"scope == "regional""#).unwrap());
+        assert!(eval("scope == regional").unwrap());
+        assert!(!eval("scope != regional").unwrap());
+        assert!(eval("limit == 10.0").unwrap());
+        assert!(!eval("verbose").unwrap());
+        assert!(!eval("unset").unwrap());
+        assert!(eval("unset == null").unwrap());
+        assert!(eval("verbose || scope == regional && limit == 10").unwrap());
+        assert!(!eval("scope == global || limit == 5").unwrap());
+        assert!(eval("region == eu").unwrap_err().contains("'region'"));
+        assert!(eval("scope ==").unwrap_err().contains("missing value"));
+
+        // Operators inside quoted literals don't split the expression
+        assert!(eval(r#"pair == "a||b""#).unwrap());
+        assert!(!eval(r#"pair == "a&&b" || scope == global"#).unwrap());
+        assert!(eval(r#"pair != "x==y" && scope == "a != b" || limit == 10"#).unwrap());
+        assert!(!eval(r#"scope == "regional||global""#).unwrap());
+    }
+
+    #[test]
+    fn test_resolve_params_when() {
+        let contents = r#"---
+description: Usage by scope
+params:
+  - name: scope
+    type: string
+    choices: [global, regional]
+    default: global
+  - name: region
+    type: string
+    when: scope == "regional"
+---
+SELECT * FROM c WHERE @scope = "global" OR c.region = @region
+"#;
+        let query = StoredQuery::parse("usage", contents).unwrap();
+
+        // region is required, but only when scope is regional; otherwise the
+        // SQL still gets it, as null
+        let resolved = query.resolve_params(&BTreeMap::new()).unwrap();
+        assert_eq!(resolved["region"], serde_json::Value::Null);
+        let params = StoredQuery::build_cosmos_params(&resolved);
+        assert!(params.contains(&serde_json::json!({"name": "@region", "value": null})));
+
+        let mut provided = BTreeMap::new();
+        provided.insert("region".to_string(), "eu".to_string());
+        let err = query.resolve_params(&provided).unwrap_err();
+        assert!(matches!(err, StoredQueryError::InactiveParam { .. }));
+
+        provided.insert("scope".to_string(), "regional".to_string());
+        let resolved = query.resolve_params(&provided).unwrap();
+        assert_eq!(resolved["region"], serde_json::json!("eu"));
+
+        provided.remove("region");
+        let err = query.resolve_params(&provided).unwrap_err();
+        assert!(matches!(err, StoredQueryError::MissingParam { .. }));
+    }
+
+    #[test]
+    fn test_parse_uuid_and_object_params() {
+        let id = "3F2504E0-4F89-11D3-9A0C-0305E82C3301";
+        let value = parse_param_value("id", &ParamType::Uuid, id).unwrap();
+        assert_eq!(value, serde_json::json!(id));
+        assert!(parse_param_value("id", &ParamType::Uuid, "order-123").is_err());
+
+        let value =
+            parse_param_value("filter", &ParamType::Object, r#"{"status": "open"}"#).unwrap();
+        assert_eq!(value, serde_json::json!({"status": "open"}));
+        assert!(parse_param_value("filter", &ParamType::Object, "{status}").is_err());
+
+        let param: ParamDef =
+            serde_yaml::from_str("name: id\ntype: uuid\ndefault: not-a-uuid").unwrap();
+        assert!(param.validate(param.default.as_ref().unwrap()).is_err());
+    }
+
+    #[test]
+    fn test_glob_match() {
+        assert!(glob_match("report-*", "report-daily"));
+        assert!(glob_match("report-*", "report-"));
+        assert!(!glob_match("report-*", "reports"));
+        assert!(glob_match("*-orders", "open-orders"));
+        assert!(glob_match("*ord*s", "open-orders"));
+        assert!(glob_match("user?", "users"));
+        assert!(!glob_match("user?", "user"));
+        assert!(glob_match("*", "anything"));
+        assert!(glob_match("exact", "exact"));
+        assert!(!glob_match("exact", "exactly"));
+    }
+
     #[test]
     fn test_build_cosmos_params() {
         let mut resolved = BTreeMap::new();
@@ -1158,6 +2288,25 @@ SELECT * FROM c WHERE c.customerId = @customer.id ORDER BY c.date DESC
         assert_eq!(layers[1], vec!["orders"]);
     }
 
+    #[test]
+    fn test_step_graph() {
+        let query = StoredQuery::parse("customer-orders", MULTI_STEP_CHAIN).unwrap();
+        let graph = query.step_graph().unwrap();
+        assert_eq!(graph.len(), 2);
+        assert_eq!(graph[0].name, "customer");
+        assert_eq!(graph[0].layer, 0);
+        assert_eq!(graph[0].params, vec!["customerName"]);
+        assert!(graph[0].inputs.is_empty());
+        assert_eq!(graph[1].name, "orders");
+        assert_eq!(graph[1].container, "orders");
+        assert_eq!(graph[1].layer, 1);
+        assert_eq!(graph[1].inputs["customer"], vec!["id"]);
+        assert!(graph[1].params.is_empty());
+
+        let single = StoredQuery::parse("q", "---\ndescription: d\n---\nSELECT * FROM c").unwrap();
+        assert!(single.step_graph().unwrap().is_empty());
+    }
+
     #[test]
     fn test_find_step_references() {
         let step_names = vec!["customer".to_string(), "orders".to_string()];
@@ -1232,6 +2381,368 @@ SELECT * FROM c
         ));
     }
 
+    #[test]
+    fn test_parse_post_run_hook() {
+        let contents = r#"---
+description: Copy to clipboard
+hooks:
+  post_run:
+    command: pbcopy
+---
+SELECT * FROM c
+"#;
+        let query = StoredQuery::parse("copy", contents).unwrap();
+        let hook = query.metadata.hooks.unwrap().post_run.unwrap();
+        assert_eq!(hook.command, "pbcopy");
+        assert_eq!(hook.input, HookInput::Output);
+
+        let contents = "---\ndescription: t\nhooks:\n  post_run:\n    command: notify.sh\n    input: json\n---\nSELECT 1\n";
+        let query = StoredQuery::parse("notify", contents).unwrap();
+        let hook = query.metadata.hooks.unwrap().post_run.unwrap();
+        assert_eq!(hook.input, HookInput::Json);
+    }
+
+    #[test]
+    fn test_parse_prometheus_config() {
+        let contents = r#"---
+description: Orders by region
+prometheus:
+  prefix: shop_
+  labels: [region]
+  metrics:
+    - field: count
+      name: orders
+      help: Orders per region
+    - field: revenue
+      type: counter
+---
+SELECT c.region, COUNT(1) AS count, SUM(c.total) AS revenue FROM c GROUP BY c.region
+"#;
+        let query = StoredQuery::parse("orders", contents).unwrap();
+        let prom = query.metadata.prometheus.unwrap();
+        assert_eq!(prom.prefix.as_deref(), Some("shop_"));
+        assert_eq!(prom.labels, Some(vec!["region".to_string()]));
+        assert_eq!(prom.metrics[0].kind, MetricKind::Gauge);
+        assert_eq!(prom.metrics[1].kind, MetricKind::Counter);
+    }
+
+    #[test]
+    fn test_parse_checks() {
+        let contents = r#"---
+description: Paid orders
+checks:
+  - row_count > 0
+  - all(c.total >= 0)
+---
+SELECT c.id, c.total FROM c WHERE c.status = "paid"
+"#;
+        let query = StoredQuery::parse("paid", contents).unwrap();
+        assert_eq!(
+            query.metadata.checks,
+            vec!["row_count > 0".to_string(), "all(c.total >= 0)".to_string()]
+        );
+        assert!(crate::checks::parse_checks(&query.metadata.checks).is_ok());
+        assert!(
+            query
+                .to_file_contents()
+                .unwrap()
+                .contains("- row_count > 0")
+        );
+    }
+
+    #[test]
+    fn test_parse_chart() {
+        let contents = r#"---
+description: Orders per status
+chart: bar:status:count
+---
+SELECT c.status, COUNT(1) AS count FROM c GROUP BY c.status
+"#;
+        let query = StoredQuery::parse("by-status", contents).unwrap();
+        let spec = crate::chart::ChartSpec::parse(query.metadata.chart.as_deref().unwrap());
+        assert_eq!(spec.unwrap().value, "count");
+    }
+
+    // --- Include tests ---
+
+    #[test]
+    fn test_include_sql_fragment_and_params() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir(dir.path().join("common")).unwrap();
+        std::fs::write(
+            dir.path().join("common/base-filters.cosq"),
+            "---\ndescription: Tenant filter\nparams:\n  - name: tenant\n    type: string\n---\nc.tenantId = @tenant AND NOT IS_DEFINED(c.deletedAt)\n",
+        )
+        .unwrap();
+        std::fs::write(dir.path().join("limit.sql"), "c.total > 100\n").unwrap();
+        let path = dir.path().join("active.cosq");
+        std::fs::write(
+            &path,
+            "---\ndescription: Active orders\ninclude:\n  - common/base-filters.cosq\n  - limit.sql\n---\nSELECT * FROM c WHERE {{> base-filters}} AND {{> limit}}\n",
+        )
+        .unwrap();
+
+        let query = StoredQuery::load(&path).unwrap();
+        assert_eq!(
+            query.sql,
+            "SELECT * FROM c WHERE c.tenantId = @tenant AND NOT IS_DEFINED(c.deletedAt) AND c.total > 100"
+        );
+        assert_eq!(query.metadata.params.len(), 1);
+        assert_eq!(query.metadata.params[0].name, "tenant");
+
+        // Parsing without loading keeps the file as written
+        let raw = StoredQuery::parse("active", &std::fs::read_to_string(&path).unwrap()).unwrap();
+        assert!(raw.sql.contains("{{> base-filters}}"));
+        assert_eq!(raw.metadata.include.len(), 2);
+    }
+
+    #[test]
+    fn test_include_steps() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(
+            dir.path().join("customer.cosq"),
+            "---\ndescription: Customer\nsteps:\n  - name: customer\n    container: customers\n---\n-- step: customer\nSELECT * FROM c WHERE c.id = @customerId\n",
+        )
+        .unwrap();
+        let path = dir.path().join("orders.cosq");
+        std::fs::write(
+            &path,
+            "---\ndescription: Orders\ninclude: customer\nsteps:\n  - name: orders\n    container: orders\n---\n-- step: orders\nSELECT * FROM c WHERE c.customerId = @customer.id\n",
+        )
+        .unwrap();
+
+        let query = StoredQuery::load(&path).unwrap();
+        let names: Vec<&str> = query
+            .metadata
+            .steps
+            .as_ref()
+            .unwrap()
+            .iter()
+            .map(|s| s.name.as_str())
+            .collect();
+        assert_eq!(names, vec!["customer", "orders"]);
+        assert!(query.step_queries.contains_key("customer"));
+        assert_eq!(
+            query.execution_order().unwrap(),
+            vec![vec!["customer".to_string()], vec!["orders".to_string()]]
+        );
+    }
+
+    #[test]
+    fn test_include_errors() {
+        let dir = tempfile::tempdir().unwrap();
+        let a = dir.path().join("a.cosq");
+        let b = dir.path().join("b.cosq");
+        std::fs::write(&a, "---\ndescription: a\ninclude: b\n---\nSELECT 1\n").unwrap();
+        std::fs::write(&b, "---\ndescription: b\ninclude: a\n---\nSELECT 2\n").unwrap();
+        let err = StoredQuery::load(&a).unwrap_err();
+        assert!(err.to_string().contains("circular include"), "{err}");
+
+        let c = dir.path().join("c.cosq");
+        std::fs::write(&c, "---\ndescription: c\ninclude: missing\n---\nSELECT 1\n").unwrap();
+        assert!(
+            StoredQuery::load(&c)
+                .unwrap_err()
+                .to_string()
+                .contains("not found")
+        );
+
+        let d = dir.path().join("d.cosq");
+        std::fs::write(
+            &d,
+            "---\ndescription: d\ninclude: b\n---\nSELECT * FROM c WHERE {{> other}}\n",
+        )
+        .unwrap();
+        assert!(StoredQuery::load(&d).is_err());
+    }
+
+    // --- Alias tests ---
+
+    fn query_with_aliases(name: &str, aliases: &[&str]) -> StoredQuery {
+        let contents = format!(
+            "---\ndescription: test\naliases: [{}]\n---\nSELECT * FROM c\n",
+            aliases.join(", ")
+        );
+        StoredQuery::parse(name, &contents).unwrap()
+    }
+
+    #[test]
+    fn test_parse_aliases() {
+        let query = query_with_aliases("recent-orders", &["ro", "recent"]);
+        assert_eq!(query.metadata.aliases, vec!["ro", "recent"]);
+        let reparsed =
+            StoredQuery::parse("recent-orders", &query.to_file_contents().unwrap()).unwrap();
+        assert_eq!(reparsed.metadata.aliases, query.metadata.aliases);
+    }
+
+    #[test]
+    fn test_resolve_alias_front_matter_and_file() {
+        let queries = vec![
+            query_with_aliases("recent-orders", &["ro"]),
+            query_with_aliases("customers", &[]),
+        ];
+        let mut file_aliases = BTreeMap::new();
+        file_aliases.insert("cust".to_string(), "customers".to_string());
+
+        assert_eq!(
+            resolve_alias("ro", &queries, &file_aliases).unwrap(),
+            Some("recent-orders".to_string())
+        );
+        assert_eq!(
+            resolve_alias("cust", &queries, &file_aliases).unwrap(),
+            Some("customers".to_string())
+        );
+        assert_eq!(
+            resolve_alias("nope", &queries, &file_aliases).unwrap(),
+            None
+        );
+    }
+
+    #[test]
+    fn test_resolve_alias_collision() {
+        let queries = vec![
+            query_with_aliases("recent-orders", &["r"]),
+            query_with_aliases("recent-users", &["r"]),
+        ];
+        let result = resolve_alias("r", &queries, &BTreeMap::new());
+        match result {
+            Err(StoredQueryError::AmbiguousAlias { candidates, .. }) => {
+                assert_eq!(candidates, "recent-orders, recent-users");
+            }
+            other => panic!("expected ambiguity error, got {other:?}"),
+        }
+
+        // The same target declared in both places is not a collision
+        let mut file_aliases = BTreeMap::new();
+        file_aliases.insert("r".to_string(), "recent-orders".to_string());
+        let queries = vec![query_with_aliases("recent-orders", &["r"])];
+        assert!(resolve_alias("r", &queries, &file_aliases).is_ok());
+    }
+
+    #[test]
+    fn test_search() {
+        let query = StoredQuery::parse(
+            "recent-orders",
+            "---\ndescription: Orders placed recently\ntags: [billing, Daily]\nparams:\n  - name: orderStatus\n    type: string\n---\nSELECT * FROM c\nWHERE c.status = @orderStatus\n",
+        )
+        .unwrap();
+
+        let fields = |term: &str| -> Vec<String> {
+            query.search(term).into_iter().map(|m| m.field).collect()
+        };
+        assert_eq!(fields("ORDER"), vec!["name", "description", "param", "sql"]);
+        assert_eq!(fields("daily"), vec!["tag"]);
+        assert!(fields("nothing").is_empty());
+
+        let sql = &query.search("STATUS")[1];
+        assert_eq!(sql.text, "WHERE c.status = @orderStatus");
+        assert_eq!(&sql.text[sql.range.clone()], "status");
+    }
+
+    #[test]
+    fn test_find_ignore_case() {
+        assert_eq!(find_ignore_case("Hello World", "world"), Some(6..11));
+        assert_eq!(find_ignore_case("Grüße", "GRÜ"), Some(0..4));
+        assert_eq!(find_ignore_case("abc", "abcd"), None);
+        assert_eq!(find_ignore_case("abc", ""), None);
+    }
+
+    #[test]
+    fn test_retarget_aliases() {
+        let mut aliases = BTreeMap::new();
+        aliases.insert("ro".to_string(), "recent-orders".to_string());
+        aliases.insert("recent".to_string(), "recent-orders.cosq".to_string());
+        aliases.insert("cust".to_string(), "customers".to_string());
+
+        let changed = retarget_aliases(&mut aliases, "recent-orders", "orders-recent");
+        assert_eq!(changed, vec!["recent", "ro"]);
+        assert_eq!(aliases["ro"], "orders-recent");
+        assert_eq!(aliases["recent"], "orders-recent");
+        assert_eq!(aliases["cust"], "customers");
+    }
+
+    #[test]
+    fn test_include_refers_to() {
+        assert!(include_refers_to("base-filters", "base-filters"));
+        assert!(include_refers_to(
+            "common/base-filters.cosq",
+            "base-filters"
+        ));
+        assert!(!include_refers_to("base-filters.sql", "base-filters"));
+        assert!(!include_refers_to("other.cosq", "base-filters"));
+    }
+
+    #[test]
+    fn test_parse_targets() {
+        let q = StoredQuery::parse(
+            "t",
+            "---\ndescription: t\ntargets: [prod-eu, prod-us/jobsdb/jobs]\n---\nSELECT * FROM c\n",
+        )
+        .unwrap();
+        assert_eq!(q.metadata.targets.len(), 2);
+
+        let target = QueryTarget::parse(&q.metadata.targets[1]).unwrap();
+        assert_eq!(target.name, "prod-us");
+        assert_eq!(target.database.as_deref(), Some("jobsdb"));
+        assert_eq!(target.container.as_deref(), Some("jobs"));
+        assert_eq!(target.to_string(), "prod-us/jobsdb/jobs");
+
+        let bare = QueryTarget::parse("prod-eu").unwrap();
+        assert!(bare.database.is_none() && bare.container.is_none());
+
+        for bad in ["", "/db", "a//c", "a/b/c/d"] {
+            assert!(QueryTarget::parse(bad).is_err(), "{bad}");
+        }
+    }
+
+    #[test]
+    fn test_decrypt_params() {
+        let key = EncryptionKey::generate();
+        let sealed = key.encrypt(&serde_json::json!("s3cret")).unwrap();
+        let contents = format!(
+            "---\ndescription: t\nparams:\n  - name: token\n    type: string\n    encrypted: true\n    default: \"{sealed}\"\n  - name: days\n    type: number\n    default: 7\n---\nSELECT * FROM c WHERE c.token = @token\n"
+        );
+        let mut query = StoredQuery::parse("q", &contents).unwrap();
+        assert!(query.has_encrypted_params());
+        assert!(
+            StoredQuery::parse("q", &contents)
+                .unwrap()
+                .decrypt_params(&EncryptionKey::generate())
+                .is_err()
+        );
+
+        query.decrypt_params(&key).unwrap();
+        let resolved = query.resolve_params(&BTreeMap::new()).unwrap();
+        assert_eq!(resolved["token"], "s3cret");
+        assert_eq!(resolved["days"], 7);
+
+        let plain = contents.replace(&sealed, "s3cret");
+        let mut query = StoredQuery::parse("q", &plain).unwrap();
+        assert!(matches!(
+            query.decrypt_params(&key),
+            Err(StoredQueryError::NotEncrypted { .. })
+        ));
+    }
+
+    #[test]
+    fn test_into_snippet() {
+        let contents = "---\ndescription: Open orders\nprofile: prod\ntargets: [eu, us]\nschedule: '@daily'\nnotify: https://hooks.example.com/x\nhooks:\n  post_run:\n    command: pbcopy\ndatabase: shop\ncontainer: orders\nparams:\n  - name: status\n    type: string\n    default: open\n---\nSELECT * FROM c WHERE c.status = @status\n";
+        let mut snippet = StoredQuery::parse("open-orders", contents)
+            .unwrap()
+            .into_snippet();
+        snippet.metadata.sample_results = vec![serde_json::json!({"id": "1"})];
+
+        let shared = snippet.to_file_contents().unwrap();
+        for stripped in ["profile:", "targets:", "schedule:", "notify:", "hooks:"] {
+            assert!(!shared.contains(stripped), "{stripped} in {shared}");
+        }
+        let installed = StoredQuery::parse("open-orders", &shared).unwrap();
+        assert_eq!(installed.metadata.database.as_deref(), Some("shop"));
+        assert_eq!(installed.metadata.params.len(), 1);
+        assert_eq!(installed.metadata.sample_results.len(), 1);
+        assert_eq!(installed.sql, snippet.sql);
+    }
+
     #[test]
     fn test_single_step_backward_compat() {
         // Existing single-step queries should still work exactly as before
@@ -0,0 +1,150 @@
+//! Built-in output templates
+//!
+//! `--template builtin:<name>` (and `template_file: builtin:<name>` in a
+//! stored query) selects a template shipped with cosq instead of a file;
+//! `cosq templates builtins` lists them. The chat templates render the results
+//! as a Slack Block Kit message or a Teams Adaptive Card, which `--notify`
+//! posts as is. All of them use an optional `title` parameter as the heading.
+
+use anyhow::{Context, Result, bail};
+
+/// Prefix selecting a built-in template instead of a file path
+pub const BUILTIN_PREFIX: &str = "builtin:";
+
+/// Built-in templates: name, description, source
+pub const BUILTIN_TEMPLATES: &[(&str, &str, &str)] = &[
+    (
+        "summary",
+        "Result count, then min/max/sum or distinct values per field",
+        include_str!("templates/summary.txt.j2"),
+    ),
+    (
+        "markdown-table",
+        "Markdown table, one column per field of the first result",
+        include_str!("templates/markdown-table.md.j2"),
+    ),
+    (
+        "bullet-list",
+        "Markdown bullet list, one bullet per result",
+        include_str!("templates/bullet-list.md.j2"),
+    ),
+    (
+        "html-report",
+        "Standalone HTML page with a table of the results",
+        include_str!("templates/html-report.html.j2"),
+    ),
+    (
+        "slack",
+        "Slack Block Kit message, one section per result (first 10)",
+        include_str!("templates/slack.json.j2"),
+    ),
+    (
+        "teams-card",
+        "Teams Adaptive Card, one fact set per result (first 10)",
+        include_str!("templates/teams-card.json.j2"),
+    ),
+];
+
+/// Read a template: `builtin:<name>` or a file path.
+pub fn read_template(path: &str) -> Result<String> {
+    let Some(name) = path.strip_prefix(BUILTIN_PREFIX) else {
+        return std::fs::read_to_string(path)
+            .with_context(|| format!("failed to read template file: {path}"));
+    };
+    match BUILTIN_TEMPLATES.iter().find(|(n, _, _)| *n == name) {
+        Some((_, _, source)) => Ok(source.to_string()),
+        None => {
+            let names: Vec<String> = BUILTIN_TEMPLATES
+                .iter()
+                .map(|(n, _, _)| format!("{BUILTIN_PREFIX}{n}"))
+                .collect();
+            bail!(
+                "unknown built-in template '{path}' (available: {})",
+                names.join(", ")
+            )
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::output::render_template;
+    use serde_json::{Value, json};
+    use std::collections::BTreeMap;
+
+    #[test]
+    fn test_builtin_templates_render_json() {
+        let mut documents = vec![
+            json!({"id": "1", "status": "open \"quoted\"", "total": 12.5, "tags": ["a"], "_ts": 1}),
+            json!("plain value"),
+        ];
+        documents.extend((0..12).map(|i| json!({"id": i.to_string()})));
+        let params = BTreeMap::from([("title".to_string(), json!("Open orders"))]);
+
+        let slack = read_template("builtin:slack").unwrap();
+        let rendered: Value =
+            serde_json::from_str(&render_template(&slack, &documents, &params).unwrap()).unwrap();
+        assert_eq!(rendered["blocks"][0]["text"]["text"], "Open orders");
+        let fields = rendered["blocks"][3]["fields"].as_array().unwrap();
+        assert_eq!(fields.len(), 4);
+        assert_eq!(fields[1]["text"], "*status*\nopen \"quoted\"");
+        let last = rendered["blocks"].as_array().unwrap().last().unwrap();
+        assert_eq!(last["elements"][0]["text"], "…and 4 more");
+
+        let teams = read_template("builtin:teams-card").unwrap();
+        let rendered: Value =
+            serde_json::from_str(&render_template(&teams, &documents, &BTreeMap::new()).unwrap())
+                .unwrap();
+        assert_eq!(rendered["type"], "AdaptiveCard");
+        assert_eq!(rendered["body"][0]["text"], "cosq results");
+        let facts = rendered["body"][2]["facts"].as_array().unwrap();
+        let total = facts.iter().find(|fact| fact["title"] == "total").unwrap();
+        assert_eq!(total["value"], "12.5");
+        assert_eq!(rendered["body"][3]["text"], "plain value");
+
+        // No results still renders valid JSON
+        for template in [&slack, &teams] {
+            let empty = render_template(template, &[], &BTreeMap::new()).unwrap();
+            serde_json::from_str::<Value>(&empty).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_text_builtins() {
+        let documents = vec![
+            json!({"id": "1", "status": "open|<new>", "total": 12.5, "_ts": 1}),
+            json!({"id": "2", "status": "paid", "total": 3}),
+        ];
+        let render = |name: &str| {
+            let template = read_template(&format!("{BUILTIN_PREFIX}{name}")).unwrap();
+            render_template(&template, &documents, &BTreeMap::new()).unwrap()
+        };
+
+        let summary = render("summary");
+        assert!(summary.starts_with("cosq results: 2 result(s)"));
+        assert!(summary.contains("min 3, max 12.5, sum 15.5"));
+        assert!(summary.contains("2 distinct of 2"));
+
+        let table = render("markdown-table");
+        assert!(table.starts_with("| id | status | total |\n| --- | --- | --- |\n"));
+        assert!(table.contains("| 1 | open\\|<new> | 12.5 |"));
+
+        assert!(render("bullet-list").contains("- **id**: 2, **status**: paid, **total**: 3"));
+        assert!(render("html-report").contains("<td>open|&lt;new&gt;</td>"));
+
+        // Every built-in renders without results
+        for (name, _, source) in BUILTIN_TEMPLATES {
+            assert!(
+                render_template(source, &[], &BTreeMap::new()).is_ok(),
+                "{name}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_unknown_builtin() {
+        let err = read_template("builtin:discord").unwrap_err().to_string();
+        assert!(err.contains("builtin:slack"));
+    }
+}
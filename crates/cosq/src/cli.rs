@@ -1,10 +1,17 @@
 //! CLI argument definitions using clap
 
+use std::ffi::OsString;
+use std::path::PathBuf;
+
 use anyhow::Result;
 use clap::Parser;
 use clap_complete::engine::{ArgValueCandidates, CompletionCandidate};
+use cosq_client::arm::AccountKeyKind;
+use cosq_client::cosmos::{PermissionMode, PriorityLevel};
+use cosq_core::import::ImportFormat;
+use cosq_core::stored_query::ParamType;
 
-use crate::output::OutputFormat;
+use crate::output::{OutputFormat, RowLimit};
 
 /// Provide tab-completion candidates for stored query names
 fn complete_query_names() -> Vec<CompletionCandidate> {
@@ -20,6 +27,45 @@ fn complete_query_names() -> Vec<CompletionCandidate> {
         .collect()
 }
 
+/// Provide tab-completion candidates for database names from the listing cache
+fn complete_database_names() -> Vec<CompletionCandidate> {
+    cached_listings()
+        .and_then(|listings| listings.databases)
+        .map(|listing| {
+            listing
+                .names
+                .into_iter()
+                .map(CompletionCandidate::new)
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Provide tab-completion candidates for container names from the listing
+/// cache, across all cached databases
+fn complete_container_names() -> Vec<CompletionCandidate> {
+    cached_listings()
+        .map(|listings| {
+            listings
+                .containers
+                .into_iter()
+                .flat_map(|(database, listing)| {
+                    listing.names.into_iter().map(move |name| {
+                        CompletionCandidate::new(name).help(Some(format!("in {database}").into()))
+                    })
+                })
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Cached listings of the configured account, however old (completion never
+/// waits on the network)
+fn cached_listings() -> Option<cosq_core::listing_cache::AccountListings> {
+    let config = cosq_core::config::Config::load().ok()?;
+    cosq_core::listing_cache::AccountListings::load(&config.account.name).ok()
+}
+
 /// A CLI to query your Azure Cosmos DB instances
 #[derive(Parser)]
 #[command(name = "cosq")]
@@ -40,6 +86,27 @@ pub struct Cli {
     #[arg(long, global = true)]
     pub no_color: bool,
 
+    /// Use a named profile from the config (e.g. `local` for the emulator)
+    #[arg(long, global = true, env = "COSQ_PROFILE")]
+    pub profile: Option<String>,
+
+    /// Mask fields matching the config's `redact:` patterns in all output
+    #[arg(long, global = true, env = "COSQ_REDACT")]
+    pub redact: bool,
+
+    /// Refuse network calls; answer queries from the local result cache
+    #[arg(long, global = true, env = "COSQ_OFFLINE")]
+    pub offline: bool,
+
+    /// Send only field names and types to AI providers, never document values
+    #[arg(long, global = true, env = "COSQ_NO_AI_DATA")]
+    pub no_ai_data: bool,
+
+    /// Print diagnostics headers of every Cosmos DB response to stderr
+    /// (activity id, session token, server latency, retry-after)
+    #[arg(long, global = true)]
+    pub show_headers: bool,
+
     #[command(subcommand)]
     pub command: Option<Commands>,
 }
@@ -49,58 +116,329 @@ pub enum Commands {
     /// Execute a SQL query against Cosmos DB
     Query {
         /// SQL query string
-        sql: String,
+        #[arg(required_unless_present_any = ["interactive", "from_file"])]
+        sql: Option<String>,
+
+        /// Build the query step by step from the container's inferred fields
+        #[arg(long, short, conflicts_with_all = ["sql", "targets", "from_file"])]
+        interactive: bool,
 
         /// Database name (overrides config)
-        #[arg(long)]
+        #[arg(long, add = ArgValueCandidates::new(complete_database_names))]
         db: Option<String>,
 
         /// Container name (overrides config)
-        #[arg(long)]
+        #[arg(long, add = ArgValueCandidates::new(complete_container_names))]
         container: Option<String>,
 
         /// Output format
         #[arg(long, short, value_enum)]
         output: Option<OutputFormat>,
 
-        /// Path to a MiniJinja template file for output formatting
+        /// Path to a MiniJinja template file for output formatting, or
+        /// builtin:<name> (see `cosq templates builtins`)
         #[arg(long)]
         template: Option<String>,
+
+        /// On a template render error, print the template with the failing line,
+        /// the available variables and a sample document (no AI fix prompt)
+        #[arg(long)]
+        template_debug: bool,
+
+        /// Render a bar or line chart of the results instead of the output format
+        /// (<bar|line>:<label field>:<value field>, e.g. bar:status:count); SVG
+        /// when --out ends in .svg
+        #[arg(long, value_name = "SPEC", conflicts_with_all = ["output", "template"])]
+        chart: Option<String>,
+
+        /// Write output to a file, Azure Blob Storage (azblob://container/path) or a
+        /// DuckDB table (*.duckdb)
+        #[arg(long, value_name = "DEST")]
+        out: Option<String>,
+
+        /// Table to write in a DuckDB --out file (default: results)
+        #[arg(long, value_name = "NAME", requires = "out")]
+        table: Option<String>,
+
+        /// Replace the DuckDB table instead of appending to it
+        #[arg(long, requires = "out")]
+        replace: bool,
+
+        /// Local SQL over the results as table `results`, before --jq (e.g. 'SELECT status,
+        /// count(*) AS n FROM results GROUP BY status'; needs the datafusion feature)
+        #[arg(long, value_name = "SQL")]
+        post_sql: Option<String>,
+
+        /// jq filter applied to the results before output formatting (e.g. 'map({id, name})')
+        #[arg(long, value_name = "FILTER")]
+        jq: Option<String>,
+
+        /// Output only the first N results (applied after the query and --jq)
+        #[arg(long, value_name = "N", conflicts_with = "tail")]
+        head: Option<usize>,
+
+        /// Output only the last N results (applied after the query and --jq)
+        #[arg(long, value_name = "N")]
+        tail: Option<usize>,
+
+        /// Request priority on accounts with priority-based execution (low yields to production traffic)
+        #[arg(long, value_enum)]
+        priority: Option<Priority>,
+
+        /// Run against several targets concurrently and merge results with a `_source` column
+        /// (repeatable; <profile-or-account>[/<database>[/<container>]])
+        #[arg(long = "target", value_name = "TARGET")]
+        targets: Vec<String>,
+
+        /// Evaluate the SQL against a JSON or NDJSON file instead of Cosmos DB
+        /// (SELECT/WHERE/ORDER BY/TOP over `c`; without SQL, output the file's documents)
+        #[arg(long, value_name = "FILE", conflicts_with = "targets")]
+        from_file: Option<PathBuf>,
+
+        /// Send SQL that is not a plain SELECT to a container marked
+        /// `protected: true` without asking for confirmation
+        #[arg(long)]
+        force: bool,
     },
 
     /// Execute a stored query by name (interactive picker if no name given)
     Run {
         /// Name of the stored query (with or without .cosq extension)
-        #[arg(add = ArgValueCandidates::new(complete_query_names))]
+        #[arg(add = ArgValueCandidates::new(complete_query_names), conflicts_with = "all")]
         name: Option<String>,
 
+        /// Run every stored query whose name matches a glob (e.g. 'report-*'), writing
+        /// each result to a file in --out-dir
+        #[arg(long, value_name = "GLOB", conflicts_with_all = ["out", "notify", "from_file"])]
+        all: Option<String>,
+
+        /// How many queries to run at once with --all
+        #[arg(long, value_name = "N", default_value_t = 1, requires = "all")]
+        parallel: usize,
+
+        /// Directory for the per-query result files of --all
+        #[arg(
+            long,
+            value_name = "DIR",
+            default_value = "cosq-results",
+            requires = "all"
+        )]
+        out_dir: PathBuf,
+
         /// Database name (overrides query metadata and config)
-        #[arg(long)]
+        #[arg(long, add = ArgValueCandidates::new(complete_database_names))]
         db: Option<String>,
 
         /// Container name (overrides query metadata and config)
-        #[arg(long)]
+        #[arg(long, add = ArgValueCandidates::new(complete_container_names))]
         container: Option<String>,
 
         /// Output format (auto-detects template from query if available)
         #[arg(long, short, value_enum)]
         output: Option<OutputFormat>,
 
-        /// Path to a MiniJinja template file for output formatting
+        /// Path to a MiniJinja template file for output formatting, or
+        /// builtin:<name> (see `cosq templates builtins`)
         #[arg(long)]
         template: Option<String>,
 
+        /// On a template render error, print the template with the failing line,
+        /// the available variables and a sample document (no AI fix prompt)
+        #[arg(long)]
+        template_debug: bool,
+
+        /// Render a bar or line chart of the results instead of the output format
+        /// (<bar|line>:<label field>:<value field>, e.g. bar:status:count); SVG
+        /// when --out ends in .svg
+        #[arg(long, value_name = "SPEC", conflicts_with_all = ["output", "template", "all"])]
+        chart: Option<String>,
+
+        /// Run the query's post-run hook without asking for confirmation
+        #[arg(long, conflicts_with = "no_hooks")]
+        run_hooks: bool,
+
+        /// Skip the query's post-run hook
+        #[arg(long)]
+        no_hooks: bool,
+
+        /// Post results to a Slack/Teams webhook (URL or name from `webhooks:` in config)
+        #[arg(long)]
+        notify: Option<String>,
+
+        /// Write output to a file, Azure Blob Storage (azblob://container/path) or a
+        /// DuckDB table (*.duckdb)
+        #[arg(long, value_name = "DEST")]
+        out: Option<String>,
+
+        /// Table to write in a DuckDB --out file (default: the query name)
+        #[arg(long, value_name = "NAME", requires = "out")]
+        table: Option<String>,
+
+        /// Replace the DuckDB table instead of appending to it
+        #[arg(long, requires = "out")]
+        replace: bool,
+
+        /// Local SQL over the results as table `results`, before --jq (e.g. 'SELECT status,
+        /// count(*) AS n FROM results GROUP BY status'; needs the datafusion feature)
+        #[arg(long, value_name = "SQL")]
+        post_sql: Option<String>,
+
+        /// jq filter applied to the results before output formatting (e.g. 'map({id, name})')
+        #[arg(long, value_name = "FILTER")]
+        jq: Option<String>,
+
+        /// Output only the first N results (applied after the query and --jq)
+        #[arg(long, value_name = "N", conflicts_with = "tail")]
+        head: Option<usize>,
+
+        /// Output only the last N results (applied after the query and --jq)
+        #[arg(long, value_name = "N")]
+        tail: Option<usize>,
+
+        /// Request priority on accounts with priority-based execution (low yields to production traffic)
+        #[arg(long, value_enum)]
+        priority: Option<Priority>,
+
+        /// Run against several targets concurrently and merge results with a `_source` column
+        /// (repeatable; <profile-or-account>[/<database>[/<container>]])
+        #[arg(long = "target", value_name = "TARGET")]
+        targets: Vec<String>,
+
+        /// Read results from a JSON or NDJSON file instead of running the query
+        #[arg(long, value_name = "FILE", conflicts_with = "targets")]
+        from_file: Option<PathBuf>,
+
+        /// Also write each step's documents of a multi-step query to <DIR>/<step>.json
+        #[arg(long, value_name = "DIR", conflicts_with = "all")]
+        save_steps: Option<PathBuf>,
+
+        /// Resume a multi-step query at this step, loading the results of the
+        /// steps before it from --steps-dir
+        #[arg(
+            long,
+            value_name = "STEP",
+            requires = "steps_dir",
+            conflicts_with_all = ["all", "targets", "from_file"]
+        )]
+        from_step: Option<String>,
+
+        /// Directory of <step>.json files (as written by --save-steps) for --from-step
+        #[arg(long, value_name = "DIR", requires = "from_step")]
+        steps_dir: Option<PathBuf>,
+
+        /// Print each step's SQL with bound parameters and target container, in
+        /// execution order, without running anything
+        #[arg(long, conflicts_with_all = ["all", "targets", "from_file", "out", "notify", "save_steps", "from_step"])]
+        plan: bool,
+
+        /// Send SQL that is not a plain SELECT to a container marked
+        /// `protected: true` without asking for confirmation
+        #[arg(long)]
+        force: bool,
+
+        /// Evaluate the query's `checks:` against its results instead of printing
+        /// them; exits non-zero when a check fails (with --all: every query with checks)
+        #[arg(long, conflicts_with_all = ["plan", "out", "save_steps", "from_step"])]
+        check: bool,
+
         /// Query parameters (passed as trailing args: -- --param1 value1 --param2 value2)
         #[arg(trailing_var_arg = true, allow_hyphen_values = true)]
         params: Vec<String>,
     },
 
+    /// Render a MiniJinja template against saved results, without querying
+    Render {
+        /// Path to the MiniJinja template file, or builtin:<name>
+        #[arg(long)]
+        template: PathBuf,
+
+        /// Results to render: a JSON array, NDJSON, or an object of step name →
+        /// documents for multi-step templates
+        #[arg(long, value_name = "FILE")]
+        data: PathBuf,
+
+        /// Template parameters as a JSON object (e.g. '{"status": "shipped"}')
+        #[arg(long, value_name = "JSON")]
+        params: Option<String>,
+
+        /// Render the template once per document with `doc` in context
+        /// (like `template_mode: row`)
+        #[arg(long)]
+        rows: bool,
+
+        /// On a render error, print the template with the failing line,
+        /// the available variables and a sample document
+        #[arg(long)]
+        template_debug: bool,
+    },
+
+    /// Check stored queries' output templates
+    Templates {
+        #[command(subcommand)]
+        command: TemplatesCommands,
+    },
+
     /// Manage stored queries
     Queries {
         #[command(subcommand)]
         command: QueriesCommands,
     },
 
+    /// Manage the local Cosmos DB emulator (Docker)
+    Emulator {
+        #[command(subcommand)]
+        command: EmulatorCommands,
+    },
+
+    /// Diagnose Azure CLI auth, config, connectivity, RBAC and database/container access
+    Doctor {
+        /// Database to check (defaults to the configured database)
+        #[arg(long, add = ArgValueCandidates::new(complete_database_names))]
+        db: Option<String>,
+
+        /// Container to check (defaults to the configured container)
+        #[arg(long, add = ArgValueCandidates::new(complete_container_names))]
+        container: Option<String>,
+    },
+
+    /// Infer a container's schema from sampled documents (cached for AI generation)
+    Schema {
+        /// Container name (defaults to the configured container)
+        #[arg(add = ArgValueCandidates::new(complete_container_names))]
+        container: Option<String>,
+
+        /// Database name (overrides config)
+        #[arg(long, add = ArgValueCandidates::new(complete_database_names))]
+        db: Option<String>,
+
+        /// Number of documents to sample
+        #[arg(long, default_value_t = 1000)]
+        sample: u32,
+
+        /// Output format
+        #[arg(long, value_enum, default_value_t)]
+        format: SchemaFormat,
+    },
+
+    /// Show or change a container's saved view preferences
+    Prefs {
+        #[command(subcommand)]
+        command: PrefsCommands,
+    },
+
+    /// Show cumulative request units (and estimated cost) per query
+    Stats {
+        /// Clear the recorded stats
+        #[arg(long)]
+        reset: bool,
+    },
+
+    /// Export query schedules to cron or systemd timers
+    Schedule {
+        #[command(subcommand)]
+        command: ScheduleCommands,
+    },
+
     /// Initialize cosq with a Cosmos DB account
     Init {
         /// Cosmos DB account name (skip interactive selection)
@@ -111,11 +449,172 @@ pub enum Commands {
         #[arg(long)]
         subscription: Option<String>,
 
+        /// Account endpoint (e.g. https://myaccount.documents.azure.com:443/); skips ARM discovery
+        #[arg(long)]
+        endpoint: Option<String>,
+
+        /// Default database (skip interactive selection)
+        #[arg(long)]
+        database: Option<String>,
+
+        /// Default container (skip interactive selection)
+        #[arg(long, add = ArgValueCandidates::new(complete_container_names))]
+        container: Option<String>,
+
+        /// Don't check (or offer to assign) the data plane RBAC role
+        #[arg(long)]
+        no_rbac_check: bool,
+
+        /// Print the resulting config as JSON instead of a summary
+        #[arg(long, value_enum, default_value_t = InitOutput::Text)]
+        output: InitOutput,
+
         /// Auto-confirm prompts (e.g. RBAC role assignment)
         #[arg(long, short)]
         yes: bool,
     },
 
+    /// Read and modify individual documents
+    #[command(visible_alias = "doc")]
+    Docs {
+        #[command(subcommand)]
+        command: DocsCommands,
+    },
+
+    /// Inspect a container's settings (partition key, TTL, analytical store, throughput)
+    Container {
+        #[command(subcommand)]
+        command: ContainerCommands,
+    },
+
+    /// List the databases and containers of every configured account (or every
+    /// account in your subscriptions) with documents, storage and throughput
+    Inventory {
+        /// Walk every Cosmos DB account found through Azure Resource Manager
+        /// instead of the configured account and profiles
+        #[arg(long)]
+        discover: bool,
+
+        /// Only discover accounts in this subscription (ID or name; repeatable)
+        #[arg(long, value_name = "SUBSCRIPTION", requires = "discover")]
+        subscription: Vec<String>,
+
+        /// Report format
+        #[arg(long, short = 'o', value_enum, default_value_t = ReportFormat::Table)]
+        output: ReportFormat,
+
+        /// How many accounts to read at once
+        #[arg(long, value_name = "N", default_value_t = 4)]
+        parallel: usize,
+    },
+
+    /// Find documents sharing a key (e.g. c.email) and optionally delete all but the newest
+    Dedupe {
+        /// Container to check (defaults to the configured container)
+        #[arg(add = ArgValueCandidates::new(complete_container_names))]
+        container: Option<String>,
+
+        /// Key expression documents are compared on (e.g. c.email or LOWER(c.email)); repeat for a compound key
+        #[arg(long, value_name = "EXPR", required = true)]
+        by: Vec<String>,
+
+        /// Database name (overrides config)
+        #[arg(long, add = ArgValueCandidates::new(complete_database_names))]
+        db: Option<String>,
+
+        /// Delete every duplicate except the newest (highest _ts) of each group
+        #[arg(long)]
+        delete: bool,
+
+        /// How many documents to delete at once
+        #[arg(long, value_name = "N", default_value_t = 8)]
+        parallel: usize,
+
+        /// Skip the confirmation prompt before deleting
+        #[arg(long, short)]
+        yes: bool,
+
+        /// Delete from a container marked `protected: true` (--yes alone still asks)
+        #[arg(long)]
+        force: bool,
+    },
+
+    /// Import documents from a JSON array, NDJSON or CSV file into a
+    /// container (upserts: documents with an existing id are replaced)
+    Import {
+        /// File to import (.json, .ndjson/.jsonl or .csv)
+        file: PathBuf,
+
+        /// Database name (overrides config)
+        #[arg(long, add = ArgValueCandidates::new(complete_database_names))]
+        db: Option<String>,
+
+        /// Container name (overrides config)
+        #[arg(long, add = ArgValueCandidates::new(complete_container_names))]
+        container: Option<String>,
+
+        /// File format (default: from the extension; .json files that don't
+        /// start with `[` are read as NDJSON)
+        #[arg(long, value_enum)]
+        format: Option<ImportFileFormat>,
+
+        /// Documents read and written per batch
+        #[arg(long, value_name = "N", default_value_t = 100)]
+        batch_size: usize,
+
+        /// How many documents of a batch to write at once
+        #[arg(long, value_name = "N", default_value_t = 8)]
+        parallel: usize,
+
+        /// Give documents without an `id` a random UUID (they fail otherwise)
+        #[arg(long)]
+        generate_ids: bool,
+
+        /// Import into a container marked `protected: true` without asking
+        #[arg(long)]
+        force: bool,
+    },
+
+    /// Record cosq commands, their prompt answers and query results to a
+    /// session file (for demos, bug reports and documentation examples)
+    Record {
+        /// Session file to write
+        file: PathBuf,
+
+        /// Mask fields matching the config's `redact:` patterns in the
+        /// recorded results (the printed output is then not kept)
+        #[arg(long)]
+        redact: bool,
+    },
+
+    /// Replay a recorded session offline, with recorded answers and results
+    Replay {
+        /// Session file written by `cosq record`
+        file: PathBuf,
+
+        /// Fail if a command's output or exit code differs from the recording
+        #[arg(long)]
+        verify: bool,
+    },
+
+    /// Back up a container's documents to a file and restore them
+    Snapshot {
+        #[command(subcommand)]
+        command: SnapshotCommands,
+    },
+
+    /// Store secrets in the OS keyring (referenced from config as `keyring:<name>`)
+    Secrets {
+        #[command(subcommand)]
+        command: SecretsCommands,
+    },
+
+    /// Manage the Cosmos DB account (master keys, backups and restores)
+    Account {
+        #[command(subcommand)]
+        command: AccountCommands,
+    },
+
     /// Manage Azure authentication
     Auth {
         #[command(subcommand)]
@@ -135,8 +634,83 @@ pub enum Commands {
         command: Option<AiCommands>,
     },
 
+    /// Troubleshooting tools (bug report bundles)
+    Debug {
+        #[command(subcommand)]
+        command: DebugCommands,
+    },
+
     /// Show version information
     Version,
+
+    /// Any other subcommand runs the `cosq-<name>` plugin from PATH
+    #[command(external_subcommand)]
+    External(Vec<OsString>),
+}
+
+#[derive(clap::Subcommand)]
+pub enum PrefsCommands {
+    /// Show a container's preferences and recent queries
+    Show {
+        /// Container name (defaults to the configured container)
+        #[arg(add = ArgValueCandidates::new(complete_container_names))]
+        container: Option<String>,
+
+        /// Database name (overrides config)
+        #[arg(long, add = ArgValueCandidates::new(complete_database_names))]
+        db: Option<String>,
+    },
+    /// Set the columns, sort or output format used for a container's results
+    Set {
+        /// Container name (defaults to the configured container)
+        #[arg(add = ArgValueCandidates::new(complete_container_names))]
+        container: Option<String>,
+
+        /// Database name (overrides config)
+        #[arg(long, add = ArgValueCandidates::new(complete_database_names))]
+        db: Option<String>,
+
+        /// Columns to show in table and CSV output, in order (comma-separated; dotted paths allowed)
+        #[arg(long, value_delimiter = ',', num_args = 1..)]
+        columns: Option<Vec<String>>,
+
+        /// Sort for table and CSV output, e.g. "total desc" (ignored when the query has ORDER BY)
+        #[arg(long)]
+        sort: Option<String>,
+
+        /// Output format when neither -o, the query nor a template picks one
+        #[arg(short, long, value_enum)]
+        output: Option<OutputFormat>,
+    },
+    /// Remove a container's preferences and recent queries
+    Clear {
+        /// Container name (defaults to the configured container)
+        #[arg(add = ArgValueCandidates::new(complete_container_names))]
+        container: Option<String>,
+
+        /// Database name (overrides config)
+        #[arg(long, add = ArgValueCandidates::new(complete_database_names))]
+        db: Option<String>,
+    },
+}
+
+#[derive(clap::Subcommand)]
+pub enum TemplatesCommands {
+    /// Report template variables that are never defined and fields the SELECT never returns
+    Lint {
+        /// Query to check (default: every stored query with a template)
+        #[arg(add = ArgValueCandidates::new(complete_query_names))]
+        name: Option<String>,
+
+        /// Check this template file instead of the query's own template
+        #[arg(long, value_name = "FILE", requires = "name")]
+        template: Option<PathBuf>,
+    },
+    /// List the built-in templates (`--template builtin:<name>`), or print one's source
+    Builtins {
+        /// Template to print, e.g. markdown-table (to copy and customize)
+        name: Option<String>,
+    },
 }
 
 #[derive(clap::Subcommand)]
@@ -144,6 +718,12 @@ pub enum QueriesCommands {
     /// List all stored queries
     List,
 
+    /// Search stored query names, descriptions, tags, parameters and SQL
+    Search {
+        /// Text to look for (case-insensitive)
+        text: String,
+    },
+
     /// Create a new stored query (opens in editor)
     Create {
         /// Name for the query (becomes the .cosq filename)
@@ -172,6 +752,60 @@ pub enum QueriesCommands {
         yes: bool,
     },
 
+    /// Rename a stored query (optionally moving it between user and project scope)
+    Rename {
+        /// Name of the query to rename
+        #[arg(add = ArgValueCandidates::new(complete_query_names))]
+        name: String,
+
+        /// New name for the query
+        new_name: String,
+
+        /// Move it to the project directory (.cosq/queries/)
+        #[arg(long, conflicts_with = "user")]
+        project: bool,
+
+        /// Move it to the user directory (~/.cosq/queries/)
+        #[arg(long)]
+        user: bool,
+    },
+
+    /// Copy a stored query under a new name (optionally into the other scope)
+    Copy {
+        /// Name of the query to copy
+        #[arg(add = ArgValueCandidates::new(complete_query_names))]
+        name: String,
+
+        /// Name for the copy
+        new_name: String,
+
+        /// Write the copy to the project directory (.cosq/queries/)
+        #[arg(long, conflicts_with = "user")]
+        project: bool,
+
+        /// Write the copy to the user directory (~/.cosq/queries/)
+        #[arg(long)]
+        user: bool,
+    },
+
+    /// List the recorded versions of a stored query
+    History {
+        /// Name of the query
+        #[arg(add = ArgValueCandidates::new(complete_query_names))]
+        name: String,
+    },
+
+    /// Restore a stored query to an earlier version (see `cosq queries history`)
+    Revert {
+        /// Name of the query to revert
+        #[arg(add = ArgValueCandidates::new(complete_query_names))]
+        name: String,
+
+        /// Version to restore
+        #[arg(long)]
+        to: u32,
+    },
+
     /// Show details of a stored query
     Show {
         /// Name of the query to show
@@ -179,26 +813,272 @@ pub enum QueriesCommands {
         name: String,
     },
 
+    /// Print the step dependency graph of a multi-step query
+    Graph {
+        /// Name of the query
+        #[arg(add = ArgValueCandidates::new(complete_query_names))]
+        name: String,
+
+        /// Output format
+        #[arg(long, value_enum, default_value_t = GraphFormat::Tree)]
+        format: GraphFormat,
+    },
+
+    /// Check stored queries for common Cosmos DB pitfalls
+    Lint {
+        /// Name of the query to lint (lints all stored queries if omitted)
+        #[arg(add = ArgValueCandidates::new(complete_query_names))]
+        name: Option<String>,
+
+        /// Fetch container metadata to check partition key filters and indexing
+        #[arg(long)]
+        connect: bool,
+    },
+
     /// Generate a stored query from a natural language description (requires AI config)
     Generate {
         /// Natural language description (interactive prompt if omitted)
         description: Option<String>,
 
         /// Database name (interactive picker if omitted)
-        #[arg(long)]
+        #[arg(long, add = ArgValueCandidates::new(complete_database_names))]
         db: Option<String>,
 
-        /// Container name (interactive picker if omitted)
-        #[arg(long)]
-        container: Option<String>,
+        /// Container name(s) — repeat or comma-separate to generate a multi-step query
+        /// (interactive picker if omitted)
+        #[arg(long, value_delimiter = ',', add = ArgValueCandidates::new(complete_container_names))]
+        container: Vec<String>,
 
         /// Save to project directory (.cosq/queries/) instead of user directory
         #[arg(long)]
         project: bool,
     },
-}
 
-#[derive(clap::Subcommand)]
+    /// Encrypt a value for an `encrypted: true` parameter default (key kept in the OS keyring)
+    Encrypt {
+        /// Value to encrypt (prompted without echo if omitted)
+        value: Option<String>,
+
+        /// Parameter type the value is parsed as
+        #[arg(long = "type", value_enum, default_value_t = EncryptType::String)]
+        param_type: EncryptType,
+
+        /// Print the base64 key instead (for COSQ_ENCRYPTION_KEY on CI or another machine)
+        #[arg(long, conflicts_with = "value")]
+        export_key: bool,
+    },
+
+    /// Package a stored query as a self-contained snippet (printed, or written with -f)
+    Share {
+        /// Name of the query to share
+        #[arg(add = ArgValueCandidates::new(complete_query_names))]
+        name: String,
+
+        /// Run the query with its default parameters and bundle up to N redacted results
+        #[arg(long, value_name = "N")]
+        results: Option<usize>,
+
+        /// Write the snippet to a file instead of stdout
+        #[arg(long, short = 'f')]
+        file: Option<std::path::PathBuf>,
+    },
+
+    /// Install a shared query snippet from a URL or file
+    Install {
+        /// URL (e.g. a raw gist link) or path of the .cosq snippet
+        source: String,
+
+        /// Name to install as (defaults to the snippet's file name)
+        #[arg(long)]
+        name: Option<String>,
+
+        /// Install to project directory (.cosq/queries/) instead of user directory
+        #[arg(long)]
+        project: bool,
+
+        /// Overwrite an existing query and skip confirmation prompts
+        #[arg(long, short)]
+        yes: bool,
+    },
+}
+
+#[derive(clap::Subcommand)]
+pub enum EmulatorCommands {
+    /// Start the emulator container, wait until it is ready, and add a `local` profile
+    Start {
+        /// Number of partitions the emulator allocates
+        #[arg(long, default_value_t = 10)]
+        partitions: u32,
+
+        /// Seconds to wait for the emulator to become ready
+        #[arg(long, default_value_t = 300)]
+        timeout: u64,
+
+        /// Skip `docker pull` (use the locally cached image)
+        #[arg(long)]
+        no_pull: bool,
+    },
+
+    /// Stop the emulator container (data is kept in a Docker volume)
+    Stop {
+        /// Also remove the container
+        #[arg(long)]
+        rm: bool,
+    },
+
+    /// Show emulator container and endpoint status
+    Status,
+}
+
+#[derive(clap::Subcommand)]
+pub enum DebugCommands {
+    /// Write a .tar.gz with the sanitized config, version and environment
+    /// details, recent commands and the last command's response diagnostics
+    Bundle {
+        /// Archive to write (default: cosq-debug-<timestamp>.tar.gz)
+        #[arg(long, short)]
+        output: Option<PathBuf>,
+    },
+}
+
+#[derive(clap::Subcommand)]
+pub enum SecretsCommands {
+    /// Store (or replace) a secret
+    Set {
+        /// Secret name (referenced as `keyring:<name>`)
+        name: String,
+
+        /// Secret value (prompted without echo if omitted)
+        value: Option<String>,
+    },
+
+    /// Delete a secret
+    Delete {
+        /// Secret name
+        name: String,
+    },
+
+    /// Check that a secret is stored (without printing it)
+    Check {
+        /// Secret name
+        name: String,
+    },
+}
+
+#[derive(clap::Subcommand)]
+pub enum ScheduleCommands {
+    /// List stored queries that have a `schedule:`
+    List,
+
+    /// Render crontab lines or systemd service/timer units for scheduled queries
+    Export {
+        /// Scheduler to export for
+        #[arg(long, value_enum)]
+        format: ScheduleFormat,
+
+        /// Write unit files to this directory instead of printing them (systemd only)
+        #[arg(long)]
+        dir: Option<std::path::PathBuf>,
+
+        /// Only export these queries (default: all scheduled queries)
+        #[arg(add = ArgValueCandidates::new(complete_query_names))]
+        names: Vec<String>,
+    },
+}
+
+/// Request priority for accounts with priority-based execution
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum Priority {
+    Low,
+    High,
+}
+
+impl From<Priority> for PriorityLevel {
+    fn from(p: Priority) -> Self {
+        match p {
+            Priority::Low => PriorityLevel::Low,
+            Priority::High => PriorityLevel::High,
+        }
+    }
+}
+
+/// Output formats for `cosq queries graph`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum GraphFormat {
+    /// ASCII tree grouped by execution layer
+    #[default]
+    Tree,
+    /// Graphviz DOT (pipe into `dot -Tsvg`)
+    Dot,
+}
+
+/// Output of `cosq init`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum InitOutput {
+    /// Human-readable summary
+    #[default]
+    Text,
+    /// The saved config as JSON (for provisioning scripts)
+    Json,
+}
+
+/// Output formats for `cosq schema`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum SchemaFormat {
+    /// Field table with types, presence and examples
+    #[default]
+    Table,
+    /// The inferred schema as JSON (same as the cache file)
+    Json,
+    /// JSON Schema (draft 2020-12)
+    JsonSchema,
+    /// TypeScript interfaces
+    Typescript,
+    /// Rust structs with serde derives
+    Rust,
+}
+
+/// Report formats for `cosq inventory` and `cosq auth audit`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum ReportFormat {
+    /// Table with a summary line
+    #[default]
+    Table,
+    /// The full report as JSON
+    Json,
+}
+
+/// File formats for `cosq import`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum ImportFileFormat {
+    /// One JSON array of documents
+    Json,
+    /// One JSON document per line
+    Ndjson,
+    /// A header row, then one document per record
+    Csv,
+}
+
+impl From<ImportFileFormat> for ImportFormat {
+    fn from(format: ImportFileFormat) -> Self {
+        match format {
+            ImportFileFormat::Json => ImportFormat::Json,
+            ImportFileFormat::Ndjson => ImportFormat::Ndjson,
+            ImportFileFormat::Csv => ImportFormat::Csv,
+        }
+    }
+}
+
+/// Host scheduler formats for `cosq schedule export`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum ScheduleFormat {
+    /// systemd service + timer units
+    Systemd,
+    /// crontab lines
+    Cron,
+}
+
+#[derive(clap::Subcommand)]
 pub enum AiCommands {
     /// Test AI integration by sending a message
     Test {
@@ -211,6 +1091,353 @@ pub enum AiCommands {
     Disable,
     /// Open AI configuration file in your editor
     Config,
+    /// Show AI token usage per month (and against `ai_budget:` in the config)
+    Usage {
+        /// Number of recent months to show
+        #[arg(long, default_value_t = 6)]
+        months: usize,
+    },
+    /// List, pull and delete Ollama models
+    Models {
+        #[command(subcommand)]
+        command: Option<ModelsCommands>,
+
+        /// Ollama server (default: the configured Ollama node, `OLLAMA_HOST`, or localhost)
+        #[arg(long, global = true)]
+        endpoint: Option<String>,
+    },
+    /// Show transcripts of AI requests (enable with `ai_log: true` in the config)
+    Log {
+        /// Number of most recent requests to show
+        #[arg(long, default_value_t = 10)]
+        last: usize,
+
+        /// Print complete prompts and responses instead of previews
+        #[arg(long)]
+        full: bool,
+    },
+}
+
+#[derive(clap::Subcommand)]
+pub enum ModelsCommands {
+    /// List installed models (the default)
+    List,
+    /// Download a model, showing progress
+    Pull {
+        /// Model name (e.g. `llama3.2` or `qwen2.5-coder:7b`)
+        model: String,
+    },
+    /// Delete an installed model
+    Delete {
+        /// Model name
+        model: String,
+
+        /// Skip confirmation prompt
+        #[arg(long, short)]
+        yes: bool,
+    },
+}
+
+#[derive(clap::Subcommand)]
+pub enum DocsCommands {
+    /// Fetch one document by id and partition key (a point read, ~1 RU)
+    Get {
+        /// Document id
+        id: String,
+
+        /// Partition key value (JSON scalars like 42 or true keep their type)
+        #[arg(long)]
+        pk: String,
+
+        /// Database name (overrides config)
+        #[arg(long, add = ArgValueCandidates::new(complete_database_names))]
+        db: Option<String>,
+
+        /// Container name (overrides config)
+        #[arg(long, add = ArgValueCandidates::new(complete_container_names))]
+        container: Option<String>,
+    },
+
+    /// Delete one document by id and partition key (asks first)
+    Delete {
+        /// Document id
+        id: String,
+
+        /// Partition key value (JSON scalars like 42 or true keep their type)
+        #[arg(long)]
+        pk: String,
+
+        /// Database name (overrides config)
+        #[arg(long, add = ArgValueCandidates::new(complete_database_names))]
+        db: Option<String>,
+
+        /// Container name (overrides config)
+        #[arg(long, add = ArgValueCandidates::new(complete_container_names))]
+        container: Option<String>,
+
+        /// Delete without asking for confirmation
+        #[arg(long, short)]
+        yes: bool,
+
+        /// Delete from a container marked `protected: true` (--yes alone
+        /// still asks)
+        #[arg(long)]
+        force: bool,
+    },
+
+    /// Create a document from a JSON file, stdin or an inline string
+    Create {
+        /// The document as inline JSON (default: read --file, or stdin)
+        #[arg(conflicts_with = "file")]
+        json: Option<String>,
+
+        /// Read the document from a file (`-` for stdin)
+        #[arg(long, short = 'f')]
+        file: Option<PathBuf>,
+
+        /// Database name (overrides config)
+        #[arg(long, add = ArgValueCandidates::new(complete_database_names))]
+        db: Option<String>,
+
+        /// Container name (overrides config)
+        #[arg(long, add = ArgValueCandidates::new(complete_container_names))]
+        container: Option<String>,
+
+        /// Create a document in a container marked `protected: true` without
+        /// asking
+        #[arg(long)]
+        force: bool,
+    },
+
+    /// Update parts of a document in place without rewriting it, e.g.
+    /// `--set /status=shipped --remove /tempField` (operations run in the order
+    /// --add, --set, --replace, --incr, --remove, all or none)
+    Patch {
+        /// Document id
+        id: String,
+
+        /// Partition key value (JSON scalars like 42 or true keep their type)
+        #[arg(long)]
+        pk: String,
+
+        /// Database name (overrides config)
+        #[arg(long, add = ArgValueCandidates::new(complete_database_names))]
+        db: Option<String>,
+
+        /// Container name (overrides config)
+        #[arg(long, add = ArgValueCandidates::new(complete_container_names))]
+        container: Option<String>,
+
+        /// Add a property, or insert into an array (`/tags/-` appends)
+        #[arg(long, value_name = "PATH=VALUE")]
+        add: Vec<String>,
+
+        /// Set a property, creating it if missing (JSON values like 42, true
+        /// or {"a":1} keep their type; anything else is a string)
+        #[arg(long, value_name = "PATH=VALUE")]
+        set: Vec<String>,
+
+        /// Replace a property that must already exist
+        #[arg(long, value_name = "PATH=VALUE")]
+        replace: Vec<String>,
+
+        /// Add a number to a property (negative to subtract)
+        #[arg(long, value_name = "PATH=N")]
+        incr: Vec<String>,
+
+        /// Remove a property that must exist
+        #[arg(long, value_name = "PATH")]
+        remove: Vec<String>,
+
+        /// Patch a document in a container marked `protected: true` without
+        /// asking
+        #[arg(long)]
+        force: bool,
+    },
+
+    /// Open a document in $EDITOR and replace it (fails if it changed meanwhile)
+    Edit {
+        /// Document id
+        id: String,
+
+        /// Partition key value (JSON scalars like 42 or true keep their type)
+        #[arg(long)]
+        pk: String,
+
+        /// Database name (overrides config)
+        #[arg(long, add = ArgValueCandidates::new(complete_database_names))]
+        db: Option<String>,
+
+        /// Container name (overrides config)
+        #[arg(long, add = ArgValueCandidates::new(complete_container_names))]
+        container: Option<String>,
+
+        /// Replace without asking for confirmation after the diff
+        #[arg(long, short)]
+        yes: bool,
+
+        /// Replace a document in a container marked `protected: true` (--yes
+        /// alone still asks)
+        #[arg(long)]
+        force: bool,
+    },
+
+    /// Delete every document a query matches, e.g.
+    /// `"SELECT * FROM c WHERE c.status = 'expired'"` (asks first)
+    DeleteWhere {
+        /// SELECT query choosing the documents (its projection is ignored)
+        sql: String,
+
+        /// Database name (overrides config)
+        #[arg(long, add = ArgValueCandidates::new(complete_database_names))]
+        db: Option<String>,
+
+        /// Container name (overrides config)
+        #[arg(long, add = ArgValueCandidates::new(complete_container_names))]
+        container: Option<String>,
+
+        /// Only count the matching documents
+        #[arg(long)]
+        dry_run: bool,
+
+        /// How many documents to delete at once
+        #[arg(long, value_name = "N", default_value_t = 8)]
+        parallel: usize,
+
+        /// Delete without asking for confirmation
+        #[arg(long, short)]
+        yes: bool,
+
+        /// Delete from a container marked `protected: true` (--yes alone
+        /// still asks)
+        #[arg(long)]
+        force: bool,
+    },
+}
+
+#[derive(clap::Subcommand)]
+pub enum ContainerCommands {
+    /// Show a container's partition key, TTL, indexing, analytical store and throughput
+    Show {
+        /// Container name (defaults to the configured container)
+        #[arg(add = ArgValueCandidates::new(complete_container_names))]
+        container: Option<String>,
+
+        /// Database name (overrides config)
+        #[arg(long, add = ArgValueCandidates::new(complete_database_names))]
+        db: Option<String>,
+    },
+
+    /// Report the most frequent partition key values and how skewed they are
+    Keys {
+        /// Container name (defaults to the configured container)
+        #[arg(add = ArgValueCandidates::new(complete_container_names))]
+        container: Option<String>,
+
+        /// Database name (overrides config)
+        #[arg(long, add = ArgValueCandidates::new(complete_database_names))]
+        db: Option<String>,
+
+        /// How many of the most frequent values to list
+        #[arg(long, value_name = "N", default_value_t = 20)]
+        top: usize,
+
+        /// Count the keys of the first N documents instead of running a GROUP BY over all of them
+        #[arg(long, value_name = "N")]
+        sample: Option<usize>,
+    },
+}
+
+#[derive(clap::Subcommand)]
+pub enum SnapshotCommands {
+    /// Write every document of a container to a snapshot file with a manifest
+    Create {
+        /// Container to snapshot (defaults to the configured container)
+        #[arg(add = ArgValueCandidates::new(complete_container_names))]
+        container: Option<String>,
+
+        /// Database name (overrides config)
+        #[arg(long, add = ArgValueCandidates::new(complete_database_names))]
+        db: Option<String>,
+
+        /// Snapshot file (default: <container>-<UTC time>.snapshot.json)
+        #[arg(long, short = 'f')]
+        file: Option<PathBuf>,
+    },
+
+    /// Verify a snapshot file and upsert its documents into a container
+    Restore {
+        /// Snapshot file written by `cosq snapshot create`
+        file: PathBuf,
+
+        /// Container to restore into
+        #[arg(long, add = ArgValueCandidates::new(complete_container_names))]
+        to: String,
+
+        /// Database name (overrides config)
+        #[arg(long, add = ArgValueCandidates::new(complete_database_names))]
+        db: Option<String>,
+
+        /// How many documents to upsert at once
+        #[arg(long, value_name = "N", default_value_t = 8)]
+        parallel: usize,
+
+        /// Restore without asking for confirmation
+        #[arg(long, short)]
+        yes: bool,
+
+        /// Restore into a container marked `protected: true` (--yes alone still asks)
+        #[arg(long)]
+        force: bool,
+    },
+}
+
+#[derive(clap::Subcommand)]
+pub enum AccountCommands {
+    /// Show the account's location, capacity mode (serverless or provisioned RU/s) and backup policy
+    Show {
+        /// Database whose throughput to show (defaults to the configured database)
+        #[arg(long, add = ArgValueCandidates::new(complete_database_names))]
+        db: Option<String>,
+        /// Container whose throughput to show (defaults to the configured container)
+        #[arg(long, add = ArgValueCandidates::new(complete_container_names))]
+        container: Option<String>,
+    },
+    /// Show the account's master keys, or regenerate one (via Azure Resource Manager)
+    Keys {
+        /// Regenerate this key; clients still using it stop working
+        #[arg(long, value_enum)]
+        regenerate: Option<KeyKind>,
+        /// Skip the confirmation prompt when regenerating
+        #[arg(long, short = 'y')]
+        yes: bool,
+    },
+    /// Restore the account as of a point in time into a new account (continuous backup only)
+    Restore {
+        /// Point in time to restore to, in UTC (e.g. 2026-10-15T08:00:00Z)
+        #[arg(long, value_name = "TIMESTAMP")]
+        to: String,
+        /// Name of the new account to create
+        #[arg(long, value_name = "ACCOUNT")]
+        target: String,
+        /// Resource group of the new account (defaults to the source account's)
+        #[arg(long)]
+        resource_group: Option<String>,
+        /// Skip the confirmation prompt
+        #[arg(long, short = 'y')]
+        yes: bool,
+    },
+    /// Inspect the account's backup policy
+    BackupPolicy {
+        #[command(subcommand)]
+        command: BackupPolicyCommands,
+    },
+}
+
+#[derive(clap::Subcommand)]
+pub enum BackupPolicyCommands {
+    /// Show the backup mode, retention and earliest restore point
+    Show,
 }
 
 #[derive(clap::Subcommand)]
@@ -221,6 +1448,88 @@ pub enum AuthCommands {
     Login,
     /// Logout from Azure
     Logout,
+    /// Create a short-lived resource token scoped to one container
+    ResourceToken {
+        /// Container the token grants access to (defaults to configured container)
+        #[arg(long, add = ArgValueCandidates::new(complete_container_names))]
+        container: Option<String>,
+        /// Database name (defaults to configured database)
+        #[arg(long, add = ArgValueCandidates::new(complete_database_names))]
+        db: Option<String>,
+        /// Restrict the token to one partition key value
+        #[arg(long)]
+        pk: Option<String>,
+        /// Access granted by the token
+        #[arg(long, value_enum, default_value_t = TokenPermission::Read)]
+        permission: TokenPermission,
+        /// Token lifetime, e.g. 30m, 1h (max 5h)
+        #[arg(long, default_value = "1h")]
+        ttl: String,
+        /// Database user the permission is created under
+        #[arg(long, default_value = "cosq")]
+        user: String,
+    },
+    /// List the account's data plane role assignments and flag overly broad ones
+    Audit {
+        /// Report format
+        #[arg(long, short = 'o', value_enum, default_value_t = ReportFormat::Table)]
+        output: ReportFormat,
+    },
+}
+
+/// Value types for `cosq queries encrypt`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum EncryptType {
+    String,
+    Number,
+    Bool,
+}
+
+impl From<EncryptType> for ParamType {
+    fn from(t: EncryptType) -> Self {
+        match t {
+            EncryptType::String => ParamType::String,
+            EncryptType::Number => ParamType::Number,
+            EncryptType::Bool => ParamType::Bool,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum TokenPermission {
+    /// Read documents
+    Read,
+    /// Read, write and delete documents
+    All,
+}
+
+impl From<TokenPermission> for PermissionMode {
+    fn from(p: TokenPermission) -> Self {
+        match p {
+            TokenPermission::Read => PermissionMode::Read,
+            TokenPermission::All => PermissionMode::All,
+        }
+    }
+}
+
+/// Account keys for `cosq account keys --regenerate`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum KeyKind {
+    Primary,
+    Secondary,
+    PrimaryReadonly,
+    SecondaryReadonly,
+}
+
+impl From<KeyKind> for AccountKeyKind {
+    fn from(k: KeyKind) -> Self {
+        match k {
+            KeyKind::Primary => AccountKeyKind::Primary,
+            KeyKind::Secondary => AccountKeyKind::Secondary,
+            KeyKind::PrimaryReadonly => AccountKeyKind::PrimaryReadonly,
+            KeyKind::SecondaryReadonly => AccountKeyKind::SecondaryReadonly,
+        }
+    }
 }
 
 #[derive(Clone, clap::ValueEnum)]
@@ -236,61 +1545,282 @@ impl Cli {
         match self.command {
             Some(Commands::Query {
                 sql,
+                interactive,
                 db,
                 container,
                 output,
                 template,
+                template_debug,
+                chart,
+                out,
+                table,
+                replace,
+                post_sql,
+                jq,
+                head,
+                tail,
+                priority,
+                targets,
+                from_file,
+                force,
             }) => {
                 crate::commands::query::run(crate::commands::query::QueryArgs {
-                    sql,
+                    sql: sql.unwrap_or_default(),
+                    interactive,
                     db,
                     container,
                     output,
                     template,
+                    template_debug,
+                    chart,
+                    out,
+                    table,
+                    replace,
+                    post_sql,
+                    jq,
+                    limit: RowLimit::from_flags(head, tail),
+                    priority: priority.map(Into::into),
+                    targets,
+                    from_file,
+                    force,
+                    profile: self.profile.clone(),
+                    redact: self.redact,
                     quiet: self.quiet,
                 })
                 .await
             }
             Some(Commands::Run {
                 name,
+                all,
+                parallel,
+                out_dir,
                 db,
                 container,
                 output,
                 template,
+                template_debug,
+                chart,
+                run_hooks,
+                no_hooks,
+                notify,
+                out,
+                table,
+                replace,
+                post_sql,
+                jq,
+                head,
+                tail,
+                priority,
+                targets,
+                from_file,
+                save_steps,
+                from_step,
+                steps_dir,
+                plan,
+                force,
+                check,
                 params,
             }) => {
-                crate::commands::run::run(crate::commands::run::RunArgs {
+                let args = crate::commands::run::RunArgs {
                     name,
                     params,
+                    prompt: true,
                     output,
                     db,
                     container,
                     template,
+                    template_debug,
+                    chart,
+                    hooks: crate::commands::hooks::HookPolicy::from_flags(run_hooks, no_hooks),
+                    notify,
+                    out,
+                    table,
+                    replace,
+                    post_sql,
+                    jq,
+                    limit: RowLimit::from_flags(head, tail),
+                    priority: priority.map(Into::into),
+                    targets,
+                    from_file,
+                    save_steps,
+                    resume: from_step.zip(steps_dir),
+                    plan,
+                    force,
+                    check,
+                    profile: self.profile.clone(),
+                    redact: self.redact,
                     quiet: self.quiet,
-                })
-                .await
+                };
+                match all {
+                    Some(pattern) => {
+                        crate::commands::batch::run(&pattern, parallel, &out_dir, args).await
+                    }
+                    None => crate::commands::run::run(args).await,
+                }
             }
+            Some(Commands::Templates { command }) => crate::commands::templates::run(command),
+            Some(Commands::Render {
+                template,
+                data,
+                params,
+                rows,
+                template_debug,
+            }) => crate::commands::render::run(crate::commands::render::RenderArgs {
+                template,
+                data,
+                params,
+                rows,
+                template_debug,
+            }),
             Some(Commands::Queries { command }) => {
-                crate::commands::queries::run(command, self.quiet).await
+                crate::commands::queries::run(command, self.profile.clone(), self.quiet).await
             }
             Some(Commands::Init {
                 account,
                 subscription,
+                endpoint,
+                database,
+                container,
+                no_rbac_check,
+                output,
                 yes,
             }) => {
                 crate::commands::init::run(crate::commands::init::InitArgs {
                     account,
                     subscription,
+                    endpoint,
+                    database,
+                    container,
+                    no_rbac_check,
+                    output,
                     yes,
                 })
                 .await
             }
-            Some(Commands::Auth { command }) => crate::commands::auth::run(command).await,
+            Some(Commands::Emulator { command }) => crate::commands::emulator::run(command).await,
+            Some(Commands::Doctor { db, container }) => {
+                crate::commands::doctor::run(crate::commands::doctor::DoctorArgs {
+                    db,
+                    container,
+                    profile: self.profile.clone(),
+                })
+                .await
+            }
+            Some(Commands::Schema {
+                container,
+                db,
+                sample,
+                format,
+            }) => {
+                crate::commands::schema::run(crate::commands::schema::SchemaArgs {
+                    container,
+                    db,
+                    sample,
+                    format,
+                    profile: self.profile.clone(),
+                    quiet: self.quiet,
+                })
+                .await
+            }
+            Some(Commands::Prefs { command }) => {
+                crate::commands::prefs::run(command, self.profile.as_deref())
+            }
+            Some(Commands::Stats { reset }) => crate::commands::stats::run(reset),
+            Some(Commands::Schedule { command }) => crate::commands::schedule::run(command),
+            Some(Commands::Secrets { command }) => {
+                crate::commands::secrets::run(command, self.quiet)
+            }
+            Some(Commands::Docs { command }) => {
+                crate::commands::docs::run(command, self.profile.clone(), self.quiet).await
+            }
+            Some(Commands::Container { command }) => {
+                crate::commands::container::run(command, self.profile.clone(), self.quiet).await
+            }
+            Some(Commands::Inventory {
+                discover,
+                subscription,
+                output,
+                parallel,
+            }) => {
+                crate::commands::inventory::run(
+                    discover,
+                    subscription,
+                    output,
+                    parallel,
+                    self.quiet,
+                )
+                .await
+            }
+            Some(Commands::Dedupe {
+                container,
+                by,
+                db,
+                delete,
+                parallel,
+                yes,
+                force,
+            }) => {
+                crate::commands::dedupe::run(crate::commands::dedupe::DedupeArgs {
+                    container,
+                    by,
+                    db,
+                    delete,
+                    parallel,
+                    yes,
+                    force,
+                    profile: self.profile.clone(),
+                    quiet: self.quiet,
+                })
+                .await
+            }
+            Some(Commands::Import {
+                file,
+                db,
+                container,
+                format,
+                batch_size,
+                parallel,
+                generate_ids,
+                force,
+            }) => {
+                crate::commands::import::run(crate::commands::import::ImportArgs {
+                    file,
+                    db,
+                    container,
+                    format,
+                    batch_size,
+                    parallel,
+                    generate_ids,
+                    force,
+                    profile: self.profile.clone(),
+                    quiet: self.quiet,
+                })
+                .await
+            }
+            Some(Commands::Record { file, redact }) => {
+                crate::commands::session::record(&file, redact)
+            }
+            Some(Commands::Replay { file, verify }) => {
+                crate::commands::session::replay_session(&file, verify, self.quiet)
+            }
+            Some(Commands::Snapshot { command }) => {
+                crate::commands::snapshot::run(command, self.profile.clone(), self.quiet).await
+            }
+            Some(Commands::Account { command }) => {
+                crate::commands::account::run(command, self.profile.clone(), self.quiet).await
+            }
+            Some(Commands::Auth { command }) => {
+                crate::commands::auth::run(command, self.profile.clone(), self.quiet).await
+            }
             Some(Commands::Ai { command }) => crate::commands::ai::run(command).await,
             Some(Commands::Completion { shell }) => {
                 crate::commands::completion::generate_completions(shell);
                 Ok(())
             }
+            Some(Commands::External(args)) => {
+                crate::commands::plugin::run(args, self.profile.clone()).await
+            }
+            Some(Commands::Debug { command }) => crate::commands::debug::run(command, self.quiet),
             Some(Commands::Version) => {
                 crate::banner::print_banner_with_version();
                 Ok(())
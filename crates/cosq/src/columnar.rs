@@ -0,0 +1,158 @@
+//! Columnar (Apache Arrow) results
+//!
+//! `-o arrow` writes the results as an Arrow IPC file, with a schema inferred
+//! from the documents, for pandas/polars/DuckDB. Builds with the `datafusion`
+//! feature can also run a local SQL statement over the results
+//! (`--post-sql`), for the aggregations Cosmos DB SQL can't do.
+
+use std::io::Write;
+use std::sync::Arc;
+
+use anyhow::{Context, Result};
+use arrow::error::ArrowError;
+use arrow::ipc::writer::FileWriter;
+use arrow::json::ReaderBuilder;
+use arrow::json::reader::infer_json_schema_from_iterator;
+use arrow::record_batch::RecordBatch;
+use serde_json::{Map, Value};
+
+/// Column holding results that aren't objects (e.g. `SELECT VALUE c.total`)
+pub const VALUE_COLUMN: &str = "value";
+
+/// Table name of the results in `--post-sql`
+#[cfg(feature = "datafusion")]
+const RESULTS_TABLE: &str = "results";
+
+/// The results as one record batch. Fields with values of different types
+/// become strings; fields missing from a document are null.
+pub fn record_batch(documents: &[Value]) -> Result<RecordBatch> {
+    let rows: Vec<Value> = documents
+        .iter()
+        .map(|doc| match doc {
+            Value::Object(_) => doc.clone(),
+            other => Value::Object(Map::from_iter([(VALUE_COLUMN.to_string(), other.clone())])),
+        })
+        .collect();
+    let schema = Arc::new(
+        infer_json_schema_from_iterator(rows.iter().map(Ok::<_, ArrowError>))
+            .context("failed to infer an Arrow schema from the results")?,
+    );
+    let mut decoder = ReaderBuilder::new(schema.clone())
+        .with_coerce_primitive(true)
+        .build_decoder()?;
+    decoder
+        .serialize(&rows)
+        .context("failed to convert the results to Arrow")?;
+    Ok(decoder
+        .flush()?
+        .unwrap_or_else(|| RecordBatch::new_empty(schema)))
+}
+
+/// Write documents as an Arrow IPC file.
+pub fn write_arrow(writer: &mut dyn Write, documents: &[Value]) -> Result<()> {
+    let batch = record_batch(documents)?;
+    let mut file = FileWriter::try_new(writer, &batch.schema())?;
+    file.write(&batch)?;
+    file.finish()?;
+    Ok(())
+}
+
+/// Run `sql` over the results, registered as the table `results`, and return
+/// the rows it produces.
+#[cfg(feature = "datafusion")]
+pub async fn post_sql(sql: &str, documents: Vec<Value>) -> Result<Vec<Value>> {
+    use datafusion::prelude::SessionContext;
+
+    let ctx = SessionContext::new();
+    ctx.register_batch(RESULTS_TABLE, record_batch(&documents)?)?;
+    let batches = ctx
+        .sql(sql)
+        .await
+        .context("--post-sql failed")?
+        .collect()
+        .await
+        .context("--post-sql failed")?;
+
+    let mut writer = arrow::json::ArrayWriter::new(Vec::new());
+    writer.write_batches(&batches.iter().collect::<Vec<_>>())?;
+    writer.finish()?;
+    let json = writer.into_inner();
+    if json.is_empty() {
+        return Ok(Vec::new());
+    }
+    Ok(serde_json::from_slice(&json)?)
+}
+
+#[cfg(not(feature = "datafusion"))]
+pub async fn post_sql(_sql: &str, _documents: Vec<Value>) -> Result<Vec<Value>> {
+    anyhow::bail!(
+        "--post-sql needs a cosq built with the `datafusion` feature \
+         (cargo install cosq --features datafusion)"
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use arrow::datatypes::DataType;
+    use arrow::ipc::reader::FileReader;
+    use serde_json::json;
+
+    #[test]
+    fn test_record_batch() {
+        let docs = vec![
+            json!({"id": "1", "total": 10, "status": "paid", "code": 7}),
+            json!({"id": "2", "total": 2.5, "code": "A7"}),
+        ];
+        let batch = record_batch(&docs).unwrap();
+        assert_eq!(batch.num_rows(), 2);
+        let schema = batch.schema();
+        assert_eq!(
+            schema.field_with_name("total").unwrap().data_type(),
+            &DataType::Float64
+        );
+        // Mixed number/string values become strings
+        assert_eq!(
+            schema.field_with_name("code").unwrap().data_type(),
+            &DataType::Utf8
+        );
+        assert_eq!(batch.column_by_name("status").unwrap().null_count(), 1);
+
+        let scalars = record_batch(&[json!(1), json!(2)]).unwrap();
+        assert!(scalars.column_by_name(VALUE_COLUMN).is_some());
+        assert_eq!(record_batch(&[]).unwrap().num_rows(), 0);
+    }
+
+    #[test]
+    fn test_write_arrow() {
+        let mut buf = Vec::new();
+        write_arrow(&mut buf, &[json!({"id": "1", "n": 3})]).unwrap();
+        let reader = FileReader::try_new(std::io::Cursor::new(buf), None).unwrap();
+        let batches: Vec<RecordBatch> = reader.map(|b| b.unwrap()).collect();
+        assert_eq!(batches[0].num_rows(), 1);
+        assert_eq!(batches[0].schema().field(1).name(), "n");
+    }
+
+    #[cfg(feature = "datafusion")]
+    #[tokio::test]
+    async fn test_post_sql() {
+        let docs = vec![
+            json!({"status": "paid", "total": 10}),
+            json!({"status": "paid", "total": 5}),
+            json!({"status": "open", "total": 1}),
+        ];
+        let rows = post_sql(
+            "SELECT status, count(*) AS n, sum(total) AS total FROM results GROUP BY status ORDER BY status",
+            docs,
+        )
+        .await
+        .unwrap();
+        assert_eq!(
+            rows,
+            vec![
+                json!({"status": "open", "n": 1, "total": 1}),
+                json!({"status": "paid", "n": 2, "total": 15}),
+            ]
+        );
+    }
+}
@@ -0,0 +1,557 @@
+//! Account management commands
+//!
+//! `cosq account show` reports how the account is billed (serverless or
+//! provisioned/autoscale RU/s) and remembers it for RU-budget advice.
+//! `cosq account keys` reads the account's master keys through Azure Resource
+//! Manager, and `--regenerate` rotates one of them — for key-based consumers
+//! that need rotation without switching to the az CLI. `cosq account restore`
+//! starts a point-in-time restore into a new account and `cosq account
+//! backup-policy show` reports how far back that can go.
+
+use std::io::IsTerminal;
+
+use anyhow::{Context, Result, bail};
+use chrono::{DateTime, NaiveDateTime, Utc};
+use colored::Colorize;
+use cosq_client::arm::{
+    AccountDetails, AccountKeyKind, AccountKeys, ArmClient, RestorableAccount, Throughput,
+    account_resource_id, restorable_account_id,
+};
+use cosq_core::config::{CapacityMode, Config};
+use cosq_core::secrets;
+
+use crate::cli::{AccountCommands, BackupPolicyCommands};
+
+pub async fn run(cmd: AccountCommands, profile: Option<String>, quiet: bool) -> Result<()> {
+    match cmd {
+        AccountCommands::Show { db, container } => show(db, container, profile).await,
+        AccountCommands::Keys { regenerate, yes } => {
+            keys(regenerate.map(Into::into), yes, profile, quiet).await
+        }
+        AccountCommands::Restore {
+            to,
+            target,
+            resource_group,
+            yes,
+        } => restore(&to, &target, resource_group, yes, profile, quiet).await,
+        AccountCommands::BackupPolicy {
+            command: BackupPolicyCommands::Show,
+        } => backup_policy(profile).await,
+    }
+}
+
+/// Load the config and the ARM resource ID of its account.
+pub fn load_account(what: &str, profile: Option<&str>) -> Result<(Config, String)> {
+    super::common::ensure_online(what)?;
+    let config = match profile {
+        Some(profile) => Config::load()?.for_profile(profile)?,
+        None => Config::load()?,
+    };
+    let account = &config.account;
+    if account.subscription.is_empty() || account.resource_group.is_empty() {
+        bail!(
+            "account '{}' has no subscription/resource group configured — run `cosq init` to select it",
+            account.name
+        );
+    }
+    let id = account_resource_id(
+        &account.subscription,
+        &account.resource_group,
+        &account.name,
+    );
+    Ok((config, id))
+}
+
+async fn show(
+    db: Option<String>,
+    container: Option<String>,
+    profile: Option<String>,
+) -> Result<()> {
+    let (mut config, id) = load_account("cosq account show", profile.as_deref())?;
+    let arm = ArmClient::new().await?;
+    let details = arm
+        .get_account(&id)
+        .await
+        .with_context(|| format!("failed to read account '{}'", config.account.name))?;
+    let mode = if details.is_serverless() {
+        CapacityMode::Serverless
+    } else {
+        CapacityMode::Provisioned
+    };
+
+    println!("  {:<18} {}", "Account:".bold(), details.name);
+    println!("  {:<18} {}", "Endpoint:".bold(), config.account.endpoint);
+    println!("  {:<18} {}", "Location:".bold(), details.location);
+    if let Some(ref kind) = details.kind {
+        println!("  {:<18} {kind}", "Kind:".bold());
+    }
+    let policy = &details.properties.backup_policy;
+    if !policy.policy_type.is_empty() {
+        println!("  {:<18} {}", "Backup policy:".bold(), policy.policy_type);
+    }
+    println!(
+        "  {:<18} {}",
+        "Synapse Link:".bold(),
+        if details.properties.enable_analytical_storage {
+            "enabled (see `cosq container show` for each container's analytical store)"
+        } else {
+            "disabled"
+        }
+    );
+
+    match mode {
+        CapacityMode::Serverless => println!(
+            "  {:<18} {} (billed per request unit consumed)",
+            "Capacity mode:".bold(),
+            "Serverless".green()
+        ),
+        CapacityMode::Provisioned => {
+            println!(
+                "  {:<18} {}",
+                "Capacity mode:".bold(),
+                "Provisioned throughput".green()
+            );
+            let database = db.or_else(|| config.database.clone());
+            let container = container.or_else(|| config.container.clone());
+            if let Some(ref database) = database {
+                print_throughput(&arm, &id, database, container.as_deref()).await?;
+            }
+        }
+    }
+
+    if let Some(ref pricing) = config.pricing {
+        let mismatch = match mode {
+            CapacityMode::Serverless => pricing.per_100_rus_hour.is_some().then_some(
+                "`pricing.per_100_rus_hour` is set, but serverless accounts are billed per million RUs (`per_million_ru`)",
+            ),
+            CapacityMode::Provisioned => pricing.per_million_ru.is_some().then_some(
+                "`pricing.per_million_ru` is a serverless price; provisioned accounts are billed per 100 RU/s per hour (`per_100_rus_hour`)",
+            ),
+        };
+        if let Some(mismatch) = mismatch {
+            eprintln!("\n{} {mismatch}", "Warning:".yellow().bold());
+        }
+    }
+
+    // Remember the mode for RU-budget advice; profiles are kept as configured
+    if profile.is_none() && config.account.capacity_mode != Some(mode) {
+        config.account.capacity_mode = Some(mode);
+        config.save()?;
+    }
+    Ok(())
+}
+
+/// Print the throughput serving `container`: its own, or the database's
+/// shared throughput when the container has none.
+async fn print_throughput(
+    arm: &ArmClient,
+    id: &str,
+    database: &str,
+    container: Option<&str>,
+) -> Result<()> {
+    if let Some(container) = container {
+        if let Some(throughput) = arm.get_throughput(id, database, Some(container)).await? {
+            println!(
+                "  {:<18} {} ({database}/{container})",
+                "Throughput:".bold(),
+                throughput_label(throughput)
+            );
+            return Ok(());
+        }
+    }
+    match arm.get_throughput(id, database, None).await? {
+        Some(throughput) => println!(
+            "  {:<18} {} (shared by the containers of {database})",
+            "Throughput:".bold(),
+            throughput_label(throughput)
+        ),
+        None => println!(
+            "  {:<18} {}",
+            "Throughput:".bold(),
+            "none provisioned on the database; pass --container to show a container's".dimmed()
+        ),
+    }
+    Ok(())
+}
+
+pub fn throughput_label(throughput: Throughput) -> String {
+    match (throughput.autoscale_max, throughput.manual) {
+        (Some(max), _) => format!("autoscale {} - {max} RU/s", max / 10),
+        (None, Some(rus)) => format!("{rus} RU/s (manual)"),
+        (None, None) => "unknown".to_string(),
+    }
+}
+
+async fn keys(
+    regenerate: Option<AccountKeyKind>,
+    yes: bool,
+    profile: Option<String>,
+    quiet: bool,
+) -> Result<()> {
+    let (config, id) = load_account("cosq account keys", profile.as_deref())?;
+    let account = &config.account;
+    let arm = ArmClient::new().await?;
+    let keys = arm
+        .list_keys(&id)
+        .await
+        .with_context(|| format!("failed to list keys of account '{}'", account.name))?;
+
+    let Some(kind) = regenerate else {
+        eprintln!(
+            "{} these keys grant access to every database in '{}' (full access for the read-write ones). Don't paste them into chats or commit them; prefer Entra ID (RBAC) auth where you can.",
+            "Warning:".yellow().bold(),
+            account.name
+        );
+        print_keys(&keys);
+        return Ok(());
+    };
+
+    // The key cosq itself authenticates with, if it is the one being rotated
+    let own_key = account
+        .master_key
+        .as_deref()
+        .and_then(|key| secrets::resolve(key).ok())
+        .is_some_and(|key| keys.kind_of(&key) == Some(kind));
+
+    eprintln!(
+        "{} regenerating the {} key of '{}' invalidates it immediately: every application, connection string and script using it fails until it is updated.",
+        "Warning:".yellow().bold(),
+        key_label(kind),
+        account.name
+    );
+    if !kind.is_readonly() {
+        eprintln!("  Rotate one key at a time: move consumers to the other read-write key first.");
+    }
+    if own_key {
+        eprintln!(
+            "  cosq's own `master_key:` for this account is this key — update it afterwards."
+        );
+    }
+    if !yes {
+        if !std::io::stdin().is_terminal() {
+            bail!("refusing to regenerate a key without confirmation — pass --yes");
+        }
+        let confirmed = inquire::Confirm::new(&format!(
+            "Regenerate the {} key of '{}'?",
+            key_label(kind),
+            account.name
+        ))
+        .with_default(false)
+        .prompt()
+        .context("confirmation cancelled")?;
+        if !confirmed {
+            eprintln!("Cancelled.");
+            return Ok(());
+        }
+    }
+
+    if !quiet {
+        eprintln!("{}", "Regenerating (this can take a minute)...".dimmed());
+    }
+    arm.regenerate_key(&id, kind)
+        .await
+        .with_context(|| format!("failed to regenerate the {} key", key_label(kind)))?;
+    let keys = arm.list_keys(&id).await?;
+    eprintln!(
+        "{} Regenerated the {} key",
+        "OK".green().bold(),
+        key_label(kind)
+    );
+    println!("{}", keys.get(kind));
+
+    if own_key {
+        match account.master_key.as_deref().and_then(|key| {
+            key.strip_prefix(secrets::KEYRING_PREFIX)
+                .map(|name| name.trim().to_string())
+        }) {
+            Some(name) => eprintln!(
+                "  Update the stored key with {}",
+                format!("cosq secrets set {name}").cyan()
+            ),
+            None => eprintln!("  Update `master_key:` in the config with the new key."),
+        }
+    }
+    Ok(())
+}
+
+async fn backup_policy(profile: Option<String>) -> Result<()> {
+    let (config, id) = load_account("cosq account backup-policy", profile.as_deref())?;
+    let arm = ArmClient::new().await?;
+    let details = arm
+        .get_account(&id)
+        .await
+        .with_context(|| format!("failed to read account '{}'", config.account.name))?;
+    let policy = &details.properties.backup_policy;
+
+    println!("  {:<24} {}", "Account:".bold(), details.name);
+    if policy.is_continuous() {
+        let tier = policy
+            .continuous_mode_properties
+            .as_ref()
+            .and_then(|p| p.tier.as_deref())
+            .map(tier_label)
+            .unwrap_or("continuous");
+        println!(
+            "  {:<24} {} (point-in-time restore)",
+            "Backup policy:".bold(),
+            format!("Continuous, {tier}").green()
+        );
+        let restorable = restorable(&arm, &config, &details).await;
+        if let Some(oldest) = restorable.and_then(|r| r.oldest_restorable_time) {
+            println!("  {:<24} {oldest}", "Earliest restore point:".bold());
+        }
+        println!(
+            "\n  Restore with {}",
+            "cosq account restore --to <timestamp> --target <new-account>".cyan()
+        );
+    } else {
+        println!(
+            "  {:<24} {}",
+            "Backup policy:".bold(),
+            if policy.policy_type.is_empty() {
+                "unknown"
+            } else {
+                policy.policy_type.as_str()
+            }
+        );
+        if let Some(ref periodic) = policy.periodic_mode_properties {
+            if let Some(minutes) = periodic.backup_interval_in_minutes {
+                println!(
+                    "  {:<24} every {minutes} minutes",
+                    "Backup interval:".bold()
+                );
+            }
+            if let Some(hours) = periodic.backup_retention_interval_in_hours {
+                println!("  {:<24} {hours} hours", "Retention:".bold());
+            }
+            if let Some(ref redundancy) = periodic.backup_storage_redundancy {
+                println!("  {:<24} {redundancy}", "Storage redundancy:".bold());
+            }
+        }
+        println!(
+            "\n  Point-in-time restore needs continuous backup; periodic backups are restored through an Azure support request."
+        );
+    }
+    Ok(())
+}
+
+async fn restore(
+    to: &str,
+    target: &str,
+    resource_group: Option<String>,
+    yes: bool,
+    profile: Option<String>,
+    quiet: bool,
+) -> Result<()> {
+    let (config, id) = load_account("cosq account restore", profile.as_deref())?;
+    let account = &config.account;
+    let timestamp = parse_restore_time(to)?;
+    if timestamp > Utc::now() {
+        bail!("--to {to} is in the future");
+    }
+    validate_account_name(target)?;
+
+    let arm = ArmClient::new().await?;
+    let details = arm
+        .get_account(&id)
+        .await
+        .with_context(|| format!("failed to read account '{}'", account.name))?;
+    if !details.properties.backup_policy.is_continuous() {
+        bail!(
+            "account '{}' uses {} backups; point-in-time restore needs continuous backup (see `cosq account backup-policy show`)",
+            account.name,
+            details.properties.backup_policy.policy_type
+        );
+    }
+    let Some(ref instance_id) = details.properties.instance_id else {
+        bail!(
+            "ARM did not return an instance ID for '{}'; it cannot be restored",
+            account.name
+        );
+    };
+    let restore_source =
+        restorable_account_id(&account.subscription, &details.location, instance_id);
+    let oldest = restorable(&arm, &config, &details)
+        .await
+        .and_then(|r| r.oldest_restorable_time);
+    if let Some(oldest) = oldest {
+        let too_early =
+            DateTime::parse_from_rfc3339(&oldest).is_ok_and(|oldest| timestamp < oldest);
+        if too_early {
+            bail!(
+                "--to {to} is before the earliest restore point of '{}' ({oldest})",
+                account.name
+            );
+        }
+    }
+
+    let resource_group = resource_group.unwrap_or_else(|| account.resource_group.clone());
+    let timestamp = timestamp.format("%Y-%m-%dT%H:%M:%SZ").to_string();
+    eprintln!(
+        "Restoring '{}' as of {} into a new account '{}' in resource group '{}' ({}).",
+        account.name,
+        timestamp.bold(),
+        target.bold(),
+        resource_group,
+        details.location
+    );
+    eprintln!(
+        "  {}",
+        "The restore creates a separate account with its own endpoint and billing; the source account is not changed.".dimmed()
+    );
+    if !yes {
+        if !std::io::stdin().is_terminal() {
+            bail!("refusing to start a restore without confirmation — pass --yes");
+        }
+        let confirmed =
+            inquire::Confirm::new(&format!("Create '{target}' from this restore point?"))
+                .with_default(false)
+                .prompt()
+                .context("confirmation cancelled")?;
+        if !confirmed {
+            eprintln!("Cancelled.");
+            return Ok(());
+        }
+    }
+
+    arm.restore_account(
+        &details,
+        &restore_source,
+        &timestamp,
+        &account.subscription,
+        &resource_group,
+        target,
+    )
+    .await
+    .with_context(|| format!("failed to start the restore into '{target}'"))?;
+    eprintln!("{} Restore into '{target}' started", "OK".green().bold());
+    if !quiet {
+        eprintln!(
+            "  Restores usually take an hour or more. When '{target}' is online, point cosq at it with {}",
+            format!("cosq init --account {target}").cyan()
+        );
+    }
+    Ok(())
+}
+
+/// The restorable account, if ARM returns it (older accounts or missing
+/// permissions only lose the earliest-restore-point check).
+async fn restorable(
+    arm: &ArmClient,
+    config: &Config,
+    details: &AccountDetails,
+) -> Option<RestorableAccount> {
+    let instance_id = details.properties.instance_id.as_deref()?;
+    let id = restorable_account_id(&config.account.subscription, &details.location, instance_id);
+    match arm.get_restorable_account(&id).await {
+        Ok(restorable) => Some(restorable),
+        Err(e) => {
+            tracing::debug!(error = %e, "failed to read restorable account");
+            None
+        }
+    }
+}
+
+/// Parse `--to`: RFC 3339 (`2026-10-15T08:00:00Z`, `...+02:00`) or a UTC
+/// `YYYY-MM-DD HH:MM[:SS]`.
+fn parse_restore_time(value: &str) -> Result<DateTime<Utc>> {
+    if let Ok(time) = DateTime::parse_from_rfc3339(value) {
+        return Ok(time.with_timezone(&Utc));
+    }
+    ["%Y-%m-%d %H:%M:%S", "%Y-%m-%d %H:%M", "%Y-%m-%dT%H:%M:%S"]
+        .iter()
+        .find_map(|format| NaiveDateTime::parse_from_str(value, format).ok())
+        .map(|time| time.and_utc())
+        .with_context(|| format!("invalid timestamp '{value}' — use UTC like 2026-10-15T08:00:00Z"))
+}
+
+/// Cosmos DB account names: 3–44 lowercase letters, digits and hyphens, not
+/// starting or ending with a hyphen.
+fn validate_account_name(name: &str) -> Result<()> {
+    let valid = (3..=44).contains(&name.len())
+        && name
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-')
+        && !name.starts_with('-')
+        && !name.ends_with('-');
+    if !valid {
+        bail!(
+            "invalid account name '{name}': use 3-44 lowercase letters, digits and hyphens (no leading or trailing hyphen)"
+        );
+    }
+    Ok(())
+}
+
+fn tier_label(tier: &str) -> &'static str {
+    match tier {
+        "Continuous7Days" => "7 days",
+        "Continuous30Days" => "30 days",
+        _ => "continuous",
+    }
+}
+
+fn print_keys(keys: &AccountKeys) {
+    for kind in [
+        AccountKeyKind::Primary,
+        AccountKeyKind::Secondary,
+        AccountKeyKind::PrimaryReadonly,
+        AccountKeyKind::SecondaryReadonly,
+    ] {
+        println!(
+            "  {:<20} {}",
+            format!("{}:", key_label(kind)).bold(),
+            keys.get(kind)
+        );
+    }
+}
+
+fn key_label(kind: AccountKeyKind) -> &'static str {
+    match kind {
+        AccountKeyKind::Primary => "primary",
+        AccountKeyKind::Secondary => "secondary",
+        AccountKeyKind::PrimaryReadonly => "primary read-only",
+        AccountKeyKind::SecondaryReadonly => "secondary read-only",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_throughput_label() {
+        let autoscale = Throughput {
+            manual: None,
+            autoscale_max: Some(4000),
+        };
+        assert_eq!(throughput_label(autoscale), "autoscale 400 - 4000 RU/s");
+        let manual = Throughput {
+            manual: Some(400),
+            autoscale_max: None,
+        };
+        assert_eq!(throughput_label(manual), "400 RU/s (manual)");
+    }
+
+    #[test]
+    fn test_parse_restore_time() {
+        let expected = "2026-10-15T08:00:00Z";
+        for value in [
+            "2026-10-15T08:00:00Z",
+            "2026-10-15T10:00:00+02:00",
+            "2026-10-15 08:00",
+            "2026-10-15T08:00:00",
+        ] {
+            assert_eq!(
+                parse_restore_time(value)
+                    .unwrap()
+                    .format("%Y-%m-%dT%H:%M:%SZ")
+                    .to_string(),
+                expected
+            );
+        }
+        assert!(parse_restore_time("yesterday").is_err());
+        assert!(validate_account_name("prod-restored-1").is_ok());
+        assert!(validate_account_name("Prod").is_err());
+        assert!(validate_account_name("-prod").is_err());
+    }
+}
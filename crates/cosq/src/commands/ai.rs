@@ -5,11 +5,29 @@
 //! `cosq ai enable`  — enable AI for cosq
 //! `cosq ai disable` — disable AI for cosq
 //! `cosq ai config`  — open config in editor
+//! `cosq ai usage`   — token usage per month (and the `ai_budget:`)
+//! `cosq ai models`  — list/pull/delete Ollama models
+//! `cosq ai log`     — transcripts of AI requests (with `ai_log: true`)
+//!
+//! Also home to the `ai_save:` gate that every AI flow goes through before
+//! writing to a .cosq file.
+
+use std::io::IsTerminal;
+use std::path::Path;
+use std::time::Instant;
 
-use anyhow::Result;
+use ailloy::ChatResponse;
+use anyhow::{Context, Result};
 use colored::Colorize;
+use cosq_client::azure_openai::AzureOpenAIClient;
+use cosq_client::ollama::{DEFAULT_ENDPOINT as DEFAULT_OLLAMA_ENDPOINT, OllamaClient};
+use cosq_core::ai_log::{self, Transcript};
+use cosq_core::ai_usage::{self, UsageRecord, UsageTotals};
+use cosq_core::config::{AiBudgetConfig, AiSavePolicy, Config};
+use cosq_core::diff::{DiffLine, diff_lines};
+use cosq_core::secrets;
 
-use crate::cli::AiCommands;
+use crate::cli::{AiCommands, ModelsCommands};
 
 const APP_NAME: &str = "cosq";
 
@@ -20,6 +38,9 @@ pub async fn run(cmd: Option<AiCommands>) -> Result<()> {
         Some(AiCommands::Enable) => enable(),
         Some(AiCommands::Disable) => disable(),
         Some(AiCommands::Config) => open_config(),
+        Some(AiCommands::Usage { months }) => usage(months),
+        Some(AiCommands::Log { last, full }) => log(last, full),
+        Some(AiCommands::Models { command, endpoint }) => models(command, endpoint).await,
     }
 }
 
@@ -49,6 +70,23 @@ fn status() -> Result<()> {
             println!("{} AI is configured but disabled\n", "!".yellow().bold());
         }
         print_node_info(id, node);
+        if let Some(key) = Config::load()
+            .ok()
+            .and_then(|c| c.ai)
+            .and_then(|a| a.api_key)
+        {
+            let shown = if secrets::is_reference(&key) {
+                key
+            } else {
+                "(set in config)".to_string()
+            };
+            println!(
+                "  {} {} {}",
+                "API key:".bold(),
+                shown,
+                "(ai.api_key)".dimmed()
+            );
+        }
         if !enabled {
             println!(
                 "\n  Run {} to re-enable.",
@@ -76,7 +114,7 @@ async fn test(message: Option<String>) -> Result<()> {
     println!("Testing AI connection...\n");
 
     let result: Result<ailloy::ChatResponse> = async {
-        let client = ailloy::Client::from_config()?;
+        let client = cosq_client::ai::client(api_key()?.as_deref())?;
         client.chat(&[ailloy::Message::user(&message)]).await
     }
     .await;
@@ -175,6 +213,509 @@ fn which(cmd: &str) -> bool {
         .is_ok_and(|s| s.success())
 }
 
+/// Send a prompt to the configured provider for `feature` (e.g. `generate`),
+/// recording and printing the tokens it used, and keeping a transcript when
+/// `ai_log:` is on.
+pub async fn generate(feature: &str, system_prompt: &str, user_prompt: &str) -> Result<String> {
+    let at = chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Secs, true);
+    let started = Instant::now();
+    let result = match api_key() {
+        Ok(key) => {
+            cosq_client::ai::chat_with_api_key(system_prompt, user_prompt, 2000, key.as_deref())
+                .await
+        }
+        Err(e) => Err(e),
+    };
+    record_call(feature, system_prompt, user_prompt, at, started, result)
+}
+
+/// Like [`generate`], but through Azure OpenAI structured output: the
+/// response is the JSON text of an object matching `schema`.
+pub async fn generate_structured(
+    client: &AzureOpenAIClient,
+    feature: &str,
+    system_prompt: &str,
+    user_prompt: &str,
+    schema_name: &str,
+    schema: &serde_json::Value,
+) -> Result<String> {
+    let at = chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Secs, true);
+    let started = Instant::now();
+    let result = client
+        .chat_structured(system_prompt, user_prompt, schema_name, schema, 2000)
+        .await
+        .map_err(anyhow::Error::from);
+    record_call(feature, system_prompt, user_prompt, at, started, result)
+}
+
+/// The `ai.api_key` from the config, resolved from the OS keyring when it
+/// is a `keyring:<name>` reference.
+pub fn api_key() -> Result<Option<String>> {
+    let Some(key) = Config::load()
+        .ok()
+        .and_then(|c| c.ai)
+        .and_then(|ai| ai.api_key)
+    else {
+        return Ok(None);
+    };
+    let key = secrets::resolve(&key).context("failed to load the `ai.api_key`")?;
+    Ok(Some(key))
+}
+
+/// Keep the transcript, record usage and warn about the budget for one AI call.
+fn record_call(
+    feature: &str,
+    system_prompt: &str,
+    user_prompt: &str,
+    at: String,
+    started: Instant,
+    result: Result<ChatResponse>,
+) -> Result<String> {
+    let config = Config::load().ok();
+    let provider = cosq_client::ai::provider_display_name().unwrap_or_else(|| "ailloy".into());
+
+    if config.as_ref().is_some_and(|c| c.ai_log) {
+        let transcript = Transcript {
+            at: at.clone(),
+            feature: feature.to_string(),
+            provider: provider.clone(),
+            model: result.as_ref().ok().map(|r| r.model.clone()),
+            duration_ms: started.elapsed().as_millis() as u64,
+            system_prompt: system_prompt.to_string(),
+            user_prompt: user_prompt.to_string(),
+            response: result.as_ref().ok().map(|r| r.content.clone()),
+            error: result.as_ref().err().map(|e| format!("{e:#}")),
+        };
+        if let Err(e) = ai_log::append(&transcript) {
+            tracing::debug!("failed to write AI transcript: {e}");
+        }
+    }
+    let response = result?;
+
+    let record = UsageRecord {
+        at,
+        feature: feature.to_string(),
+        provider,
+        model: response.model.clone(),
+        prompt_tokens: response.usage.as_ref().map(|u| u64::from(u.prompt_tokens)),
+        completion_tokens: response
+            .usage
+            .as_ref()
+            .map(|u| u64::from(u.completion_tokens)),
+    };
+    if let Err(e) = ai_usage::append(&record) {
+        tracing::debug!("failed to record AI usage: {e}");
+    }
+
+    let budget = config.and_then(|c| c.ai_budget);
+    if let (Some(prompt), Some(completion)) = (record.prompt_tokens, record.completion_tokens) {
+        let cost = budget
+            .as_ref()
+            .and_then(|b| Some(format!(", ~{}", b.format_cost(b.cost(prompt, completion)?))))
+            .unwrap_or_default();
+        eprintln!(
+            "{}",
+            format!("AI usage: {prompt} prompt + {completion} completion tokens{cost}").dimmed()
+        );
+    }
+    if let Some(budget) = budget {
+        let month = record.at.get(..7).unwrap_or_default().to_string();
+        let used = ai_usage::load()
+            .map(|records| ai_usage::totals_by_month(&records))
+            .ok()
+            .and_then(|mut months| months.remove(&month))
+            .unwrap_or_default()
+            .total_tokens();
+        if budget.is_over(used) {
+            eprintln!(
+                "{} {used} AI tokens used this month — over the `ai_budget:` of {}",
+                "Warning:".yellow().bold(),
+                budget.monthly_tokens.unwrap_or_default()
+            );
+        }
+    }
+
+    Ok(response.content)
+}
+
+fn usage(months: usize) -> Result<()> {
+    let records = ai_usage::load()?;
+    if records.is_empty() {
+        println!("No AI usage recorded yet.");
+        return Ok(());
+    }
+    let budget = Config::load()
+        .ok()
+        .and_then(|c| c.ai_budget)
+        .unwrap_or_default();
+    let by_month = ai_usage::totals_by_month(&records);
+
+    println!(
+        "{} ({}):\n",
+        "AI token usage".bold(),
+        ai_usage::usage_file()?.display().to_string().dimmed()
+    );
+    println!(
+        "  {:<12}  {:>6}  {:>12}  {:>12}  {:>12}  {:>10}",
+        "MONTH", "CALLS", "PROMPT", "COMPLETION", "TOTAL", "COST"
+    );
+    for (month, totals) in by_month.iter().rev().take(months.max(1)) {
+        print_usage_row(month, totals, &budget);
+    }
+
+    let mut by_feature: std::collections::BTreeMap<&str, UsageTotals> = Default::default();
+    let current = chrono::Utc::now().format("%Y-%m").to_string();
+    for record in records.iter().filter(|r| r.at.starts_with(&current)) {
+        by_feature.entry(&record.feature).or_default().add(record);
+    }
+    if !by_feature.is_empty() {
+        println!("\n  {} ({current}):", "By feature".bold());
+        for (feature, totals) in &by_feature {
+            print_usage_row(feature, totals, &budget);
+        }
+    }
+
+    if let Some(limit) = budget.monthly_tokens {
+        let used = by_month
+            .get(&current)
+            .map(UsageTotals::total_tokens)
+            .unwrap_or_default();
+        let line = format!(
+            "\n  Budget: {used} of {limit} tokens this month ({:.0}%)",
+            used as f64 * 100.0 / limit.max(1) as f64
+        );
+        if budget.is_over(used) {
+            println!("{}", line.yellow());
+        } else {
+            println!("{line}");
+        }
+    }
+
+    let unreported: u64 = by_month.values().map(|t| t.unreported).sum();
+    if unreported > 0 {
+        println!(
+            "\n  {}",
+            format!("{unreported} calls had no token counts from the provider.").dimmed()
+        );
+    }
+    Ok(())
+}
+
+fn log(last: usize, full: bool) -> Result<()> {
+    let enabled = Config::load().is_ok_and(|c| c.ai_log);
+    let transcripts = ai_log::load_recent(last)?;
+    if transcripts.is_empty() {
+        println!("No AI transcripts recorded.");
+        if !enabled {
+            println!(
+                "\n  Add {} to the config to keep them under {}.",
+                "ai_log: true".cyan(),
+                "~/.cosq/ai-logs/".dimmed()
+            );
+        }
+        return Ok(());
+    }
+
+    for transcript in &transcripts {
+        let outcome = match transcript.error {
+            Some(ref e) => format!("failed: {e}").red().to_string(),
+            None => transcript.model.clone().unwrap_or_default(),
+        };
+        println!(
+            "{}  {}  {} {}  {}",
+            transcript.at.dimmed(),
+            transcript.feature.bold(),
+            transcript.provider.cyan(),
+            outcome,
+            format!("{:.1}s", transcript.duration_ms as f64 / 1000.0).dimmed()
+        );
+        let sections = [
+            ("System prompt", Some(&transcript.system_prompt)),
+            ("User prompt", Some(&transcript.user_prompt)),
+            ("Response", transcript.response.as_ref()),
+        ];
+        for (label, text) in sections {
+            let Some(text) = text else { continue };
+            if full {
+                println!("  {}\n{}\n", format!("{label}:").bold(), text);
+            } else {
+                println!(
+                    "  {:<14} {}",
+                    format!("{label}:").dimmed(),
+                    preview(text, 100)
+                );
+            }
+        }
+        println!();
+    }
+    if !full {
+        println!(
+            "  {}",
+            "Pass --full for the complete prompts and responses.".dimmed()
+        );
+    }
+    if !enabled {
+        println!(
+            "  {}",
+            "Transcripts are off (`ai_log: false`); these are from earlier.".dimmed()
+        );
+    }
+    Ok(())
+}
+
+/// `text` on one line, cut to `max` characters (noting the full size when cut).
+fn preview(text: &str, max: usize) -> String {
+    let flat = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    if flat.chars().count() <= max {
+        return flat;
+    }
+    let cut: String = flat.chars().take(max).collect();
+    format!("{cut}… ({} chars)", text.chars().count())
+}
+
+async fn models(cmd: Option<ModelsCommands>, endpoint: Option<String>) -> Result<()> {
+    let client = OllamaClient::new(&ollama_endpoint(endpoint));
+    let configured = configured_ollama_model();
+    match cmd.unwrap_or(ModelsCommands::List) {
+        ModelsCommands::List => {
+            let models = client.list_models().await.with_context(|| {
+                format!(
+                    "could not reach Ollama at {} (is it running?)",
+                    client.endpoint()
+                )
+            })?;
+            if models.is_empty() {
+                println!("No models installed on {}.", client.endpoint());
+            } else {
+                println!(
+                    "{} ({}):\n",
+                    "Ollama models".bold(),
+                    client.endpoint().dimmed()
+                );
+                let width = models.iter().map(|m| m.name.len()).max().unwrap_or(0);
+                for model in &models {
+                    let in_use = configured
+                        .as_deref()
+                        .is_some_and(|c| ollama_model_matches(c, &model.name));
+                    println!(
+                        "  {:<width$}  {:>8}  {}{}",
+                        model.name.green(),
+                        format_size(model.size),
+                        model.modified_at.get(..10).unwrap_or_default().dimmed(),
+                        if in_use {
+                            " (configured)".cyan().to_string()
+                        } else {
+                            String::new()
+                        },
+                    );
+                }
+            }
+            if let Some(model) = configured {
+                if !models.iter().any(|m| ollama_model_matches(&model, &m.name)) {
+                    println!(
+                        "\n{} the configured model '{model}' is not installed. Pull it with: {}",
+                        "Note:".yellow().bold(),
+                        format!("cosq ai models pull {model}").cyan()
+                    );
+                }
+            }
+            Ok(())
+        }
+        ModelsCommands::Pull { model } => {
+            eprintln!("Pulling {} from {}...", model.cyan(), client.endpoint());
+            let mut last_status = String::new();
+            client
+                .pull(&model, |progress| match progress.percent() {
+                    Some(pct) => {
+                        if progress.status != last_status && !last_status.is_empty() {
+                            eprintln!();
+                        }
+                        eprint!(
+                            "\r  {} {:>5.1}% of {}   ",
+                            progress.status,
+                            pct,
+                            format_size(progress.total.unwrap_or_default())
+                        );
+                        last_status = progress.status.clone();
+                    }
+                    None if progress.status != last_status => {
+                        if !last_status.is_empty() {
+                            eprintln!();
+                        }
+                        eprint!("  {}", progress.status);
+                        last_status = progress.status.clone();
+                    }
+                    None => {}
+                })
+                .await
+                .with_context(|| format!("failed to pull '{model}'"))?;
+            eprintln!();
+            println!("{} Pulled {model}", "OK".green().bold());
+            if configured.is_none() {
+                println!(
+                    "\n  Use it for cosq by adding an Ollama node with {}",
+                    format!("{APP_NAME} ai config").cyan()
+                );
+            }
+            Ok(())
+        }
+        ModelsCommands::Delete { model, yes } => {
+            if !yes
+                && !inquire::Confirm::new(&format!(
+                    "Delete model '{model}' from {}?",
+                    client.endpoint()
+                ))
+                .with_default(false)
+                .prompt()
+                .context("confirmation cancelled")?
+            {
+                println!("Cancelled.");
+                return Ok(());
+            }
+            client.delete(&model).await?;
+            println!("{} Deleted {model}", "OK".green().bold());
+            Ok(())
+        }
+    }
+}
+
+/// Ollama server: `--endpoint`, the endpoint of a configured Ollama node,
+/// `OLLAMA_HOST`, or the default `http://localhost:11434`.
+fn ollama_endpoint(cli: Option<String>) -> String {
+    let configured = || {
+        let config = ailloy::config::Config::load().ok()?;
+        config
+            .nodes
+            .values()
+            .filter(|n| n.provider == ailloy::config::ProviderKind::Ollama)
+            .find_map(|n| n.endpoint.clone())
+    };
+    let endpoint = cli
+        .or_else(configured)
+        .or_else(|| std::env::var("OLLAMA_HOST").ok().filter(|h| !h.is_empty()))
+        .unwrap_or_else(|| DEFAULT_OLLAMA_ENDPOINT.to_string());
+    if endpoint.contains("://") {
+        endpoint
+    } else {
+        format!("http://{endpoint}")
+    }
+}
+
+/// Model of the default chat node, when it is an Ollama node.
+fn configured_ollama_model() -> Option<String> {
+    let config = ailloy::config::Config::load().ok()?;
+    let (_, node) = config.default_chat_node().ok()?;
+    (node.provider == ailloy::config::ProviderKind::Ollama)
+        .then(|| node.model.clone())
+        .flatten()
+}
+
+/// Whether an installed model name satisfies a configured one (`llama3.2`
+/// means `llama3.2:latest`).
+fn ollama_model_matches(configured: &str, installed: &str) -> bool {
+    configured == installed
+        || (!configured.contains(':') && installed == format!("{configured}:latest"))
+}
+
+fn format_size(bytes: u64) -> String {
+    const GB: f64 = 1024.0 * 1024.0 * 1024.0;
+    const MB: f64 = 1024.0 * 1024.0;
+    let bytes = bytes as f64;
+    if bytes >= GB {
+        format!("{:.1} GB", bytes / GB)
+    } else {
+        format!("{:.0} MB", bytes / MB)
+    }
+}
+
+fn print_usage_row(label: &str, totals: &UsageTotals, budget: &AiBudgetConfig) {
+    let cost = budget
+        .cost(totals.prompt_tokens, totals.completion_tokens)
+        .map(|c| budget.format_cost(c))
+        .unwrap_or_else(|| "-".to_string());
+    println!(
+        "  {:<12}  {:>6}  {:>12}  {:>12}  {:>12}  {:>10}",
+        label,
+        totals.calls,
+        totals.prompt_tokens,
+        totals.completion_tokens,
+        totals.total_tokens(),
+        cost
+    );
+}
+
+/// Preview an AI-generated change to the .cosq file at `path` as a diff and
+/// decide, per the `ai_save:` policy, whether it may be written.
+pub fn confirm_save(path: &Path, new_contents: &str) -> Result<bool> {
+    let policy = Config::load()
+        .ok()
+        .and_then(|c| c.ai_save)
+        .unwrap_or_default();
+    let old_contents = std::fs::read_to_string(path).unwrap_or_default();
+
+    eprintln!("\n{} {}", "Changes to".bold(), path.display());
+    print_diff(&old_contents, new_contents);
+
+    match policy {
+        AiSavePolicy::Always => Ok(true),
+        AiSavePolicy::Never => {
+            eprintln!(
+                "{} not saved (`ai_save: never` in the config)",
+                "Note:".yellow().bold()
+            );
+            Ok(false)
+        }
+        AiSavePolicy::Prompt => {
+            if !std::io::stdin().is_terminal() {
+                eprintln!(
+                    "{} not saved (not interactive; set `ai_save: always` to save without asking)",
+                    "Note:".yellow().bold()
+                );
+                return Ok(false);
+            }
+            inquire::Confirm::new(&format!("Save these changes to {}?", path.display()))
+                .with_default(true)
+                .prompt()
+                .context("confirmation cancelled")
+        }
+    }
+}
+
+/// Print a diff with three lines of context around each change.
+fn print_diff(old: &str, new: &str) {
+    const CONTEXT: usize = 3;
+    let lines = diff_lines(old, new);
+    if !lines.iter().any(DiffLine::is_change) {
+        eprintln!("  {}", "(no changes)".dimmed());
+        return;
+    }
+
+    let near_change = |i: usize| {
+        let start = i.saturating_sub(CONTEXT);
+        let end = (i + CONTEXT + 1).min(lines.len());
+        lines[start..end].iter().any(DiffLine::is_change)
+    };
+    let mut skipped = false;
+    for (i, line) in lines.iter().enumerate() {
+        if !near_change(i) {
+            skipped = true;
+            continue;
+        }
+        if skipped {
+            eprintln!("  {}", "...".dimmed());
+            skipped = false;
+        }
+        match line {
+            DiffLine::Same(text) => eprintln!("  {}", text.dimmed()),
+            DiffLine::Removed(text) => eprintln!("{}", format!("- {text}").red()),
+            DiffLine::Added(text) => eprintln!("{}", format!("+ {text}").green()),
+        }
+    }
+    if skipped {
+        eprintln!("  {}", "...".dimmed());
+    }
+}
+
 fn disabled_marker_path() -> std::path::PathBuf {
     dirs::config_dir()
         .unwrap_or_else(|| std::path::PathBuf::from("."))
@@ -1,20 +1,55 @@
 //! Azure authentication commands
+//!
+//! Also creates Cosmos DB resource tokens: short-lived credentials scoped to
+//! one container (and optionally one partition key) that can be handed to
+//! another tool or person, and audits the account's data plane role
+//! assignments.
 
-use anyhow::Result;
+use std::collections::HashMap;
+
+use anyhow::{Context, Result, bail};
 use colored::Colorize;
-use cosq_client::auth::AzCliAuth;
+use comfy_table::Table;
+use comfy_table::presets::UTF8_FULL_CONDENSED;
+use cosq_client::arm::{ArmClient, SqlRoleDefinition};
+use cosq_client::auth::{self, AuthStatus, AzCliAuth};
+use cosq_client::graph::{GraphClient, Principal};
+use cosq_client::workload_identity::WorkloadIdentity;
+use cosq_core::config::{AuthMethod, Config};
+use cosq_core::rbac_audit::{self, AuditEntry, relative_scope};
+use cosq_core::snapshot::hash_documents;
+
+use super::account::load_account;
+use super::docs::{connect_container, parse_partition_key};
+use crate::cli::{AuthCommands, ReportFormat, TokenPermission};
 
-use crate::cli::AuthCommands;
+/// Longest lifetime Cosmos DB allows for a resource token
+const MAX_TOKEN_TTL_SECS: u64 = 5 * 60 * 60;
 
-pub async fn run(cmd: AuthCommands) -> Result<()> {
+pub async fn run(cmd: AuthCommands, profile: Option<String>, quiet: bool) -> Result<()> {
     match cmd {
         AuthCommands::Status => status().await,
         AuthCommands::Login => login().await,
         AuthCommands::Logout => logout().await,
+        AuthCommands::ResourceToken {
+            container,
+            db,
+            pk,
+            permission,
+            ttl,
+            user,
+        } => {
+            let ttl = parse_ttl(&ttl)?;
+            resource_token(container, db, pk, permission, ttl, &user, profile, quiet).await
+        }
+        AuthCommands::Audit { output } => audit(output, profile, quiet).await,
     }
 }
 
 async fn status() -> Result<()> {
+    if auth::method() == AuthMethod::WorkloadIdentity {
+        return workload_identity_status().await;
+    }
     let status = AzCliAuth::check_status().await?;
 
     if status.logged_in {
@@ -22,6 +57,9 @@ async fn status() -> Result<()> {
         if let Some(user) = &status.user {
             println!("  {} {}", "User:".bold(), user);
         }
+        if let Some(identity) = signed_in_identity(&status).await {
+            println!("  {} {}", "Identity:".bold(), identity);
+        }
         if let Some(sub) = &status.subscription_name {
             println!("  {} {}", "Subscription:".bold(), sub);
         }
@@ -32,12 +70,7 @@ async fn status() -> Result<()> {
             println!("  {} {}", "Tenant:".bold(), tenant.dimmed());
         }
 
-        // Test Cosmos DB token acquisition
-        print!("\n  {} ", "Cosmos DB token:".bold());
-        match AzCliAuth::get_token(cosq_client::auth::COSMOS_RESOURCE).await {
-            Ok(_) => println!("{}", "OK".green()),
-            Err(e) => println!("{} ({})", "FAILED".red(), e),
-        }
+        print_token_check().await;
     } else {
         println!("{}", "Azure CLI: not logged in".red().bold());
         println!(
@@ -49,6 +82,67 @@ async fn status() -> Result<()> {
     Ok(())
 }
 
+/// Status when tokens come from a federated credential instead of the Azure CLI
+async fn workload_identity_status() -> Result<()> {
+    let Some(identity) = WorkloadIdentity::from_env() else {
+        bail!(
+            "`auth: workload-identity` needs AZURE_CLIENT_ID, AZURE_TENANT_ID and a federated \
+             token (a GitHub Actions job with `permissions: id-token: write`, or \
+             AZURE_FEDERATED_TOKEN_FILE)"
+        );
+    };
+    println!("{}", "Workload identity".green().bold());
+    println!("  {} {}", "Identity:".bold(), identity.describe());
+    print_token_check().await;
+    Ok(())
+}
+
+/// Test Cosmos DB token acquisition
+async fn print_token_check() {
+    print!("\n  {} ", "Cosmos DB token:".bold());
+    match auth::get_token(auth::COSMOS_RESOURCE).await {
+        Ok(_) => println!("{}", "OK".green()),
+        Err(e) => println!("{} ({})", "FAILED".red(), e),
+    }
+}
+
+/// The signed-in user or service principal as the directory knows it
+/// (display name, kind and object ID). `None` when Graph can't be read.
+async fn signed_in_identity(status: &AuthStatus) -> Option<String> {
+    let graph = GraphClient::new().await.ok()?;
+    let principal = match status.user_type.as_deref() {
+        Some("servicePrincipal") => {
+            let app_id = status.user.as_deref()?;
+            graph
+                .get_service_principal_by_app_id(app_id)
+                .await
+                .ok()
+                .flatten()?
+        }
+        _ => {
+            let id = AzCliAuth::get_principal_id().await.ok()?;
+            graph.get_principal(&id).await.ok().flatten()?
+        }
+    };
+    Some(format!(
+        "{} {}",
+        principal.display_name,
+        format!("({}, object ID {})", principal.kind.label(), principal.id).dimmed()
+    ))
+}
+
+/// A principal's display name and sign-in name for messages, or `None` when
+/// the directory can't be read.
+pub async fn principal_label(id: &str) -> Option<String> {
+    let graph = GraphClient::new().await.ok()?;
+    graph
+        .get_principal(id)
+        .await
+        .ok()
+        .flatten()
+        .map(|p| p.label())
+}
+
 async fn login() -> Result<()> {
     println!("Opening browser for Azure login...\n");
     AzCliAuth::login().await?;
@@ -69,3 +163,287 @@ async fn logout() -> Result<()> {
     println!("{}", "Logged out of Azure CLI.".green());
     Ok(())
 }
+
+#[allow(clippy::too_many_arguments)]
+async fn resource_token(
+    container: Option<String>,
+    db: Option<String>,
+    pk: Option<String>,
+    permission: TokenPermission,
+    ttl_seconds: u64,
+    user: &str,
+    profile: Option<String>,
+    quiet: bool,
+) -> Result<()> {
+    let (config, client, database, container) =
+        connect_container(profile.as_deref(), db, container).await?;
+    if config.account.master_key.is_none() {
+        bail!(
+            "resource tokens require master key auth — set `master_key` for account '{}' in {}",
+            config.account.name,
+            Config::path()?.display()
+        );
+    }
+
+    let partition_key = pk.as_deref().map(parse_partition_key);
+    let permission_id = permission_id(&container, permission, partition_key.as_ref());
+    let granted = client
+        .create_resource_token(
+            &database,
+            &container,
+            user,
+            &permission_id,
+            permission.into(),
+            partition_key.as_ref(),
+            ttl_seconds,
+        )
+        .await
+        .context("failed to create resource token")?;
+
+    println!("{}", granted.token);
+    if !quiet {
+        let expires = chrono::Utc::now() + chrono::Duration::seconds(ttl_seconds as i64);
+        eprintln!(
+            "{} {} token for {}{} (user '{user}'), expires {}",
+            "✓".green(),
+            granted.mode,
+            granted.resource,
+            partition_key
+                .map(|pk| format!(" partition {pk}"))
+                .unwrap_or_default(),
+            expires.format("%Y-%m-%d %H:%M:%S UTC")
+        );
+    }
+    Ok(())
+}
+
+async fn audit(output: ReportFormat, profile: Option<String>, quiet: bool) -> Result<()> {
+    let (config, id) = load_account("cosq auth audit", profile.as_deref())?;
+    let arm = ArmClient::new().await?;
+    let assignments = arm.list_sql_role_assignments(&id).await.with_context(|| {
+        format!(
+            "failed to list role assignments of '{}'",
+            config.account.name
+        )
+    })?;
+    let definitions: HashMap<String, SqlRoleDefinition> = arm
+        .list_sql_role_definitions(&id)
+        .await?
+        .into_iter()
+        .map(|d| (d.id.to_ascii_lowercase(), d))
+        .collect();
+
+    // Names make the report readable, but the audit works on IDs without them
+    let mut ids: Vec<String> = assignments
+        .iter()
+        .map(|a| a.properties.principal_id.clone())
+        .collect();
+    ids.sort();
+    ids.dedup();
+    let principals: Option<HashMap<String, Principal>> = match resolve_principals(&ids).await {
+        Ok(principals) => Some(principals),
+        Err(e) => {
+            if !quiet {
+                eprintln!(
+                    "{} principal names could not be resolved via Microsoft Graph: {e}",
+                    "Warning:".yellow().bold()
+                );
+            }
+            None
+        }
+    };
+
+    let mut entries: Vec<AuditEntry> = assignments
+        .into_iter()
+        .map(|assignment| {
+            let properties = assignment.properties;
+            let definition = definitions.get(&properties.role_definition_id.to_ascii_lowercase());
+            let data_actions = definition
+                .map(SqlRoleDefinition::data_actions)
+                .unwrap_or_default();
+            let principal = principals
+                .as_ref()
+                .and_then(|p| p.get(&properties.principal_id));
+            let principal_type = principal.map(|p| p.kind.label().to_string());
+            let scope = relative_scope(&properties.scope, &id);
+            let findings = rbac_audit::findings(
+                &data_actions,
+                &scope,
+                definition.is_some(),
+                principals.as_ref().map(|_| principal.is_some()),
+                principal_type.as_deref(),
+            );
+            AuditEntry {
+                assignment_id: last_segment(&assignment.id).to_string(),
+                principal_id: properties.principal_id.clone(),
+                principal_name: principal.map(|p| p.display_name.clone()),
+                principal_type,
+                role: definition
+                    .map(|d| d.properties.role_name.clone())
+                    .unwrap_or_else(|| last_segment(&properties.role_definition_id).to_string()),
+                data_actions,
+                scope,
+                findings,
+            }
+        })
+        .collect();
+    // Flagged assignments first
+    entries.sort_by(|a, b| {
+        b.findings
+            .len()
+            .cmp(&a.findings.len())
+            .then_with(|| a.scope.cmp(&b.scope))
+            .then_with(|| a.principal_name.cmp(&b.principal_name))
+    });
+
+    let flagged = entries.iter().filter(|e| !e.findings.is_empty()).count();
+    match output {
+        ReportFormat::Json => println!(
+            "{}",
+            serde_json::to_string_pretty(&serde_json::json!({
+                "account": config.account.name,
+                "assignments": entries,
+            }))?
+        ),
+        ReportFormat::Table => {
+            if !entries.is_empty() {
+                println!("{}", audit_table(&entries));
+            }
+            let summary = format!(
+                "{} role assignment(s) on '{}', {flagged} flagged",
+                entries.len(),
+                config.account.name
+            );
+            if flagged > 0 {
+                println!("{}", summary.yellow());
+            } else {
+                println!("{summary}");
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Look up every principal ID in the directory, keyed by ID.
+async fn resolve_principals(ids: &[String]) -> Result<HashMap<String, Principal>> {
+    if ids.is_empty() {
+        return Ok(HashMap::new());
+    }
+    let graph = GraphClient::new().await?;
+    Ok(graph
+        .get_by_ids(ids)
+        .await?
+        .into_iter()
+        .map(|p| (p.id.clone(), p))
+        .collect())
+}
+
+fn audit_table(entries: &[AuditEntry]) -> Table {
+    let mut table = Table::new();
+    table.load_preset(UTF8_FULL_CONDENSED);
+    table.set_header(vec!["Principal", "Type", "Role", "Scope", "Findings"]);
+    for entry in entries {
+        let principal = match entry.principal_name {
+            Some(ref name) => format!("{name}\n{}", entry.principal_id),
+            None => entry.principal_id.clone(),
+        };
+        let findings: Vec<&str> = entry.findings.iter().map(|f| f.description()).collect();
+        table.add_row(vec![
+            principal,
+            entry
+                .principal_type
+                .clone()
+                .unwrap_or_else(|| "-".to_string()),
+            entry.role.clone(),
+            entry.scope.clone(),
+            findings.join("\n"),
+        ]);
+    }
+    table
+}
+
+/// The last segment of an ARM resource ID (e.g. a role definition's GUID).
+fn last_segment(id: &str) -> &str {
+    id.rsplit('/').next().unwrap_or(id)
+}
+
+/// The permission behind a token: one per container, mode and partition key,
+/// so a token for one partition doesn't replace another partition's.
+fn permission_id(
+    container: &str,
+    permission: TokenPermission,
+    partition_key: Option<&serde_json::Value>,
+) -> String {
+    let mode = match permission {
+        TokenPermission::Read => "read",
+        TokenPermission::All => "all",
+    };
+    match partition_key {
+        // A hash keeps the id short and free of characters ids can't contain
+        Some(pk) => {
+            let hash = hash_documents(std::slice::from_ref(pk));
+            format!("cosq-{container}-{mode}-{}", &hash[..12])
+        }
+        None => format!("cosq-{container}-{mode}"),
+    }
+}
+
+/// Parse a token lifetime: `90s`, `30m`, `1h` or bare seconds.
+fn parse_ttl(value: &str) -> Result<u64> {
+    let value = value.trim();
+    let (number, unit) = match value.find(|c: char| !c.is_ascii_digit()) {
+        Some(i) => value.split_at(i),
+        None => (value, "s"),
+    };
+    let number: u64 = number
+        .parse()
+        .with_context(|| format!("invalid TTL '{value}' (expected e.g. 30m or 1h)"))?;
+    let multiplier = match unit {
+        "s" => 1,
+        "m" => 60,
+        "h" => 60 * 60,
+        _ => bail!("invalid TTL unit in '{value}' (use s, m or h)"),
+    };
+    number
+        .checked_mul(multiplier)
+        .filter(|seconds| (1..=MAX_TOKEN_TTL_SECS).contains(seconds))
+        .with_context(|| format!("TTL must be between 1s and 5h, got '{value}'"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_ttl() {
+        assert_eq!(parse_ttl("90s").unwrap(), 90);
+        assert_eq!(parse_ttl("30m").unwrap(), 1800);
+        assert_eq!(parse_ttl("1h").unwrap(), 3600);
+        assert_eq!(parse_ttl("600").unwrap(), 600);
+        assert!(parse_ttl("6h").is_err());
+        assert!(parse_ttl("0m").is_err());
+        assert!(parse_ttl("1d").is_err());
+        assert!(parse_ttl("h").is_err());
+        assert!(parse_ttl("18446744073709551615h").is_err());
+    }
+
+    #[test]
+    fn test_permission_id_per_partition_key() {
+        let a = permission_id(
+            "orders",
+            TokenPermission::Read,
+            Some(&serde_json::json!("a")),
+        );
+        let b = permission_id(
+            "orders",
+            TokenPermission::Read,
+            Some(&serde_json::json!("b")),
+        );
+        assert_ne!(a, b);
+        assert!(a.starts_with("cosq-orders-read-"));
+        assert_eq!(
+            permission_id("orders", TokenPermission::All, None),
+            "cosq-orders-all"
+        );
+    }
+}
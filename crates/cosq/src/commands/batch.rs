@@ -0,0 +1,205 @@
+//! Batch runs — `cosq run --all <glob>`
+//!
+//! Runs every stored query whose name matches a glob, one after another or
+//! `--parallel N` at a time, writes each result to `<out-dir>/<name>.<ext>` and
+//! prints a summary table. Parameters come from the command line or their
+//! defaults; nothing is prompted. With `--check`, only queries that have
+//! `checks:` run and nothing is written; a failed check fails that query.
+//! Ctrl+C stops the running queries after their current page, keeps their
+//! partial results and starts no further ones.
+
+use std::path::Path;
+use std::time::Instant;
+
+use anyhow::{Context, Result, bail};
+use colored::Colorize;
+use cosq_core::config::Config;
+use cosq_core::stored_query::find_stored_queries;
+use serde_json::json;
+use tokio::task::JoinSet;
+
+use super::common;
+use super::hooks::HookPolicy;
+use super::run::{self, RunArgs, RunSummary};
+use crate::output::{OutputFormat, write_results};
+
+/// Outcome of one query in a batch
+struct BatchEntry {
+    name: String,
+    file: String,
+    seconds: f64,
+    result: Result<RunSummary>,
+}
+
+pub async fn run(pattern: &str, parallel: usize, out_dir: &Path, args: RunArgs) -> Result<()> {
+    let mut queries = find_stored_queries(pattern)?;
+    if queries.is_empty() {
+        bail!("No stored queries match '{pattern}'. List them with `cosq queries list`.");
+    }
+    if args.check {
+        queries.retain(|query| !query.metadata.checks.is_empty());
+        if queries.is_empty() {
+            bail!("No stored queries matching '{pattern}' have `checks:`.");
+        }
+    } else {
+        std::fs::create_dir_all(out_dir)
+            .with_context(|| format!("failed to create {}", out_dir.display()))?;
+    }
+
+    let parallel = parallel.max(1);
+    // Picks each result file's extension the way the run picks its format
+    let config = Config::load().ok();
+    if !args.quiet {
+        eprintln!(
+            "{} {} queries matching '{}'{}",
+            "Running".bold(),
+            queries.len(),
+            pattern.cyan(),
+            if parallel > 1 {
+                format!(" ({parallel} at a time)")
+            } else {
+                String::new()
+            }
+        );
+    }
+
+    let mut tasks = JoinSet::new();
+    let mut entries = Vec::with_capacity(queries.len());
+    let mut interrupted = false;
+    for (index, query) in queries.iter().enumerate() {
+        while tasks.len() >= parallel {
+            let (index, entry) = finished(tasks.join_next().await, args.quiet)?;
+            interrupted |= is_interrupted(&entry.result);
+            entries.push((index, entry));
+        }
+        if interrupted {
+            break;
+        }
+        let view = config.as_ref().and_then(|config| {
+            common::ContainerView::for_query(
+                config,
+                query,
+                args.db.as_deref(),
+                args.container.as_deref(),
+                &args.targets,
+            )
+        });
+        let format = run::effective_output(
+            args.output.clone(),
+            args.template.is_some(),
+            query,
+            config
+                .as_ref()
+                .and_then(|config| common::configured_output(config, view.as_ref())),
+        )?;
+        let file = if args.check {
+            String::new()
+        } else {
+            out_dir
+                .join(format!("{}.{}", query.name, run::extension(&format)))
+                .display()
+                .to_string()
+        };
+        let run_args = RunArgs {
+            name: Some(query.name.clone()),
+            prompt: false,
+            out: (!args.check).then(|| file.clone()),
+            // Pinning the format keeps `chart:` out of the result files
+            output: Some(format.clone()),
+            // Hooks can't be confirmed mid-batch: only run them with --run-hooks
+            hooks: match args.hooks {
+                HookPolicy::Always => HookPolicy::Always,
+                _ => HookPolicy::Never,
+            },
+            quiet: true,
+            ..args.clone()
+        };
+        let name = query.name.clone();
+        tasks.spawn(async move {
+            let started = Instant::now();
+            let result = run::execute(run_args).await;
+            (
+                index,
+                BatchEntry {
+                    name,
+                    file,
+                    seconds: started.elapsed().as_secs_f64(),
+                    result,
+                },
+            )
+        });
+    }
+    while !tasks.is_empty() {
+        entries.push(finished(tasks.join_next().await, args.quiet)?);
+    }
+    entries.sort_by_key(|(index, _)| *index);
+
+    let rows: Vec<_> = entries
+        .iter()
+        .map(|(_, entry)| match entry.result {
+            Ok(ref summary) => json!({
+                "query": entry.name,
+                "rows": summary.documents,
+                "RUs": format!("{:.2}", summary.request_charge),
+                "seconds": format!("{:.1}", entry.seconds),
+                "status": "ok",
+                "file": entry.file,
+            }),
+            Err(ref e) => json!({
+                "query": entry.name,
+                "rows": null,
+                "RUs": null,
+                "seconds": format!("{:.1}", entry.seconds),
+                "status": if e.is::<common::Interrupted>() { "interrupted" } else { "failed" },
+                "file": null,
+            }),
+        })
+        .collect();
+    let mut out = Vec::new();
+    write_results(&mut out, &rows, &OutputFormat::Table)?;
+    print!("{}", String::from_utf8_lossy(&out));
+
+    let skipped = queries.len() - entries.len();
+    if skipped > 0 {
+        eprintln!(
+            "{} {skipped} queries were not run after Ctrl+C",
+            "Stopped:".yellow().bold()
+        );
+    }
+    let failed = entries.iter().filter(|(_, e)| e.result.is_err()).count();
+    if failed > 0 {
+        bail!("{failed} of {} queries failed", entries.len());
+    }
+    Ok(())
+}
+
+fn is_interrupted(result: &Result<RunSummary>) -> bool {
+    result
+        .as_ref()
+        .is_err_and(|e| e.is::<common::Interrupted>())
+}
+
+/// Take a finished task's entry, printing a progress line (failures even when quiet).
+fn finished(
+    joined: Option<Result<(usize, BatchEntry), tokio::task::JoinError>>,
+    quiet: bool,
+) -> Result<(usize, BatchEntry)> {
+    let (index, entry) = joined
+        .context("no batch task running")?
+        .context("batch task panicked")?;
+    match entry.result {
+        Ok(ref summary) if !quiet => eprintln!(
+            "  {} {} {}",
+            "✓".green(),
+            entry.name.cyan(),
+            format!(
+                "({} rows, {:.2} RUs)",
+                summary.documents, summary.request_charge
+            )
+            .dimmed()
+        ),
+        Ok(_) => {}
+        Err(ref e) => eprintln!("  {} {}: {e:#}", "✗".red(), entry.name.cyan()),
+    }
+    Ok((index, entry))
+}
@@ -1,23 +1,1058 @@
 //! Shared helpers for CLI commands
 //!
-//! Database and container resolution with the standard fallback chain:
+//! Client construction from account config, and database and container
+//! resolution with the standard fallback chain:
 //! CLI flag > stored query metadata > config > interactive picker.
 
 use anyhow::{Context, Result, bail};
 use colored::Colorize;
-use cosq_client::cosmos::CosmosClient;
-use cosq_core::config::Config;
-use inquire::Select;
+use std::io::IsTerminal;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, Once, Weak};
+use std::time::Duration;
+
+use cosq_client::arm::{ArmClient, account_resource_id};
+use cosq_client::backend::CosmosBackend;
+use cosq_client::cosmos::{CosmosClient, PartitionStats, QueryResult, ResponseDiagnostics};
+use cosq_client::error::ClientError;
+use cosq_client::retry::RetryPolicy;
+use cosq_core::config::{Config, SlowQueryConfig};
+use cosq_core::encryption::EncryptionKey;
+use cosq_core::lint::{
+    SqlShape, composite_index_for, is_analytical_scan, is_composite_index_error, sql_shape,
+};
+use cosq_core::listing_cache::{AccountListings, Listing};
+use cosq_core::plan_cache;
+use cosq_core::prefs::ContainerPrefs;
+use cosq_core::redact::Redactor;
+use cosq_core::result_cache::{self, CachedResult};
+use cosq_core::secrets;
+use cosq_core::stats::{AD_HOC, UsageStats};
+use cosq_core::stored_query::StoredQuery;
+use inquire::{Confirm, InquireError, MultiSelect, Select};
+use serde_json::Value;
+use tokio::task::JoinSet;
+
+use crate::output::{OutputFormat, RowLimit};
+
+/// Set by the global `--offline` flag
+static OFFLINE: AtomicBool = AtomicBool::new(false);
+
+pub fn set_offline(offline: bool) {
+    OFFLINE.store(offline, Ordering::Relaxed);
+}
+
+pub fn is_offline() -> bool {
+    OFFLINE.load(Ordering::Relaxed)
+}
+
+/// Set by the global `--no-ai-data` flag
+static NO_AI_DATA: AtomicBool = AtomicBool::new(false);
+
+pub fn set_no_ai_data(no_ai_data: bool) {
+    NO_AI_DATA.store(no_ai_data, Ordering::Relaxed);
+}
+
+pub fn is_no_ai_data() -> bool {
+    NO_AI_DATA.load(Ordering::Relaxed)
+}
+
+/// Set by the global `--show-headers` flag
+static SHOW_HEADERS: AtomicBool = AtomicBool::new(false);
+
+pub fn set_show_headers(show: bool) {
+    SHOW_HEADERS.store(show, Ordering::Relaxed);
+}
+
+/// Fail fast when `--offline` is set and `what` would need the network.
+pub fn ensure_online(what: &str) -> Result<()> {
+    if is_offline() {
+        bail!("{what} needs network access, but --offline is set");
+    }
+    Ok(())
+}
+
+/// Exit code after printing the partial results of a query stopped with Ctrl+C
+/// (the shell convention for SIGINT)
+pub const INTERRUPTED_EXIT_CODE: i32 = 130;
+
+/// Ctrl+C handling while a query runs.
+///
+/// One handler serves the whole process, so queries running side by side
+/// (`cosq run --all --parallel`) each get their own [`Interrupt`]. The first
+/// Ctrl+C sets the cancellation flag of every unfinished query, whose client
+/// then stops requesting further pages and returns what it has, marked
+/// partial. A second Ctrl+C, or one while no query is fetching (all dropped or
+/// past [`Interrupt::finish`]), exits immediately.
+pub struct Interrupt {
+    state: Arc<InterruptState>,
+}
+
+struct InterruptState {
+    cancelled: Arc<AtomicBool>,
+    finished: AtomicBool,
+}
+
+/// Queries the Ctrl+C handler cancels; dropped [`Interrupt`]s fall out on their own
+static RUNNING: Mutex<Vec<Weak<InterruptState>>> = Mutex::new(Vec::new());
+static HANDLER: Once = Once::new();
+
+impl Interrupt {
+    pub fn install() -> Self {
+        let state = Arc::new(InterruptState {
+            cancelled: Arc::new(AtomicBool::new(false)),
+            finished: AtomicBool::new(false),
+        });
+        {
+            let mut running = RUNNING.lock().unwrap();
+            running.retain(|s| s.strong_count() > 0);
+            running.push(Arc::downgrade(&state));
+        }
+        HANDLER.call_once(|| {
+            tokio::spawn(async {
+                while tokio::signal::ctrl_c().await.is_ok() {
+                    if !Self::cancel_running() {
+                        std::process::exit(INTERRUPTED_EXIT_CODE);
+                    }
+                    eprintln!(
+                        "\n{} stopping after the current page (press Ctrl+C again to abort)",
+                        "Interrupted:".yellow().bold()
+                    );
+                }
+            });
+        });
+        Self { state }
+    }
+
+    /// Cancel every query still fetching; false when there is none, or they
+    /// were already cancelled, and Ctrl+C should exit instead.
+    fn cancel_running() -> bool {
+        let fetching: Vec<_> = RUNNING
+            .lock()
+            .unwrap()
+            .iter()
+            .filter_map(Weak::upgrade)
+            .filter(|s| !s.finished.load(Ordering::Relaxed))
+            .collect();
+        if fetching.is_empty() || fetching.iter().any(|s| s.cancelled.load(Ordering::Relaxed)) {
+            return false;
+        }
+        for state in fetching {
+            state.cancelled.store(true, Ordering::Relaxed);
+        }
+        true
+    }
+
+    /// Flag for [`CosmosClient::with_cancellation`].
+    pub fn flag(&self) -> Arc<AtomicBool> {
+        self.state.cancelled.clone()
+    }
+
+    /// The query is done fetching; Ctrl+C no longer waits for it.
+    pub fn finish(&self) {
+        self.state.finished.store(true, Ordering::Relaxed);
+    }
+}
+
+/// A query stopped with Ctrl+C, returned after its partial results were
+/// written; `main` exits with [`INTERRUPTED_EXIT_CODE`] and batches report it.
+#[derive(Debug)]
+pub struct Interrupted {
+    documents: usize,
+}
+
+impl std::fmt::Display for Interrupted {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "the query was interrupted after fetching {} documents",
+            self.documents
+        )
+    }
+}
+
+impl std::error::Error for Interrupted {}
+
+/// After printing the results of an interrupted query: report the RUs spent
+/// and return the [`Interrupted`] error saying the results are partial.
+pub fn partial_results(
+    config: &Config,
+    name: &str,
+    documents: usize,
+    request_charge: f64,
+    quiet: bool,
+) -> anyhow::Error {
+    report_charge(config, name, request_charge, quiet);
+    Interrupted { documents }.into()
+}
+
+/// Create a Cosmos client for the configured account: master key auth when a
+/// key is configured (plaintext or a `keyring:` reference), otherwise an AAD token from the Azure CLI, with the
+/// `retry:` policy from the config.
+pub async fn connect(config: &Config) -> Result<CosmosClient> {
+    ensure_online("Connecting to Cosmos DB")?;
+    let account = &config.account;
+    let mut builder = CosmosClient::builder(&account.endpoint);
+    if let Some(ref key) = account.master_key {
+        let key = secrets::resolve(key)
+            .with_context(|| format!("failed to load master key for account '{}'", account.name))?;
+        builder = builder.master_key(key);
+    }
+    if let Some(ref path) = account.ca_cert {
+        let pem = std::fs::read(path)
+            .with_context(|| format!("failed to read CA certificate: {path}"))?;
+        builder = builder.root_certificate(pem);
+    }
+    if let Some(ref retry) = config.retry {
+        builder = builder.retry_policy(RetryPolicy::from_config(retry));
+    }
+    let client = builder.build().await?;
+    // Every response is kept for `cosq debug bundle`
+    let show_headers = SHOW_HEADERS.load(Ordering::Relaxed);
+    let client = client.with_diagnostics(Arc::new(move |d: &ResponseDiagnostics| {
+        super::debug::record_response(d);
+        if show_headers {
+            eprintln!("{}", format_diagnostics(d).dimmed());
+        }
+    }));
+    Ok(client)
+}
+
+/// One stderr line per response for `--show-headers`.
+fn format_diagnostics(d: &ResponseDiagnostics) -> String {
+    let mut line = format!("[{} {} → {}]", d.method, d.path, d.status);
+    if let Some(ref id) = d.activity_id {
+        line.push_str(&format!(" activity-id={id}"));
+    }
+    if let Some(ref token) = d.session_token {
+        line.push_str(&format!(" session-token={token}"));
+    }
+    if let Some(ms) = d.server_duration_ms {
+        line.push_str(&format!(" server-latency={ms}ms"));
+    }
+    if let Some(charge) = d.request_charge {
+        line.push_str(&format!(" request-charge={charge}"));
+    }
+    if let Some(ms) = d.retry_after_ms {
+        line.push_str(&format!(" retry-after={ms}ms"));
+    }
+    line
+}
+
+/// Decrypt `encrypted: true` parameter defaults with the key from the OS
+/// keyring (or `COSQ_ENCRYPTION_KEY`); a no-op for queries without any.
+pub fn decrypt_params(query: &mut StoredQuery) -> Result<()> {
+    if query.has_encrypted_params() {
+        let key = EncryptionKey::load()?;
+        query.decrypt_params(&key)?;
+    }
+    Ok(())
+}
+
+/// Redactor for query output: the config's `redact:` patterns when `--redact`
+/// is set, otherwise a no-op.
+pub fn output_redactor(config: &Config, enabled: bool) -> Redactor {
+    if !enabled {
+        return Redactor::default();
+    }
+    if config.redact.is_empty() {
+        eprintln!(
+            "{} --redact is set but the config has no `redact:` patterns",
+            "Warning:".yellow().bold()
+        );
+    }
+    Redactor::new(&config.redact)
+}
+
+/// Redactor for data sent to AI providers (always applies `redact:` patterns).
+pub fn ai_redactor(config: &Config) -> Redactor {
+    Redactor::new(&config.redact)
+}
+
+/// Sample documents as they may be included in an AI prompt: redacted and cut
+/// to the `ai_data:` limits, or `None` when only the schema may be sent
+/// (`--no-ai-data` or `ai_data.schema_only`).
+pub fn ai_documents(config: &Config, documents: &[Value]) -> Option<Vec<Value>> {
+    let limits = config.ai_data.clone().unwrap_or_default();
+    if limits.schema_only || is_no_ai_data() {
+        return None;
+    }
+    let mut documents: Vec<Value> = documents
+        .iter()
+        .take(limits.max_documents())
+        .cloned()
+        .collect();
+    limits.limit(&mut documents);
+    ai_redactor(config).redact_all(&mut documents);
+    Some(documents)
+}
+
+/// Field names, types and presence of `documents`, for prompts that may not
+/// include document values.
+pub fn ai_schema_summary(documents: &[Value]) -> String {
+    format!(
+        "(document values withheld; fields of {} sampled documents)\n{}",
+        documents.len(),
+        cosq_core::schema::Schema::infer(documents).summary()
+    )
+}
+
+/// Apply `--head` / `--tail`, noting on stderr when rows were dropped.
+pub fn apply_row_limit(limit: Option<RowLimit>, documents: &mut Vec<Value>, quiet: bool) {
+    let Some(limit) = limit else { return };
+    let total = limit.apply(documents);
+    if !quiet && documents.len() < total {
+        let which = match limit {
+            RowLimit::Head(_) => "first",
+            RowLimit::Tail(_) => "last",
+        };
+        eprintln!(
+            "{}",
+            format!("Showing {which} {} of {total} results", documents.len()).dimmed()
+        );
+    }
+}
+
+/// Print the request charge (with an estimated cost when `pricing:` is
+/// configured) and add the run to the cumulative usage stats.
+pub fn report_charge(config: &Config, name: &str, request_charge: f64, quiet: bool) {
+    if !quiet {
+        let cost = config.pricing.as_ref().and_then(|p| {
+            p.cost(request_charge)
+                .map(|c| format!(" (≈ {})", p.format_cost(c)))
+        });
+        eprintln!(
+            "\n{} {:.2} RUs{}",
+            "Request charge:".dimmed(),
+            request_charge,
+            cost.unwrap_or_default().dimmed()
+        );
+    }
+    let now = chrono::Utc::now().to_rfc3339();
+    if let Err(e) = UsageStats::record_run(name, request_charge, Some(now)) {
+        tracing::debug!(error = %e, "failed to record usage stats");
+    }
+}
+
+/// Cache gateway query plans for a client that runs a stored query, which
+/// tends to run again (schedules, `--watch`). Ad-hoc queries don't fetch
+/// plans.
+pub fn with_plan_cache(client: CosmosClient) -> CosmosClient {
+    match plan_cache::cache_dir() {
+        Ok(dir) => client.with_plan_cache(dir),
+        Err(_) => client,
+    }
+}
+
+/// Failures listed before the summary of a bulk operation
+pub const MAX_REPORTED_FAILURES: usize = 5;
+
+/// Tasks of a bulk operation (one per document), at most `limit` running at
+/// once. Keeps the value of every task that succeeded and the message of
+/// every one that failed.
+pub struct BoundedTasks<T> {
+    tasks: JoinSet<std::result::Result<T, String>>,
+    limit: usize,
+    succeeded: Vec<T>,
+    failures: Vec<String>,
+}
+
+impl<T: Send + 'static> BoundedTasks<T> {
+    pub fn new(limit: usize) -> Self {
+        Self {
+            tasks: JoinSet::new(),
+            limit: limit.max(1),
+            succeeded: Vec::new(),
+            failures: Vec::new(),
+        }
+    }
+
+    /// Spawn `task`, first waiting for a running one to finish if `limit`
+    /// are in flight.
+    pub async fn spawn(
+        &mut self,
+        task: impl Future<Output = std::result::Result<T, String>> + Send + 'static,
+    ) {
+        while self.tasks.len() >= self.limit {
+            self.join_next().await;
+        }
+        self.tasks.spawn(task);
+    }
+
+    /// Wait for every running task.
+    pub async fn wait(&mut self) {
+        while !self.tasks.is_empty() {
+            self.join_next().await;
+        }
+    }
+
+    /// Count a failure that happened before a task could be spawned.
+    pub fn fail(&mut self, failure: String) {
+        self.failures.push(failure);
+    }
+
+    pub fn succeeded(&self) -> &[T] {
+        &self.succeeded
+    }
+
+    pub fn failures(&self) -> &[String] {
+        &self.failures
+    }
+
+    async fn join_next(&mut self) {
+        match self.tasks.join_next().await {
+            Some(Ok(Ok(value))) => self.succeeded.push(value),
+            Some(Ok(Err(failure))) => self.failures.push(failure),
+            Some(Err(e)) => self.failures.push(e.to_string()),
+            None => {}
+        }
+    }
+}
+
+/// Print the first [`MAX_REPORTED_FAILURES`] failures of a bulk operation
+/// and how many more there were.
+pub fn report_failures(failures: &[String]) {
+    for failure in failures.iter().take(MAX_REPORTED_FAILURES) {
+        eprintln!("  {} {failure}", "✗".red());
+    }
+    if failures.len() > MAX_REPORTED_FAILURES {
+        eprintln!("  ... and {} more", failures.len() - MAX_REPORTED_FAILURES);
+    }
+}
+
+/// Partitions listed in the slow query breakdown
+const SLOW_QUERY_TOP_PARTITIONS: usize = 5;
+
+/// Warn on stderr when a run crosses the `slow_query:` thresholds, with the
+/// costliest partitions and a hint on how to narrow the query.
+pub fn warn_if_slow(
+    config: &Config,
+    name: &str,
+    request_charge: f64,
+    elapsed: Duration,
+    partitions: &[PartitionStats],
+) {
+    let thresholds = config.slow_query.clone().unwrap_or_default();
+    if let Some(warning) =
+        slow_query_warning(&thresholds, name, request_charge, elapsed, partitions)
+    {
+        eprintln!("\n{warning}");
+        if let Some(mode) = config.account.capacity_mode {
+            eprintln!("  {}", mode.budget_hint().dimmed());
+        }
+    }
+}
+
+/// Before an obviously analytical full scan (aggregates without a WHERE
+/// filter) runs on the transactional store, point at the container's
+/// analytical store when it has one. Best effort: errors reading the
+/// container are ignored.
+pub async fn warn_if_analytical(
+    client: &CosmosClient,
+    database: &str,
+    container: &str,
+    sql: &str,
+    quiet: bool,
+) {
+    if quiet || !is_analytical_scan(sql) {
+        return;
+    }
+    let Ok(properties) = client.get_container(database, container).await else {
+        return;
+    };
+    if properties.has_analytical_store() {
+        eprintln!(
+            "{} this query aggregates over every document of {database}/{container} on the transactional store, billed in RUs.",
+            "Warning:".yellow().bold()
+        );
+        eprintln!(
+            "  {}",
+            "The container has an analytical store: run reports like this through Synapse Link or Fabric mirroring instead (no RU cost, no impact on production traffic)."
+                .dimmed()
+        );
+    }
+}
+
+fn slow_query_warning(
+    thresholds: &SlowQueryConfig,
+    name: &str,
+    request_charge: f64,
+    elapsed: Duration,
+    partitions: &[PartitionStats],
+) -> Option<String> {
+    let seconds = elapsed.as_secs_f64();
+    if !thresholds.is_slow(request_charge, seconds) {
+        return None;
+    }
+
+    let mut lines = vec![format!(
+        "{} {request_charge:.2} RUs in {seconds:.1}s (thresholds: {} RUs, {}s)",
+        "⚠ Slow query:".yellow().bold(),
+        thresholds.warn_ru(),
+        thresholds.warn_seconds()
+    )];
+    if partitions.len() > 1 {
+        let mut costliest: Vec<&PartitionStats> = partitions.iter().collect();
+        costliest.sort_by(|a, b| b.request_charge.total_cmp(&a.request_charge));
+        lines.push(format!(
+            "  Scanned {} partition key ranges; costliest:",
+            partitions.len()
+        ));
+        for p in costliest.iter().take(SLOW_QUERY_TOP_PARTITIONS) {
+            lines.push(format!(
+                "    range {:<8} {:>10.2} RUs  {} documents",
+                p.range_id, p.request_charge, p.documents
+            ));
+        }
+    }
+    let hint = if name == AD_HOC {
+        "Filter on the partition key to avoid scanning every partition, and select only the fields you need."
+            .to_string()
+    } else {
+        format!(
+            "Run `cosq queries lint {name} --connect` for partition key and indexing suggestions."
+        )
+    };
+    lines.push(format!("  {}", hint.dimmed()));
+    Some(lines.join("\n"))
+}
+
+/// Handle a failed query: when Cosmos DB reports a missing composite index for
+/// a multi-field `ORDER BY`, print the index that would serve it and offer to
+/// add it to the container's indexing policy via ARM. Returns the original error.
+pub async fn advise_on_query_error(
+    err: ClientError,
+    config: &Config,
+    database: &str,
+    container: &str,
+    sql: &str,
+) -> anyhow::Error {
+    if !is_composite_index_error(&err.to_string()) {
+        return err.into();
+    }
+    let Some(index) = composite_index_for(sql) else {
+        return err.into();
+    };
+
+    let entry: Vec<serde_json::Value> = index
+        .iter()
+        .map(|(path, order)| serde_json::json!({ "path": path, "order": order }))
+        .collect();
+    eprintln!(
+        "\n{} this ORDER BY needs a composite index on {}/{}:",
+        "Index advisor:".yellow().bold(),
+        database,
+        container
+    );
+    eprintln!(
+        "  \"compositeIndexes\": [{}]",
+        serde_json::Value::Array(entry)
+    );
+
+    let account = &config.account;
+    if account.subscription.is_empty() || account.resource_group.is_empty() {
+        eprintln!(
+            "{}",
+            "Add it to the container's indexing policy (the account has no subscription/resource group configured, so cosq can't apply it).".dimmed()
+        );
+        return err.into();
+    }
+    if !std::io::stdin().is_terminal() {
+        return err.into();
+    }
+    let apply = Confirm::new("Add this composite index via Azure Resource Manager?")
+        .with_default(false)
+        .prompt()
+        .unwrap_or(false);
+    if !apply {
+        return err.into();
+    }
+
+    let result = async {
+        let arm = ArmClient::new().await?;
+        let id = account_resource_id(
+            &account.subscription,
+            &account.resource_group,
+            &account.name,
+        );
+        arm.add_composite_index(&id, database, container, &index)
+            .await
+    }
+    .await;
+    match result {
+        Ok(true) => eprintln!(
+            "{} indexing policy updated — the index builds in the background; re-run the query once it completes",
+            "✓".green()
+        ),
+        Ok(false) => eprintln!(
+            "{}",
+            "The composite index already exists; it may still be building.".dimmed()
+        ),
+        Err(e) => eprintln!("{} failed to update the indexing policy: {e}", "✗".red()),
+    }
+    err.into()
+}
+
+/// Result cache key for a query against `database`/`container` of the
+/// configured account (`container` is empty for multi-step queries).
+pub fn cache_key(
+    config: &Config,
+    database: &str,
+    container: &str,
+    sql: &str,
+    params: &impl serde::Serialize,
+) -> Result<String> {
+    let params = serde_json::to_string(params)?;
+    Ok(result_cache::key(&[
+        &config.account.name,
+        database,
+        container,
+        sql,
+        &params,
+    ]))
+}
+
+/// A container's saved preferences, and the container they belong to
+pub struct ContainerView {
+    account: String,
+    database: String,
+    container: String,
+    /// Where the preferences come from, for error messages
+    origin: String,
+    pub prefs: ContainerPrefs,
+}
+
+impl ContainerView {
+    /// Load the preferences of the container a query targets, as far as it is
+    /// known without listing the account (`--db`/`--container`, the query's
+    /// front matter, then the config). `None` when no container is known.
+    pub fn load(config: &Config, database: Option<&str>, container: Option<&str>) -> Option<Self> {
+        let database = database.or(config.database.as_deref())?.to_string();
+        let container = container.or(config.container.as_deref())?.to_string();
+        let prefs = ContainerPrefs::load(&config.account.name, &database, &container)
+            .unwrap_or_else(|e| {
+                tracing::debug!(error = %e, "failed to load container preferences");
+                ContainerPrefs::default()
+            });
+        Some(Self {
+            account: config.account.name.clone(),
+            origin: format!("the preferences for {database}/{container}"),
+            database,
+            container,
+            prefs,
+        })
+    }
+
+    /// Load the preferences of the container a stored query reads, unless it
+    /// is a multi-step or federated query (which read several).
+    pub fn for_query(
+        config: &Config,
+        query: &StoredQuery,
+        db: Option<&str>,
+        container: Option<&str>,
+        targets: &[String],
+    ) -> Option<Self> {
+        if query.is_multi_step() || !targets.is_empty() || !query.metadata.targets.is_empty() {
+            return None;
+        }
+        Self::load(
+            config,
+            db.or(query.metadata.database.as_deref()),
+            container.or(query.metadata.container.as_deref()),
+        )
+    }
+
+    /// Apply the preferred columns and sort to table and CSV output. A query's
+    /// own ORDER BY wins over the preferred sort.
+    pub fn apply(&self, format: &OutputFormat, sql: &str, documents: &mut [Value]) {
+        if !matches!(format, OutputFormat::Table | OutputFormat::Csv) {
+            return;
+        }
+        let ordered = regex::Regex::new(r"(?i)\border\s+by\b")
+            .unwrap()
+            .is_match(sql);
+        if ordered && self.prefs.sort.is_some() {
+            let mut prefs = self.prefs.clone();
+            prefs.sort = None;
+            prefs.apply_view(documents);
+        } else {
+            self.prefs.apply_view(documents);
+        }
+    }
+
+    /// Whether [`apply`](Self::apply) would change `format` output (a
+    /// preferred sort or columns for table/CSV).
+    pub fn shapes(&self, format: &OutputFormat) -> bool {
+        matches!(format, OutputFormat::Table | OutputFormat::Csv)
+            && (self.prefs.sort.is_some() || !self.prefs.columns.is_empty())
+    }
+
+    /// Save `columns` as the container's preferred columns.
+    fn save_columns(&self, columns: Vec<String>) -> Result<std::path::PathBuf> {
+        let mut prefs = ContainerPrefs::load(&self.account, &self.database, &self.container)?;
+        prefs.columns = columns;
+        Ok(prefs.save(&self.account, &self.database, &self.container)?)
+    }
+
+    /// Remember `query` (ad-hoc SQL or a stored query name) as recently run
+    /// against this container; failures are logged rather than reported.
+    pub fn record_query(&self, query: &str) {
+        let result = ContainerPrefs::load(&self.account, &self.database, &self.container).and_then(
+            |mut prefs| {
+                prefs.record_query(query);
+                prefs.save(&self.account, &self.database, &self.container)
+            },
+        );
+        if let Err(e) = result {
+            tracing::debug!(error = %e, "failed to record recent query");
+        }
+    }
+}
+
+/// When a table would be wider than the terminal, ask which columns to show
+/// instead of letting every row wrap. The choice becomes the container's
+/// preferred columns, so each container is only asked once.
+pub fn fit_table(
+    view: Option<&ContainerView>,
+    format: &OutputFormat,
+    to_terminal: bool,
+    quiet: bool,
+    documents: &mut [Value],
+) -> Result<()> {
+    if !matches!(format, OutputFormat::Table) || !to_terminal || documents.is_empty() {
+        return Ok(());
+    }
+    if view.is_some_and(|v| !v.prefs.columns.is_empty()) || !std::io::stdin().is_terminal() {
+        return Ok(());
+    }
+    let Some(width) = crate::output::terminal_width() else {
+        return Ok(());
+    };
+    let columns = crate::output::table_columns(documents);
+    let total = columns.iter().map(|(_, w)| w).sum::<usize>() + 1;
+    if total <= width {
+        return Ok(());
+    }
+
+    // Preselect the leading columns that fit
+    let mut used = 1;
+    let fitting: Vec<usize> = columns
+        .iter()
+        .take_while(|(_, w)| {
+            used += w;
+            used <= width
+        })
+        .enumerate()
+        .map(|(i, _)| i)
+        .collect();
+    let names: Vec<&str> = columns.iter().map(|(c, _)| c.as_str()).collect();
+    let chosen = match MultiSelect::new(
+        &format!("The table is {total} characters wide (terminal: {width}). Columns to show:"),
+        names,
+    )
+    .with_default(&fitting)
+    .prompt()
+    {
+        Ok(chosen) if !chosen.is_empty() => chosen,
+        // Esc or nothing selected: show every column
+        Ok(_) | Err(InquireError::OperationCanceled) => return Ok(()),
+        Err(e) => return Err(e.into()),
+    };
+    let chosen: Vec<String> = chosen.into_iter().map(str::to_string).collect();
+    ContainerPrefs {
+        columns: chosen.clone(),
+        ..Default::default()
+    }
+    .apply_view(documents);
+
+    if let Some(view) = view {
+        let path = view.save_columns(chosen)?;
+        if !quiet {
+            eprintln!(
+                "{}",
+                format!(
+                    "Saved as the columns for {}/{} ({}); change them with `cosq prefs set --columns` or `cosq prefs clear`",
+                    view.database,
+                    view.container,
+                    path.display()
+                )
+                .dimmed()
+            );
+        }
+    }
+    Ok(())
+}
+
+/// The output format chosen outside the query, with where it was set: the
+/// container's preferences, then `output:` in the config.
+pub fn configured_output<'a>(
+    config: &'a Config,
+    view: Option<&'a ContainerView>,
+) -> Option<(&'a str, &'a str)> {
+    view.and_then(|v| {
+        v.prefs
+            .output
+            .as_deref()
+            .map(|name| (name, v.origin.as_str()))
+    })
+    .or_else(|| {
+        config
+            .output
+            .as_deref()
+            .map(|name| (name, "the config's `output:`"))
+    })
+}
+
+/// Store results for offline replay; failures only affect `--offline`, so
+/// they are logged rather than reported.
+pub fn cache_results(
+    key: &str,
+    request_charge: f64,
+    documents: Vec<Value>,
+    steps: std::collections::BTreeMap<String, Vec<Value>>,
+) {
+    let cached = CachedResult {
+        cached_at: chrono::Utc::now().to_rfc3339(),
+        request_charge,
+        documents,
+        steps,
+    };
+    super::session::record_results(key, &cached);
+    if let Err(e) = result_cache::save(key, &cached) {
+        tracing::debug!("failed to cache results: {e}");
+    }
+}
+
+/// Load cached results for `--offline`.
+pub fn cached_results(key: &str, quiet: bool) -> Result<CachedResult> {
+    if let Some(recorded) = super::session::replayed_results(key) {
+        return Ok(recorded);
+    }
+    let cached = result_cache::load(key)?.context(
+        "No cached results for this query. Run it once without --offline \
+         (with the same database, container and parameters), or use --from-file.",
+    )?;
+    if !quiet {
+        eprintln!(
+            "{} {}",
+            "Using cached results from".dimmed(),
+            cached.cached_at.cyan()
+        );
+    }
+    Ok(cached)
+}
+
+/// Print the `--template-debug` report for a failed render and return the
+/// error to exit with.
+pub fn template_debug_failure(
+    template: &str,
+    error: &anyhow::Error,
+    context: &std::collections::BTreeMap<String, Value>,
+) -> anyhow::Error {
+    eprint!(
+        "\n{}",
+        crate::output::template_debug_report(template, error, context)
+    );
+    anyhow::anyhow!("failed to render the template (see above)")
+}
+
+/// Results for `--from-file`, in place of running the query.
+pub fn replay_file(path: &Path, quiet: bool) -> Result<QueryResult> {
+    let documents = load_documents(path)?;
+    if !quiet {
+        eprintln!(
+            "{} {} {}",
+            "Using".dimmed(),
+            documents.len(),
+            format!(
+                "documents from {} (the SQL is not evaluated)",
+                path.display()
+            )
+            .dimmed()
+        );
+    }
+    Ok(QueryResult {
+        documents,
+        request_charge: 0.0,
+        partitions: Vec::new(),
+        partial: false,
+    })
+}
+
+/// Results for `cosq query --from-file <FILE> <SQL>`: the SQL evaluated
+/// locally against the file's documents (see [`cosq_core::local_query`]).
+pub fn query_file(path: &Path, sql: &str, quiet: bool) -> Result<QueryResult> {
+    let query = cosq_core::local_query::LocalQuery::parse(sql)?;
+    let loaded = load_documents(path)?;
+    let scanned = loaded.len();
+    let documents = query.execute(loaded);
+    if !quiet {
+        eprintln!(
+            "{} {} {}",
+            "Evaluated locally against".dimmed(),
+            scanned,
+            format!("documents from {}", path.display()).dimmed()
+        );
+    }
+    Ok(QueryResult {
+        documents,
+        request_charge: 0.0,
+        partitions: Vec::new(),
+        partial: false,
+    })
+}
+
+/// Resolve a database or container name without listing the account
+/// (`--offline`): `cli` > `metadata` > `configured` > the pick of a replayed
+/// session, with no picker fallback.
+pub fn resolve_offline(
+    kind: &str,
+    cli: Option<String>,
+    metadata: Option<&str>,
+    configured: Option<&str>,
+) -> Result<String> {
+    let picker = if kind == "db" { "database" } else { kind };
+    cli.or_else(|| metadata.map(str::to_string))
+        .or_else(|| configured.map(str::to_string))
+        .or_else(|| {
+            super::session::replayed_answer(&picker_prompt(picker))?
+                .as_str()
+                .map(str::to_string)
+        })
+        .with_context(|| format!("No {kind} configured; pass --{kind} when using --offline"))
+}
+
+/// Load documents for `--from-file`: a JSON array, or one JSON document per
+/// line (NDJSON, as written by `--output json-compact`).
+pub fn load_documents(path: &Path) -> Result<Vec<Value>> {
+    let contents = std::fs::read_to_string(path)
+        .with_context(|| format!("failed to read {}", path.display()))?;
+    parse_documents(&contents).with_context(|| format!("invalid data in {}", path.display()))
+}
+
+fn parse_documents(contents: &str) -> Result<Vec<Value>> {
+    if contents.trim_start().starts_with('[') {
+        return Ok(serde_json::from_str(contents)?);
+    }
+    // A `cosq snapshot create` file
+    if contents.trim_start().starts_with("{\"manifest\"") {
+        let snapshot: cosq_core::snapshot::Snapshot = serde_json::from_str(contents)?;
+        snapshot.verify()?;
+        return Ok(snapshot.documents);
+    }
+    contents
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(i, line)| serde_json::from_str(line).with_context(|| format!("line {}", i + 1)))
+        .collect()
+}
+
+/// Check SQL bound for a container marked `protected: true` in the config.
+///
+/// A single SELECT passes. Anything else (a write statement, several
+/// statements, an unrecognised statement) needs `force`, or confirmation when
+/// `interactive` and stdin is a terminal.
+pub fn guard_protected(
+    config: &Config,
+    database: &str,
+    container: &str,
+    sql: &str,
+    force: bool,
+    interactive: bool,
+) -> Result<()> {
+    if force || !config.is_protected(database, container) {
+        return Ok(());
+    }
+    let what = match sql_shape(sql) {
+        SqlShape::Select => return Ok(()),
+        SqlShape::Write(keyword) => format!("a {keyword} statement"),
+        SqlShape::Multiple => "several statements".to_string(),
+        SqlShape::Other => "SQL that is not a SELECT".to_string(),
+    };
+    confirm_protected(database, container, &what, interactive)
+}
+
+/// Ask before `what` is sent to a protected container; without a terminal
+/// (or when not `interactive`) fail with a hint to pass `--force`.
+fn confirm_protected(database: &str, container: &str, what: &str, interactive: bool) -> Result<()> {
+    if !interactive || !std::io::stdin().is_terminal() {
+        bail!(
+            "{database}/{container} is protected and this would send {what}; pass --force to proceed"
+        );
+    }
+    eprintln!(
+        "{} {database}/{container} is marked protected in the config",
+        "Warning:".yellow().bold()
+    );
+    let proceed = Confirm::new(&format!("Send {what} to {database}/{container}?"))
+        .with_default(false)
+        .prompt()
+        .unwrap_or(false);
+    if !proceed {
+        bail!("cancelled: {database}/{container} is protected");
+    }
+    Ok(())
+}
+
+/// Confirm a write of `what` (e.g. "a delete of document 'x'") before it
+/// reaches `database/container`.
+///
+/// Protected containers ask even with `yes`; only `force` skips that. Other
+/// containers ask `prompt` unless `yes` (no prompt: no confirmation needed).
+/// Without a terminal either question fails with a hint to pass `--force` or
+/// `--yes`, rather than writing unconfirmed.
+pub fn confirm_destructive(
+    config: &Config,
+    database: &str,
+    container: &str,
+    what: &str,
+    prompt: Option<&str>,
+    yes: bool,
+    force: bool,
+) -> Result<()> {
+    if !force && config.is_protected(database, container) {
+        return confirm_protected(database, container, what, true);
+    }
+    let Some(prompt) = prompt.filter(|_| !yes) else {
+        return Ok(());
+    };
+    if !std::io::stdin().is_terminal() {
+        bail!(
+            "refusing to send {what} to {database}/{container} without confirmation — pass --yes"
+        );
+    }
+    let proceed = Confirm::new(prompt)
+        .with_default(false)
+        .prompt()
+        .context("confirmation cancelled")?;
+    if !proceed {
+        bail!("cancelled: nothing was sent to {database}/{container}");
+    }
+    Ok(())
+}
 
 /// Resolve which database to target.
 ///
 /// Fallback chain: `cli` > `metadata` > `config.database` > interactive picker.
 /// Returns the database name and whether the config was updated (needs save).
 pub async fn resolve_database(
-    client: &CosmosClient,
+    client: &impl CosmosBackend,
     config: &mut Config,
     cli: Option<String>,
     metadata: Option<&str>,
+) -> Result<(String, bool)> {
+    let cache = AccountListings::path(&config.account.name).ok();
+    resolve_database_in(client, config, cli, metadata, cache.as_deref()).await
+}
+
+async fn resolve_database_in(
+    client: &impl CosmosBackend,
+    config: &mut Config,
+    cli: Option<String>,
+    metadata: Option<&str>,
+    cache: Option<&Path>,
 ) -> Result<(String, bool)> {
     if let Some(db) = cli {
         return Ok((db, false));
@@ -29,21 +1064,18 @@ pub async fn resolve_database(
         return Ok((db.clone(), false));
     }
 
-    let databases = client.list_databases().await?;
-    if databases.is_empty() {
+    let cached = cache.and_then(|path| AccountListings::load_from(path).ok()?.databases);
+    let picked = pick_listed("database", cached, client.list_databases(), |names| {
+        store_listing(cache, |listings| {
+            listings.databases = Some(Listing::new(names));
+        })
+    })
+    .await?;
+    let Some(db) = picked else {
         bail!(
             "No databases found in Cosmos DB account '{}'.",
             config.account.name
         );
-    }
-
-    let db = if databases.len() == 1 {
-        eprintln!("{} {}", "Using database:".bold(), databases[0].green());
-        databases[0].clone()
-    } else {
-        Select::new("Select a database:", databases.clone())
-            .prompt()
-            .context("database selection cancelled")?
     };
 
     config.database = Some(db.clone());
@@ -55,11 +1087,23 @@ pub async fn resolve_database(
 /// Fallback chain: `cli` > `metadata` > `config.container` > interactive picker.
 /// Returns the container name and whether the config was updated (needs save).
 pub async fn resolve_container(
-    client: &CosmosClient,
+    client: &impl CosmosBackend,
+    config: &mut Config,
+    database: &str,
+    cli: Option<String>,
+    metadata: Option<&str>,
+) -> Result<(String, bool)> {
+    let cache = AccountListings::path(&config.account.name).ok();
+    resolve_container_in(client, config, database, cli, metadata, cache.as_deref()).await
+}
+
+async fn resolve_container_in(
+    client: &impl CosmosBackend,
     config: &mut Config,
     database: &str,
     cli: Option<String>,
     metadata: Option<&str>,
+    cache: Option<&Path>,
 ) -> Result<(String, bool)> {
     if let Some(ctr) = cli {
         return Ok((ctr, false));
@@ -71,20 +1115,439 @@ pub async fn resolve_container(
         return Ok((ctr.clone(), false));
     }
 
-    let containers = client.list_containers(database).await?;
-    if containers.is_empty() {
+    let cached = cache.and_then(|path| {
+        AccountListings::load_from(path)
+            .ok()?
+            .containers
+            .remove(database)
+    });
+    let picked = pick_listed(
+        "container",
+        cached,
+        client.list_containers(database),
+        |names| {
+            store_listing(cache, |listings| {
+                listings
+                    .containers
+                    .insert(database.to_string(), Listing::new(names));
+            })
+        },
+    )
+    .await?;
+    let Some(ctr) = picked else {
         bail!("No containers found in database '{database}'.");
-    }
-
-    let ctr = if containers.len() == 1 {
-        eprintln!("{} {}", "Using container:".bold(), containers[0].green());
-        containers[0].clone()
-    } else {
-        Select::new("Select a container:", containers.clone())
-            .prompt()
-            .context("container selection cancelled")?
     };
 
     config.container = Some(ctr.clone());
     Ok((ctr, true))
 }
+
+/// Container names of a database: the cached listing if it is fresh,
+/// otherwise listed from the account (and cached).
+pub async fn list_containers_cached(
+    client: &impl CosmosBackend,
+    account: &str,
+    database: &str,
+) -> Result<Vec<String>> {
+    let cache = AccountListings::path(account).ok();
+    if let Some(listing) = cache
+        .as_deref()
+        .and_then(|path| {
+            AccountListings::load_from(path)
+                .ok()?
+                .containers
+                .remove(database)
+        })
+        .filter(|listing| !listing.names.is_empty() && listing.is_fresh(chrono::Utc::now()))
+    {
+        return Ok(listing.names);
+    }
+    let names = client.list_containers(database).await?;
+    if !names.is_empty() {
+        store_listing(cache.as_deref(), |listings| {
+            listings
+                .containers
+                .insert(database.to_string(), Listing::new(names.clone()));
+        });
+    }
+    Ok(names)
+}
+
+/// Pick a database or container name from a listing.
+///
+/// A fresh cached listing is used as is. A stale one is shown right away
+/// while `list` refreshes it; without one, `list` runs first. Non-empty
+/// results of `list` are passed to `store`. Returns `None` when there is
+/// nothing to pick from.
+async fn pick_listed(
+    kind: &'static str,
+    cached: Option<Listing>,
+    list: impl Future<Output = Result<Vec<String>, ClientError>>,
+    store: impl FnOnce(Vec<String>),
+) -> Result<Option<String>> {
+    let store_nonempty = |names: Vec<String>| {
+        if !names.is_empty() {
+            store(names.clone());
+        }
+        names
+    };
+    match cached.filter(|listing| !listing.names.is_empty()) {
+        Some(listing) if listing.is_fresh(chrono::Utc::now()) => {
+            pick_name(kind, listing.names).await
+        }
+        Some(listing) => {
+            let (picked, refreshed) = tokio::join!(pick_name(kind, listing.names), list);
+            match refreshed {
+                Ok(names) => {
+                    store_nonempty(names);
+                }
+                Err(e) => tracing::debug!(error = %e, "failed to refresh {kind} listing"),
+            }
+            picked
+        }
+        None => {
+            let names = store_nonempty(list.await?);
+            pick_name(kind, names).await
+        }
+    }
+}
+
+/// Use the only name, or prompt for one without blocking the runtime (so a
+/// listing can refresh meanwhile).
+async fn pick_name(kind: &'static str, mut names: Vec<String>) -> Result<Option<String>> {
+    let name = match names.len() {
+        0 => return Ok(None),
+        1 => {
+            let name = names.remove(0);
+            eprintln!("{} {}", format!("Using {kind}:").bold(), name.green());
+            name
+        }
+        _ => {
+            let picked = tokio::task::spawn_blocking(move || {
+                Select::new(&picker_prompt(kind), names).prompt()
+            })
+            .await?;
+            picked.with_context(|| format!("{kind} selection cancelled"))?
+        }
+    };
+    // A replay runs offline, where nothing can be listed to pick from
+    super::session::record_answer(&picker_prompt(kind), &Value::String(name.clone()));
+    Ok(Some(name))
+}
+
+fn picker_prompt(kind: &str) -> String {
+    format!("Select a {kind}:")
+}
+
+/// Update the listing cache, if there is one. Failures only cost a later
+/// listing, so they are logged rather than reported.
+fn store_listing(cache: Option<&Path>, change: impl FnOnce(&mut AccountListings)) {
+    if let Some(path) = cache {
+        if let Err(e) = AccountListings::update(path, change) {
+            tracing::debug!(error = %e, "failed to update listing cache");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cosq_client::backend::MemoryBackend;
+    use cosq_core::config::AccountConfig;
+    use serde_json::json;
+
+    fn test_config() -> Config {
+        Config {
+            account: AccountConfig {
+                name: "test".into(),
+                subscription: String::new(),
+                resource_group: String::new(),
+                endpoint: "https://test.documents.azure.com:443/".into(),
+                master_key: None,
+                ca_cert: None,
+                capacity_mode: None,
+            },
+            database: None,
+            container: None,
+            profiles: Default::default(),
+            webhooks: Default::default(),
+            storage_account: None,
+            retry: None,
+            pricing: None,
+            redact: Vec::new(),
+            slow_query: None,
+            trusted_commands: Vec::new(),
+            ai_save: None,
+            ai_budget: None,
+            ai_log: false,
+            ai_data: None,
+            ai: None,
+            output: None,
+            http: None,
+            auth: None,
+            containers: Default::default(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_interrupt_cancels_only_running_queries() {
+        let done = Interrupt::install();
+        done.finish();
+        let running = Interrupt::install();
+        let dropped = Interrupt::install();
+        let dropped_flag = dropped.flag();
+        drop(dropped);
+
+        assert!(Interrupt::cancel_running());
+        assert!(running.flag().load(Ordering::Relaxed));
+        assert!(!done.flag().load(Ordering::Relaxed));
+        assert!(!dropped_flag.load(Ordering::Relaxed));
+        // A second Ctrl+C exits
+        assert!(!Interrupt::cancel_running());
+
+        drop(running);
+        assert!(!Interrupt::cancel_running());
+    }
+
+    #[tokio::test]
+    async fn test_resolve_prefers_flags_and_config() {
+        let backend = MemoryBackend::new();
+        let mut config = test_config();
+        config.database = Some("configdb".into());
+
+        let (db, changed) =
+            resolve_database_in(&backend, &mut config, Some("cli".into()), None, None)
+                .await
+                .unwrap();
+        assert_eq!((db.as_str(), changed), ("cli", false));
+
+        let (db, changed) = resolve_database_in(&backend, &mut config, None, Some("meta"), None)
+            .await
+            .unwrap();
+        assert_eq!((db.as_str(), changed), ("meta", false));
+
+        let (db, _) = resolve_database_in(&backend, &mut config, None, None, None)
+            .await
+            .unwrap();
+        assert_eq!(db, "configdb");
+    }
+
+    #[tokio::test]
+    async fn test_resolve_single_database_and_container() {
+        let backend = MemoryBackend::new().with_container("app", "users", "/id");
+        let mut config = test_config();
+
+        let (db, changed) = resolve_database_in(&backend, &mut config, None, None, None)
+            .await
+            .unwrap();
+        assert_eq!((db.as_str(), changed), ("app", true));
+        assert_eq!(config.database.as_deref(), Some("app"));
+
+        let (ctr, changed) = resolve_container_in(&backend, &mut config, &db, None, None, None)
+            .await
+            .unwrap();
+        assert_eq!((ctr.as_str(), changed), ("users", true));
+    }
+
+    #[test]
+    fn test_guard_protected() {
+        let mut config = test_config();
+        config.containers.insert(
+            "app/orders".into(),
+            cosq_core::config::ContainerConfig { protected: true },
+        );
+        let delete = "DELETE FROM c WHERE c.id = '1'";
+        assert!(guard_protected(&config, "app", "orders", "SELECT * FROM c", false, false).is_ok());
+        assert!(guard_protected(&config, "app", "users", delete, false, false).is_ok());
+        assert!(guard_protected(&config, "app", "orders", delete, true, false).is_ok());
+        let err = guard_protected(&config, "app", "orders", delete, false, false)
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("a DELETE statement"));
+        assert!(err.contains("--force"));
+    }
+
+    #[test]
+    fn test_confirm_destructive() {
+        let mut config = test_config();
+        config.containers.insert(
+            "app/orders".into(),
+            cosq_core::config::ContainerConfig { protected: true },
+        );
+        let confirm = |container, prompt, yes, force| {
+            confirm_destructive(&config, "app", container, "a delete", prompt, yes, force)
+        };
+        // --yes covers other containers, only --force covers protected ones
+        assert!(confirm("users", Some("Delete?"), true, false).is_ok());
+        assert!(confirm("orders", Some("Delete?"), true, true).is_ok());
+        // Without a prompt, only protected containers ask
+        assert!(confirm("users", None, false, false).is_ok());
+        if !std::io::stdin().is_terminal() {
+            let err = confirm("orders", None, true, false)
+                .unwrap_err()
+                .to_string();
+            assert!(err.contains("--force"));
+            let err = confirm("users", Some("Delete?"), false, false)
+                .unwrap_err()
+                .to_string();
+            assert!(err.contains("--yes"));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_resolve_uses_cached_listing() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = dir.path().join("test.json");
+        let backend = MemoryBackend::new().with_container("app", "users", "/id");
+        let mut config = test_config();
+        resolve_database_in(&backend, &mut config, None, None, Some(&cache))
+            .await
+            .unwrap();
+        resolve_container_in(&backend, &mut config, "app", None, None, Some(&cache))
+            .await
+            .unwrap();
+        let listings = AccountListings::load_from(&cache).unwrap();
+        assert_eq!(listings.databases.unwrap().names, vec!["app"]);
+        assert_eq!(listings.containers["app"].names, vec!["users"]);
+
+        // A fresh listing is used without asking the account
+        let empty = MemoryBackend::new();
+        let mut config = test_config();
+        let (db, _) = resolve_database_in(&empty, &mut config, None, None, Some(&cache))
+            .await
+            .unwrap();
+        let (ctr, _) = resolve_container_in(&empty, &mut config, &db, None, None, Some(&cache))
+            .await
+            .unwrap();
+        assert_eq!((db.as_str(), ctr.as_str()), ("app", "users"));
+    }
+
+    #[tokio::test]
+    async fn test_resolve_empty_account_fails() {
+        let backend = MemoryBackend::new();
+        let mut config = test_config();
+        assert!(
+            resolve_database_in(&backend, &mut config, None, None, None)
+                .await
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_slow_query_warning() {
+        let thresholds = SlowQueryConfig {
+            warn_ru: Some(100.0),
+            warn_seconds: None,
+        };
+        let partitions: Vec<PartitionStats> = (0..7)
+            .map(|i| PartitionStats {
+                range_id: i.to_string(),
+                documents: i,
+                request_charge: i as f64 * 10.0,
+            })
+            .collect();
+        assert!(
+            slow_query_warning(
+                &thresholds,
+                AD_HOC,
+                99.0,
+                Duration::from_secs(1),
+                &partitions
+            )
+            .is_none()
+        );
+
+        let warning = slow_query_warning(
+            &thresholds,
+            "recent-orders",
+            210.0,
+            Duration::from_secs(2),
+            &partitions,
+        )
+        .unwrap();
+        assert!(warning.contains("210.00 RUs in 2.0s"));
+        assert!(warning.contains("Scanned 7 partition key ranges"));
+        // Costliest first, top 5 only
+        assert!(warning.find("range 6").unwrap() < warning.find("range 5").unwrap());
+        assert!(!warning.contains("range 1 "));
+        assert!(warning.contains("cosq queries lint recent-orders --connect"));
+    }
+
+    #[test]
+    fn test_parse_documents() {
+        let ndjson = "{\"id\": \"1\"}\n\n{\"id\": \"2\"}\n";
+        assert_eq!(
+            parse_documents(ndjson).unwrap(),
+            vec![json!({"id": "1"}), json!({"id": "2"})]
+        );
+        assert_eq!(
+            parse_documents("[{\"id\": \"1\"}]").unwrap(),
+            vec![json!({"id": "1"})]
+        );
+        let snapshot = cosq_core::snapshot::Snapshot::new(
+            "acct",
+            "db",
+            "orders",
+            Vec::new(),
+            vec![json!({"id": "1"})],
+        );
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("orders.snapshot.json");
+        snapshot.write(&path).unwrap();
+        assert_eq!(load_documents(&path).unwrap(), vec![json!({"id": "1"})]);
+        let err = parse_documents("{\"id\": 1}\nnot json").unwrap_err();
+        assert!(err.to_string().contains("line 2"));
+    }
+
+    #[test]
+    fn test_ai_documents() {
+        let mut config = test_config();
+        config.redact = vec!["*.email".into()];
+        config.ai_data = Some(cosq_core::config::AiDataConfig {
+            max_documents: Some(1),
+            ..Default::default()
+        });
+        let documents = vec![
+            json!({"id": "1", "email": "a@example.com"}),
+            json!({"id": "2", "email": "b@example.com"}),
+        ];
+        let sent = ai_documents(&config, &documents).unwrap();
+        assert_eq!(sent, vec![json!({"id": "1", "email": "***"})]);
+
+        config.ai_data = Some(cosq_core::config::AiDataConfig {
+            schema_only: true,
+            ..Default::default()
+        });
+        assert!(ai_documents(&config, &documents).is_none());
+        let summary = ai_schema_summary(&documents);
+        assert!(summary.contains("email"));
+        assert!(!summary.contains("example.com"));
+    }
+
+    #[test]
+    fn test_resolve_offline() {
+        let name = resolve_offline("db", None, Some("meta"), Some("config")).unwrap();
+        assert_eq!(name, "meta");
+        let name = resolve_offline("db", Some("cli".into()), Some("meta"), None).unwrap();
+        assert_eq!(name, "cli");
+        assert!(resolve_offline("container", None, None, None).is_err());
+    }
+
+    #[test]
+    fn test_format_diagnostics() {
+        let d = ResponseDiagnostics {
+            method: "POST".into(),
+            path: "/dbs/shop/colls/orders/docs".into(),
+            status: 429,
+            activity_id: Some("a1b2".into()),
+            server_duration_ms: Some(1.5),
+            retry_after_ms: Some(100.0),
+            ..Default::default()
+        };
+        assert_eq!(
+            format_diagnostics(&d),
+            "[POST /dbs/shop/colls/orders/docs → 429] activity-id=a1b2 \
+             server-latency=1.5ms retry-after=100ms"
+        );
+    }
+}
@@ -0,0 +1,241 @@
+//! Container command — inspect a container's settings and data
+//!
+//! `cosq container show` prints the partition key, TTL, indexing and
+//! analytical store (Synapse Link) settings from the data plane, plus the
+//! provisioned throughput and account-level Synapse Link flag from Azure
+//! Resource Manager when the account was set up through ARM. `cosq container
+//! keys` reports how documents spread over partition key values.
+
+use anyhow::{Result, bail};
+use colored::Colorize;
+use cosq_client::arm::{AccountDetails, ArmClient, account_resource_id};
+use cosq_core::config::Config;
+use cosq_core::key_distribution::{KeyDistribution, group_by_sql, row_count, sample_sql};
+
+use super::account::throughput_label;
+use super::common;
+use super::docs::connect_container;
+use crate::cli::ContainerCommands;
+
+/// Skew (largest count / mean count) from which the distribution is flagged
+const SKEW_WARNING: f64 = 10.0;
+
+/// Share of all documents (percent) one key may hold before it is flagged
+const HOT_KEY_PERCENT: f64 = 20.0;
+
+pub async fn run(cmd: ContainerCommands, profile: Option<String>, quiet: bool) -> Result<()> {
+    match cmd {
+        ContainerCommands::Show { container, db } => show(container, db, profile).await,
+        ContainerCommands::Keys {
+            container,
+            db,
+            top,
+            sample,
+        } => keys(container, db, top, sample, profile, quiet).await,
+    }
+}
+
+async fn show(
+    container: Option<String>,
+    db: Option<String>,
+    profile: Option<String>,
+) -> Result<()> {
+    common::ensure_online("cosq container show")?;
+    let (config, client, database, container) =
+        connect_container(profile.as_deref(), db, container).await?;
+    let properties = client.get_container(&database, &container).await?;
+    let arm = arm_details(&config).await;
+
+    println!("  {:<18} {database}/{container}", "Container:".bold());
+    println!(
+        "  {:<18} {}",
+        "Partition key:".bold(),
+        properties.partition_key.paths.join(", ")
+    );
+    println!(
+        "  {:<18} {}",
+        "Default TTL:".bold(),
+        match properties.default_ttl {
+            None => "off".to_string(),
+            Some(-1) => "on (no default expiry)".to_string(),
+            Some(seconds) => format!("{seconds} seconds"),
+        }
+    );
+    let indexing = &properties.indexing_policy;
+    println!(
+        "  {:<18} {} included, {} excluded paths, {} composite indexes",
+        "Indexing:".bold(),
+        indexing.included_paths.len(),
+        indexing.excluded_paths.len(),
+        indexing.composite_indexes.len()
+    );
+
+    let analytical = match properties.analytical_storage_ttl {
+        Some(-1) => "on, documents kept forever".green().to_string(),
+        Some(seconds) if seconds > 0 => format!("on, documents kept {seconds} seconds")
+            .green()
+            .to_string(),
+        _ => match arm {
+            Some((ref account, _, _)) if !account.properties.enable_analytical_storage => {
+                "off (Synapse Link is not enabled on the account)".to_string()
+            }
+            _ => "off".to_string(),
+        },
+    };
+    println!("  {:<18} {analytical}", "Analytical store:".bold());
+
+    match arm {
+        Some((account, arm, id)) if !account.is_serverless() => {
+            let throughput = match arm.get_throughput(&id, &database, Some(&container)).await {
+                Ok(Some(throughput)) => Some(throughput_label(throughput)),
+                Ok(None) => arm
+                    .get_throughput(&id, &database, None)
+                    .await
+                    .ok()
+                    .flatten()
+                    .map(|t| format!("{} (shared by the database)", throughput_label(t))),
+                Err(_) => None,
+            };
+            if let Some(throughput) = throughput {
+                println!("  {:<18} {throughput}", "Throughput:".bold());
+            }
+        }
+        Some(_) => println!("  {:<18} serverless", "Throughput:".bold()),
+        None => {}
+    }
+    Ok(())
+}
+
+async fn keys(
+    container: Option<String>,
+    db: Option<String>,
+    top: usize,
+    sample: Option<usize>,
+    profile: Option<String>,
+    quiet: bool,
+) -> Result<()> {
+    common::ensure_online("cosq container keys")?;
+    let (_, client, database, container) =
+        connect_container(profile.as_deref(), db, container).await?;
+    let paths = client
+        .get_container(&database, &container)
+        .await?
+        .partition_key
+        .paths;
+    if paths.is_empty() {
+        bail!("{database}/{container} has no partition key");
+    }
+    // Every document of a key value lives in one partition key range, so the
+    // per-range GROUP BY results don't overlap
+    let sql = match sample {
+        Some(limit) => sample_sql(&paths, limit.max(1)),
+        None => group_by_sql(&paths),
+    };
+
+    if !quiet {
+        eprintln!(
+            "{} {}/{} by {}...",
+            "Counting".dimmed(),
+            database.cyan(),
+            container.cyan(),
+            paths.join(", ")
+        );
+    }
+    let interrupt = common::Interrupt::install();
+    let result = client
+        .with_cancellation(interrupt.flag())
+        .query(&database, &container, &sql)
+        .await;
+    interrupt.finish();
+    let result = result?;
+    if result.partial {
+        bail!("interrupted; the key counts would be incomplete");
+    }
+
+    let distribution = KeyDistribution::new(
+        result
+            .documents
+            .iter()
+            .map(|row| row_count(row, paths.len())),
+        top,
+    );
+    println!(
+        "  {:<16} {} documents{}",
+        "Counted:".bold(),
+        distribution.total,
+        if sample.is_some() { " (sample)" } else { "" }
+    );
+    println!(
+        "  {:<16} {}",
+        "Distinct keys:".bold(),
+        distribution.distinct
+    );
+    println!(
+        "  {:<16} {:.1}x the mean per key",
+        "Skew:".bold(),
+        distribution.skew
+    );
+    if !distribution.top.is_empty() {
+        println!();
+        println!(
+            "  {:>10}  {:>6}  {}",
+            "DOCUMENTS",
+            "SHARE",
+            paths.join(" / ")
+        );
+        for entry in &distribution.top {
+            println!(
+                "  {:>10}  {:>5.1}%  {}",
+                entry.count,
+                distribution.percent(entry.count),
+                entry.key
+            );
+        }
+    }
+
+    let hottest = distribution
+        .top
+        .first()
+        .map(|entry| distribution.percent(entry.count))
+        .unwrap_or_default();
+    if distribution.distinct > 1
+        && (distribution.skew >= SKEW_WARNING || hottest >= HOT_KEY_PERCENT)
+    {
+        eprintln!(
+            "\n{} one key holds {hottest:.1}% of the documents ({:.1}x the mean). Requests for it all go to one physical partition, which tops out at 10,000 RU/s and shows up as 429s before the container's throughput is used up.",
+            "Warning:".yellow().bold(),
+            distribution.skew
+        );
+        eprintln!(
+            "  {}",
+            "Consider a higher-cardinality key (or a hierarchical partition key) for new containers."
+                .dimmed()
+        );
+    }
+    if !quiet {
+        eprintln!(
+            "\n{} {:.2} RUs",
+            "Request charge:".dimmed(),
+            result.request_charge
+        );
+    }
+    Ok(())
+}
+
+/// The account's ARM details, a client and its resource ID — `None` when the
+/// account has no subscription/resource group configured or ARM can't be
+/// reached (e.g. the emulator or key-only access).
+async fn arm_details(config: &Config) -> Option<(AccountDetails, ArmClient, String)> {
+    let account = &config.account;
+    if account.subscription.is_empty() || account.resource_group.is_empty() {
+        return None;
+    }
+    let id = account_resource_id(
+        &account.subscription,
+        &account.resource_group,
+        &account.name,
+    );
+    let arm = ArmClient::new().await.ok()?;
+    let details = arm.get_account(&id).await.ok()?;
+    Some((details, arm, id))
+}
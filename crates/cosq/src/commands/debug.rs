@@ -0,0 +1,248 @@
+//! Debug command — bug report bundles
+//!
+//! Every command records its sanitized arguments, outcome and the
+//! diagnostics of each Cosmos DB response in `~/.cosq/debug/` (see
+//! [`cosq_core::debug_bundle`]). `cosq debug bundle` packs those records with
+//! the sanitized config, version and environment details into a `.tar.gz`
+//! to attach to an issue.
+
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::Instant;
+
+use anyhow::{Context, Result};
+use colored::Colorize;
+use cosq_client::cosmos::ResponseDiagnostics;
+use cosq_core::config::Config;
+use cosq_core::debug_bundle::{self, CommandRecord, ResponseRecord};
+
+use crate::cli::DebugCommands;
+
+/// Responses of the running command
+static RESPONSES: Mutex<Option<CommandRecord>> = Mutex::new(None);
+
+/// Environment variables whose values are shown in a bundle; others that
+/// cosq reads are listed as set without their value.
+const SHOWN_VARS: &[&str] = &[
+    "COSQ_PROFILE",
+    "COSQ_OFFLINE",
+    "COSQ_REDACT",
+    "COSQ_NO_AI_DATA",
+    "COSQ_NO_UPDATE_CHECK",
+    "AZURE_AUTHORITY_HOST",
+    "CI",
+    "GITHUB_ACTIONS",
+    "TERM",
+    "SHELL",
+    "NO_COLOR",
+];
+
+/// Prefixes of environment variables that affect cosq
+const VAR_PREFIXES: &[&str] = &["COSQ_", "AZURE_", "ACTIONS_ID_TOKEN_"];
+
+/// Proxy variables honored by the HTTP client
+const PROXY_VARS: &[&str] = &[
+    "HTTPS_PROXY",
+    "https_proxy",
+    "HTTP_PROXY",
+    "http_proxy",
+    "NO_PROXY",
+    "no_proxy",
+];
+
+pub fn run(cmd: DebugCommands, quiet: bool) -> Result<()> {
+    match cmd {
+        DebugCommands::Bundle { output } => bundle(output, quiet),
+    }
+}
+
+/// Keep a response's diagnostics for the record of the running command.
+pub fn record_response(d: &ResponseDiagnostics) {
+    let mut record = RESPONSES.lock().unwrap();
+    record
+        .get_or_insert_with(CommandRecord::default)
+        .push_response(ResponseRecord {
+            method: d.method.clone(),
+            path: d.path.clone(),
+            status: d.status,
+            activity_id: d.activity_id.clone(),
+            server_duration_ms: d.server_duration_ms,
+            request_charge: d.request_charge,
+            retry_after_ms: d.retry_after_ms,
+        });
+}
+
+/// Save the record of the command that just ran. Best effort: a failure
+/// only loses the record.
+pub fn save_command(started: Instant, at: String, result: &Result<()>) {
+    let mut record = RESPONSES.lock().unwrap().take().unwrap_or_default();
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    record.at = at;
+    record.cosq_version = env!("CARGO_PKG_VERSION").to_string();
+    record.args = debug_bundle::sanitize_args(&args);
+    record.duration_ms = started.elapsed().as_millis() as u64;
+    record.error = result
+        .as_ref()
+        .err()
+        .map(|e| debug_bundle::sanitize_text(&format!("{e:#}")));
+    if let Err(e) = debug_bundle::save(&record) {
+        tracing::debug!(error = %e, "failed to save debug record");
+    }
+}
+
+/// `cosq debug bundle`: write the bug report archive.
+fn bundle(output: Option<PathBuf>, quiet: bool) -> Result<()> {
+    let now = chrono::Local::now();
+    let root = format!("cosq-debug-{}", now.format("%Y%m%d-%H%M%S"));
+    let path = output.unwrap_or_else(|| PathBuf::from(format!("{root}.tar.gz")));
+
+    let dir = debug_bundle::debug_dir()?;
+    let mut files = vec![
+        ("version.txt".to_string(), version_info()),
+        ("environment.txt".to_string(), environment_info()),
+        ("config.yaml".to_string(), config_info()),
+    ];
+    let mut included = vec!["version", "environment", "sanitized config"];
+    if let Some(last) = debug_bundle::load_last_from(&dir)? {
+        files.push((
+            "last-command.json".to_string(),
+            serde_json::to_string_pretty(&last)? + "\n",
+        ));
+        included.push("last command diagnostics");
+    }
+    let log = debug_bundle::load_log_from(&dir)?;
+    if !log.is_empty() {
+        let lines: Vec<String> = log
+            .iter()
+            .map(serde_json::to_string)
+            .collect::<Result<_, _>>()?;
+        files.push(("commands.jsonl".to_string(), lines.join("\n") + "\n"));
+        included.push("recent commands");
+    }
+    files.insert(0, ("README.txt".to_string(), readme(&files)));
+
+    let files: Vec<(String, Vec<u8>)> = files
+        .into_iter()
+        .map(|(name, text)| (name, text.into_bytes()))
+        .collect();
+    debug_bundle::write_archive(&path, &root, &files)
+        .with_context(|| format!("failed to write {}", path.display()))?;
+
+    println!(
+        "{} Wrote {} ({})",
+        "✓".green(),
+        path.display().to_string().bold(),
+        included.join(", ")
+    );
+    if !quiet {
+        println!(
+            "{}",
+            format!(
+                "  Keys, tokens, string literals and parameter values are masked; \
+                 review the files before attaching the bundle to an issue at\n  {}/issues",
+                env!("CARGO_PKG_REPOSITORY")
+            )
+            .dimmed()
+        );
+    }
+    Ok(())
+}
+
+fn readme(files: &[(String, String)]) -> String {
+    let mut text = format!(
+        "cosq debug bundle, created {}\n\n\
+         Master keys, API keys and webhook URLs are masked in config.yaml; string\n\
+         literals, parameter values, credentials and tokens are masked in commands\n\
+         and error messages.\n\nFiles:\n",
+        chrono::Local::now().to_rfc3339()
+    );
+    for (name, _) in files {
+        text.push_str(&format!("  {name}\n"));
+    }
+    text
+}
+
+fn version_info() -> String {
+    let mut features = Vec::new();
+    if cfg!(feature = "datafusion") {
+        features.push("datafusion");
+    }
+    if cfg!(feature = "duckdb") {
+        features.push("duckdb");
+    }
+    format!(
+        "cosq {}\nos: {} ({})\narch: {}\nfeatures: {}\n",
+        env!("CARGO_PKG_VERSION"),
+        std::env::consts::OS,
+        std::env::consts::FAMILY,
+        std::env::consts::ARCH,
+        if features.is_empty() {
+            "(none)".to_string()
+        } else {
+            features.join(", ")
+        }
+    )
+}
+
+fn environment_info() -> String {
+    let auth = serde_json::to_value(cosq_client::auth::method())
+        .ok()
+        .and_then(|v| v.as_str().map(str::to_string))
+        .unwrap_or_default();
+    let mut text = format!(
+        "auth method: {auth}\nconfig: {}\nterminal: {}\n\nEnvironment variables:\n",
+        Config::path()
+            .map(|p| describe_path(&p))
+            .unwrap_or_else(|e| e.to_string()),
+        if std::io::IsTerminal::is_terminal(&std::io::stdout()) {
+            "yes"
+        } else {
+            "no"
+        }
+    );
+    let mut vars: Vec<(String, String)> = std::env::vars_os()
+        .filter_map(|(name, value)| {
+            Some((
+                name.into_string().ok()?,
+                value.to_string_lossy().into_owned(),
+            ))
+        })
+        .filter(|(name, _)| {
+            SHOWN_VARS.contains(&name.as_str())
+                || PROXY_VARS.contains(&name.as_str())
+                || VAR_PREFIXES.iter().any(|p| name.starts_with(p))
+        })
+        .map(|(name, value)| {
+            let shown = if SHOWN_VARS.contains(&name.as_str()) {
+                value
+            } else {
+                "(set)".to_string()
+            };
+            (name, shown)
+        })
+        .collect();
+    vars.sort();
+    if vars.is_empty() {
+        text.push_str("  (none)\n");
+    }
+    for (name, value) in vars {
+        text.push_str(&format!("  {name}={value}\n"));
+    }
+    text
+}
+
+fn describe_path(path: &Path) -> String {
+    let state = if path.exists() { "" } else { " (missing)" };
+    format!("{}{state}", path.display())
+}
+
+fn config_info() -> String {
+    match Config::load() {
+        Ok(config) => debug_bundle::sanitized_config(&config)
+            .unwrap_or_else(|e| format!("# failed to encode config: {e}\n")),
+        Err(e) => format!(
+            "# no config: {}\n",
+            debug_bundle::sanitize_text(&e.to_string())
+        ),
+    }
+}
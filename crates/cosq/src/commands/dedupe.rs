@@ -0,0 +1,154 @@
+//! Dedupe command — find and remove duplicate documents
+//!
+//! Reads the id, `_ts`, partition key and key expressions of every document
+//! (one cross-partition query), groups them client-side and lists the
+//! documents sharing a key. With `--delete`, every duplicate except the newest
+//! of its group is deleted after confirmation.
+
+use anyhow::{Result, bail};
+use colored::Colorize;
+use cosq_core::dedupe::{dedupe_sql, find_duplicates};
+
+use super::common::{self, BoundedTasks};
+use super::docs::connect_container;
+
+/// Duplicate groups listed in full before the summary
+const MAX_LISTED_GROUPS: usize = 20;
+
+pub struct DedupeArgs {
+    pub container: Option<String>,
+    pub by: Vec<String>,
+    pub db: Option<String>,
+    pub delete: bool,
+    pub parallel: usize,
+    pub yes: bool,
+    pub force: bool,
+    pub profile: Option<String>,
+    pub quiet: bool,
+}
+
+pub async fn run(args: DedupeArgs) -> Result<()> {
+    common::ensure_online("cosq dedupe")?;
+    if args.by.iter().any(|expr| expr.trim().is_empty()) {
+        bail!("--by needs a key expression such as c.email");
+    }
+    let (config, client, database, container) =
+        connect_container(args.profile.as_deref(), args.db, args.container).await?;
+    let paths = client
+        .get_container(&database, &container)
+        .await?
+        .partition_key
+        .paths;
+    let [pk_path] = paths.as_slice() else {
+        bail!(
+            "{database}/{container} has {} partition key paths; deduplicating containers with hierarchical partition keys is not supported",
+            paths.len()
+        );
+    };
+
+    if !args.quiet {
+        eprintln!(
+            "{} {}/{} by {}...",
+            "Reading".dimmed(),
+            database.cyan(),
+            container.cyan(),
+            args.by.join(", ")
+        );
+    }
+    let interrupt = common::Interrupt::install();
+    let result = client
+        .clone()
+        .with_cancellation(interrupt.flag())
+        .query(&database, &container, &dedupe_sql(&args.by, pk_path))
+        .await;
+    interrupt.finish();
+    let result = result?;
+    if result.partial {
+        bail!("interrupted; duplicates can only be found after reading every document");
+    }
+
+    let groups = find_duplicates(&result.documents, args.by.len());
+    let duplicates: usize = groups.iter().map(|g| g.remove.len()).sum();
+    if !args.quiet {
+        eprintln!(
+            "{} {} documents read ({:.2} RUs)",
+            "✓".green(),
+            result.documents.len(),
+            result.request_charge
+        );
+    }
+    if groups.is_empty() {
+        println!("No duplicates by {}.", args.by.join(", "));
+        return Ok(());
+    }
+
+    for group in groups.iter().take(MAX_LISTED_GROUPS) {
+        println!("{} {} documents", group.key.bold(), group.remove.len() + 1);
+        println!("  {} {} (newest)", "keep".green(), group.keep.id);
+        for doc in &group.remove {
+            println!("  {} {}", "dup ".yellow(), doc.id);
+        }
+    }
+    if groups.len() > MAX_LISTED_GROUPS {
+        println!("... and {} more groups", groups.len() - MAX_LISTED_GROUPS);
+    }
+    println!(
+        "\n{} duplicate documents in {} groups",
+        duplicates.to_string().bold(),
+        groups.len()
+    );
+
+    if !args.delete {
+        if !args.quiet {
+            eprintln!(
+                "  {}",
+                "Pass --delete to remove all but the newest document of each group.".dimmed()
+            );
+        }
+        return Ok(());
+    }
+
+    common::confirm_destructive(
+        &config,
+        &database,
+        &container,
+        &format!("{duplicates} document deletes"),
+        Some(&format!(
+            "Delete {duplicates} duplicate documents from {database}/{container}, keeping the newest of each group?"
+        )),
+        args.yes,
+        args.force,
+    )?;
+
+    let mut tasks = BoundedTasks::new(args.parallel);
+    for doc in groups.into_iter().flat_map(|g| g.remove) {
+        let (client, database, container) = (client.clone(), database.clone(), container.clone());
+        tasks
+            .spawn(async move {
+                client
+                    .delete_document(&database, &container, &doc.id, &doc.partition_key)
+                    .await
+                    .map(|_| ())
+                    .map_err(|e| format!("{}: {e}", doc.id))
+            })
+            .await;
+    }
+    tasks.wait().await;
+
+    let failures = tasks.failures();
+    if !failures.is_empty() {
+        common::report_failures(failures);
+        bail!(
+            "{} of {duplicates} duplicates could not be deleted from {database}/{container}",
+            failures.len()
+        );
+    }
+    if !args.quiet {
+        eprintln!(
+            "{} Deleted {} duplicates from {database}/{container}",
+            "✓".green(),
+            tasks.succeeded().len()
+        );
+    }
+    Ok(())
+}
@@ -0,0 +1,748 @@
+//! Docs command — work with individual documents
+//!
+//! `cosq docs get` fetches one document with a point read instead of a
+//! query. `cosq docs delete` removes one after confirmation. `cosq docs
+//! create` inserts a JSON document, taking the partition key value
+//! from the document itself. `cosq docs patch` changes individual properties
+//! with the Patch API. `cosq docs edit` fetches a document, opens it in `$EDITOR`, validates the
+//! result, shows a diff, and replaces the document only if its etag is
+//! unchanged since it was read. `cosq docs delete-where` deletes every
+//! document a query matches, reading only their ids and partition keys.
+
+use std::io::{IsTerminal, Read};
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result, bail};
+use colored::Colorize;
+use cosq_client::cosmos::{MAX_PATCH_OPERATIONS, PatchOperation};
+use cosq_client::error::ClientError;
+use cosq_core::config::Config;
+use cosq_core::delete_where::{DeleteTarget, delete_targets, delete_where_sql};
+use cosq_core::diff::{DiffLine, diff_lines};
+use cosq_core::schema::SYSTEM_FIELDS;
+use cosq_core::snapshot::partition_key_value;
+use inquire::Confirm;
+use serde_json::Value;
+
+use super::common::{self, BoundedTasks};
+use crate::cli::DocsCommands;
+
+pub async fn run(cmd: DocsCommands, profile: Option<String>, quiet: bool) -> Result<()> {
+    match cmd {
+        DocsCommands::Get {
+            id,
+            pk,
+            db,
+            container,
+        } => get(&id, &pk, db, container, profile).await,
+        DocsCommands::Delete {
+            id,
+            pk,
+            db,
+            container,
+            yes,
+            force,
+        } => delete(&id, &pk, db, container, yes, force, profile, quiet).await,
+        DocsCommands::Create {
+            json,
+            file,
+            db,
+            container,
+            force,
+        } => create(json, file, db, container, force, profile, quiet).await,
+        DocsCommands::Patch {
+            id,
+            pk,
+            db,
+            container,
+            add,
+            set,
+            replace,
+            incr,
+            remove,
+            force,
+        } => {
+            let operations = patch_operations(&add, &set, &replace, &incr, &remove)?;
+            patch(&id, &pk, db, container, &operations, force, profile, quiet).await
+        }
+        DocsCommands::Edit {
+            id,
+            pk,
+            db,
+            container,
+            yes,
+            force,
+        } => edit(&id, &pk, db, container, yes, force, profile, quiet).await,
+        DocsCommands::DeleteWhere {
+            sql,
+            db,
+            container,
+            dry_run,
+            parallel,
+            yes,
+            force,
+        } => {
+            let options = DeleteWhereOptions {
+                dry_run,
+                parallel,
+                yes,
+                force,
+            };
+            delete_where(&sql, db, container, options, profile, quiet).await
+        }
+    }
+}
+
+/// Parse a `--pk` value: JSON scalars (`42`, `true`, `"007"`) keep their type,
+/// anything else is a string.
+pub fn parse_partition_key(value: &str) -> Value {
+    match serde_json::from_str::<Value>(value) {
+        Ok(v @ (Value::Number(_) | Value::Bool(_) | Value::Null | Value::String(_))) => v,
+        _ => Value::String(value.to_string()),
+    }
+}
+
+/// Load the config and resolve database/container (flag > config > picker).
+pub async fn connect_container(
+    profile: Option<&str>,
+    db: Option<String>,
+    container: Option<String>,
+) -> Result<(Config, cosq_client::cosmos::CosmosClient, String, String)> {
+    let (mut config, pinned) = match profile {
+        Some(profile) => (Config::load()?.for_profile(profile)?, true),
+        None => (Config::load()?, false),
+    };
+    let client = common::connect(&config).await?;
+    let (database, db_changed) = common::resolve_database(&client, &mut config, db, None).await?;
+    let (container, ctr_changed) =
+        common::resolve_container(&client, &mut config, &database, container, None).await?;
+    if (db_changed || ctr_changed) && !pinned {
+        config.save()?;
+    }
+    Ok((config, client, database, container))
+}
+
+async fn get(
+    id: &str,
+    pk: &str,
+    db: Option<String>,
+    container: Option<String>,
+    profile: Option<String>,
+) -> Result<()> {
+    common::ensure_online("cosq docs get")?;
+    let (_, client, database, container) =
+        connect_container(profile.as_deref(), db, container).await?;
+    let partition_key = parse_partition_key(pk);
+    let Some(document) = client
+        .read_document(&database, &container, id, &partition_key)
+        .await?
+    else {
+        bail!(
+            "document '{id}' with partition key {partition_key} not found in {database}/{container}"
+        );
+    };
+    println!("{}", serde_json::to_string_pretty(&document)?);
+    Ok(())
+}
+
+#[allow(clippy::too_many_arguments)]
+async fn delete(
+    id: &str,
+    pk: &str,
+    db: Option<String>,
+    container: Option<String>,
+    yes: bool,
+    force: bool,
+    profile: Option<String>,
+    quiet: bool,
+) -> Result<()> {
+    common::ensure_online("cosq docs delete")?;
+    let (config, client, database, container) =
+        connect_container(profile.as_deref(), db, container).await?;
+    let partition_key = parse_partition_key(pk);
+
+    common::confirm_destructive(
+        &config,
+        &database,
+        &container,
+        &format!("a delete of document '{id}'"),
+        Some(&format!(
+            "Delete document '{id}' (partition key {partition_key}) from {database}/{container}?"
+        )),
+        yes,
+        force,
+    )?;
+
+    let Some(request_charge) = client
+        .delete_document(&database, &container, id, &partition_key)
+        .await?
+    else {
+        bail!(
+            "document '{id}' with partition key {partition_key} not found in {database}/{container}"
+        );
+    };
+    if !quiet {
+        eprintln!(
+            "{} Deleted document '{id}' from {database}/{container} ({request_charge:.2} RUs)",
+            "✓".green()
+        );
+    }
+    Ok(())
+}
+
+struct DeleteWhereOptions {
+    dry_run: bool,
+    parallel: usize,
+    yes: bool,
+    force: bool,
+}
+
+async fn delete_where(
+    sql: &str,
+    db: Option<String>,
+    container: Option<String>,
+    options: DeleteWhereOptions,
+    profile: Option<String>,
+    quiet: bool,
+) -> Result<()> {
+    common::ensure_online("cosq docs delete-where")?;
+    let (config, client, database, container) =
+        connect_container(profile.as_deref(), db, container).await?;
+    let paths = client
+        .get_container(&database, &container)
+        .await?
+        .partition_key
+        .paths;
+    let [pk_path] = paths.as_slice() else {
+        bail!(
+            "{database}/{container} has {} partition key paths; deleting by query with hierarchical partition keys is not supported",
+            paths.len()
+        );
+    };
+
+    let query = delete_where_sql(sql, pk_path)?;
+    let result = client.query(&database, &container, &query).await?;
+    let targets = delete_targets(&result.documents);
+    let query_charge = result.request_charge;
+    if targets.is_empty() {
+        if !quiet {
+            eprintln!(
+                "No documents in {database}/{container} match the query ({query_charge:.2} RUs)"
+            );
+        }
+        return Ok(());
+    }
+    if options.dry_run {
+        println!(
+            "{} documents in {database}/{container} would be deleted",
+            targets.len()
+        );
+        common::report_charge(&config, "docs delete-where", query_charge, quiet);
+        return Ok(());
+    }
+
+    common::confirm_destructive(
+        &config,
+        &database,
+        &container,
+        &format!("a delete of {} documents", targets.len()),
+        Some(&format!(
+            "Delete {} documents from {database}/{container}?",
+            targets.len()
+        )),
+        options.yes,
+        options.force,
+    )?;
+
+    let total = targets.len();
+    let mut tasks = BoundedTasks::new(options.parallel);
+    for DeleteTarget { id, partition_key } in targets {
+        let (client, database, container) = (client.clone(), database.clone(), container.clone());
+        tasks
+            .spawn(async move {
+                client
+                    .delete_document(&database, &container, &id, &partition_key)
+                    .await
+                    .map_err(|e| format!("{id}: {e}"))
+            })
+            .await;
+    }
+    tasks.wait().await;
+
+    // Deletes of documents already gone have no charge
+    let charges: Vec<f64> = tasks.succeeded().iter().flatten().copied().collect();
+    let missing = tasks.succeeded().len() - charges.len();
+    let delete_charge: f64 = charges.iter().sum();
+    let failed = tasks.failures().len();
+    common::report_failures(tasks.failures());
+    if !quiet {
+        let missing = if missing > 0 {
+            format!(", {missing} were already gone")
+        } else {
+            String::new()
+        };
+        eprintln!(
+            "{} Deleted {} documents from {database}/{container}{missing}",
+            "✓".green(),
+            charges.len()
+        );
+        eprintln!(
+            "  {} {query_charge:.2} RUs for the query, {delete_charge:.2} RUs for the deletes",
+            "Charge:".dimmed()
+        );
+    }
+    common::report_charge(
+        &config,
+        "docs delete-where",
+        query_charge + delete_charge,
+        quiet,
+    );
+    if failed > 0 {
+        bail!("{failed} of {total} documents could not be deleted from {database}/{container}");
+    }
+    Ok(())
+}
+
+async fn create(
+    json: Option<String>,
+    file: Option<PathBuf>,
+    db: Option<String>,
+    container: Option<String>,
+    force: bool,
+    profile: Option<String>,
+    quiet: bool,
+) -> Result<()> {
+    common::ensure_online("cosq docs create")?;
+    let document = parse_document(&read_document_input(json, file.as_deref())?)?;
+    let (config, client, database, container) =
+        connect_container(profile.as_deref(), db, container).await?;
+
+    let paths = client
+        .get_container(&database, &container)
+        .await?
+        .partition_key
+        .paths;
+    let [pk_path] = paths.as_slice() else {
+        bail!(
+            "{database}/{container} has {} partition key paths; creating documents with hierarchical partition keys is not supported",
+            paths.len()
+        );
+    };
+    let partition_key = partition_key_value(&document, pk_path);
+    let id = document["id"].as_str().unwrap_or_default().to_string();
+
+    common::confirm_destructive(
+        &config,
+        &database,
+        &container,
+        &format!("a new document '{id}'"),
+        None,
+        false,
+        force,
+    )?;
+
+    let written = client
+        .create_document(&database, &container, &document, &partition_key)
+        .await?;
+    println!("{}", serde_json::to_string_pretty(&written.document)?);
+    if !quiet {
+        eprintln!(
+            "{} Created document '{id}' in {database}/{container} (partition key {partition_key}, {:.2} RUs)",
+            "✓".green(),
+            written.request_charge
+        );
+    }
+    Ok(())
+}
+
+#[allow(clippy::too_many_arguments)]
+async fn patch(
+    id: &str,
+    pk: &str,
+    db: Option<String>,
+    container: Option<String>,
+    operations: &[PatchOperation],
+    force: bool,
+    profile: Option<String>,
+    quiet: bool,
+) -> Result<()> {
+    common::ensure_online("cosq docs patch")?;
+    let (config, client, database, container) =
+        connect_container(profile.as_deref(), db, container).await?;
+    let partition_key = parse_partition_key(pk);
+
+    common::confirm_destructive(
+        &config,
+        &database,
+        &container,
+        &format!("a patch of document '{id}'"),
+        None,
+        false,
+        force,
+    )?;
+
+    let patched = match client
+        .patch_document(&database, &container, id, &partition_key, operations)
+        .await
+    {
+        Err(ClientError::NotFound { .. }) => bail!(
+            "document '{id}' with partition key {partition_key} not found in {database}/{container}"
+        ),
+        result => result?,
+    };
+    println!("{}", serde_json::to_string_pretty(&patched.document)?);
+    if !quiet {
+        eprintln!(
+            "{} Patched document '{id}' in {database}/{container} ({} operation(s), {:.2} RUs)",
+            "✓".green(),
+            operations.len(),
+            patched.request_charge
+        );
+    }
+    Ok(())
+}
+
+/// Patch operations from the `--add`, `--set`, `--replace`, `--incr` and
+/// `--remove` flags, in that order.
+pub fn patch_operations(
+    add: &[String],
+    set: &[String],
+    replace: &[String],
+    incr: &[String],
+    remove: &[String],
+) -> Result<Vec<PatchOperation>> {
+    let mut operations = Vec::new();
+    for spec in add {
+        let (path, value) = parse_patch_assignment(spec)?;
+        operations.push(PatchOperation::Add { path, value });
+    }
+    for spec in set {
+        let (path, value) = parse_patch_assignment(spec)?;
+        operations.push(PatchOperation::Set { path, value });
+    }
+    for spec in replace {
+        let (path, value) = parse_patch_assignment(spec)?;
+        operations.push(PatchOperation::Replace { path, value });
+    }
+    for spec in incr {
+        let (path, value) = parse_patch_assignment(spec)?;
+        if !value.is_number() {
+            bail!("--incr {spec}: the increment must be a number");
+        }
+        operations.push(PatchOperation::Increment { path, value });
+    }
+    for path in remove {
+        operations.push(PatchOperation::Remove {
+            path: patch_path(path)?,
+        });
+    }
+
+    if operations.is_empty() {
+        bail!("nothing to patch — pass --set, --add, --replace, --incr or --remove");
+    }
+    if operations.len() > MAX_PATCH_OPERATIONS {
+        bail!(
+            "{} operations given; Cosmos DB applies at most {MAX_PATCH_OPERATIONS} in one patch",
+            operations.len()
+        );
+    }
+    Ok(operations)
+}
+
+/// Split `PATH=VALUE`. The value is JSON if it parses (`42`, `true`,
+/// `{"a":1}`, `"007"`), a string otherwise.
+fn parse_patch_assignment(spec: &str) -> Result<(String, Value)> {
+    let Some((path, value)) = spec.split_once('=') else {
+        bail!("expected PATH=VALUE (e.g. /status=shipped), got '{spec}'");
+    };
+    let value = serde_json::from_str(value).unwrap_or_else(|_| Value::String(value.to_string()));
+    Ok((patch_path(path)?, value))
+}
+
+/// A patch path: a JSON pointer to a property other than the root.
+fn patch_path(path: &str) -> Result<String> {
+    if !path.starts_with('/') || path.len() < 2 {
+        bail!("patch paths are JSON pointers like /status or /items/0/qty, got '{path}'");
+    }
+    Ok(path.to_string())
+}
+
+/// The document text: inline JSON, a file (`-` for stdin), or piped stdin.
+fn read_document_input(json: Option<String>, file: Option<&Path>) -> Result<String> {
+    if let Some(json) = json {
+        return Ok(json);
+    }
+    match file {
+        Some(path) if path != Path::new("-") => std::fs::read_to_string(path)
+            .with_context(|| format!("failed to read {}", path.display())),
+        Some(_) => read_stdin(),
+        None if !std::io::stdin().is_terminal() => read_stdin(),
+        None => bail!("pass the document as JSON, with --file, or on stdin"),
+    }
+}
+
+fn read_stdin() -> Result<String> {
+    let mut text = String::new();
+    std::io::stdin()
+        .read_to_string(&mut text)
+        .context("failed to read the document from stdin")?;
+    Ok(text)
+}
+
+/// Parse a document to create: a JSON object with a string `id`. System
+/// properties (e.g. from a copied document) are dropped.
+pub fn parse_document(text: &str) -> Result<Value> {
+    let document: Value = serde_json::from_str(text).context("the document is not valid JSON")?;
+    if !document.is_object() {
+        bail!("the document must be a JSON object");
+    }
+    if !document.get("id").is_some_and(Value::is_string) {
+        bail!("the document needs a string \"id\" property");
+    }
+    Ok(strip_system_fields(document))
+}
+
+#[allow(clippy::too_many_arguments)]
+async fn edit(
+    id: &str,
+    pk: &str,
+    db: Option<String>,
+    container: Option<String>,
+    yes: bool,
+    force: bool,
+    profile: Option<String>,
+    quiet: bool,
+) -> Result<()> {
+    common::ensure_online("cosq docs edit")?;
+    let (config, client, database, container) =
+        connect_container(profile.as_deref(), db, container).await?;
+    let partition_key = parse_partition_key(pk);
+
+    let Some(original) = client
+        .read_document(&database, &container, id, &partition_key)
+        .await?
+    else {
+        bail!(
+            "document '{id}' with partition key {partition_key} not found in {database}/{container}"
+        );
+    };
+    let etag = original
+        .get("_etag")
+        .and_then(Value::as_str)
+        .map(str::to_string);
+    let editable = strip_system_fields(original);
+    let before = serde_json::to_string_pretty(&editable)?;
+
+    let path = std::env::temp_dir().join(format!(
+        "cosq-{}-{}.json",
+        sanitize_file_name(id),
+        std::process::id()
+    ));
+    std::fs::write(&path, format!("{before}\n"))
+        .with_context(|| format!("failed to write {}", path.display()))?;
+    let edited = edit_until_valid(&path);
+    let _ = std::fs::remove_file(&path);
+    let edited = edited?;
+
+    if edited == editable {
+        if !quiet {
+            eprintln!("No changes.");
+        }
+        return Ok(());
+    }
+    if edited.get("id").and_then(Value::as_str) != Some(id) {
+        bail!("the document id must stay '{id}' (create a new document instead)");
+    }
+
+    let after = serde_json::to_string_pretty(&edited)?;
+    eprintln!();
+    for line in diff_lines(&before, &after) {
+        match line {
+            DiffLine::Same(l) => eprintln!("  {}", l.dimmed()),
+            DiffLine::Removed(l) => eprintln!("{}", format!("- {l}").red()),
+            DiffLine::Added(l) => eprintln!("{}", format!("+ {l}").green()),
+        }
+    }
+    eprintln!();
+
+    common::confirm_destructive(
+        &config,
+        &database,
+        &container,
+        &format!("a replacement of document '{id}'"),
+        Some(&format!("Replace document '{id}'?")),
+        yes,
+        force,
+    )?;
+
+    match client
+        .replace_document(
+            &database,
+            &container,
+            id,
+            &edited,
+            &partition_key,
+            etag.as_deref(),
+        )
+        .await
+    {
+        Ok(_) => {
+            if !quiet {
+                eprintln!("{} Replaced document '{id}'", "✓".green());
+            }
+            Ok(())
+        }
+        Err(e @ ClientError::PreconditionFailed { .. }) => Err(anyhow::anyhow!(e).context(
+            "someone else changed the document while you were editing — run the edit again to start from the latest version",
+        )),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Open `path` in the editor until it holds valid JSON (or the user gives up).
+fn edit_until_valid(path: &Path) -> Result<Value> {
+    loop {
+        run_editor(path)?;
+        let contents = std::fs::read_to_string(path)
+            .with_context(|| format!("failed to read {}", path.display()))?;
+        match serde_json::from_str::<Value>(&contents) {
+            Ok(value @ Value::Object(_)) => return Ok(value),
+            Ok(_) => eprintln!("{} a document must be a JSON object", "Error:".red().bold()),
+            Err(e) => eprintln!("{} invalid JSON: {e}", "Error:".red().bold()),
+        }
+        if !Confirm::new("Edit again?")
+            .with_default(true)
+            .prompt()
+            .context("edit cancelled")?
+        {
+            bail!("edit abandoned; the document was not changed");
+        }
+    }
+}
+
+/// Run `$VISUAL` / `$EDITOR` (falling back to vi / notepad) and wait for it to exit.
+fn run_editor(path: &Path) -> Result<()> {
+    let editor = std::env::var("VISUAL")
+        .or_else(|_| std::env::var("EDITOR"))
+        .unwrap_or_else(|_| {
+            if cfg!(target_os = "windows") {
+                "notepad".to_string()
+            } else {
+                "vi".to_string()
+            }
+        });
+    // Editors like `code --wait` come with arguments
+    let mut parts = editor.split_whitespace();
+    let program = parts.next().unwrap_or("vi");
+    let status = std::process::Command::new(program)
+        .args(parts)
+        .arg(path)
+        .status()
+        .with_context(|| format!("failed to open editor: {editor}"))?;
+    if !status.success() {
+        bail!("editor exited with {status}; the document was not changed");
+    }
+    Ok(())
+}
+
+/// Remove the properties Cosmos DB maintains itself (`_rid`, `_etag`, ...).
+pub fn strip_system_fields(mut document: Value) -> Value {
+    if let Value::Object(ref mut map) = document {
+        for field in SYSTEM_FIELDS {
+            map.remove(*field);
+        }
+    }
+    document
+}
+
+fn sanitize_file_name(id: &str) -> String {
+    id.chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '-' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .take(40)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_parse_partition_key() {
+        assert_eq!(parse_partition_key("tenant-a"), json!("tenant-a"));
+        assert_eq!(parse_partition_key("42"), json!(42));
+        assert_eq!(parse_partition_key("true"), json!(true));
+        assert_eq!(parse_partition_key("\"007\""), json!("007"));
+        assert_eq!(parse_partition_key("{\"a\":1}"), json!("{\"a\":1}"));
+    }
+
+    #[test]
+    fn test_parse_document() {
+        let doc = parse_document(r#"{"id": "1", "tenant": "a", "_etag": "x"}"#).unwrap();
+        assert_eq!(doc, json!({"id": "1", "tenant": "a"}));
+        assert!(parse_document("[1]").is_err());
+        assert!(parse_document(r#"{"id": 1}"#).is_err());
+        assert!(parse_document("{oops").is_err());
+    }
+
+    #[test]
+    fn test_patch_operations() {
+        let strings = |list: &[&str]| list.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+        let ops = patch_operations(
+            &[],
+            &strings(&["/status=shipped", "/qty=3", "/meta={\"a\":1}", "/code=007"]),
+            &[],
+            &strings(&["/retries=-1"]),
+            &strings(&["/tempField"]),
+        )
+        .unwrap();
+        assert_eq!(
+            ops,
+            vec![
+                PatchOperation::Set {
+                    path: "/status".to_string(),
+                    value: json!("shipped")
+                },
+                PatchOperation::Set {
+                    path: "/qty".to_string(),
+                    value: json!(3)
+                },
+                PatchOperation::Set {
+                    path: "/meta".to_string(),
+                    value: json!({"a": 1})
+                },
+                PatchOperation::Set {
+                    path: "/code".to_string(),
+                    value: json!("007")
+                },
+                PatchOperation::Increment {
+                    path: "/retries".to_string(),
+                    value: json!(-1)
+                },
+                PatchOperation::Remove {
+                    path: "/tempField".to_string()
+                },
+            ]
+        );
+
+        assert!(patch_operations(&[], &[], &[], &[], &[]).is_err());
+        assert!(patch_operations(&[], &strings(&["status=x"]), &[], &[], &[]).is_err());
+        assert!(patch_operations(&[], &strings(&["/status"]), &[], &[], &[]).is_err());
+        assert!(patch_operations(&[], &[], &[], &strings(&["/n=many"]), &[]).is_err());
+        assert!(patch_operations(&[], &[], &[], &[], &strings(&["/"])).is_err());
+        let eleven: Vec<String> = (0..11).map(|i| format!("/f{i}=1")).collect();
+        assert!(patch_operations(&[], &eleven, &[], &[], &[]).is_err());
+    }
+
+    #[test]
+    fn test_strip_system_fields() {
+        let doc = json!({"id": "1", "_etag": "x", "_ts": 1, "_rid": "r", "name": "Ada"});
+        assert_eq!(strip_system_fields(doc), json!({"id": "1", "name": "Ada"}));
+    }
+}
@@ -0,0 +1,506 @@
+//! Doctor command — end-to-end connectivity and configuration diagnostics
+//!
+//! Walks the chain a query depends on (Azure CLI, tokens, config, endpoint,
+//! RBAC, database, container) and prints pass/fail with a remediation hint
+//! for each step, so failures don't surface as opaque query errors.
+
+use std::process::{Command, Stdio};
+
+use anyhow::{Result, bail};
+use colored::Colorize;
+use cosq_client::arm::{ArmClient, account_resource_id};
+use cosq_client::auth::{self, ARM_RESOURCE, AzCliAuth, COSMOS_RESOURCE};
+use cosq_client::cosmos::CosmosClient;
+use cosq_client::workload_identity::WorkloadIdentity;
+use cosq_core::config::{AuthMethod, Config};
+
+use super::common;
+
+pub struct DoctorArgs {
+    pub db: Option<String>,
+    pub container: Option<String>,
+    pub profile: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Status {
+    Pass,
+    Warn,
+    Fail,
+    Skip,
+}
+
+/// Outcome of one diagnostic step
+#[derive(Debug)]
+struct Check {
+    name: &'static str,
+    status: Status,
+    detail: String,
+    hint: Option<String>,
+}
+
+impl Check {
+    fn pass(name: &'static str, detail: impl Into<String>) -> Self {
+        Self {
+            name,
+            status: Status::Pass,
+            detail: detail.into(),
+            hint: None,
+        }
+    }
+
+    fn fail(name: &'static str, detail: impl Into<String>, hint: impl Into<String>) -> Self {
+        Self {
+            name,
+            status: Status::Fail,
+            detail: detail.into(),
+            hint: Some(hint.into()),
+        }
+    }
+
+    fn warn(name: &'static str, detail: impl Into<String>, hint: impl Into<String>) -> Self {
+        Self {
+            name,
+            status: Status::Warn,
+            detail: detail.into(),
+            hint: Some(hint.into()),
+        }
+    }
+
+    fn skip(name: &'static str, reason: impl Into<String>) -> Self {
+        Self {
+            name,
+            status: Status::Skip,
+            detail: reason.into(),
+            hint: None,
+        }
+    }
+
+    fn print(&self) {
+        let label = match self.status {
+            Status::Pass => "PASS".green().bold(),
+            Status::Warn => "WARN".yellow().bold(),
+            Status::Fail => "FAIL".red().bold(),
+            Status::Skip => "SKIP".dimmed(),
+        };
+        println!("  {label}  {:<24} {}", self.name, self.detail.dimmed());
+        if let Some(ref hint) = self.hint {
+            println!("        {} {hint}", "→".cyan());
+        }
+    }
+}
+
+/// Runs checks in order, printing each as it completes.
+#[derive(Default)]
+struct Report {
+    checks: Vec<Check>,
+}
+
+impl Report {
+    /// Record a check; returns whether it passed (or only warned).
+    fn add(&mut self, check: Check) -> bool {
+        check.print();
+        let ok = matches!(check.status, Status::Pass | Status::Warn);
+        self.checks.push(check);
+        ok
+    }
+
+    fn skip_rest(&mut self, names: &[&'static str], reason: &str) {
+        for name in names {
+            self.add(Check::skip(name, reason));
+        }
+    }
+
+    fn failures(&self) -> usize {
+        self.checks
+            .iter()
+            .filter(|c| c.status == Status::Fail)
+            .count()
+    }
+}
+
+const CHECK_ENDPOINT: &str = "Account reachable";
+const CHECK_DATA_PLANE: &str = "Data plane access";
+const CHECK_RBAC: &str = "RBAC data-plane role";
+const CHECK_DATABASE: &str = "Database exists";
+const CHECK_CONTAINER: &str = "Container exists";
+const CHECK_PROBE: &str = "Probe query";
+
+pub async fn run(args: DoctorArgs) -> Result<()> {
+    println!("{}\n", "cosq doctor".bold());
+    let mut report = Report::default();
+
+    // Config first: it decides whether Azure CLI auth is needed at all
+    let config = match load_config(args.profile.as_deref()) {
+        Ok(config) => {
+            report.add(Check::pass(
+                "Config",
+                format!("account '{}'", config.account.name),
+            ));
+            Some(config)
+        }
+        Err(e) => {
+            report.add(Check::fail(
+                "Config",
+                e.to_string(),
+                "run `cosq init` (or `cosq emulator start` for local development)",
+            ));
+            None
+        }
+    };
+    let uses_master_key = config
+        .as_ref()
+        .is_some_and(|c| c.account.master_key.is_some());
+
+    if uses_master_key {
+        report.skip_rest(
+            &[
+                "Azure CLI",
+                "Azure CLI login",
+                "ARM token",
+                "Cosmos DB token",
+            ],
+            "master key auth",
+        );
+    } else if auth::method() == AuthMethod::WorkloadIdentity {
+        check_workload_identity(&mut report).await;
+    } else {
+        check_azure_cli(&mut report).await;
+    }
+
+    let Some(config) = config else {
+        report.skip_rest(
+            &[
+                CHECK_ENDPOINT,
+                CHECK_DATA_PLANE,
+                CHECK_RBAC,
+                CHECK_DATABASE,
+                CHECK_CONTAINER,
+                CHECK_PROBE,
+            ],
+            "no config",
+        );
+        return finish(&report);
+    };
+
+    check_account(&mut report, &config, &args, uses_master_key).await;
+    finish(&report)
+}
+
+fn load_config(profile: Option<&str>) -> Result<Config> {
+    let config = Config::load()?;
+    Ok(match profile {
+        Some(profile) => config.for_profile(profile)?,
+        None => config,
+    })
+}
+
+async fn check_azure_cli(report: &mut Report) {
+    let installed = Command::new("az")
+        .arg("version")
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .is_ok_and(|s| s.success());
+    if !report.add(if installed {
+        Check::pass("Azure CLI", "az found on PATH")
+    } else {
+        Check::fail(
+            "Azure CLI",
+            "az not found",
+            "install the Azure CLI: https://aka.ms/install-azure-cli",
+        )
+    }) {
+        report.skip_rest(
+            &["Azure CLI login", "ARM token", "Cosmos DB token"],
+            "no Azure CLI",
+        );
+        return;
+    }
+
+    let logged_in = match AzCliAuth::check_status().await {
+        Ok(status) if status.logged_in => report.add(Check::pass(
+            "Azure CLI login",
+            status.user.unwrap_or_default(),
+        )),
+        Ok(_) => report.add(Check::fail(
+            "Azure CLI login",
+            "not logged in",
+            "run `cosq auth login`",
+        )),
+        Err(e) => report.add(Check::fail(
+            "Azure CLI login",
+            e.to_string(),
+            "run `cosq auth login`",
+        )),
+    };
+    if !logged_in {
+        report.skip_rest(&["ARM token", "Cosmos DB token"], "not logged in");
+        return;
+    }
+
+    check_tokens(
+        report,
+        "run `cosq auth login` to refresh credentials (check the tenant with `az account show`)",
+    )
+    .await;
+}
+
+async fn check_workload_identity(report: &mut Report) {
+    let found = report.add(match WorkloadIdentity::from_env() {
+        Some(identity) => Check::pass("Workload identity", identity.describe()),
+        None => Check::fail(
+            "Workload identity",
+            "AZURE_CLIENT_ID, AZURE_TENANT_ID or the federated token not set",
+            "grant the job `permissions: id-token: write` and set AZURE_CLIENT_ID and \
+             AZURE_TENANT_ID (or AZURE_FEDERATED_TOKEN_FILE)",
+        ),
+    });
+    if !found {
+        report.skip_rest(&["ARM token", "Cosmos DB token"], "no workload identity");
+        return;
+    }
+    check_tokens(
+        report,
+        "add a federated credential for this workflow's subject to the app registration",
+    )
+    .await;
+}
+
+async fn check_tokens(report: &mut Report, hint: &str) {
+    for (name, resource) in [
+        ("ARM token", ARM_RESOURCE),
+        ("Cosmos DB token", COSMOS_RESOURCE),
+    ] {
+        report.add(match auth::get_token(resource).await {
+            Ok(_) => Check::pass(name, "acquired"),
+            Err(e) => Check::fail(name, e.to_string(), hint),
+        });
+    }
+}
+
+async fn check_account(
+    report: &mut Report,
+    config: &Config,
+    args: &DoctorArgs,
+    uses_master_key: bool,
+) {
+    // Any HTTP response means DNS, TLS and the network path are fine
+    let http = reqwest::Client::builder()
+        .timeout(std::time::Duration::from_secs(10))
+        .build()
+        .unwrap_or_default();
+    let endpoint = &config.account.endpoint;
+    let reachable = match http.get(endpoint).send().await {
+        Ok(resp) => report.add(Check::pass(
+            CHECK_ENDPOINT,
+            format!("{endpoint} (HTTP {})", resp.status().as_u16()),
+        )),
+        // The emulator's self-signed certificate needs the configured CA
+        Err(_) if config.account.ca_cert.is_some() => report.add(Check::pass(
+            CHECK_ENDPOINT,
+            format!("{endpoint} (custom CA)"),
+        )),
+        Err(e) => report.add(Check::fail(
+            CHECK_ENDPOINT,
+            format!("{endpoint}: {e}"),
+            "check the endpoint in the config, your network/VPN, and the account's firewall settings",
+        )),
+    };
+    let rest = [
+        CHECK_DATA_PLANE,
+        CHECK_RBAC,
+        CHECK_DATABASE,
+        CHECK_CONTAINER,
+        CHECK_PROBE,
+    ];
+    if !reachable {
+        report.skip_rest(&rest, "account unreachable");
+        return;
+    }
+
+    let client = match common::connect(config).await {
+        Ok(client) => client,
+        Err(e) => {
+            report.add(Check::fail(
+                CHECK_DATA_PLANE,
+                format!("{e:#}"),
+                "run `cosq auth login`",
+            ));
+            report.skip_rest(&rest[1..], "no client");
+            return;
+        }
+    };
+
+    let databases = match client.list_databases().await {
+        Ok(databases) => {
+            report.add(Check::pass(
+                CHECK_DATA_PLANE,
+                format!("{} database(s) visible", databases.len()),
+            ));
+            Some(databases)
+        }
+        Err(e) => {
+            report.add(Check::fail(
+                CHECK_DATA_PLANE,
+                e.to_string(),
+                "assign a Cosmos DB data plane role (e.g. run `cosq init` and accept the role assignment)",
+            ));
+            None
+        }
+    };
+
+    check_rbac(report, config, uses_master_key).await;
+
+    let Some(databases) = databases else {
+        report.skip_rest(&rest[2..], "no data plane access");
+        return;
+    };
+    check_database_and_probe(report, &client, config, args, &databases).await;
+}
+
+async fn check_rbac(report: &mut Report, config: &Config, uses_master_key: bool) {
+    let account = &config.account;
+    if uses_master_key {
+        report.add(Check::skip(CHECK_RBAC, "master key auth"));
+        return;
+    }
+    if auth::method() == AuthMethod::WorkloadIdentity {
+        // The data plane check above already proves the identity's access
+        report.add(Check::skip(CHECK_RBAC, "workload identity"));
+        return;
+    }
+    if account.subscription.is_empty() || account.resource_group.is_empty() {
+        report.add(Check::skip(
+            CHECK_RBAC,
+            "no subscription/resource group in config",
+        ));
+        return;
+    }
+    let result = async {
+        let principal = AzCliAuth::get_principal_id().await?;
+        let arm = ArmClient::new().await?;
+        let id = account_resource_id(
+            &account.subscription,
+            &account.resource_group,
+            &account.name,
+        );
+        arm.has_cosmos_data_role(&id, &principal).await
+    }
+    .await;
+    report.add(match result {
+        Ok(true) => Check::pass(CHECK_RBAC, "role assignment found"),
+        Ok(false) => Check::fail(
+            CHECK_RBAC,
+            "no Cosmos DB SQL role assigned to you",
+            "run `cosq init` to assign the Data Contributor role, or ask an account owner",
+        ),
+        Err(e) => Check::warn(
+            CHECK_RBAC,
+            format!("could not verify: {e}"),
+            "reading role assignments needs Reader on the account; queries may still work",
+        ),
+    });
+}
+
+async fn check_database_and_probe(
+    report: &mut Report,
+    client: &CosmosClient,
+    config: &Config,
+    args: &DoctorArgs,
+    databases: &[String],
+) {
+    let Some(database) = args.db.as_deref().or(config.database.as_deref()) else {
+        report.skip_rest(
+            &[CHECK_DATABASE, CHECK_CONTAINER, CHECK_PROBE],
+            "no database configured (use --db)",
+        );
+        return;
+    };
+    if !report.add(if databases.iter().any(|d| d == database) {
+        Check::pass(CHECK_DATABASE, database)
+    } else {
+        Check::fail(
+            CHECK_DATABASE,
+            format!("'{database}' not found"),
+            format!("available: {}", databases.join(", ")),
+        )
+    }) {
+        report.skip_rest(&[CHECK_CONTAINER, CHECK_PROBE], "database missing");
+        return;
+    }
+
+    let Some(container) = args.container.as_deref().or(config.container.as_deref()) else {
+        report.skip_rest(
+            &[CHECK_CONTAINER, CHECK_PROBE],
+            "no container configured (use --container)",
+        );
+        return;
+    };
+    let exists = match client.get_container(database, container).await {
+        Ok(props) => report.add(Check::pass(
+            CHECK_CONTAINER,
+            format!(
+                "{container} (partition key {})",
+                props.partition_key.paths.join(", ")
+            ),
+        )),
+        Err(e) => {
+            let available = client.list_containers(database).await.unwrap_or_default();
+            report.add(Check::fail(
+                CHECK_CONTAINER,
+                e.to_string(),
+                format!("available: {}", available.join(", ")),
+            ))
+        }
+    };
+    if !exists {
+        report.add(Check::skip(CHECK_PROBE, "container missing"));
+        return;
+    }
+
+    report.add(
+        match client
+            .query(database, container, "SELECT TOP 1 VALUE c.id FROM c")
+            .await
+        {
+            Ok(result) => Check::pass(
+                CHECK_PROBE,
+                format!("{:.2} RUs", result.request_charge),
+            ),
+            Err(e) => Check::fail(
+                CHECK_PROBE,
+                e.to_string(),
+                "the account is reachable but queries fail — check data plane RBAC and firewall rules",
+            ),
+        },
+    );
+}
+
+fn finish(report: &Report) -> Result<()> {
+    println!();
+    match report.failures() {
+        0 => {
+            println!("{}", "All checks passed.".green().bold());
+            Ok(())
+        }
+        n => bail!("{n} check(s) failed"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_report_counts_failures() {
+        let mut report = Report::default();
+        assert!(report.add(Check::pass("a", "ok")));
+        assert!(report.add(Check::warn("b", "meh", "hint")));
+        assert!(!report.add(Check::fail("c", "bad", "fix it")));
+        report.skip_rest(&["d", "e"], "skipped");
+        assert_eq!(report.checks.len(), 5);
+        assert_eq!(report.failures(), 1);
+        assert!(finish(&report).is_err());
+    }
+}
@@ -0,0 +1,398 @@
+//! Emulator command — manage the Linux Cosmos DB emulator in Docker
+//!
+//! `cosq emulator start` pulls and runs the emulator container, waits for it
+//! to serve its certificate, saves that certificate next to the config, and
+//! adds a `local` profile that uses the emulator's well-known master key.
+
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::time::{Duration, Instant};
+
+use anyhow::{Context, Result, bail};
+use colored::Colorize;
+use cosq_core::config::{AccountConfig, Config, ProfileConfig};
+
+use crate::cli::EmulatorCommands;
+
+const CONTAINER_NAME: &str = "cosq-emulator";
+const IMAGE: &str = "mcr.microsoft.com/cosmosdb/linux/azure-cosmos-emulator:latest";
+const VOLUME: &str = "cosq-emulator-data";
+const ENDPOINT: &str = "https://localhost:8081/";
+const CERT_URL: &str = "https://localhost:8081/_explorer/emulator.pem";
+const PROFILE: &str = "local";
+
+/// The emulator's fixed, publicly documented master key
+const MASTER_KEY: &str =
+    "C2y6yDjf5/R+ob0N8A7Cgv30VRDJIWEHLM+4QDU5DE2nQ9nDuVTqobD4b8mGGyPMbIZnqyMsEcaGQy67XIw/Jw==";
+
+pub async fn run(cmd: EmulatorCommands) -> Result<()> {
+    match cmd {
+        EmulatorCommands::Start {
+            partitions,
+            timeout,
+            no_pull,
+        } => start(partitions, timeout, no_pull).await,
+        EmulatorCommands::Stop { rm } => stop(rm),
+        EmulatorCommands::Status => status().await,
+    }
+}
+
+async fn start(partitions: u32, timeout: u64, no_pull: bool) -> Result<()> {
+    ensure_docker()?;
+
+    match container_state()? {
+        Some(state) if state == "running" => {
+            eprintln!("{} emulator container is already running", "✓".green());
+        }
+        Some(_) => {
+            eprintln!("{}", "Starting existing emulator container...".dimmed());
+            docker(&["start", CONTAINER_NAME])?;
+        }
+        None => {
+            if !no_pull {
+                eprintln!("{}", format!("Pulling {IMAGE}...").dimmed());
+                docker_inherit(&["pull", IMAGE])?;
+            }
+            eprintln!("{}", "Creating emulator container...".dimmed());
+            docker(&run_args(partitions))?;
+        }
+    }
+
+    eprintln!(
+        "{}",
+        "Waiting for the emulator to become ready (this can take a few minutes)...".dimmed()
+    );
+    let pem = wait_until_ready(Duration::from_secs(timeout)).await?;
+    eprintln!("{} emulator is ready at {}", "✓".green(), ENDPOINT.cyan());
+
+    let cert_path = cert_path()?;
+    std::fs::write(&cert_path, &pem)
+        .with_context(|| format!("failed to write {}", cert_path.display()))?;
+    eprintln!(
+        "{} saved emulator certificate to {}",
+        "✓".green(),
+        cert_path.display()
+    );
+
+    let existing = Config::load().ok();
+    let had_config = existing.is_some();
+    let config = with_local_profile(existing, &cert_path);
+    let path = config.save()?;
+    if had_config {
+        eprintln!(
+            "{} profile '{}' saved to {}",
+            "✓".green(),
+            PROFILE,
+            path.display()
+        );
+        eprintln!(
+            "\nUse it with {} or set {}.",
+            "--profile local".cyan(),
+            "COSQ_PROFILE=local".cyan()
+        );
+    } else {
+        eprintln!(
+            "{} config created at {} with the emulator as the default account",
+            "✓".green(),
+            path.display()
+        );
+    }
+    Ok(())
+}
+
+fn stop(rm: bool) -> Result<()> {
+    ensure_docker()?;
+    if container_state()?.is_none() {
+        eprintln!("No emulator container found.");
+        return Ok(());
+    }
+    docker(&["stop", CONTAINER_NAME])?;
+    eprintln!("{} emulator stopped", "✓".green());
+    if rm {
+        docker(&["rm", CONTAINER_NAME])?;
+        eprintln!(
+            "{} container removed (data remains in volume '{VOLUME}')",
+            "✓".green()
+        );
+    }
+    Ok(())
+}
+
+async fn status() -> Result<()> {
+    ensure_docker()?;
+    let state = container_state()?;
+    println!(
+        "{}  {}",
+        "Container:".bold(),
+        state.as_deref().unwrap_or("not created")
+    );
+
+    let reachable = state.as_deref() == Some("running") && fetch_certificate().await.is_ok();
+    println!(
+        "{}   {} ({})",
+        "Endpoint:".bold(),
+        ENDPOINT,
+        if reachable {
+            "ready".green()
+        } else {
+            "not reachable".yellow()
+        }
+    );
+
+    let configured = Config::load()
+        .map(|c| c.profiles.contains_key(PROFILE) || c.account.endpoint == ENDPOINT)
+        .unwrap_or(false);
+    println!(
+        "{}    {}",
+        "Profile:".bold(),
+        if configured {
+            PROFILE.green()
+        } else {
+            "not configured (run `cosq emulator start`)".yellow()
+        }
+    );
+    Ok(())
+}
+
+/// Arguments for `docker run` that create the emulator container.
+fn run_args(partitions: u32) -> Vec<String> {
+    let mut args: Vec<String> = vec![
+        "run".into(),
+        "-d".into(),
+        "--name".into(),
+        CONTAINER_NAME.into(),
+        "-p".into(),
+        "8081:8081".into(),
+    ];
+    for port in 10250..=10255 {
+        args.push("-p".into());
+        args.push(format!("{port}:{port}"));
+    }
+    args.extend([
+        "-e".into(),
+        format!("AZURE_COSMOS_EMULATOR_PARTITION_COUNT={partitions}"),
+        "-e".into(),
+        "AZURE_COSMOS_EMULATOR_ENABLE_DATA_PERSISTENCE=true".into(),
+        "-v".into(),
+        format!("{VOLUME}:/tmp/cosmos/appdata"),
+        IMAGE.into(),
+    ]);
+    args
+}
+
+/// Account config pointing at the emulator with its well-known key and certificate.
+fn local_account(cert_path: &Path) -> AccountConfig {
+    AccountConfig {
+        name: "localhost".to_string(),
+        subscription: String::new(),
+        resource_group: String::new(),
+        endpoint: ENDPOINT.to_string(),
+        master_key: Some(MASTER_KEY.to_string()),
+        ca_cert: Some(cert_path.display().to_string()),
+        capacity_mode: None,
+    }
+}
+
+/// Add (or refresh) the `local` profile, or create a config whose default
+/// account is the emulator when none exists yet.
+fn with_local_profile(existing: Option<Config>, cert_path: &Path) -> Config {
+    let account = local_account(cert_path);
+    match existing {
+        Some(mut config) => {
+            let previous = config.profiles.remove(PROFILE);
+            config.profiles.insert(
+                PROFILE.to_string(),
+                ProfileConfig {
+                    account,
+                    database: previous.as_ref().and_then(|p| p.database.clone()),
+                    container: previous.and_then(|p| p.container),
+                },
+            );
+            config
+        }
+        None => Config {
+            account: account.clone(),
+            database: None,
+            container: None,
+            profiles: [(
+                PROFILE.to_string(),
+                ProfileConfig {
+                    account,
+                    database: None,
+                    container: None,
+                },
+            )]
+            .into(),
+            webhooks: Default::default(),
+            storage_account: None,
+            retry: None,
+            pricing: None,
+            redact: Vec::new(),
+            slow_query: None,
+            trusted_commands: Vec::new(),
+            ai_save: None,
+            ai_budget: None,
+            ai_log: false,
+            ai_data: None,
+            ai: None,
+            output: None,
+            http: None,
+            auth: None,
+            containers: Default::default(),
+        },
+    }
+}
+
+/// Where the emulator certificate is stored: next to the config file.
+fn cert_path() -> Result<PathBuf> {
+    let config_path = Config::path()?;
+    let dir = config_path
+        .parent()
+        .context("config path has no parent directory")?;
+    std::fs::create_dir_all(dir)?;
+    Ok(dir.join("emulator.pem"))
+}
+
+/// Poll the emulator's certificate endpoint until it responds or the timeout elapses.
+async fn wait_until_ready(timeout: Duration) -> Result<String> {
+    let started = Instant::now();
+    loop {
+        if let Ok(pem) = fetch_certificate().await {
+            return Ok(pem);
+        }
+        if started.elapsed() >= timeout {
+            bail!(
+                "emulator did not become ready within {}s — check `docker logs {CONTAINER_NAME}`",
+                timeout.as_secs()
+            );
+        }
+        tokio::time::sleep(Duration::from_secs(3)).await;
+    }
+}
+
+/// Download the emulator's self-signed certificate. The certificate is not yet
+/// trusted at this point, so verification is skipped for this one request.
+async fn fetch_certificate() -> Result<String> {
+    let client = reqwest::Client::builder()
+        .danger_accept_invalid_certs(true)
+        .timeout(Duration::from_secs(5))
+        .build()?;
+    let pem = client
+        .get(CERT_URL)
+        .send()
+        .await?
+        .error_for_status()?
+        .text()
+        .await?;
+    if !pem.contains("BEGIN CERTIFICATE") {
+        bail!("unexpected response from {CERT_URL}");
+    }
+    Ok(pem)
+}
+
+fn ensure_docker() -> Result<()> {
+    let ok = Command::new("docker")
+        .arg("version")
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .map(|s| s.success())
+        .unwrap_or(false);
+    if !ok {
+        bail!("Docker is not available. Install Docker and make sure the daemon is running.");
+    }
+    Ok(())
+}
+
+/// The container's state (`running`, `exited`, ...), or `None` if it doesn't exist.
+fn container_state() -> Result<Option<String>> {
+    let output = Command::new("docker")
+        .args(["inspect", "-f", "{{.State.Status}}", CONTAINER_NAME])
+        .stderr(Stdio::null())
+        .output()
+        .context("failed to run docker")?;
+    if !output.status.success() {
+        return Ok(None);
+    }
+    Ok(Some(
+        String::from_utf8_lossy(&output.stdout).trim().to_string(),
+    ))
+}
+
+/// Run a docker command, capturing output and surfacing stderr on failure.
+fn docker<S: AsRef<std::ffi::OsStr>>(args: &[S]) -> Result<()> {
+    let output = Command::new("docker")
+        .args(args)
+        .output()
+        .context("failed to run docker")?;
+    if !output.status.success() {
+        bail!(
+            "docker {} failed: {}",
+            args.first()
+                .map(|a| a.as_ref().to_string_lossy().into_owned())
+                .unwrap_or_default(),
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(())
+}
+
+/// Run a docker command with its progress output shown to the user.
+fn docker_inherit(args: &[&str]) -> Result<()> {
+    let status = Command::new("docker")
+        .args(args)
+        .stdout(Stdio::inherit())
+        .stderr(Stdio::inherit())
+        .status()
+        .context("failed to run docker")?;
+    if !status.success() {
+        bail!("docker {} failed", args.join(" "));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_run_args() {
+        let args = run_args(4);
+        assert_eq!(&args[..4], ["run", "-d", "--name", CONTAINER_NAME]);
+        assert!(args.contains(&"8081:8081".to_string()));
+        assert!(args.contains(&"10255:10255".to_string()));
+        assert!(args.contains(&"AZURE_COSMOS_EMULATOR_PARTITION_COUNT=4".to_string()));
+        assert!(args.contains(&format!("{VOLUME}:/tmp/cosmos/appdata")));
+        assert_eq!(args.last().unwrap(), IMAGE);
+    }
+
+    #[test]
+    fn test_with_local_profile_new_config() {
+        let config = with_local_profile(None, Path::new("/tmp/emulator.pem"));
+        assert_eq!(config.account.endpoint, ENDPOINT);
+        assert_eq!(config.account.master_key.as_deref(), Some(MASTER_KEY));
+        assert_eq!(config.account.ca_cert.as_deref(), Some("/tmp/emulator.pem"));
+        assert!(config.profiles.contains_key(PROFILE));
+    }
+
+    #[test]
+    fn test_with_local_profile_keeps_existing() {
+        let mut existing = with_local_profile(None, Path::new("/old.pem"));
+        existing.account = AccountConfig {
+            name: "prod".into(),
+            subscription: "sub".into(),
+            resource_group: "rg".into(),
+            endpoint: "https://prod.documents.azure.com:443/".into(),
+            master_key: None,
+            ca_cert: None,
+            capacity_mode: None,
+        };
+        existing.profiles.get_mut(PROFILE).unwrap().database = Some("devdb".into());
+
+        let config = with_local_profile(Some(existing), Path::new("/new.pem"));
+        assert_eq!(config.account.name, "prod");
+        let local = &config.profiles[PROFILE];
+        assert_eq!(local.account.ca_cert.as_deref(), Some("/new.pem"));
+        assert_eq!(local.database.as_deref(), Some("devdb"));
+    }
+}
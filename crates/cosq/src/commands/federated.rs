@@ -0,0 +1,316 @@
+//! Federated queries — run the same SQL against several accounts concurrently
+//!
+//! Targets come from `--target` or `targets:` in a stored query. Each target is
+//! resolved to a profile (or account), connected to in parallel, and the
+//! results are merged with a `_source` field naming the target.
+
+use anyhow::{Context, Result, bail};
+use colored::Colorize;
+use cosq_client::cosmos::{PriorityLevel, QueryResult};
+use cosq_core::config::Config;
+use cosq_core::stored_query::QueryTarget;
+use serde_json::Value;
+use tokio::task::JoinSet;
+
+use super::common;
+
+/// Field added to every merged document naming the target it came from
+pub const SOURCE_FIELD: &str = "_source";
+
+/// Where and what to query on every target
+pub struct FederatedQuery<'a> {
+    pub sql: &'a str,
+    pub parameters: Vec<Value>,
+    /// `--db` / `--container` flags (override the targets)
+    pub db: Option<&'a str>,
+    pub container: Option<&'a str>,
+    /// Stored query `database:` / `container:` (used when a target doesn't name one)
+    pub default_db: Option<&'a str>,
+    pub default_container: Option<&'a str>,
+    pub priority: Option<PriorityLevel>,
+    /// `--force`, and whether a protected container may be confirmed at a prompt
+    /// (see `common::guard_protected`)
+    pub force: bool,
+    pub interactive: bool,
+    pub quiet: bool,
+    /// Cache query plans (see `common::with_plan_cache`), for stored queries
+    pub cache_plans: bool,
+}
+
+/// Parse target strings from the CLI or front matter.
+pub fn parse_targets(targets: &[String]) -> Result<Vec<QueryTarget>> {
+    Ok(targets
+        .iter()
+        .map(|t| QueryTarget::parse(t))
+        .collect::<Result<_, _>>()?)
+}
+
+/// Config for a target: a profile of that name, else the named account.
+fn target_config(base: &Config, target: &QueryTarget) -> Config {
+    if base.profiles.contains_key(&target.name) {
+        base.for_profile(&target.name)
+            .unwrap_or_else(|_| base.for_account(&target.name))
+    } else {
+        base.for_account(&target.name)
+    }
+}
+
+/// Resolve the database and container for a target (no interactive pickers).
+fn target_location(
+    target: &QueryTarget,
+    config: &Config,
+    query: &FederatedQuery<'_>,
+) -> Result<(String, String)> {
+    let database = query
+        .db
+        .or(target.database.as_deref())
+        .or(query.default_db)
+        .or(config.database.as_deref());
+    let container = query
+        .container
+        .or(target.container.as_deref())
+        .or(query.default_container)
+        .or(config.container.as_deref());
+    match (database, container) {
+        (Some(db), Some(ctr)) => Ok((db.to_string(), ctr.to_string())),
+        _ => bail!(
+            "target '{target}' has no database/container — use <name>/<database>/<container>, --db/--container, or set them in the query or profile"
+        ),
+    }
+}
+
+/// Run the query on every target concurrently and merge the results.
+///
+/// Targets that fail are reported and skipped; it is an error only if every
+/// target fails.
+pub async fn execute(
+    base: &Config,
+    targets: &[QueryTarget],
+    query: FederatedQuery<'_>,
+) -> Result<QueryResult> {
+    let mut tasks = JoinSet::new();
+    for (index, target) in targets.iter().enumerate() {
+        let config = target_config(base, target);
+        let location = target_location(target, &config, &query);
+        if let Ok((ref database, ref container)) = location {
+            common::guard_protected(
+                &config,
+                database,
+                container,
+                query.sql,
+                query.force,
+                query.interactive,
+            )
+            .with_context(|| format!("target '{target}'"))?;
+        }
+        let sql = query.sql.to_string();
+        let parameters = query.parameters.clone();
+        let priority = query.priority;
+        let cache_plans = query.cache_plans;
+        tasks.spawn(async move {
+            let result = async {
+                let (database, container) = location?;
+                let mut client = common::connect(&config).await?;
+                if cache_plans {
+                    client = common::with_plan_cache(client);
+                }
+                if let Some(level) = priority {
+                    client = client.with_priority(level);
+                }
+                Ok::<_, anyhow::Error>(
+                    client
+                        .query_with_params(&database, &container, &sql, parameters)
+                        .await?,
+                )
+            }
+            .await;
+            (index, result)
+        });
+    }
+
+    let mut results: Vec<Option<Result<QueryResult>>> = (0..targets.len()).map(|_| None).collect();
+    while let Some(joined) = tasks.join_next().await {
+        let (index, result) = joined?;
+        results[index] = Some(result);
+    }
+
+    let mut documents = Vec::new();
+    let mut request_charge = 0.0;
+    let mut partitions = Vec::new();
+    let mut failures = Vec::new();
+    for (target, result) in targets.iter().zip(results) {
+        let label = target.to_string();
+        match result.expect("every target task reports back") {
+            Ok(result) => {
+                if !query.quiet {
+                    eprintln!(
+                        "  {} {} {}",
+                        "✓".green(),
+                        label.cyan(),
+                        format!(
+                            "({} documents, {:.2} RUs)",
+                            result.documents.len(),
+                            result.request_charge
+                        )
+                        .dimmed()
+                    );
+                }
+                request_charge += result.request_charge;
+                // Range ids repeat across accounts, so label them with the target
+                partitions.extend(result.partitions.into_iter().map(|mut p| {
+                    p.range_id = format!("{label}:{}", p.range_id);
+                    p
+                }));
+                documents.extend(result.documents.into_iter().map(|d| tag_source(d, &label)));
+            }
+            Err(e) => {
+                eprintln!("  {} {}: {e:#}", "✗".red(), label.cyan());
+                failures.push(label);
+            }
+        }
+    }
+
+    if failures.len() == targets.len() {
+        bail!("the query failed on every target");
+    }
+    if !failures.is_empty() && !query.quiet {
+        eprintln!(
+            "{} results are partial — failed targets: {}",
+            "Warning:".yellow().bold(),
+            failures.join(", ")
+        );
+    }
+
+    Ok(QueryResult {
+        documents,
+        request_charge,
+        partitions,
+        partial: false,
+    })
+}
+
+/// Add `_source` to a document (non-object values are wrapped as `{"_source", "value"}`).
+fn tag_source(document: Value, label: &str) -> Value {
+    match document {
+        Value::Object(mut map) => {
+            map.insert(SOURCE_FIELD.to_string(), Value::String(label.to_string()));
+            Value::Object(map)
+        }
+        other => serde_json::json!({ SOURCE_FIELD: label, "value": other }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cosq_core::config::{AccountConfig, ProfileConfig};
+    use serde_json::json;
+
+    fn base_config() -> Config {
+        let account = |name: &str| AccountConfig {
+            name: name.into(),
+            subscription: String::new(),
+            resource_group: String::new(),
+            endpoint: format!("https://{name}.documents.azure.com:443/"),
+            master_key: None,
+            ca_cert: None,
+            capacity_mode: None,
+        };
+        Config {
+            account: account("main"),
+            database: Some("maindb".into()),
+            container: None,
+            profiles: [(
+                "eu".to_string(),
+                ProfileConfig {
+                    account: account("cosmos-eu"),
+                    database: Some("eudb".into()),
+                    container: Some("jobs".into()),
+                },
+            )]
+            .into(),
+            webhooks: Default::default(),
+            storage_account: None,
+            retry: None,
+            pricing: None,
+            redact: Vec::new(),
+            slow_query: None,
+            trusted_commands: Vec::new(),
+            ai_save: None,
+            ai_budget: None,
+            ai_log: false,
+            ai_data: None,
+            ai: None,
+            output: None,
+            http: None,
+            auth: None,
+            containers: Default::default(),
+        }
+    }
+
+    fn query<'a>() -> FederatedQuery<'a> {
+        FederatedQuery {
+            sql: "SELECT * FROM c",
+            parameters: Vec::new(),
+            db: None,
+            container: None,
+            default_db: None,
+            default_container: None,
+            priority: None,
+            force: false,
+            interactive: false,
+            quiet: true,
+            cache_plans: false,
+        }
+    }
+
+    #[test]
+    fn test_target_resolution() {
+        let base = base_config();
+
+        let eu = QueryTarget::parse("eu").unwrap();
+        let config = target_config(&base, &eu);
+        assert_eq!(config.account.name, "cosmos-eu");
+        assert_eq!(
+            target_location(&eu, &config, &query()).unwrap(),
+            ("eudb".to_string(), "jobs".to_string())
+        );
+
+        // Unknown names are accounts; the stored query's container fills the gap
+        let us = QueryTarget::parse("cosmos-us/usdb").unwrap();
+        let config = target_config(&base, &us);
+        assert_eq!(config.account.name, "cosmos-us");
+        let q = FederatedQuery {
+            default_container: Some("jobs"),
+            ..query()
+        };
+        assert_eq!(
+            target_location(&us, &config, &q).unwrap(),
+            ("usdb".to_string(), "jobs".to_string())
+        );
+        assert!(target_location(&us, &config, &query()).is_err());
+
+        // --db/--container override the target
+        let q = FederatedQuery {
+            db: Some("flagdb"),
+            container: Some("flagctr"),
+            ..query()
+        };
+        assert_eq!(
+            target_location(&eu, &target_config(&base, &eu), &q).unwrap(),
+            ("flagdb".to_string(), "flagctr".to_string())
+        );
+    }
+
+    #[test]
+    fn test_tag_source() {
+        assert_eq!(
+            tag_source(json!({"id": "1"}), "eu"),
+            json!({"id": "1", "_source": "eu"})
+        );
+        assert_eq!(
+            tag_source(json!(42), "eu"),
+            json!({"_source": "eu", "value": 42})
+        );
+    }
+}
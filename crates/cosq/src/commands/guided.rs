@@ -0,0 +1,181 @@
+//! Guided query builder (`cosq query --interactive`)
+//!
+//! Infers the container schema from sampled documents, then walks the user
+//! through fields, filters, ordering and TOP with prompts. Values are bound
+//! as parameters, and the generated SQL is shown before it runs.
+
+use anyhow::{Context, Result, bail};
+use colored::Colorize;
+use cosq_client::backend::CosmosBackend;
+use cosq_core::query_builder::{BuiltQuery, Op, Order, Query};
+use cosq_core::schema::{JsonType, Schema};
+use inquire::{Confirm, MultiSelect, Select, Text};
+use serde_json::Value;
+
+/// Documents sampled to infer the schema
+const SAMPLE_SIZE: u32 = 100;
+
+const OPERATORS: &[(&str, Op)] = &[
+    ("=", Op::Eq),
+    ("!=", Op::Ne),
+    ("<", Op::Lt),
+    ("<=", Op::Le),
+    (">", Op::Gt),
+    (">=", Op::Ge),
+    ("contains", Op::Contains),
+    ("starts with", Op::StartsWith),
+];
+
+/// Prompt for a query against `database`/`container` and return it once confirmed.
+pub async fn build_query(
+    client: &impl CosmosBackend,
+    database: &str,
+    container: &str,
+) -> Result<BuiltQuery> {
+    eprintln!(
+        "{}",
+        format!("Sampling documents from {container}...").dimmed()
+    );
+    let sample = client
+        .query(
+            database,
+            container,
+            &format!("SELECT TOP {SAMPLE_SIZE} * FROM c"),
+        )
+        .await
+        .with_context(|| format!("failed to sample documents from {container}"))?;
+    let schema = Schema::infer(&sample.documents);
+    if schema.fields.is_empty() {
+        bail!("container '{container}' has no documents to infer fields from");
+    }
+    let labels: Vec<String> = schema.fields.iter().map(field_label).collect();
+
+    // Fields (none selected means SELECT *)
+    let selected = MultiSelect::new("Fields to return (none = all):", labels.clone())
+        .prompt()
+        .context("field selection cancelled")?;
+    let mut query = Query::from("c").select(selected.iter().map(|l| path_of(l)));
+
+    // Filters
+    while Confirm::new("Add a filter?")
+        .with_default(false)
+        .prompt()
+        .context("filter prompt cancelled")?
+    {
+        let label = Select::new("Filter on field:", labels.clone())
+            .prompt()
+            .context("filter cancelled")?;
+        let path = path_of(&label);
+        let op_names: Vec<&str> = OPERATORS.iter().map(|(name, _)| *name).collect();
+        let op_name = Select::new("Operator:", op_names)
+            .prompt()
+            .context("filter cancelled")?;
+        let op = OPERATORS
+            .iter()
+            .find(|(name, _)| *name == op_name)
+            .map(|(_, op)| *op)
+            .expect("operator comes from OPERATORS");
+        let input = Text::new("Value:").prompt().context("filter cancelled")?;
+        let kind = schema.field(path).and_then(|f| f.primary_type());
+        query = query.filter(path, op, parse_value(&input, kind));
+    }
+
+    // Ordering
+    let mut order_options = vec!["(none)".to_string()];
+    order_options.extend(labels.iter().cloned());
+    let order_label = Select::new("Order by:", order_options)
+        .prompt()
+        .context("order prompt cancelled")?;
+    if order_label != "(none)" {
+        let direction = Select::new("Direction:", vec!["ascending", "descending"])
+            .prompt()
+            .context("order prompt cancelled")?;
+        let order = if direction == "descending" {
+            Order::Desc
+        } else {
+            Order::Asc
+        };
+        query = query.order_by(path_of(&order_label), order);
+    }
+
+    // TOP
+    let top = Text::new("Maximum number of results (empty = no limit):")
+        .with_default("100")
+        .prompt()
+        .context("TOP prompt cancelled")?;
+    if !top.trim().is_empty() {
+        let n: u64 = top
+            .trim()
+            .parse()
+            .with_context(|| format!("'{top}' is not a whole number"))?;
+        query = query.top(n);
+    }
+
+    let built = query.build()?;
+    eprintln!("\n{}", built.sql.cyan());
+    for (name, value) in &built.parameters {
+        eprintln!("  {} = {value}", name.dimmed());
+    }
+    eprintln!();
+    if !Confirm::new("Run this query?")
+        .with_default(true)
+        .prompt()
+        .context("confirmation cancelled")?
+    {
+        bail!("query not run");
+    }
+    Ok(built)
+}
+
+/// `customer.name (string)`
+fn field_label(field: &cosq_core::schema::FieldSchema) -> String {
+    let types: Vec<String> = field.types.iter().map(ToString::to_string).collect();
+    format!("{} ({})", field.path, types.join("|"))
+}
+
+/// Field path from a label produced by [`field_label`].
+fn path_of(label: &str) -> &str {
+    label.split(" (").next().unwrap_or(label)
+}
+
+/// Interpret typed input according to the field's inferred type, falling
+/// back to a string.
+fn parse_value(input: &str, kind: Option<JsonType>) -> Value {
+    let trimmed = input.trim();
+    match kind {
+        Some(JsonType::Number) => serde_json::from_str::<serde_json::Number>(trimmed)
+            .map(Value::Number)
+            .unwrap_or_else(|_| Value::String(input.to_string())),
+        Some(JsonType::Bool) => match trimmed.to_ascii_lowercase().as_str() {
+            "true" | "yes" => Value::Bool(true),
+            "false" | "no" => Value::Bool(false),
+            _ => Value::String(input.to_string()),
+        },
+        _ if trimmed == "null" => Value::Null,
+        _ => Value::String(input.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_parse_value() {
+        assert_eq!(parse_value("42", Some(JsonType::Number)), json!(42));
+        assert_eq!(parse_value(" 1.5 ", Some(JsonType::Number)), json!(1.5));
+        assert_eq!(parse_value("abc", Some(JsonType::Number)), json!("abc"));
+        assert_eq!(parse_value("Yes", Some(JsonType::Bool)), json!(true));
+        assert_eq!(parse_value("42", Some(JsonType::String)), json!("42"));
+        assert_eq!(parse_value("null", None), Value::Null);
+    }
+
+    #[test]
+    fn test_field_label_round_trip() {
+        let schema = Schema::infer(&[json!({"customer": {"name": "Ada"}, "n": 1})]);
+        let label = field_label(schema.field("customer.name").unwrap());
+        assert_eq!(label, "customer.name (string)");
+        assert_eq!(path_of(&label), "customer.name");
+    }
+}
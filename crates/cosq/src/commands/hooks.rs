@@ -0,0 +1,235 @@
+//! Post-run hooks — pipe stored query output to a shell command
+//!
+//! Hooks come from `.cosq` files that may be shared, so they only run after
+//! confirmation unless `--run-hooks` is given. The same policy applies to
+//! parameter `default_cmd:` commands, except those listed under
+//! `trusted_commands:` in the config.
+
+use std::io::{IsTerminal, Write};
+use std::process::{Command, Stdio};
+
+use anyhow::{Context, Result, bail};
+use colored::Colorize;
+use cosq_core::stored_query::PostRunHook;
+use inquire::Confirm;
+
+/// Whether to run a query's post-run hook
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HookPolicy {
+    /// Ask before running (skipped when not interactive)
+    Ask,
+    /// Run without asking (`--run-hooks`)
+    Always,
+    /// Never run (`--no-hooks`)
+    Never,
+}
+
+impl HookPolicy {
+    pub fn from_flags(run_hooks: bool, no_hooks: bool) -> Self {
+        if no_hooks {
+            HookPolicy::Never
+        } else if run_hooks {
+            HookPolicy::Always
+        } else {
+            HookPolicy::Ask
+        }
+    }
+}
+
+/// Run a post-run hook, piping `input` to its stdin.
+///
+/// Fails if the command exits with a non-zero status.
+pub fn run_post_run(
+    hook: &PostRunHook,
+    input: &[u8],
+    policy: HookPolicy,
+    quiet: bool,
+) -> Result<()> {
+    match policy {
+        HookPolicy::Never => return Ok(()),
+        HookPolicy::Always => {}
+        HookPolicy::Ask => {
+            if !std::io::stdin().is_terminal() {
+                eprintln!(
+                    "{} post-run hook `{}` skipped (pass --run-hooks to run it non-interactively)",
+                    "Note:".yellow().bold(),
+                    hook.command
+                );
+                return Ok(());
+            }
+            let confirmed = Confirm::new(&format!("Run post-run hook `{}`?", hook.command))
+                .with_default(false)
+                .prompt()
+                .unwrap_or(false);
+            if !confirmed {
+                return Ok(());
+            }
+        }
+    }
+
+    if !quiet {
+        eprintln!("{} {}", "Post-run hook:".dimmed(), hook.command.cyan());
+    }
+
+    let mut child = shell_command(&hook.command)
+        .stdin(Stdio::piped())
+        .spawn()
+        .with_context(|| format!("failed to start post-run hook `{}`", hook.command))?;
+
+    if let Some(mut stdin) = child.stdin.take() {
+        // A hook that ignores its input closes the pipe early — not an error
+        if let Err(e) = stdin.write_all(input)
+            && e.kind() != std::io::ErrorKind::BrokenPipe
+        {
+            return Err(e).context("failed to write to post-run hook");
+        }
+    }
+
+    let status = child.wait().context("failed to wait for post-run hook")?;
+    if !status.success() {
+        match status.code() {
+            Some(code) => bail!("Post-run hook `{}` exited with status {code}", hook.command),
+            None => bail!(
+                "Post-run hook `{}` was terminated by a signal",
+                hook.command
+            ),
+        }
+    }
+
+    if !quiet {
+        eprintln!("  {} {}", "exit status".dimmed(), "0".green());
+    }
+
+    Ok(())
+}
+
+/// Compute a parameter default with its `default_cmd:`, returning the trimmed
+/// stdout. `None` when the command is declined or skipped, or fails (with a
+/// warning), so the caller falls back to the static default.
+pub fn run_default_cmd(
+    param: &str,
+    command: &str,
+    trusted: &[String],
+    policy: HookPolicy,
+) -> Result<Option<String>> {
+    let policy = if trusted.iter().any(|t| t == command) && policy == HookPolicy::Ask {
+        HookPolicy::Always
+    } else {
+        policy
+    };
+    match policy {
+        HookPolicy::Never => return Ok(None),
+        HookPolicy::Always => {}
+        HookPolicy::Ask => {
+            if !std::io::stdin().is_terminal() {
+                eprintln!(
+                    "{} default command `{command}` for '{param}' skipped \
+                     (pass --run-hooks or add it to `trusted_commands:` in the config)",
+                    "Note:".yellow().bold(),
+                );
+                return Ok(None);
+            }
+            let confirmed = Confirm::new(&format!(
+                "Run `{command}` to compute the default for '{param}'?"
+            ))
+            .with_default(false)
+            .prompt()
+            .unwrap_or(false);
+            if !confirmed {
+                return Ok(None);
+            }
+        }
+    }
+
+    let output = shell_command(command)
+        .stdin(Stdio::null())
+        .stderr(Stdio::inherit())
+        .output()
+        .with_context(|| format!("failed to start default command `{command}`"))?;
+    if !output.status.success() {
+        eprintln!(
+            "{} default command `{command}` for '{param}' failed ({}), using the static default",
+            "Warning:".yellow().bold(),
+            output.status
+        );
+        return Ok(None);
+    }
+    Ok(Some(
+        String::from_utf8_lossy(&output.stdout).trim().to_string(),
+    ))
+}
+
+fn shell_command(command: &str) -> Command {
+    if cfg!(windows) {
+        let mut cmd = Command::new("cmd");
+        cmd.args(["/C", command]);
+        cmd
+    } else {
+        let mut cmd = Command::new("sh");
+        cmd.args(["-c", command]);
+        cmd
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cosq_core::stored_query::HookInput;
+
+    fn hook(command: &str) -> PostRunHook {
+        PostRunHook {
+            command: command.to_string(),
+            input: HookInput::Output,
+        }
+    }
+
+    #[test]
+    fn test_policy_from_flags() {
+        assert_eq!(HookPolicy::from_flags(false, false), HookPolicy::Ask);
+        assert_eq!(HookPolicy::from_flags(true, false), HookPolicy::Always);
+        assert_eq!(HookPolicy::from_flags(false, true), HookPolicy::Never);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_run_post_run_pipes_input() {
+        let dir = tempfile::tempdir().unwrap();
+        let out = dir.path().join("out.txt");
+        let h = hook(&format!("cat > '{}'", out.display()));
+        run_post_run(&h, b"hello", HookPolicy::Always, true).unwrap();
+        assert_eq!(std::fs::read_to_string(&out).unwrap(), "hello");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_run_post_run_reports_exit_status() {
+        let err = run_post_run(&hook("exit 3"), b"", HookPolicy::Always, true).unwrap_err();
+        assert!(err.to_string().contains("status 3"));
+    }
+
+    #[test]
+    fn test_run_post_run_never() {
+        run_post_run(&hook("exit 1"), b"", HookPolicy::Never, true).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_run_default_cmd() {
+        let value = run_default_cmd("branch", "echo ' main '", &[], HookPolicy::Always).unwrap();
+        assert_eq!(value.as_deref(), Some("main"));
+
+        // Trusted commands run without asking, even when not interactive
+        let trusted = vec!["echo dev".to_string()];
+        let value = run_default_cmd("branch", "echo dev", &trusted, HookPolicy::Ask).unwrap();
+        assert_eq!(value.as_deref(), Some("dev"));
+
+        assert_eq!(
+            run_default_cmd("branch", "echo dev", &trusted, HookPolicy::Never).unwrap(),
+            None
+        );
+        assert_eq!(
+            run_default_cmd("branch", "exit 1", &[], HookPolicy::Always).unwrap(),
+            None
+        );
+    }
+}
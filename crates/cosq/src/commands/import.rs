@@ -0,0 +1,258 @@
+//! Import command — load documents from a file into a container
+//!
+//! Streams documents from a JSON array, NDJSON or CSV file and upserts them
+//! in batches, each batch written with bounded concurrency. Throttled writes
+//! (429) that outlast the client's retry policy are retried here with a
+//! growing backoff. Documents that can't be read or written are counted and
+//! reported without stopping the import.
+
+use std::io::{BufRead, BufReader, IsTerminal, Write};
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::time::Duration;
+
+use anyhow::{Context, Result, bail};
+use colored::Colorize;
+use cosq_client::error::ClientError;
+use cosq_core::import::{DocumentReader, ImportFormat};
+use cosq_core::snapshot::partition_key_value;
+use serde_json::Value;
+
+use super::common::{self, BoundedTasks};
+use super::docs::{connect_container, strip_system_fields};
+use crate::cli::ImportFileFormat;
+
+/// Extra attempts for a write still throttled after the client's retries
+const MAX_THROTTLE_RETRIES: u32 = 8;
+
+/// First delay before retrying a throttled write; doubled up to the maximum
+const THROTTLE_BACKOFF: Duration = Duration::from_secs(1);
+const MAX_THROTTLE_BACKOFF: Duration = Duration::from_secs(30);
+
+/// Width of the progress bar in characters
+const BAR_WIDTH: usize = 30;
+
+pub struct ImportArgs {
+    pub file: PathBuf,
+    pub db: Option<String>,
+    pub container: Option<String>,
+    pub format: Option<ImportFileFormat>,
+    pub batch_size: usize,
+    pub parallel: usize,
+    pub generate_ids: bool,
+    pub force: bool,
+    pub profile: Option<String>,
+    pub quiet: bool,
+}
+
+pub async fn run(args: ImportArgs) -> Result<()> {
+    common::ensure_online("cosq import")?;
+    let file = std::fs::File::open(&args.file)
+        .with_context(|| format!("failed to open {}", args.file.display()))?;
+    let total_bytes = file.metadata().map(|m| m.len()).unwrap_or(0);
+    let mut reader = BufReader::new(file);
+    let format = match args.format {
+        Some(format) => format.into(),
+        None => {
+            let first = reader
+                .fill_buf()?
+                .iter()
+                .copied()
+                .find(|b| !b.is_ascii_whitespace());
+            ImportFormat::detect(&args.file, first)
+        }
+    };
+
+    let (config, client, database, container) =
+        connect_container(args.profile.as_deref(), args.db, args.container).await?;
+    let paths = client
+        .get_container(&database, &container)
+        .await?
+        .partition_key
+        .paths;
+    let [pk_path] = paths.as_slice() else {
+        bail!(
+            "{database}/{container} has {} partition key paths; importing into hierarchical partition keys is not supported",
+            paths.len()
+        );
+    };
+    common::confirm_destructive(
+        &config,
+        &database,
+        &container,
+        &format!("document upserts from {}", args.file.display()),
+        None,
+        false,
+        args.force,
+    )?;
+
+    let batch_size = args.batch_size.max(1);
+    let progress = !args.quiet && std::io::stderr().is_terminal();
+    if !args.quiet {
+        eprintln!(
+            "{} {} into {}/{} ({}, batches of {batch_size})",
+            "Importing".dimmed(),
+            args.file.display(),
+            database.cyan(),
+            container.cyan(),
+            format!("{format:?}").to_lowercase()
+        );
+    }
+
+    let mut documents = DocumentReader::new(reader, format);
+    let mut tasks = BoundedTasks::new(args.parallel);
+    // Writes retried here after throttling
+    let throttled = Arc::new(AtomicU32::new(0));
+    let mut read_error = None;
+    let mut exhausted = false;
+    while !exhausted && read_error.is_none() {
+        let mut batch = Vec::with_capacity(batch_size);
+        while batch.len() < batch_size {
+            match documents.next() {
+                Some(Ok(document)) => match prepare(document, args.generate_ids) {
+                    Ok(document) => batch.push(document),
+                    Err(failure) => tasks.fail(failure),
+                },
+                Some(Err(e)) if e.is_recoverable() => tasks.fail(e.to_string()),
+                Some(Err(e)) => {
+                    read_error = Some(e);
+                    break;
+                }
+                None => {
+                    exhausted = true;
+                    break;
+                }
+            }
+        }
+
+        for document in batch {
+            let partition_key = partition_key_value(&document, pk_path);
+            let (client, database, container, throttled) = (
+                client.clone(),
+                database.clone(),
+                container.clone(),
+                throttled.clone(),
+            );
+            tasks
+                .spawn(async move {
+                    let mut retries = 0;
+                    loop {
+                        match client
+                            .upsert_document(&database, &container, &document, &partition_key)
+                            .await
+                        {
+                            Ok(_) => return Ok(()),
+                            Err(ClientError::Api { status: 429, .. })
+                                if retries < MAX_THROTTLE_RETRIES =>
+                            {
+                                tokio::time::sleep(throttle_backoff(retries)).await;
+                                retries += 1;
+                                throttled.fetch_add(1, Ordering::Relaxed);
+                            }
+                            Err(e) => return Err(format!("{}: {e}", document_id(&document))),
+                        }
+                    }
+                })
+                .await;
+        }
+        tasks.wait().await;
+        if progress {
+            draw_progress(documents.bytes_read(), total_bytes, &tasks);
+        }
+    }
+    if progress {
+        eprintln!();
+    }
+
+    let imported = tasks.succeeded().len();
+    let failed = tasks.failures().len();
+    common::report_failures(tasks.failures());
+    let throttled = throttled.load(Ordering::Relaxed);
+    if !args.quiet && throttled > 0 {
+        eprintln!(
+            "  {} {throttled} throttled writes were retried; consider more throughput or a lower --parallel",
+            "Note:".yellow().bold()
+        );
+    }
+    let summary = format!(
+        "{failed} of {} documents could not be imported into {database}/{container}, {imported} were",
+        imported + failed
+    );
+    if let Some(e) = read_error {
+        bail!(
+            "stopped reading {}: {e}; {imported} documents were imported into {database}/{container} before that",
+            args.file.display()
+        );
+    }
+    if failed > 0 {
+        bail!("{summary}");
+    }
+    if !args.quiet {
+        eprintln!(
+            "{} Imported {imported} documents into {database}/{container}",
+            "✓".green()
+        );
+    }
+    Ok(())
+}
+
+/// Strip system properties and make sure the document has a string id.
+fn prepare(document: Value, generate_ids: bool) -> std::result::Result<Value, String> {
+    let mut document = strip_system_fields(document);
+    let map = document
+        .as_object_mut()
+        .expect("import documents are objects");
+    match map.get("id").cloned() {
+        Some(Value::String(id)) if !id.is_empty() => {}
+        // Numeric ids from JSON files become strings, as Cosmos DB requires
+        Some(Value::Number(n)) => {
+            map.insert("id".to_string(), Value::String(n.to_string()));
+        }
+        None if generate_ids => {
+            map.insert(
+                "id".to_string(),
+                Value::String(uuid::Uuid::new_v4().to_string()),
+            );
+        }
+        None => return Err("document without an `id` (pass --generate-ids)".to_string()),
+        Some(other) => return Err(format!("invalid id {other}: ids must be strings")),
+    }
+    Ok(document)
+}
+
+fn document_id(document: &Value) -> &str {
+    document
+        .get("id")
+        .and_then(|id| id.as_str())
+        .unwrap_or("(no id)")
+}
+
+fn throttle_backoff(retry: u32) -> Duration {
+    THROTTLE_BACKOFF
+        .saturating_mul(1 << retry.min(16))
+        .min(MAX_THROTTLE_BACKOFF)
+}
+
+/// Redraw the progress line: a bar of the file read so far and the counts.
+fn draw_progress(bytes: u64, total: u64, tasks: &BoundedTasks<()>) {
+    let fraction = if total == 0 {
+        1.0
+    } else {
+        (bytes as f64 / total as f64).min(1.0)
+    };
+    let filled = (fraction * BAR_WIDTH as f64).round() as usize;
+    let mut line = format!(
+        "\r  [{}{}] {:>3.0}%  {} imported",
+        "#".repeat(filled),
+        "-".repeat(BAR_WIDTH - filled),
+        fraction * 100.0,
+        tasks.succeeded().len()
+    );
+    if !tasks.failures().is_empty() {
+        line.push_str(&format!(", {} failed", tasks.failures().len()));
+    }
+    let mut stderr = std::io::stderr();
+    let _ = write!(stderr, "{line}   ");
+    let _ = stderr.flush();
+}
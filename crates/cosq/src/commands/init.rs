@@ -2,7 +2,14 @@
 //!
 //! Discovers Azure subscriptions and Cosmos DB accounts, then saves
 //! the selection to a local `cosq.yaml` config file. Also ensures
-//! the user has Cosmos DB data plane access (RBAC).
+//! the user has Cosmos DB data plane access (RBAC), and optionally
+//! picks the default database and container.
+//!
+//! Every choice can also be given as a flag (`--endpoint`, `--database`,
+//! `--container`, `--no-rbac-check`, `--output json`) so provisioning
+//! scripts can run it without prompts; progress goes to stderr.
+
+use std::io::IsTerminal;
 
 use anyhow::{Context, Result, bail};
 use colored::Colorize;
@@ -11,25 +18,150 @@ use cosq_client::auth::AzCliAuth;
 use cosq_core::config::{AccountConfig, Config};
 use inquire::{Confirm, Select};
 
+use crate::cli::InitOutput;
+
 pub struct InitArgs {
     pub account: Option<String>,
     pub subscription: Option<String>,
+    /// Use this account endpoint as is, skipping ARM discovery
+    pub endpoint: Option<String>,
+    pub database: Option<String>,
+    pub container: Option<String>,
+    /// Skip the data plane (RBAC) role check
+    pub no_rbac_check: bool,
+    pub output: InitOutput,
     pub yes: bool,
 }
 
 pub async fn run(args: InitArgs) -> Result<()> {
+    super::common::ensure_online("cosq init")?;
+    let interactive = std::io::stdin().is_terminal();
+
+    let account_config = match args.endpoint {
+        Some(ref endpoint) => {
+            let name = match args.account {
+                Some(ref name) => name.clone(),
+                None => account_name_from_endpoint(endpoint).with_context(|| {
+                    format!("can't derive an account name from '{endpoint}' — pass --account")
+                })?,
+            };
+            eprintln!(
+                "{} {} ({})",
+                "Using endpoint:".bold(),
+                name.green(),
+                endpoint.dimmed()
+            );
+            if !args.no_rbac_check {
+                eprintln!(
+                    "  {} No ARM lookup with --endpoint, so the data plane role isn't checked.",
+                    "Note:".dimmed()
+                );
+            }
+            AccountConfig {
+                name,
+                subscription: args.subscription.clone().unwrap_or_default(),
+                resource_group: String::new(),
+                endpoint: endpoint.clone(),
+                master_key: None,
+                ca_cert: None,
+                capacity_mode: None,
+            }
+        }
+        None => discover_account(&args, interactive).await?,
+    };
+
+    // Step 5: Build config (keeping profiles, webhooks and storage from a previous config)
+    let previous = Config::load().ok();
+    let mut config = Config {
+        account: account_config,
+        database: args.database.clone(),
+        container: args.container.clone(),
+        profiles: previous
+            .as_ref()
+            .map(|c| c.profiles.clone())
+            .unwrap_or_default(),
+        webhooks: previous
+            .as_ref()
+            .map(|c| c.webhooks.clone())
+            .unwrap_or_default(),
+        storage_account: previous.as_ref().and_then(|c| c.storage_account.clone()),
+        retry: previous.as_ref().and_then(|c| c.retry.clone()),
+        pricing: previous.as_ref().and_then(|c| c.pricing.clone()),
+        redact: previous
+            .as_ref()
+            .map(|c| c.redact.clone())
+            .unwrap_or_default(),
+        slow_query: previous.as_ref().and_then(|c| c.slow_query.clone()),
+        ai_save: previous.as_ref().and_then(|c| c.ai_save),
+        ai_budget: previous.as_ref().and_then(|c| c.ai_budget.clone()),
+        ai_log: previous.as_ref().is_some_and(|c| c.ai_log),
+        ai_data: previous.as_ref().and_then(|c| c.ai_data.clone()),
+        ai: previous.as_ref().and_then(|c| c.ai.clone()),
+        output: previous.as_ref().and_then(|c| c.output.clone()),
+        http: previous.as_ref().and_then(|c| c.http.clone()),
+        auth: previous.as_ref().and_then(|c| c.auth),
+        containers: previous
+            .as_ref()
+            .map(|c| c.containers.clone())
+            .unwrap_or_default(),
+        trusted_commands: previous.map(|c| c.trusted_commands).unwrap_or_default(),
+    };
+
+    // Step 6: Optionally pick the default database and container right away
+    if (config.database.is_none() || config.container.is_none()) && !args.yes && interactive {
+        choose_defaults(&mut config).await?;
+    }
+
+    // Step 7: Save config
+    let config_path = config.save()?;
+
+    if args.output == InitOutput::Json {
+        println!("{}", serde_json::to_string_pretty(&config)?);
+        return Ok(());
+    }
+    println!(
+        "\n{} Saved configuration to {}",
+        "Done!".green().bold(),
+        config_path.display().to_string().cyan()
+    );
+    println!("  {} {}", "Account:".bold(), config.account.name);
+    println!(
+        "  {} {}",
+        "Endpoint:".bold(),
+        config.account.endpoint.dimmed()
+    );
+    if let Some(ref database) = config.database {
+        println!("  {} {}", "Database:".bold(), database);
+    }
+    if let Some(ref container) = config.container {
+        println!("  {} {}", "Container:".bold(), container);
+    }
+
+    Ok(())
+}
+
+/// The account name in a standard `https://<name>.documents.azure.com` endpoint.
+fn account_name_from_endpoint(endpoint: &str) -> Option<String> {
+    let host = endpoint.split("://").nth(1)?.split(['/', ':']).next()?;
+    let name = host.strip_suffix(".documents.azure.com")?;
+    (!name.is_empty() && !name.contains('.')).then(|| name.to_string())
+}
+
+/// Find the account through Azure Resource Manager: subscription, then
+/// account (prompting only where flags leave a choice), then the RBAC check.
+async fn discover_account(args: &InitArgs, interactive: bool) -> Result<AccountConfig> {
     // Step 1: Check Azure auth
     let status = AzCliAuth::check_status().await?;
     if !status.logged_in {
-        println!(
+        eprintln!(
             "{} You are not logged in to Azure CLI.",
             "!".yellow().bold()
         );
-        println!("  Run {} first.\n", "cosq auth login".cyan().bold());
+        eprintln!("  Run {} first.\n", "cosq auth login".cyan().bold());
         bail!("Azure authentication required. Run `cosq auth login` first.");
     }
 
-    println!(
+    eprintln!(
         "{} {}",
         "Logged in as:".bold(),
         status.user.as_deref().unwrap_or("unknown")
@@ -38,9 +170,9 @@ pub async fn run(args: InitArgs) -> Result<()> {
     let arm = ArmClient::new().await?;
 
     // Step 2: Select subscription
-    let subscription_id = if let Some(sub_id) = args.subscription {
-        println!("{} {}", "Using subscription:".bold(), sub_id);
-        sub_id
+    let subscription_id = if let Some(ref sub_id) = args.subscription {
+        eprintln!("{} {}", "Using subscription:".bold(), sub_id);
+        sub_id.clone()
     } else {
         let subs = arm.list_subscriptions().await?;
         if subs.is_empty() {
@@ -49,7 +181,7 @@ pub async fn run(args: InitArgs) -> Result<()> {
 
         if subs.len() == 1 {
             let sub = &subs[0];
-            println!(
+            eprintln!(
                 "{} {} ({})",
                 "Using subscription:".bold(),
                 sub.display_name.green(),
@@ -57,6 +189,12 @@ pub async fn run(args: InitArgs) -> Result<()> {
             );
             sub.subscription_id.clone()
         } else {
+            if !interactive {
+                bail!(
+                    "{} subscriptions available — pass --subscription",
+                    subs.len()
+                );
+            }
             let labels: Vec<String> = subs
                 .iter()
                 .map(|s| format!("{} ({})", s.display_name, s.subscription_id))
@@ -68,7 +206,7 @@ pub async fn run(args: InitArgs) -> Result<()> {
 
             let idx = labels.iter().position(|l| l == &selection).unwrap();
             let sub = &subs[idx];
-            println!("  {} {}", "Selected:".dimmed(), sub.display_name.green());
+            eprintln!("  {} {}", "Selected:".dimmed(), sub.display_name.green());
             sub.subscription_id.clone()
         }
     };
@@ -82,10 +220,10 @@ pub async fn run(args: InitArgs) -> Result<()> {
         );
     }
 
-    let account = if let Some(account_name) = args.account {
+    let account = if let Some(ref account_name) = args.account {
         accounts
             .into_iter()
-            .find(|a| a.name == account_name)
+            .find(|a| &a.name == account_name)
             .ok_or_else(|| {
                 anyhow::anyhow!(
                     "Cosmos DB account '{}' not found in subscription",
@@ -94,7 +232,7 @@ pub async fn run(args: InitArgs) -> Result<()> {
             })?
     } else if accounts.len() == 1 {
         let acct = &accounts[0];
-        println!(
+        eprintln!(
             "{} {} ({})",
             "Using Cosmos DB account:".bold(),
             acct.name.green(),
@@ -102,6 +240,12 @@ pub async fn run(args: InitArgs) -> Result<()> {
         );
         accounts.into_iter().next().unwrap()
     } else {
+        if !interactive {
+            bail!(
+                "{} Cosmos DB accounts in the subscription — pass --account",
+                accounts.len()
+            );
+        }
         let labels: Vec<String> = accounts
             .iter()
             .map(|a| {
@@ -120,35 +264,56 @@ pub async fn run(args: InitArgs) -> Result<()> {
 
         let idx = labels.iter().position(|l| l == &selection).unwrap();
         let acct = &accounts[idx];
-        println!("  {} {}", "Selected:".dimmed(), acct.name.green());
+        eprintln!("  {} {}", "Selected:".dimmed(), acct.name.green());
         accounts.into_iter().nth(idx).unwrap()
     };
 
     // Step 4: Ensure data plane access
-    ensure_data_plane_access(&arm, &account, args.yes).await?;
-
-    // Step 5: Save config
-    let config = Config {
-        account: AccountConfig {
-            name: account.name.clone(),
-            subscription: subscription_id,
-            resource_group: account.resource_group.clone(),
-            endpoint: account.endpoint.clone(),
-        },
-        database: None,
-        container: None,
-    };
+    if !args.no_rbac_check {
+        ensure_data_plane_access(&arm, &account, args.yes, interactive).await?;
+    }
 
-    let config_path = config.save()?;
+    Ok(AccountConfig {
+        name: account.name,
+        subscription: subscription_id,
+        resource_group: account.resource_group,
+        endpoint: account.endpoint,
+        master_key: None,
+        ca_cert: None,
+        capacity_mode: None,
+    })
+}
 
-    println!(
-        "\n{} Saved configuration to {}",
-        "Done!".green().bold(),
-        config_path.display().to_string().cyan()
-    );
-    println!("  {} {}", "Account:".bold(), account.name);
-    println!("  {} {}", "Endpoint:".bold(), account.endpoint.dimmed());
+/// Offer to list databases and containers through the data plane and store
+/// the picks as defaults, instead of asking (and saving) on the first query.
+async fn choose_defaults(config: &mut Config) -> Result<()> {
+    let pick = Confirm::new("Pick a default database and container now?")
+        .with_default(true)
+        .prompt()
+        .context("confirmation cancelled")?;
+    if !pick {
+        eprintln!(
+            "  {} The first query will ask for them.",
+            "Skipped.".yellow()
+        );
+        return Ok(());
+    }
 
+    let result = async {
+        let client = super::common::connect(config).await?;
+        let (database, _) = super::common::resolve_database(&client, config, None, None).await?;
+        super::common::resolve_container(&client, config, &database, None, None).await?;
+        anyhow::Ok(())
+    }
+    .await;
+    if let Err(e) = result {
+        // Keep whatever was picked before the failure; the rest is asked later
+        eprintln!(
+            "  {} Could not list databases/containers: {e:#}",
+            "Warning:".yellow().bold()
+        );
+        eprintln!("  RBAC changes can take a few minutes; the first query will ask instead.");
+    }
     Ok(())
 }
 
@@ -157,14 +322,15 @@ async fn ensure_data_plane_access(
     arm: &ArmClient,
     account: &cosq_client::arm::CosmosAccount,
     auto_confirm: bool,
+    interactive: bool,
 ) -> Result<()> {
-    println!("\n{}", "Checking data plane access...".dimmed());
+    eprintln!("\n{}", "Checking data plane access...".dimmed());
 
     let principal_id = AzCliAuth::get_principal_id().await?;
 
     match arm.has_cosmos_data_role(&account.id, &principal_id).await {
         Ok(true) => {
-            println!("  {} Data plane access is configured.", "OK".green().bold());
+            eprintln!("  {} Data plane access is configured.", "OK".green().bold());
             return Ok(());
         }
         Ok(false) => {
@@ -172,28 +338,33 @@ async fn ensure_data_plane_access(
         }
         Err(e) => {
             // Can't check (e.g. insufficient permissions) — warn and continue
-            println!(
+            eprintln!(
                 "  {} Could not verify data plane access: {}",
                 "Warning:".yellow().bold(),
                 e
             );
-            println!("  If queries fail, you may need to assign a Cosmos DB data plane role.");
+            eprintln!("  If queries fail, you may need to assign a Cosmos DB data plane role.");
             return Ok(());
         }
     }
 
-    println!(
-        "\n{} Your account does not have Cosmos DB {} access.",
+    // Only looked up when needed: a name is easier to check than a GUID
+    let who = super::auth::principal_label(&principal_id).await;
+    eprintln!(
+        "\n{} Your account{} does not have Cosmos DB {} access.",
         "!".yellow().bold(),
+        who.as_ref().map(|w| format!(" ({w})")).unwrap_or_default(),
         "data plane".bold()
     );
-    println!(
+    eprintln!(
         "  This is required to run queries. cosq can assign the {} role for you.",
         "Data Contributor".cyan()
     );
 
     let confirm = if auto_confirm {
         true
+    } else if !interactive {
+        false
     } else {
         Confirm::new("Grant data plane access now?")
             .with_default(true)
@@ -202,15 +373,18 @@ async fn ensure_data_plane_access(
     };
 
     if !confirm {
-        println!(
+        eprintln!(
             "\n  {} You can assign the role manually later:",
             "Skipped.".yellow()
         );
-        println!("  az cosmosdb sql role assignment create \\",);
-        println!("    --account-name {} \\", account.name);
-        println!("    --resource-group {} \\", account.resource_group);
-        println!("    --role-definition-id 00000000-0000-0000-0000-000000000002 \\");
-        println!("    --principal-id {principal_id} --scope /");
+        eprintln!("  az cosmosdb sql role assignment create \\",);
+        eprintln!("    --account-name {} \\", account.name);
+        eprintln!("    --resource-group {} \\", account.resource_group);
+        eprintln!("    --role-definition-id 00000000-0000-0000-0000-000000000002 \\");
+        eprintln!("    --principal-id {principal_id} --scope /");
+        if let Some(ref who) = who {
+            eprintln!("  {}", format!("({principal_id} is {who})").dimmed());
+        }
         return Ok(());
     }
 
@@ -218,11 +392,34 @@ async fn ensure_data_plane_access(
         .await
         .context("failed to assign data plane role")?;
 
-    println!("  {} Data plane access granted.", "OK".green().bold());
-    println!(
+    eprintln!(
+        "  {} Data plane access granted{}.",
+        "OK".green().bold(),
+        who.map(|w| format!(" to {w}")).unwrap_or_default()
+    );
+    eprintln!(
         "  {} RBAC changes may take a few seconds to propagate.",
         "Note:".dimmed()
     );
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_account_name_from_endpoint() {
+        assert_eq!(
+            account_name_from_endpoint("https://my-cosmos.documents.azure.com:443/").as_deref(),
+            Some("my-cosmos")
+        );
+        assert_eq!(
+            account_name_from_endpoint("https://my-cosmos.documents.azure.com").as_deref(),
+            Some("my-cosmos")
+        );
+        assert_eq!(account_name_from_endpoint("https://localhost:8081/"), None);
+        assert_eq!(account_name_from_endpoint("my-cosmos"), None);
+    }
+}
@@ -0,0 +1,364 @@
+//! Inventory command — an estate overview across accounts
+//!
+//! `cosq inventory` walks the configured account and every profile (or, with
+//! `--discover`, every Cosmos DB account visible through Azure Resource
+//! Manager), lists each container with its partition key, document count,
+//! storage and throughput, and prints one consolidated table or JSON report.
+//! Accounts that can't be read are reported without stopping the others.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use anyhow::{Context, Result, bail};
+use colored::Colorize;
+use comfy_table::Table;
+use comfy_table::presets::UTF8_FULL_CONDENSED;
+use cosq_client::arm::{ArmClient, account_resource_id};
+use cosq_core::config::{AccountConfig, Config};
+use cosq_core::inventory::{AccountFailure, ContainerEntry, Inventory, format_size_kb};
+use tokio::task::JoinSet;
+
+use super::account::throughput_label;
+use super::common;
+use crate::cli::ReportFormat;
+
+/// Source label of the top-level account in the config
+const DEFAULT_SOURCE: &str = "(default)";
+
+/// An account to inventory and where it came from
+struct Target {
+    source: String,
+    config: Config,
+}
+
+pub async fn run(
+    discover: bool,
+    subscriptions: Vec<String>,
+    output: ReportFormat,
+    parallel: usize,
+    quiet: bool,
+) -> Result<()> {
+    common::ensure_online("cosq inventory")?;
+    let config = Config::load()?;
+    // Throughput comes from ARM; without it the report still lists containers
+    let arm = ArmClient::new().await.ok().map(Arc::new);
+
+    let mut inventory = Inventory::default();
+    let targets = if discover {
+        let arm = arm
+            .as_deref()
+            .context("--discover needs Azure Resource Manager access (run `az login`)")?;
+        discovered_targets(&config, arm, &subscriptions, &mut inventory).await?
+    } else {
+        configured_targets(&config)
+    };
+    if targets.is_empty() {
+        bail!("no Cosmos DB accounts found");
+    }
+    if !quiet {
+        eprintln!(
+            "{}",
+            format!("Reading {} account(s)...", targets.len()).dimmed()
+        );
+    }
+
+    let parallel = parallel.max(1);
+    let mut tasks = JoinSet::new();
+    for target in targets {
+        while tasks.len() >= parallel {
+            record(tasks.join_next().await, &mut inventory);
+        }
+        tasks.spawn(inventory_account(target, arm.clone()));
+    }
+    while !tasks.is_empty() {
+        record(tasks.join_next().await, &mut inventory);
+    }
+    inventory.sort();
+
+    match output {
+        ReportFormat::Json => println!("{}", serde_json::to_string_pretty(&inventory)?),
+        ReportFormat::Table => {
+            if !inventory.containers.is_empty() {
+                println!("{}", inventory_table(&inventory));
+            }
+            let (documents, size_kb) = inventory.totals();
+            println!(
+                "{} containers in {} account(s), {documents} documents, {}",
+                inventory.containers.len(),
+                inventory.accounts(),
+                format_size_kb(size_kb)
+            );
+        }
+    }
+    for failure in &inventory.failures {
+        eprintln!(
+            "{} {} ({}): {}",
+            "Warning:".yellow().bold(),
+            failure.account,
+            failure.source,
+            failure.error
+        );
+    }
+    Ok(())
+}
+
+/// The top-level account and every profile, skipping profiles that point at
+/// an account already listed.
+fn configured_targets(config: &Config) -> Vec<Target> {
+    let mut targets = vec![Target {
+        source: DEFAULT_SOURCE.to_string(),
+        config: config.clone(),
+    }];
+    for name in config.profiles.keys() {
+        let Ok(profile) = config.for_profile(name) else {
+            continue;
+        };
+        if targets
+            .iter()
+            .any(|t| t.config.account.endpoint == profile.account.endpoint)
+        {
+            continue;
+        }
+        targets.push(Target {
+            source: name.clone(),
+            config: profile,
+        });
+    }
+    targets
+}
+
+/// Every account in the enabled subscriptions (or the ones named with
+/// `--subscription`). Subscriptions that can't be listed become failures.
+async fn discovered_targets(
+    config: &Config,
+    arm: &ArmClient,
+    only: &[String],
+    inventory: &mut Inventory,
+) -> Result<Vec<Target>> {
+    let subscriptions: Vec<_> = arm
+        .list_subscriptions()
+        .await?
+        .into_iter()
+        .filter(|s| {
+            only.is_empty()
+                || only
+                    .iter()
+                    .any(|o| *o == s.subscription_id || o.eq_ignore_ascii_case(&s.display_name))
+        })
+        .collect();
+    if subscriptions.is_empty() {
+        bail!("no matching enabled Azure subscriptions found");
+    }
+
+    let mut targets = Vec::new();
+    for subscription in subscriptions {
+        let accounts = match arm
+            .list_cosmos_accounts(&subscription.subscription_id)
+            .await
+        {
+            Ok(accounts) => accounts,
+            Err(e) => {
+                inventory.failures.push(AccountFailure {
+                    account: "(all accounts)".to_string(),
+                    source: subscription.display_name,
+                    error: e.to_string(),
+                });
+                continue;
+            }
+        };
+        for account in accounts {
+            let mut target = config.clone();
+            target.account = AccountConfig {
+                name: account.name,
+                subscription: subscription.subscription_id.clone(),
+                resource_group: account.resource_group,
+                endpoint: account.endpoint,
+                master_key: None,
+                ca_cert: None,
+                capacity_mode: None,
+            };
+            target.database = None;
+            target.container = None;
+            target.profiles.clear();
+            targets.push(Target {
+                source: subscription.display_name.clone(),
+                config: target,
+            });
+        }
+    }
+    Ok(targets)
+}
+
+fn record(
+    joined: Option<std::result::Result<Inventory, tokio::task::JoinError>>,
+    inventory: &mut Inventory,
+) {
+    match joined {
+        Some(Ok(account)) => {
+            inventory.containers.extend(account.containers);
+            inventory.failures.extend(account.failures);
+        }
+        Some(Err(e)) => inventory.failures.push(AccountFailure {
+            account: "(unknown)".to_string(),
+            source: String::new(),
+            error: e.to_string(),
+        }),
+        None => {}
+    }
+}
+
+/// Every container of one account, with the databases or containers that
+/// couldn't be read as failures.
+async fn inventory_account(target: Target, arm: Option<Arc<ArmClient>>) -> Inventory {
+    let account = target.config.account.name.clone();
+    let mut inventory = Inventory::default();
+    let fail = |inventory: &mut Inventory, error: String| {
+        inventory.failures.push(AccountFailure {
+            account: account.clone(),
+            source: target.source.clone(),
+            error,
+        })
+    };
+
+    let client = match common::connect(&target.config).await {
+        Ok(client) => client,
+        Err(e) => {
+            fail(&mut inventory, format!("{e:#}"));
+            return inventory;
+        }
+    };
+    let databases = match client.list_databases().await {
+        Ok(databases) => databases,
+        Err(e) => {
+            fail(&mut inventory, e.to_string());
+            return inventory;
+        }
+    };
+    let throughput = match arm {
+        Some(arm) => ThroughputLookup::new(arm, &target.config.account).await,
+        None => None,
+    };
+
+    for database in databases {
+        let containers = match client.list_containers(&database).await {
+            Ok(containers) => containers,
+            Err(e) => {
+                fail(&mut inventory, format!("{database}: {e}"));
+                continue;
+            }
+        };
+        for container in containers {
+            match client.get_container_usage(&database, &container).await {
+                Ok((properties, usage)) => {
+                    let label = match throughput {
+                        Some(ref lookup) => lookup.label(&database, &container).await,
+                        None => None,
+                    };
+                    inventory.containers.push(ContainerEntry {
+                        account: account.clone(),
+                        source: target.source.clone(),
+                        database: database.clone(),
+                        container,
+                        partition_key: properties.partition_key.paths,
+                        documents: Some(usage.documents_count),
+                        size_kb: Some(usage.documents_size_kb),
+                        throughput: label,
+                    });
+                }
+                Err(e) => fail(&mut inventory, format!("{database}/{container}: {e}")),
+            }
+        }
+    }
+    inventory
+}
+
+/// Throughput of an account's containers from Azure Resource Manager
+struct ThroughputLookup {
+    arm: Arc<ArmClient>,
+    id: String,
+    serverless: bool,
+    /// Throughput shared by each database, once read
+    shared: tokio::sync::Mutex<HashMap<String, Option<String>>>,
+}
+
+impl ThroughputLookup {
+    /// `None` when the account has no subscription/resource group or ARM
+    /// can't read it (e.g. the emulator).
+    async fn new(arm: Arc<ArmClient>, account: &AccountConfig) -> Option<Self> {
+        if account.subscription.is_empty() || account.resource_group.is_empty() {
+            return None;
+        }
+        let id = account_resource_id(
+            &account.subscription,
+            &account.resource_group,
+            &account.name,
+        );
+        let details = arm.get_account(&id).await.ok()?;
+        Some(Self {
+            arm,
+            id,
+            serverless: details.is_serverless(),
+            shared: Default::default(),
+        })
+    }
+
+    /// The container's own throughput, or the database's shared throughput.
+    async fn label(&self, database: &str, container: &str) -> Option<String> {
+        if self.serverless {
+            return Some("serverless".to_string());
+        }
+        match self
+            .arm
+            .get_throughput(&self.id, database, Some(container))
+            .await
+        {
+            Ok(Some(throughput)) => return Some(throughput_label(throughput)),
+            Ok(None) => {}
+            Err(_) => return None,
+        }
+        let mut shared = self.shared.lock().await;
+        if !shared.contains_key(database) {
+            let label = self
+                .arm
+                .get_throughput(&self.id, database, None)
+                .await
+                .ok()
+                .flatten()
+                .map(|t| format!("{} (shared)", throughput_label(t)));
+            shared.insert(database.to_string(), label);
+        }
+        shared[database].clone()
+    }
+}
+
+fn inventory_table(inventory: &Inventory) -> Table {
+    let mut table = Table::new();
+    table.load_preset(UTF8_FULL_CONDENSED);
+    table.set_header(vec![
+        "Account",
+        "Database",
+        "Container",
+        "Partition key",
+        "Documents",
+        "Size",
+        "Throughput",
+    ]);
+    for entry in &inventory.containers {
+        table.add_row(vec![
+            entry.account.clone(),
+            entry.database.clone(),
+            entry.container.clone(),
+            entry.partition_key.join(", "),
+            entry
+                .documents
+                .map(|d| d.to_string())
+                .unwrap_or_else(|| "-".to_string()),
+            entry
+                .size_kb
+                .map(format_size_kb)
+                .unwrap_or_else(|| "-".to_string()),
+            entry.throughput.clone().unwrap_or_else(|| "-".to_string()),
+        ]);
+    }
+    table
+}
@@ -1,11 +1,36 @@
 //! CLI command implementations
 
+pub mod account;
 pub mod ai;
 pub mod auth;
+pub mod batch;
 pub mod common;
 pub mod completion;
+pub mod container;
+pub mod debug;
+pub mod dedupe;
+pub mod docs;
+pub mod doctor;
+pub mod emulator;
+pub mod federated;
+pub mod guided;
+pub mod hooks;
+pub mod import;
 pub mod init;
+pub mod inventory;
+pub mod notify;
 pub mod pipeline;
+pub mod plugin;
+pub mod prefs;
 pub mod queries;
 pub mod query;
+pub mod render;
 pub mod run;
+pub mod schedule;
+pub mod schema;
+pub mod secrets;
+pub mod session;
+pub mod snapshot;
+pub mod stats;
+pub mod stream;
+pub mod templates;
@@ -0,0 +1,190 @@
+//! Result notifications — post run output to Slack/Teams incoming webhooks
+
+use anyhow::{Context, Result, bail};
+use cosq_core::config::{Config, WebhookConfig, WebhookKind};
+use cosq_core::secrets;
+use serde_json::{Value, json};
+
+/// Slack and Teams truncate long messages; keep well under their limits.
+const MAX_MESSAGE_CHARS: usize = 3500;
+
+/// Resolve `--notify` / `notify:` to a webhook: a URL, or a name from `webhooks:` in config
+/// (whose URL may be a `keyring:` reference).
+pub fn resolve_webhook(target: &str, config: &Config) -> Result<WebhookConfig> {
+    if target.starts_with("https://") || target.starts_with("http://") {
+        return Ok(WebhookConfig {
+            url: target.to_string(),
+            kind: None,
+        });
+    }
+    match config.webhooks.get(target) {
+        Some(webhook) => Ok(WebhookConfig {
+            url: secrets::resolve(&webhook.url)
+                .with_context(|| format!("failed to load URL for webhook '{target}'"))?,
+            kind: webhook.kind,
+        }),
+        None => {
+            let names: Vec<&str> = config.webhooks.keys().map(String::as_str).collect();
+            if names.is_empty() {
+                bail!(
+                    "Webhook '{target}' not found. Pass a URL or add it under `webhooks:` in the config."
+                )
+            }
+            bail!(
+                "Webhook '{target}' not found (configured: {})",
+                names.join(", ")
+            )
+        }
+    }
+}
+
+/// One-line summary used when the query has no template.
+pub fn summary(query_name: &str, doc_count: usize, request_charge: f64) -> String {
+    format!("cosq: `{query_name}` returned {doc_count} document(s) ({request_charge:.2} RUs)")
+}
+
+/// Build the webhook payload for the given kind. Output that is already a
+/// Slack Block Kit message or Teams Adaptive Card (e.g. from `builtin:slack`
+/// or `builtin:teams-card`) is posted as is; anything else is sent as text.
+fn payload(kind: WebhookKind, message: &str) -> Value {
+    if let Some(structured) = structured_payload(kind, message) {
+        return structured;
+    }
+    let text = truncate(message.trim_end());
+    match kind {
+        WebhookKind::Slack => json!({ "text": text }),
+        WebhookKind::Teams => json!({
+            "type": "message",
+            "attachments": [{
+                "contentType": "application/vnd.microsoft.card.adaptive",
+                "content": {
+                    "$schema": "http://adaptivecards.io/schemas/adaptive-card.json",
+                    "type": "AdaptiveCard",
+                    "version": "1.4",
+                    "body": [{ "type": "TextBlock", "text": text, "wrap": true }]
+                }
+            }]
+        }),
+    }
+}
+
+/// The message as a ready-made payload, when it is JSON of the webhook's shape.
+fn structured_payload(kind: WebhookKind, message: &str) -> Option<Value> {
+    let value: Value = serde_json::from_str(message.trim()).ok()?;
+    match (kind, value.get("type").and_then(Value::as_str)) {
+        (WebhookKind::Slack, _) if value.get("blocks").is_some_and(Value::is_array) => Some(value),
+        (WebhookKind::Teams, Some("message")) if value.get("attachments").is_some() => Some(value),
+        (WebhookKind::Teams, Some("AdaptiveCard")) => Some(json!({
+            "type": "message",
+            "attachments": [{
+                "contentType": "application/vnd.microsoft.card.adaptive",
+                "content": value
+            }]
+        })),
+        _ => None,
+    }
+}
+
+fn truncate(message: &str) -> String {
+    if message.chars().count() <= MAX_MESSAGE_CHARS {
+        message.to_string()
+    } else {
+        let truncated: String = message.chars().take(MAX_MESSAGE_CHARS).collect();
+        format!("{truncated}\n…(truncated)")
+    }
+}
+
+/// Post a message to the webhook.
+pub async fn send(webhook: &WebhookConfig, message: &str) -> Result<()> {
+    super::common::ensure_online("Sending notifications")?;
+    let response = cosq_client::http::client()
+        .post(&webhook.url)
+        .json(&payload(webhook.kind(), message))
+        .send()
+        .await?;
+    let status = response.status();
+    if !status.is_success() {
+        let body = response.text().await.unwrap_or_default();
+        bail!("Webhook returned {status}: {body}");
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config_with_webhook() -> Config {
+        serde_yaml::from_str(
+            r#"
+account:
+  name: a
+  subscription: s
+  resource_group: rg
+  endpoint: https://a.documents.azure.com:443/
+webhooks:
+  ops:
+    url: https://hooks.slack.com/services/T0/B0/x
+"#,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_resolve_webhook() {
+        let config = config_with_webhook();
+        let by_name = resolve_webhook("ops", &config).unwrap();
+        assert_eq!(by_name.kind(), WebhookKind::Slack);
+
+        let by_url = resolve_webhook("https://x.webhook.office.com/abc", &config).unwrap();
+        assert_eq!(by_url.kind(), WebhookKind::Teams);
+
+        let err = resolve_webhook("missing", &config).unwrap_err().to_string();
+        assert!(err.contains("ops"));
+    }
+
+    #[test]
+    fn test_payload_shapes() {
+        let slack = payload(WebhookKind::Slack, "hello\n");
+        assert_eq!(slack["text"], "hello");
+
+        let teams = payload(WebhookKind::Teams, "hello");
+        assert_eq!(
+            teams["attachments"][0]["content"]["body"][0]["text"],
+            "hello"
+        );
+    }
+
+    #[test]
+    fn test_structured_payloads() {
+        let blocks = r#"{"text": "t", "blocks": [{"type": "divider"}]}"#;
+        assert_eq!(
+            payload(WebhookKind::Slack, blocks)["blocks"][0]["type"],
+            "divider"
+        );
+
+        let card = r#"{"type": "AdaptiveCard", "version": "1.4", "body": []}"#;
+        let teams = payload(WebhookKind::Teams, card);
+        assert_eq!(teams["type"], "message");
+        assert_eq!(teams["attachments"][0]["content"]["version"], "1.4");
+
+        // A card sent to Slack (or plain JSON output) is still posted as text
+        assert_eq!(payload(WebhookKind::Slack, card)["text"], card);
+        assert_eq!(payload(WebhookKind::Teams, "[1, 2]")["type"], "message");
+    }
+
+    #[test]
+    fn test_truncate_long_message() {
+        let long = "x".repeat(MAX_MESSAGE_CHARS + 10);
+        assert!(truncate(&long).ends_with("…(truncated)"));
+        assert_eq!(truncate("short"), "short");
+    }
+
+    #[test]
+    fn test_summary() {
+        assert_eq!(
+            summary("orders", 3, 2.5),
+            "cosq: `orders` returned 3 document(s) (2.50 RUs)"
+        );
+    }
+}
@@ -6,6 +6,7 @@
 //! 3. Resolving step references by injecting actual values as parameters
 
 use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
 
 use anyhow::{Context, Result, bail};
 use colored::Colorize;
@@ -19,18 +20,22 @@ pub struct PipelineResult {
     pub step_results: BTreeMap<String, Vec<Value>>,
     /// Total request charge across all steps
     pub total_charge: f64,
+    /// Ctrl+C stopped at least one step before all its pages were fetched
+    pub partial: bool,
 }
 
 /// Execute a multi-step stored query.
 ///
 /// Steps are executed in dependency order — steps that only reference `@param`
 /// parameters run in parallel, while steps referencing `@step.field` wait for
-/// that step to complete first.
+/// that step to complete first. Steps in `preloaded` (see [`load_steps`]) are
+/// not run; their documents are used as they are.
 pub async fn execute(
     client: &CosmosClient,
     database: &str,
     query: &StoredQuery,
     params: &BTreeMap<String, Value>,
+    preloaded: BTreeMap<String, Vec<Value>>,
     quiet: bool,
 ) -> Result<PipelineResult> {
     let steps = query
@@ -43,10 +48,19 @@ pub async fn execute(
         .execution_order()
         .map_err(|e| anyhow::anyhow!("{e}"))?;
 
-    let mut step_results: BTreeMap<String, Vec<Value>> = BTreeMap::new();
+    let mut step_results = preloaded;
     let mut total_charge = 0.0;
+    let mut partial = false;
 
     for layer in &layers {
+        let layer: Vec<String> = layer
+            .iter()
+            .filter(|name| !step_results.contains_key(*name))
+            .cloned()
+            .collect();
+        if layer.is_empty() {
+            continue;
+        }
         if layer.len() == 1 {
             // Single step in this layer — execute directly
             let step_name = &layer[0];
@@ -69,12 +83,13 @@ pub async fn execute(
                 .with_context(|| format!("step '{step_name}' failed"))?;
 
             total_charge += result.request_charge;
+            partial |= result.partial;
             step_results.insert(step_name.clone(), result.documents);
         } else {
             // Multiple steps in this layer — execute in parallel
             let mut handles = Vec::new();
 
-            for step_name in layer {
+            for step_name in &layer {
                 let step_def = steps.iter().find(|s| s.name == *step_name).unwrap();
                 let sql = query.step_queries[step_name].clone();
 
@@ -106,6 +121,7 @@ pub async fn execute(
                 let (name, result) = handle.await.context("step task panicked")?;
                 let result = result.with_context(|| format!("step '{name}' failed"))?;
                 total_charge += result.request_charge;
+                partial |= result.partial;
                 step_results.insert(name, result.documents);
             }
         }
@@ -114,9 +130,183 @@ pub async fn execute(
     Ok(PipelineResult {
         step_results,
         total_charge,
+        partial,
     })
 }
 
+/// Load the results of every step that runs before `from_step` from
+/// `<dir>/<step>.json` (as written by [`save_steps`]), so execution resumes
+/// at `from_step`.
+pub fn load_steps(
+    query: &StoredQuery,
+    from_step: &str,
+    dir: &Path,
+) -> Result<BTreeMap<String, Vec<Value>>> {
+    let order: Vec<String> = query
+        .execution_order()
+        .map_err(|e| anyhow::anyhow!("{e}"))?
+        .into_iter()
+        .flatten()
+        .collect();
+    let Some(position) = order.iter().position(|s| s == from_step) else {
+        bail!(
+            "query '{}' has no step '{from_step}' (steps: {})",
+            query.name,
+            order.join(", ")
+        );
+    };
+    let mut loaded = BTreeMap::new();
+    for step in &order[..position] {
+        let path = dir.join(format!("{step}.json"));
+        if !path.is_file() {
+            bail!(
+                "no saved results for step '{step}' ({} not found) — run with --save-steps {} first",
+                path.display(),
+                dir.display()
+            );
+        }
+        let documents = super::common::load_documents(&path)?;
+        loaded.insert(step.clone(), documents);
+    }
+    Ok(loaded)
+}
+
+/// Write each step's documents to `<dir>/<step>.json`, creating `dir` if
+/// needed. Returns the files written.
+pub fn save_steps(dir: &Path, step_results: &BTreeMap<String, Vec<Value>>) -> Result<Vec<PathBuf>> {
+    std::fs::create_dir_all(dir).with_context(|| format!("failed to create {}", dir.display()))?;
+    let mut written = Vec::new();
+    for (step, documents) in step_results {
+        let path = dir.join(format!("{step}.json"));
+        std::fs::write(&path, serde_json::to_string_pretty(documents)? + "\n")
+            .with_context(|| format!("failed to write {}", path.display()))?;
+        written.push(path);
+    }
+    Ok(written)
+}
+
+/// Describe how a query would run, without sending any requests: each step in
+/// execution order with its container, its SQL with parameter values inlined,
+/// and the `@step.field` references left to resolve at run time. A
+/// single-step query is shown as one step against `container`.
+pub fn render_plan(
+    query: &StoredQuery,
+    params: &BTreeMap<String, Value>,
+    database: Option<&str>,
+    container: Option<&str>,
+) -> Result<String> {
+    let nodes = query.step_graph().map_err(|e| anyhow::anyhow!("{e}"))?;
+    let layers = nodes.iter().map(|n| n.layer).max().map_or(1, |l| l + 1);
+    let step_names: Vec<String> = nodes.iter().map(|n| n.name.clone()).collect();
+    let steps = nodes.len().max(1);
+
+    let mut out = format!(
+        "{} {} ({} step{}, database {})\n",
+        "Plan for".bold(),
+        query.name.cyan(),
+        steps,
+        if steps == 1 { "" } else { "s" },
+        database.map_or_else(
+            || "chosen at run time".dimmed().to_string(),
+            |db| db.cyan().to_string()
+        )
+    );
+    if nodes.is_empty() {
+        out.push_str(&format!(
+            "\n  {} {}\n",
+            "▸".dimmed(),
+            container.map_or_else(
+                || "(container chosen at run time)".dimmed().to_string(),
+                |c| c.to_string()
+            )
+        ));
+        push_step_sql(&mut out, &query.sql, params, &step_names);
+    }
+    for layer in 0..if nodes.is_empty() { 0 } else { layers } {
+        let in_layer: Vec<_> = nodes.iter().filter(|n| n.layer == layer).collect();
+        let parallel = if in_layer.len() > 1 {
+            format!(" — {} steps in parallel", in_layer.len())
+        } else {
+            String::new()
+        };
+        out.push_str(&format!(
+            "\n{}{}\n",
+            format!("Layer {}", layer + 1).bold(),
+            parallel.dimmed()
+        ));
+        for node in in_layer {
+            out.push_str(&format!(
+                "  {} {} ({})\n",
+                "▸".dimmed(),
+                node.name.cyan(),
+                node.container.dimmed()
+            ));
+            let sql = query
+                .step_queries
+                .get(&node.name)
+                .map_or("", String::as_str);
+            push_step_sql(&mut out, sql, params, &step_names);
+        }
+    }
+    Ok(out)
+}
+
+/// Append a step's SQL with bound parameters inlined, followed by its bindings.
+fn push_step_sql(
+    out: &mut String,
+    sql: &str,
+    params: &BTreeMap<String, Value>,
+    step_names: &[String],
+) {
+    let (bound, bindings) = bind_params(sql, params, step_names);
+    for line in bound.trim().lines() {
+        out.push_str(&format!("      {line}\n"));
+    }
+    for binding in bindings {
+        out.push_str(&format!("      {}\n", binding.dimmed()));
+    }
+}
+
+/// Inline resolved `@param` values into `sql` as JSON literals. Step
+/// references (`@step.field`) and parameters without a value stay as they
+/// are. Returns the SQL and one line per distinct parameter describing where
+/// its value comes from.
+fn bind_params(
+    sql: &str,
+    params: &BTreeMap<String, Value>,
+    step_names: &[String],
+) -> (String, Vec<String>) {
+    let re = regex::Regex::new(r"@(\w+)(\.(\w+))?").unwrap();
+    let mut bindings = Vec::new();
+    let mut note = |line: String| {
+        if !bindings.contains(&line) {
+            bindings.push(line);
+        }
+    };
+    let bound = re.replace_all(sql, |cap: &regex::Captures| {
+        let name = &cap[1];
+        if let Some(field) = cap.get(3).filter(|_| step_names.iter().any(|s| s == name)) {
+            let field = field.as_str();
+            note(format!(
+                "@{name}.{field} ← {field} of the first result of step '{name}' (at run time)"
+            ));
+            return cap[0].to_string();
+        }
+        let rest = cap.get(2).map_or("", |m| m.as_str());
+        match params.get(name) {
+            Some(value) => {
+                note(format!("@{name} = {value}"));
+                format!("{value}{rest}")
+            }
+            None => {
+                note(format!("@{name} is not set"));
+                cap[0].to_string()
+            }
+        }
+    });
+    (bound.into_owned(), bindings)
+}
+
 /// Build Cosmos DB parameters for a step, resolving both regular @params
 /// and @step.field references from previously completed steps.
 fn build_step_params(
@@ -294,4 +484,130 @@ SELECT * FROM c WHERE c.customerId = @customer.id
         assert!(result.is_err());
         assert!(result.unwrap_err().to_string().contains("no results"));
     }
+
+    #[test]
+    fn test_save_steps() {
+        let dir = tempfile::tempdir().unwrap();
+        let out = dir.path().join("out");
+        let mut step_results = BTreeMap::new();
+        step_results.insert("customer".to_string(), vec![json!({"id": "c1"})]);
+        step_results.insert("orders".to_string(), Vec::new());
+
+        let written = save_steps(&out, &step_results).unwrap();
+        assert_eq!(
+            written,
+            vec![out.join("customer.json"), out.join("orders.json")]
+        );
+        let customer: Vec<Value> =
+            serde_json::from_str(&std::fs::read_to_string(&written[0]).unwrap()).unwrap();
+        assert_eq!(customer, vec![json!({"id": "c1"})]);
+        assert_eq!(std::fs::read_to_string(&written[1]).unwrap(), "[]\n");
+    }
+
+    #[test]
+    fn test_load_steps() {
+        let query = StoredQuery::parse(
+            "test",
+            r#"---
+description: test
+steps:
+  - name: customer
+    container: customers
+  - name: orders
+    container: orders
+  - name: items
+    container: items
+---
+-- step: customer
+SELECT TOP 1 * FROM c
+
+-- step: orders
+SELECT * FROM c WHERE c.customerId = @customer.id
+
+-- step: items
+SELECT * FROM c WHERE c.orderId = @orders.id
+"#,
+        )
+        .unwrap();
+        let dir = tempfile::tempdir().unwrap();
+        let mut saved = BTreeMap::new();
+        saved.insert("customer".to_string(), vec![json!({"id": "c1"})]);
+        save_steps(dir.path(), &saved).unwrap();
+
+        // Resuming at the first step loads nothing
+        assert!(
+            load_steps(&query, "customer", dir.path())
+                .unwrap()
+                .is_empty()
+        );
+        // Resuming at `orders` loads `customer`
+        let loaded = load_steps(&query, "orders", dir.path()).unwrap();
+        assert_eq!(loaded, saved);
+        // `items` also needs `orders.json`
+        let err = load_steps(&query, "items", dir.path()).unwrap_err();
+        assert!(err.to_string().contains("step 'orders'"));
+        assert!(load_steps(&query, "nope", dir.path()).is_err());
+    }
+
+    #[test]
+    fn test_render_plan() {
+        let contents = r#"---
+description: test
+params:
+  - name: name
+    type: string
+  - name: limit
+    type: number
+    required: false
+steps:
+  - name: customer
+    container: customers
+  - name: orders
+    container: orders
+  - name: tickets
+    container: tickets
+---
+-- step: customer
+SELECT TOP 1 * FROM c WHERE c.name = @name
+
+-- step: orders
+SELECT TOP @limit * FROM c WHERE c.customerId = @customer.id
+
+-- step: tickets
+SELECT * FROM c WHERE c.customerId = @customer.id AND c.name = @name
+"#;
+        colored::control::set_override(false);
+        let query = StoredQuery::parse("test", contents).unwrap();
+        let mut params = BTreeMap::new();
+        params.insert("name".to_string(), json!("Alice"));
+
+        let plan = render_plan(&query, &params, Some("shop"), None).unwrap();
+        assert!(plan.starts_with("Plan for test (3 steps, database shop)"));
+        assert!(plan.contains("SELECT TOP 1 * FROM c WHERE c.name = \"Alice\""));
+        assert!(plan.contains("@name = \"Alice\""));
+        assert!(plan.contains("Layer 2 — 2 steps in parallel"));
+        assert!(plan.contains("▸ orders (orders)"));
+        assert!(plan.contains("SELECT TOP @limit * FROM c WHERE c.customerId = @customer.id"));
+        assert!(plan.contains("@limit is not set"));
+        assert!(plan.contains("@customer.id ← id of the first result of step 'customer'"));
+        // Steps run in dependency order
+        assert!(plan.find("▸ customer").unwrap() < plan.find("▸ orders").unwrap());
+    }
+
+    #[test]
+    fn test_render_plan_single_step() {
+        colored::control::set_override(false);
+        let query = StoredQuery::parse(
+            "single",
+            "---\ndescription: test\nparams:\n  - name: id\n    type: number\n---\nSELECT * FROM c WHERE c.n = @id OR c.m = @idx\n",
+        )
+        .unwrap();
+        let mut params = BTreeMap::new();
+        params.insert("id".to_string(), json!(7));
+
+        let plan = render_plan(&query, &params, None, Some("users")).unwrap();
+        assert!(plan.contains("(1 step, database chosen at run time)"));
+        assert!(plan.contains("▸ users"));
+        assert!(plan.contains("WHERE c.n = 7 OR c.m = @idx"));
+    }
 }
@@ -0,0 +1,147 @@
+//! External subcommand plugins
+//!
+//! Like git, an unknown subcommand `cosq foo args...` runs `cosq-foo args...`
+//! from `PATH`. The plugin inherits stdio and receives the connection context
+//! through environment variables, so it can talk to the same account without
+//! re-implementing config loading or auth:
+//!
+//! - `COSQ_BIN` — path of the running cosq binary
+//! - `COSQ_CONFIG` — path of the config file
+//! - `COSQ_ACCOUNT`, `COSQ_ENDPOINT` — the selected account (honours `--profile`)
+//! - `COSQ_DATABASE`, `COSQ_CONTAINER` — configured defaults, when set
+//! - `COSQ_TOKEN` — an AAD bearer token for Cosmos DB, or `COSQ_MASTER_KEY`
+//!   when the account uses key auth
+//!
+//! Context that can't be resolved (no config yet, not logged in) is left
+//! unset rather than failing the plugin.
+
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result, bail};
+use cosq_client::auth::{self, COSMOS_RESOURCE};
+use cosq_core::config::Config;
+use cosq_core::secrets;
+
+/// Executable name prefix for plugins
+const PREFIX: &str = "cosq-";
+
+/// Run the plugin for `args[0]`, exiting with its status code.
+pub async fn run(args: Vec<OsString>, profile: Option<String>) -> Result<()> {
+    let Some((name, rest)) = args.split_first() else {
+        bail!("missing subcommand");
+    };
+    let name = name.to_string_lossy();
+    let path_var = std::env::var_os("PATH").unwrap_or_default();
+    let Some(plugin) = find_plugin(std::env::split_paths(&path_var), &name) else {
+        bail!(
+            "unrecognized subcommand '{name}' (no `{PREFIX}{name}` plugin found on PATH)\n\n\
+             For more information, try '--help'."
+        );
+    };
+
+    let mut command = std::process::Command::new(&plugin);
+    command.args(rest);
+    for (key, value) in context_env(profile.as_deref()).await {
+        command.env(key, value);
+    }
+    let status = command
+        .status()
+        .with_context(|| format!("failed to run plugin {}", plugin.display()))?;
+    // Mirror the plugin's exit status (signals map to 1)
+    std::process::exit(status.code().unwrap_or(1));
+}
+
+/// First `cosq-<name>` executable in `dirs`.
+fn find_plugin(dirs: impl IntoIterator<Item = PathBuf>, name: &str) -> Option<PathBuf> {
+    if name.is_empty() || name.contains(['/', '\\']) {
+        return None;
+    }
+    let file_name = format!("{PREFIX}{name}{}", std::env::consts::EXE_SUFFIX);
+    dirs.into_iter()
+        .map(|dir| dir.join(&file_name))
+        .find(|candidate| is_executable(candidate))
+}
+
+#[cfg(unix)]
+fn is_executable(path: &Path) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+    path.metadata()
+        .is_ok_and(|m| m.is_file() && m.permissions().mode() & 0o111 != 0)
+}
+
+#[cfg(not(unix))]
+fn is_executable(path: &Path) -> bool {
+    path.is_file()
+}
+
+/// Environment variables describing the current connection context.
+async fn context_env(profile: Option<&str>) -> Vec<(&'static str, String)> {
+    let mut env = Vec::new();
+    if let Ok(exe) = std::env::current_exe() {
+        env.push(("COSQ_BIN", exe.display().to_string()));
+    }
+    if let Ok(path) = Config::path() {
+        env.push(("COSQ_CONFIG", path.display().to_string()));
+    }
+
+    let config = match Config::load() {
+        Ok(config) => match profile {
+            Some(profile) => config.for_profile(profile),
+            None => Ok(config),
+        },
+        Err(e) => Err(e),
+    };
+    let config = match config {
+        Ok(config) => config,
+        Err(e) => {
+            tracing::debug!(error = %e, "no config for plugin context");
+            return env;
+        }
+    };
+
+    env.push(("COSQ_ACCOUNT", config.account.name.clone()));
+    env.push(("COSQ_ENDPOINT", config.account.endpoint.clone()));
+    if let Some(ref db) = config.database {
+        env.push(("COSQ_DATABASE", db.clone()));
+    }
+    if let Some(ref ctr) = config.container {
+        env.push(("COSQ_CONTAINER", ctr.clone()));
+    }
+    match config.account.master_key {
+        Some(ref key) => match secrets::resolve(key) {
+            Ok(key) => env.push(("COSQ_MASTER_KEY", key)),
+            Err(e) => tracing::debug!(error = %e, "master key unavailable for plugin"),
+        },
+        None => match auth::get_token(COSMOS_RESOURCE).await {
+            Ok(token) => env.push(("COSQ_TOKEN", token)),
+            Err(e) => tracing::debug!(error = %e, "no Cosmos DB token for plugin"),
+        },
+    }
+    env
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_find_plugin() {
+        let empty = tempfile::tempdir().unwrap();
+        let bin = tempfile::tempdir().unwrap();
+        let plugin = bin
+            .path()
+            .join(format!("cosq-hello{}", std::env::consts::EXE_SUFFIX));
+        std::fs::write(&plugin, "#!/bin/sh\n").unwrap();
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            std::fs::set_permissions(&plugin, std::fs::Permissions::from_mode(0o755)).unwrap();
+        }
+
+        let dirs = || vec![empty.path().to_path_buf(), bin.path().to_path_buf()];
+        assert_eq!(find_plugin(dirs(), "hello"), Some(plugin));
+        assert_eq!(find_plugin(dirs(), "missing"), None);
+        assert_eq!(find_plugin(dirs(), "../hello"), None);
+    }
+}
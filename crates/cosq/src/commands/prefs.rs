@@ -0,0 +1,136 @@
+//! Prefs command — show and change per-container view preferences
+//!
+//! `query` and `run` shape table and CSV output with a container's preferred
+//! columns and sort, fall back to its preferred output format, and record the
+//! queries run against it (see `common::ContainerView`).
+
+use anyhow::{Context, Result, bail};
+use clap::ValueEnum;
+use colored::Colorize;
+use cosq_core::config::Config;
+use cosq_core::prefs::{ContainerPrefs, parse_sort};
+
+use crate::cli::PrefsCommands;
+
+pub fn run(cmd: PrefsCommands, profile: Option<&str>) -> Result<()> {
+    let config = match profile {
+        Some(profile) => Config::load()?.for_profile(profile)?,
+        None => Config::load()?,
+    };
+    match cmd {
+        PrefsCommands::Show { container, db } => {
+            let (database, container) = target(&config, db, container)?;
+            show(&config, &database, &container)
+        }
+        PrefsCommands::Set {
+            container,
+            db,
+            columns,
+            sort,
+            output,
+        } => {
+            let (database, container) = target(&config, db, container)?;
+            if columns.is_none() && sort.is_none() && output.is_none() {
+                bail!("nothing to set (pass --columns, --sort or --output)");
+            }
+            if let Some(ref sort) = sort
+                && parse_sort(sort).is_none()
+            {
+                bail!(
+                    "invalid sort '{sort}' (expected a field, optionally followed by asc or desc)"
+                );
+            }
+            let account = &config.account.name;
+            let mut prefs = ContainerPrefs::load(account, &database, &container)?;
+            if let Some(columns) = columns {
+                prefs.columns = columns
+                    .into_iter()
+                    .map(|c| c.trim().to_string())
+                    .filter(|c| !c.is_empty())
+                    .collect();
+            }
+            if sort.is_some() {
+                prefs.sort = sort;
+            }
+            if let Some(output) = output {
+                prefs.output = output.to_possible_value().map(|v| v.get_name().to_string());
+            }
+            let path = prefs.save(account, &database, &container)?;
+            println!(
+                "{} preferences for {database}/{container} ({})",
+                "Saved".green().bold(),
+                path.display()
+            );
+            Ok(())
+        }
+        PrefsCommands::Clear { container, db } => {
+            let (database, container) = target(&config, db, container)?;
+            let path = ContainerPrefs::path(&config.account.name, &database, &container)?;
+            match std::fs::remove_file(&path) {
+                Ok(()) => {
+                    println!(
+                        "{} preferences for {database}/{container}",
+                        "Cleared".green().bold()
+                    );
+                    Ok(())
+                }
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                    println!("No preferences saved for {database}/{container}");
+                    Ok(())
+                }
+                Err(e) => Err(e).with_context(|| format!("failed to remove {}", path.display())),
+            }
+        }
+    }
+}
+
+/// The database and container to use: flags, then the configured defaults.
+fn target(
+    config: &Config,
+    db: Option<String>,
+    container: Option<String>,
+) -> Result<(String, String)> {
+    let database = db
+        .or_else(|| config.database.clone())
+        .context("No database configured; pass --db")?;
+    let container = container
+        .or_else(|| config.container.clone())
+        .context("No container configured; pass a container name")?;
+    Ok((database, container))
+}
+
+fn show(config: &Config, database: &str, container: &str) -> Result<()> {
+    let prefs = ContainerPrefs::load(&config.account.name, database, container)?;
+    if prefs == ContainerPrefs::default() {
+        println!("No preferences saved for {database}/{container}");
+        return Ok(());
+    }
+    println!("{}", format!("{database}/{container}").bold());
+    let unset = || "(not set)".dimmed().to_string();
+    println!(
+        "  {} {}",
+        "Columns:".dimmed(),
+        if prefs.columns.is_empty() {
+            unset()
+        } else {
+            prefs.columns.join(", ")
+        }
+    );
+    println!(
+        "  {} {}",
+        "Sort:".dimmed(),
+        prefs.sort.clone().unwrap_or_else(unset)
+    );
+    println!(
+        "  {} {}",
+        "Output:".dimmed(),
+        prefs.output.clone().unwrap_or_else(unset)
+    );
+    if !prefs.recent_queries.is_empty() {
+        println!("  {}", "Recent queries:".dimmed());
+        for query in &prefs.recent_queries {
+            println!("    {}", query.replace('\n', " ").cyan());
+        }
+    }
+    Ok(())
+}
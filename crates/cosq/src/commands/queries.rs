@@ -1,31 +1,73 @@
-//! Queries management commands — list, create, edit, delete, show, generate
+//! Queries management commands — list, search, create, edit, delete, rename, copy,
+//! history, revert, show, lint, generate, encrypt, share, install
 //!
 //! Manages stored .cosq query files in `~/.cosq/queries/` (user-level)
 //! and `.cosq/queries/` (project-level).
 
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
 use anyhow::{Context, Result, bail};
 use colored::Colorize;
-use cosq_core::config::Config;
+use cosq_core::config::{AiDataConfig, Config};
+use cosq_core::encryption::EncryptionKey;
+use cosq_core::query_history;
+use cosq_core::schema::Schema;
 use cosq_core::stored_query::{
-    StoredQuery, StoredQueryMetadata, find_stored_query, list_stored_queries, query_file_path,
-    user_queries_dir,
+    ParamType, StepNode, StoredQuery, StoredQueryMetadata, find_stored_query, include_refers_to,
+    list_stored_queries, load_aliases_file, project_queries_dir, query_file_path, retarget_aliases,
+    save_aliases_file,
 };
 
-use crate::cli::QueriesCommands;
+use crate::builtin_templates::BUILTIN_PREFIX;
+use crate::cli::{GraphFormat, QueriesCommands};
 
-pub async fn run(cmd: QueriesCommands, quiet: bool) -> Result<()> {
+pub async fn run(cmd: QueriesCommands, profile: Option<String>, quiet: bool) -> Result<()> {
     match cmd {
         QueriesCommands::List => list(),
+        QueriesCommands::Search { text } => search(&text),
         QueriesCommands::Create { name, project } => create(&name, project),
         QueriesCommands::Edit { name } => edit(&name),
         QueriesCommands::Delete { name, yes } => delete(&name, yes),
+        QueriesCommands::Rename {
+            name,
+            new_name,
+            project,
+            user,
+        } => rename(&name, &new_name, scope(project, user)),
+        QueriesCommands::Copy {
+            name,
+            new_name,
+            project,
+            user,
+        } => copy(&name, &new_name, scope(project, user)),
+        QueriesCommands::History { name } => history(&name),
+        QueriesCommands::Revert { name, to } => revert(&name, to),
         QueriesCommands::Show { name } => show(&name),
+        QueriesCommands::Graph { name, format } => graph(&name, format),
+        QueriesCommands::Lint { name, connect } => lint(name, connect).await,
         QueriesCommands::Generate {
             description,
             db,
             container,
             project,
         } => generate(description, db, container, project, quiet).await,
+        QueriesCommands::Encrypt {
+            value,
+            param_type,
+            export_key,
+        } => encrypt(value, param_type.into(), export_key, quiet),
+        QueriesCommands::Share {
+            name,
+            results,
+            file,
+        } => share(&name, results, file, profile, quiet).await,
+        QueriesCommands::Install {
+            source,
+            name,
+            project,
+            yes,
+        } => install(&source, name, project, yes, quiet).await,
     }
 }
 
@@ -68,6 +110,47 @@ fn list() -> Result<()> {
     Ok(())
 }
 
+fn search(text: &str) -> Result<()> {
+    let queries = list_stored_queries().unwrap_or_default();
+    let found: Vec<_> = queries
+        .iter()
+        .map(|q| (q, q.search(text)))
+        .filter(|(_, matches)| !matches.is_empty())
+        .collect();
+
+    if found.is_empty() {
+        println!(
+            "No stored queries match '{text}' ({} searched).",
+            queries.len()
+        );
+        return Ok(());
+    }
+
+    for (query, matches) in &found {
+        println!(
+            "{}  {}",
+            query.name.green().bold(),
+            query.metadata.description.dimmed()
+        );
+        for m in matches.iter().filter(|m| m.field != "name") {
+            let line = m.text.trim();
+            let offset = m.text.len() - m.text.trim_start().len();
+            let start = m.range.start.saturating_sub(offset).min(line.len());
+            let end = m.range.end.saturating_sub(offset).min(line.len());
+            println!(
+                "  {:>12}  {}{}{}",
+                m.field.dimmed(),
+                &line[..start],
+                line[start..end].yellow().bold(),
+                &line[end..]
+            );
+        }
+    }
+
+    println!("\n{} of {} queries match.", found.len(), queries.len());
+    Ok(())
+}
+
 fn create(name: &str, project: bool) -> Result<()> {
     let path = query_file_path(name, project)?;
 
@@ -88,24 +171,18 @@ fn create(name: &str, project: bool) -> Result<()> {
     // Write a template .cosq file
     let template = StoredQueryMetadata {
         description: "TODO: describe what this query does".to_string(),
-        database: None,
-        container: None,
-        steps: None,
-        params: Vec::new(),
-        template: None,
-        template_file: None,
-        generated_by: None,
-        generated_from: None,
+        ..Default::default()
     };
     let yaml = serde_yaml::to_string(&template)?;
     let contents =
         format!("---\n{yaml}---\n-- Write your Cosmos DB SQL query below\nSELECT * FROM c\n");
-    std::fs::write(&path, &contents)?;
+    query_history::save(&path, &contents, "create")?;
 
     println!("{} Created {}", "OK".green().bold(), path.display());
 
     // Open in editor
     open_in_editor(&path)?;
+    query_history::record(&path, "edit")?;
 
     Ok(())
 }
@@ -117,7 +194,9 @@ fn edit(name: &str) -> Result<()> {
     // Verify it parses before opening
     let _ = StoredQuery::load(&path).map_err(|e| anyhow::anyhow!("Query file has errors: {e}"))?;
 
+    query_history::checkpoint(&path)?;
     open_in_editor(&path)?;
+    query_history::record(&path, "edit")?;
     Ok(())
 }
 
@@ -142,6 +221,339 @@ fn delete(name: &str, yes: bool) -> Result<()> {
     Ok(())
 }
 
+/// Target scope from `--project` / `--user` (`None` keeps the source's scope).
+fn scope(project: bool, user: bool) -> Option<bool> {
+    match (project, user) {
+        (true, _) => Some(true),
+        (_, true) => Some(false),
+        _ => None,
+    }
+}
+
+/// Resolve the source file and destination path for a rename or copy.
+fn relocate_paths(name: &str, new_name: &str, project: Option<bool>) -> Result<(PathBuf, PathBuf)> {
+    let source = find_query_path(name)?;
+    let new_name = new_name.strip_suffix(".cosq").unwrap_or(new_name);
+    if new_name.is_empty()
+        || !new_name
+            .chars()
+            .all(|c| c.is_alphanumeric() || c == '-' || c == '_')
+    {
+        bail!("'{new_name}' is not a valid query name (use letters, digits, '-' and '_')");
+    }
+
+    let in_project = project_queries_dir().is_some_and(|dir| source.starts_with(dir));
+    let target = query_file_path(new_name, project.unwrap_or(in_project))?;
+    if target == source {
+        bail!("'{name}' is already at {}", source.display());
+    }
+    if target.exists() {
+        bail!(
+            "Query '{new_name}' already exists at {}. Delete it first with `cosq queries delete {new_name}`.",
+            target.display()
+        );
+    }
+    if let Some(parent) = target.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    Ok((source, target))
+}
+
+fn rename(name: &str, new_name: &str, project: Option<bool>) -> Result<()> {
+    let (source, target) = relocate_paths(name, new_name, project)?;
+    let old_name = file_stem(&source);
+    let new_name = file_stem(&target);
+    let scheduled = StoredQuery::load(&source).is_ok_and(|q| q.metadata.schedule.is_some());
+    let includers = includers_of(&old_name);
+
+    // A plain rename fails across filesystems; fall back to copy + delete
+    if std::fs::rename(&source, &target).is_err() {
+        std::fs::copy(&source, &target)
+            .with_context(|| format!("failed to write {}", target.display()))?;
+        std::fs::remove_file(&source)?;
+    }
+    query_history::rename(&source, &target)?;
+    println!(
+        "{} Renamed '{old_name}' to '{new_name}' ({})",
+        "OK".green().bold(),
+        target.display()
+    );
+
+    if old_name != new_name {
+        let mut aliases = load_aliases_file()?;
+        let changed = retarget_aliases(&mut aliases, &old_name, &new_name);
+        if !changed.is_empty() {
+            save_aliases_file(&aliases)?;
+            println!(
+                "  Updated {} in ~/.cosq/aliases.yaml",
+                changed
+                    .iter()
+                    .map(|a| format!("'{a}'"))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            );
+        }
+        // References a rename can't safely rewrite
+        for (includer, include) in includers {
+            eprintln!(
+                "{} '{includer}' includes '{include}' — update its `include:`",
+                "Warning:".yellow().bold()
+            );
+        }
+        if scheduled {
+            eprintln!(
+                "{} exported schedules still run `cosq run {old_name}` — re-export with `cosq schedule export`",
+                "Warning:".yellow().bold()
+            );
+        }
+    }
+    Ok(())
+}
+
+fn copy(name: &str, new_name: &str, project: Option<bool>) -> Result<()> {
+    let (source, target) = relocate_paths(name, new_name, project)?;
+    let contents = std::fs::read_to_string(&source)
+        .with_context(|| format!("failed to read {}", source.display()))?;
+    let new_name = file_stem(&target);
+
+    // Front matter aliases would now match both queries, so the copy drops them
+    let mut query = StoredQuery::parse(&new_name, &contents)
+        .map_err(|e| anyhow::anyhow!("Query file has errors: {e}"))?;
+    let contents = if query.metadata.aliases.is_empty() {
+        contents
+    } else {
+        query.metadata.aliases.clear();
+        query.to_file_contents()?
+    };
+    query_history::save(
+        &target,
+        &contents,
+        &format!("copy of {}", file_stem(&source)),
+    )?;
+    println!(
+        "{} Copied '{}' to '{new_name}' ({})",
+        "OK".green().bold(),
+        file_stem(&source),
+        target.display()
+    );
+    if query.metadata.schedule.is_some() {
+        eprintln!(
+            "{} the copy keeps `schedule:` — both queries will run once you `cosq schedule export`",
+            "Warning:".yellow().bold()
+        );
+    }
+    Ok(())
+}
+
+fn history(name: &str) -> Result<()> {
+    let path = find_query_path(name)?;
+    let entries = query_history::list(&path)?;
+    if entries.is_empty() {
+        println!(
+            "No history for '{name}' yet — versions are recorded whenever cosq changes the query."
+        );
+        return Ok(());
+    }
+
+    let latest = entries.last().map_or(0, |e| e.version);
+    let changed =
+        query_history::read_version(&path, latest).ok() != std::fs::read_to_string(&path).ok();
+    println!(
+        "{} ({}):\n",
+        "Query history".bold(),
+        query_history::history_dir(&path)
+            .display()
+            .to_string()
+            .dimmed()
+    );
+    for entry in &entries {
+        println!(
+            "  {:>4}  {}  {}{}",
+            entry.version.to_string().green().bold(),
+            entry.saved_at.dimmed(),
+            entry.reason,
+            if entry.version == latest && !changed {
+                " (current)".cyan().to_string()
+            } else {
+                String::new()
+            }
+        );
+    }
+    if changed {
+        println!("\n  The file has changed since version {latest}.");
+    }
+    println!(
+        "\n  Restore one with: {}",
+        format!("cosq queries revert {name} --to <version>").cyan()
+    );
+    Ok(())
+}
+
+fn revert(name: &str, version: u32) -> Result<()> {
+    let path = find_query_path(name)?;
+    let contents = query_history::read_version(&path, version)?;
+    if let Err(e) = StoredQuery::parse(&file_stem(&path), &contents) {
+        eprintln!(
+            "{} version {version} does not parse: {e}",
+            "Warning:".yellow().bold()
+        );
+    }
+    query_history::revert(&path, version)?;
+    println!(
+        "{} Reverted '{}' to version {version}",
+        "OK".green().bold(),
+        file_stem(&path)
+    );
+    Ok(())
+}
+
+/// Queries whose `include:` refers to `name`, as (query, include entry) pairs.
+/// Collected before a rename, since the includers stop loading afterwards.
+fn includers_of(name: &str) -> Vec<(String, String)> {
+    list_stored_queries()
+        .unwrap_or_default()
+        .into_iter()
+        .filter_map(|q| {
+            let include = q
+                .metadata
+                .include
+                .iter()
+                .find(|i| include_refers_to(i, name))?
+                .clone();
+            Some((q.name, include))
+        })
+        .collect()
+}
+
+/// Query name from its file path.
+fn file_stem(path: &Path) -> String {
+    path.file_stem()
+        .and_then(|s| s.to_str())
+        .unwrap_or_default()
+        .to_string()
+}
+
+fn graph(name: &str, format: GraphFormat) -> Result<()> {
+    let query =
+        find_stored_query(name).map_err(|e| anyhow::anyhow!("Query '{name}' not found: {e}"))?;
+    let nodes = query.step_graph()?;
+    if nodes.is_empty() {
+        bail!(
+            "'{}' is not a multi-step query (it has no `steps:`)",
+            query.name
+        );
+    }
+    match format {
+        GraphFormat::Tree => print!("{}", graph_tree(&query.name, &nodes)),
+        GraphFormat::Dot => print!("{}", graph_dot(&query.name, &nodes)),
+    }
+    Ok(())
+}
+
+/// Steps grouped by execution layer, with the step fields and parameters each one uses.
+fn graph_tree(name: &str, nodes: &[StepNode]) -> String {
+    let layers = nodes.iter().map(|n| n.layer).max().map_or(0, |l| l + 1);
+    let width = nodes.iter().map(|n| n.name.len()).max().unwrap_or(0);
+    let mut out = format!(
+        "{} ({} steps, {} layer{})\n",
+        name.bold(),
+        nodes.len(),
+        layers,
+        if layers == 1 { "" } else { "s" }
+    );
+    for layer in 0..layers {
+        let steps: Vec<&StepNode> = nodes.iter().filter(|n| n.layer == layer).collect();
+        let parallel = if steps.len() > 1 {
+            format!(" — {} steps in parallel", steps.len())
+        } else {
+            String::new()
+        };
+        out.push_str(&format!(
+            "\n{}{}\n",
+            format!("Layer {}", layer + 1).bold(),
+            parallel.dimmed()
+        ));
+        for (i, node) in steps.iter().enumerate() {
+            let last = i + 1 == steps.len();
+            out.push_str(&format!(
+                "{} {:<width$}  {}\n",
+                if last { "└──" } else { "├──" },
+                node.name.green(),
+                format!("({})", node.container).dimmed()
+            ));
+            let indent = if last { "    " } else { "│   " };
+            let mut details: Vec<String> = node
+                .inputs
+                .iter()
+                .map(|(from, fields)| {
+                    let fields: Vec<String> =
+                        fields.iter().map(|f| format!("@{from}.{f}")).collect();
+                    format!("uses {} from {}", fields.join(", "), from.cyan())
+                })
+                .collect();
+            if !node.params.is_empty() {
+                let params: Vec<String> = node.params.iter().map(|p| format!("@{p}")).collect();
+                details.push(format!("params {}", params.join(", ")));
+            }
+            for (j, detail) in details.iter().enumerate() {
+                let branch = if j + 1 == details.len() {
+                    "└─"
+                } else {
+                    "├─"
+                };
+                out.push_str(&format!("{indent}{branch} {detail}\n"));
+            }
+        }
+    }
+    out
+}
+
+/// Graphviz DOT for the step graph: one edge per consumed step, labelled
+/// with the fields, and parallel steps on the same rank.
+fn graph_dot(name: &str, nodes: &[StepNode]) -> String {
+    // Labels carry DOT `\n` line breaks, so only quotes are escaped
+    let quote = |s: &str| format!("\"{}\"", s.replace('"', "\\\""));
+    let mut out = format!(
+        "digraph {} {{\n  rankdir=LR;\n  node [shape=box];\n",
+        quote(name)
+    );
+    for node in nodes {
+        let mut label = format!("{}\\n({})", node.name, node.container);
+        if !node.params.is_empty() {
+            let params: Vec<String> = node.params.iter().map(|p| format!("@{p}")).collect();
+            label.push_str(&format!("\\n{}", params.join(", ")));
+        }
+        out.push_str(&format!(
+            "  {} [label={}];\n",
+            quote(&node.name),
+            quote(&label)
+        ));
+    }
+    for node in nodes {
+        for (from, fields) in &node.inputs {
+            out.push_str(&format!(
+                "  {} -> {} [label={}];\n",
+                quote(from),
+                quote(&node.name),
+                quote(&fields.join(", "))
+            ));
+        }
+    }
+    let layers = nodes.iter().map(|n| n.layer).max().map_or(0, |l| l + 1);
+    for layer in 0..layers {
+        let same: Vec<String> = nodes
+            .iter()
+            .filter(|n| n.layer == layer)
+            .map(|n| quote(&n.name))
+            .collect();
+        if same.len() > 1 {
+            out.push_str(&format!("  {{ rank=same; {}; }}\n", same.join("; ")));
+        }
+    }
+    out.push_str("}\n");
+    out
+}
+
 fn show(name: &str) -> Result<()> {
     let query =
         find_stored_query(name).map_err(|e| anyhow::anyhow!("Query '{name}' not found: {e}"))?;
@@ -149,6 +561,26 @@ fn show(name: &str) -> Result<()> {
     println!("{}", query.name.green().bold());
     println!("  {} {}", "Description:".bold(), query.metadata.description);
 
+    if !query.metadata.aliases.is_empty() {
+        println!(
+            "  {}    {}",
+            "Aliases:".bold(),
+            query.metadata.aliases.join(", ")
+        );
+    }
+    if !query.metadata.tags.is_empty() {
+        println!(
+            "  {}       {}",
+            "Tags:".bold(),
+            query.metadata.tags.join(", ")
+        );
+    }
+
+    if let Some(ref profile) = query.metadata.profile {
+        println!("  {}    {}", "Profile:".bold(), profile);
+    } else if let Some(ref account) = query.metadata.account {
+        println!("  {}    {}", "Account:".bold(), account);
+    }
     if let Some(ref db) = query.metadata.database {
         println!("  {}  {}", "Database:".bold(), db);
     }
@@ -175,11 +607,12 @@ fn show(name: &str) -> Result<()> {
         for param in &query.metadata.params {
             let type_str = param.param_type.to_string();
             let desc = param.description.as_deref().unwrap_or("");
-            let default_str = param
-                .default
-                .as_ref()
-                .map(|d| format!(" (default: {})", d))
-                .unwrap_or_default();
+            let default_str = match (&param.default_cmd, &param.default) {
+                (Some(cmd), _) => format!(" (default: `{cmd}`)"),
+                (None, Some(_)) if param.encrypted => " (default: encrypted)".to_string(),
+                (None, Some(d)) => format!(" (default: {})", d),
+                (None, None) => String::new(),
+            };
 
             println!(
                 "    {} <{}>  {}{}",
@@ -199,6 +632,9 @@ fn show(name: &str) -> Result<()> {
                     .collect();
                 println!("      {}: {}", "choices".dimmed(), choices_str.join(", "));
             }
+            if let Some(ref when) = param.when {
+                println!("      {}: {}", "when".dimmed(), when);
+            }
             if let Some(min) = param.min {
                 print!("      {}: {min}", "min".dimmed());
             }
@@ -223,20 +659,161 @@ fn show(name: &str) -> Result<()> {
         }
     }
 
+    if !query.metadata.sample_results.is_empty() {
+        println!("\n  {}:", "Sample results".bold());
+        let sample = serde_json::to_string_pretty(&query.metadata.sample_results)?;
+        for line in sample.lines() {
+            println!("    {}", line.dimmed());
+        }
+    }
+
     Ok(())
 }
 
+async fn lint(name: Option<String>, connect: bool) -> Result<()> {
+    let queries = if let Some(ref name) = name {
+        vec![
+            find_stored_query(name)
+                .map_err(|e| anyhow::anyhow!("Query '{name}' not found: {e}"))?,
+        ]
+    } else {
+        list_stored_queries().unwrap_or_default()
+    };
+
+    if queries.is_empty() {
+        println!("No stored queries found.");
+        return Ok(());
+    }
+
+    let mut total = 0;
+    let mut client_and_config = None;
+
+    for query in &queries {
+        let mut containers = std::collections::BTreeMap::new();
+        if connect {
+            if client_and_config.is_none() {
+                let config = Config::load()?;
+                let client = super::common::connect(&config).await?;
+                client_and_config = Some((client, config));
+            }
+            let (client, config) = client_and_config.as_ref().unwrap();
+            containers = fetch_container_info(client, config, query).await;
+        }
+
+        let warnings = cosq_core::lint::lint_query(query, &containers);
+        if warnings.is_empty() {
+            if name.is_some() {
+                println!("{} {}: no issues found", "OK".green().bold(), query.name);
+            }
+            continue;
+        }
+
+        print_lint_warnings(&query.name, &warnings);
+        total += warnings.len();
+    }
+
+    if total > 0 {
+        bail!("{total} lint warning(s) found");
+    }
+    if name.is_none() {
+        println!(
+            "{} {} queries checked, no issues found",
+            "OK".green().bold(),
+            queries.len()
+        );
+    }
+    Ok(())
+}
+
+/// Print one query's lint findings (shared with `cosq templates lint`).
+pub fn print_lint_warnings(name: &str, warnings: &[cosq_core::lint::LintWarning]) {
+    println!("{}", name.green().bold());
+    for warning in warnings {
+        let location = warning
+            .step
+            .as_ref()
+            .map(|s| format!(" [step {s}]"))
+            .unwrap_or_default();
+        println!(
+            "  {} {}{}: {}",
+            "warning".yellow().bold(),
+            warning.rule.cyan(),
+            location.dimmed(),
+            warning.message
+        );
+        println!("    {} {}", "fix:".dimmed(), warning.suggestion);
+    }
+    println!();
+}
+
+/// Fetch partition key and indexing metadata for every container a query targets.
+/// Containers that cannot be read are skipped (their rules are not applied).
+async fn fetch_container_info(
+    client: &cosq_client::cosmos::CosmosClient,
+    config: &Config,
+    query: &StoredQuery,
+) -> std::collections::BTreeMap<String, cosq_core::lint::ContainerInfo> {
+    let mut infos = std::collections::BTreeMap::new();
+    let Some(database) = query
+        .metadata
+        .database
+        .as_ref()
+        .or(config.database.as_ref())
+    else {
+        return infos;
+    };
+
+    let containers: Vec<String> = match query.metadata.steps {
+        Some(ref steps) => steps.iter().map(|s| s.container.clone()).collect(),
+        None => query
+            .metadata
+            .container
+            .clone()
+            .or_else(|| config.container.clone())
+            .into_iter()
+            .collect(),
+    };
+
+    for ctr in containers {
+        match client.get_container(database, &ctr).await {
+            Ok(props) => {
+                let policy = props.indexing_policy;
+                infos.insert(
+                    ctr,
+                    cosq_core::lint::ContainerInfo {
+                        partition_key_paths: props.partition_key.paths,
+                        excluded_paths: policy.excluded_paths.into_iter().map(|p| p.path).collect(),
+                        composite_indexes: policy
+                            .composite_indexes
+                            .into_iter()
+                            .map(|idx| idx.into_iter().map(|p| (p.path, p.order)).collect())
+                            .collect(),
+                    },
+                );
+            }
+            Err(e) => {
+                eprintln!(
+                    "{} could not read container '{ctr}': {e}",
+                    "Warning:".yellow().bold()
+                );
+            }
+        }
+    }
+
+    infos
+}
+
 async fn generate(
     description: Option<String>,
     cli_db: Option<String>,
-    cli_container: Option<String>,
+    cli_containers: Vec<String>,
     project: bool,
     quiet: bool,
 ) -> Result<()> {
     let mut config = Config::load()?;
 
     // --- Step 1: Resolve database ---
-    let client = cosq_client::cosmos::CosmosClient::new(&config.account.endpoint).await?;
+    let client = super::common::connect(&config).await?;
 
     let (database, db_changed) =
         super::common::resolve_database(&client, &mut config, cli_db, None).await?;
@@ -246,28 +823,48 @@ async fn generate(
     }
 
     // --- Step 2: Resolve containers (one or many) ---
-    let containers = if let Some(ctr) = cli_container {
-        vec![ctr]
+    let containers = if !cli_containers.is_empty() {
+        cli_containers
     } else {
-        pick_containers_interactive(&client, &database).await?
+        pick_containers_interactive(&client, &config.account.name, &database).await?
     };
 
     // --- Step 3: Sample documents from all containers ---
+    let sample_size = config
+        .ai_data
+        .as_ref()
+        .map_or(AiDataConfig::DEFAULT_MAX_DOCUMENTS, |d| d.max_documents());
     let mut container_samples: Vec<(String, String)> = Vec::new();
     for ctr in &containers {
         if !quiet {
             eprintln!("{}", format!("Sampling documents from {ctr}...").dimmed());
         }
         let sample_result = client
-            .query(&database, ctr, "SELECT TOP 3 * FROM c")
+            .query(
+                &database,
+                ctr,
+                &format!("SELECT TOP {sample_size} * FROM c"),
+            )
             .await
             .with_context(|| format!("failed to sample documents from {ctr}"))?;
 
-        let sample_json = if sample_result.documents.is_empty() {
+        let documents = sample_result.documents;
+        let mut sample_json = if documents.is_empty() {
             "(container is empty)".to_string()
         } else {
-            format_sample_documents(&sample_result.documents)
+            match super::common::ai_documents(&config, &documents) {
+                Some(documents) => format_sample_documents(&documents),
+                None => super::common::ai_schema_summary(&documents),
+            }
         };
+        // A schema cached by `cosq schema` covers fields the few samples miss
+        if let Ok(Some(schema)) = Schema::load_cached(&config.account.name, &database, ctr) {
+            sample_json.push_str(&format!(
+                "\nInferred schema ({} documents sampled):\n{}",
+                schema.sampled,
+                schema.summary()
+            ));
+        }
         container_samples.push((ctr.clone(), sample_json));
     }
 
@@ -282,7 +879,16 @@ async fn generate(
     };
 
     // --- Step 5-6: Build prompt and call AI (with conversation loop) ---
-    let system_prompt = build_system_prompt(&database, &container_samples);
+    // Azure OpenAI can return the query as a schema-checked object instead of
+    // free text (single-container queries; multi-step ones stay .cosq text)
+    let structured = if containers.len() == 1 {
+        cosq_client::azure_openai::AzureOpenAIClient::from_ailloy_config(
+            super::ai::api_key()?.as_deref(),
+        )?
+    } else {
+        None
+    };
+    let system_prompt = build_system_prompt(&database, &container_samples, structured.is_some());
 
     let user_prompt = format!("Generate a .cosq stored query for: {description}");
 
@@ -304,14 +910,38 @@ async fn generate(
     let max_rounds = 3;
 
     for round in 0..max_rounds {
-        let response = cosq_client::ai::generate_text(&system_prompt, &conversation_prompt)
-            .await
-            .context("failed to generate query")?;
-
-        let content = strip_markdown_fences(&response);
+        let content = match &structured {
+            Some(client) => {
+                let response = super::ai::generate_structured(
+                    client,
+                    "generate",
+                    &system_prompt,
+                    &conversation_prompt,
+                    "cosq_query",
+                    &generated_query_schema(),
+                )
+                .await
+                .context("failed to generate query")?;
+                serde_json::from_str::<GeneratedQuery>(&response)
+                    .context("structured response does not match the query schema")?
+                    .into_content()?
+            }
+            None => {
+                let response =
+                    super::ai::generate("generate", &system_prompt, &conversation_prompt)
+                        .await
+                        .context("failed to generate query")?;
+                strip_markdown_fences(&response)
+            }
+        };
 
-        // Try to parse as a .cosq query
-        match StoredQuery::parse("generated", &content) {
+        // Try to parse as a .cosq query, then check it only targets sampled containers
+        match StoredQuery::parse("generated", &content)
+            .map_err(anyhow::Error::from)
+            .and_then(|parsed| {
+                validate_generated_containers(&parsed, &containers)?;
+                Ok(parsed)
+            }) {
             Ok(parsed) => {
                 query = Some(parsed);
                 break;
@@ -413,11 +1043,13 @@ async fn generate(
 
     // Save
     let path = query_file_path(&name, project)?;
-    if let Some(parent) = path.parent() {
-        std::fs::create_dir_all(parent)?;
-    }
     let contents = query.to_file_contents()?;
-    std::fs::write(&path, &contents)?;
+    if !super::ai::confirm_save(&path, &contents)? {
+        // Still hand over the result, e.g. to paste into `cosq queries create`
+        println!("{contents}");
+        return Ok(());
+    }
+    query_history::save(&path, &contents, "ai generate")?;
 
     println!("{} Saved to {}", "OK".green().bold(), path.display());
 
@@ -435,10 +1067,31 @@ async fn generate(
             super::run::run(super::run::RunArgs {
                 name: Some(name),
                 params: Vec::new(),
+                prompt: true,
                 output: None,
                 db: None,
                 container: None,
                 template: None,
+                template_debug: false,
+                chart: None,
+                hooks: super::hooks::HookPolicy::Ask,
+                notify: None,
+                out: None,
+                table: None,
+                replace: false,
+                post_sql: None,
+                jq: None,
+                limit: None,
+                priority: None,
+                targets: Vec::new(),
+                from_file: None,
+                save_steps: None,
+                resume: None,
+                plan: false,
+                force: false,
+                check: false,
+                profile: None,
+                redact: false,
                 quiet,
             })
             .await?;
@@ -455,9 +1108,10 @@ async fn generate(
 /// Interactively pick one or more containers from a database
 async fn pick_containers_interactive(
     client: &cosq_client::cosmos::CosmosClient,
+    account: &str,
     database: &str,
 ) -> Result<Vec<String>> {
-    let all_containers = client.list_containers(database).await?;
+    let all_containers = super::common::list_containers_cached(client, account, database).await?;
     if all_containers.is_empty() {
         bail!("No containers found in database '{database}'.");
     }
@@ -472,12 +1126,21 @@ async fn pick_containers_interactive(
     }
 
     // Ask if single or multi-container
-    let scope_options = vec!["Single container", "Multiple containers (multi-step query)"];
+    let scope_options = vec![
+        "Single container",
+        "Multiple containers (multi-step query)",
+        "All containers (let AI decide)",
+    ];
     let mode = inquire::Select::new("Query scope:", scope_options)
         .prompt()
         .context("selection cancelled")?;
 
-    if mode == "Single container" {
+    if mode == "All containers (let AI decide)" {
+        for ctr in &all_containers {
+            eprintln!("  {} {}", "▸".dimmed(), ctr.green());
+        }
+        Ok(all_containers)
+    } else if mode == "Single container" {
         // Single container
         let selection = inquire::Select::new("Select a container:", all_containers.clone())
             .prompt()
@@ -563,7 +1226,11 @@ fn show_query_preview(query: &StoredQuery, suggested_name: &str) {
 
 /// Build the system prompt with schema context from sampled documents.
 /// Supports both single-container and multi-container contexts.
-fn build_system_prompt(database: &str, container_samples: &[(String, String)]) -> String {
+fn build_system_prompt(
+    database: &str,
+    container_samples: &[(String, String)],
+    structured: bool,
+) -> String {
     let is_multi = container_samples.len() > 1;
 
     // Build container context section
@@ -584,6 +1251,8 @@ MULTI-STEP QUERY RULES — when the user's request involves data from multiple c
   This creates a DEPENDENCY — the referenced step runs first, then the value from its first result row is injected
 - Do NOT create fan-out queries (one step running per row of another). This is NOT supported.
 - Each step's results are available in the template as a top-level array by step name
+- Step containers MUST be one of the containers listed above
+- If the request only needs ONE of the containers, generate a single-step query instead and set `container` to that container
 
 Multi-step example (parallel — same input):
 ---
@@ -649,20 +1318,37 @@ SELECT * FROM c WHERE c.customerId = @customer.id ORDER BY c.date DESC
         "- Templates have access to 'documents' (array of results) and all parameter values"
     };
 
+    let (format_section, response_rules) = if structured {
+        (
+            "FORMAT — respond with a JSON object describing the query: `description`, `container`, \
+             the SQL in `sql`, `params` (each with name, type, description and default as text, \
+             null when unset) and the MiniJinja template in `template` (null for JSON output).",
+            "- When asking questions, ask 1-3 short questions in `question` and leave the other fields empty.\n\
+             - When generating, set `question` to null.",
+        )
+    } else {
+        (
+            "FORMAT — .cosq files use YAML front matter between --- delimiters, followed by the SQL query.",
+            "- When asking questions, ask 1-3 short questions. Do NOT generate a .cosq file in the same response.\n\
+             - When generating, respond with ONLY the .cosq file content — no explanation, no markdown fences.",
+        )
+    };
+
     format!(
         r#"You are a Cosmos DB SQL query generator. You create .cosq stored query files.
 
 TARGET:
   Database: "{database}"
 {container_section}
-FORMAT — .cosq files use YAML front matter between --- delimiters, followed by the SQL query.
+{format_section}
 
 SQL RULES:
 - ONLY reference fields that exist in the sample documents above
 - Use 'c' as the container alias (e.g., SELECT * FROM c)
 - Use Cosmos DB SQL syntax (TOP not LIMIT, no OFFSET, use DateTimeAdd/GetCurrentDateTime for dates)
 - Extract variable parts as @param parameters
-- Parameters: define in params section with name, type (string/number/bool), description, and optional default/choices/min/max{single_container_note}
+- Parameters: define in params section with name, type (string/number/bool/array/uuid/object), description, and optional default/choices/min/max{single_container_note}
+- A parameter that only matters for some values of an earlier one gets `when: scope == "regional"` (==, !=, &&, ||)
 {multi_step_rules}
 OUTPUT TEMPLATE RULES — ALWAYS include a MiniJinja template in the .cosq file:
 - Templates use {{{{ variable }}}} syntax and {{% for doc in documents %}} loops
@@ -692,11 +1378,153 @@ Choose the template style based on the query:
 CONVERSATION RULES:
 - Be CONFIDENT. If you can make a reasonable assumption, make it and generate the query.
 - Only ask clarifying questions if the description is genuinely ambiguous (e.g., which field to filter on, or the user mentions something not in the schema)
-- When asking questions, ask 1-3 short questions. Do NOT generate a .cosq file in the same response.
-- When generating, respond with ONLY the .cosq file content — no explanation, no markdown fences."#
+{response_rules}"#
     )
 }
 
+/// Check that a generated query only targets containers that were sampled for the prompt.
+///
+/// Multi-step queries must use a sampled container for every step and have a
+/// resolvable execution order. Single-step queries generated from several
+/// candidate containers must name the container they target.
+fn validate_generated_containers(query: &StoredQuery, containers: &[String]) -> Result<()> {
+    if let Some(ref steps) = query.metadata.steps {
+        for step in steps {
+            if !containers.contains(&step.container) {
+                bail!(
+                    "step '{}' targets container '{}', which is not one of: {}",
+                    step.name,
+                    step.container,
+                    containers.join(", ")
+                );
+            }
+        }
+        query.execution_order()?;
+    } else if containers.len() > 1 {
+        match query.metadata.container {
+            Some(ref ctr) if containers.contains(ctr) => {}
+            Some(ref ctr) => {
+                bail!("container '{ctr}' is not one of: {}", containers.join(", "));
+            }
+            None => {
+                bail!(
+                    "single-step query must set `container` to one of: {}",
+                    containers.join(", ")
+                );
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// A single-container query returned by Azure OpenAI structured output
+/// (see [`generated_query_schema`]).
+#[derive(Debug, serde::Deserialize)]
+struct GeneratedQuery {
+    /// Clarifying questions instead of a query
+    question: Option<String>,
+    description: String,
+    container: String,
+    sql: String,
+    params: Vec<GeneratedParam>,
+    template: Option<String>,
+}
+
+#[derive(Debug, serde::Deserialize)]
+struct GeneratedParam {
+    name: String,
+    #[serde(rename = "type")]
+    param_type: ParamType,
+    description: Option<String>,
+    /// Defaults come back as text and are parsed according to `type`
+    default: Option<String>,
+}
+
+/// JSON schema (strict mode) for [`GeneratedQuery`].
+fn generated_query_schema() -> serde_json::Value {
+    let nullable_string = serde_json::json!({ "type": ["string", "null"] });
+    serde_json::json!({
+        "type": "object",
+        "additionalProperties": false,
+        "required": ["question", "description", "container", "sql", "params", "template"],
+        "properties": {
+            "question": nullable_string,
+            "description": { "type": "string" },
+            "container": { "type": "string" },
+            "sql": { "type": "string" },
+            "params": {
+                "type": "array",
+                "items": {
+                    "type": "object",
+                    "additionalProperties": false,
+                    "required": ["name", "type", "description", "default"],
+                    "properties": {
+                        "name": { "type": "string" },
+                        "type": {
+                            "type": "string",
+                            "enum": ["string", "number", "bool", "array", "uuid", "object"]
+                        },
+                        "description": nullable_string,
+                        "default": nullable_string,
+                    }
+                }
+            },
+            "template": nullable_string,
+        }
+    })
+}
+
+impl GeneratedQuery {
+    /// The clarifying questions, or the query as .cosq file contents, so
+    /// structured answers go through the same checks as free-text ones.
+    fn into_content(self) -> Result<String> {
+        if let Some(question) = self.question.filter(|q| !q.trim().is_empty()) {
+            return Ok(question);
+        }
+        let params = self
+            .params
+            .into_iter()
+            .map(|p| {
+                let default = p
+                    .default
+                    .map(|raw| {
+                        cosq_core::stored_query::parse_param_value_public(
+                            &p.name,
+                            &p.param_type,
+                            &raw,
+                        )
+                    })
+                    .transpose()?;
+                let mut param = serde_json::json!({
+                    "name": p.name,
+                    "type": p.param_type,
+                });
+                if let Some(description) = p.description {
+                    param["description"] = description.into();
+                }
+                if let Some(default) = default {
+                    param["default"] = default;
+                }
+                Ok(serde_json::from_value(param)?)
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let query = StoredQuery {
+            name: "generated".into(),
+            metadata: StoredQueryMetadata {
+                description: self.description,
+                container: Some(self.container),
+                params,
+                template: self.template.filter(|t| !t.trim().is_empty()),
+                ..Default::default()
+            },
+            sql: self.sql,
+            step_queries: BTreeMap::new(),
+        };
+        Ok(query.to_file_contents()?)
+    }
+}
+
 /// Strip markdown code fences from AI responses.
 fn strip_markdown_fences(response: &str) -> String {
     let trimmed = response.trim();
@@ -797,6 +1625,308 @@ fn generate_filename(description: &str) -> String {
         .collect()
 }
 
+/// Encrypt a parameter value for an `encrypted: true` default, creating the
+/// key in the OS keyring on first use.
+fn encrypt(
+    value: Option<String>,
+    param_type: ParamType,
+    export_key: bool,
+    quiet: bool,
+) -> Result<()> {
+    let (key, created) = EncryptionKey::load_or_create()?;
+    if created && !quiet {
+        eprintln!(
+            "{} Created a new encryption key in the OS keyring",
+            "OK".green().bold()
+        );
+    }
+    if export_key {
+        eprintln!(
+            "{} anyone with this key can decrypt your encrypted query values",
+            "Warning:".yellow().bold()
+        );
+        println!("{}", key.to_base64());
+        return Ok(());
+    }
+
+    let raw = match value {
+        Some(value) => value,
+        None => inquire::Password::new("Value to encrypt:")
+            .without_confirmation()
+            .with_display_toggle_enabled()
+            .prompt()
+            .context("input cancelled")?,
+    };
+    let value = cosq_core::stored_query::parse_param_value_public("value", &param_type, &raw)?;
+    let sealed = key.encrypt(&value)?;
+    println!("{sealed}");
+    if !quiet {
+        eprintln!(
+            "\n  Use it as a parameter default:\n\n    {}\n    {}\n    {}",
+            format!("type: {param_type}").dimmed(),
+            "encrypted: true".dimmed(),
+            format!("default: \"{sealed}\"").dimmed()
+        );
+    }
+    Ok(())
+}
+
+/// Package a stored query as a self-contained snippet: includes and the
+/// template file are inlined, environment-specific settings are stripped, and
+/// optionally a few redacted results are bundled as `sample_results`.
+async fn share(
+    name: &str,
+    results: Option<usize>,
+    file: Option<PathBuf>,
+    profile: Option<String>,
+    quiet: bool,
+) -> Result<()> {
+    let path = find_query_path(name)?;
+    let mut query =
+        StoredQuery::load(&path).map_err(|e| anyhow::anyhow!("Query file has errors: {e}"))?;
+
+    if let Some(template_file) = query.metadata.template_file.take() {
+        if template_file.starts_with(BUILTIN_PREFIX) {
+            // Every cosq has the built-in templates; keep the reference
+            query.metadata.template_file = Some(template_file);
+        } else if query.metadata.template.is_none() {
+            // Same lookup as `cosq run` (working directory), then next to the query
+            let candidate = Path::new(&template_file);
+            let resolved = if candidate.exists() {
+                candidate.to_path_buf()
+            } else {
+                path.parent().unwrap_or(Path::new(".")).join(candidate)
+            };
+            let template = std::fs::read_to_string(&resolved)
+                .with_context(|| format!("failed to read template file: {template_file}"))?;
+            query.metadata.template = Some(template);
+        }
+    }
+
+    if let Some(limit) = results {
+        query.metadata.sample_results =
+            sample_results(&query, limit, profile.as_deref(), quiet).await?;
+    }
+    let hooks_removed = query.metadata.hooks.is_some();
+    let has_encrypted = query.has_encrypted_params();
+    let contents = query.into_snippet().to_file_contents()?;
+
+    match file {
+        Some(file) => {
+            std::fs::write(&file, &contents)
+                .with_context(|| format!("failed to write {}", file.display()))?;
+            if !quiet {
+                eprintln!(
+                    "{} Wrote snippet to {} — install it with `cosq queries install <url|path>`",
+                    "OK".green().bold(),
+                    file.display()
+                );
+            }
+        }
+        None => print!("{contents}"),
+    }
+    if hooks_removed && !quiet {
+        eprintln!(
+            "{} post-run hooks were left out of the snippet",
+            "Note:".dimmed()
+        );
+    }
+    if has_encrypted && !quiet {
+        eprintln!(
+            "{} encrypted parameter defaults can only be decrypted with your key",
+            "Note:".dimmed()
+        );
+    }
+    Ok(())
+}
+
+/// Run a single-step query with its default parameters and return up to
+/// `limit` results, without system properties and with `redact:` applied.
+async fn sample_results(
+    query: &StoredQuery,
+    limit: usize,
+    profile: Option<&str>,
+    quiet: bool,
+) -> Result<Vec<serde_json::Value>> {
+    if query.is_multi_step() {
+        bail!("--results is only supported for single-step queries");
+    }
+    let mut query = query.clone();
+    super::common::decrypt_params(&mut query)?;
+    let query = &query;
+    let resolved = query.resolve_params(&BTreeMap::new()).map_err(|e| {
+        anyhow::anyhow!("{e} (--results runs the query with its default parameter values)")
+    })?;
+
+    let (mut config, _) = super::run::query_config(Config::load()?, query, profile)?;
+    let client = super::common::connect(&config).await?;
+    let (database, _) = super::common::resolve_database(
+        &client,
+        &mut config,
+        None,
+        query.metadata.database.as_deref(),
+    )
+    .await?;
+    let (container, _) = super::common::resolve_container(
+        &client,
+        &mut config,
+        &database,
+        None,
+        query.metadata.container.as_deref(),
+    )
+    .await?;
+
+    if !quiet {
+        eprintln!(
+            "{}",
+            format!("Running {} for sample results...", query.name).dimmed()
+        );
+    }
+    let result = client
+        .query_with_params(
+            &database,
+            &container,
+            &query.sql,
+            StoredQuery::build_cosmos_params(&resolved),
+        )
+        .await
+        .with_context(|| format!("failed to run query '{}'", query.name))?;
+
+    let redactor = super::common::ai_redactor(&config);
+    if redactor.is_empty() && !quiet {
+        eprintln!(
+            "{} no `redact:` patterns are configured, so results are shared unmasked",
+            "Warning:".yellow().bold()
+        );
+    }
+    let mut documents: Vec<serde_json::Value> = result
+        .documents
+        .into_iter()
+        .take(limit)
+        .map(|mut doc| {
+            if let serde_json::Value::Object(ref mut map) = doc {
+                for field in cosq_core::schema::SYSTEM_FIELDS {
+                    map.remove(*field);
+                }
+            }
+            doc
+        })
+        .collect();
+    redactor.redact_all(&mut documents);
+    Ok(documents)
+}
+
+/// Install a snippet from a URL or file into the queries directory.
+async fn install(
+    source: &str,
+    name: Option<String>,
+    project: bool,
+    yes: bool,
+    quiet: bool,
+) -> Result<()> {
+    let contents = if source.starts_with("http://") || source.starts_with("https://") {
+        super::common::ensure_online("Downloading a query")?;
+        let response = reqwest::get(source)
+            .await
+            .with_context(|| format!("failed to download {source}"))?;
+        if !response.status().is_success() {
+            bail!("failed to download {source}: HTTP {}", response.status());
+        }
+        response.text().await?
+    } else {
+        std::fs::read_to_string(source).with_context(|| format!("failed to read {source}"))?
+    };
+
+    let name = match name {
+        Some(name) => name,
+        None => snippet_name(source)?,
+    };
+    let query = StoredQuery::parse(&name, &contents)
+        .map_err(|e| anyhow::anyhow!("{source} is not a valid .cosq snippet: {e}"))?;
+
+    let path = query_file_path(&name, project)?;
+    if path.exists() && !yes {
+        bail!(
+            "Query '{name}' already exists at {}. Pass --name to install under another name, or --yes to overwrite.",
+            path.display()
+        );
+    }
+
+    // Snippets from `cosq queries share` never carry hooks, but hand-written ones
+    // might; parameter `default_cmd:` commands are kept when sharing
+    let mut commands = Vec::new();
+    if let Some(hook) = query
+        .metadata
+        .hooks
+        .as_ref()
+        .and_then(|h| h.post_run.as_ref())
+    {
+        commands.push(("after each run", hook.command.as_str()));
+    }
+    for param in &query.metadata.params {
+        if let Some(ref cmd) = param.default_cmd {
+            commands.push(("for a parameter default", cmd.as_str()));
+        }
+    }
+    if !commands.is_empty() {
+        for (when, command) in &commands {
+            eprintln!(
+                "{} this query runs a command {when}: {}",
+                "Warning:".yellow().bold(),
+                command.cyan()
+            );
+        }
+        if !yes
+            && !inquire::Confirm::new("Install it anyway?")
+                .with_default(false)
+                .prompt()
+                .context("confirmation cancelled")?
+        {
+            println!("Cancelled.");
+            return Ok(());
+        }
+    }
+
+    query_history::save(&path, &contents, &format!("install from {source}"))?;
+    println!(
+        "{} Installed query '{name}' to {}",
+        "OK".green().bold(),
+        path.display()
+    );
+    if !quiet {
+        if !query.metadata.description.is_empty() {
+            println!("  {}", query.metadata.description.dimmed());
+        }
+        println!(
+            "
+  Run it with: {}",
+            format!("cosq run {name}").cyan()
+        );
+    }
+    Ok(())
+}
+
+/// Query name from a snippet path or URL (`.../open-orders.cosq` → `open-orders`).
+fn snippet_name(source: &str) -> Result<String> {
+    let last = source
+        .split(['?', '#'])
+        .next()
+        .unwrap_or(source)
+        .trim_end_matches('/')
+        .rsplit(['/', '\\'])
+        .next()
+        .unwrap_or_default();
+    let name = last.strip_suffix(".cosq").unwrap_or(last);
+    if name.is_empty()
+        || !name
+            .chars()
+            .all(|c| c.is_alphanumeric() || c == '-' || c == '_')
+    {
+        bail!("can't derive a query name from '{source}'; pass --name");
+    }
+    Ok(name.to_string())
+}
+
 /// Open a file in the user's default editor
 fn open_in_editor(path: &std::path::Path) -> Result<()> {
     let editor = std::env::var("VISUAL")
@@ -821,34 +1951,77 @@ fn open_in_editor(path: &std::path::Path) -> Result<()> {
     Ok(())
 }
 
-/// Find the file path for a stored query by name (checking project then user dir)
+/// Find the file path for a stored query by name or alias (checking project then user dir)
 fn find_query_path(name: &str) -> Result<std::path::PathBuf> {
-    let filename = if name.ends_with(".cosq") {
-        name.to_string()
-    } else {
-        format!("{name}.cosq")
-    };
-
-    if let Some(project_dir) = cosq_core::stored_query::project_queries_dir() {
-        let path = project_dir.join(&filename);
-        if path.exists() {
-            return Ok(path);
+    match cosq_core::stored_query::find_query_file(name) {
+        Ok(path) => Ok(path),
+        Err(e @ cosq_core::stored_query::StoredQueryError::AmbiguousAlias { .. }) => {
+            bail!("{e}")
+        }
+        Err(_) => {
+            bail!("Query '{name}' not found. Run `cosq queries list` to see available queries.")
         }
     }
-
-    let user_dir = user_queries_dir().map_err(|e| anyhow::anyhow!("{e}"))?;
-    let path = user_dir.join(&filename);
-    if path.exists() {
-        return Ok(path);
-    }
-
-    bail!("Query '{name}' not found. Run `cosq queries list` to see available queries.")
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    fn sample_graph() -> Vec<StepNode> {
+        let node =
+            |name: &str, container: &str, layer, inputs: &[(&str, &[&str])], params: &[&str]| {
+                StepNode {
+                    name: name.into(),
+                    container: container.into(),
+                    layer,
+                    inputs: inputs
+                        .iter()
+                        .map(|(from, fields)| {
+                            (
+                                from.to_string(),
+                                fields.iter().map(|f| f.to_string()).collect(),
+                            )
+                        })
+                        .collect(),
+                    params: params.iter().map(|p| p.to_string()).collect(),
+                }
+            };
+        vec![
+            node("customer", "customers", 0, &[], &["email"]),
+            node("orders", "orders", 1, &[("customer", &["id"])], &[]),
+            node(
+                "tickets",
+                "tickets",
+                1,
+                &[("customer", &["id", "tier"])],
+                &[],
+            ),
+        ]
+    }
+
+    #[test]
+    fn test_graph_tree() {
+        colored::control::set_override(false);
+        let tree = graph_tree("customer-360", &sample_graph());
+        assert!(tree.starts_with("customer-360 (3 steps, 2 layers)"));
+        assert!(tree.contains("Layer 2 — 2 steps in parallel"));
+        assert!(tree.contains("├── orders"));
+        assert!(tree.contains("└── tickets"));
+        assert!(tree.contains("uses @customer.id, @customer.tier from customer"));
+        assert!(tree.contains("params @email"));
+    }
+
+    #[test]
+    fn test_graph_dot() {
+        let dot = graph_dot("customer-360", &sample_graph());
+        assert!(dot.starts_with("digraph \"customer-360\" {"));
+        assert!(dot.contains("\"customer\" [label=\"customer\\n(customers)\\n@email\"];"));
+        assert!(dot.contains("\"customer\" -> \"tickets\" [label=\"id, tier\"];"));
+        assert!(dot.contains("{ rank=same; \"orders\"; \"tickets\"; }"));
+        assert!(dot.trim_end().ends_with('}'));
+    }
+
     #[test]
     fn test_generate_filename() {
         assert_eq!(
@@ -862,6 +2035,62 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_generated_query_into_content() {
+        let generated: GeneratedQuery = serde_json::from_value(serde_json::json!({
+            "question": null,
+            "description": "Recent orders",
+            "container": "orders",
+            "sql": "SELECT TOP @n * FROM c ORDER BY c.date DESC",
+            "params": [
+                { "name": "n", "type": "number", "description": "How many", "default": "10" }
+            ],
+            "template": "{% for doc in documents %}{{ doc.id }}\n{% endfor %}"
+        }))
+        .unwrap();
+        let query = StoredQuery::parse("generated", &generated.into_content().unwrap()).unwrap();
+        assert_eq!(query.metadata.description, "Recent orders");
+        assert_eq!(query.metadata.container.as_deref(), Some("orders"));
+        assert_eq!(
+            query.metadata.params[0].default,
+            Some(serde_json::json!(10))
+        );
+        assert!(query.metadata.template.is_some());
+        assert!(query.sql.starts_with("SELECT TOP @n"));
+
+        let question: GeneratedQuery = serde_json::from_value(serde_json::json!({
+            "question": "Which date field?",
+            "description": "",
+            "container": "",
+            "sql": "",
+            "params": [],
+            "template": null
+        }))
+        .unwrap();
+        assert_eq!(question.into_content().unwrap(), "Which date field?");
+
+        let schema = generated_query_schema();
+        assert_eq!(
+            schema["required"].as_array().unwrap().len(),
+            schema["properties"].as_object().unwrap().len()
+        );
+    }
+
+    #[test]
+    fn test_snippet_name() {
+        assert_eq!(
+            snippet_name("shared/open-orders.cosq").unwrap(),
+            "open-orders"
+        );
+        assert_eq!(
+            snippet_name("https://gist.githubusercontent.com/u/abc/raw/open-orders.cosq?x=1")
+                .unwrap(),
+            "open-orders"
+        );
+        assert!(snippet_name("https://example.com/").is_err());
+        assert!(snippet_name("weird name.cosq").is_err());
+    }
+
     #[test]
     fn test_generate_filename_empty_input() {
         let name = generate_filename("users");
@@ -917,6 +2146,46 @@ mod tests {
         assert_eq!(items.len(), 4); // 3 items + "... (7 items total)"
     }
 
+    const GENERATED_MULTI_STEP: &str = r#"---
+description: Customer orders
+steps:
+  - name: customer
+    container: customers
+  - name: orders
+    container: orders
+---
+-- step: customer
+SELECT TOP 1 * FROM c WHERE c.name = @name
+
+-- step: orders
+SELECT * FROM c WHERE c.customerId = @customer.id
+"#;
+
+    #[test]
+    fn test_validate_generated_multi_step() {
+        let query = StoredQuery::parse("generated", GENERATED_MULTI_STEP).unwrap();
+        let containers = vec!["customers".to_string(), "orders".to_string()];
+        assert!(validate_generated_containers(&query, &containers).is_ok());
+    }
+
+    #[test]
+    fn test_validate_generated_unknown_step_container() {
+        let query = StoredQuery::parse("generated", GENERATED_MULTI_STEP).unwrap();
+        let containers = vec!["customers".to_string(), "invoices".to_string()];
+        let err = validate_generated_containers(&query, &containers).unwrap_err();
+        assert!(err.to_string().contains("'orders'"));
+    }
+
+    #[test]
+    fn test_validate_generated_single_step_needs_container() {
+        let contents = "---\ndescription: test\n---\nSELECT * FROM c\n";
+        let query = StoredQuery::parse("generated", contents).unwrap();
+        let containers = vec!["customers".to_string(), "orders".to_string()];
+        assert!(validate_generated_containers(&query, &containers).is_err());
+        // A single sampled container is filled in after generation
+        assert!(validate_generated_containers(&query, &containers[..1]).is_ok());
+    }
+
     #[test]
     fn test_format_sample_documents() {
         use serde_json::json;
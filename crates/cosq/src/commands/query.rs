@@ -3,78 +3,304 @@
 //! Resolves database and container from CLI flags, config, or interactive
 //! prompts, then executes the query and prints results in the requested format.
 
-use anyhow::{Context, Result};
-use colored::Colorize;
-use cosq_client::cosmos::CosmosClient;
-use cosq_core::config::Config;
+use std::path::PathBuf;
+use std::time::Instant;
 
-use super::common;
-use crate::output::{OutputFormat, render_template, write_results};
+use anyhow::{Result, bail};
+use cosq_client::cosmos::{PriorityLevel, QueryResult};
+use cosq_core::chart::ChartSpec;
+use cosq_core::config::{Config, ConfigError};
+use cosq_core::stats::AD_HOC;
+
+use super::{common, federated, guided};
+use crate::builtin_templates::read_template;
+use crate::destination::{self, DEFAULT_TABLE, Destination};
+use crate::output::{
+    OutputFormat, RowLimit, render_template, template_context, write_chart, write_results,
+};
 
 pub struct QueryArgs {
     pub sql: String,
+    /// Build the query with the guided builder instead of `sql`
+    pub interactive: bool,
     pub db: Option<String>,
     pub container: Option<String>,
     pub output: Option<OutputFormat>,
     pub template: Option<String>,
+    /// Explain template render errors instead of just failing
+    pub template_debug: bool,
+    /// `--chart` spec, rendered instead of the output format
+    pub chart: Option<String>,
+    pub out: Option<String>,
+    /// `--table` / `--replace` for a DuckDB `out`
+    pub table: Option<String>,
+    pub replace: bool,
+    /// Local SQL over the results (`--post-sql`), applied before `jq`
+    pub post_sql: Option<String>,
+    pub jq: Option<String>,
+    /// `--head` / `--tail`, applied after `jq`
+    pub limit: Option<RowLimit>,
+    pub priority: Option<PriorityLevel>,
+    pub targets: Vec<String>,
+    /// Query this JSON/NDJSON file instead of Cosmos DB (with no `sql`, its
+    /// documents are used as-is)
+    pub from_file: Option<PathBuf>,
+    /// Skip the confirmation for non-SELECT SQL on protected containers
+    pub force: bool,
+    pub profile: Option<String>,
+    /// Mask fields matching the config's `redact:` patterns
+    pub redact: bool,
     pub quiet: bool,
 }
 
 pub async fn run(args: QueryArgs) -> Result<()> {
-    let mut config = Config::load()?;
-    let client = CosmosClient::new(&config.account.endpoint).await?;
+    let destination = args
+        .out
+        .as_deref()
+        .map(|out| {
+            Destination::parse(out)?.with_table(args.table.clone(), args.replace, DEFAULT_TABLE)
+        })
+        .transpose()?;
+    let chart = args.chart.as_deref().map(ChartSpec::parse).transpose()?;
+    let loaded = base_config(Config::load(), args.from_file.is_some())?;
+    // --profile targets a named profile; picker choices are then not saved
+    let (mut config, pinned) = match args.profile {
+        Some(ref profile) => (loaded.for_profile(profile)?, true),
+        None => (loaded, false),
+    };
+    // Preferences of the queried container (federated queries read several)
+    let view = if args.targets.is_empty() {
+        common::ContainerView::load(&config, args.db.as_deref(), args.container.as_deref())
+    } else {
+        None
+    };
+    let mut sql = args.sql;
+    let mut started = Instant::now();
+    // Replayed results are neither charged nor cached
+    let replayed = args.from_file.is_some() || common::is_offline();
+    let mut result = if let Some(ref path) = args.from_file {
+        if sql.trim().is_empty() {
+            common::replay_file(path, args.quiet)?
+        } else {
+            common::query_file(path, &sql, args.quiet)?
+        }
+    } else if common::is_offline() {
+        if args.interactive || !args.targets.is_empty() {
+            bail!("--interactive and --target are not supported with --offline");
+        }
+        let database = common::resolve_offline("db", args.db, None, config.database.as_deref())?;
+        let container = common::resolve_offline(
+            "container",
+            args.container,
+            None,
+            config.container.as_deref(),
+        )?;
+        let key = common::cache_key(
+            &config,
+            &database,
+            &container,
+            &sql,
+            &Vec::<serde_json::Value>::new(),
+        )?;
+        let cached = common::cached_results(&key, args.quiet)?;
+        QueryResult {
+            documents: cached.documents,
+            request_charge: cached.request_charge,
+            partitions: Vec::new(),
+            partial: false,
+        }
+    } else if args.targets.is_empty() {
+        let mut client = common::connect(&config).await?;
+        if let Some(level) = args.priority {
+            client = client.with_priority(level);
+        }
+
+        let (database, db_changed) =
+            common::resolve_database(&client, &mut config, args.db, None).await?;
+        let (container, ctr_changed) =
+            common::resolve_container(&client, &mut config, &database, args.container, None)
+                .await?;
 
-    let (database, db_changed) =
-        common::resolve_database(&client, &mut config, args.db, None).await?;
-    let (container, ctr_changed) =
-        common::resolve_container(&client, &mut config, &database, args.container, None).await?;
+        if (db_changed || ctr_changed) && !pinned {
+            config.save()?;
+        }
+
+        let mut parameters = Vec::new();
+        if args.interactive {
+            let built = guided::build_query(&client, &database, &container).await?;
+            parameters = built.cosmos_parameters();
+            sql = built.sql;
+            // Time the query, not the prompts
+            started = Instant::now();
+        }
 
-    if db_changed || ctr_changed {
-        config.save()?;
+        common::guard_protected(&config, &database, &container, &sql, args.force, true)?;
+        common::warn_if_analytical(&client, &database, &container, &sql, args.quiet).await;
+        let key = common::cache_key(&config, &database, &container, &sql, &parameters)?;
+        let interrupt = common::Interrupt::install();
+        let result = client
+            .with_cancellation(interrupt.flag())
+            .query_with_params(&database, &container, &sql, parameters)
+            .await;
+        interrupt.finish();
+        match result {
+            Ok(result) => {
+                if !result.partial {
+                    common::cache_results(
+                        &key,
+                        result.request_charge,
+                        result.documents.clone(),
+                        Default::default(),
+                    );
+                }
+                result
+            }
+            Err(e) => {
+                return Err(
+                    common::advise_on_query_error(e, &config, &database, &container, &sql).await,
+                );
+            }
+        }
+    } else {
+        let targets = federated::parse_targets(&args.targets)?;
+        federated::execute(
+            &config,
+            &targets,
+            federated::FederatedQuery {
+                sql: &sql,
+                parameters: Vec::new(),
+                db: args.db.as_deref(),
+                container: args.container.as_deref(),
+                default_db: None,
+                default_container: None,
+                priority: args.priority,
+                force: args.force,
+                interactive: true,
+                quiet: args.quiet,
+                cache_plans: false,
+            },
+        )
+        .await?
+    };
+    let elapsed = started.elapsed();
+    let fetched = result.documents.len();
+    common::output_redactor(&config, args.redact).redact_all(&mut result.documents);
+    if let Some(ref sql) = args.post_sql {
+        result.documents =
+            crate::columnar::post_sql(sql, std::mem::take(&mut result.documents)).await?;
+    }
+    if let Some(ref filter) = args.jq {
+        result.documents = crate::jq::apply(filter, std::mem::take(&mut result.documents))?;
     }
 
-    // Execute query
-    let result = client.query(&database, &container, &args.sql).await?;
+    // Determine output format: -o > --template > the container's preferences >
+    // `output:` in the config > JSON
+    let format = match (
+        args.output.clone(),
+        args.template.is_some(),
+        common::configured_output(&config, view.as_ref()),
+    ) {
+        (Some(format), ..) => format,
+        (None, true, _) => OutputFormat::Template,
+        (None, false, Some((name, origin))) => super::run::parse_output_format(name, origin)?,
+        (None, false, None) => OutputFormat::Json,
+    };
+    let format = match destination {
+        Some(ref destination) => destination.output_format(args.output.as_ref(), format)?,
+        None => format,
+    };
+    if let Some(ref view) = view {
+        view.apply(&format, &sql, &mut result.documents);
+    }
+    common::apply_row_limit(args.limit, &mut result.documents, args.quiet);
+    common::fit_table(
+        view.as_ref(),
+        &format,
+        destination.is_none(),
+        args.quiet,
+        &mut result.documents,
+    )?;
 
-    // Determine output format
-    let has_template = args.template.is_some();
-    let format = args.output.unwrap_or(if has_template {
-        OutputFormat::Template
+    let mut out: Vec<u8> = Vec::new();
+    if let Some(ref spec) = chart {
+        let svg = destination.as_ref().is_some_and(Destination::is_svg);
+        write_chart(&mut out, &result.documents, spec, svg, args.quiet)?;
     } else {
-        OutputFormat::Json
-    });
-
-    match format {
-        OutputFormat::Template => {
-            if let Some(ref path) = args.template {
-                let template_str = std::fs::read_to_string(path)
-                    .with_context(|| format!("failed to read template file: {path}"))?;
-                let rendered = render_template(
-                    &template_str,
-                    &result.documents,
-                    &std::collections::BTreeMap::new(),
-                )?;
-                print!("{rendered}");
-            } else {
-                write_results(
-                    &mut std::io::stdout(),
-                    &result.documents,
-                    &OutputFormat::Json,
-                )?;
+        match format {
+            OutputFormat::Template => {
+                if let Some(ref path) = args.template {
+                    let template_str = read_template(path)?;
+                    let params = std::collections::BTreeMap::new();
+                    let rendered = match render_template(&template_str, &result.documents, &params)
+                    {
+                        Ok(rendered) => rendered,
+                        Err(e) if args.template_debug => {
+                            return Err(common::template_debug_failure(
+                                &template_str,
+                                &e,
+                                &template_context(&result.documents, &params),
+                            ));
+                        }
+                        Err(e) => return Err(e),
+                    };
+                    out.extend_from_slice(rendered.as_bytes());
+                } else {
+                    write_results(&mut out, &result.documents, &OutputFormat::Json)?;
+                }
+            }
+            _ => {
+                write_results(&mut out, &result.documents, &format)?;
             }
-        }
-        _ => {
-            write_results(&mut std::io::stdout(), &result.documents, &format)?;
         }
     }
+    destination::emit(&out, destination.as_ref(), &config, args.quiet).await?;
 
-    if !args.quiet {
-        eprintln!(
-            "\n{} {:.2} RUs",
-            "Request charge:".dimmed(),
-            result.request_charge
+    if result.partial {
+        return Err(common::partial_results(
+            &config,
+            AD_HOC,
+            fetched,
+            result.request_charge,
+            args.quiet,
+        ));
+    }
+    if !replayed {
+        if let Some(ref view) = view {
+            view.record_query(&sql);
+        }
+        common::report_charge(&config, AD_HOC, result.request_charge, args.quiet);
+        common::warn_if_slow(
+            &config,
+            AD_HOC,
+            result.request_charge,
+            elapsed,
+            &result.partitions,
         );
     }
 
     Ok(())
 }
+
+/// The loaded config, or an empty one for `--from-file` before `cosq init`
+/// (a local file needs no account).
+fn base_config(loaded: Result<Config, ConfigError>, from_file: bool) -> Result<Config> {
+    match loaded {
+        Err(ConfigError::NotFound) if from_file => Ok(Config::default()),
+        loaded => Ok(loaded?),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_base_config_without_config_file() {
+        let config = base_config(Err(ConfigError::NotFound), true).unwrap();
+        assert!(config.account.name.is_empty());
+        assert!(config.database.is_none());
+
+        let err = base_config(Err(ConfigError::NotFound), false).unwrap_err();
+        assert!(err.downcast_ref::<ConfigError>().is_some());
+    }
+}
@@ -0,0 +1,168 @@
+//! Render command — format saved results with a template, without querying
+//!
+//! For developing report templates offline: `--data` is a JSON array or an
+//! NDJSON export (`-o json` / `-o json-compact`, a `--save-steps` file), or an
+//! object of step name → documents (`-o json` of a multi-step query) for
+//! templates that use step variables.
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result, bail};
+use serde_json::Value;
+
+use super::common;
+use crate::builtin_templates::read_template;
+use crate::output::{
+    FailedRow, multi_step_template_context, render_multi_step_template, render_rows,
+    render_template, row_template_context, template_context,
+};
+
+pub struct RenderArgs {
+    pub template: PathBuf,
+    pub data: PathBuf,
+    /// JSON object of template parameters
+    pub params: Option<String>,
+    /// Render once per document with `doc` (as `template_mode: row`)
+    pub rows: bool,
+    pub template_debug: bool,
+}
+
+/// Results to render: one document list, or one per step
+#[derive(Debug, PartialEq)]
+enum RenderData {
+    Documents(Vec<Value>),
+    Steps(BTreeMap<String, Vec<Value>>),
+}
+
+pub fn run(args: RenderArgs) -> Result<()> {
+    let template = read_template(&args.template.to_string_lossy())?;
+    let params = parse_params(args.params.as_deref())?;
+    let data = load_data(&args.data)?;
+
+    let (rendered, context) = match data {
+        RenderData::Documents(documents) if args.rows => {
+            let rendered = render_rows(&template, &documents, &params);
+            let failed = rendered
+                .as_ref()
+                .err()
+                .and_then(|e| e.downcast_ref::<FailedRow>())
+                .and_then(|row| documents.get(row.0));
+            let context = row_template_context(failed.unwrap_or(&Value::Null), &params);
+            (rendered, context)
+        }
+        RenderData::Steps(_) if args.rows => {
+            bail!(
+                "--rows renders one document at a time and needs a document list, not step results"
+            )
+        }
+        RenderData::Documents(documents) => (
+            render_template(&template, &documents, &params),
+            template_context(&documents, &params),
+        ),
+        RenderData::Steps(steps) => (
+            render_multi_step_template(&template, &steps, &params),
+            multi_step_template_context(&steps, &params),
+        ),
+    };
+    match rendered {
+        Ok(rendered) => {
+            print!("{rendered}");
+            Ok(())
+        }
+        Err(e) if args.template_debug => {
+            Err(common::template_debug_failure(&template, &e, &context))
+        }
+        Err(e) => Err(e.context(format!(
+            "failed to render {} (add --template-debug for details)",
+            args.template.display()
+        ))),
+    }
+}
+
+/// Parse `--params` (a JSON object) into template variables.
+fn parse_params(params: Option<&str>) -> Result<BTreeMap<String, Value>> {
+    let Some(params) = params else {
+        return Ok(BTreeMap::new());
+    };
+    match serde_json::from_str(params).context("--params must be a JSON object")? {
+        Value::Object(map) => Ok(map.into_iter().collect()),
+        _ => bail!("--params must be a JSON object, e.g. '{{\"status\": \"shipped\"}}'"),
+    }
+}
+
+/// Load `--data`: an object whose values are all arrays is treated as
+/// multi-step results, anything else as a document list.
+fn load_data(path: &Path) -> Result<RenderData> {
+    let contents = std::fs::read_to_string(path)
+        .with_context(|| format!("failed to read {}", path.display()))?;
+    if let Ok(Value::Object(map)) = serde_json::from_str::<Value>(&contents)
+        && !map.is_empty()
+        && map.values().all(Value::is_array)
+    {
+        let steps = map
+            .into_iter()
+            .filter_map(|(step, docs)| match docs {
+                Value::Array(docs) => Some((step, docs)),
+                _ => None,
+            })
+            .collect();
+        return Ok(RenderData::Steps(steps));
+    }
+    Ok(RenderData::Documents(common::load_documents(path)?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_parse_params() {
+        assert!(parse_params(None).unwrap().is_empty());
+        let params = parse_params(Some(r#"{"status": "shipped", "limit": 10}"#)).unwrap();
+        assert_eq!(params["status"], json!("shipped"));
+        assert_eq!(params["limit"], json!(10));
+        assert!(parse_params(Some("[1, 2]")).is_err());
+        assert!(parse_params(Some("status=shipped")).is_err());
+    }
+
+    #[test]
+    fn test_load_data() {
+        let dir = tempfile::tempdir().unwrap();
+        let write = |name: &str, contents: &str| {
+            let path = dir.path().join(name);
+            std::fs::write(&path, contents).unwrap();
+            path
+        };
+
+        let array = write("array.json", r#"[{"id": "1"}, {"id": "2"}]"#);
+        assert_eq!(
+            load_data(&array).unwrap(),
+            RenderData::Documents(vec![json!({"id": "1"}), json!({"id": "2"})])
+        );
+
+        let ndjson = write("rows.ndjson", "{\"id\": \"1\"}\n{\"id\": \"2\"}\n");
+        assert_eq!(
+            load_data(&ndjson).unwrap(),
+            RenderData::Documents(vec![json!({"id": "1"}), json!({"id": "2"})])
+        );
+
+        let steps = write(
+            "steps.json",
+            r#"{"customer": [{"id": "c1"}], "orders": []}"#,
+        );
+        let RenderData::Steps(steps) = load_data(&steps).unwrap() else {
+            panic!("expected step results");
+        };
+        assert_eq!(steps["customer"], vec![json!({"id": "c1"})]);
+        assert!(steps["orders"].is_empty());
+
+        // A single document object is one row, not step results
+        let single = write("single.json", r#"{"id": "1", "tags": []}"#);
+        assert_eq!(
+            load_data(&single).unwrap(),
+            RenderData::Documents(vec![json!({"id": "1", "tags": []})])
+        );
+    }
+}
@@ -3,37 +3,143 @@
 //! Resolves parameters from CLI arguments or interactive prompts,
 //! validates them, and executes the query against Cosmos DB.
 
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, BTreeSet};
+use std::io::Write;
+use std::path::PathBuf;
+use std::time::Instant;
 
 use anyhow::{Context, Result, bail};
+use clap::ValueEnum;
 use colored::Colorize;
-use cosq_client::cosmos::CosmosClient;
+use cosq_client::cosmos::{CosmosClient, PriorityLevel, QueryResult};
+use cosq_core::chart::ChartSpec;
+use cosq_core::checks::{Check, parse_checks};
 use cosq_core::config::Config;
-use cosq_core::stored_query::{StoredQuery, find_stored_query, list_stored_queries};
-use inquire::{Confirm, Select, Text};
+use cosq_core::stored_query::{
+    HookInput, ParamDef, ParamType, QueryTarget, StoredQuery, StoredQueryError, TemplateMode,
+    find_stored_query, list_stored_queries,
+};
+use inquire::validator::Validation;
+use inquire::{Confirm, MultiSelect, Select, Text};
 use serde_json::Value;
 
-use super::common;
-use crate::output::{OutputFormat, render_multi_step_template, render_template, write_results};
-
+use super::hooks::HookPolicy;
+use super::pipeline::PipelineResult;
+use super::stream::StreamFormat;
+use super::{common, federated, session};
+use crate::builtin_templates::read_template;
+use crate::destination::{self, Destination};
+use crate::output::{
+    FailedRow, OutputFormat, RowLimit, multi_step_template_context, render_multi_step_template,
+    render_rows, render_template, row_template_context, template_context, write_chart,
+    write_prometheus, write_results,
+};
+
+#[derive(Clone)]
 pub struct RunArgs {
     pub name: Option<String>,
     pub params: Vec<String>,
+    /// Prompt for parameters not given on the command line (otherwise use
+    /// their defaults and fail on missing required ones)
+    pub prompt: bool,
     pub output: Option<OutputFormat>,
     pub db: Option<String>,
     pub container: Option<String>,
     pub template: Option<String>,
+    /// Explain template render errors instead of offering an AI fix
+    pub template_debug: bool,
+    /// `--chart` spec, rendered instead of the output format
+    pub chart: Option<String>,
+    pub hooks: HookPolicy,
+    pub notify: Option<String>,
+    pub out: Option<String>,
+    /// `--table` / `--replace` for a DuckDB `out` (the table defaults to the
+    /// query name)
+    pub table: Option<String>,
+    pub replace: bool,
+    /// Local SQL over the results (`--post-sql`), applied before `jq`
+    pub post_sql: Option<String>,
+    pub jq: Option<String>,
+    /// `--head` / `--tail`, applied after `jq`
+    pub limit: Option<RowLimit>,
+    pub priority: Option<PriorityLevel>,
+    pub targets: Vec<String>,
+    /// Read results from this JSON/NDJSON file instead of running the query
+    pub from_file: Option<PathBuf>,
+    /// Write each step's documents to `<dir>/<step>.json`
+    pub save_steps: Option<PathBuf>,
+    /// `--from-step` / `--steps-dir`: resume a multi-step query at a step,
+    /// loading the earlier steps' saved results
+    pub resume: Option<(String, PathBuf)>,
+    /// Print the execution plan instead of running the query
+    pub plan: bool,
+    /// Skip the confirmation for non-SELECT SQL on protected containers
+    pub force: bool,
+    /// Evaluate the query's `checks:` instead of printing its results
+    pub check: bool,
+    pub profile: Option<String>,
+    /// Mask fields matching the config's `redact:` patterns
+    pub redact: bool,
     pub quiet: bool,
 }
 
+/// What a run returned, for batch summaries
+pub struct RunSummary {
+    pub documents: usize,
+    pub request_charge: f64,
+}
+
 pub async fn run(args: RunArgs) -> Result<()> {
+    execute(args).await.map(|_| ())
+}
+
+/// What a run settled on before fetching results, shared by its fetch and output paths
+struct RunContext {
+    args: RunArgs,
+    query: StoredQuery,
+    config: Config,
+    /// The config is for a pinned account or profile, so picks are not saved
+    pinned: bool,
+    /// Preferences of the container a single-container query reads
+    view: Option<common::ContainerView>,
+    resolved: BTreeMap<String, Value>,
+    destination: Option<Destination>,
+    /// `-o` > front matter > `--template` > config, as the destination allows
+    format: OutputFormat,
+    chart: Option<ChartSpec>,
+    /// Results come from `--from-file` or the offline cache, so they are
+    /// neither charged nor cached
+    replayed: bool,
+}
+
+/// Results of a run, before they are written out
+enum Fetched {
+    /// Each step's documents of a multi-step query
+    Steps(PipelineResult),
+    /// A single-step query's documents
+    Rows(QueryResult),
+    /// A single-step query whose documents were already streamed out
+    Streamed(RunSummary),
+}
+
+/// Rendered output and raw JSON results, kept for post-run hooks and notifications
+struct RunOutput {
+    out: Vec<u8>,
+    raw_json: String,
+    templated: bool,
+    summary: RunSummary,
+}
+
+/// Run a stored query and report how many documents it returned and what it cost.
+pub async fn execute(args: RunArgs) -> Result<RunSummary> {
     // Resolve query: from name argument or interactive picker
-    let query = if let Some(ref name) = args.name {
+    let mut query = if let Some(ref name) = args.name {
         find_stored_query(name)
             .map_err(|e| anyhow::anyhow!("Failed to load query '{name}': {e}"))?
     } else {
         pick_query_interactive()?
     };
+    common::decrypt_params(&mut query)?;
 
     if !args.quiet {
         eprintln!("{} {}", "Running:".bold(), query.name.cyan());
@@ -42,156 +148,975 @@ pub async fn run(args: RunArgs) -> Result<()> {
         }
     }
 
+    let destination = args
+        .out
+        .as_deref()
+        .map(|out| {
+            Destination::parse(out)?.with_table(args.table.clone(), args.replace, &query.name)
+        })
+        .transpose()?;
+
     // Parse CLI params (--key value pairs from the raw args)
     let cli_params = parse_cli_params(&args.params)?;
 
-    // Resolve parameters: CLI > interactive > default
-    let resolved = resolve_params_interactive(&query, &cli_params)?;
+    // Load config for connection details (a pinned account/profile overrides the default)
+    let (config, pinned) = query_config(Config::load()?, &query, args.profile.as_deref())?;
+    if pinned && !args.quiet {
+        eprintln!(
+            "  {} {}",
+            "Account:".dimmed(),
+            config.account.name.as_str().cyan()
+        );
+    }
 
-    // Load config for connection details
-    let mut config = Config::load()?;
-    let client = CosmosClient::new(&config.account.endpoint).await?;
+    // Preferences of the container a single-container query reads
+    let view = common::ContainerView::for_query(
+        &config,
+        &query,
+        args.db.as_deref(),
+        args.container.as_deref(),
+        &args.targets,
+    );
 
-    let (database, db_changed) = common::resolve_database(
-        &client,
-        &mut config,
-        args.db,
-        query.metadata.database.as_deref(),
-    )
-    .await?;
+    // Resolve parameters: CLI > interactive > default (`default_cmd:` > `default:`)
+    let resolved = resolve_params_interactive(
+        &query,
+        &cli_params,
+        &config.trusted_commands,
+        args.hooks,
+        args.prompt,
+    )?;
+
+    let mut format = effective_output(
+        args.output.clone(),
+        args.template.is_some(),
+        &query,
+        common::configured_output(&config, view.as_ref()),
+    )?;
+    if let Some(ref destination) = destination {
+        format = destination.output_format(args.output.as_ref(), format)?;
+    }
 
-    if query.is_multi_step() {
-        // Multi-step execution: resolve database only (containers are per-step)
-        if db_changed {
-            config.save()?;
+    // Warn about template variables that can never be defined before running
+    if !args.quiet && format == OutputFormat::Template {
+        if let Ok(Some(template)) = resolve_template_str(&args.template, &query) {
+            for warning in crate::template_lint::lint_template(&template, &query) {
+                eprintln!(
+                    "  {} {}: {}",
+                    "Template warning:".yellow(),
+                    warning.rule.cyan(),
+                    warning.message
+                );
+                eprintln!("    {} {}", "fix:".dimmed(), warning.suggestion);
+            }
         }
+    }
 
+    if args.plan {
+        let database = args
+            .db
+            .as_deref()
+            .or(query.metadata.database.as_deref())
+            .or(config.database.as_deref());
+        let container = args
+            .container
+            .as_deref()
+            .or(query.metadata.container.as_deref())
+            .or(config.container.as_deref());
+        print!(
+            "{}",
+            super::pipeline::render_plan(&query, &resolved, database, container)?
+        );
         if !args.quiet {
-            eprintln!("{}", "Executing steps:".dimmed());
+            eprintln!("\n{}", "Plan only — no requests were sent.".dimmed());
+        }
+        return Ok(RunSummary {
+            documents: 0,
+            request_charge: 0.0,
+        });
+    }
+
+    // `--check`: parse the assertions before spending RUs on the query
+    let checks = if args.check {
+        if query.metadata.checks.is_empty() {
+            bail!(
+                "query '{}' has no `checks:` to evaluate; add e.g. `checks: [\"row_count > 0\"]` to its front matter",
+                query.name
+            );
+        }
+        if query.is_multi_step() {
+            bail!("--check needs a single-step query");
+        }
+        Some(parse_checks(&query.metadata.checks)?)
+    } else {
+        None
+    };
+    let chart = chart_spec(&args, &query)?;
+    if args.post_sql.is_some() && query.is_multi_step() {
+        bail!("--post-sql needs a single-step query");
+    }
+    if matches!(destination, Some(Destination::DuckDb(_))) && query.is_multi_step() {
+        bail!("DuckDB destinations need a single-step query");
+    }
+
+    // Federated targets: --target flags > `targets:` in front matter
+    let targets = federated::parse_targets(if args.targets.is_empty() {
+        &query.metadata.targets
+    } else {
+        &args.targets
+    })?;
+    if !query.is_multi_step() {
+        if args.save_steps.is_some() {
+            bail!("--save-steps needs a multi-step query (one with `steps:`)");
         }
+        if args.resume.is_some() {
+            bail!("--from-step needs a multi-step query (one with `steps:`)");
+        }
+    }
+    let preloaded = match args.resume {
+        Some((ref step, ref dir)) => {
+            if common::is_offline() {
+                bail!("--from-step is not supported with --offline");
+            }
+            let loaded = super::pipeline::load_steps(&query, step, dir)?;
+            if !args.quiet && !loaded.is_empty() {
+                let names: Vec<&str> = loaded.keys().map(String::as_str).collect();
+                eprintln!(
+                    "  {} {} (from {})",
+                    "Loaded:".dimmed(),
+                    names.join(", "),
+                    dir.display()
+                );
+            }
+            Some(loaded)
+        }
+        None => None,
+    };
+    if !targets.is_empty() && query.is_multi_step() {
+        bail!("targets are not supported for multi-step queries");
+    }
 
-        let pipeline_result =
-            super::pipeline::execute(&client, &database, &query, &resolved, args.quiet).await?;
+    let replayed = args.from_file.is_some() || common::is_offline();
+    let mut run = RunContext {
+        args,
+        query,
+        config,
+        pinned,
+        view,
+        resolved,
+        destination,
+        format,
+        chart,
+        replayed,
+    };
 
-        // Output multi-step results
-        let has_template = args.template.is_some()
-            || query.metadata.template.is_some()
-            || query.metadata.template_file.is_some();
+    let started = Instant::now();
+    let fetched = if run.replayed {
+        run.fetch_replayed(&targets)?
+    } else if !targets.is_empty() {
+        run.fetch_targets(&targets).await?
+    } else {
+        run.fetch_live(preloaded, started).await?
+    };
 
-        let effective_output = args.output.unwrap_or(if has_template {
-            OutputFormat::Template
-        } else {
-            OutputFormat::Json
-        });
+    let elapsed = started.elapsed();
+    let output = match fetched {
+        Fetched::Streamed(summary) => return Ok(summary),
+        Fetched::Steps(result) => run.write_steps(result, elapsed).await?,
+        Fetched::Rows(result) => match checks {
+            Some(ref checks) => return run.finish_checks(checks, &result, elapsed),
+            None => run.write_rows(result, elapsed).await?,
+        },
+    };
+    run.after_run(output).await
+}
 
-        match effective_output {
-            OutputFormat::Template => {
-                let template_str = resolve_template_str(&args.template, &query)?;
-                if let Some(tmpl) = template_str {
-                    // Flatten all step results for rendering recovery
-                    let all_docs: Vec<Value> = pipeline_result
-                        .step_results
-                        .values()
-                        .flat_map(|v| v.clone())
-                        .collect();
-                    match render_multi_step_template(
-                        &tmpl,
-                        &pipeline_result.step_results,
-                        &resolved,
-                    ) {
-                        Ok(rendered) => print!("{rendered}"),
-                        Err(_) => {
-                            let rendered =
-                                render_with_ai_recovery(&tmpl, &all_docs, &resolved, &query)
-                                    .await?;
-                            print!("{rendered}");
-                        }
-                    }
-                } else {
-                    // No template — output all step results as JSON
-                    let combined: serde_json::Value =
-                        serde_json::to_value(&pipeline_result.step_results)?;
-                    let json = serde_json::to_string_pretty(&combined)?;
-                    println!("{json}");
-                }
-            }
-            _ => {
-                // For non-template formats, combine all step results
-                let combined: serde_json::Value =
-                    serde_json::to_value(&pipeline_result.step_results)?;
-                let json = serde_json::to_string_pretty(&combined)?;
-                println!("{json}");
+impl RunContext {
+    /// Results saved earlier: the `--from-file` file, or the `--offline` cache.
+    fn fetch_replayed(&self, targets: &[QueryTarget]) -> Result<Fetched> {
+        let (args, query, config) = (&self.args, &self.query, &self.config);
+        if let Some(ref path) = args.from_file {
+            if query.is_multi_step() {
+                bail!("--from-file is not supported for multi-step queries");
             }
+            return Ok(Fetched::Rows(common::replay_file(path, args.quiet)?));
         }
+        if !targets.is_empty() {
+            bail!("targets are not supported with --offline");
+        }
+        let database = common::resolve_offline(
+            "db",
+            args.db.clone(),
+            query.metadata.database.as_deref(),
+            config.database.as_deref(),
+        )?;
+        if query.is_multi_step() {
+            let key = common::cache_key(config, &database, "", &steps_sql(query)?, &self.resolved)?;
+            let cached = common::cached_results(&key, args.quiet)?;
+            return Ok(Fetched::Steps(PipelineResult {
+                step_results: cached.steps,
+                total_charge: cached.request_charge,
+                partial: false,
+            }));
+        }
+        let container = common::resolve_offline(
+            "container",
+            args.container.clone(),
+            query.metadata.container.as_deref(),
+            config.container.as_deref(),
+        )?;
+        let cosmos_params = StoredQuery::build_cosmos_params(&self.resolved);
+        let key = common::cache_key(config, &database, &container, &query.sql, &cosmos_params)?;
+        let cached = common::cached_results(&key, args.quiet)?;
+        Ok(Fetched::Rows(QueryResult {
+            documents: cached.documents,
+            request_charge: cached.request_charge,
+            partitions: Vec::new(),
+            partial: false,
+        }))
+    }
 
+    /// Run a single-step query against each federated target and merge the results.
+    async fn fetch_targets(&self, targets: &[QueryTarget]) -> Result<Fetched> {
+        let (args, query) = (&self.args, &self.query);
         if !args.quiet {
-            eprintln!(
-                "\n{} {:.2} RUs",
-                "Request charge:".dimmed(),
-                pipeline_result.total_charge
-            );
+            eprintln!("{}", "Querying targets:".dimmed());
         }
-    } else {
-        // Single-step execution (original path)
+        let result = federated::execute(
+            &self.config,
+            targets,
+            federated::FederatedQuery {
+                sql: &query.sql,
+                parameters: StoredQuery::build_cosmos_params(&self.resolved),
+                db: args.db.as_deref(),
+                container: args.container.as_deref(),
+                default_db: query.metadata.database.as_deref(),
+                default_container: query.metadata.container.as_deref(),
+                priority: args.priority,
+                force: args.force,
+                interactive: args.prompt,
+                quiet: args.quiet,
+                cache_plans: true,
+            },
+        )
+        .await?;
+        Ok(Fetched::Rows(result))
+    }
+
+    /// Query the account, resolving (and remembering) the database and container.
+    async fn fetch_live(
+        &mut self,
+        preloaded: Option<BTreeMap<String, Vec<Value>>>,
+        started: Instant,
+    ) -> Result<Fetched> {
+        let mut client = common::with_plan_cache(common::connect(&self.config).await?);
+        if let Some(level) = self.args.priority {
+            client = client.with_priority(level);
+        }
+
+        let (database, db_changed) = common::resolve_database(
+            &client,
+            &mut self.config,
+            self.args.db.clone(),
+            self.query.metadata.database.as_deref(),
+        )
+        .await?;
+
+        if self.query.is_multi_step() {
+            // Multi-step execution: resolve database only (containers are per-step)
+            if db_changed && !self.pinned {
+                self.config.save()?;
+            }
+            return self.fetch_steps(client, &database, preloaded).await;
+        }
+
         let (container, ctr_changed) = common::resolve_container(
             &client,
-            &mut config,
+            &mut self.config,
             &database,
-            args.container,
-            query.metadata.container.as_deref(),
+            self.args.container.clone(),
+            self.query.metadata.container.as_deref(),
         )
         .await?;
+        if (db_changed || ctr_changed) && !self.pinned {
+            self.config.save()?;
+        }
 
-        if db_changed || ctr_changed {
-            config.save()?;
+        common::guard_protected(
+            &self.config,
+            &database,
+            &container,
+            &self.query.sql,
+            self.args.force,
+            self.args.prompt,
+        )?;
+        common::warn_if_analytical(
+            &client,
+            &database,
+            &container,
+            &self.query.sql,
+            self.args.quiet,
+        )
+        .await;
+        match self.stream_format()? {
+            Some(format) => {
+                self.stream_rows(client, &database, &container, format, started)
+                    .await
+            }
+            None => self.query_rows(client, &database, &container).await,
         }
+    }
+
+    /// Run each step of a multi-step query, caching the results of a complete run.
+    async fn fetch_steps(
+        &self,
+        client: CosmosClient,
+        database: &str,
+        preloaded: Option<BTreeMap<String, Vec<Value>>>,
+    ) -> Result<Fetched> {
+        let (args, query, config) = (&self.args, &self.query, &self.config);
+        if !args.quiet {
+            eprintln!("{}", "Executing steps:".dimmed());
+        }
+
+        if let Some(ref steps) = query.metadata.steps {
+            for step in steps {
+                let sql = &query.step_queries[&step.name];
+                common::guard_protected(
+                    config,
+                    database,
+                    &step.container,
+                    sql,
+                    args.force,
+                    args.prompt,
+                )
+                .with_context(|| format!("step '{}'", step.name))?;
+            }
+        }
+
+        let interrupt = common::Interrupt::install();
+        let client = client.with_cancellation(interrupt.flag());
+        let resumed = preloaded.is_some();
+        let result = super::pipeline::execute(
+            &client,
+            database,
+            query,
+            &self.resolved,
+            preloaded.unwrap_or_default(),
+            args.quiet,
+        )
+        .await?;
+        interrupt.finish();
+        // Loaded steps may be stale, so a resumed run is not cached
+        if !result.partial && !resumed {
+            let key = common::cache_key(config, database, "", &steps_sql(query)?, &self.resolved)?;
+            common::cache_results(
+                &key,
+                result.total_charge,
+                Vec::new(),
+                result.step_results.clone(),
+            );
+        }
+        Ok(Fetched::Steps(result))
+    }
+
+    /// Write a single-step query's documents out page by page as they arrive.
+    async fn stream_rows(
+        &self,
+        client: CosmosClient,
+        database: &str,
+        container: &str,
+        format: StreamFormat,
+        started: Instant,
+    ) -> Result<Fetched> {
+        let (query, config) = (&self.query, &self.config);
+        let interrupt = common::Interrupt::install();
+        let streamed = super::stream::execute(
+            &client.with_cancellation(interrupt.flag()),
+            database,
+            container,
+            &query.sql,
+            StoredQuery::build_cosmos_params(&self.resolved),
+            format,
+            common::output_redactor(config, self.args.redact),
+            stream_writer(self.destination.as_ref())?,
+        )
+        .await;
+        interrupt.finish();
+        let streamed = streamed?;
+        let result = match streamed.result {
+            Ok(result) => result,
+            Err(e) => {
+                return Err(common::advise_on_query_error(
+                    e, config, database, container, &query.sql,
+                )
+                .await);
+            }
+        };
+        self.finish_streamed(
+            &result,
+            streamed.written,
+            &streamed.dropped_columns,
+            started.elapsed(),
+        )?;
+        Ok(Fetched::Streamed(RunSummary {
+            documents: streamed.written,
+            request_charge: result.request_charge,
+        }))
+    }
 
-        let cosmos_params = StoredQuery::build_cosmos_params(&resolved);
+    /// Fetch all of a single-step query's documents, caching a complete result.
+    async fn query_rows(
+        &self,
+        client: CosmosClient,
+        database: &str,
+        container: &str,
+    ) -> Result<Fetched> {
+        let (query, config) = (&self.query, &self.config);
+        let cosmos_params = StoredQuery::build_cosmos_params(&self.resolved);
+        let key = common::cache_key(config, database, container, &query.sql, &cosmos_params)?;
+        let interrupt = common::Interrupt::install();
         let result = client
-            .query_with_params(&database, &container, &query.sql, cosmos_params)
-            .await?;
+            .with_cancellation(interrupt.flag())
+            .query_with_params(database, container, &query.sql, cosmos_params)
+            .await;
+        interrupt.finish();
+        let result = match result {
+            Ok(result) => result,
+            Err(e) => {
+                return Err(common::advise_on_query_error(
+                    e, config, database, container, &query.sql,
+                )
+                .await);
+            }
+        };
+        if !result.partial {
+            common::cache_results(
+                &key,
+                result.request_charge,
+                result.documents.clone(),
+                BTreeMap::new(),
+            );
+        }
+        Ok(Fetched::Rows(result))
+    }
 
-        let has_template = args.template.is_some()
-            || query.metadata.template.is_some()
-            || query.metadata.template_file.is_some();
+    /// Write out a multi-step query's results, keyed by step name.
+    async fn write_steps(
+        &self,
+        mut result: PipelineResult,
+        elapsed: std::time::Duration,
+    ) -> Result<RunOutput> {
+        let (args, query, config) = (&self.args, &self.query, &self.config);
+        let fetched = result.step_results.values().map(Vec::len).sum();
+        let redactor = common::output_redactor(config, args.redact);
+        for documents in result.step_results.values_mut() {
+            redactor.redact_all(documents);
+        }
+        if let Some(ref dir) = args.save_steps {
+            let written = super::pipeline::save_steps(dir, &result.step_results)?;
+            if !args.quiet {
+                eprintln!(
+                    "{} {} step file{} to {}",
+                    "Saved".dimmed(),
+                    written.len(),
+                    if written.len() == 1 { "" } else { "s" },
+                    dir.display()
+                );
+            }
+        }
+        if let Some(ref filter) = args.jq {
+            result.step_results =
+                crate::jq::apply_steps(filter, std::mem::take(&mut result.step_results))?;
+        }
+        for documents in result.step_results.values_mut() {
+            common::apply_row_limit(args.limit, documents, args.quiet);
+        }
 
-        let effective_output = args.output.unwrap_or(if has_template {
-            OutputFormat::Template
+        let mut out = Vec::new();
+        let mut templated = false;
+        let raw_json = serde_json::to_string_pretty(&result.step_results)?;
+        let template = match self.format {
+            OutputFormat::Template => resolve_template_str(&args.template, query)?,
+            _ => None,
+        };
+        if let Some(tmpl) = template {
+            templated = true;
+            match render_multi_step_template(&tmpl, &result.step_results, &self.resolved) {
+                Ok(rendered) => out.extend_from_slice(rendered.as_bytes()),
+                Err(e) if args.template_debug => {
+                    return Err(common::template_debug_failure(
+                        &tmpl,
+                        &e,
+                        &multi_step_template_context(&result.step_results, &self.resolved),
+                    ));
+                }
+                Err(_) => {
+                    // Flatten all step results for rendering recovery
+                    let all_docs: Vec<Value> = result
+                        .step_results
+                        .values()
+                        .flat_map(|v| v.clone())
+                        .collect();
+                    let rendered =
+                        render_with_ai_recovery(&tmpl, &all_docs, &self.resolved, query).await?;
+                    out.extend_from_slice(rendered.as_bytes());
+                }
+            }
         } else {
-            OutputFormat::Json
-        });
+            // Other formats (or no template) print all step results as JSON
+            writeln!(out, "{raw_json}")?;
+        }
+        destination::emit(&out, self.destination.as_ref(), config, args.quiet).await?;
+
+        if result.partial {
+            return Err(common::partial_results(
+                config,
+                &query.name,
+                fetched,
+                result.total_charge,
+                args.quiet,
+            ));
+        }
+        if !self.replayed {
+            common::report_charge(config, &query.name, result.total_charge, args.quiet);
+            common::warn_if_slow(config, &query.name, result.total_charge, elapsed, &[]);
+        }
+        Ok(RunOutput {
+            out,
+            raw_json,
+            templated,
+            summary: RunSummary {
+                documents: result.step_results.values().map(Vec::len).sum(),
+                request_charge: result.total_charge,
+            },
+        })
+    }
 
-        match effective_output {
-            OutputFormat::Template => {
-                let template_str = resolve_template_str(&args.template, &query)?;
-                if let Some(tmpl) = template_str {
-                    let rendered =
-                        render_with_ai_recovery(&tmpl, &result.documents, &resolved, &query)
-                            .await?;
-                    print!("{rendered}");
-                } else {
-                    write_results(
-                        &mut std::io::stdout(),
-                        &result.documents,
-                        &OutputFormat::Json,
-                    )?;
+    /// Shape a single-step query's documents (`--post-sql`, `--jq`, container
+    /// preferences, `--head`/`--tail`) and write them as a chart or in the output format.
+    async fn write_rows(
+        &self,
+        mut result: QueryResult,
+        elapsed: std::time::Duration,
+    ) -> Result<RunOutput> {
+        let (args, query, config) = (&self.args, &self.query, &self.config);
+        let (view, resolved) = (self.view.as_ref(), &self.resolved);
+        let fetched = result.documents.len();
+        common::output_redactor(config, args.redact).redact_all(&mut result.documents);
+        if let Some(ref sql) = args.post_sql {
+            result.documents =
+                crate::columnar::post_sql(sql, std::mem::take(&mut result.documents)).await?;
+        }
+        if let Some(ref filter) = args.jq {
+            result.documents = crate::jq::apply(filter, std::mem::take(&mut result.documents))?;
+        }
+        if let Some(view) = view {
+            view.apply(&self.format, &query.sql, &mut result.documents);
+        }
+        common::apply_row_limit(args.limit, &mut result.documents, args.quiet);
+        common::fit_table(
+            view,
+            &self.format,
+            self.destination.is_none(),
+            args.quiet,
+            &mut result.documents,
+        )?;
+
+        let mut out = Vec::new();
+        let mut templated = false;
+        let raw_json = serde_json::to_string_pretty(&result.documents)?;
+        if let Some(ref spec) = self.chart {
+            let svg = self.destination.as_ref().is_some_and(Destination::is_svg);
+            write_chart(&mut out, &result.documents, spec, svg, args.quiet)?;
+        } else {
+            match self.format {
+                OutputFormat::Template => {
+                    let template_str = resolve_template_str(&args.template, query)?;
+                    if let Some(tmpl) = template_str {
+                        templated = true;
+                        let rendered = if query.metadata.template_mode == Some(TemplateMode::Row) {
+                            render_rows(&tmpl, &result.documents, resolved).map_err(|e| {
+                                if !args.template_debug {
+                                    return e;
+                                }
+                                let doc = e
+                                    .downcast_ref::<FailedRow>()
+                                    .and_then(|row| result.documents.get(row.0))
+                                    .unwrap_or(&Value::Null);
+                                common::template_debug_failure(
+                                    &tmpl,
+                                    &e,
+                                    &row_template_context(doc, resolved),
+                                )
+                            })?
+                        } else if args.template_debug {
+                            render_template(&tmpl, &result.documents, resolved).map_err(|e| {
+                                common::template_debug_failure(
+                                    &tmpl,
+                                    &e,
+                                    &template_context(&result.documents, resolved),
+                                )
+                            })?
+                        } else {
+                            render_with_ai_recovery(&tmpl, &result.documents, resolved, query)
+                                .await?
+                        };
+                        out.extend_from_slice(rendered.as_bytes());
+                    } else {
+                        write_results(&mut out, &result.documents, &OutputFormat::Json)?;
+                    }
+                }
+                OutputFormat::Prom => {
+                    let prom = query.metadata.prometheus.clone().unwrap_or_default();
+                    write_prometheus(&mut out, &result.documents, &prom)?;
+                }
+                _ => {
+                    write_results(&mut out, &result.documents, &self.format)?;
                 }
             }
-            _ => {
-                write_results(&mut std::io::stdout(), &result.documents, &effective_output)?;
+        }
+        destination::emit(&out, self.destination.as_ref(), config, args.quiet).await?;
+
+        if result.partial {
+            return Err(common::partial_results(
+                config,
+                &query.name,
+                fetched,
+                result.request_charge,
+                args.quiet,
+            ));
+        }
+        if !self.replayed {
+            if let Some(view) = view {
+                view.record_query(&query.name);
             }
+            common::report_charge(config, &query.name, result.request_charge, args.quiet);
+            common::warn_if_slow(
+                config,
+                &query.name,
+                result.request_charge,
+                elapsed,
+                &result.partitions,
+            );
         }
+        Ok(RunOutput {
+            out,
+            raw_json,
+            templated,
+            summary: RunSummary {
+                documents: result.documents.len(),
+                request_charge: result.request_charge,
+            },
+        })
+    }
 
-        if !args.quiet {
-            eprintln!(
-                "\n{} {:.2} RUs",
-                "Request charge:".dimmed(),
-                result.request_charge
+    /// Run the query's post-run hook and send its notification.
+    async fn after_run(&self, output: RunOutput) -> Result<RunSummary> {
+        let (args, query) = (&self.args, &self.query);
+        if let Some(hook) = query
+            .metadata
+            .hooks
+            .as_ref()
+            .and_then(|h| h.post_run.as_ref())
+        {
+            let input = match hook.input {
+                HookInput::Output => output.out.as_slice(),
+                HookInput::Json => output.raw_json.as_bytes(),
+            };
+            super::hooks::run_post_run(hook, input, args.hooks, args.quiet)?;
+        }
+
+        // Notify: --notify flag > `notify:` in front matter
+        if let Some(target) = self.notify_target() {
+            let webhook = super::notify::resolve_webhook(target, &self.config)?;
+            let message = if output.templated {
+                String::from_utf8_lossy(&output.out).into_owned()
+            } else {
+                super::notify::summary(
+                    &query.name,
+                    output.summary.documents,
+                    output.summary.request_charge,
+                )
+            };
+            super::notify::send(&webhook, &message).await?;
+            if !args.quiet {
+                eprintln!("{} {}", "Notified:".dimmed(), target.cyan());
+            }
+        }
+        Ok(output.summary)
+    }
+
+    /// The `--notify` target, else the query's `notify:`.
+    fn notify_target(&self) -> Option<&str> {
+        self.args
+            .notify
+            .as_deref()
+            .or(self.query.metadata.notify.as_deref())
+    }
+
+    /// The streamed form of a single-step run's output, when nothing needs the
+    /// whole result set: compact JSON, CSV, or a row template, without `--check`,
+    /// `--chart`, `--post-sql`, `--jq`, `--head`/`--tail`, a post-run hook, a
+    /// notification, a blob or DuckDB destination, `--template-debug`, or
+    /// container preferences that sort or pick CSV columns.
+    /// Streamed results are not cached for `--offline`.
+    fn stream_format(&self) -> Result<Option<StreamFormat>> {
+        let args = &self.args;
+        let needs_all = args.check
+            || self.chart.is_some()
+            || args.post_sql.is_some()
+            || args.jq.is_some()
+            || args.limit.is_some()
+            || self.notify_target().is_some()
+            || self
+                .query
+                .metadata
+                .hooks
+                .as_ref()
+                .is_some_and(|h| h.post_run.is_some())
+            || matches!(
+                self.destination,
+                Some(Destination::AzBlob(_) | Destination::DuckDb(_))
+            )
+            || self.view.as_ref().is_some_and(|v| v.shapes(&self.format));
+        if needs_all {
+            return Ok(None);
+        }
+        Ok(match self.format {
+            OutputFormat::JsonCompact => Some(StreamFormat::JsonLines),
+            OutputFormat::Csv => Some(StreamFormat::Csv),
+            OutputFormat::Template
+                if self.query.metadata.template_mode == Some(TemplateMode::Row)
+                    && !args.template_debug =>
+            {
+                resolve_template_str(&args.template, &self.query)?.map(|template| {
+                    StreamFormat::Rows {
+                        template,
+                        params: self.resolved.clone(),
+                    }
+                })
+            }
+            _ => None,
+        })
+    }
+
+    /// Report on a streamed run as the buffered path does after printing.
+    fn finish_streamed(
+        &self,
+        result: &QueryResult,
+        written: usize,
+        dropped_columns: &BTreeSet<String>,
+        elapsed: std::time::Duration,
+    ) -> Result<()> {
+        let (config, name, quiet) = (&self.config, &self.query.name, self.args.quiet);
+        if !quiet {
+            if !dropped_columns.is_empty() {
+                let dropped: Vec<&str> = dropped_columns.iter().map(String::as_str).collect();
+                eprintln!(
+                    "{} CSV columns are taken from the first page; left out: {}",
+                    "Note:".yellow().bold(),
+                    dropped.join(", ")
+                );
+            }
+            if let Some(Destination::File(path)) = self.destination.as_ref() {
+                eprintln!(
+                    "{} {}",
+                    "Wrote output to".dimmed(),
+                    path.display().to_string().cyan()
+                );
+            }
+        }
+        if result.partial {
+            return Err(common::partial_results(
+                config,
+                name,
+                written,
+                result.request_charge,
+                quiet,
+            ));
+        }
+        if let Some(ref view) = self.view {
+            view.record_query(name);
+        }
+        common::report_charge(config, name, result.request_charge, quiet);
+        common::warn_if_slow(
+            config,
+            name,
+            result.request_charge,
+            elapsed,
+            &result.partitions,
+        );
+        Ok(())
+    }
+
+    /// Evaluate `checks` against a single-step run's results and print a line per
+    /// check; fails (naming the failed checks) when any does not hold.
+    fn finish_checks(
+        &self,
+        checks: &[Check],
+        result: &QueryResult,
+        elapsed: std::time::Duration,
+    ) -> Result<RunSummary> {
+        let (config, name, quiet) = (&self.config, &self.query.name, self.args.quiet);
+        if result.partial {
+            // Checks over part of the results would be meaningless
+            return Err(common::partial_results(
+                config,
+                name,
+                result.documents.len(),
+                result.request_charge,
+                quiet,
+            ));
+        }
+        let outcomes: Vec<_> = checks
+            .iter()
+            .map(|check| check.evaluate(&result.documents))
+            .collect();
+        if !quiet {
+            for outcome in &outcomes {
+                let mark = if outcome.passed {
+                    "✓".green()
+                } else {
+                    "✗".red()
+                };
+                println!(
+                    "  {mark} {}  {}",
+                    outcome.check,
+                    format!("({})", outcome.detail).dimmed()
+                );
+            }
+        }
+        if !self.replayed {
+            common::report_charge(config, name, result.request_charge, quiet);
+            common::warn_if_slow(
+                config,
+                name,
+                result.request_charge,
+                elapsed,
+                &result.partitions,
             );
         }
+
+        let failed: Vec<String> = outcomes
+            .iter()
+            .filter(|outcome| !outcome.passed)
+            .map(|outcome| format!("{} ({})", outcome.check, outcome.detail))
+            .collect();
+        if !failed.is_empty() {
+            bail!(
+                "{} of {} checks failed for '{name}': {}",
+                failed.len(),
+                outcomes.len(),
+                failed.join("; ")
+            );
+        }
+        Ok(RunSummary {
+            documents: result.documents.len(),
+            request_charge: result.request_charge,
+        })
     }
+}
 
-    Ok(())
+/// The SQL of every step of a multi-step query, for its result cache key.
+fn steps_sql(query: &StoredQuery) -> Result<String> {
+    Ok(serde_json::to_string(&query.metadata.steps)?)
+}
+
+/// Format hint shown under free-text prompts for structured parameter types.
+fn input_help(param_type: &ParamType) -> Option<&'static str> {
+    match param_type {
+        ParamType::Array => Some("Comma-separated values or a JSON array"),
+        ParamType::Uuid => Some("e.g. 3f2504e0-4f89-11d3-9a0c-0305e82c3301"),
+        ParamType::Object => Some(r#"JSON, e.g. {"status": "open"}"#),
+        _ => None,
+    }
+}
+
+/// Where streamed output goes: the `--out` file, or stdout.
+fn stream_writer(destination: Option<&Destination>) -> Result<Box<dyn Write + Send>> {
+    Ok(match destination {
+        Some(Destination::File(path)) => Box::new(destination::create_file(path)?),
+        _ => Box::new(std::io::stdout()),
+    })
+}
+
+/// File extension for results written in `format`.
+pub fn extension(format: &OutputFormat) -> &'static str {
+    match format {
+        OutputFormat::Json => "json",
+        OutputFormat::JsonCompact => "ndjson",
+        OutputFormat::Table | OutputFormat::Template => "txt",
+        OutputFormat::Csv => "csv",
+        OutputFormat::Prom => "prom",
+        OutputFormat::Arrow => "arrow",
+    }
+}
+
+/// Apply the `--profile` flag, or the query's `profile:`/`account:` pin, to the
+/// loaded config.
+///
+/// Returns the config to use and whether it differs from the default account
+/// (in which case picker choices must not be saved back to the config file).
+pub fn query_config(
+    config: Config,
+    query: &StoredQuery,
+    cli_profile: Option<&str>,
+) -> Result<(Config, bool)> {
+    if let Some(profile) = cli_profile {
+        return Ok((config.for_profile(profile)?, true));
+    }
+    if let Some(ref profile) = query.metadata.profile {
+        return Ok((config.for_profile(profile)?, true));
+    }
+    match query.metadata.account {
+        Some(ref account) if *account != config.account.name => {
+            Ok((config.for_account(account), true))
+        }
+        _ => Ok((config, false)),
+    }
+}
+
+/// Pick the output format: `-o` flag > `output:` in front matter > template if one
+/// is available > the configured default (see `common::configured_output`) > JSON.
+pub fn effective_output(
+    cli_output: Option<OutputFormat>,
+    cli_template: bool,
+    query: &StoredQuery,
+    configured: Option<(&str, &str)>,
+) -> Result<OutputFormat> {
+    if let Some(format) = cli_output {
+        return Ok(format);
+    }
+
+    if let Some(ref name) = query.metadata.output {
+        return parse_output_format(name, &format!("query '{}'", query.name));
+    }
+
+    let has_template =
+        cli_template || query.metadata.template.is_some() || query.metadata.template_file.is_some();
+    if has_template {
+        return Ok(OutputFormat::Template);
+    }
+    match configured {
+        Some((name, origin)) => parse_output_format(name, origin),
+        None => Ok(OutputFormat::Json),
+    }
+}
+
+/// Parse an output format name from a query or the config.
+pub fn parse_output_format(name: &str, origin: &str) -> Result<OutputFormat> {
+    OutputFormat::from_str(name, true).map_err(|_| {
+        let valid: Vec<String> = OutputFormat::value_variants()
+            .iter()
+            .filter_map(|v| v.to_possible_value())
+            .map(|v| v.get_name().to_string())
+            .collect();
+        anyhow::anyhow!(
+            "Invalid output format '{name}' in {origin} (expected one of: {})",
+            valid.join(", ")
+        )
+    })
 }
 
 /// Attempt to render a template, and if it fails, offer AI-assisted fix.
@@ -208,8 +1133,8 @@ async fn render_with_ai_recovery(
             let error_msg = format!("{e}");
             eprintln!("\n{} {}", "Template error:".red().bold(), error_msg);
 
-            // Check if AI is configured
-            if crate::commands::ai::is_ai_active() {
+            // Check if AI is configured (and reachable)
+            if crate::commands::ai::is_ai_active() && !common::is_offline() {
                 let fix = Confirm::new("Would you like AI to fix this?")
                     .with_default(true)
                     .prompt()
@@ -251,10 +1176,14 @@ async fn fix_template_with_ai(
         .dimmed()
     );
 
-    let sample = if documents.is_empty() {
-        "(no documents)".to_string()
-    } else {
-        serde_json::to_string_pretty(&documents[0]).unwrap_or_default()
+    let sample = match Config::load() {
+        _ if documents.is_empty() => "(no documents)".to_string(),
+        Ok(config) => match common::ai_documents(&config, &documents[..1]) {
+            Some(first) => serde_json::to_string_pretty(&first[0]).unwrap_or_default(),
+            None => common::ai_schema_summary(documents),
+        },
+        Err(_) if common::is_no_ai_data() => common::ai_schema_summary(documents),
+        Err(_) => serde_json::to_string_pretty(&documents[0]).unwrap_or_default(),
     };
 
     let system_prompt = format!(
@@ -269,7 +1198,7 @@ async fn fix_template_with_ai(
         "This template has an error:\n\n{broken_template}\n\nError: {error_msg}\n\nFix the template."
     );
 
-    let response = cosq_client::ai::generate_text(&system_prompt, &user_prompt)
+    let response = crate::commands::ai::generate("template fix", &system_prompt, &user_prompt)
         .await
         .context("AI fix failed")?;
 
@@ -286,17 +1215,10 @@ async fn fix_template_with_ai(
         Ok(rendered) => {
             eprintln!("{} Template fixed successfully.", "OK".green().bold());
 
-            // Offer to save the fix
+            // Offer to save the fix (subject to `ai_save:`)
             if query.metadata.template.is_some() {
-                let save = Confirm::new("Save the fixed template to the query file?")
-                    .with_default(true)
-                    .prompt()
-                    .unwrap_or(false);
-
-                if save {
-                    if let Err(e) = save_fixed_template(query, fixed) {
-                        eprintln!("{} Could not save fix: {e}", "Warning:".yellow().bold());
-                    }
+                if let Err(e) = save_fixed_template(query, fixed) {
+                    eprintln!("{} Could not save fix: {e}", "Warning:".yellow().bold());
                 }
             }
 
@@ -311,30 +1233,44 @@ async fn fix_template_with_ai(
 
 /// Save a fixed template back to the query's .cosq file
 fn save_fixed_template(query: &StoredQuery, fixed_template: &str) -> Result<()> {
-    let mut updated = query.clone();
+    // Re-read the file as written so resolved includes are not baked into it
+    let path = cosq_core::stored_query::find_query_file(&query.name)?;
+    let mut updated = StoredQuery::parse(&query.name, &std::fs::read_to_string(&path)?)?;
     updated.metadata.template = Some(fixed_template.to_string());
     let contents = updated.to_file_contents()?;
-    let path = cosq_core::stored_query::query_file_path(&query.name, false)?;
-    std::fs::write(&path, &contents)?;
+    if !crate::commands::ai::confirm_save(&path, &contents)? {
+        return Ok(());
+    }
+    cosq_core::query_history::save(&path, &contents, "ai template fix")?;
     eprintln!("{} Saved fix to {}", "OK".green().bold(), path.display());
     Ok(())
 }
 
+/// The chart to render: `--chart`, else the query's `chart:` when neither
+/// `-o` nor `--template` picks another output.
+fn chart_spec(args: &RunArgs, query: &StoredQuery) -> Result<Option<ChartSpec>> {
+    let spec = match (&args.chart, &query.metadata.chart) {
+        (Some(chart), _) => chart,
+        (None, Some(chart)) if args.output.is_none() && args.template.is_none() => chart,
+        _ => return Ok(None),
+    };
+    if query.is_multi_step() {
+        bail!("charts need a single-step query");
+    }
+    Ok(Some(ChartSpec::parse(spec)?))
+}
+
 /// Resolve the template string from CLI arg, query metadata, or template file
-fn resolve_template_str(
+pub fn resolve_template_str(
     cli_template: &Option<String>,
     query: &StoredQuery,
 ) -> Result<Option<String>> {
     if let Some(path) = cli_template {
-        let content = std::fs::read_to_string(path)
-            .with_context(|| format!("failed to read template file: {path}"))?;
-        Ok(Some(content))
+        Ok(Some(read_template(path)?))
     } else if let Some(ref tmpl) = query.metadata.template {
         Ok(Some(tmpl.clone()))
     } else if let Some(ref tmpl_file) = query.metadata.template_file {
-        let content = std::fs::read_to_string(tmpl_file)
-            .with_context(|| format!("failed to read template file: {tmpl_file}"))?;
-        Ok(Some(content))
+        Ok(Some(read_template(tmpl_file)?))
     } else {
         Ok(None)
     }
@@ -342,7 +1278,7 @@ fn resolve_template_str(
 
 /// Interactively pick a stored query from a fuzzy-select list.
 fn pick_query_interactive() -> Result<StoredQuery> {
-    let queries = list_stored_queries().unwrap_or_default();
+    let mut queries = list_stored_queries().unwrap_or_default();
     if queries.is_empty() {
         bail!(
             "No stored queries found.\n\n  \
@@ -361,12 +1297,25 @@ fn pick_query_interactive() -> Result<StoredQuery> {
         })
         .collect();
 
-    let selection = Select::new("Select a stored query:", display_items.clone())
+    const PROMPT: &str = "Select a stored query:";
+    if let Some(Value::String(name)) = session::replayed_answer(PROMPT) {
+        if let Some(query) = queries.iter().position(|q| q.name == name) {
+            return Ok(queries.swap_remove(query));
+        }
+    }
+    let selection = Select::new(PROMPT, display_items.clone())
         .prompt()
         .context("query selection cancelled")?;
 
     let idx = display_items.iter().position(|d| d == &selection).unwrap();
-    Ok(queries.into_iter().nth(idx).unwrap())
+    let query = queries.into_iter().nth(idx).unwrap();
+    session::record_answer(PROMPT, &Value::String(query.name.clone()));
+    Ok(query)
+}
+
+/// Prompt key of a parameter in recorded sessions
+fn param_prompt(name: &str) -> String {
+    format!("Parameter {name}:")
 }
 
 /// Parse --key value pairs from the raw parameter strings.
@@ -394,12 +1343,68 @@ fn parse_cli_params(params: &[String]) -> Result<BTreeMap<String, String>> {
 fn resolve_params_interactive(
     query: &StoredQuery,
     cli_params: &BTreeMap<String, String>,
+    trusted_commands: &[String],
+    hooks: HookPolicy,
+    prompt: bool,
 ) -> Result<BTreeMap<String, Value>> {
     let mut resolved = BTreeMap::new();
 
     for param in &query.metadata.params {
+        // Skip parameters whose `when:` doesn't hold for the answers so far
+        if !query.param_applies(param, &resolved)? {
+            if cli_params.contains_key(&param.name) {
+                return Err(StoredQueryError::InactiveParam {
+                    name: param.name.clone(),
+                    when: param.when.clone().unwrap_or_default(),
+                }
+                .into());
+            }
+            // Still bound, as null: the SQL references it
+            resolved.insert(param.name.clone(), serde_json::Value::Null);
+            continue;
+        }
+        // A replayed session answers what was prompted when it was recorded
+        let replayed = session::replayed_answer(&param_prompt(&param.name));
+        // A `default_cmd:` that runs replaces the static default
+        let computed;
+        let param = match param.default_cmd {
+            Some(ref cmd) if !cli_params.contains_key(&param.name) && replayed.is_none() => {
+                match super::hooks::run_default_cmd(&param.name, cmd, trusted_commands, hooks)? {
+                    Some(output) => {
+                        computed = ParamDef {
+                            default: Some(cosq_core::stored_query::parse_param_value_public(
+                                &param.name,
+                                &param.param_type,
+                                &output,
+                            )?),
+                            ..param.clone()
+                        };
+                        &computed
+                    }
+                    None => param,
+                }
+            }
+            _ => param,
+        };
         let value = if let Some(raw) = cli_params.get(&param.name) {
             cosq_core::stored_query::parse_param_value_public(&param.name, &param.param_type, raw)?
+        } else if let Some(value) = replayed {
+            value
+        } else if let (true, Some(default)) = (param.encrypted, &param.default) {
+            // Never echo a decrypted secret in a prompt
+            default.clone()
+        } else if !prompt {
+            match (&param.default, &param.choices) {
+                (Some(default), _) => default.clone(),
+                (None, Some(choices)) if choices.len() == 1 => choices[0].clone(),
+                _ if param.is_required() => {
+                    return Err(StoredQueryError::MissingParam {
+                        name: param.name.clone(),
+                    }
+                    .into());
+                }
+                _ => continue,
+            }
         } else if let Some(ref choices) = param.choices {
             let choice_strs: Vec<String> = choices
                 .iter()
@@ -409,27 +1414,53 @@ fn resolve_params_interactive(
                 })
                 .collect();
 
-            let default_idx = param
-                .default
-                .as_ref()
-                .and_then(|d| choices.iter().position(|c| c == d))
-                .unwrap_or(0);
-
             let prompt = if let Some(ref desc) = param.description {
                 format!("{} ({})", param.name, desc)
             } else {
                 param.name.clone()
             };
 
-            let select_prompt = format!("{prompt}:");
-            let mut select = Select::new(&select_prompt, choice_strs.clone());
-            if default_idx < choice_strs.len() {
-                select = select.with_starting_cursor(default_idx);
-            }
-            let selected = select.prompt().context("parameter selection cancelled")?;
+            let value = if param.param_type == ParamType::Array {
+                // Pick one or more; the default array is pre-checked
+                let defaults: Vec<usize> = match param.default {
+                    Some(Value::Array(ref items)) => choices
+                        .iter()
+                        .enumerate()
+                        .filter(|(_, c)| items.contains(c))
+                        .map(|(i, _)| i)
+                        .collect(),
+                    _ => Vec::new(),
+                };
+                let select_prompt = format!("{prompt}:");
+                let selected = MultiSelect::new(&select_prompt, choice_strs.clone())
+                    .with_default(&defaults)
+                    .raw_prompt()
+                    .context("parameter selection cancelled")?;
+                Value::Array(
+                    selected
+                        .into_iter()
+                        .map(|option| choices[option.index].clone())
+                        .collect(),
+                )
+            } else {
+                let default_idx = param
+                    .default
+                    .as_ref()
+                    .and_then(|d| choices.iter().position(|c| c == d))
+                    .unwrap_or(0);
+
+                let select_prompt = format!("{prompt}:");
+                let mut select = Select::new(&select_prompt, choice_strs.clone());
+                if default_idx < choice_strs.len() {
+                    select = select.with_starting_cursor(default_idx);
+                }
+                let selected = select.prompt().context("parameter selection cancelled")?;
 
-            let idx = choice_strs.iter().position(|c| c == &selected).unwrap();
-            choices[idx].clone()
+                let idx = choice_strs.iter().position(|c| c == &selected).unwrap();
+                choices[idx].clone()
+            };
+            session::record_answer(&param_prompt(&param.name), &value);
+            value
         } else if param.is_required() || param.default.is_some() {
             let prompt = if let Some(ref desc) = param.description {
                 format!("{} ({})", param.name, desc)
@@ -447,9 +1478,32 @@ fn resolve_params_interactive(
             if let Some(ref def) = default_str {
                 text = text.with_default(def);
             }
+            if let Some(help) = input_help(&param.param_type) {
+                text = text.with_help_message(help);
+            }
+            // Re-prompt on malformed input instead of failing the run
+            let (name, param_type) = (param.name.clone(), param.param_type.clone());
+            text = text.with_validator(move |input: &str| {
+                Ok(
+                    match cosq_core::stored_query::parse_param_value_public(
+                        &name,
+                        &param_type,
+                        input,
+                    ) {
+                        Ok(_) => Validation::Valid,
+                        Err(e) => Validation::Invalid(e.to_string().into()),
+                    },
+                )
+            });
             let raw = text.prompt().context("input cancelled")?;
 
-            cosq_core::stored_query::parse_param_value_public(&param.name, &param.param_type, &raw)?
+            let value = cosq_core::stored_query::parse_param_value_public(
+                &param.name,
+                &param.param_type,
+                &raw,
+            )?;
+            session::record_answer(&param_prompt(&param.name), &value);
+            value
         } else {
             continue;
         };
@@ -478,6 +1532,117 @@ mod tests {
         assert_eq!(parsed.get("status"), Some(&"active".to_string()));
     }
 
+    fn test_config() -> Config {
+        serde_yaml::from_str(
+            r#"
+account:
+  name: main
+  subscription: s
+  resource_group: rg
+  endpoint: https://main.documents.azure.com:443/
+profiles:
+  analytics:
+    account:
+      name: analytics-cosmos
+      subscription: s
+      resource_group: rg
+      endpoint: https://analytics-cosmos.documents.azure.com:443/
+"#,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_query_config_pinning() {
+        let plain = StoredQuery::parse("q", "---\ndescription: t\n---\nSELECT 1\n").unwrap();
+        let (config, pinned) = query_config(test_config(), &plain, None).unwrap();
+        assert!(!pinned);
+        assert_eq!(config.account.name, "main");
+
+        let by_profile = StoredQuery::parse(
+            "q",
+            "---\ndescription: t\nprofile: analytics\n---\nSELECT 1\n",
+        )
+        .unwrap();
+        let (config, pinned) = query_config(test_config(), &by_profile, None).unwrap();
+        assert!(pinned);
+        assert_eq!(config.account.name, "analytics-cosmos");
+
+        let same_account =
+            StoredQuery::parse("q", "---\ndescription: t\naccount: main\n---\nSELECT 1\n").unwrap();
+        let (_, pinned) = query_config(test_config(), &same_account, None).unwrap();
+        assert!(!pinned);
+
+        let missing =
+            StoredQuery::parse("q", "---\ndescription: t\nprofile: nope\n---\nSELECT 1\n").unwrap();
+        assert!(query_config(test_config(), &missing, None).is_err());
+
+        // --profile overrides the query's own pin
+        let (config, pinned) = query_config(test_config(), &missing, Some("analytics")).unwrap();
+        assert!(pinned);
+        assert_eq!(config.account.name, "analytics-cosmos");
+    }
+
+    #[test]
+    fn test_effective_output_precedence() {
+        const CONFIG: &str = "the config's `output:`";
+        let plain = StoredQuery::parse("q", "---\ndescription: t\n---\nSELECT * FROM c\n").unwrap();
+        assert_eq!(
+            effective_output(None, false, &plain, None).unwrap(),
+            OutputFormat::Json
+        );
+        assert_eq!(
+            effective_output(None, true, &plain, Some(("table", CONFIG))).unwrap(),
+            OutputFormat::Template
+        );
+        // The config's `output:` applies when the query has no format or template
+        assert_eq!(
+            effective_output(None, false, &plain, Some(("table", CONFIG))).unwrap(),
+            OutputFormat::Table
+        );
+        assert!(
+            effective_output(None, false, &plain, Some(("xml", CONFIG)))
+                .unwrap_err()
+                .to_string()
+                .contains("config")
+        );
+
+        let table = StoredQuery::parse(
+            "q",
+            "---\ndescription: t\noutput: table\ntemplate: \"x\"\n---\nSELECT * FROM c\n",
+        )
+        .unwrap();
+        assert_eq!(
+            effective_output(None, false, &table, Some(("csv", CONFIG))).unwrap(),
+            OutputFormat::Table
+        );
+        assert_eq!(
+            effective_output(Some(OutputFormat::Csv), false, &table, None).unwrap(),
+            OutputFormat::Csv
+        );
+
+        let compact = StoredQuery::parse(
+            "q",
+            "---\ndescription: t\noutput: json-compact\n---\nSELECT * FROM c\n",
+        )
+        .unwrap();
+        assert_eq!(
+            effective_output(None, false, &compact, None).unwrap(),
+            OutputFormat::JsonCompact
+        );
+    }
+
+    #[test]
+    fn test_effective_output_invalid() {
+        let bad =
+            StoredQuery::parse("q", "---\ndescription: t\noutput: xml\n---\nSELECT 1\n").unwrap();
+        let err = effective_output(None, false, &bad, None)
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("'xml'"));
+        assert!(err.contains("table"));
+    }
+
     #[test]
     fn test_parse_cli_params_empty() {
         let parsed = parse_cli_params(&[]).unwrap();
@@ -0,0 +1,302 @@
+//! Schedule command — export stored query schedules to the host scheduler
+//!
+//! Queries declare `schedule:` in front matter; `cosq schedule export` renders
+//! crontab lines or systemd service/timer units that invoke `cosq run`.
+
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result, bail};
+use colored::Colorize;
+use cosq_core::schedule::Schedule;
+use cosq_core::stored_query::{StoredQuery, find_stored_query, list_stored_queries};
+
+use crate::cli::{ScheduleCommands, ScheduleFormat};
+
+pub fn run(cmd: ScheduleCommands) -> Result<()> {
+    match cmd {
+        ScheduleCommands::List => list(),
+        ScheduleCommands::Export { format, dir, names } => export(format, dir, &names),
+    }
+}
+
+/// Load scheduled queries (all, or the named ones) with their parsed schedules.
+fn scheduled_queries(names: &[String]) -> Result<Vec<(StoredQuery, Schedule)>> {
+    let queries = if names.is_empty() {
+        list_stored_queries()
+            .unwrap_or_default()
+            .into_iter()
+            .filter(|q| q.metadata.schedule.is_some())
+            .collect()
+    } else {
+        let mut queries = Vec::new();
+        for name in names {
+            let query = find_stored_query(name)
+                .map_err(|e| anyhow::anyhow!("Failed to load query '{name}': {e}"))?;
+            if query.metadata.schedule.is_none() {
+                bail!(
+                    "Query '{}' has no `schedule:` in its front matter",
+                    query.name
+                );
+            }
+            queries.push(query);
+        }
+        queries
+    };
+
+    queries
+        .into_iter()
+        .map(|q| {
+            let expr = q.metadata.schedule.as_deref().unwrap_or_default();
+            let schedule =
+                Schedule::parse(expr).with_context(|| format!("in query '{}'", q.name))?;
+            Ok((q, schedule))
+        })
+        .collect()
+}
+
+fn list() -> Result<()> {
+    let scheduled = scheduled_queries(&[])?;
+    if scheduled.is_empty() {
+        println!("No scheduled queries found.");
+        println!(
+            "\n  Add {} to a query's front matter to schedule it.",
+            "schedule: \"0 7 * * 1-5\"".cyan()
+        );
+        return Ok(());
+    }
+
+    let max_name_len = scheduled
+        .iter()
+        .map(|(q, _)| q.name.len())
+        .max()
+        .unwrap_or(0);
+    for (query, schedule) in &scheduled {
+        println!(
+            "  {:<width$}  {:<16}  {}",
+            query.name.green().bold(),
+            schedule.to_cron(),
+            query.metadata.description.dimmed(),
+            width = max_name_len,
+        );
+    }
+    println!("\n{} scheduled queries found.", scheduled.len());
+    Ok(())
+}
+
+fn export(format: ScheduleFormat, dir: Option<PathBuf>, names: &[String]) -> Result<()> {
+    let scheduled = scheduled_queries(names)?;
+    if scheduled.is_empty() {
+        bail!("No scheduled queries found. Add `schedule:` to a query's front matter first.");
+    }
+    if dir.is_some() && format == ScheduleFormat::Cron {
+        bail!(
+            "--dir is only supported with --format systemd (install cron lines with `crontab -e`)"
+        );
+    }
+
+    let exe = std::env::current_exe()
+        .context("failed to determine the cosq executable path")?
+        .display()
+        .to_string();
+    // Schedulers run with a minimal PATH; keep ours so `az` can be found
+    let path_env = std::env::var("PATH").unwrap_or_default();
+
+    for (query, schedule) in &scheduled {
+        warn_about(query, schedule);
+    }
+
+    match format {
+        ScheduleFormat::Cron => {
+            println!("# cosq scheduled queries (generated by `cosq schedule export`)");
+            if !path_env.is_empty() {
+                println!("PATH={path_env}");
+            }
+            for (query, schedule) in &scheduled {
+                println!();
+                print!("{}", cron_entry(query, schedule, &exe));
+            }
+        }
+        ScheduleFormat::Systemd => {
+            let mut written = Vec::new();
+            for (query, schedule) in &scheduled {
+                let unit = unit_name(&query.name);
+                let service = systemd_service(query, &exe, &path_env);
+                let timer = systemd_timer(query, schedule);
+                match dir {
+                    Some(ref dir) => {
+                        std::fs::create_dir_all(dir)?;
+                        write_unit(dir, &format!("{unit}.service"), &service)?;
+                        write_unit(dir, &format!("{unit}.timer"), &timer)?;
+                        written.push(unit);
+                    }
+                    None => {
+                        println!("# --- {unit}.service ---\n{service}");
+                        println!("# --- {unit}.timer ---\n{timer}");
+                    }
+                }
+            }
+            if let Some(ref dir) = dir {
+                eprintln!(
+                    "{} Wrote {} timer(s) to {}",
+                    "OK".green().bold(),
+                    written.len(),
+                    dir.display()
+                );
+                eprintln!("\n  Enable them with:");
+                eprintln!("    systemctl --user daemon-reload");
+                for unit in &written {
+                    eprintln!("    systemctl --user enable --now {unit}.timer");
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Warn about things that will make an unattended run fail or differ.
+fn warn_about(query: &StoredQuery, schedule: &Schedule) {
+    let missing: Vec<&str> = query
+        .metadata
+        .params
+        .iter()
+        .filter(|p| p.default.is_none())
+        .map(|p| p.name.as_str())
+        .collect();
+    if !missing.is_empty() {
+        eprintln!(
+            "{} '{}' has parameters without defaults ({}); scheduled runs cannot prompt for them",
+            "Warning:".yellow().bold(),
+            query.name,
+            missing.join(", ")
+        );
+    }
+    if schedule.has_day_ambiguity() {
+        eprintln!(
+            "{} '{}' restricts both day-of-month and day-of-week; cron runs when either matches, systemd only when both do",
+            "Warning:".yellow().bold(),
+            query.name
+        );
+    }
+}
+
+fn write_unit(dir: &Path, filename: &str, contents: &str) -> Result<()> {
+    let path = dir.join(filename);
+    std::fs::write(&path, contents).with_context(|| format!("failed to write {}", path.display()))
+}
+
+/// systemd unit name for a query (`cosq-<name>`).
+fn unit_name(query_name: &str) -> String {
+    let sanitized: String = query_name
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '-' || c == '_' {
+                c
+            } else {
+                '-'
+            }
+        })
+        .collect();
+    format!("cosq-{sanitized}")
+}
+
+/// Arguments passed to `cosq` for a scheduled run.
+fn run_args(query: &StoredQuery) -> Vec<String> {
+    let mut args = vec!["--quiet".to_string(), "run".to_string(), query.name.clone()];
+    // Exporting a schedule is the opt-in for its post-run hook: there is nobody to confirm
+    if query
+        .metadata
+        .hooks
+        .as_ref()
+        .is_some_and(|h| h.post_run.is_some())
+    {
+        args.push("--run-hooks".to_string());
+    }
+    args
+}
+
+fn cron_entry(query: &StoredQuery, schedule: &Schedule, exe: &str) -> String {
+    let exe = if exe.contains(' ') {
+        format!("'{exe}'")
+    } else {
+        exe.to_string()
+    };
+    format!(
+        "# {}: {}\n{} {} {}\n",
+        query.name,
+        query.metadata.description,
+        schedule.to_cron(),
+        exe,
+        run_args(query).join(" ")
+    )
+}
+
+fn systemd_service(query: &StoredQuery, exe: &str, path_env: &str) -> String {
+    let exe = if exe.contains(' ') {
+        format!("\"{exe}\"")
+    } else {
+        exe.to_string()
+    };
+    let mut unit = format!(
+        "[Unit]\nDescription=cosq: {}\n\n[Service]\nType=oneshot\n",
+        query.metadata.description
+    );
+    if !path_env.is_empty() {
+        unit.push_str(&format!("Environment=\"PATH={path_env}\"\n"));
+    }
+    unit.push_str(&format!("ExecStart={exe} {}\n", run_args(query).join(" ")));
+    unit
+}
+
+fn systemd_timer(query: &StoredQuery, schedule: &Schedule) -> String {
+    format!(
+        "[Unit]\nDescription=Schedule for cosq query {}\n\n[Timer]\nOnCalendar={}\nPersistent=true\n\n[Install]\nWantedBy=timers.target\n",
+        query.name,
+        schedule.to_on_calendar()
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scheduled(extra: &str) -> (StoredQuery, Schedule) {
+        let contents = format!(
+            "---\ndescription: Daily orders\nschedule: \"0 7 * * 1-5\"\n{extra}---\nSELECT * FROM c\n"
+        );
+        let query = StoredQuery::parse("daily-orders", &contents).unwrap();
+        let schedule = Schedule::parse(query.metadata.schedule.as_deref().unwrap()).unwrap();
+        (query, schedule)
+    }
+
+    #[test]
+    fn test_cron_entry() {
+        let (query, schedule) = scheduled("");
+        assert_eq!(
+            cron_entry(&query, &schedule, "/usr/local/bin/cosq"),
+            "# daily-orders: Daily orders\n0 7 * * 1-5 /usr/local/bin/cosq --quiet run daily-orders\n"
+        );
+    }
+
+    #[test]
+    fn test_systemd_units() {
+        let (query, schedule) = scheduled("hooks:\n  post_run:\n    command: mail -s report ops\n");
+        let service = systemd_service(&query, "/opt/my tools/cosq", "/usr/bin");
+        assert!(service.contains("Environment=\"PATH=/usr/bin\"\n"));
+        assert!(
+            service.contains(
+                "ExecStart=\"/opt/my tools/cosq\" --quiet run daily-orders --run-hooks\n"
+            )
+        );
+
+        let timer = systemd_timer(&query, &schedule);
+        assert!(timer.contains("OnCalendar=Mon..Fri *-*-* 07:00:00\n"));
+        assert!(timer.contains("WantedBy=timers.target"));
+    }
+
+    #[test]
+    fn test_unit_name() {
+        assert_eq!(unit_name("daily-orders"), "cosq-daily-orders");
+        assert_eq!(unit_name("a b.c"), "cosq-a-b-c");
+    }
+}
@@ -0,0 +1,128 @@
+//! Schema command — infer a container's schema from sampled documents
+//!
+//! Prints field paths with types, presence/null percentages and example
+//! values, and caches the result for reuse by AI query generation. The
+//! schema can also be exported as JSON Schema, TypeScript or Rust types.
+
+use anyhow::{Context, Result};
+use colored::Colorize;
+use comfy_table::Table;
+use comfy_table::presets::UTF8_FULL_CONDENSED;
+use cosq_core::config::Config;
+use cosq_core::schema::Schema;
+use cosq_core::schema_export;
+
+use super::common;
+use crate::cli::SchemaFormat;
+
+pub struct SchemaArgs {
+    pub container: Option<String>,
+    pub db: Option<String>,
+    pub sample: u32,
+    pub format: SchemaFormat,
+    pub profile: Option<String>,
+    pub quiet: bool,
+}
+
+pub async fn run(args: SchemaArgs) -> Result<()> {
+    let (mut config, pinned) = match args.profile {
+        Some(ref profile) => (Config::load()?.for_profile(profile)?, true),
+        None => (Config::load()?, false),
+    };
+    let client = common::connect(&config).await?;
+    let (database, db_changed) =
+        common::resolve_database(&client, &mut config, args.db, None).await?;
+    let (container, ctr_changed) =
+        common::resolve_container(&client, &mut config, &database, args.container, None).await?;
+    if (db_changed || ctr_changed) && !pinned {
+        config.save()?;
+    }
+
+    if !args.quiet {
+        eprintln!(
+            "{}",
+            format!(
+                "Sampling up to {} documents from {container}...",
+                args.sample
+            )
+            .dimmed()
+        );
+    }
+    let sample = client
+        .query(
+            &database,
+            &container,
+            &format!("SELECT TOP {} * FROM c", args.sample),
+        )
+        .await
+        .with_context(|| format!("failed to sample documents from {container}"))?;
+
+    let mut documents = sample.documents;
+    // The cache feeds AI prompts, so example values are always redacted
+    common::ai_redactor(&config).redact_all(&mut documents);
+    let mut schema = Schema::infer(&documents);
+    schema.sampled_at = Some(chrono::Utc::now().to_rfc3339());
+    let path = schema.save_cached(&config.account.name, &database, &container)?;
+
+    match args.format {
+        SchemaFormat::Table if schema.fields.is_empty() => {
+            println!("No documents in {container}.");
+        }
+        SchemaFormat::Table => println!("{}", schema_table(&schema)),
+        SchemaFormat::Json => println!("{}", serde_json::to_string_pretty(&schema)?),
+        SchemaFormat::JsonSchema => println!(
+            "{}",
+            serde_json::to_string_pretty(&schema_export::to_json_schema(&schema, &container))?
+        ),
+        SchemaFormat::Typescript => {
+            print!("{}", schema_export::to_typescript(&schema, &container));
+        }
+        SchemaFormat::Rust => print!("{}", schema_export::to_rust(&schema, &container)),
+    }
+
+    if !args.quiet {
+        eprintln!(
+            "{} {} documents sampled ({:.2} RUs), cached at {}",
+            "✓".green(),
+            schema.sampled,
+            sample.request_charge,
+            path.display()
+        );
+    }
+    Ok(())
+}
+
+fn schema_table(schema: &Schema) -> Table {
+    let mut table = Table::new();
+    table.load_preset(UTF8_FULL_CONDENSED);
+    table.set_header(vec!["Field", "Types", "Present", "Null", "Examples"]);
+    for field in &schema.fields {
+        let examples: Vec<String> = field.examples.iter().map(ToString::to_string).collect();
+        table.add_row(vec![
+            field.path.clone(),
+            field.type_names(),
+            format!("{:.0}%", field.presence_pct(schema.sampled)),
+            format!("{:.0}%", field.null_pct()),
+            examples.join(", "),
+        ]);
+    }
+    table
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_schema_table() {
+        let schema = Schema::infer(&[
+            json!({"id": "a", "total": 5}),
+            json!({"id": "b", "total": null}),
+        ]);
+        let rendered = schema_table(&schema).to_string();
+        assert!(rendered.contains("null|number"));
+        assert!(rendered.contains("\"a\", \"b\""));
+        assert!(rendered.contains("50%"));
+    }
+}
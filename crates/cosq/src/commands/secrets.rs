@@ -0,0 +1,61 @@
+//! Secrets command — store sensitive config values in the OS keyring
+//!
+//! A secret stored as `<name>` is referenced from the config as
+//! `keyring:<name>` (e.g. `master_key: keyring:prod-key`).
+
+use anyhow::{Context, Result, bail};
+use colored::Colorize;
+use cosq_core::secrets;
+
+use crate::cli::SecretsCommands;
+
+pub fn run(cmd: SecretsCommands, quiet: bool) -> Result<()> {
+    match cmd {
+        SecretsCommands::Set { name, value } => set(&name, value, quiet),
+        SecretsCommands::Delete { name } => delete(&name),
+        SecretsCommands::Check { name } => check(&name),
+    }
+}
+
+fn set(name: &str, value: Option<String>, quiet: bool) -> Result<()> {
+    let value = match value {
+        Some(value) => value,
+        None => inquire::Password::new(&format!("Value for '{name}':"))
+            .without_confirmation()
+            .with_display_toggle_enabled()
+            .prompt()
+            .context("input cancelled")?,
+    };
+    if value.is_empty() {
+        bail!("refusing to store an empty secret");
+    }
+    secrets::set(name, &value)?;
+    println!(
+        "{} Stored secret '{name}' in the OS keyring",
+        "OK".green().bold()
+    );
+    if !quiet {
+        println!(
+            "\n  Reference it from the config as: {}",
+            format!("{}{name}", secrets::KEYRING_PREFIX).cyan()
+        );
+    }
+    Ok(())
+}
+
+fn delete(name: &str) -> Result<()> {
+    if secrets::delete(name)? {
+        println!("{} Deleted secret '{name}'", "OK".green().bold());
+    } else {
+        println!("Secret '{name}' not found.");
+    }
+    Ok(())
+}
+
+fn check(name: &str) -> Result<()> {
+    match secrets::get(name)? {
+        Some(_) => println!("{} secret '{name}' is stored", "✓".green()),
+        None => bail!("secret '{name}' not found — store it with `cosq secrets set {name}`"),
+    }
+    Ok(())
+}
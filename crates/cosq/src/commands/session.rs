@@ -0,0 +1,351 @@
+//! Session recording and replay — `cosq record` / `cosq replay`
+//!
+//! `cosq record` reads cosq commands line by line and runs each one as a
+//! child process, echoing and capturing what it prints. The child reports the
+//! prompts it asked and the query results it received through an events file
+//! (`COSQ_SESSION_EVENTS`). `cosq replay` runs the recorded commands again in
+//! offline mode with the step in `COSQ_SESSION_REPLAY`: prompts take their
+//! recorded answers and queries get their recorded results, so nothing
+//! touches an account and the output is the same every time.
+//!
+//! Recorded results are served where `--offline` reads the result cache, not
+//! through `cosq_client::MemoryBackend`: commands query a `CosmosClient`, and
+//! the step already holds each query's results, so there is nothing left for
+//! a mock backend to evaluate.
+
+use std::io::{BufRead, Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::{Mutex, OnceLock};
+
+use anyhow::{Context, Result, bail};
+use colored::Colorize;
+use cosq_core::config::Config;
+use cosq_core::result_cache::CachedResult;
+use cosq_core::session::{
+    Answer, Event, RecordedResult, ResultQueue, Session, Step, quote_arg, split_command_line,
+};
+use serde_json::Value;
+
+use super::common;
+
+/// File a recorded command appends its events to
+const EVENTS_ENV: &str = "COSQ_SESSION_EVENTS";
+
+/// File holding the step a replayed command runs
+const REPLAY_ENV: &str = "COSQ_SESSION_REPLAY";
+
+/// The step being replayed, if this process is one
+struct Replay {
+    step: Step,
+    results: Mutex<ResultQueue>,
+}
+
+impl Replay {
+    /// The step written to `path` by `cosq replay`.
+    fn load(path: &Path) -> Option<Self> {
+        let text = std::fs::read_to_string(path).ok()?;
+        let step: Step = serde_json::from_str(&text).ok()?;
+        Some(Replay {
+            results: Mutex::new(ResultQueue::new(step.results.clone())),
+            step,
+        })
+    }
+
+    fn answer(&self, prompt: &str) -> Option<Value> {
+        self.step.answer(prompt).cloned()
+    }
+
+    fn results(&self, key: &str) -> Option<CachedResult> {
+        self.results.lock().unwrap().take(key)
+    }
+}
+
+fn replay() -> Option<&'static Replay> {
+    static REPLAY: OnceLock<Option<Replay>> = OnceLock::new();
+    REPLAY
+        .get_or_init(|| Replay::load(Path::new(&std::env::var_os(REPLAY_ENV)?)))
+        .as_ref()
+}
+
+/// The recorded answer to `prompt`, when replaying.
+pub fn replayed_answer(prompt: &str) -> Option<Value> {
+    replay()?.answer(prompt)
+}
+
+/// The recorded results for a query, when replaying.
+pub fn replayed_results(key: &str) -> Option<CachedResult> {
+    replay()?.results(key)
+}
+
+/// Report an answered prompt to the recorder, if this command is recorded.
+pub fn record_answer(prompt: &str, value: &Value) {
+    record_event(&Event::Answer(Answer {
+        prompt: prompt.to_string(),
+        value: value.clone(),
+    }));
+}
+
+/// Report query results to the recorder, if this command is recorded.
+pub fn record_results(key: &str, result: &CachedResult) {
+    record_event(&Event::Result(RecordedResult {
+        key: key.to_string(),
+        result: result.clone(),
+    }));
+}
+
+/// Recording is best effort: a failure only loses this event.
+fn record_event(event: &Event) {
+    let Some(path) = std::env::var_os(EVENTS_ENV) else {
+        return;
+    };
+    let written = serde_json::to_string(event)
+        .map_err(std::io::Error::from)
+        .and_then(|line| {
+            let mut file = std::fs::OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(path)?;
+            writeln!(file, "{line}")
+        });
+    if let Err(e) = written {
+        tracing::debug!(error = %e, "failed to record session event");
+    }
+}
+
+/// `cosq record`: run commands typed at the prompt and save them as a session.
+pub fn record(path: &Path, redact: bool) -> Result<()> {
+    let redactor = if redact {
+        let config = Config::load().context("--redact uses the config's `redact:` patterns")?;
+        if config.redact.is_empty() {
+            bail!("--redact needs `redact:` patterns in the config");
+        }
+        Some(common::output_redactor(&config, true))
+    } else {
+        None
+    };
+    let mut session = Session::new(env!("CARGO_PKG_VERSION"), redact);
+    let events = tempfile_path("events");
+
+    eprintln!(
+        "{} {}",
+        "Recording to".bold(),
+        path.display().to_string().cyan()
+    );
+    eprintln!(
+        "{}",
+        "Enter cosq commands without `cosq` (e.g. query \"SELECT * FROM c\"); `exit` or an empty line stops."
+            .dimmed()
+    );
+    let stdin = std::io::stdin();
+    loop {
+        eprint!("{} ", "cosq>".green().bold());
+        std::io::stderr().flush()?;
+        let mut line = String::new();
+        if stdin.lock().read_line(&mut line)? == 0 {
+            break;
+        }
+        let line = line.trim();
+        if line.is_empty() || line == "exit" {
+            break;
+        }
+        let args = match split_command_line(line) {
+            Ok(args) => args,
+            Err(e) => {
+                eprintln!("{} {e}", "✗".red());
+                continue;
+            }
+        };
+        if matches!(args[0].as_str(), "record" | "replay") {
+            eprintln!("{} sessions can't be recorded from a session", "✗".red());
+            continue;
+        }
+
+        let _ = std::fs::remove_file(&events);
+        let mut child = cosq_command(&args, false)
+            .env(EVENTS_ENV, &events)
+            .stdout(Stdio::piped())
+            .spawn()
+            .context("failed to run cosq")?;
+        let output = tee(child.stdout.take().expect("piped stdout"))?;
+        let status = child.wait()?;
+
+        let recorded = std::fs::read_to_string(&events).unwrap_or_default();
+        let mut step = Step::from_events(args, &recorded, status.code().unwrap_or(1));
+        match redactor {
+            Some(ref redactor) => step.redact(redactor),
+            None => step.output = Some(output),
+        }
+        session.steps.push(step);
+        // Saved after every command, so an interrupted recording keeps what ran
+        session
+            .write(path)
+            .with_context(|| format!("failed to write {}", path.display()))?;
+    }
+    let _ = std::fs::remove_file(&events);
+
+    eprintln!(
+        "{} Recorded {} command(s) to {}",
+        "✓".green(),
+        session.steps.len(),
+        path.display()
+    );
+    Ok(())
+}
+
+/// `cosq replay`: run a session's commands again against its recorded
+/// results. With `verify`, fail if any output differs from the recording.
+pub fn replay_session(path: &Path, verify: bool, quiet: bool) -> Result<()> {
+    let session =
+        Session::read(path).with_context(|| format!("failed to read {}", path.display()))?;
+    if verify && session.redacted {
+        bail!("--verify needs a session recorded without --redact (it keeps no output)");
+    }
+    let step_file = tempfile_path("step");
+    let mut mismatches = Vec::new();
+
+    for (index, step) in session.steps.iter().enumerate() {
+        if !quiet {
+            let line: Vec<String> = step.args.iter().map(|a| quote_arg(a)).collect();
+            eprintln!("{} {}", "$ cosq".green().bold(), line.join(" ").bold());
+        }
+        std::fs::write(&step_file, serde_json::to_string(step)?)?;
+        let mut command = cosq_command(&step.args, true);
+        command.env(REPLAY_ENV, &step_file);
+
+        let exit_code = if verify {
+            let output = command.stderr(Stdio::inherit()).output()?;
+            let stdout = String::from_utf8_lossy(&output.stdout);
+            print!("{stdout}");
+            if step.output.as_deref() != Some(stdout.as_ref()) {
+                mismatches.push(format!("step {}: output differs", index + 1));
+            }
+            output.status.code().unwrap_or(1)
+        } else {
+            command.status()?.code().unwrap_or(1)
+        };
+        if exit_code != step.exit_code {
+            mismatches.push(format!(
+                "step {}: exited with {exit_code}, recorded {}",
+                index + 1,
+                step.exit_code
+            ));
+        }
+        if !quiet {
+            eprintln!();
+        }
+    }
+    let _ = std::fs::remove_file(&step_file);
+
+    if !mismatches.is_empty() {
+        for mismatch in &mismatches {
+            eprintln!("{} {mismatch}", "✗".red());
+        }
+        bail!(
+            "{} of {} step(s) replayed differently",
+            mismatches.len(),
+            session.steps.len()
+        );
+    }
+    if !quiet {
+        eprintln!(
+            "{} Replayed {} command(s) from {}",
+            "✓".green(),
+            session.steps.len(),
+            path.display()
+        );
+    }
+    Ok(())
+}
+
+/// This cosq binary with `args`. Replayed steps run `--offline`, so only
+/// recorded results are used.
+fn cosq_command(args: &[String], offline: bool) -> Command {
+    let exe = std::env::current_exe().unwrap_or_else(|_| PathBuf::from("cosq"));
+    let mut command = Command::new(exe);
+    command
+        .env("COSQ_NO_UPDATE_CHECK", "1")
+        .env_remove(EVENTS_ENV)
+        .env_remove(REPLAY_ENV);
+    if offline {
+        command.arg("--offline");
+    }
+    command.args(args);
+    command
+}
+
+/// Copy a child's stdout to ours as it arrives, returning what it printed.
+fn tee(mut from: impl Read) -> Result<String> {
+    let mut captured = Vec::new();
+    let mut buf = [0u8; 8192];
+    let mut stdout = std::io::stdout();
+    loop {
+        let n = from.read(&mut buf)?;
+        if n == 0 {
+            break;
+        }
+        stdout.write_all(&buf[..n])?;
+        stdout.flush()?;
+        captured.extend_from_slice(&buf[..n]);
+    }
+    Ok(String::from_utf8_lossy(&captured).into_owned())
+}
+
+/// A per-process scratch file under the temp directory.
+fn tempfile_path(kind: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("cosq-session-{}-{kind}.json", std::process::id()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_replay_serves_recorded_step() {
+        let recorded = |documents| CachedResult {
+            cached_at: "2026-01-01T00:00:00Z".to_string(),
+            request_charge: 2.5,
+            documents,
+            ..CachedResult::default()
+        };
+        let step = Step {
+            args: vec!["run".to_string(), "orders".to_string()],
+            answers: vec![Answer {
+                prompt: "Select a database:".to_string(),
+                value: json!("shop"),
+            }],
+            results: vec![
+                RecordedResult {
+                    key: "first".to_string(),
+                    result: recorded(vec![json!({"id": "1"})]),
+                },
+                RecordedResult {
+                    key: "second".to_string(),
+                    result: recorded(vec![json!({"id": "2"})]),
+                },
+            ],
+            output: None,
+            exit_code: 0,
+        };
+        // Written the way `cosq replay` hands a step to the command it runs
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("step.json");
+        std::fs::write(&path, serde_json::to_string(&step).unwrap()).unwrap();
+
+        let replay = Replay::load(&path).unwrap();
+        assert_eq!(replay.answer("Select a database:"), Some(json!("shop")));
+        assert_eq!(replay.answer("Select a container:"), None);
+        assert_eq!(
+            replay.results("second").unwrap().documents,
+            vec![json!({"id": "2"})]
+        );
+        // A key the recording doesn't know gets the next unused result
+        let next = replay.results("other-account").unwrap();
+        assert_eq!(next.documents, vec![json!({"id": "1"})]);
+        assert_eq!(next.request_charge, 2.5);
+        assert!(replay.results("first").is_none());
+
+        assert!(Replay::load(&dir.path().join("missing.json")).is_none());
+    }
+}
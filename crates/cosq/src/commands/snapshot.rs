@@ -0,0 +1,201 @@
+//! Snapshot command — ad-hoc container backups
+//!
+//! `cosq snapshot create` reads every document of a container into one file
+//! with a manifest (source, timestamp, count, hash); `cosq snapshot restore`
+//! verifies that manifest and upserts the documents into a container, e.g. to
+//! undo a risky bulk change.
+
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result, bail};
+use colored::Colorize;
+use cosq_core::snapshot::{Snapshot, default_file_name, partition_key_value};
+
+use super::common::{self, BoundedTasks};
+use super::docs::{connect_container, strip_system_fields};
+use crate::cli::SnapshotCommands;
+
+pub async fn run(cmd: SnapshotCommands, profile: Option<String>, quiet: bool) -> Result<()> {
+    match cmd {
+        SnapshotCommands::Create {
+            container,
+            db,
+            file,
+        } => create(container, db, file, profile, quiet).await,
+        SnapshotCommands::Restore {
+            file,
+            to,
+            db,
+            parallel,
+            yes,
+            force,
+        } => restore(&file, to, db, parallel, yes, force, profile, quiet).await,
+    }
+}
+
+async fn create(
+    container: Option<String>,
+    db: Option<String>,
+    file: Option<PathBuf>,
+    profile: Option<String>,
+    quiet: bool,
+) -> Result<()> {
+    common::ensure_online("cosq snapshot create")?;
+    let (config, client, database, container) =
+        connect_container(profile.as_deref(), db, container).await?;
+    let properties = client.get_container(&database, &container).await?;
+
+    if !quiet {
+        eprintln!(
+            "{} {}/{}...",
+            "Reading".dimmed(),
+            database.cyan(),
+            container.cyan()
+        );
+    }
+    let interrupt = common::Interrupt::install();
+    let result = client
+        .with_cancellation(interrupt.flag())
+        .query(&database, &container, "SELECT * FROM c")
+        .await;
+    interrupt.finish();
+    let result = result?;
+    if result.partial {
+        bail!("snapshot interrupted; no file was written");
+    }
+
+    let snapshot = Snapshot::new(
+        &config.account.name,
+        &database,
+        &container,
+        properties.partition_key.paths,
+        result.documents,
+    );
+    let path = file.unwrap_or_else(|| PathBuf::from(default_file_name(&container)));
+    snapshot
+        .write(&path)
+        .with_context(|| format!("failed to write {}", path.display()))?;
+
+    if !quiet {
+        eprintln!(
+            "{} Saved {} documents from {}/{} to {} ({:.2} RUs)",
+            "✓".green(),
+            snapshot.manifest.count,
+            database,
+            container,
+            path.display().to_string().bold(),
+            result.request_charge
+        );
+        eprintln!(
+            "  {} cosq snapshot restore {} --to {}",
+            "Restore with:".dimmed(),
+            path.display(),
+            container
+        );
+    }
+    Ok(())
+}
+
+#[allow(clippy::too_many_arguments)]
+async fn restore(
+    file: &Path,
+    to: String,
+    db: Option<String>,
+    parallel: usize,
+    yes: bool,
+    force: bool,
+    profile: Option<String>,
+    quiet: bool,
+) -> Result<()> {
+    common::ensure_online("cosq snapshot restore")?;
+    let snapshot =
+        Snapshot::read(file).with_context(|| format!("cannot restore from {}", file.display()))?;
+    let manifest = &snapshot.manifest;
+    let (config, client, database, container) =
+        connect_container(profile.as_deref(), db, Some(to)).await?;
+
+    let paths = client
+        .get_container(&database, &container)
+        .await?
+        .partition_key
+        .paths;
+    let [pk_path] = paths.as_slice() else {
+        bail!(
+            "{database}/{container} has {} partition key paths; restoring into hierarchical partition keys is not supported",
+            paths.len()
+        );
+    };
+    if !manifest.partition_key_paths.is_empty() && manifest.partition_key_paths != paths {
+        eprintln!(
+            "{} the snapshot was taken from a container partitioned by {}, {}/{} is partitioned by {}",
+            "Warning:".yellow().bold(),
+            manifest.partition_key_paths.join(", "),
+            database,
+            container,
+            pk_path
+        );
+    }
+
+    if !quiet {
+        eprintln!(
+            "Snapshot of {}/{}/{} taken {}: {} documents",
+            manifest.account,
+            manifest.database,
+            manifest.container,
+            manifest.created_at,
+            manifest.count
+        );
+    }
+    common::confirm_destructive(
+        &config,
+        &database,
+        &container,
+        &format!("{} document upserts", manifest.count),
+        Some(&format!(
+            "Upsert {} documents into {database}/{container}? Documents with the same id are overwritten.",
+            manifest.count
+        )),
+        yes,
+        force,
+    )?;
+
+    let total = snapshot.documents.len();
+    let mut tasks = BoundedTasks::new(parallel);
+    for document in snapshot.documents {
+        let document = strip_system_fields(document);
+        let partition_key = partition_key_value(&document, pk_path);
+        let (client, database, container) = (client.clone(), database.clone(), container.clone());
+        tasks
+            .spawn(async move {
+                let id = document
+                    .get("id")
+                    .and_then(|id| id.as_str())
+                    .unwrap_or("(no id)")
+                    .to_string();
+                client
+                    .upsert_document(&database, &container, &document, &partition_key)
+                    .await
+                    .map(|_| ())
+                    .map_err(|e| format!("{id}: {e}"))
+            })
+            .await;
+    }
+    tasks.wait().await;
+
+    let failures = tasks.failures();
+    if !failures.is_empty() {
+        common::report_failures(failures);
+        bail!(
+            "{} of {total} documents could not be restored into {database}/{container}",
+            failures.len()
+        );
+    }
+    if !quiet {
+        eprintln!(
+            "{} Restored {} documents into {database}/{container}",
+            "✓".green(),
+            tasks.succeeded().len()
+        );
+    }
+    Ok(())
+}
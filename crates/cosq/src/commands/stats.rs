@@ -0,0 +1,99 @@
+//! Stats command — cumulative request units (and estimated cost) per query
+
+use anyhow::Result;
+use colored::Colorize;
+use comfy_table::Table;
+use comfy_table::presets::UTF8_FULL_CONDENSED;
+use cosq_core::config::{Config, PricingConfig};
+use cosq_core::stats::UsageStats;
+
+pub fn run(reset: bool) -> Result<()> {
+    let path = UsageStats::path()?;
+    if reset {
+        UsageStats::default().save_to(&path)?;
+        println!("{} usage stats reset", "✓".green());
+        return Ok(());
+    }
+
+    let stats = UsageStats::load_from(&path)?;
+    if stats.queries.is_empty() {
+        println!("No runs recorded yet.");
+        return Ok(());
+    }
+
+    let pricing = Config::load().ok().and_then(|c| c.pricing);
+    let table = stats_table(&stats, pricing.as_ref());
+    println!("{table}");
+
+    if pricing.is_none() {
+        eprintln!(
+            "{}",
+            "Tip: add `pricing:` (per_million_ru, per_100_rus_hour or region) to the config to see estimated costs."
+                .dimmed()
+        );
+    }
+    Ok(())
+}
+
+fn stats_table(stats: &UsageStats, pricing: Option<&PricingConfig>) -> Table {
+    let cost = |ru: f64| {
+        pricing
+            .and_then(|p| p.cost(ru).map(|c| p.format_cost(c)))
+            .unwrap_or_else(|| "-".to_string())
+    };
+
+    let mut table = Table::new();
+    table.load_preset(UTF8_FULL_CONDENSED);
+    table.set_header(vec![
+        "Query",
+        "Runs",
+        "Total RUs",
+        "Avg RUs",
+        "Est. cost",
+        "Last run",
+    ]);
+    for (name, q) in &stats.queries {
+        table.add_row(vec![
+            name.clone(),
+            q.runs.to_string(),
+            format!("{:.2}", q.request_units),
+            format!("{:.2}", q.request_units / q.runs.max(1) as f64),
+            cost(q.request_units),
+            q.last_run.clone().unwrap_or_default(),
+        ]);
+    }
+    let (runs, ru) = stats.totals();
+    table.add_row(vec![
+        "Total".to_string(),
+        runs.to_string(),
+        format!("{ru:.2}"),
+        format!("{:.2}", ru / runs.max(1) as f64),
+        cost(ru),
+        String::new(),
+    ]);
+    table
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stats_table() {
+        let mut stats = UsageStats::default();
+        stats.record("daily", 3.0, None);
+        stats.record("daily", 5.0, None);
+        let pricing = PricingConfig {
+            per_million_ru: Some(1_000_000.0),
+            ..Default::default()
+        };
+
+        let rendered = stats_table(&stats, Some(&pricing)).to_string();
+        assert!(rendered.contains("daily"));
+        assert!(rendered.contains("8.00"));
+        assert!(rendered.contains("$8.00"));
+
+        let rendered = stats_table(&stats, None).to_string();
+        assert!(!rendered.contains('$'));
+    }
+}
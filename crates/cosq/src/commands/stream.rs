@@ -0,0 +1,229 @@
+//! Streamed output for single-step `run` results
+//!
+//! When nothing needs the whole result set at once, documents are rendered on
+//! a blocking task that consumes pages from a channel while the client keeps
+//! fetching, so compact JSON (NDJSON), CSV and row-template output start with
+//! the first page and only a few pages are held in memory at a time.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::io::{BufWriter, Write};
+
+use anyhow::{Context, Result};
+use cosq_client::cosmos::{CosmosClient, QueryResult};
+use cosq_client::error::ClientError;
+use cosq_core::redact::Redactor;
+use serde_json::Value;
+use tokio::sync::mpsc;
+
+use crate::output::{
+    FailedRow, OutputFormat, collect_columns, create_template_env, row_template_context,
+    write_csv_header, write_csv_rows, write_results,
+};
+
+/// Pages buffered between fetching and rendering
+const BUFFERED_PAGES: usize = 4;
+
+/// An output format that can be written one page at a time
+pub enum StreamFormat {
+    /// One compact JSON document per line
+    JsonLines,
+    /// CSV with the columns of the first page
+    Csv,
+    /// A `template_mode: row` template rendered once per document
+    Rows {
+        template: String,
+        params: BTreeMap<String, Value>,
+    },
+}
+
+/// What a streamed run fetched and wrote
+pub struct Streamed {
+    /// The query outcome (its `documents` are empty; they were written)
+    pub result: Result<QueryResult, ClientError>,
+    /// Documents written
+    pub written: usize,
+    /// CSV fields that appeared only after the header was written
+    pub dropped_columns: BTreeSet<String>,
+}
+
+/// Run the query, rendering each page to `writer` as it arrives.
+///
+/// A render failure (e.g. a row template error) stops the query and is
+/// returned as the error; query failures are returned in [`Streamed::result`].
+#[allow(clippy::too_many_arguments)]
+pub async fn execute(
+    client: &CosmosClient,
+    database: &str,
+    container: &str,
+    sql: &str,
+    parameters: Vec<Value>,
+    format: StreamFormat,
+    redactor: Redactor,
+    writer: Box<dyn Write + Send>,
+) -> Result<Streamed> {
+    let (pages, receiver) = mpsc::channel(BUFFERED_PAGES);
+    let renderer = tokio::task::spawn_blocking(move || render(receiver, format, &redactor, writer));
+    let result = client
+        .query_stream(database, container, sql, parameters, pages)
+        .await;
+    let (written, dropped_columns) = renderer.await.context("render task panicked")??;
+    Ok(Streamed {
+        result,
+        written,
+        dropped_columns,
+    })
+}
+
+/// Render pages until the channel closes; returns the number of documents
+/// written and the CSV fields left out.
+fn render(
+    mut pages: mpsc::Receiver<Vec<Value>>,
+    format: StreamFormat,
+    redactor: &Redactor,
+    writer: Box<dyn Write + Send>,
+) -> Result<(usize, BTreeSet<String>)> {
+    let mut writer = BufWriter::new(writer);
+    let mut written = 0;
+    let mut csv_columns: Option<Vec<String>> = None;
+    let mut dropped = BTreeSet::new();
+
+    let mut env = create_template_env();
+    env.set_keep_trailing_newline(true);
+    if let StreamFormat::Rows { ref template, .. } = format {
+        env.add_template("output", template)?;
+    }
+
+    while let Some(mut page) = pages.blocking_recv() {
+        redactor.redact_all(&mut page);
+        match format {
+            StreamFormat::JsonLines => {
+                write_results(&mut writer, &page, &OutputFormat::JsonCompact)?;
+            }
+            StreamFormat::Csv => {
+                if page.is_empty() {
+                    continue;
+                }
+                let columns = csv_columns.get_or_insert_with(|| collect_columns(&page));
+                if written == 0 {
+                    write_csv_header(&mut writer, columns)?;
+                }
+                dropped.extend(
+                    collect_columns(&page)
+                        .into_iter()
+                        .filter(|c| !columns.contains(c)),
+                );
+                write_csv_rows(&mut writer, &page, columns)?;
+            }
+            StreamFormat::Rows { ref params, .. } => {
+                let tmpl = env.get_template("output")?;
+                for (index, doc) in page.iter().enumerate() {
+                    let row = tmpl
+                        .render(row_template_context(doc, params))
+                        .map_err(anyhow::Error::from)
+                        .context(FailedRow(written + index))?;
+                    if row.is_empty() {
+                        continue;
+                    }
+                    writer.write_all(row.as_bytes())?;
+                    if !row.ends_with('\n') {
+                        writer.write_all(b"\n")?;
+                    }
+                }
+            }
+        }
+        written += page.len();
+        writer.flush()?;
+    }
+    writer.flush()?;
+    Ok((written, dropped))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use std::sync::{Arc, Mutex};
+
+    /// A writer whose output can be read after it is moved into `render`
+    #[derive(Clone, Default)]
+    struct Shared(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for Shared {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    fn render_pages(format: StreamFormat, pages: Vec<Vec<Value>>) -> (String, usize, Vec<String>) {
+        let (tx, rx) = mpsc::channel(pages.len().max(1));
+        for page in pages {
+            tx.try_send(page).unwrap();
+        }
+        drop(tx);
+        let out = Shared::default();
+        let redactor = Redactor::new(["*.secret"]);
+        let (written, dropped) = render(rx, format, &redactor, Box::new(out.clone())).unwrap();
+        let text = String::from_utf8(out.0.lock().unwrap().clone()).unwrap();
+        (text, written, dropped.into_iter().collect())
+    }
+
+    #[test]
+    fn test_render_json_lines() {
+        let (text, written, _) = render_pages(
+            StreamFormat::JsonLines,
+            vec![
+                vec![json!({"id": "1", "secret": "x"})],
+                vec![json!({"id": "2"})],
+            ],
+        );
+        assert_eq!(text, "{\"id\":\"1\",\"secret\":\"***\"}\n{\"id\":\"2\"}\n");
+        assert_eq!(written, 2);
+    }
+
+    #[test]
+    fn test_render_csv_uses_first_page_columns() {
+        let (text, written, dropped) = render_pages(
+            StreamFormat::Csv,
+            vec![
+                vec![],
+                vec![json!({"id": "1", "name": "Ada"})],
+                vec![json!({"id": "2", "name": "Bo", "extra": true})],
+            ],
+        );
+        assert_eq!(text, "id,name\n1,Ada\n2,Bo\n");
+        assert_eq!(written, 2);
+        assert_eq!(dropped, vec!["extra"]);
+    }
+
+    #[test]
+    fn test_render_rows() {
+        let format = StreamFormat::Rows {
+            template: "{{ doc.id }}{% if doc.vip %} *{% endif %}".into(),
+            params: BTreeMap::new(),
+        };
+        let (text, _, _) = render_pages(
+            format,
+            vec![
+                vec![json!({"id": "1"}), json!({"id": "2", "vip": true})],
+                vec![json!({"id": "3"})],
+            ],
+        );
+        assert_eq!(text, "1\n2 *\n3\n");
+
+        let (tx, rx) = mpsc::channel(2);
+        tx.try_send(vec![json!({"id": "1"})]).unwrap();
+        tx.try_send(vec![json!({"id": "2"})]).unwrap();
+        drop(tx);
+        let broken = StreamFormat::Rows {
+            template: "{{ doc.id | nope }}".into(),
+            params: BTreeMap::new(),
+        };
+        let err = render(rx, broken, &Redactor::default(), Box::new(std::io::sink()));
+        assert!(err.is_err());
+    }
+}
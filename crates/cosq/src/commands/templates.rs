@@ -0,0 +1,104 @@
+//! Templates command — checks for stored queries' output templates
+//!
+//! `cosq templates lint` reports template variables that can never be
+//! defined and fields the query's SELECT never returns, before a run
+//! fails on them. `cosq templates builtins` lists the templates shipped with
+//! cosq.
+
+use std::path::PathBuf;
+
+use anyhow::{Result, bail};
+use colored::Colorize;
+use cosq_core::stored_query::{find_stored_query, list_stored_queries};
+
+use crate::builtin_templates::{BUILTIN_PREFIX, BUILTIN_TEMPLATES, read_template};
+use crate::cli::TemplatesCommands;
+use crate::template_lint::lint_template;
+
+pub fn run(cmd: TemplatesCommands) -> Result<()> {
+    match cmd {
+        TemplatesCommands::Lint { name, template } => lint(name, template),
+        TemplatesCommands::Builtins { name } => builtins(name),
+    }
+}
+
+fn builtins(name: Option<String>) -> Result<()> {
+    if let Some(name) = name {
+        let name = name.strip_prefix(BUILTIN_PREFIX).unwrap_or(&name);
+        print!("{}", read_template(&format!("{BUILTIN_PREFIX}{name}"))?);
+        return Ok(());
+    }
+    let width = BUILTIN_TEMPLATES
+        .iter()
+        .map(|(name, _, _)| name.len() + BUILTIN_PREFIX.len())
+        .max()
+        .unwrap_or_default();
+    for (name, description, _) in BUILTIN_TEMPLATES {
+        let label = format!("{BUILTIN_PREFIX}{name}");
+        println!(
+            "  {}  {}",
+            format!("{label:<width$}").cyan(),
+            description.dimmed()
+        );
+    }
+    println!(
+        "\n{} cosq run <query> --template builtin:<name> (`title` parameter sets the heading)",
+        "Use with:".dimmed()
+    );
+    Ok(())
+}
+
+fn lint(name: Option<String>, template: Option<PathBuf>) -> Result<()> {
+    let queries = match name {
+        Some(ref name) => vec![
+            find_stored_query(name)
+                .map_err(|e| anyhow::anyhow!("Query '{name}' not found: {e}"))?,
+        ],
+        None => list_stored_queries().unwrap_or_default(),
+    };
+    let external = template
+        .as_ref()
+        .map(|path| read_template(&path.to_string_lossy()))
+        .transpose()?;
+
+    let mut checked = 0;
+    let mut total = 0;
+    for query in &queries {
+        let source = match external {
+            Some(ref source) => Some(source.clone()),
+            None => super::run::resolve_template_str(&None, query)?,
+        };
+        let Some(source) = source else {
+            if name.is_some() {
+                bail!(
+                    "query '{}' has no template (pass --template to check a template file against it)",
+                    query.name
+                );
+            }
+            continue;
+        };
+        checked += 1;
+
+        let warnings = lint_template(&source, query);
+        if warnings.is_empty() {
+            if name.is_some() {
+                println!("{} {}: no issues found", "OK".green().bold(), query.name);
+            }
+            continue;
+        }
+        super::queries::print_lint_warnings(&query.name, &warnings);
+        total += warnings.len();
+    }
+
+    if total > 0 {
+        bail!("{total} template warning(s) found");
+    }
+    if name.is_none() {
+        println!(
+            "{} {} template(s) checked, no issues found",
+            "OK".green().bold(),
+            checked
+        );
+    }
+    Ok(())
+}
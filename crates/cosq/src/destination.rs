@@ -0,0 +1,366 @@
+//! Output destinations for `--out` (local files, Azure Blob Storage and, with
+//! the `duckdb` feature, tables in DuckDB database files)
+
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Result, bail};
+use colored::Colorize;
+use cosq_client::blob::{BlobClient, BlobLocation};
+use cosq_core::config::Config;
+
+use crate::output::OutputFormat;
+
+/// Environment variable that overrides `storage_account` in the config
+const STORAGE_ACCOUNT_ENV: &str = "AZURE_STORAGE_ACCOUNT";
+
+/// Table written when `--table` isn't given
+pub const DEFAULT_TABLE: &str = "results";
+
+/// Where query output is written
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Destination {
+    /// A local file path
+    File(PathBuf),
+    /// A blob in the configured storage account (`azblob://container/path`)
+    AzBlob(BlobLocation),
+    /// A table in a DuckDB database file (`*.duckdb`)
+    DuckDb(DuckDbTable),
+}
+
+/// `--out <file>.duckdb --table <name> [--replace]`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DuckDbTable {
+    pub path: PathBuf,
+    pub table: String,
+    /// Replace the table instead of appending to it
+    pub replace: bool,
+}
+
+impl Destination {
+    /// Parse an `--out` value: `azblob://container/path` or a local file path.
+    pub fn parse(value: &str) -> Result<Self> {
+        if value.starts_with("azblob://") {
+            match BlobLocation::parse(value) {
+                Some(location) => Ok(Destination::AzBlob(location)),
+                None => bail!(
+                    "Invalid blob destination '{value}' (expected azblob://<container>/<path>)"
+                ),
+            }
+        } else {
+            let path = PathBuf::from(value);
+            if path
+                .extension()
+                .is_some_and(|ext| ext.eq_ignore_ascii_case("duckdb"))
+            {
+                return Ok(Destination::DuckDb(DuckDbTable {
+                    path,
+                    table: DEFAULT_TABLE.to_string(),
+                    replace: false,
+                }));
+            }
+            Ok(Destination::File(path))
+        }
+    }
+
+    /// Apply `--table` / `--replace`, which only DuckDB destinations take.
+    /// `default_table` names the table when `--table` isn't given.
+    pub fn with_table(
+        self,
+        table: Option<String>,
+        replace: bool,
+        default_table: &str,
+    ) -> Result<Self> {
+        match self {
+            Destination::DuckDb(target) => Ok(Destination::DuckDb(DuckDbTable {
+                table: table.unwrap_or_else(|| default_table.to_string()),
+                replace,
+                ..target
+            })),
+            _ if table.is_some() || replace => {
+                bail!(
+                    "--table and --replace need a DuckDB file as --out (e.g. --out results.duckdb)"
+                )
+            }
+            other => Ok(other),
+        }
+    }
+
+    /// The format to write: DuckDB tables are loaded from JSON lines, whatever
+    /// the query or config would otherwise pick; `-o` may only ask for JSON.
+    pub fn output_format(
+        &self,
+        cli_output: Option<&OutputFormat>,
+        format: OutputFormat,
+    ) -> Result<OutputFormat> {
+        if !matches!(self, Destination::DuckDb(_)) {
+            return Ok(format);
+        }
+        match cli_output {
+            None | Some(OutputFormat::Json | OutputFormat::JsonCompact) => {
+                Ok(OutputFormat::JsonCompact)
+            }
+            Some(_) => bail!("DuckDB destinations load JSON results; drop -o or use -o json"),
+        }
+    }
+
+    /// Path used to pick a content type
+    fn path(&self) -> &Path {
+        match self {
+            Destination::File(path) => path,
+            Destination::AzBlob(location) => Path::new(&location.path),
+            Destination::DuckDb(target) => &target.path,
+        }
+    }
+
+    /// Whether the destination is an `.svg` file (or blob)
+    pub fn is_svg(&self) -> bool {
+        self.path()
+            .extension()
+            .is_some_and(|ext| ext.eq_ignore_ascii_case("svg"))
+    }
+
+    /// Write `data` to the destination.
+    pub async fn write(&self, data: &[u8], config: &Config, quiet: bool) -> Result<()> {
+        let written_to = match self {
+            Destination::File(path) => {
+                create_file(path)?.write_all(data)?;
+                path.display().to_string()
+            }
+            Destination::AzBlob(location) => {
+                crate::commands::common::ensure_online("Writing to Azure Blob Storage")?;
+                let account = storage_account(config)?;
+                let client = BlobClient::new(&account).await?;
+                client
+                    .upload(location, data.to_vec(), content_type(self.path()))
+                    .await?
+            }
+            Destination::DuckDb(target) => {
+                let target = target.clone();
+                let data = data.to_vec();
+                tokio::task::spawn_blocking(move || write_duckdb(&target, &data)).await??
+            }
+        };
+        if !quiet {
+            eprintln!("{} {}", "Wrote output to".dimmed(), written_to.cyan());
+        }
+        Ok(())
+    }
+}
+
+/// Create (or truncate) a local output file, creating its parent directories.
+pub fn create_file(path: &Path) -> Result<std::fs::File> {
+    if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+        std::fs::create_dir_all(parent)?;
+    }
+    Ok(std::fs::File::create(path)?)
+}
+
+/// Write output to the destination, or to stdout when none is given.
+pub async fn emit(
+    data: &[u8],
+    destination: Option<&Destination>,
+    config: &Config,
+    quiet: bool,
+) -> Result<()> {
+    match destination {
+        Some(dest) => dest.write(data, config, quiet).await,
+        None => {
+            std::io::stdout().write_all(data)?;
+            Ok(())
+        }
+    }
+}
+
+/// Load JSON lines into a DuckDB table, creating the file and table as needed.
+/// New tables take their columns from the data (`read_json_auto`); appends
+/// match columns by name.
+#[cfg(feature = "duckdb")]
+fn write_duckdb(target: &DuckDbTable, data: &[u8]) -> Result<String> {
+    use anyhow::Context;
+
+    let written_to = format!("{} (table {})", target.path.display(), target.table);
+    if data.iter().all(u8::is_ascii_whitespace) {
+        eprintln!(
+            "{} no results; table {} was not changed",
+            "Warning:".yellow().bold(),
+            target.table
+        );
+        return Ok(written_to);
+    }
+    if let Some(parent) = target.path.parent().filter(|p| !p.as_os_str().is_empty()) {
+        std::fs::create_dir_all(parent)?;
+    }
+    // DuckDB infers the schema from a file, so stage the results as one
+    let staged = std::env::temp_dir().join(format!("cosq-{}.ndjson", std::process::id()));
+    std::fs::write(&staged, data)?;
+    let result = (|| -> Result<()> {
+        let conn = duckdb::Connection::open(&target.path)
+            .with_context(|| format!("failed to open {}", target.path.display()))?;
+        let source = format!(
+            "read_json_auto('{}', format = 'newline_delimited')",
+            staged.display().to_string().replace('\'', "''")
+        );
+        let table = format!("\"{}\"", target.table.replace('"', "\"\""));
+        let exists: bool = conn.query_row(
+            "SELECT count(*) > 0 FROM information_schema.tables WHERE table_name = ?",
+            [&target.table],
+            |row| row.get(0),
+        )?;
+        let sql = if target.replace || !exists {
+            format!("CREATE OR REPLACE TABLE {table} AS SELECT * FROM {source}")
+        } else {
+            format!("INSERT INTO {table} BY NAME SELECT * FROM {source}")
+        };
+        conn.execute_batch(&sql)
+            .with_context(|| format!("failed to write table {}", target.table))?;
+        Ok(())
+    })();
+    let _ = std::fs::remove_file(&staged);
+    result.map(|()| written_to)
+}
+
+#[cfg(not(feature = "duckdb"))]
+fn write_duckdb(_target: &DuckDbTable, _data: &[u8]) -> Result<String> {
+    bail!(
+        "writing DuckDB files needs a cosq built with the `duckdb` feature \
+         (cargo install cosq --features duckdb)"
+    )
+}
+
+/// Storage account for blob uploads: `AZURE_STORAGE_ACCOUNT` > `storage_account` in config.
+fn storage_account(config: &Config) -> Result<String> {
+    if let Ok(account) = std::env::var(STORAGE_ACCOUNT_ENV)
+        && !account.is_empty()
+    {
+        return Ok(account);
+    }
+    match config.storage_account {
+        Some(ref account) => Ok(account.clone()),
+        None => bail!(
+            "No storage account configured for azblob:// output.\n\n  \
+             Set `storage_account: <name>` in the config, or the {STORAGE_ACCOUNT_ENV} environment variable."
+        ),
+    }
+}
+
+/// Content type from the file extension.
+fn content_type(path: &Path) -> &'static str {
+    match path.extension().and_then(|e| e.to_str()) {
+        Some("csv") => "text/csv; charset=utf-8",
+        Some("json") => "application/json",
+        Some("ndjson") | Some("jsonl") => "application/x-ndjson",
+        Some("html") => "text/html; charset=utf-8",
+        Some("md") => "text/markdown; charset=utf-8",
+        Some("svg") => "image/svg+xml",
+        Some("arrow") => "application/vnd.apache.arrow.file",
+        _ => "text/plain; charset=utf-8",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_destination() {
+        assert_eq!(
+            Destination::parse("out/report.csv").unwrap(),
+            Destination::File(PathBuf::from("out/report.csv"))
+        );
+        match Destination::parse("azblob://reports/daily/report.csv").unwrap() {
+            Destination::AzBlob(location) => {
+                assert_eq!(location.container, "reports");
+                assert_eq!(location.path, "daily/report.csv");
+            }
+            other => panic!("expected blob destination, got {other:?}"),
+        }
+        assert!(Destination::parse("azblob://reports").is_err());
+    }
+
+    #[test]
+    fn test_duckdb_destination() {
+        let out = Destination::parse("data/cosq.duckdb")
+            .unwrap()
+            .with_table(None, true, "orders")
+            .unwrap();
+        assert_eq!(
+            out,
+            Destination::DuckDb(DuckDbTable {
+                path: PathBuf::from("data/cosq.duckdb"),
+                table: "orders".into(),
+                replace: true,
+            })
+        );
+        assert_eq!(
+            out.output_format(None, OutputFormat::Table).unwrap(),
+            OutputFormat::JsonCompact
+        );
+        assert!(
+            out.output_format(Some(&OutputFormat::Csv), OutputFormat::Csv)
+                .is_err()
+        );
+
+        let file = Destination::parse("out.csv").unwrap();
+        assert!(
+            file.clone()
+                .with_table(Some("t".into()), false, "x")
+                .is_err()
+        );
+        assert_eq!(
+            file.output_format(None, OutputFormat::Csv).unwrap(),
+            OutputFormat::Csv
+        );
+    }
+
+    #[cfg(feature = "duckdb")]
+    #[test]
+    fn test_write_duckdb() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut target = DuckDbTable {
+            path: dir.path().join("cosq.duckdb"),
+            table: "orders".into(),
+            replace: false,
+        };
+        write_duckdb(&target, b"{\"id\": \"1\", \"total\": 10}\n").unwrap();
+        write_duckdb(&target, b"{\"total\": 5, \"id\": \"2\"}\n").unwrap();
+        let count = |target: &DuckDbTable| -> i64 {
+            duckdb::Connection::open(&target.path)
+                .unwrap()
+                .query_row("SELECT count(*) FROM orders", [], |row| row.get(0))
+                .unwrap()
+        };
+        assert_eq!(count(&target), 2);
+        target.replace = true;
+        write_duckdb(&target, b"{\"id\": \"3\", \"total\": 1}\n").unwrap();
+        assert_eq!(count(&target), 1);
+    }
+
+    #[test]
+    fn test_content_type() {
+        assert_eq!(
+            content_type(Path::new("a/report.csv")),
+            "text/csv; charset=utf-8"
+        );
+        assert_eq!(content_type(Path::new("a.json")), "application/json");
+        assert_eq!(
+            content_type(Path::new("notes")),
+            "text/plain; charset=utf-8"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_write_file_destination() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("nested").join("out.json");
+        let config: Config = serde_yaml::from_str(
+            "account:\n  name: a\n  subscription: s\n  resource_group: rg\n  endpoint: https://a.documents.azure.com:443/\n",
+        )
+        .unwrap();
+        Destination::File(path.clone())
+            .write(b"[]", &config, true)
+            .await
+            .unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "[]");
+    }
+}
@@ -0,0 +1,154 @@
+//! jq-style post-processing of query results (`--jq`), powered by jaq
+//!
+//! The filter receives the documents array as its input. A single array output
+//! replaces the documents; any other outputs become the documents themselves,
+//! so both `map({id, name})` and `.[] | {id, name}` work as expected.
+//!
+//! For multi-step queries the filter receives the object of step results and must
+//! return an object of the same shape (e.g. `.orders |= map(select(.total > 100))`).
+
+use std::collections::BTreeMap;
+
+use anyhow::{Result, anyhow, bail};
+use jaq_core::load::{Arena, File, Loader};
+use jaq_core::{Compiler, Ctx, RcIter};
+use jaq_json::Val;
+use serde_json::Value;
+
+/// Apply a jq filter to the documents array.
+pub fn apply(filter: &str, documents: Vec<Value>) -> Result<Vec<Value>> {
+    let mut outputs = run(filter, Value::Array(documents))?;
+    Ok(match outputs.len() {
+        1 if outputs[0].is_array() => match outputs.pop() {
+            Some(Value::Array(docs)) => docs,
+            _ => unreachable!(),
+        },
+        _ => outputs,
+    })
+}
+
+/// Apply a jq filter to multi-step results (`{step: [documents]}`).
+pub fn apply_steps(
+    filter: &str,
+    step_results: BTreeMap<String, Vec<Value>>,
+) -> Result<BTreeMap<String, Vec<Value>>> {
+    let input = serde_json::to_value(step_results)?;
+    let mut outputs = run(filter, input)?;
+    if outputs.len() != 1 {
+        bail!("--jq filter for a multi-step query must produce exactly one object");
+    }
+    serde_json::from_value(outputs.remove(0)).map_err(|_| {
+        anyhow!("--jq filter for a multi-step query must return an object of step result arrays")
+    })
+}
+
+/// Compile and run a filter against a single input value.
+fn run(filter: &str, input: Value) -> Result<Vec<Value>> {
+    let program = File {
+        code: filter,
+        path: (),
+    };
+    let loader = Loader::new(jaq_std::defs().chain(jaq_json::defs()));
+    let arena = Arena::default();
+    let modules = loader
+        .load(&arena, program)
+        .map_err(|errs| anyhow!("invalid --jq filter: {}", describe_load_errors(&errs)))?;
+
+    let compiled = Compiler::default()
+        .with_funs(jaq_std::funs().chain(jaq_json::funs()))
+        .compile(modules)
+        .map_err(|errs| {
+            let undefined: Vec<String> = errs
+                .iter()
+                .flat_map(|(_, errs)| errs.iter())
+                .map(|(name, kind)| format!("undefined {} `{name}`", kind.as_str()))
+                .collect();
+            anyhow!("invalid --jq filter: {}", undefined.join(", "))
+        })?;
+
+    let inputs = RcIter::new(core::iter::empty());
+    let mut outputs = Vec::new();
+    for output in compiled.run((Ctx::new([], &inputs), Val::from(input))) {
+        match output {
+            Ok(val) => outputs.push(Value::from(val)),
+            Err(e) => bail!("--jq filter failed: {e}"),
+        }
+    }
+    Ok(outputs)
+}
+
+fn describe_load_errors(errs: &jaq_core::load::Errors<&str, ()>) -> String {
+    use jaq_core::load::Error;
+
+    let mut messages = Vec::new();
+    for (_, err) in errs {
+        match err {
+            Error::Io(errs) => messages.extend(errs.iter().map(|(_, e)| e.clone())),
+            Error::Lex(errs) => messages.extend(
+                errs.iter()
+                    .map(|(expect, near)| format!("expected {} near `{near}`", expect.as_str())),
+            ),
+            Error::Parse(errs) => messages.extend(
+                errs.iter()
+                    .map(|(expect, near)| format!("expected {} near `{near}`", expect.as_str())),
+            ),
+        }
+    }
+    messages.join("; ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn docs() -> Vec<Value> {
+        vec![
+            json!({"id": "1", "name": "Alice", "age": 30}),
+            json!({"id": "2", "name": "Bob", "age": 25}),
+        ]
+    }
+
+    #[test]
+    fn test_apply_map_replaces_documents() {
+        let result = apply("map({name})", docs()).unwrap();
+        assert_eq!(
+            result,
+            vec![json!({"name": "Alice"}), json!({"name": "Bob"})]
+        );
+    }
+
+    #[test]
+    fn test_apply_stream_outputs() {
+        let result = apply(".[] | select(.age > 26) | .id", docs()).unwrap();
+        assert_eq!(result, vec![json!("1")]);
+    }
+
+    #[test]
+    fn test_apply_scalar_output() {
+        assert_eq!(apply("length", docs()).unwrap(), vec![json!(2)]);
+        assert_eq!(
+            apply("sort_by(.age) | .[0].name", docs()).unwrap(),
+            vec![json!("Bob")]
+        );
+    }
+
+    #[test]
+    fn test_apply_steps() {
+        let mut steps = BTreeMap::new();
+        steps.insert("users".to_string(), docs());
+        let result = apply_steps(".users |= map(select(.age < 26))", steps.clone()).unwrap();
+        assert_eq!(result["users"].len(), 1);
+
+        assert!(apply_steps(".users[]", steps.clone()).is_err());
+        assert!(apply_steps(".users | length", steps).is_err());
+    }
+
+    #[test]
+    fn test_apply_invalid_filter() {
+        let err = apply(".[] | {", docs()).unwrap_err().to_string();
+        assert!(err.starts_with("invalid --jq filter"), "{err}");
+        let err = apply("nosuchfn", docs()).unwrap_err().to_string();
+        assert!(err.contains("nosuchfn"), "{err}");
+    }
+}
@@ -1,19 +1,65 @@
 //! cosq - A CLI to query your Azure Cosmos DB instances
 
-use anyhow::Result;
+use anyhow::{Context, Result};
 use clap::{CommandFactory, Parser};
+use colored::Colorize;
+use cosq_core::dotenv;
 use tracing_subscriber::{EnvFilter, fmt, prelude::*};
 
 mod banner;
+mod builtin_templates;
 mod cli;
+mod columnar;
 mod commands;
+mod destination;
+mod jq;
 mod output;
+mod template_lint;
 mod update;
 
 use cli::Cli;
 
+fn main() -> Result<()> {
+    // Before the runtime starts any threads (setting variables isn't thread-safe)
+    // and before parsing, so `COSQ_*` flags in the file apply
+    load_project_env();
+    match run() {
+        // The partial results are already out; only say so and exit like SIGINT
+        Err(e) if e.is::<commands::common::Interrupted>() => {
+            eprintln!("{} {e}", "Partial results:".yellow().bold());
+            std::process::exit(commands::common::INTERRUPTED_EXIT_CODE);
+        }
+        result => result,
+    }
+}
+
+/// Load `.cosq/.env` (or `.env`) from the current directory, without
+/// overriding variables that are already set.
+fn load_project_env() {
+    if std::env::var_os(dotenv::NO_DOTENV_ENV).is_some() {
+        return;
+    }
+    let Some(path) = std::env::current_dir()
+        .ok()
+        .and_then(|dir| dotenv::project_env_file(&dir))
+    else {
+        return;
+    };
+    match dotenv::read(&path) {
+        Ok(vars) => {
+            for (name, value) in vars {
+                if std::env::var_os(&name).is_none() {
+                    // SAFETY: called from `main` before any other thread exists
+                    unsafe { std::env::set_var(name, value) };
+                }
+            }
+        }
+        Err(e) => eprintln!("Warning: ignoring {}: {e}", path.display()),
+    }
+}
+
 #[tokio::main]
-async fn main() -> Result<()> {
+async fn run() -> Result<()> {
     // Handle dynamic shell completions (when invoked via COMPLETE=<shell> cosq)
     clap_complete::CompleteEnv::with_factory(Cli::command).complete();
 
@@ -39,15 +85,34 @@ async fn main() -> Result<()> {
     if cli.no_color {
         colored::control::set_override(false);
     }
+    commands::common::set_offline(cli.offline);
+    commands::common::set_no_ai_data(cli.no_ai_data);
+    commands::common::set_show_headers(cli.show_headers);
+    // Proxy, CA certificate, timeouts and the token method apply to every
+    // request, so set them first
+    let config = cosq_core::config::Config::load().ok();
+    if let Some(http) = config.as_ref().and_then(|c| c.http.as_ref()) {
+        cosq_client::http::configure(http).context("invalid `http:` settings in the config")?;
+    }
+    if let Some(method) = config.and_then(|c| c.auth) {
+        cosq_client::auth::configure(method);
+    }
 
-    // Spawn background update check (skip in quiet mode or if disabled via env)
-    let update_handle = if !cli.quiet && std::env::var("COSQ_NO_UPDATE_CHECK").is_err() {
-        Some(tokio::spawn(update::check_for_updates()))
-    } else {
-        None
-    };
+    // Spawn background update check (skip in quiet or offline mode, or if disabled via env)
+    let update_handle =
+        if !cli.quiet && !cli.offline && std::env::var("COSQ_NO_UPDATE_CHECK").is_err() {
+            Some(tokio::spawn(update::check_for_updates()))
+        } else {
+            None
+        };
 
+    // `cosq debug` itself isn't recorded, so a bundle shows the command before it
+    let record = !matches!(cli.command, Some(cli::Commands::Debug { .. }));
+    let (started, at) = (std::time::Instant::now(), chrono::Utc::now().to_rfc3339());
     let result = cli.run().await;
+    if record {
+        commands::debug::save_command(started, at, &result);
+    }
 
     // Wait for update check to complete before exiting
     if let Some(handle) = update_handle {
@@ -1,17 +1,21 @@
 //! Output formatting for query results
 //!
-//! Supports JSON (default), CSV, table, and MiniJinja template output modes.
+//! Supports JSON (default), CSV, table, Prometheus exposition, Arrow IPC and
+//! MiniJinja template output modes.
 
 use std::collections::BTreeSet;
 use std::io::Write;
 
-use anyhow::Result;
+use anyhow::{Context, Result};
+use colored::Colorize;
 use comfy_table::Table;
 use comfy_table::presets::UTF8_FULL_CONDENSED;
+use cosq_core::chart::{ChartData, ChartSpec, render_svg, render_text};
+use cosq_core::stored_query::{MetricKind, PrometheusConfig};
 use serde_json::Value;
 
 /// Output format for query results
-#[derive(Debug, Clone, Default, clap::ValueEnum)]
+#[derive(Debug, Clone, Default, PartialEq, Eq, clap::ValueEnum)]
 pub enum OutputFormat {
     /// Pretty-printed JSON array (default)
     #[default]
@@ -22,10 +26,40 @@ pub enum OutputFormat {
     Table,
     /// Comma-separated values
     Csv,
+    /// Prometheus text exposition format (numeric fields as metrics)
+    Prom,
+    /// Apache Arrow IPC file (binary; write it with --out or a redirect)
+    Arrow,
     /// Use template from stored query or --template file
     Template,
 }
 
+/// `--head N` / `--tail N`: keep only the first or last rows of a result set
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RowLimit {
+    Head(usize),
+    Tail(usize),
+}
+
+impl RowLimit {
+    /// Resolve from the (mutually exclusive) `--head` / `--tail` flags.
+    pub fn from_flags(head: Option<usize>, tail: Option<usize>) -> Option<Self> {
+        head.map(RowLimit::Head).or(tail.map(RowLimit::Tail))
+    }
+
+    /// Trim `documents` in place; returns the row count before trimming.
+    pub fn apply(self, documents: &mut Vec<Value>) -> usize {
+        let total = documents.len();
+        match self {
+            RowLimit::Head(n) => documents.truncate(n),
+            RowLimit::Tail(n) => {
+                documents.drain(..total.saturating_sub(n));
+            }
+        }
+        total
+    }
+}
+
 /// Format and write query results to the given writer.
 pub fn write_results(
     writer: &mut dyn Write,
@@ -37,6 +71,8 @@ pub fn write_results(
         OutputFormat::JsonCompact => write_json_compact(writer, documents),
         OutputFormat::Table => write_table(writer, documents),
         OutputFormat::Csv => write_csv(writer, documents),
+        OutputFormat::Prom => write_prometheus(writer, documents, &PrometheusConfig::default()),
+        OutputFormat::Arrow => crate::columnar::write_arrow(writer, documents),
         OutputFormat::Template => {
             // Template output is handled separately by the caller
             write_json(writer, documents)
@@ -44,26 +80,61 @@ pub fn write_results(
     }
 }
 
+/// Write a `--chart` / `chart:` chart of the results: SVG, or text sized to
+/// the terminal.
+pub fn write_chart(
+    writer: &mut dyn Write,
+    documents: &[Value],
+    spec: &ChartSpec,
+    svg: bool,
+    quiet: bool,
+) -> Result<()> {
+    let data = ChartData::new(spec, documents);
+    if data.skipped > 0 && !quiet {
+        eprintln!(
+            "{} skipped {} result(s) without a numeric '{}'",
+            "Warning:".yellow().bold(),
+            data.skipped,
+            spec.value
+        );
+    }
+    let chart = if svg {
+        render_svg(spec, &data)
+    } else {
+        render_text(spec, &data, terminal_width().unwrap_or(80))
+    };
+    writer.write_all(chart.as_bytes())?;
+    Ok(())
+}
+
 /// Create a MiniJinja environment with custom filters registered.
-fn create_template_env() -> minijinja::Environment<'static> {
+pub fn create_template_env() -> minijinja::Environment<'static> {
     let mut env = minijinja::Environment::new();
     env.add_filter("truncate", truncate_filter);
     env.add_filter("pad", pad_filter);
+    env.add_filter("json", json_filter);
     env
 }
 
 /// MiniJinja filter: truncate a string to a maximum length, appending "..." if truncated.
 fn truncate_filter(value: String, length: Option<usize>) -> String {
     let max = length.unwrap_or(255);
-    if value.len() <= max {
+    if value.chars().count() <= max {
         value
     } else if max <= 3 {
-        value[..max].to_string()
+        value.chars().take(max).collect()
     } else {
-        format!("{}...", &value[..max - 3])
+        format!("{}...", value.chars().take(max - 3).collect::<String>())
     }
 }
 
+/// MiniJinja filter: serialize a value as compact JSON (quoting strings), for
+/// templates that produce JSON payloads.
+fn json_filter(value: minijinja::Value) -> Result<String, minijinja::Error> {
+    serde_json::to_string(&value)
+        .map_err(|e| minijinja::Error::new(minijinja::ErrorKind::InvalidOperation, e.to_string()))
+}
+
 /// MiniJinja filter: pad a string to a minimum width (left-aligned).
 fn pad_filter(value: String, width: Option<usize>) -> String {
     let w = width.unwrap_or(0);
@@ -75,11 +146,82 @@ pub fn render_template(
     template_str: &str,
     documents: &[Value],
     params: &std::collections::BTreeMap<String, Value>,
+) -> Result<String> {
+    render_context(template_str, template_context(documents, params))
+}
+
+/// Render a MiniJinja template for multi-step queries.
+/// Each step's results are available as a top-level variable by step name.
+pub fn render_multi_step_template(
+    template_str: &str,
+    step_results: &std::collections::BTreeMap<String, Vec<Value>>,
+    params: &std::collections::BTreeMap<String, Value>,
+) -> Result<String> {
+    render_context(
+        template_str,
+        multi_step_template_context(step_results, params),
+    )
+}
+
+/// Render a `template_mode: row` template once per document and concatenate
+/// the results. Each non-empty row ends with a newline; rows that render
+/// empty are skipped. A failing row's error carries a [`FailedRow`] context.
+pub fn render_rows(
+    template_str: &str,
+    documents: &[Value],
+    params: &std::collections::BTreeMap<String, Value>,
 ) -> Result<String> {
     let mut env = create_template_env();
+    env.set_keep_trailing_newline(true);
     env.add_template("output", template_str)?;
     let tmpl = env.get_template("output")?;
 
+    let mut out = String::new();
+    for (index, doc) in documents.iter().enumerate() {
+        let row = tmpl
+            .render(row_template_context(doc, params))
+            .map_err(anyhow::Error::from)
+            .context(FailedRow(index))?;
+        if row.is_empty() {
+            continue;
+        }
+        out.push_str(&row);
+        if !row.ends_with('\n') {
+            out.push('\n');
+        }
+    }
+    Ok(out)
+}
+
+/// The document (0-based index) a row template failed on
+#[derive(Debug, Clone, Copy)]
+pub struct FailedRow(pub usize);
+
+impl std::fmt::Display for FailedRow {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "failed to render row {}", self.0 + 1)
+    }
+}
+
+/// Template variables for one row of a `template_mode: row` template:
+/// `doc` plus parameters
+pub fn row_template_context(
+    doc: &Value,
+    params: &std::collections::BTreeMap<String, Value>,
+) -> std::collections::BTreeMap<String, Value> {
+    let mut context = std::collections::BTreeMap::new();
+    context.insert("doc".to_string(), doc.clone());
+    for (key, value) in params {
+        context.insert(key.clone(), value.clone());
+    }
+    context
+}
+
+/// Template variables for a single-step query: `documents` plus parameters
+pub fn template_context(
+    documents: &[Value],
+    params: &std::collections::BTreeMap<String, Value>,
+) -> std::collections::BTreeMap<String, Value> {
     let mut context = std::collections::BTreeMap::new();
     context.insert("documents".to_string(), Value::Array(documents.to_vec()));
 
@@ -87,22 +229,14 @@ pub fn render_template(
     for (key, value) in params {
         context.insert(key.clone(), value.clone());
     }
-
-    let rendered = tmpl.render(context)?;
-    Ok(rendered)
+    context
 }
 
-/// Render a MiniJinja template for multi-step queries.
-/// Each step's results are available as a top-level variable by step name.
-pub fn render_multi_step_template(
-    template_str: &str,
+/// Template variables for a multi-step query: one array per step plus parameters
+pub fn multi_step_template_context(
     step_results: &std::collections::BTreeMap<String, Vec<Value>>,
     params: &std::collections::BTreeMap<String, Value>,
-) -> Result<String> {
-    let mut env = create_template_env();
-    env.add_template("output", template_str)?;
-    let tmpl = env.get_template("output")?;
-
+) -> std::collections::BTreeMap<String, Value> {
     let mut context = std::collections::BTreeMap::new();
 
     // Add step results as top-level template variables (step_name → documents array)
@@ -114,11 +248,113 @@ pub fn render_multi_step_template(
     for (key, value) in params {
         context.insert(key.clone(), value.clone());
     }
+    context
+}
 
+fn render_context(
+    template_str: &str,
+    context: std::collections::BTreeMap<String, Value>,
+) -> Result<String> {
+    let mut env = create_template_env();
+    env.add_template("output", template_str)?;
+    let tmpl = env.get_template("output")?;
     let rendered = tmpl.render(context)?;
     Ok(rendered)
 }
 
+/// Explain a template render failure for `--template-debug`: the error, the
+/// template with the failing line marked, the variables the template can use
+/// and a sample document.
+pub fn template_debug_report(
+    template_str: &str,
+    error: &anyhow::Error,
+    context: &std::collections::BTreeMap<String, Value>,
+) -> String {
+    let jinja = error.downcast_ref::<minijinja::Error>();
+    let failing_line = jinja.and_then(minijinja::Error::line);
+    let message = match jinja {
+        Some(e) => match e.detail() {
+            Some(detail) => format!("{}: {detail}", e.kind()),
+            None => e.kind().to_string(),
+        },
+        None => error.to_string(),
+    };
+
+    let mut out = format!("{} {message}\n", "Template error:".red().bold());
+    let row = error
+        .downcast_ref::<FailedRow>()
+        .map(|r| format!("row {}", r.0 + 1));
+    let location: Vec<String> = row
+        .into_iter()
+        .chain(failing_line.map(|line| format!("line {line}")))
+        .collect();
+    if !location.is_empty() {
+        out.push_str(&format!("  ({})\n", location.join(", ")));
+    }
+
+    out.push_str(&format!("\n{}\n", "Template:".bold()));
+    let lines: Vec<&str> = template_str.lines().collect();
+    let width = lines.len().to_string().len();
+    for (i, text) in lines.iter().enumerate() {
+        let number = i + 1;
+        if Some(number) == failing_line {
+            out.push_str(&format!(
+                "{} {}\n",
+                format!("> {number:>width$} |").red().bold(),
+                text.red()
+            ));
+        } else {
+            out.push_str(&format!(
+                "{} {text}\n",
+                format!("  {number:>width$} |").dimmed()
+            ));
+        }
+    }
+
+    out.push_str(&format!("\n{}\n", "Variables:".bold()));
+    for (name, value) in context {
+        let summary = match value {
+            Value::Array(items) => format!(
+                "list of {} item{}",
+                items.len(),
+                if items.len() == 1 { "" } else { "s" }
+            ),
+            Value::Object(fields) => format!(
+                "object with {} field{}",
+                fields.len(),
+                if fields.len() == 1 { "" } else { "s" }
+            ),
+            other => format!("= {other}"),
+        };
+        out.push_str(&format!("  {} {}\n", name.cyan(), summary.dimmed()));
+    }
+
+    // The first document of the first non-empty list, or a row template's `doc`
+    let sample = context
+        .iter()
+        .find_map(|(name, value)| match value {
+            Value::Array(items) => items.first().map(|doc| (format!("{name}[0]"), doc)),
+            _ => None,
+        })
+        .or_else(|| {
+            context
+                .get("doc")
+                .filter(|doc| doc.is_object())
+                .map(|doc| ("doc".to_string(), doc))
+        });
+    match sample {
+        Some((name, doc)) => {
+            out.push_str(&format!("\n{} ({name}):\n", "Sample document".bold()));
+            let pretty = serde_json::to_string_pretty(doc).unwrap_or_default();
+            for line in pretty.lines() {
+                out.push_str(&format!("  {line}\n"));
+            }
+        }
+        None => out.push_str(&format!("\n{}\n", "No documents to sample.".dimmed())),
+    }
+    out
+}
+
 fn write_json(writer: &mut dyn Write, documents: &[Value]) -> Result<()> {
     let json = serde_json::to_string_pretty(documents)?;
     writeln!(writer, "{json}")?;
@@ -138,7 +374,11 @@ fn write_table(writer: &mut dyn Write, documents: &[Value]) -> Result<()> {
         writeln!(writer, "(no results)")?;
         return Ok(());
     }
+    writeln!(writer, "{}", build_table(documents))?;
+    Ok(())
+}
 
+fn build_table(documents: &[Value]) -> Table {
     let columns = collect_columns(documents);
 
     let mut table = Table::new();
@@ -152,9 +392,23 @@ fn write_table(writer: &mut dyn Write, documents: &[Value]) -> Result<()> {
             .collect();
         table.add_row(row);
     }
+    table
+}
 
-    writeln!(writer, "{table}")?;
-    Ok(())
+/// Each table column with the width it takes up (content, padding and its
+/// left border); the whole table is one character wider for the right border.
+pub fn table_columns(documents: &[Value]) -> Vec<(String, usize)> {
+    let table = build_table(documents);
+    collect_columns(documents)
+        .into_iter()
+        .zip(table.column_max_content_widths())
+        .map(|(column, width)| (column, usize::from(width) + 3))
+        .collect()
+}
+
+/// Width of the terminal stdout is connected to, if it is one.
+pub fn terminal_width() -> Option<usize> {
+    Table::new().width().map(usize::from)
 }
 
 fn write_csv(writer: &mut dyn Write, documents: &[Value]) -> Result<()> {
@@ -163,8 +417,12 @@ fn write_csv(writer: &mut dyn Write, documents: &[Value]) -> Result<()> {
     }
 
     let columns = collect_columns(documents);
+    write_csv_header(writer, &columns)?;
+    write_csv_rows(writer, documents, &columns)
+}
 
-    // Header
+/// Write a CSV header line for `columns`.
+pub fn write_csv_header(writer: &mut dyn Write, columns: &[String]) -> Result<()> {
     writeln!(
         writer,
         "{}",
@@ -174,8 +432,15 @@ fn write_csv(writer: &mut dyn Write, documents: &[Value]) -> Result<()> {
             .collect::<Vec<_>>()
             .join(",")
     )?;
+    Ok(())
+}
 
-    // Rows
+/// Write one CSV line per document with the values of `columns`.
+pub fn write_csv_rows(
+    writer: &mut dyn Write,
+    documents: &[Value],
+    columns: &[String],
+) -> Result<()> {
     for doc in documents {
         let row: Vec<String> = columns
             .iter()
@@ -183,12 +448,146 @@ fn write_csv(writer: &mut dyn Write, documents: &[Value]) -> Result<()> {
             .collect();
         writeln!(writer, "{}", row.join(","))?;
     }
+    Ok(())
+}
+
+/// Write documents in Prometheus text exposition format.
+///
+/// Without explicit `metrics`, every numeric (or boolean) top-level field becomes
+/// a gauge; without explicit `labels`, every string field becomes a label.
+pub fn write_prometheus(
+    writer: &mut dyn Write,
+    documents: &[Value],
+    config: &PrometheusConfig,
+) -> Result<()> {
+    let prefix = config.prefix.as_deref().unwrap_or("cosq_");
+    let columns = collect_columns(documents);
+    let is_metric_value = |v: &Value| v.is_number() || v.is_boolean();
+
+    let labels: Vec<String> = match config.labels {
+        Some(ref labels) => labels.clone(),
+        None => columns
+            .iter()
+            .filter(|c| {
+                documents
+                    .iter()
+                    .any(|d| d.get(c.as_str()).is_some_and(Value::is_string))
+            })
+            .cloned()
+            .collect(),
+    };
+
+    // (field, metric name, help, type)
+    let metrics: Vec<(String, String, String, MetricKind)> = if config.metrics.is_empty() {
+        columns
+            .iter()
+            .filter(|c| !labels.contains(c))
+            .filter(|c| {
+                documents
+                    .iter()
+                    .any(|d| d.get(c.as_str()).is_some_and(is_metric_value))
+            })
+            .map(|c| {
+                (
+                    c.clone(),
+                    c.clone(),
+                    format!("cosq field {c}"),
+                    MetricKind::Gauge,
+                )
+            })
+            .collect()
+    } else {
+        config
+            .metrics
+            .iter()
+            .map(|m| {
+                (
+                    m.field.clone(),
+                    m.name.clone().unwrap_or_else(|| m.field.clone()),
+                    m.help
+                        .clone()
+                        .unwrap_or_else(|| format!("cosq field {}", m.field)),
+                    m.kind,
+                )
+            })
+            .collect()
+    };
+
+    for (field, name, help, kind) in metrics {
+        let name = prom_name(&format!("{prefix}{name}"));
+        let kind = match kind {
+            MetricKind::Gauge => "gauge",
+            MetricKind::Counter => "counter",
+        };
+        writeln!(
+            writer,
+            "# HELP {name} {}",
+            help.replace('\\', "\\\\").replace('\n', "\\n")
+        )?;
+        writeln!(writer, "# TYPE {name} {kind}")?;
+
+        for doc in documents {
+            let value = match lookup_path(doc, &field) {
+                Some(Value::Number(n)) => n.to_string(),
+                Some(Value::Bool(b)) => if *b { "1" } else { "0" }.to_string(),
+                _ => continue,
+            };
+            let label_pairs: Vec<String> = labels
+                .iter()
+                .filter_map(|label| {
+                    let value = match lookup_path(doc, label)? {
+                        Value::String(s) => s.clone(),
+                        Value::Number(n) => n.to_string(),
+                        Value::Bool(b) => b.to_string(),
+                        _ => return None,
+                    };
+                    Some(format!("{}=\"{}\"", prom_name(label), prom_escape(&value)))
+                })
+                .collect();
+            if label_pairs.is_empty() {
+                writeln!(writer, "{name} {value}")?;
+            } else {
+                writeln!(writer, "{name}{{{}}} {value}", label_pairs.join(","))?;
+            }
+        }
+    }
 
     Ok(())
 }
 
+/// Look up a dotted field path (e.g. `stats.total`) in a document.
+fn lookup_path<'a>(doc: &'a Value, path: &str) -> Option<&'a Value> {
+    path.split('.').try_fold(doc, |value, key| value.get(key))
+}
+
+/// Sanitize a string into a valid Prometheus metric or label name.
+fn prom_name(name: &str) -> String {
+    let mut out: String = name
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '_' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    if out.starts_with(|c: char| c.is_ascii_digit()) {
+        out.insert(0, '_');
+    }
+    out
+}
+
+/// Escape a Prometheus label value.
+fn prom_escape(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
 /// Collect column names from all documents, preserving order from the first document.
-fn collect_columns(documents: &[Value]) -> Vec<String> {
+pub fn collect_columns(documents: &[Value]) -> Vec<String> {
     let mut seen = BTreeSet::new();
     let mut columns = Vec::new();
 
@@ -243,6 +642,26 @@ mod tests {
     use super::*;
     use serde_json::json;
 
+    #[test]
+    fn test_row_limit() {
+        let docs: Vec<Value> = (1..=5).map(|i| json!(i)).collect();
+
+        let mut head = docs.clone();
+        assert_eq!(RowLimit::Head(2).apply(&mut head), 5);
+        assert_eq!(head, vec![json!(1), json!(2)]);
+
+        let mut tail = docs.clone();
+        RowLimit::Tail(2).apply(&mut tail);
+        assert_eq!(tail, vec![json!(4), json!(5)]);
+
+        let mut all = docs.clone();
+        RowLimit::Tail(10).apply(&mut all);
+        assert_eq!(all, docs);
+
+        assert_eq!(RowLimit::from_flags(None, Some(3)), Some(RowLimit::Tail(3)));
+        assert_eq!(RowLimit::from_flags(None, None), None);
+    }
+
     #[test]
     fn test_format_cell_types() {
         assert_eq!(format_cell(Some(&json!("hello"))), "hello");
@@ -268,6 +687,44 @@ mod tests {
         assert_eq!(format_cell(Some(&large_obj)), "{4 fields}");
     }
 
+    #[test]
+    fn test_write_prometheus_defaults() {
+        let docs = vec![
+            json!({"region": "eu", "count": 3, "active": true}),
+            json!({"region": "us", "count": 5, "active": false}),
+        ];
+        let mut buf = Vec::new();
+        write_results(&mut buf, &docs, &OutputFormat::Prom).unwrap();
+        let output = String::from_utf8(buf).unwrap();
+        assert!(output.contains("# TYPE cosq_count gauge"));
+        assert!(output.contains("cosq_count{region=\"eu\"} 3"));
+        assert!(output.contains("cosq_count{region=\"us\"} 5"));
+        assert!(output.contains("cosq_active{region=\"us\"} 0"));
+    }
+
+    #[test]
+    fn test_write_prometheus_configured() {
+        let docs = vec![json!({"region": "eu", "tier": "gold", "stats": {"total": 12.5}})];
+        let config: PrometheusConfig = serde_yaml::from_str(
+            "prefix: shop_\nlabels: [tier]\nmetrics:\n  - field: stats.total\n    name: revenue-total\n    help: Revenue\n    type: counter\n",
+        )
+        .unwrap();
+        let mut buf = Vec::new();
+        write_prometheus(&mut buf, &docs, &config).unwrap();
+        let output = String::from_utf8(buf).unwrap();
+        assert_eq!(
+            output,
+            "# HELP shop_revenue_total Revenue\n# TYPE shop_revenue_total counter\nshop_revenue_total{tier=\"gold\"} 12.5\n"
+        );
+    }
+
+    #[test]
+    fn test_prom_name_and_escape() {
+        assert_eq!(prom_name("orders.by-region"), "orders_by_region");
+        assert_eq!(prom_name("1st"), "_1st");
+        assert_eq!(prom_escape("a\"b\\c\nd"), "a\\\"b\\\\c\\nd");
+    }
+
     #[test]
     fn test_csv_escape() {
         assert_eq!(csv_escape("hello"), "hello");
@@ -337,6 +794,23 @@ mod tests {
         assert!(output.contains("Alice"));
     }
 
+    #[test]
+    fn test_table_columns() {
+        let docs = vec![
+            json!({"id": "1", "name": "Alice"}),
+            json!({"id": "22", "name": "Bo"}),
+        ];
+        let columns = table_columns(&docs);
+        assert_eq!(columns, vec![("id".into(), 5), ("name".into(), 8)]);
+
+        // The widths add up to the rendered table's width
+        let mut buf = Vec::new();
+        write_results(&mut buf, &docs, &OutputFormat::Table).unwrap();
+        let output = String::from_utf8(buf).unwrap();
+        let rendered = output.lines().next().unwrap().chars().count();
+        assert_eq!(rendered, columns.iter().map(|(_, w)| w).sum::<usize>() + 1);
+    }
+
     #[test]
     fn test_render_template() {
         let docs = vec![
@@ -384,6 +858,53 @@ mod tests {
         assert!(result.contains("2"));
     }
 
+    #[test]
+    fn test_render_rows() {
+        let docs = vec![
+            json!({"id": "1", "name": "Alice"}),
+            json!({"id": "2", "name": "Bob", "hidden": true}),
+            json!({"id": "3", "name": "Carol"}),
+        ];
+        let mut params = std::collections::BTreeMap::new();
+        params.insert("sep".to_string(), json!(";"));
+
+        let out = render_rows(
+            "{% if not doc.hidden %}{{ doc.id }}{{ sep }}{{ doc.name }}{% endif %}",
+            &docs,
+            &params,
+        )
+        .unwrap();
+        assert_eq!(out, "1;Alice\n3;Carol\n");
+
+        // A trailing newline in the template is not doubled
+        let out = render_rows("{{ doc.id }}\n", &docs, &params).unwrap();
+        assert_eq!(out, "1\n2\n3\n");
+
+        let err = render_rows("{{ doc.name | pad(\"x\") }}", &docs, &params).unwrap_err();
+        assert_eq!(err.downcast_ref::<FailedRow>().map(|r| r.0), Some(0));
+        assert!(err.downcast_ref::<minijinja::Error>().is_some());
+    }
+
+    #[test]
+    fn test_template_debug_report() {
+        colored::control::set_override(false);
+        let template = "Users:\n{% for d in documents %}\n{{ d.name | pad(\"x\") }}\n{% endfor %}";
+        let docs = vec![json!({"name": "Alice", "age": 30})];
+        let mut params = std::collections::BTreeMap::new();
+        params.insert("region".to_string(), json!("eu"));
+
+        let err = render_template(template, &docs, &params).unwrap_err();
+        let report = template_debug_report(template, &err, &template_context(&docs, &params));
+        assert!(report.starts_with("Template error:"));
+        assert!(report.contains("(line 3)"));
+        assert!(report.contains("> 3 | {{ d.name | pad(\"x\") }}"));
+        assert!(report.contains("  1 | Users:"));
+        assert!(report.contains("documents list of 1 item"));
+        assert!(report.contains("region = \"eu\""));
+        assert!(report.contains("Sample document (documents[0]):"));
+        assert!(report.contains("\"name\": \"Alice\""));
+    }
+
     #[test]
     fn test_render_template_with_params() {
         let docs = vec![json!({"total": 100})];
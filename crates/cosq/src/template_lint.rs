@@ -0,0 +1,245 @@
+//! Static checks for output templates
+//!
+//! A stored query's template sees `documents` (or one list per step of a
+//! multi-step query) and the query's parameters; any other top-level name is
+//! always undefined. When the SQL projects explicit fields, fields the
+//! template reads from the results must be among them.
+
+use std::collections::BTreeSet;
+
+use cosq_core::lint::{LintWarning, projected_fields, template_fields};
+use cosq_core::stored_query::{StoredQuery, TemplateMode};
+
+use crate::output::create_template_env;
+
+/// Check `template` against the variables and result fields `query` provides.
+pub fn lint_template(template: &str, query: &StoredQuery) -> Vec<LintWarning> {
+    let mut warnings = Vec::new();
+    let mut env = create_template_env();
+    if let Err(e) = env.add_template("output", template) {
+        warnings.push(LintWarning {
+            rule: "template-syntax",
+            step: None,
+            message: e.to_string(),
+            suggestion: "fix the template syntax (`cosq render --template-debug` shows the line)"
+                .into(),
+        });
+        return warnings;
+    }
+    let Ok(tmpl) = env.get_template("output") else {
+        return warnings;
+    };
+
+    let row_mode = query.metadata.template_mode == Some(TemplateMode::Row);
+    // (collection, step, SQL) for each list of results the template can read;
+    // a row template reads one document as `doc`
+    let collections: Vec<(&str, Option<&str>, &str)> = match query.metadata.steps {
+        Some(ref steps) => steps
+            .iter()
+            .map(|s| {
+                let sql = query.step_queries.get(&s.name).map_or("", String::as_str);
+                (s.name.as_str(), Some(s.name.as_str()), sql)
+            })
+            .collect(),
+        None if row_mode => vec![("doc", None, query.sql.as_str())],
+        None => vec![("documents", None, query.sql.as_str())],
+    };
+
+    let mut known: BTreeSet<String> = collections.iter().map(|(c, ..)| c.to_string()).collect();
+    known.extend(query.metadata.params.iter().map(|p| p.name.clone()));
+    let globals: BTreeSet<&str> = env.globals().map(|(name, _)| name).collect();
+
+    let mut undeclared: Vec<String> = tmpl.undeclared_variables(false).into_iter().collect();
+    undeclared.sort();
+    for name in undeclared {
+        if known.contains(&name) || globals.contains(name.as_str()) {
+            continue;
+        }
+        let available = known.iter().cloned().collect::<Vec<_>>().join(", ");
+        warnings.push(LintWarning {
+            rule: "undefined-variable",
+            step: None,
+            message: format!("`{name}` is never defined (the template has: {available})"),
+            suggestion: match closest(&name, known.iter().map(String::as_str)) {
+                Some(similar) => format!("did you mean `{similar}`?"),
+                None => format!("declare `{name}` under `params:` or remove it"),
+            },
+        });
+    }
+
+    for (collection, step, sql) in collections {
+        let Some(projected) = projected_fields(sql) else {
+            continue;
+        };
+        let selected = projected.iter().cloned().collect::<Vec<_>>().join(", ");
+        let used = if row_mode {
+            row_fields(template)
+        } else {
+            template_fields(template, collection)
+        };
+        for field in used.difference(&projected) {
+            warnings.push(LintWarning {
+                rule: "unknown-field",
+                step: step.map(String::from),
+                message: format!(
+                    "the template reads `{field}` from `{collection}`, but the SELECT only returns: {selected}"
+                ),
+                suggestion: match closest(field, projected.iter().map(String::as_str)) {
+                    Some(similar) => format!("did you mean `{similar}`?"),
+                    None => format!("add c.{field} to the SELECT list"),
+                },
+            });
+        }
+    }
+    warnings
+}
+
+/// Fields a row template reads from `doc` (`doc.field`, `doc["field"]`).
+fn row_fields(template: &str) -> BTreeSet<String> {
+    let re = regex::Regex::new(r#"\bdoc(?:\.(\w+)|\[\s*["']([^"']+)["']\s*\])"#).unwrap();
+    re.captures_iter(template)
+        .filter_map(|cap| cap.get(1).or(cap.get(2)))
+        .map(|m| m.as_str().to_string())
+        .collect()
+}
+
+/// The candidate closest to `name`, if it is a plausible typo of it.
+fn closest<'a>(name: &str, candidates: impl Iterator<Item = &'a str>) -> Option<&'a str> {
+    let max = (name.chars().count() / 3).clamp(1, 3);
+    candidates
+        .map(|c| (edit_distance(name, c), c))
+        .filter(|(d, _)| *d <= max)
+        .min()
+        .map(|(_, c)| c)
+}
+
+/// Edit distance between two strings, counting a swap of adjacent
+/// characters as one edit (optimal string alignment).
+fn edit_distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    let mut d = vec![vec![0; b.len() + 1]; a.len() + 1];
+    for (i, row) in d.iter_mut().enumerate() {
+        row[0] = i;
+    }
+    for (j, cell) in d[0].iter_mut().enumerate() {
+        *cell = j;
+    }
+    for i in 1..=a.len() {
+        for j in 1..=b.len() {
+            let cost = usize::from(a[i - 1] != b[j - 1]);
+            d[i][j] = (d[i - 1][j] + 1)
+                .min(d[i][j - 1] + 1)
+                .min(d[i - 1][j - 1] + cost);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                d[i][j] = d[i][j].min(d[i - 2][j - 2] + 1);
+            }
+        }
+    }
+    d[a.len()][b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rules(warnings: &[LintWarning]) -> Vec<&'static str> {
+        warnings.iter().map(|w| w.rule).collect()
+    }
+
+    #[test]
+    fn test_lint_template_single_step() {
+        let query = StoredQuery::parse(
+            "test",
+            "---\ndescription: test\nparams:\n  - name: status\n    type: string\n---\nSELECT c.id, c.name FROM c WHERE c.status = @status\n",
+        )
+        .unwrap();
+
+        let ok = "{% for d in documents %}{{ loop.index }} {{ d.name }} ({{ status }}){% endfor %}{% for i in range(2) %}{% endfor %}";
+        assert!(lint_template(ok, &query).is_empty());
+
+        let typos = "{% for d in documnts %}{{ d.nmae }}{% endfor %}{{ staus }}{{ total }}";
+        let warnings = lint_template(typos, &query);
+        assert_eq!(
+            rules(&warnings),
+            vec![
+                "undefined-variable",
+                "undefined-variable",
+                "undefined-variable"
+            ]
+        );
+        assert_eq!(warnings[0].suggestion, "did you mean `documents`?");
+        assert_eq!(warnings[1].suggestion, "did you mean `status`?");
+        assert!(warnings[2].suggestion.contains("declare `total`"));
+
+        let field = "{% for d in documents %}{{ d.nmae }}{{ d.email }}{% endfor %}";
+        let warnings = lint_template(field, &query);
+        assert_eq!(rules(&warnings), vec!["unknown-field", "unknown-field"]);
+        assert_eq!(warnings[0].suggestion, "add c.email to the SELECT list");
+        assert_eq!(warnings[1].suggestion, "did you mean `name`?");
+
+        assert_eq!(
+            rules(&lint_template("{% for d in documents %}", &query)),
+            vec!["template-syntax"]
+        );
+    }
+
+    #[test]
+    fn test_lint_template_multi_step() {
+        let query = StoredQuery::parse(
+            "test",
+            r#"---
+description: test
+steps:
+  - name: customer
+    container: customers
+  - name: orders
+    container: orders
+---
+-- step: customer
+SELECT * FROM c
+
+-- step: orders
+SELECT c.id, c.total FROM c WHERE c.customerId = @customer.id
+"#,
+        )
+        .unwrap();
+        // `SELECT *` leaves customer fields open
+        let warnings = lint_template(
+            "{{ customer[0].anything }}{% for o in orders %}{{ o.totl }}{% endfor %}{{ order }}",
+            &query,
+        );
+        assert_eq!(
+            rules(&warnings),
+            vec!["undefined-variable", "unknown-field"]
+        );
+        assert_eq!(warnings[0].suggestion, "did you mean `orders`?");
+        assert_eq!(warnings[1].step.as_deref(), Some("orders"));
+        assert_eq!(warnings[1].suggestion, "did you mean `total`?");
+    }
+
+    #[test]
+    fn test_lint_template_row_mode() {
+        let query = StoredQuery::parse(
+            "test",
+            "---\ndescription: test\ntemplate_mode: row\n---\nSELECT c.id, c.name FROM c\n",
+        )
+        .unwrap();
+        assert!(lint_template("{{ doc.id }},{{ doc[\"name\"] }}", &query).is_empty());
+        let warnings = lint_template("{{ documents }}{{ doc.nmae }}", &query);
+        assert_eq!(
+            rules(&warnings),
+            vec!["undefined-variable", "unknown-field"]
+        );
+        assert_eq!(warnings[1].suggestion, "did you mean `name`?");
+    }
+
+    #[test]
+    fn test_edit_distance() {
+        assert_eq!(edit_distance("status", "status"), 0);
+        assert_eq!(edit_distance("staus", "status"), 1);
+        assert_eq!(edit_distance("", "abc"), 3);
+        assert_eq!(edit_distance("nmae", "name"), 1);
+        assert_eq!(edit_distance("kitten", "sitting"), 3);
+    }
+}
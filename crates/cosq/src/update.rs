@@ -68,7 +68,8 @@ fn write_cache(latest_version: &str) {
 
 async fn fetch_latest_version() -> Option<String> {
     let url = format!("https://crates.io/api/v1/crates/{CRATE_NAME}");
-    let client = reqwest::Client::builder()
+    let client = cosq_client::http::builder()
+        .ok()?
         .user_agent(format!("cosq/{}", env!("CARGO_PKG_VERSION")))
         .build()
         .ok()?;